@@ -1,4 +1,4 @@
-use crate::api::ApiState;
+use crate::api::{ensure_success, ApiState};
 use crate::error::AppResult;
 use serde::{Deserialize, Serialize};
 use tauri::State;
@@ -44,19 +44,11 @@ pub async fn api_login(
         .post(&url)
         .json(&LoginRequest { email, password })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let status = res.status();
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Login failed ({}): {}", status, text).into());
-    }
+    let res = ensure_success(res, "Login failed").await?;
 
-    let auth_response: AuthResponse = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let auth_response: AuthResponse = res.json().await?;
 
     // Store token for subsequent requests
     state.set_token(Some(auth_response.token.clone())).await;
@@ -82,19 +74,11 @@ pub async fn api_register(
             password,
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let status = res.status();
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Registration failed ({}): {}", status, text).into());
-    }
+    let res = ensure_success(res, "Registration failed").await?;
 
-    let auth_response: AuthResponse = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let auth_response: AuthResponse = res.json().await?;
 
     // Store token for subsequent requests
     state.set_token(Some(auth_response.token.clone())).await;
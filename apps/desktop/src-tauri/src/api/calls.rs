@@ -0,0 +1,48 @@
+use crate::api::{ensure_success, ApiState};
+use crate::error::AppResult;
+use media::IceServerConfig;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct IceServersResponse {
+    #[serde(default)]
+    ice_servers: Vec<IceServerConfig>,
+}
+
+/// STUN/TURN servers pushed by the server. Empty when the server has none
+/// configured.
+pub async fn fetch_ice_servers(state: &ApiState) -> AppResult<Vec<IceServerConfig>> {
+    let token = state.bearer_token().await?;
+
+    let url = format!("{}/calls/ice-servers", state.base_url);
+
+    let res = state
+        .client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to fetch ICE servers").await?;
+    let data: IceServersResponse = res.json().await?;
+
+    Ok(data.ice_servers)
+}
+
+/// STUN entries plus TURN credentials minted for this user, valid for a
+/// limited time. Fails (404) when the server has no TURN secret.
+pub async fn fetch_ice_credentials(state: &ApiState) -> AppResult<Vec<IceServerConfig>> {
+    let token = state.bearer_token().await?;
+
+    let url = format!("{}/calls/ice/credentials", state.base_url);
+
+    let res = state
+        .client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to fetch TURN credentials").await?;
+    Ok(res.json().await?)
+}
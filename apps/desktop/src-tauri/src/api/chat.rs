@@ -1,13 +1,15 @@
-use crate::api::servers::ChannelMessage;
-use crate::api::ApiState;
-use crate::error::AppResult;
+use crate::api::servers::{api_to_persisted_channel_message, ChannelMessage};
+use crate::api::{ensure_success, error_for_response, ApiState};
+use crate::error::{AppError, AppResult};
 use crate::messaging::domain::{
-    ConversationKind, MessageStatus as LocalMessageStatus, PersistedMessage,
+    Attachment, ConversationKind, MessageStatus as LocalMessageStatus, OutboxMessage,
+    PersistedMessage,
 };
+use crate::messaging::service::{OutboxSender, SendFailure};
 use crate::MessagingState;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{Manager, State};
 use url::form_urlencoded::byte_serialize;
 use uuid::Uuid;
 
@@ -42,6 +44,9 @@ struct SendMessageRequest {
     content: String,
     nonce: Option<String>,
     client_id: Option<String>,
+    /// Only channels take attachments so far
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Attachment>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,6 +59,46 @@ struct ReadRequest {
     upto_message_id: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct MessageStatusRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_ids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmSearchMatch {
+    pub message_id: String,
+    pub sender_id: Option<String>,
+    pub snippet: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmSearchConversation {
+    pub room_id: String,
+    pub peer_id: Option<String>,
+    pub peer_username: Option<String>,
+    pub matches: Vec<DmSearchMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DmSearchResults {
+    pub conversations: Vec<DmSearchConversation>,
+    pub next_before: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageStatusEntry {
+    pub message_id: String,
+    pub status: String,
+    pub delivered_at: Option<String>,
+    pub read_at: Option<String>,
+}
+
 fn parse_local_status(status: Option<&str>) -> LocalMessageStatus {
     match status {
         Some("sending") => LocalMessageStatus::Sending,
@@ -96,6 +141,7 @@ fn api_to_persisted_message(room_id: &str, message: &Message) -> PersistedMessag
             .unwrap_or_else(|| Utc::now().to_rfc3339()),
         edited_at: message.edited_at.clone(),
         status: parse_local_status(message.status.as_deref()),
+        attachments: Vec::new(),
     }
 }
 
@@ -114,18 +160,11 @@ pub async fn api_create_or_get_dm(
         .header("Authorization", format!("Bearer {}", token))
         .json(&CreateDmRequest { friend_id })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to create DM: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to create DM").await?;
 
-    let room: Room = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let room: Room = res.json().await?;
 
     Ok(room)
 }
@@ -158,27 +197,28 @@ pub async fn api_fetch_messages(
         .query(&query_params)
         .send()
         .await
-        .map_err(|e| format!("Network error: {}", e));
+        .map_err(AppError::from);
 
     match remote_res {
         Ok(res) if res.status().is_success() => {
-            let remote_messages: Vec<Message> = res
-                .json()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))?;
+            let remote_messages: Vec<Message> = res.json().await?;
 
             let persisted = remote_messages
                 .iter()
                 .map(|m| api_to_persisted_message(&room_id, m))
                 .collect::<Vec<_>>();
             if let Err(err) = messaging.service.cache_remote_messages(&persisted).await {
-                eprintln!("[Messaging] Failed to cache DM messages: {}", err);
+                tracing::warn!(
+                    component = "storage",
+                    "failed to cache DM messages: {}",
+                    err
+                );
                 return Ok(remote_messages);
             }
 
             match messaging
                 .service
-                .load_messages(
+                .fetch_history(
                     ConversationKind::Dm,
                     &room_id,
                     before_cursor.as_deref(),
@@ -192,49 +232,66 @@ pub async fn api_fetch_messages(
                     .collect()),
                 Ok(_) => Ok(remote_messages),
                 Err(err) => {
-                    eprintln!("[Messaging] Failed to load cached DM messages: {}", err);
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to load cached DM messages: {}",
+                        err
+                    );
                     Ok(remote_messages)
                 }
             }
         }
         Ok(res) => {
-            let text = res.text().await.unwrap_or_default();
-            let remote_error = format!("Failed to fetch messages: {}", text);
+            let remote_error = error_for_response(res, "Failed to fetch messages").await;
 
             let cached = messaging
                 .service
-                .load_messages(
+                .fetch_history(
                     ConversationKind::Dm,
                     &room_id,
                     before_cursor.as_deref(),
                     limit,
                 )
                 .await
-                .map_err(|e| format!("{} (cache unavailable: {})", remote_error, e))?;
+                .map_err(|e| {
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to load cached DM messages: {}",
+                        e
+                    );
+                    remote_error.clone()
+                })?;
 
             if !cached.is_empty() {
                 return Ok(cached.into_iter().map(persisted_to_api_message).collect());
             }
 
-            Err(remote_error.into())
+            Err(remote_error)
         }
         Err(remote_error) => {
             let cached = messaging
                 .service
-                .load_messages(
+                .fetch_history(
                     ConversationKind::Dm,
                     &room_id,
                     before_cursor.as_deref(),
                     limit,
                 )
                 .await
-                .map_err(|e| format!("{} (cache unavailable: {})", remote_error, e))?;
+                .map_err(|e| {
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to load cached DM messages: {}",
+                        e
+                    );
+                    remote_error.clone()
+                })?;
 
             if !cached.is_empty() {
                 return Ok(cached.into_iter().map(persisted_to_api_message).collect());
             }
 
-            Err(remote_error.into())
+            Err(remote_error)
         }
     }
 }
@@ -263,10 +320,15 @@ pub async fn api_send_message(
             content.clone(),
             nonce.clone(),
             resolved_client_id.clone(),
+            Vec::new(),
         )
         .await
     {
-        eprintln!("[Messaging] Failed to persist pending DM message: {}", err);
+        tracing::warn!(
+            component = "storage",
+            "failed to persist pending DM message: {}",
+            err
+        );
     }
 
     let res = state
@@ -277,27 +339,32 @@ pub async fn api_send_message(
             content: content.clone(),
             nonce: nonce.clone(),
             client_id: Some(resolved_client_id.clone()),
+            attachments: Vec::new(),
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
     if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
+        let error = error_for_response(res, "Failed to send message").await;
+        let reason = error
+            .details
+            .clone()
+            .unwrap_or_else(|| error.message.clone());
         if let Err(err) = messaging
             .service
-            .mark_send_failed(&resolved_client_id, &text)
+            .mark_send_failed(&resolved_client_id, &reason)
             .await
         {
-            eprintln!("[Messaging] Failed to mark DM send failure: {}", err);
+            tracing::warn!(
+                component = "storage",
+                "failed to mark DM send failure: {}",
+                err
+            );
         }
-        return Err((format!("Failed to send message: {}", text)).into());
+        return Err(error);
     }
 
-    let mut message: Message = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let mut message: Message = res.json().await?;
 
     if message.client_id.is_none() {
         message.client_id = Some(resolved_client_id.clone());
@@ -323,10 +390,15 @@ pub async fn api_send_message(
                 .unwrap_or_else(|| Utc::now().to_rfc3339()),
             message.edited_at.clone(),
             parse_local_status(message.status.as_deref()),
+            Vec::new(),
         )
         .await
     {
-        eprintln!("[Messaging] Failed to persist sent DM message: {}", err);
+        tracing::warn!(
+            component = "storage",
+            "failed to persist sent DM message: {}",
+            err
+        );
     }
 
     Ok(message)
@@ -338,7 +410,7 @@ pub async fn api_drain_outbox(
     messaging: State<'_, MessagingState>,
     limit: Option<i64>,
 ) -> AppResult<u32> {
-    let token = state.get_token().await.ok_or("Not authenticated")?;
+    state.get_token().await.ok_or("Not authenticated")?;
 
     let limit = limit.unwrap_or(200).clamp(1, 1000);
     let outbox_items = messaging
@@ -350,180 +422,30 @@ pub async fn api_drain_outbox(
     let mut delivered = 0u32;
 
     for item in outbox_items {
-        match item.target_kind {
-            ConversationKind::Dm => {
-                let url = format!("{}/chat/{}/messages", state.base_url, item.target_id);
-
-                let response = state
-                    .client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", token))
-                    .json(&SendMessageRequest {
-                        content: item.content.clone(),
-                        nonce: item.nonce.clone(),
-                        client_id: Some(item.client_id.clone()),
-                    })
-                    .send()
-                    .await;
-
-                match response {
-                    Ok(res) if res.status().is_success() => {
-                        let mut message: Message = match res.json().await {
-                            Ok(message) => message,
-                            Err(err) => {
-                                let reason = format!("Failed to parse DM retry response: {}", err);
-                                let _ = messaging
-                                    .service
-                                    .mark_send_failed(&item.client_id, &reason)
-                                    .await;
-                                continue;
-                            }
-                        };
-
-                        if message.client_id.is_none() {
-                            message.client_id = Some(item.client_id.clone());
-                        }
-                        if message.status.is_none() {
-                            message.status = Some("sent".to_string());
-                        }
-
-                        if let Err(err) = messaging
-                            .service
-                            .mark_send_success(
-                                ConversationKind::Dm,
-                                &item.target_id,
-                                message.id.clone(),
-                                message.client_id.clone(),
-                                message.sender_id.clone(),
-                                None,
-                                message.content.clone(),
-                                message.nonce.clone(),
-                                message
-                                    .created_at
-                                    .clone()
-                                    .unwrap_or_else(|| Utc::now().to_rfc3339()),
-                                message.edited_at.clone(),
-                                parse_local_status(message.status.as_deref()),
-                            )
-                            .await
-                        {
-                            eprintln!("[Messaging] Failed to persist retried DM message: {}", err);
-                        } else {
-                            delivered += 1;
-                        }
-                    }
-                    Ok(res) => {
-                        let text = res.text().await.unwrap_or_default();
-                        let _ = messaging
-                            .service
-                            .mark_send_failed(&item.client_id, &text)
-                            .await;
-                    }
-                    Err(err) => {
-                        let reason = format!("Network error: {}", err);
-                        let _ = messaging
-                            .service
-                            .mark_send_failed(&item.client_id, &reason)
-                            .await;
-                        break;
-                    }
+        match post_outbox_item(&state, &item).await {
+            Ok(message) => {
+                if let Err(err) = messaging.service.complete_send(&message).await {
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to persist retried message: {}",
+                        err
+                    );
+                } else {
+                    delivered += 1;
                 }
             }
-            ConversationKind::Channel => {
-                let Some(server_id) = item.server_scope_id.clone() else {
-                    let _ = messaging
-                        .service
-                        .mark_send_failed(
-                            &item.client_id,
-                            "Missing server_scope_id for channel outbox message",
-                        )
-                        .await;
-                    continue;
-                };
-
-                let url = format!(
-                    "{}/servers/{}/channels/{}/messages",
-                    state.base_url, server_id, item.target_id
-                );
-
-                let response = state
-                    .client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", token))
-                    .json(&serde_json::json!({
-                        "content": item.content.clone(),
-                        "nonce": item.nonce.clone(),
-                        "client_id": item.client_id.clone(),
-                    }))
-                    .send()
+            Err(SendFailure::Offline(reason)) => {
+                let _ = messaging
+                    .service
+                    .mark_send_failed(&item.client_id, &reason)
+                    .await;
+                break;
+            }
+            Err(SendFailure::Retryable(reason) | SendFailure::Rejected(reason)) => {
+                let _ = messaging
+                    .service
+                    .mark_send_failed(&item.client_id, &reason)
                     .await;
-
-                match response {
-                    Ok(res) if res.status().is_success() => {
-                        let mut message: ChannelMessage = match res.json().await {
-                            Ok(message) => message,
-                            Err(err) => {
-                                let reason =
-                                    format!("Failed to parse channel retry response: {}", err);
-                                let _ = messaging
-                                    .service
-                                    .mark_send_failed(&item.client_id, &reason)
-                                    .await;
-                                continue;
-                            }
-                        };
-
-                        if message.client_id.is_none() {
-                            message.client_id = Some(item.client_id.clone());
-                        }
-                        if message.status.is_none() {
-                            message.status = Some("sent".to_string());
-                        }
-
-                        if let Err(err) = messaging
-                            .service
-                            .mark_send_success(
-                                ConversationKind::Channel,
-                                &item.target_id,
-                                message.id.clone(),
-                                message.client_id.clone(),
-                                message.sender_id.clone(),
-                                message.sender_username.clone(),
-                                message.content.clone(),
-                                message.nonce.clone(),
-                                message
-                                    .created_at
-                                    .clone()
-                                    .unwrap_or_else(|| Utc::now().to_rfc3339()),
-                                message.edited_at.clone(),
-                                parse_local_status(message.status.as_deref()),
-                            )
-                            .await
-                        {
-                            eprintln!(
-                                "[Messaging] Failed to persist retried channel message: {}",
-                                err
-                            );
-                        } else {
-                            delivered += 1;
-                        }
-                    }
-                    Ok(res) => {
-                        let text = res.text().await.unwrap_or_default();
-                        let _ = messaging
-                            .service
-                            .mark_send_failed(&item.client_id, &text)
-                            .await;
-                    }
-                    Err(err) => {
-                        let reason = format!("Network error: {}", err);
-                        let _ = messaging
-                            .service
-                            .mark_send_failed(&item.client_id, &reason)
-                            .await;
-                        break;
-                    }
-                }
             }
         }
     }
@@ -531,6 +453,99 @@ pub async fn api_drain_outbox(
     Ok(delivered)
 }
 
+/// Sends the outbox worker's retries with the app's `ApiState`
+pub struct ApiOutboxSender {
+    app: tauri::AppHandle,
+}
+
+impl ApiOutboxSender {
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl OutboxSender for ApiOutboxSender {
+    async fn send(&self, item: &OutboxMessage) -> Result<PersistedMessage, SendFailure> {
+        let state = self
+            .app
+            .try_state::<ApiState>()
+            .ok_or_else(|| SendFailure::Offline("API not ready".to_string()))?;
+        post_outbox_item(&state, item).await
+    }
+}
+
+/// Post a queued DM or channel message, with its `client_id` so the server
+/// drops it if an earlier attempt got through after all
+async fn post_outbox_item(
+    state: &ApiState,
+    item: &OutboxMessage,
+) -> Result<PersistedMessage, SendFailure> {
+    let token = state
+        .get_token()
+        .await
+        .ok_or_else(|| SendFailure::Offline("Not authenticated".to_string()))?;
+
+    let url = match item.target_kind {
+        ConversationKind::Dm => format!("{}/chat/{}/messages", state.base_url, item.target_id),
+        ConversationKind::Channel => {
+            let Some(server_id) = &item.server_scope_id else {
+                return Err(SendFailure::Rejected(
+                    "Missing server_scope_id for channel outbox message".to_string(),
+                ));
+            };
+            format!(
+                "{}/servers/{}/channels/{}/messages",
+                state.base_url, server_id, item.target_id
+            )
+        }
+    };
+
+    let res = state
+        .client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&SendMessageRequest {
+            content: item.content.clone(),
+            nonce: item.nonce.clone(),
+            client_id: Some(item.client_id.clone()),
+            attachments: item.attachments.clone(),
+        })
+        .send()
+        .await
+        .map_err(|err| SendFailure::Offline(format!("Network error: {}", err)))?;
+
+    let status = res.status();
+    if !status.is_success() {
+        let text = res.text().await.unwrap_or_default();
+        return Err(match status.as_u16() {
+            // Signed out: picked up again once there is a valid token
+            401 => SendFailure::Offline(text),
+            408 | 429 => SendFailure::Retryable(text),
+            400..=499 => SendFailure::Rejected(text),
+            _ => SendFailure::Retryable(text),
+        });
+    }
+
+    let mut message = match item.target_kind {
+        ConversationKind::Dm => {
+            let message: Message = res.json().await.map_err(|err| {
+                SendFailure::Retryable(format!("Failed to parse DM retry response: {}", err))
+            })?;
+            api_to_persisted_message(&item.target_id, &message)
+        }
+        ConversationKind::Channel => {
+            let message: ChannelMessage = res.json().await.map_err(|err| {
+                SendFailure::Retryable(format!("Failed to parse channel retry response: {}", err))
+            })?;
+            api_to_persisted_channel_message(&item.target_id, &message)
+        }
+    };
+    if message.client_id.is_none() {
+        message.client_id = Some(item.client_id.clone());
+    }
+    Ok(message)
+}
+
 #[tauri::command]
 pub async fn api_cache_message_status(
     messaging: State<'_, MessagingState>,
@@ -569,13 +584,9 @@ pub async fn api_send_typing(
         .header("Authorization", format!("Bearer {}", token))
         .json(&TypingRequest { is_typing })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to send typing: {}", text)).into());
-    }
+    ensure_success(res, "Failed to send typing").await?;
 
     Ok(())
 }
@@ -598,13 +609,9 @@ pub async fn api_mark_message_delivered(
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to mark delivered: {}", text)).into());
-    }
+    ensure_success(res, "Failed to mark delivered").await?;
 
     Ok(())
 }
@@ -625,20 +632,71 @@ pub async fn api_mark_room_read(
         .header("Authorization", format!("Bearer {}", token))
         .json(&ReadRequest { upto_message_id })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to mark room read: {}", text)).into());
-    }
+    ensure_success(res, "Failed to mark room read").await?;
 
     Ok(())
 }
 
+/// Fetch delivery/read status for a batch of messages, by id or for the page
+/// `api_fetch_messages` would return for the same `before`/`limit`. Delivered
+/// and read statuses are written through to the local cache.
+#[tauri::command]
+pub async fn api_fetch_message_statuses(
+    state: State<'_, ApiState>,
+    messaging: State<'_, MessagingState>,
+    room_id: String,
+    message_ids: Option<Vec<String>>,
+    before: Option<String>,
+    limit: Option<i64>,
+) -> AppResult<Vec<MessageStatusEntry>> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+
+    let url = format!("{}/chat/{}/messages/status", state.base_url, room_id);
+
+    let res = state
+        .client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&MessageStatusRequest {
+            message_ids,
+            before,
+            limit,
+        })
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to fetch message statuses").await?;
+
+    let statuses: Vec<MessageStatusEntry> = res.json().await?;
+
+    for entry in &statuses {
+        let next = match entry.status.as_str() {
+            "delivered" => LocalMessageStatus::Delivered,
+            "read" => LocalMessageStatus::Read,
+            _ => continue,
+        };
+        if let Err(err) = messaging
+            .service
+            .set_status_by_server_id(&entry.message_id, next)
+            .await
+        {
+            tracing::warn!(
+                component = "storage",
+                "failed to cache message status: {}",
+                err
+            );
+        }
+    }
+
+    Ok(statuses)
+}
+
 #[tauri::command]
 pub async fn api_delete_message(
     state: State<'_, ApiState>,
+    messaging: State<'_, MessagingState>,
     room_id: String,
     message_id: String,
 ) -> AppResult<()> {
@@ -654,12 +712,16 @@ pub async fn api_delete_message(
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to delete message: {}", text)).into());
+    ensure_success(res, "Failed to delete message").await?;
+
+    if let Err(err) = messaging.service.remove_message(&message_id).await {
+        tracing::warn!(
+            component = "storage",
+            "failed to uncache deleted message: {}",
+            err
+        );
     }
 
     Ok(())
@@ -679,13 +741,9 @@ pub async fn api_delete_all_messages(
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to delete all messages: {}", text)).into());
-    }
+    ensure_success(res, "Failed to delete all messages").await?;
 
     Ok(())
 }
@@ -699,6 +757,7 @@ struct EditMessageRequest {
 #[tauri::command]
 pub async fn api_edit_message(
     state: State<'_, ApiState>,
+    messaging: State<'_, MessagingState>,
     room_id: String,
     message_id: String,
     content: String,
@@ -717,18 +776,20 @@ pub async fn api_edit_message(
         .header("Authorization", format!("Bearer {}", token))
         .json(&EditMessageRequest { content, nonce })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to edit message: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to edit message").await?;
 
-    let message: Message = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let message: Message = res.json().await?;
+
+    let persisted = api_to_persisted_message(&room_id, &message);
+    if let Err(err) = messaging.service.cache_remote_messages(&[persisted]).await {
+        tracing::warn!(
+            component = "storage",
+            "failed to cache edited message: {}",
+            err
+        );
+    }
 
     Ok(message)
 }
@@ -766,19 +827,44 @@ pub async fn api_search_messages(
         .header("Authorization", format!("Bearer {}", token))
         .query(&params)
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to search messages: {}", text)).into());
+    let res = ensure_success(res, "Failed to search messages").await?;
+
+    Ok(res.json().await?)
+}
+
+/// Search all of the user's DMs; pass `next_before` back as `before` for the
+/// next page.
+#[tauri::command]
+pub async fn api_search_all_dms(
+    state: State<'_, ApiState>,
+    query: String,
+    limit: Option<i64>,
+    before: Option<String>,
+) -> AppResult<DmSearchResults> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+    let url = format!("{}/chat/search", state.base_url);
+
+    let mut params = vec![("q".to_string(), query)];
+    if let Some(limit) = limit {
+        params.push(("limit".to_string(), limit.to_string()));
+    }
+    if let Some(before) = before {
+        params.push(("before".to_string(), before));
     }
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    let res = state
+        .client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&params)
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to search messages").await?;
+
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -798,19 +884,11 @@ pub async fn api_fetch_message_reactions(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch message reactions: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch message reactions").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -832,19 +910,11 @@ pub async fn api_add_message_reaction(
         .header("Authorization", format!("Bearer {}", token))
         .json(&ReactionRequest { emoji })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to add message reaction: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to add message reaction").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -866,19 +936,11 @@ pub async fn api_remove_message_reaction(
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to remove message reaction: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to remove message reaction").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -898,19 +960,11 @@ pub async fn api_fetch_thread_messages(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch thread messages: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch thread messages").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -936,19 +990,12 @@ pub async fn api_send_thread_message(
             content,
             nonce,
             client_id,
+            attachments: Vec::new(),
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to send thread message: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to send thread message").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
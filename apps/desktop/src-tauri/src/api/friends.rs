@@ -1,4 +1,4 @@
-use crate::api::ApiState;
+use crate::api::{ensure_success, ApiState};
 use crate::error::AppResult;
 use serde::{Deserialize, Serialize};
 use tauri::State;
@@ -10,11 +10,17 @@ pub struct Friend {
     pub avatar_url: Option<String>,
     pub status: String,
     pub last_seen: Option<String>,
+    #[serde(default)]
+    pub presence_status: Option<String>,
+    #[serde(default)]
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct FriendRequestPayload {
     username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
 }
 
 #[tauri::command]
@@ -28,18 +34,11 @@ pub async fn api_fetch_friends(state: State<'_, ApiState>) -> AppResult<Vec<Frie
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to fetch friends: {}", text).into());
-    }
+    let res = ensure_success(res, "Failed to fetch friends").await?;
 
-    let friends: Vec<Friend> = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let friends: Vec<Friend> = res.json().await?;
 
     Ok(friends)
 }
@@ -55,18 +54,11 @@ pub async fn api_fetch_pending_requests(state: State<'_, ApiState>) -> AppResult
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to fetch pending requests: {}", text).into());
-    }
+    let res = ensure_success(res, "Failed to fetch pending requests").await?;
 
-    let requests: Vec<Friend> = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let requests: Vec<Friend> = res.json().await?;
 
     Ok(requests)
 }
@@ -75,6 +67,7 @@ pub async fn api_fetch_pending_requests(state: State<'_, ApiState>) -> AppResult
 pub async fn api_send_friend_request(
     state: State<'_, ApiState>,
     username: String,
+    client_id: Option<String>,
 ) -> AppResult<()> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -84,15 +77,14 @@ pub async fn api_send_friend_request(
         .client
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
-        .json(&FriendRequestPayload { username })
+        .json(&FriendRequestPayload {
+            username,
+            client_id,
+        })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to send friend request: {}", text).into());
-    }
+    ensure_success(res, "Failed to send friend request").await?;
 
     Ok(())
 }
@@ -111,13 +103,9 @@ pub async fn api_accept_friend(
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to accept friend: {}", text).into());
-    }
+    ensure_success(res, "Failed to accept friend").await?;
 
     Ok(())
 }
@@ -133,18 +121,11 @@ pub async fn api_fetch_online_friends(state: State<'_, ApiState>) -> AppResult<V
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
-
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to fetch online friends: {}", text).into());
-    }
-
-    let online: Vec<String> = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .await?;
+
+    let res = ensure_success(res, "Failed to fetch online friends").await?;
+
+    let online: Vec<String> = res.json().await?;
 
     Ok(online)
 }
@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod calls;
 pub mod chat;
 pub mod friends;
 pub mod servers;
@@ -84,3 +85,22 @@ impl ApiState {
         self.get_token().await.map(|t| format!("Bearer {}", t))
     }
 }
+
+/// Pass successful responses through; turn anything else into a typed
+/// [`AppError`] carrying the HTTP status and the server's message.
+pub async fn ensure_success(
+    res: reqwest::Response,
+    context: &str,
+) -> AppResult<reqwest::Response> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    Err(error_for_response(res, context).await)
+}
+
+/// Build the typed error for a non-success response, consuming its body.
+pub async fn error_for_response(res: reqwest::Response, context: &str) -> AppError {
+    let status = res.status().as_u16();
+    let body = res.text().await.unwrap_or_default();
+    AppError::from_http_response(status, context, &body)
+}
@@ -1,7 +1,7 @@
-use crate::api::ApiState;
-use crate::error::AppResult;
+use crate::api::{ensure_success, error_for_response, ApiState};
+use crate::error::{AppError, AppResult};
 use crate::messaging::domain::{
-    ConversationKind, MessageStatus as LocalMessageStatus, PersistedMessage,
+    Attachment, ConversationKind, MessageStatus as LocalMessageStatus, PersistedMessage,
 };
 use crate::MessagingState;
 use chrono::Utc;
@@ -37,6 +37,8 @@ pub struct ServerMember {
     pub avatar_url: Option<String>,
     pub role: String,
     pub last_seen: Option<String>,
+    #[serde(default)]
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +46,13 @@ pub struct VoiceChannelParticipant {
     pub user_id: String,
     pub username: String,
     pub joined_at: Option<String>,
+    /// `speak` or `listen`
+    #[serde(default)]
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub speaking: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +67,23 @@ pub struct ChannelMessage {
     pub created_at: Option<String>,
     pub edited_at: Option<String>,
     pub status: Option<String>,
+    /// Missing from servers without attachment support
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedChannelMessage {
+    pub message_id: String,
+    pub channel_id: String,
+    pub sender_id: Option<String>,
+    pub sender_username: Option<String>,
+    pub content: String,
+    pub created_at: Option<String>,
+    pub edited_at: Option<String>,
+    pub pinned_by: String,
+    pub pinned_by_username: String,
+    pub pinned_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,12 +97,31 @@ pub struct ServerWithChannels {
 struct CreateServerRequest {
     name: String,
     icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct CreateChannelRequest {
     name: String,
     channel_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChannelReadRequest {
+    upto_message_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JoinVoiceRequest {
+    mode: String,
+}
+
+#[derive(Debug, Serialize)]
+struct NudgeRequest {
+    target_user_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,6 +129,7 @@ struct SendChannelMessageRequest {
     content: String,
     nonce: Option<String>,
     client_id: Option<String>,
+    attachments: Vec<Attachment>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -113,10 +159,11 @@ fn persisted_to_channel_message(message: PersistedMessage) -> ChannelMessage {
         created_at: Some(message.created_at),
         edited_at: message.edited_at,
         status: Some(message.status.as_str().to_string()),
+        attachments: message.attachments,
     }
 }
 
-fn api_to_persisted_channel_message(
+pub(crate) fn api_to_persisted_channel_message(
     channel_id: &str,
     message: &ChannelMessage,
 ) -> PersistedMessage {
@@ -137,6 +184,7 @@ fn api_to_persisted_channel_message(
             .unwrap_or_else(|| Utc::now().to_rfc3339()),
         edited_at: message.edited_at.clone(),
         status: parse_local_status(message.status.as_deref()),
+        attachments: message.attachments.clone(),
     }
 }
 
@@ -151,18 +199,11 @@ pub async fn api_fetch_servers(state: State<'_, ApiState>) -> AppResult<Vec<Serv
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch servers: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch servers").await?;
 
-    let servers: Vec<Server> = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let servers: Vec<Server> = res.json().await?;
 
     Ok(servers)
 }
@@ -172,6 +213,7 @@ pub async fn api_create_server(
     state: State<'_, ApiState>,
     name: String,
     icon_url: Option<String>,
+    client_id: Option<String>,
 ) -> AppResult<Server> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -181,20 +223,17 @@ pub async fn api_create_server(
         .client
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
-        .json(&CreateServerRequest { name, icon_url })
+        .json(&CreateServerRequest {
+            name,
+            icon_url,
+            client_id,
+        })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to create server: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to create server").await?;
 
-    let server: Server = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let server: Server = res.json().await?;
 
     Ok(server)
 }
@@ -213,18 +252,11 @@ pub async fn api_join_server(
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to join server: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to join server").await?;
 
-    let server: Server = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let server: Server = res.json().await?;
 
     Ok(server)
 }
@@ -240,13 +272,9 @@ pub async fn api_leave_server(state: State<'_, ApiState>, server_id: String) ->
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to leave server: {}", text)).into());
-    }
+    ensure_success(res, "Failed to leave server").await?;
 
     Ok(())
 }
@@ -265,18 +293,11 @@ pub async fn api_fetch_server_details(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch server details: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch server details").await?;
 
-    let data: ServerWithChannels = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let data: ServerWithChannels = res.json().await?;
 
     Ok(data)
 }
@@ -287,6 +308,7 @@ pub async fn api_create_channel(
     server_id: String,
     name: String,
     channel_type: Option<String>,
+    client_id: Option<String>,
 ) -> AppResult<Channel> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -296,20 +318,17 @@ pub async fn api_create_channel(
         .client
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
-        .json(&CreateChannelRequest { name, channel_type })
+        .json(&CreateChannelRequest {
+            name,
+            channel_type,
+            client_id,
+        })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to create channel: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to create channel").await?;
 
-    let channel: Channel = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let channel: Channel = res.json().await?;
 
     Ok(channel)
 }
@@ -328,18 +347,11 @@ pub async fn api_fetch_server_members(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch members: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch members").await?;
 
-    let members: Vec<ServerMember> = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let members: Vec<ServerMember> = res.json().await?;
 
     Ok(members)
 }
@@ -376,27 +388,28 @@ pub async fn api_fetch_channel_messages(
         .query(&query_params)
         .send()
         .await
-        .map_err(|e| format!("Network error: {}", e));
+        .map_err(AppError::from);
 
     match remote_res {
         Ok(res) if res.status().is_success() => {
-            let remote_messages: Vec<ChannelMessage> = res
-                .json()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))?;
+            let remote_messages: Vec<ChannelMessage> = res.json().await?;
 
             let persisted = remote_messages
                 .iter()
                 .map(|m| api_to_persisted_channel_message(&channel_id, m))
                 .collect::<Vec<_>>();
             if let Err(err) = messaging.service.cache_remote_messages(&persisted).await {
-                eprintln!("[Messaging] Failed to cache channel messages: {}", err);
+                tracing::warn!(
+                    component = "storage",
+                    "failed to cache channel messages: {}",
+                    err
+                );
                 return Ok(remote_messages);
             }
 
             match messaging
                 .service
-                .load_messages(
+                .fetch_history(
                     ConversationKind::Channel,
                     &channel_id,
                     before_cursor.as_deref(),
@@ -410,8 +423,9 @@ pub async fn api_fetch_channel_messages(
                     .collect()),
                 Ok(_) => Ok(remote_messages),
                 Err(err) => {
-                    eprintln!(
-                        "[Messaging] Failed to load cached channel messages: {}",
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to load cached channel messages: {}",
                         err
                     );
                     Ok(remote_messages)
@@ -419,19 +433,25 @@ pub async fn api_fetch_channel_messages(
             }
         }
         Ok(res) => {
-            let text = res.text().await.unwrap_or_default();
-            let remote_error = format!("Failed to fetch channel messages: {}", text);
+            let remote_error = error_for_response(res, "Failed to fetch channel messages").await;
 
             let cached = messaging
                 .service
-                .load_messages(
+                .fetch_history(
                     ConversationKind::Channel,
                     &channel_id,
                     before_cursor.as_deref(),
                     limit,
                 )
                 .await
-                .map_err(|e| format!("{} (cache unavailable: {})", remote_error, e))?;
+                .map_err(|e| {
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to load cached channel messages: {}",
+                        e
+                    );
+                    remote_error.clone()
+                })?;
 
             if !cached.is_empty() {
                 return Ok(cached
@@ -440,19 +460,26 @@ pub async fn api_fetch_channel_messages(
                     .collect());
             }
 
-            Err(remote_error.into())
+            Err(remote_error)
         }
         Err(remote_error) => {
             let cached = messaging
                 .service
-                .load_messages(
+                .fetch_history(
                     ConversationKind::Channel,
                     &channel_id,
                     before_cursor.as_deref(),
                     limit,
                 )
                 .await
-                .map_err(|e| format!("{} (cache unavailable: {})", remote_error, e))?;
+                .map_err(|e| {
+                    tracing::warn!(
+                        component = "storage",
+                        "failed to load cached channel messages: {}",
+                        e
+                    );
+                    remote_error.clone()
+                })?;
 
             if !cached.is_empty() {
                 return Ok(cached
@@ -461,7 +488,7 @@ pub async fn api_fetch_channel_messages(
                     .collect());
             }
 
-            Err(remote_error.into())
+            Err(remote_error)
         }
     }
 }
@@ -475,6 +502,7 @@ pub async fn api_send_channel_message(
     content: String,
     nonce: Option<String>,
     client_id: Option<String>,
+    attachments: Option<Vec<Attachment>>,
 ) -> AppResult<ChannelMessage> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -483,6 +511,7 @@ pub async fn api_send_channel_message(
         state.base_url, server_id, channel_id
     );
     let resolved_client_id = client_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let attachments = attachments.unwrap_or_default();
 
     if let Err(err) = messaging
         .service
@@ -494,11 +523,13 @@ pub async fn api_send_channel_message(
             content.clone(),
             nonce.clone(),
             resolved_client_id.clone(),
+            attachments.clone(),
         )
         .await
     {
-        eprintln!(
-            "[Messaging] Failed to persist pending channel message: {}",
+        tracing::warn!(
+            component = "storage",
+            "failed to persist pending channel message: {}",
             err
         );
     }
@@ -511,27 +542,32 @@ pub async fn api_send_channel_message(
             content: content.clone(),
             nonce: nonce.clone(),
             client_id: Some(resolved_client_id.clone()),
+            attachments,
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
     if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
+        let error = error_for_response(res, "Failed to send channel message").await;
+        let reason = error
+            .details
+            .clone()
+            .unwrap_or_else(|| error.message.clone());
         if let Err(err) = messaging
             .service
-            .mark_send_failed(&resolved_client_id, &text)
+            .mark_send_failed(&resolved_client_id, &reason)
             .await
         {
-            eprintln!("[Messaging] Failed to mark channel send failure: {}", err);
+            tracing::warn!(
+                component = "storage",
+                "failed to mark channel send failure: {}",
+                err
+            );
         }
-        return Err((format!("Failed to send channel message: {}", text)).into());
+        return Err(error);
     }
 
-    let mut message: ChannelMessage = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let mut message: ChannelMessage = res.json().await?;
 
     if message.client_id.is_none() {
         message.client_id = Some(resolved_client_id.clone());
@@ -557,11 +593,13 @@ pub async fn api_send_channel_message(
                 .unwrap_or_else(|| Utc::now().to_rfc3339()),
             message.edited_at.clone(),
             parse_local_status(message.status.as_deref()),
+            message.attachments.clone(),
         )
         .await
     {
-        eprintln!(
-            "[Messaging] Failed to persist sent channel message: {}",
+        tracing::warn!(
+            component = "storage",
+            "failed to persist sent channel message: {}",
             err
         );
     }
@@ -589,13 +627,9 @@ pub async fn api_send_channel_typing(
         .header("Authorization", format!("Bearer {}", token))
         .json(&TypingRequest { is_typing })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to send channel typing: {}", text)).into());
-    }
+    ensure_success(res, "Failed to send channel typing").await?;
 
     Ok(())
 }
@@ -618,18 +652,11 @@ pub async fn api_fetch_voice_channel_presence(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch voice presence: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch voice presence").await?;
 
-    let participants: Vec<VoiceChannelParticipant> = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let participants: Vec<VoiceChannelParticipant> = res.json().await?;
 
     Ok(participants)
 }
@@ -639,6 +666,7 @@ pub async fn api_join_voice_channel(
     state: State<'_, ApiState>,
     server_id: String,
     channel_id: String,
+    mode: Option<String>,
 ) -> AppResult<()> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -651,14 +679,13 @@ pub async fn api_join_voice_channel(
         .client
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
+        .json(&JoinVoiceRequest {
+            mode: mode.unwrap_or_else(|| "speak".to_string()),
+        })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to join voice channel: {}", text)).into());
-    }
+    ensure_success(res, "Failed to join voice channel").await?;
 
     Ok(())
 }
@@ -681,13 +708,37 @@ pub async fn api_leave_voice_channel(
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to leave voice channel: {}", text)).into());
-    }
+    ensure_success(res, "Failed to leave voice channel").await?;
+
+    Ok(())
+}
+
+/// Nudge another participant of the voice channel we are in.
+#[tauri::command]
+pub async fn api_nudge_voice_participant(
+    state: State<'_, ApiState>,
+    server_id: String,
+    channel_id: String,
+    target_user_id: String,
+) -> AppResult<()> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+
+    let url = format!(
+        "{}/servers/{}/channels/{}/nudge",
+        state.base_url, server_id, channel_id
+    );
+
+    let res = state
+        .client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&NudgeRequest { target_user_id })
+        .send()
+        .await?;
+
+    ensure_success(res, "Failed to nudge user").await?;
 
     Ok(())
 }
@@ -737,13 +788,9 @@ pub async fn api_delete_server(
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to delete server: {}", text)).into());
-    }
+    ensure_success(res, "Failed to delete server").await?;
 
     Ok(())
 }
@@ -761,19 +808,11 @@ pub async fn api_regenerate_server_invite(
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to regenerate invite: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to regenerate invite").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -795,13 +834,9 @@ pub async fn api_update_member_role(
         .header("Authorization", format!("Bearer {}", token))
         .json(&UpdateMemberRoleRequest { role })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to update member role: {}", text)).into());
-    }
+    ensure_success(res, "Failed to update member role").await?;
 
     Ok(())
 }
@@ -823,13 +858,9 @@ pub async fn api_kick_member(
         .post(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to kick member: {}", text)).into());
-    }
+    ensure_success(res, "Failed to kick member").await?;
 
     Ok(())
 }
@@ -853,13 +884,9 @@ pub async fn api_ban_member(
         .header("Authorization", format!("Bearer {}", token))
         .json(&BanMemberRequest { reason })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to ban member: {}", text)).into());
-    }
+    ensure_success(res, "Failed to ban member").await?;
 
     Ok(())
 }
@@ -877,19 +904,11 @@ pub async fn api_list_server_bans(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to list bans: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to list bans").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -909,13 +928,9 @@ pub async fn api_unban_member(
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to unban member: {}", text)).into());
-    }
+    ensure_success(res, "Failed to unban member").await?;
 
     Ok(())
 }
@@ -945,19 +960,11 @@ pub async fn api_search_channel_messages(
         .header("Authorization", format!("Bearer {}", token))
         .query(&params)
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to search channel messages: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to search channel messages").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -978,19 +985,11 @@ pub async fn api_fetch_channel_message_reactions(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch reactions: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch reactions").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -1013,19 +1012,11 @@ pub async fn api_add_channel_message_reaction(
         .header("Authorization", format!("Bearer {}", token))
         .json(&ReactionRequest { emoji })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to add reaction: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to add reaction").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -1048,19 +1039,113 @@ pub async fn api_remove_channel_message_reaction(
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to remove reaction: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to remove reaction").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
+}
+
+#[tauri::command]
+pub async fn api_fetch_channel_pins(
+    state: State<'_, ApiState>,
+    server_id: String,
+    channel_id: String,
+) -> AppResult<Vec<PinnedChannelMessage>> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+    let url = format!(
+        "{}/servers/{}/channels/{}/pins",
+        state.base_url, server_id, channel_id
+    );
+
+    let res = state
+        .client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to fetch pinned messages").await?;
+
+    Ok(res.json().await?)
+}
+
+/// Move the read marker of a channel forward, to `upto_message_id` or to
+/// now. Mentions up to it become read.
+#[tauri::command]
+pub async fn api_mark_channel_read(
+    state: State<'_, ApiState>,
+    server_id: String,
+    channel_id: String,
+    upto_message_id: Option<String>,
+) -> AppResult<()> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+    let url = format!(
+        "{}/servers/{}/channels/{}/read",
+        state.base_url, server_id, channel_id
+    );
+
+    let res = state
+        .client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&ChannelReadRequest { upto_message_id })
+        .send()
+        .await?;
+
+    ensure_success(res, "Failed to mark channel read").await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn api_pin_channel_message(
+    state: State<'_, ApiState>,
+    server_id: String,
+    channel_id: String,
+    message_id: String,
+) -> AppResult<Vec<PinnedChannelMessage>> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+    let url = format!(
+        "{}/servers/{}/channels/{}/messages/{}/pin",
+        state.base_url, server_id, channel_id, message_id
+    );
+
+    let res = state
+        .client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to pin message").await?;
+
+    Ok(res.json().await?)
+}
+
+#[tauri::command]
+pub async fn api_unpin_channel_message(
+    state: State<'_, ApiState>,
+    server_id: String,
+    channel_id: String,
+    message_id: String,
+) -> AppResult<Vec<PinnedChannelMessage>> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+    let url = format!(
+        "{}/servers/{}/channels/{}/messages/{}/pin",
+        state.base_url, server_id, channel_id, message_id
+    );
+
+    let res = state
+        .client
+        .delete(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to unpin message").await?;
+
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -1081,19 +1166,11 @@ pub async fn api_fetch_channel_thread_messages(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to fetch thread messages: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to fetch thread messages").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -1120,19 +1197,12 @@ pub async fn api_send_channel_thread_message(
             content,
             nonce,
             client_id,
+            attachments: Vec::new(),
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err((format!("Failed to send thread message: {}", text)).into());
-    }
+    let res = ensure_success(res, "Failed to send thread message").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
@@ -1,4 +1,4 @@
-use crate::api::ApiState;
+use crate::api::{ensure_success, ApiState};
 use crate::error::AppResult;
 use serde::{Deserialize, Serialize};
 use tauri::State;
@@ -21,6 +21,8 @@ pub struct UserProfile {
     pub avatar_url: Option<String>,
     pub last_seen: Option<String>,
     pub public_key: Option<String>,
+    #[serde(default)]
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,14 +31,53 @@ pub struct UserSettings {
     pub allow_dm_from_strangers: bool,
     pub enable_mention_notifications: bool,
     pub enable_sound_notifications: bool,
+    #[serde(default)]
+    pub presence_status: Option<String>,
+    #[serde(default)]
+    pub filtered_words: Vec<String>,
+    #[serde(default)]
+    pub call_waiting: bool,
+    /// Servers that predate the setting always send read receipts
+    #[serde(default = "default_send_read_receipts")]
+    pub send_read_receipts: bool,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
 
+fn default_send_read_receipts() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Mention {
+    pub id: String,
+    pub message_id: String,
+    pub sender_id: Option<String>,
+    pub sender_username: Option<String>,
+    pub room_id: Option<String>,
+    pub server_id: Option<String>,
+    pub channel_id: Option<String>,
+    pub created_at: String,
+    pub read_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MentionsResponse {
+    pub unread_count: i64,
+    pub mentions: Vec<Mention>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MarkMentionsReadRequest {
+    room_id: Option<String>,
+    channel_id: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct UpdateProfileRequest {
     username: Option<String>,
     avatar_url: Option<String>,
+    status_message: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,6 +85,10 @@ struct UpdateSettingsRequest {
     allow_dm_from_strangers: Option<bool>,
     enable_mention_notifications: Option<bool>,
     enable_sound_notifications: Option<bool>,
+    presence_status: Option<String>,
+    filtered_words: Option<Vec<String>>,
+    call_waiting: Option<bool>,
+    send_read_receipts: Option<bool>,
 }
 
 #[tauri::command]
@@ -61,13 +106,9 @@ pub async fn api_upload_public_key(
         .header("Authorization", format!("Bearer {}", token))
         .json(&PublicKeyRequest { public_key })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to upload public key: {}", text).into());
-    }
+    ensure_success(res, "Failed to upload public key").await?;
 
     Ok(())
 }
@@ -86,17 +127,13 @@ pub async fn api_fetch_user_public_key(
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
     if !res.status().is_success() {
         return Ok(None);
     }
 
-    let data: PublicKeyResponse = res
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let data: PublicKeyResponse = res.json().await?;
 
     Ok(data.public_key)
 }
@@ -112,19 +149,11 @@ pub async fn api_fetch_my_profile(state: State<'_, ApiState>) -> AppResult<UserP
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to fetch profile: {}", text).into());
-    }
+    let res = ensure_success(res, "Failed to fetch profile").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -132,6 +161,7 @@ pub async fn api_update_my_profile(
     state: State<'_, ApiState>,
     username: Option<String>,
     avatar_url: Option<String>,
+    status_message: Option<String>,
 ) -> AppResult<UserProfile> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -144,21 +174,14 @@ pub async fn api_update_my_profile(
         .json(&UpdateProfileRequest {
             username,
             avatar_url,
+            status_message,
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to update profile: {}", text).into());
-    }
+    let res = ensure_success(res, "Failed to update profile").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -172,19 +195,11 @@ pub async fn api_fetch_my_settings(state: State<'_, ApiState>) -> AppResult<User
         .get(&url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to fetch settings: {}", text).into());
-    }
+    let res = ensure_success(res, "Failed to fetch settings").await?;
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    Ok(res.json().await?)
 }
 
 #[tauri::command]
@@ -193,6 +208,10 @@ pub async fn api_update_my_settings(
     allow_dm_from_strangers: Option<bool>,
     enable_mention_notifications: Option<bool>,
     enable_sound_notifications: Option<bool>,
+    presence_status: Option<String>,
+    filtered_words: Option<Vec<String>>,
+    call_waiting: Option<bool>,
+    send_read_receipts: Option<bool>,
 ) -> AppResult<UserSettings> {
     let token = state.get_token().await.ok_or("Not authenticated")?;
 
@@ -206,19 +225,70 @@ pub async fn api_update_my_settings(
             allow_dm_from_strangers,
             enable_mention_notifications,
             enable_sound_notifications,
+            presence_status,
+            filtered_words,
+            call_waiting,
+            send_read_receipts,
         })
         .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .await?;
 
-    if !res.status().is_success() {
-        let text = res.text().await.unwrap_or_default();
-        return Err(format!("Failed to update settings: {}", text).into());
+    let res = ensure_success(res, "Failed to update settings").await?;
+
+    Ok(res.json().await?)
+}
+
+#[tauri::command]
+pub async fn api_fetch_my_mentions(
+    state: State<'_, ApiState>,
+    unread_only: bool,
+    limit: Option<i64>,
+) -> AppResult<MentionsResponse> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+
+    let url = format!("{}/users/me/mentions", state.base_url);
+
+    let mut query_params: Vec<(String, String)> =
+        vec![("unread".to_string(), unread_only.to_string())];
+    if let Some(limit) = limit {
+        query_params.push(("limit".to_string(), limit.to_string()));
     }
 
-    Ok(
-        res.json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?,
-    )
+    let res = state
+        .client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&query_params)
+        .send()
+        .await?;
+
+    let res = ensure_success(res, "Failed to fetch mentions").await?;
+
+    Ok(res.json().await?)
+}
+
+#[tauri::command]
+pub async fn api_mark_mentions_read(
+    state: State<'_, ApiState>,
+    room_id: Option<String>,
+    channel_id: Option<String>,
+) -> AppResult<()> {
+    let token = state.get_token().await.ok_or("Not authenticated")?;
+
+    let url = format!("{}/users/me/mentions/read", state.base_url);
+
+    let res = state
+        .client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&MarkMentionsReadRequest {
+            room_id,
+            channel_id,
+        })
+        .send()
+        .await?;
+
+    ensure_success(res, "Failed to mark mentions read").await?;
+
+    Ok(())
 }
@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+pub const AUDIO_PREFS_FILE: &str = "audio_devices.json";
+
+/// Output device choices that survive restarts, stored as JSON in the app
+/// data directory. `None` means the system default.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioDevicePrefs {
+    pub output_device: Option<String>,
+    pub ringtone_device: Option<String>,
+    /// Move call audio onto a new system default device mid-call
+    pub follow_default_device: bool,
+}
+
+impl Default for AudioDevicePrefs {
+    fn default() -> Self {
+        Self {
+            output_device: None,
+            ringtone_device: None,
+            follow_default_device: true,
+        }
+    }
+}
+
+impl AudioDevicePrefs {
+    /// Read the saved choices. A missing or unreadable file yields the
+    /// defaults; a corrupt file is logged and ignored.
+    pub fn load(path: &Path) -> Self {
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(_) => return Self::default(),
+        };
+        serde_json::from_str(&raw).unwrap_or_else(|e| {
+            tracing::warn!(
+                component = "audio",
+                path = %path.display(),
+                "ignoring unreadable audio device preferences: {}",
+                e
+            );
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+
+    /// Load, change one field and write back, logging instead of failing:
+    /// a device switch that worked should not error because the preference
+    /// could not be stored.
+    pub fn update(path: &Path, apply: impl FnOnce(&mut Self)) {
+        let mut prefs = Self::load(path);
+        apply(&mut prefs);
+        if let Err(e) = prefs.save(path) {
+            tracing::warn!(
+                component = "audio",
+                path = %path.display(),
+                "failed to save audio device preferences: {}",
+                e
+            );
+        }
+    }
+}
+
+pub fn prefs_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(AUDIO_PREFS_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_tolerates_missing_or_partial_files() {
+        let dir = std::env::temp_dir().join(format!("audio-prefs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = prefs_path(&dir);
+
+        assert_eq!(AudioDevicePrefs::load(&path), AudioDevicePrefs::default());
+
+        AudioDevicePrefs::update(&path, |p| p.ringtone_device = Some("Speakers".into()));
+        AudioDevicePrefs::update(&path, |p| p.output_device = Some("Headset".into()));
+        assert_eq!(
+            AudioDevicePrefs::load(&path),
+            AudioDevicePrefs {
+                output_device: Some("Headset".into()),
+                ringtone_device: Some("Speakers".into()),
+                follow_default_device: true,
+            }
+        );
+
+        std::fs::write(&path, r#"{"ringtone_device":"Speakers"}"#).unwrap();
+        assert_eq!(AudioDevicePrefs::load(&path).output_device, None);
+        assert!(AudioDevicePrefs::load(&path).follow_default_device);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(AudioDevicePrefs::load(&path), AudioDevicePrefs::default());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -8,13 +8,55 @@ pub enum AppErrorCode {
     Auth,
     Storage,
     Validation,
+    Device,
     Internal,
 }
 
+/// Stable, UI-facing classification of an error.
+///
+/// `code` says which layer failed; `kind` tells the frontend what to do
+/// about it (re-login, retry, show a field error, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppErrorKind {
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    RateLimited,
+    Validation,
+    Network,
+    Server,
+    Protocol,
+    Storage,
+    DeviceBusy,
+    DeviceNotFound,
+    Device,
+    Internal,
+}
+
+impl From<AppErrorCode> for AppErrorKind {
+    fn from(code: AppErrorCode) -> Self {
+        match code {
+            AppErrorCode::Network => AppErrorKind::Network,
+            AppErrorCode::Protocol => AppErrorKind::Protocol,
+            AppErrorCode::Auth => AppErrorKind::Unauthorized,
+            AppErrorCode::Storage => AppErrorKind::Storage,
+            AppErrorCode::Validation => AppErrorKind::Validation,
+            AppErrorCode::Device => AppErrorKind::Device,
+            AppErrorCode::Internal => AppErrorKind::Internal,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct AppError {
     pub code: AppErrorCode,
+    pub kind: AppErrorKind,
     pub message: String,
+    /// HTTP status when the error came from an API response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -27,12 +69,43 @@ impl AppError {
     pub fn new(code: AppErrorCode, message: impl Into<String>) -> Self {
         Self {
             code,
+            kind: code.into(),
             message: message.into(),
+            status: None,
             details: None,
             trace_id: Some(crate::observability::trace_id().to_string()),
         }
     }
 
+    pub fn with_kind(mut self, kind: AppErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Map a non-success HTTP response into a typed error. `context` is the
+    /// user-facing action ("Failed to fetch servers"); the server's error
+    /// message, if any, ends up in `details`.
+    pub fn from_http_response(status: u16, context: &str, body: &str) -> Self {
+        let (code, kind) = match status {
+            401 => (AppErrorCode::Auth, AppErrorKind::Unauthorized),
+            403 => (AppErrorCode::Auth, AppErrorKind::Forbidden),
+            404 => (AppErrorCode::Validation, AppErrorKind::NotFound),
+            409 => (AppErrorCode::Validation, AppErrorKind::Conflict),
+            400 | 422 => (AppErrorCode::Validation, AppErrorKind::Validation),
+            426 => (AppErrorCode::Protocol, AppErrorKind::Protocol),
+            429 => (AppErrorCode::Network, AppErrorKind::RateLimited),
+            500..=599 => (AppErrorCode::Network, AppErrorKind::Server),
+            _ => (AppErrorCode::Protocol, AppErrorKind::Protocol),
+        };
+
+        let mut error = AppError::new(code, format!("{} ({})", context, status)).with_kind(kind);
+        error.status = Some(status);
+        match server_error_message(body) {
+            Some(details) => error.with_details(details),
+            None => error,
+        }
+    }
+
     pub fn with_details(mut self, details: impl Into<String>) -> Self {
         self.details = Some(details.into());
         self
@@ -85,10 +158,32 @@ impl From<String> for AppError {
 
 impl From<reqwest::Error> for AppError {
     fn from(value: reqwest::Error) -> Self {
+        if value.is_decode() {
+            return AppError::protocol("Failed to parse response").with_details(value.to_string());
+        }
         AppError::network("Network request failed").with_details(value.to_string())
     }
 }
 
+/// Pull a human-readable message out of a server error body. The server
+/// answers with `{"error": ...}`, `{"message": ...}` or plain text.
+fn server_error_message(body: &str) -> Option<String> {
+    let trimmed = body.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(trimmed) {
+        for key in ["error", "message"] {
+            if let Some(message) = value.get(key).and_then(|v| v.as_str()) {
+                return Some(message.to_string());
+            }
+        }
+    }
+
+    Some(trimmed.to_string())
+}
+
 impl From<serde_json::Error> for AppError {
     fn from(value: serde_json::Error) -> Self {
         AppError::protocol("Invalid JSON payload").with_details(value.to_string())
@@ -107,6 +202,52 @@ impl From<url::ParseError> for AppError {
     }
 }
 
+impl From<media::AudioDeviceError> for AppError {
+    fn from(value: media::AudioDeviceError) -> Self {
+        let kind = match &value {
+            media::AudioDeviceError::Busy { .. } => AppErrorKind::DeviceBusy,
+            media::AudioDeviceError::NotFound { .. } => AppErrorKind::DeviceNotFound,
+            media::AudioDeviceError::Failed { .. } => AppErrorKind::Device,
+        };
+        AppError::new(AppErrorCode::Device, value.to_string()).with_kind(kind)
+    }
+}
+
+impl From<media::CryptoError> for AppError {
+    fn from(value: media::CryptoError) -> Self {
+        use media::CryptoError;
+        match value {
+            // The peer sent a key we cannot use
+            CryptoError::Base64Decode(_)
+            | CryptoError::InvalidPublicKeyLength { .. }
+            | CryptoError::KeyAgreementFailed
+            | CryptoError::InvalidSenderKey(_) => AppError::validation(value.to_string()),
+            // Key exchange out of step with the call
+            CryptoError::NoKeyPair => {
+                AppError::internal(value.to_string()).with_kind(AppErrorKind::Conflict)
+            }
+            _ => AppError::internal(value.to_string()),
+        }
+    }
+}
+
+/// Keep typed audio device and crypto failures from the media engine;
+/// everything else goes through the usual string classification with
+/// `context` prepended.
+pub fn from_media_error(error: anyhow::Error, context: &str) -> AppError {
+    if let Some(crypto_error) = error.downcast_ref::<media::CryptoError>() {
+        let typed = AppError::from(crypto_error.clone());
+        return AppError {
+            message: format!("{}: {}", context, typed.message),
+            ..typed
+        };
+    }
+    match error.downcast::<media::AudioDeviceError>() {
+        Ok(device_error) => device_error.into(),
+        Err(other) => format!("{}: {}", context, other).into(),
+    }
+}
+
 impl From<crate::messaging::error::MessagingError> for AppError {
     fn from(value: crate::messaging::error::MessagingError) -> Self {
         AppError::storage("Messaging storage failure").with_details(value.to_string())
@@ -159,5 +300,69 @@ mod tests {
         assert_eq!(json["message"], "Network error: timeout");
         assert_eq!(json["details"], "socket timeout");
         assert!(json.get("trace_id").is_some());
+        assert_eq!(json["kind"], "network");
+    }
+
+    #[test]
+    fn maps_http_status_to_kind() {
+        let unauthorized = AppError::from_http_response(401, "Failed to fetch servers", "");
+        assert_eq!(unauthorized.kind, AppErrorKind::Unauthorized);
+        assert_eq!(unauthorized.status, Some(401));
+        assert!(unauthorized.details.is_none());
+
+        let validation = AppError::from_http_response(
+            400,
+            "Registration failed",
+            r#"{"error":"Validation error: username too short"}"#,
+        );
+        assert_eq!(validation.kind, AppErrorKind::Validation);
+        assert_eq!(
+            validation.details.as_deref(),
+            Some("Validation error: username too short")
+        );
+
+        let server = AppError::from_http_response(503, "Failed to join server", "upstream down");
+        assert_eq!(server.kind, AppErrorKind::Server);
+        assert_eq!(server.message, "Failed to join server (503)");
+        assert_eq!(server.details.as_deref(), Some("upstream down"));
+    }
+
+    #[test]
+    fn busy_audio_device_keeps_its_kind() {
+        let busy = from_media_error(
+            media::AudioDeviceError::Busy {
+                direction: media::AudioDirection::Input,
+                device: "USB Mic".to_string(),
+            }
+            .into(),
+            "Failed to set audio device",
+        );
+        assert_eq!(busy.kind, AppErrorKind::DeviceBusy);
+        assert_eq!(busy.message, "Microphone is in use by another application");
+
+        let other = from_media_error(anyhow::anyhow!("boom"), "Failed to set audio device");
+        assert_eq!(other.message, "Failed to set audio device: boom");
+    }
+
+    #[test]
+    fn bad_peer_key_is_told_apart_from_a_missing_keypair() {
+        let bad_key = from_media_error(
+            media::CryptoError::InvalidPublicKeyLength {
+                expected: 32,
+                actual: 31,
+            }
+            .into(),
+            "Key exchange failed",
+        );
+        assert_eq!(bad_key.kind, AppErrorKind::Validation);
+        assert_eq!(
+            bad_key.message,
+            "Key exchange failed: Invalid public key: expected 32 bytes, got 31"
+        );
+
+        let no_keypair =
+            from_media_error(media::CryptoError::NoKeyPair.into(), "Key exchange failed");
+        assert_eq!(no_keypair.kind, AppErrorKind::Conflict);
+        assert!(matches!(no_keypair.code, AppErrorCode::Internal));
     }
 }
@@ -2,6 +2,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod api;
+mod audio_prefs;
 mod backoff;
 mod config;
 mod error;
@@ -12,11 +13,20 @@ mod signaling;
 mod updater;
 
 use api::ApiState;
-use error::AppResult;
-use media::{AudioSettings, IceServerConfig, MediaEngine};
+use audio_prefs::AudioDevicePrefs;
+use error::{from_media_error, AppError, AppResult};
+use media::{
+    AudioDeviceEvent, AudioProfile, AudioSettings, CallAudioParams, CallQualityScore, CallStats,
+    ConnectivityReport, IceServerConfig, IceStateChange, MediaEngine, PlaybackBufferStats,
+    RecordingInfo, RecordingSummary, RingbackRegion, RingtoneClip, SdpTransform,
+};
+use messaging::domain::{ConversationKind, PersistedMessage};
 use messaging::service::MessagingService;
+use messaging::storage::StoreStats;
+use shared_proto::redact::Redacted;
 use shared_proto::signaling::SignalingMessage;
 use signaling::WsSender;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::{Emitter, Manager, State};
 use tokio::sync::Mutex;
@@ -24,11 +34,15 @@ use tokio::sync::Mutex;
 struct AppState {
     media: Arc<Mutex<MediaEngine>>,
     ws_sender: WsSender,
+    audio_prefs_path: PathBuf,
 }
 
 #[derive(Clone)]
 pub struct MessagingState {
     pub service: MessagingService,
+    /// Messages kept per conversation when the cache is pruned; `None` keeps
+    /// everything
+    pub cache_limit: Option<i64>,
 }
 
 fn parse_csv_env(name: &str) -> Vec<String> {
@@ -50,7 +64,10 @@ fn load_ice_servers_from_env() -> Vec<IceServerConfig> {
                 return parsed;
             }
         }
-        eprintln!("[ICE] Failed to parse ICE_SERVERS_JSON, falling back to STUN/TURN vars");
+        tracing::warn!(
+            component = "webrtc",
+            "failed to parse ICE_SERVERS_JSON, falling back to STUN/TURN vars"
+        );
     }
 
     let mut servers: Vec<IceServerConfig> = Vec::new();
@@ -85,6 +102,100 @@ fn load_ice_servers_from_env() -> Vec<IceServerConfig> {
     servers
 }
 
+/// Optional SDP rewrite from `OPUS_MAX_AVERAGE_BITRATE` (bits/s) and
+/// `SDP_BANDWIDTH_KBPS`, applied to local offers and answers.
+fn sdp_transform_from_env() -> Option<Box<SdpTransform>> {
+    let parse = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .filter(|v| *v > 0)
+    };
+    let opus_bitrate = parse("OPUS_MAX_AVERAGE_BITRATE");
+    let bandwidth_kbps = parse("SDP_BANDWIDTH_KBPS");
+    if opus_bitrate.is_none() && bandwidth_kbps.is_none() {
+        return None;
+    }
+
+    Some(Box::new(move |sdp: String| {
+        let sdp = match opus_bitrate {
+            Some(bitrate) => media::cap_opus_bitrate(&sdp, bitrate),
+            None => sdp,
+        };
+        match bandwidth_kbps {
+            Some(kbps) => media::set_bandwidth_limit(&sdp, kbps),
+            None => sdp,
+        }
+    }))
+}
+
+/// Debug toggle: `AUDIO_RAW_MONITOR=1` plays remote audio without volume or
+/// limiter processing, to check whether distortion comes from the peer.
+fn playback_raw_monitor_from_env() -> bool {
+    std::env::var("AUDIO_RAW_MONITOR")
+        .map(|v| matches!(v.trim(), "1" | "true"))
+        .unwrap_or(false)
+}
+
+/// Ringback cadence from `RINGBACK_REGION` (`us`, the default, or `uk`);
+/// `off` disables the ringback.
+fn ringback_region_from_env() -> Option<RingbackRegion> {
+    let Ok(raw) = std::env::var("RINGBACK_REGION") else {
+        return Some(RingbackRegion::default());
+    };
+    if matches!(raw.trim(), "off" | "none" | "0") {
+        return None;
+    }
+    match raw.parse() {
+        Ok(region) => Some(region),
+        Err(e) => {
+            tracing::warn!(component = "audio", "{}, using the US ringback", e);
+            Some(RingbackRegion::default())
+        }
+    }
+}
+
+/// `MESSAGE_CACHE_LIMIT=N` keeps only the newest N cached messages per
+/// conversation; unset or 0 keeps everything.
+fn message_cache_limit_from_env() -> Option<i64> {
+    std::env::var("MESSAGE_CACHE_LIMIT")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|v| *v > 0)
+}
+
+/// Open an audio stream off the async runtime, without holding the media
+/// lock: opening waits on the audio thread for up to a few seconds
+async fn open_blocking<T, F>(open: F, context: &str) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(open)
+        .await
+        .map_err(|e| AppError::internal(format!("{}: {}", context, e)))?
+        .map_err(|e| from_media_error(e, context))
+}
+
+/// Restore the output and ringtone devices chosen in an earlier session
+fn apply_audio_device_prefs(engine: &mut MediaEngine, prefs: AudioDevicePrefs) {
+    engine.enable_auto_device_follow(prefs.follow_default_device);
+    if let Err(e) = engine.set_output_device(prefs.output_device) {
+        tracing::warn!(
+            component = "audio",
+            "failed to restore output device: {}",
+            e
+        );
+    }
+    if let Err(e) = engine.set_ringtone_device(prefs.ringtone_device) {
+        tracing::warn!(
+            component = "audio",
+            "failed to restore ringtone device: {}",
+            e
+        );
+    }
+}
+
 #[tauri::command]
 async fn identify_user(
     state: State<'_, AppState>,
@@ -99,6 +210,32 @@ async fn identify_user(
     );
     let token = api_state.bearer_token().await?;
     signaling::send_identify(&state.ws_sender, &user_id, &token).await?;
+
+    // Minted TURN credentials win, then the server's ICE list; without
+    // either (or if the fetch fails) the env config stays in place.
+    match api::calls::fetch_ice_credentials(&api_state).await {
+        Ok(servers) if !servers.is_empty() => {
+            tracing::info!(count = servers.len(), "using minted TURN credentials");
+            state.media.lock().await.set_ice_servers(servers);
+            return Ok(());
+        }
+        Ok(_) => {}
+        Err(e) => tracing::debug!("No minted TURN credentials, trying ICE servers: {}", e),
+    }
+    match api::calls::fetch_ice_servers(&api_state).await {
+        Ok(servers) if !servers.is_empty() => {
+            tracing::info!(count = servers.len(), "using server-provided ICE servers");
+            state.media.lock().await.set_ice_servers(servers);
+        }
+        Ok(_) => {
+            state
+                .media
+                .lock()
+                .await
+                .set_ice_servers(load_ice_servers_from_env());
+        }
+        Err(e) => tracing::warn!("Could not fetch ICE servers, keeping local config: {}", e),
+    }
     Ok(())
 }
 
@@ -111,6 +248,7 @@ async fn send_offer(
     let msg = SignalingMessage::Offer {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         target_id,
         sdp,
     };
@@ -126,44 +264,85 @@ async fn send_answer(
     let msg = SignalingMessage::Answer {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         target_id,
         sdp,
     };
     signaling::send_signal(&state.ws_sender, msg).await
 }
 
+/// Report our mic/speaking state in a voice channel to its other members
+#[tauri::command]
+async fn send_voice_state(
+    state: State<'_, AppState>,
+    channel_id: String,
+    muted: bool,
+    speaking: bool,
+) -> AppResult<()> {
+    let msg = SignalingMessage::VoiceState {
+        version: protocol::PROTOCOL_VERSION,
+        trace_id: Some(observability::trace_id().to_string()),
+        channel_id,
+        muted,
+        speaking,
+    };
+    signaling::send_signal(&state.ws_sender, msg).await
+}
+
 // === Call Commands ===
 
 /// Start a call to a friend - generates keypair and sends CallInitiate
 #[tauri::command]
-async fn start_call(state: State<'_, AppState>, target_id: String) -> AppResult<String> {
-    println!("📞 [CALL-DEBUG] ===== STARTING CALL =====");
-    println!("📞 [CALL-DEBUG] Target ID: {}", target_id);
-
+async fn start_call(
+    state: State<'_, AppState>,
+    target_id: String,
+    ring_timeout_secs: Option<u32>,
+) -> AppResult<String> {
     // Generate keypair for E2EE
-    println!("📞 [CALL-DEBUG] Generating keypair...");
     let public_key = {
         let mut engine = state.media.lock().await;
-        engine.generate_keypair().map_err(|e| {
-            println!("📞 [CALL-DEBUG] ❌ Failed to generate keypair: {}", e);
-            e.to_string()
-        })?
+        let pk = engine.generate_keypair().map_err(|e| {
+            tracing::error!(component = "call", "failed to generate keypair: {}", e);
+            from_media_error(e, "Failed to generate keypair")
+        })?;
+        // Open devices while the callee's client rings; a failure here is
+        // reported again when the call connects
+        if let Err(e) = engine.prewarm_audio() {
+            tracing::warn!("Failed to pre-warm audio devices: {}", e);
+        }
+        pk
     };
-    println!(
-        "📞 [CALL-DEBUG] Generated public key: {}...",
-        &public_key[..30.min(public_key.len())]
-    );
 
     // Send call initiate signal
-    println!("📞 [CALL-DEBUG] Sending CallInitiate signal...");
+    let call_id = observability::begin_call();
+    tracing::info!(
+        component = "call",
+        call_id = %call_id,
+        target_id = %target_id,
+        public_key = %Redacted(&public_key),
+        "placing call"
+    );
     let msg = SignalingMessage::CallInitiate {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: Some(call_id),
         target_id: target_id.clone(),
         public_key: public_key.clone(),
+        ring_timeout_secs,
     };
     signaling::send_signal(&state.ws_sender, msg).await?;
-    println!("📞 [CALL-DEBUG] ✅ CallInitiate sent successfully");
+
+    // Ringback until the callee answers (complete_call_handshake) or the
+    // call is torn down (reset)
+    if let Some(region) = ringback_region_from_env() {
+        let pending = state.media.lock().await.prepare_ringback(region);
+        match open_blocking(move || pending.open(), "Failed to play ringback").await {
+            Ok(opened) => {
+                state.media.lock().await.install_ringtone(opened);
+            }
+            Err(e) => tracing::warn!(component = "audio", "failed to play ringback: {}", e),
+        }
+    }
 
     Ok(public_key)
 }
@@ -175,79 +354,144 @@ async fn accept_call(
     caller_id: String,
     caller_public_key: String,
 ) -> AppResult<String> {
-    println!("✅ [CALL-DEBUG] ===== ACCEPTING CALL =====");
-    println!("✅ [CALL-DEBUG] Caller ID: {}", caller_id);
-    println!(
-        "✅ [CALL-DEBUG] Caller public key: {}...",
-        &caller_public_key[..30.min(caller_public_key.len())]
+    tracing::info!(
+        component = "call",
+        call_id = ?observability::call_id(),
+        caller_id = %caller_id,
+        caller_public_key = %Redacted(&caller_public_key),
+        "accepting call"
     );
 
     // Generate our keypair and complete key exchange
-    println!("✅ [CALL-DEBUG] Generating keypair and completing key exchange...");
     let public_key = {
         let mut engine = state.media.lock().await;
+        engine.stop_ringtone();
         let pk = engine.generate_keypair().map_err(|e| {
-            println!("✅ [CALL-DEBUG] ❌ Failed to generate keypair: {}", e);
-            e.to_string()
+            tracing::error!(component = "call", "failed to generate keypair: {}", e);
+            from_media_error(e, "Failed to generate keypair")
         })?;
         engine
             .complete_key_exchange(&caller_public_key)
             .map_err(|e| {
-                println!("✅ [CALL-DEBUG] ❌ Key exchange failed: {}", e);
-                e.to_string()
+                tracing::error!(component = "call", "key exchange failed: {}", e);
+                from_media_error(e, "Key exchange failed")
             })?;
         pk
     };
-    println!(
-        "✅ [CALL-DEBUG] Generated our public key: {}...",
-        &public_key[..30.min(public_key.len())]
+    tracing::debug!(
+        component = "call",
+        public_key = %Redacted(&public_key),
+        "generated call keypair"
     );
 
     // Send accept signal with our public key
-    println!("✅ [CALL-DEBUG] Sending CallAccept signal...");
     let msg = SignalingMessage::CallAccept {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         caller_id,
         public_key: public_key.clone(),
     };
     signaling::send_signal(&state.ws_sender, msg).await?;
-    println!("✅ [CALL-DEBUG] ✅ CallAccept sent successfully");
 
     Ok(public_key)
 }
 
+/// Answer a call that rang while already in one. Accepting drops the
+/// current call (the server tells its peer) and returns our public key for
+/// the new one, like `accept_call`; declining returns `None`.
+#[tauri::command]
+async fn answer_waiting_call(
+    state: State<'_, AppState>,
+    caller_id: String,
+    call_id: Option<String>,
+    caller_public_key: String,
+    accept: bool,
+) -> AppResult<Option<String>> {
+    tracing::info!(
+        component = "call",
+        call_id = ?call_id,
+        caller_id = %caller_id,
+        accept,
+        "answering waiting call"
+    );
+
+    if !accept {
+        let msg = SignalingMessage::CallWaiting {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: Some(observability::trace_id().to_string()),
+            call_id,
+            caller_id,
+            accept: false,
+            public_key: None,
+        };
+        signaling::send_signal(&state.ws_sender, msg).await?;
+        return Ok(None);
+    }
+
+    // The current call's media goes away before the new keys are made
+    let public_key = {
+        let mut engine = state.media.lock().await;
+        engine.reset().await;
+        let pk = engine.generate_keypair().map_err(|e| {
+            tracing::error!(component = "call", "failed to generate keypair: {}", e);
+            from_media_error(e, "Failed to generate keypair")
+        })?;
+        engine
+            .complete_key_exchange(&caller_public_key)
+            .map_err(|e| {
+                tracing::error!(component = "call", "key exchange failed: {}", e);
+                from_media_error(e, "Key exchange failed")
+            })?;
+        pk
+    };
+    observability::finish_call(None);
+    observability::adopt_call_id(call_id.as_deref());
+
+    let msg = SignalingMessage::CallWaiting {
+        version: protocol::PROTOCOL_VERSION,
+        trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
+        caller_id,
+        accept: true,
+        public_key: Some(public_key.clone()),
+    };
+    signaling::send_signal(&state.ws_sender, msg).await?;
+
+    Ok(Some(public_key))
+}
+
 /// Complete key exchange after call is accepted (caller side)
 #[tauri::command]
 async fn complete_call_handshake(
     state: State<'_, AppState>,
     peer_public_key: String,
 ) -> AppResult<()> {
-    println!("🔐 [CALL-DEBUG] ===== COMPLETING E2EE HANDSHAKE =====");
-    println!(
-        "🔐 [CALL-DEBUG] Peer public key (first 30 chars): {}...",
-        &peer_public_key[..30.min(peer_public_key.len())]
+    tracing::info!(
+        component = "call",
+        call_id = ?observability::call_id(),
+        peer_public_key = %Redacted(&peer_public_key),
+        "completing key exchange"
     );
 
     {
         let mut engine = state.media.lock().await;
-        println!("🔐 [CALL-DEBUG] Got media engine lock");
+        engine.stop_ringback();
         engine
             .complete_key_exchange(&peer_public_key)
             .map_err(|e| {
-                println!("🔐 [CALL-DEBUG] ❌ Key exchange failed: {}", e);
-                e.to_string()
+                tracing::error!(component = "call", "key exchange failed: {}", e);
+                from_media_error(e, "Key exchange failed")
             })?;
     }
 
-    println!("🔐 [CALL-DEBUG] ✅ Key exchange completed successfully");
     Ok(())
 }
 
 /// Decline incoming call
 #[tauri::command]
 async fn decline_call(state: State<'_, AppState>, caller_id: String) -> AppResult<()> {
-    println!("❌ Declining call from {}", caller_id);
+    tracing::info!(component = "call", caller_id = %caller_id, "declining call");
 
     // Reset media engine (may have generated keypair)
     {
@@ -258,15 +502,18 @@ async fn decline_call(state: State<'_, AppState>, caller_id: String) -> AppResul
     let msg = SignalingMessage::CallDecline {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         caller_id,
     };
-    signaling::send_signal(&state.ws_sender, msg).await
+    let sent = signaling::send_signal(&state.ws_sender, msg).await;
+    observability::finish_call(None);
+    sent
 }
 
 /// End active call
 #[tauri::command]
 async fn end_call(state: State<'_, AppState>, peer_id: String) -> AppResult<()> {
-    println!("📴 Ending call with {}", peer_id);
+    tracing::info!(component = "call", peer_id = %peer_id, "ending call");
 
     // Reset media engine for next call
     {
@@ -277,15 +524,18 @@ async fn end_call(state: State<'_, AppState>, peer_id: String) -> AppResult<()>
     let msg = SignalingMessage::CallEnd {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         peer_id,
     };
-    signaling::send_signal(&state.ws_sender, msg).await
+    let sent = signaling::send_signal(&state.ws_sender, msg).await;
+    observability::finish_call(None);
+    sent
 }
 
 /// Cancel outgoing call before answer
 #[tauri::command]
 async fn cancel_call(state: State<'_, AppState>, target_id: String) -> AppResult<()> {
-    println!("🚫 Cancelling call to {}", target_id);
+    tracing::info!(component = "call", target_id = %target_id, "cancelling call");
 
     // Reset media engine
     {
@@ -296,9 +546,21 @@ async fn cancel_call(state: State<'_, AppState>, target_id: String) -> AppResult
     let msg = SignalingMessage::CallCancel {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         target_id,
     };
-    signaling::send_signal(&state.ws_sender, msg).await
+    let sent = signaling::send_signal(&state.ws_sender, msg).await;
+    observability::finish_call(None);
+    sent
+}
+
+/// Open and start audio devices for a ringing incoming call, so audio flows
+/// as soon as it is accepted. Released by decline/cancel/reset.
+#[tauri::command]
+async fn prewarm_call_audio(state: State<'_, AppState>) -> AppResult<()> {
+    let engine = state.media.lock().await;
+    engine.prewarm_audio().map_err(|e| e.to_string())?;
+    Ok(())
 }
 
 /// Reset local call media state without sending signaling
@@ -361,8 +623,8 @@ async fn set_audio_device(state: State<'_, AppState>, device_id: String) -> AppR
     let mut engine = state.media.lock().await;
     engine
         .set_input_device(Some(device_id.clone()))
-        .map_err(|e| format!("Failed to set audio device: {}", e))?;
-    println!("🔊 [AUDIO] Input device set to: {}", device_id);
+        .map_err(|e| from_media_error(e, "Failed to set audio device"))?;
+    tracing::info!(component = "audio", device_id = %device_id, "input device set");
     Ok(())
 }
 
@@ -405,11 +667,91 @@ async fn get_selected_output_device(
 
 #[tauri::command]
 async fn set_output_device(state: State<'_, AppState>, device_id: String) -> AppResult<()> {
+    let change = state
+        .media
+        .lock()
+        .await
+        .prepare_output_device(Some(device_id.clone()));
+    let applied = open_blocking(move || change.apply(), "Failed to set output device").await?;
     let mut engine = state.media.lock().await;
-    engine
-        .set_output_device(Some(device_id.clone()))
-        .map_err(|e| format!("Failed to set output device: {}", e))?;
-    println!("🔊 [AUDIO] Output device set to: {}", device_id);
+    engine.commit_output_device(applied);
+    AudioDevicePrefs::update(&state.audio_prefs_path, |prefs| {
+        prefs.output_device = engine.selected_output_device();
+    });
+    tracing::info!(component = "audio", device_id = %device_id, "output device set");
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_selected_ringtone_device(
+    state: State<'_, AppState>,
+) -> AppResult<Option<AudioDevice>> {
+    let engine = state.media.lock().await;
+    Ok(engine.selected_ringtone_device().map(|name| AudioDevice {
+        id: name.clone(),
+        name,
+    }))
+}
+
+/// Pick the device the incoming-call ringtone plays on; call audio stays on
+/// the output device
+#[tauri::command]
+async fn set_ringtone_device(state: State<'_, AppState>, device_id: String) -> AppResult<()> {
+    let pending = state
+        .media
+        .lock()
+        .await
+        .prepare_ringtone_device(Some(device_id.clone()));
+    if let Some(pending) = pending {
+        let opened = open_blocking(move || pending.open(), "Failed to set ringtone device").await?;
+        state.media.lock().await.install_ringtone(opened);
+    }
+    let engine = state.media.lock().await;
+    AudioDevicePrefs::update(&state.audio_prefs_path, |prefs| {
+        prefs.ringtone_device = engine.selected_ringtone_device();
+    });
+    tracing::info!(component = "audio", device_id = %device_id, "ringtone device set");
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_auto_device_follow(state: State<'_, AppState>) -> AppResult<bool> {
+    let engine = state.media.lock().await;
+    Ok(engine.auto_device_follow())
+}
+
+/// Follow the system default device during calls when no device is chosen
+#[tauri::command]
+async fn set_auto_device_follow(state: State<'_, AppState>, enabled: bool) -> AppResult<()> {
+    let engine = state.media.lock().await;
+    engine.enable_auto_device_follow(enabled);
+    AudioDevicePrefs::update(&state.audio_prefs_path, |prefs| {
+        prefs.follow_default_device = enabled;
+    });
+    tracing::info!(component = "audio", enabled, "default device follow set");
+    Ok(())
+}
+
+/// Ring on the ringtone device until the call is accepted or reset. `path`
+/// names a WAV file; without it the built-in tone is used.
+#[tauri::command]
+async fn play_ringtone(state: State<'_, AppState>, path: Option<String>) -> AppResult<()> {
+    let clip = match path {
+        Some(path) => {
+            RingtoneClip::from_wav_file(&path).map_err(|e| AppError::validation(e.to_string()))?
+        }
+        None => RingtoneClip::default_tone(),
+    };
+    let pending = state.media.lock().await.prepare_ringtone(clip);
+    let opened = open_blocking(move || pending.open(), "Failed to play ringtone").await?;
+    state.media.lock().await.install_ringtone(opened);
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_ringtone(state: State<'_, AppState>) -> AppResult<()> {
+    let mut engine = state.media.lock().await;
+    engine.stop_ringtone();
     Ok(())
 }
 
@@ -419,13 +761,133 @@ async fn get_audio_settings(state: State<'_, AppState>) -> AppResult<AudioSettin
     Ok(engine.get_audio_settings())
 }
 
+#[tauri::command]
+async fn get_audio_profile(state: State<'_, AppState>) -> AppResult<AudioProfile> {
+    let engine = state.media.lock().await;
+    Ok(engine.audio_profile())
+}
+
+/// Apply audio settings and return the ones now in effect. In a 1:1 call a
+/// new frame duration or stereo setting is offered to the peer instead and
+/// only applies once the peer accepts (`audio-params-answer`).
 #[tauri::command]
 async fn update_audio_settings(
     state: State<'_, AppState>,
-    settings: AudioSettings,
+    mut settings: AudioSettings,
+) -> AppResult<AudioSettings> {
+    let (offer, effective) = {
+        let mut engine = state.media.lock().await;
+        let wanted = CallAudioParams::of(&settings);
+        let current = engine.call_audio_params();
+        let offer = match engine.active_peer() {
+            Some(peer_id) if engine.in_call() && wanted != current => {
+                let peer_id = peer_id.to_string();
+                wanted
+                    .validate()
+                    .map_err(|e| AppError::validation(e.to_string()))?;
+                settings.frame_duration_ms = current.frame_duration_ms;
+                settings.stereo = current.channels == 2;
+                Some((peer_id, wanted))
+            }
+            _ => None,
+        };
+        engine
+            .update_audio_settings(settings)
+            .map_err(|e| AppError::validation(e.to_string()))?;
+        if let Some((_, params)) = &offer {
+            engine
+                .offer_call_audio_params(*params)
+                .map_err(|e| AppError::validation(e.to_string()))?;
+        }
+        (offer, engine.get_audio_settings())
+    };
+
+    if let Some((target_id, params)) = offer {
+        tracing::info!(
+            component = "call",
+            peer_id = %target_id,
+            frame_duration_ms = params.frame_duration_ms,
+            channels = params.channels,
+            "offering new audio params"
+        );
+        let msg = SignalingMessage::AudioParamsOffer {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: Some(observability::trace_id().to_string()),
+            call_id: observability::call_id(),
+            target_id,
+            frame_duration_ms: params.frame_duration_ms,
+            channels: params.channels,
+        };
+        signaling::send_signal(&state.ws_sender, msg).await?;
+    }
+    Ok(effective)
+}
+
+/// The peer offered new audio params: switch to them if we can and tell
+/// the peer either way. `self_id` is our user id, which settles offers that
+/// cross. The settings now in effect go out as `audio-settings-changed`.
+#[tauri::command]
+async fn answer_audio_params_offer(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    self_id: String,
+    peer_id: String,
+    params: CallAudioParams,
+) -> AppResult<()> {
+    let (accepted, answer, effective) = {
+        let mut engine = state.media.lock().await;
+        let accepted = match engine.accept_call_audio_params(&self_id, params) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(
+                    component = "call",
+                    peer_id = %peer_id,
+                    "rejecting audio params offer: {}",
+                    e
+                );
+                false
+            }
+        };
+        (
+            accepted,
+            engine.call_audio_params(),
+            engine.get_audio_settings(),
+        )
+    };
+
+    let msg = SignalingMessage::AudioParamsAnswer {
+        version: protocol::PROTOCOL_VERSION,
+        trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
+        target_id: peer_id,
+        accepted,
+        frame_duration_ms: answer.frame_duration_ms,
+        channels: answer.channels,
+    };
+    let _ = app.emit("audio-settings-changed", &effective);
+    signaling::send_signal(&state.ws_sender, msg).await
+}
+
+/// The peer answered our audio params offer; switch to it if accepted. The
+/// settings now in effect go out as `audio-settings-changed`.
+#[tauri::command]
+async fn finish_audio_params_offer(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    accepted: bool,
+    params: CallAudioParams,
 ) -> AppResult<()> {
     let mut engine = state.media.lock().await;
-    engine.update_audio_settings(settings);
+    engine.finish_call_audio_params(accepted, params);
+    let _ = app.emit("audio-settings-changed", engine.get_audio_settings());
+    if !accepted {
+        tracing::info!(
+            component = "call",
+            frame_duration_ms = params.frame_duration_ms,
+            channels = params.channels,
+            "peer rejected audio params offer"
+        );
+    }
     Ok(())
 }
 
@@ -443,6 +905,241 @@ async fn set_remote_user_volume(state: State<'_, AppState>, volume: f32) -> AppR
     Ok(())
 }
 
+/// Short authentication string of the call's key exchange, for the users to
+/// compare aloud; None until the keys are exchanged
+#[tauri::command]
+async fn get_sas_code(state: State<'_, AppState>) -> AppResult<Option<String>> {
+    let engine = state.media.lock().await;
+    Ok(engine.sas_code())
+}
+
+/// Loop the microphone into the speaker for a few seconds so the user can
+/// hear themselves. Returns at once; the test stops on its own.
+#[tauri::command]
+async fn run_mic_test(state: State<'_, AppState>, seconds: Option<u64>) -> AppResult<()> {
+    // The engine caps this at `media::MAX_LOOPBACK_TEST`
+    let duration = std::time::Duration::from_secs(seconds.unwrap_or(5));
+    let engine = state.media.lock().await;
+    engine
+        .start_loopback_test(duration)
+        .map_err(|e| from_media_error(e, "Failed to start mic test"))?;
+    tracing::info!(component = "audio", "mic test started");
+    Ok(())
+}
+
+/// Record the current call into an encrypted file, by default under the app
+/// data dir. Returns the file's key, which is not kept anywhere else.
+#[tauri::command]
+async fn start_recording(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> AppResult<RecordingInfo> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = app
+                .path()
+                .app_data_dir()
+                .map_err(|e| AppError::internal(format!("No app data directory: {e}")))?
+                .join("recordings");
+            std::fs::create_dir_all(&dir).map_err(|e| {
+                AppError::internal(format!("Failed to create {}: {e}", dir.display()))
+            })?;
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            dir.join(format!("call-{stamp}.p2prec"))
+        }
+    };
+
+    let engine = state.media.lock().await;
+    let info = engine
+        .start_recording(&path)
+        .map_err(|e| from_media_error(e, "Failed to start recording"))?;
+    tracing::info!(
+        component = "call",
+        call_id = ?observability::call_id(),
+        "recording started"
+    );
+    let _ = app.emit("recording-started", &info.path);
+    Ok(info)
+}
+
+/// Stop recording and finalize the file. Ending the call also stops it.
+#[tauri::command]
+async fn stop_recording(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AppResult<Option<RecordingSummary>> {
+    let engine = state.media.lock().await;
+    let summary = engine
+        .stop_recording()
+        .map_err(|e| from_media_error(e, "Failed to finish recording"))?;
+    if let Some(summary) = &summary {
+        tracing::info!(
+            component = "call",
+            duration_secs = summary.duration_secs,
+            "recording stopped"
+        );
+        let _ = app.emit("recording-stopped", summary);
+    }
+    Ok(summary)
+}
+
+/// Play a WAV file to the other side of the current call, over the
+/// microphone or instead of it. Returns the clip's length in ms.
+#[tauri::command]
+async fn play_sound_into_call(
+    state: State<'_, AppState>,
+    path: String,
+    replace_mic: bool,
+) -> AppResult<u64> {
+    let engine = state.media.lock().await;
+    let duration = engine
+        .play_file_into_call(&path, replace_mic)
+        .map_err(|e| from_media_error(e, "Failed to play sound"))?;
+    tracing::info!(
+        component = "call",
+        duration_ms = duration.as_millis() as u64,
+        replace_mic,
+        "sound clip playing into call"
+    );
+    Ok(duration.as_millis() as u64)
+}
+
+#[tauri::command]
+async fn stop_sound_into_call(state: State<'_, AppState>) -> AppResult<()> {
+    let engine = state.media.lock().await;
+    engine.stop_file_into_call();
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_peer_volume(state: State<'_, AppState>, peer_id: String) -> AppResult<f32> {
+    let engine = state.media.lock().await;
+    Ok(engine.get_peer_volume(&peer_id))
+}
+
+/// Volume for one remote peer, kept for later calls with them
+#[tauri::command]
+async fn set_peer_volume(
+    state: State<'_, AppState>,
+    peer_id: String,
+    volume: f32,
+) -> AppResult<()> {
+    let mut engine = state.media.lock().await;
+    engine.set_peer_volume(&peer_id, volume);
+    Ok(())
+}
+
+// === Diagnostics ===
+
+/// Connection quality for the current call; errors when no call is active
+#[tauri::command]
+async fn get_call_stats(state: State<'_, AppState>) -> AppResult<CallStats> {
+    state
+        .media
+        .lock()
+        .await
+        .get_call_stats()
+        .await
+        .map_err(|e| AppError::validation(e.to_string()))
+}
+
+/// Quality score and level of the current call as of the last sample; null
+/// outside a call and before the first sample
+#[tauri::command]
+async fn get_call_quality(state: State<'_, AppState>) -> AppResult<Option<CallQualityScore>> {
+    Ok(state.media.lock().await.call_quality())
+}
+
+/// Gather candidates against the configured ICE servers and report
+/// whether STUN and TURN answered. The engine is only locked to read the
+/// servers, so calls are not held up while this waits on them.
+#[tauri::command]
+async fn test_ice_servers(state: State<'_, AppState>) -> AppResult<ConnectivityReport> {
+    let ice_servers = state.media.lock().await.get_ice_servers();
+    let report = media::check_ice_servers(&ice_servers, media::CONNECTIVITY_CHECK_TIMEOUT)
+        .await
+        .map_err(|e| AppError::network(format!("ICE server check failed: {}", e)))?;
+    tracing::info!(
+        component = "call",
+        stun = report.stun,
+        turn = report.turn,
+        turn_configured = report.turn_configured,
+        timed_out = report.timed_out,
+        elapsed_ms = report.elapsed_ms,
+        "ice server check"
+    );
+    Ok(report)
+}
+
+#[derive(serde::Serialize)]
+struct MemoryReport {
+    /// `None` outside a call
+    playback: Option<PlaybackBufferStats>,
+    store: StoreStats,
+    message_cache_limit: Option<i64>,
+}
+
+/// Sizes of the buffers and stores that grow with use
+#[tauri::command]
+async fn get_memory_report(
+    state: State<'_, AppState>,
+    messaging: State<'_, MessagingState>,
+) -> AppResult<MemoryReport> {
+    let playback = state.media.lock().await.playback_buffer_stats();
+    let store = messaging.service.store_stats().await?;
+    Ok(MemoryReport {
+        playback,
+        store,
+        message_cache_limit: messaging.cache_limit,
+    })
+}
+
+/// Prune the local message cache now, down to `keep_per_conversation` or
+/// the configured `MESSAGE_CACHE_LIMIT`. Returns the number of messages
+/// removed.
+#[tauri::command]
+async fn prune_message_cache(
+    messaging: State<'_, MessagingState>,
+    keep_per_conversation: Option<i64>,
+) -> AppResult<u64> {
+    let limit = keep_per_conversation
+        .or(messaging.cache_limit)
+        .ok_or_else(|| AppError::validation("No message cache limit given or configured"))?;
+    if limit < 1 {
+        return Err(AppError::validation(
+            "Message cache limit must be at least 1",
+        ));
+    }
+
+    let pruned = messaging.service.prune_messages(limit).await?;
+    tracing::info!(
+        component = "storage",
+        pruned,
+        limit,
+        "pruned local message cache"
+    );
+    Ok(pruned)
+}
+
+/// Search the local message cache, best match first. Works offline; only
+/// messages cached on this device and not end-to-end encrypted are found.
+#[tauri::command]
+async fn search_local_messages(
+    messaging: State<'_, MessagingState>,
+    query: String,
+    target_kind: Option<ConversationKind>,
+    target_id: Option<String>,
+    limit: Option<i64>,
+) -> AppResult<Vec<PersistedMessage>> {
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    Ok(messaging
+        .service
+        .search_local(&query, target_kind, target_id.as_deref(), limit)
+        .await?)
+}
+
 #[derive(serde::Deserialize)]
 struct IceCandidatePayload {
     candidate: String,
@@ -454,7 +1151,7 @@ struct IceCandidatePayload {
 /// Initializes PC, DC, creates Offer, and sends it via WS.
 #[tauri::command]
 async fn init_audio_call(state: State<'_, AppState>, target_id: String) -> AppResult<()> {
-    println!("📞 [WEBRTC] Initializing audio call to {}", target_id);
+    tracing::info!(component = "webrtc", target_id = %target_id, "initializing audio call");
 
     // 1. Initialize WebRTC
     let mut ice_rx = {
@@ -462,6 +1159,7 @@ async fn init_audio_call(state: State<'_, AppState>, target_id: String) -> AppRe
         if !engine.is_ready_for_audio() {
             return Err("E2EE handshake not completed".to_string().into());
         }
+        engine.set_active_peer(Some(target_id.clone()));
         engine.init_webrtc().await.map_err(|e| e.to_string())?
     };
 
@@ -479,6 +1177,7 @@ async fn init_audio_call(state: State<'_, AppState>, target_id: String) -> AppRe
                 let msg = SignalingMessage::Candidate {
                     version: protocol::PROTOCOL_VERSION,
                     trace_id: Some(observability::trace_id().to_string()),
+                    call_id: observability::call_id(),
                     target_id: target_id_clone.clone(),
                     candidate,
                     sdp_mid,
@@ -501,14 +1200,19 @@ async fn init_audio_call(state: State<'_, AppState>, target_id: String) -> AppRe
     // 4. Create Offer
     let sdp = {
         let engine = state.media.lock().await;
-        engine.create_offer().await.map_err(|e| e.to_string())?
+        let transform = sdp_transform_from_env();
+        engine
+            .create_offer(transform.as_deref())
+            .await
+            .map_err(|e| e.to_string())?
     };
-    println!("📞 [WEBRTC] Offer created, sending...");
+    tracing::debug!(component = "webrtc", "offer created, sending");
 
     // 5. Send Offer via WS
     let msg = SignalingMessage::Offer {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         target_id,
         sdp,
     };
@@ -523,12 +1227,13 @@ async fn handle_audio_offer(
     target_id: String,
     sdp: String,
 ) -> AppResult<()> {
-    println!("📞 [WEBRTC] Handling Offer from {}", target_id);
+    tracing::info!(component = "webrtc", target_id = %target_id, "handling offer");
 
     // 1. Initialize WebRTC (Answerer)
     let mut ice_rx = {
         let mut engine = state.media.lock().await;
-        // Note: is_ready_for_audio check might fail if E2EE not finished, but normally it is.
+        // E2EE may finish after this; audio then attaches in complete_key_exchange
+        engine.set_active_peer(Some(target_id.clone()));
         engine.init_webrtc().await.map_err(|e| e.to_string())?
     };
 
@@ -545,6 +1250,7 @@ async fn handle_audio_offer(
                 let msg = SignalingMessage::Candidate {
                     version: protocol::PROTOCOL_VERSION,
                     trace_id: Some(observability::trace_id().to_string()),
+                    call_id: observability::call_id(),
                     target_id: target_id_clone.clone(),
                     candidate,
                     sdp_mid,
@@ -558,14 +1264,19 @@ async fn handle_audio_offer(
     // 3. Accept Offer and Create Answer
     let answer_sdp = {
         let engine = state.media.lock().await;
-        engine.accept_offer(&sdp).await.map_err(|e| e.to_string())?
+        let transform = sdp_transform_from_env();
+        engine
+            .accept_offer(&sdp, transform.as_deref())
+            .await
+            .map_err(|e| e.to_string())?
     };
-    println!("📞 [WEBRTC] Answer created, sending...");
+    tracing::debug!(component = "webrtc", "answer created, sending");
 
     // 4. Send Answer
     let msg = SignalingMessage::Answer {
         version: protocol::PROTOCOL_VERSION,
         trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
         target_id,
         sdp: answer_sdp,
     };
@@ -575,7 +1286,7 @@ async fn handle_audio_offer(
 /// Handle received Answer (Caller side)
 #[tauri::command]
 async fn handle_audio_answer(state: State<'_, AppState>, sdp: String) -> AppResult<()> {
-    println!("📞 [WEBRTC] Handling Answer");
+    tracing::info!(component = "webrtc", "handling answer");
     let engine = state.media.lock().await;
     engine
         .set_remote_description(&sdp)
@@ -584,6 +1295,59 @@ async fn handle_audio_answer(state: State<'_, AppState>, sdp: String) -> AppResu
     Ok(())
 }
 
+/// Restart ICE on the current call (recovering side)
+/// Sends an offer with fresh ICE credentials; the answer comes back as a
+/// regular `webrtc-answer`. New candidates go out through the forwarder
+/// spawned when the call was set up.
+#[tauri::command]
+async fn restart_ice(state: State<'_, AppState>, target_id: String) -> AppResult<()> {
+    tracing::info!(component = "webrtc", target_id = %target_id, "restarting ICE");
+    let sdp = {
+        let engine = state.media.lock().await;
+        let transform = sdp_transform_from_env();
+        engine
+            .restart_ice(transform.as_deref())
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let msg = SignalingMessage::CallRestart {
+        version: protocol::PROTOCOL_VERSION,
+        trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
+        target_id,
+        sdp,
+    };
+    signaling::send_signal(&state.ws_sender, msg).await
+}
+
+/// Handle an ICE restart offer from the peer and answer it
+#[tauri::command]
+async fn handle_ice_restart(
+    state: State<'_, AppState>,
+    target_id: String,
+    sdp: String,
+) -> AppResult<()> {
+    tracing::info!(component = "webrtc", target_id = %target_id, "handling ICE restart");
+    let answer_sdp = {
+        let engine = state.media.lock().await;
+        let transform = sdp_transform_from_env();
+        engine
+            .accept_ice_restart(&sdp, transform.as_deref())
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let msg = SignalingMessage::Answer {
+        version: protocol::PROTOCOL_VERSION,
+        trace_id: Some(observability::trace_id().to_string()),
+        call_id: observability::call_id(),
+        target_id,
+        sdp: answer_sdp,
+    };
+    signaling::send_signal(&state.ws_sender, msg).await
+}
+
 /// Handle received ICE candidate
 #[tauri::command]
 async fn handle_ice_candidate(
@@ -609,7 +1373,7 @@ async fn handle_ice_candidate(
 /// Start audio capture on call (after E2EE handshake)
 #[tauri::command]
 async fn start_call_audio(state: State<'_, AppState>) -> AppResult<()> {
-    println!("🔊 [AUDIO] Starting call audio...");
+    tracing::info!(component = "audio", "starting call audio");
 
     let engine = state.media.lock().await;
 
@@ -624,7 +1388,10 @@ async fn start_call_audio(state: State<'_, AppState>) -> AppResult<()> {
         .await
         .map_err(|e| format!("Failed to create audio channel: {}", e))?;
 
-    println!("🔊 [AUDIO] ✅ Audio DataChannel created, capture will start when channel opens");
+    tracing::debug!(
+        component = "audio",
+        "audio data channel created, capture starts when it opens"
+    );
 
     Ok(())
 }
@@ -634,18 +1401,32 @@ async fn start_call_audio(state: State<'_, AppState>) -> AppResult<()> {
 async fn toggle_mute(state: State<'_, AppState>) -> AppResult<bool> {
     let engine = state.media.lock().await;
     let muted = engine.toggle_mute();
-    println!("🔊 [AUDIO] Mute toggled: {}", muted);
+    tracing::info!(component = "audio", muted, "mute toggled");
     Ok(muted)
 }
 
-/// Start VU meter — emits `vu-level` events to the frontend
+/// Start VU meter — emits `vu-level` events to the frontend, and
+/// `speaking-changed` (`{ active }`) when the debounced speaking indicator
+/// flips, and `remote-speaking-changed` (`{ peer_id, active }`, `peer_id`
+/// null for the 1:1 peer) for the remote side. `remote-vu-level` carries
+/// the playback output level the same way as `vu-level`.
 #[tauri::command]
 async fn start_vu_meter(app: tauri::AppHandle, state: State<'_, AppState>) -> AppResult<()> {
     let mut rms_rx = None;
+    let mut speaking_rx = None;
+    let mut remote_speaking_rx = None;
+    let mut output_rms_rx = None;
     for _ in 0..20 {
         {
             let engine = state.media.lock().await;
             rms_rx = engine.take_rms_receiver();
+            speaking_rx = engine.take_speaking_receiver();
+            if remote_speaking_rx.is_none() {
+                remote_speaking_rx = engine.take_remote_speaking_receiver();
+            }
+            if output_rms_rx.is_none() {
+                output_rms_rx = engine.take_output_rms_receiver();
+            }
         }
 
         if rms_rx.is_some() {
@@ -658,23 +1439,154 @@ async fn start_vu_meter(app: tauri::AppHandle, state: State<'_, AppState>) -> Ap
     let mut rms_rx =
         rms_rx.ok_or_else(|| "RMS receiver not available (capture not started)".to_string())?;
 
-    // Spawn a background task to forward RMS levels to the frontend
-    tauri::async_runtime::spawn(async move {
-        use std::time::{Duration, Instant};
-        let mut last_emit = Instant::now();
-        let throttle = Duration::from_millis(50); // ~20 FPS for smooth animation
-
-        while let Some(rms) = rms_rx.recv().await {
-            if last_emit.elapsed() >= throttle {
-                let _ = app.emit("vu-level", rms);
-                last_emit = Instant::now();
+    // Transitions only, so each one is forwarded as it comes
+    if let Some(mut speaking_rx) = speaking_rx {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(speaking) = speaking_rx.recv().await {
+                let _ = app.emit("speaking-changed", speaking);
+            }
+        });
+    }
+    if let Some(mut remote_speaking_rx) = remote_speaking_rx {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(speaking) = remote_speaking_rx.recv().await {
+                let _ = app.emit("remote-speaking-changed", speaking);
+            }
+        });
+    }
+
+    // Spawn a background task to forward RMS levels to the frontend. The
+    // watch channel only holds the newest level, so sleeping between emits
+    // throttles without letting samples pile up behind a slow UI.
+    let throttle = std::time::Duration::from_millis(50); // ~20 FPS for smooth animation
+    if let Some(mut output_rms_rx) = output_rms_rx {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while output_rms_rx.changed().await.is_ok() {
+                let rms = *output_rms_rx.borrow_and_update();
+                let _ = app.emit("remote-vu-level", rms);
+                tokio::time::sleep(throttle).await;
             }
+            // Playback went away; leave the meter at rest
+            let _ = app.emit("remote-vu-level", 0.0f32);
+        });
+    }
+    tauri::async_runtime::spawn(async move {
+        while rms_rx.changed().await.is_ok() {
+            let rms = *rms_rx.borrow_and_update();
+            let _ = app.emit("vu-level", rms);
+            tokio::time::sleep(throttle).await;
         }
     });
 
     Ok(())
 }
 
+/// Forward media engine events that no command is waiting on to the
+/// frontend: device failures while a call connects (`audio-device-error`),
+/// devices unplugged mid-call (`audio-device-lost`), peer connection
+/// state changes (`media-state`), which drive call teardown independently
+/// of the signaling socket, and the ICE progress behind them (`ice-state`).
+fn forward_media_events(app: tauri::AppHandle, engine: &mut MediaEngine) {
+    if let Some(mut device_errors) = engine.take_device_error_receiver() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(device_error) = device_errors.recv().await {
+                let _ = app.emit("audio-device-error", AppError::from(device_error));
+            }
+        });
+    }
+
+    if let Some(mut device_events) = engine.take_device_event_receiver() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(event) = device_events.recv().await {
+                match event {
+                    AudioDeviceEvent::Disconnected { is_input } => {
+                        tracing::warn!(component = "audio", is_input, "audio device lost");
+                        let _ = app.emit(
+                            "audio-device-lost",
+                            serde_json::json!({ "is_input": is_input }),
+                        );
+                    }
+                }
+            }
+        });
+    }
+
+    if let Some(mut jitter_events) = engine.take_capture_jitter_receiver() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(jitter) = jitter_events.recv().await {
+                let interval_ms = jitter.interval.as_secs_f64() * 1000.0;
+                let expected_ms = jitter.expected.as_secs_f64() * 1000.0;
+                tracing::debug!(
+                    component = "audio",
+                    call_id = ?observability::call_id(),
+                    interval_ms,
+                    expected_ms,
+                    "late capture callback"
+                );
+                let _ = app.emit(
+                    "capture-jitter",
+                    serde_json::json!({
+                        "interval_ms": interval_ms,
+                        "expected_ms": expected_ms,
+                    }),
+                );
+            }
+        });
+    }
+
+    if let Some(mut ice_states) = engine.take_ice_state_receiver() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(change) = ice_states.recv().await {
+                let (kind, state) = match change {
+                    IceStateChange::Gathering(state) => ("gathering", state.to_string()),
+                    IceStateChange::Connection(state) => ("connection", state.to_string()),
+                };
+                tracing::info!(
+                    component = "call",
+                    call_id = ?observability::call_id(),
+                    kind,
+                    state = %state,
+                    "ice state changed"
+                );
+                let _ = app.emit(
+                    "ice-state",
+                    serde_json::json!({ "kind": kind, "state": state }),
+                );
+            }
+        });
+    }
+
+    if let Some(mut qualities) = engine.take_call_quality_receiver() {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(quality) = qualities.recv().await {
+                tracing::info!(
+                    component = "call",
+                    call_id = ?observability::call_id(),
+                    ?quality,
+                    "call quality changed"
+                );
+                let _ = app.emit("call-quality", quality);
+            }
+        });
+    }
+
+    if let Some(mut connection_states) = engine.take_connection_state_receiver() {
+        tauri::async_runtime::spawn(async move {
+            while let Some(connection_state) = connection_states.recv().await {
+                let _ = app.emit("media-state", connection_state.to_string());
+            }
+        });
+    }
+}
+
 fn main() {
     observability::init_tracing();
 
@@ -696,6 +1608,7 @@ fn main() {
             })?;
             std::fs::create_dir_all(&app_data_dir)?;
             let messaging_db_path = app_data_dir.join("messaging.sqlite");
+            let audio_prefs_path = audio_prefs::prefs_path(&app_data_dir);
             let messaging_service = tauri::async_runtime::block_on(MessagingService::new(
                 messaging_db_path,
             ))
@@ -705,14 +1618,53 @@ fn main() {
                     format!("Failed to initialize messaging storage: {e}"),
                 )
             })?;
+            let cache_limit = message_cache_limit_from_env();
+            if let Some(limit) = cache_limit {
+                let service = messaging_service.clone();
+                tauri::async_runtime::spawn(async move {
+                    match service.prune_messages(limit).await {
+                        Ok(pruned) => tracing::info!(
+                            component = "storage",
+                            pruned,
+                            limit,
+                            "pruned local message cache"
+                        ),
+                        Err(e) => tracing::warn!(
+                            component = "storage",
+                            "failed to prune local message cache: {}",
+                            e
+                        ),
+                    }
+                });
+            }
             app.manage(MessagingState {
                 service: messaging_service,
+                cache_limit,
             });
 
             // Initialize API state for HTTP requests
             let api_state = ApiState::new(config::API_URL.to_string());
             app.manage(api_state);
 
+            // Retries queued messages in the background; pauses while the
+            // signaling socket is down
+            let (outbox_tx, mut outbox_events) = tokio::sync::mpsc::unbounded_channel();
+            let outbox_service = app.state::<MessagingState>().service.clone();
+            tauri::async_runtime::block_on(async {
+                outbox_service.start_outbox_worker(
+                    api::chat::ApiOutboxSender::new(app_handle.clone()),
+                    signaling::online_watch(),
+                    outbox_tx,
+                    backoff::BackoffConfig::outbox_default(),
+                )
+            });
+            let outbox_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                while let Some(event) = outbox_events.recv().await {
+                    let _ = outbox_app.emit("outbox-status", event);
+                }
+            });
+
             // Connect to signaling server (without identifying yet)
             tauri::async_runtime::spawn(async move {
                 let ice_servers = load_ice_servers_from_env();
@@ -721,32 +1673,59 @@ fn main() {
                     .iter()
                     .flat_map(|s| s.urls.iter().cloned())
                     .collect::<Vec<_>>();
-                println!("[ICE] Configured ICE servers: {:?}", configured_urls);
+                tracing::info!(
+                    component = "webrtc",
+                    ice_servers = ?configured_urls,
+                    "configured ICE servers"
+                );
 
                 match signaling::connect(config::SERVER_URL, app_handle.clone()).await {
                     Ok(sender) => {
                         let mut media_engine = MediaEngine::new();
                         media_engine.set_ice_servers(ice_servers.clone());
+                        media_engine.set_playback_raw_mode(playback_raw_monitor_from_env());
+                        forward_media_events(app_handle.clone(), &mut media_engine);
+                        apply_audio_device_prefs(
+                            &mut media_engine,
+                            AudioDevicePrefs::load(&audio_prefs_path),
+                        );
 
                         // Store the sender in app state
                         let state = AppState {
                             media: Arc::new(Mutex::new(media_engine)),
                             ws_sender: sender,
+                            audio_prefs_path,
                         };
                         app_handle.manage(state);
-                        println!("WebSocket connected. Waiting for user login to identify...");
+                        tracing::info!(
+                            component = "ws",
+                            "signaling started, waiting for user login to identify"
+                        );
                     }
                     Err(e) => {
-                        eprintln!("Warning: Could not connect to signaling server: {}", e);
-                        eprintln!("Server URL: {}", config::SERVER_URL);
+                        // Only an unusable URL gets here; network failures
+                        // are retried inside `signaling::connect`
+                        tracing::warn!(
+                            component = "ws",
+                            server_url = config::SERVER_URL,
+                            "could not start signaling: {}",
+                            e
+                        );
 
                         let mut media_engine = MediaEngine::new();
                         media_engine.set_ice_servers(ice_servers.clone());
+                        media_engine.set_playback_raw_mode(playback_raw_monitor_from_env());
+                        forward_media_events(app_handle.clone(), &mut media_engine);
+                        apply_audio_device_prefs(
+                            &mut media_engine,
+                            AudioDevicePrefs::load(&audio_prefs_path),
+                        );
 
                         // Manage with empty sender
                         let state = AppState {
                             media: Arc::new(Mutex::new(media_engine)),
                             ws_sender: Arc::new(Mutex::new(None)),
+                            audio_prefs_path,
                         };
                         app_handle.manage(state);
                     }
@@ -759,13 +1738,16 @@ fn main() {
             // WebRTC/Call commands
             send_offer,
             send_answer,
+            send_voice_state,
             identify_user,
             start_call,
             accept_call,
+            answer_waiting_call,
             complete_call_handshake,
             decline_call,
             end_call,
             cancel_call,
+            prewarm_call_audio,
             reset_call_media,
             // Audio commands
             list_audio_devices,
@@ -776,10 +1758,27 @@ fn main() {
             get_default_output_device,
             get_selected_output_device,
             set_output_device,
+            get_selected_ringtone_device,
+            set_ringtone_device,
+            get_auto_device_follow,
+            set_auto_device_follow,
+            play_ringtone,
+            stop_ringtone,
             get_audio_settings,
+            get_audio_profile,
             update_audio_settings,
+            answer_audio_params_offer,
+            finish_audio_params_offer,
             set_ptt_active,
             set_remote_user_volume,
+            get_peer_volume,
+            set_peer_volume,
+            get_sas_code,
+            run_mic_test,
+            start_recording,
+            stop_recording,
+            play_sound_into_call,
+            stop_sound_into_call,
             toggle_mute,
             start_vu_meter,
             start_call_audio,
@@ -787,6 +1786,14 @@ fn main() {
             handle_audio_offer,
             handle_audio_answer,
             handle_ice_candidate,
+            restart_ice,
+            handle_ice_restart,
+            get_call_stats,
+            get_call_quality,
+            test_ice_servers,
+            get_memory_report,
+            prune_message_cache,
+            search_local_messages,
             // API commands
             api::auth::api_login,
             api::auth::api_register,
@@ -798,6 +1805,8 @@ fn main() {
             api::users::api_update_my_profile,
             api::users::api_fetch_my_settings,
             api::users::api_update_my_settings,
+            api::users::api_fetch_my_mentions,
+            api::users::api_mark_mentions_read,
             api::friends::api_fetch_friends,
             api::friends::api_fetch_pending_requests,
             api::friends::api_fetch_online_friends,
@@ -811,10 +1820,12 @@ fn main() {
             api::chat::api_send_typing,
             api::chat::api_mark_message_delivered,
             api::chat::api_mark_room_read,
+            api::chat::api_fetch_message_statuses,
             api::chat::api_delete_message,
             api::chat::api_delete_all_messages,
             api::chat::api_edit_message,
             api::chat::api_search_messages,
+            api::chat::api_search_all_dms,
             api::chat::api_fetch_message_reactions,
             api::chat::api_add_message_reaction,
             api::chat::api_remove_message_reaction,
@@ -840,12 +1851,17 @@ fn main() {
             api::servers::api_fetch_channel_message_reactions,
             api::servers::api_add_channel_message_reaction,
             api::servers::api_remove_channel_message_reaction,
+            api::servers::api_fetch_channel_pins,
+            api::servers::api_mark_channel_read,
+            api::servers::api_pin_channel_message,
+            api::servers::api_unpin_channel_message,
             api::servers::api_fetch_channel_thread_messages,
             api::servers::api_send_channel_thread_message,
             api::servers::api_send_channel_typing,
             api::servers::api_fetch_voice_channel_presence,
             api::servers::api_join_voice_channel,
             api::servers::api_leave_voice_channel,
+            api::servers::api_nudge_voice_participant,
             updater::app_check_for_updates,
             updater::app_download_and_install_update,
             updater::app_restart_after_update,
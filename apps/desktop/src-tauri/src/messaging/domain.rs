@@ -52,6 +52,8 @@ pub struct PersistedMessage {
     pub created_at: String,
     pub edited_at: Option<String>,
     pub status: MessageStatus,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,4 +68,18 @@ pub struct OutboxMessage {
     pub created_at: String,
     pub attempts: i64,
     pub last_error: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// Metadata of an encrypted file attached to a message; the file itself is
+/// stored elsewhere
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub id: String,
+    pub mime: String,
+    /// Plaintext size in bytes
+    pub size: u64,
+    pub encrypted_url: String,
+    pub nonce: String,
 }
@@ -1,10 +1,61 @@
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
 
-use super::domain::{ConversationKind, MessageStatus, OutboxMessage, PersistedMessage};
+use super::domain::{Attachment, ConversationKind, MessageStatus, OutboxMessage, PersistedMessage};
 use super::error::MessagingError;
-use super::storage::MessagingStorage;
+use super::storage::{MessagingStorage, StoreStats};
+use crate::backoff::{compute_backoff_delay, BackoffConfig};
+
+/// How often the outbox worker looks for messages that are due
+pub const OUTBOX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Outbox items the worker loads per pass
+const OUTBOX_BATCH: i64 = 200;
+
+/// Why sending an outbox item did not go through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendFailure {
+    /// The server could not be reached or we are signed out. Does not count
+    /// as an attempt; the pass stops and the worker tries again later.
+    Offline(String),
+    /// The server failed or asked us to slow down; retried with backoff
+    Retryable(String),
+    /// The server refused the message itself, e.g. a validation error.
+    /// Retrying cannot help, so the message fails right away.
+    Rejected(String),
+}
+
+/// Posts outbox items to the server for the outbox worker
+pub trait OutboxSender: Send + Sync + 'static {
+    /// Returns the message as the server stored it
+    fn send(
+        &self,
+        item: &OutboxMessage,
+    ) -> impl Future<Output = Result<PersistedMessage, SendFailure>> + Send;
+}
+
+/// Status change of a queued message, emitted by the outbox worker
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboxEvent {
+    pub client_id: String,
+    pub target_kind: ConversationKind,
+    pub target_id: String,
+    /// `sending` while retries are scheduled, then `sent` or `failed`
+    pub status: MessageStatus,
+    /// Server id, once sent
+    pub message_id: Option<String>,
+    pub attempts: i64,
+    pub error: Option<String>,
+    /// Delay before the next attempt, while `sending`
+    pub retry_in_ms: Option<u64>,
+}
 
 #[derive(Clone)]
 pub struct MessagingService {
@@ -18,6 +69,7 @@ impl MessagingService {
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_pending_message(
         &self,
         target_kind: ConversationKind,
@@ -27,6 +79,7 @@ impl MessagingService {
         content: String,
         nonce: Option<String>,
         client_id: String,
+        attachments: Vec<Attachment>,
     ) -> Result<PersistedMessage, MessagingError> {
         let now = Utc::now().to_rfc3339();
         let local_id = format!("local-{}", client_id);
@@ -44,6 +97,7 @@ impl MessagingService {
             created_at: now.clone(),
             edited_at: None,
             status: MessageStatus::Sending,
+            attachments: attachments.clone(),
         };
 
         let outbox = OutboxMessage {
@@ -57,6 +111,7 @@ impl MessagingService {
             created_at: now,
             attempts: 0,
             last_error: None,
+            attachments,
         };
 
         self.storage.upsert_message(&message).await?;
@@ -94,6 +149,7 @@ impl MessagingService {
         created_at: String,
         edited_at: Option<String>,
         status: MessageStatus,
+        attachments: Vec<Attachment>,
     ) -> Result<PersistedMessage, MessagingError> {
         let message = PersistedMessage {
             local_id: server_id.clone(),
@@ -108,6 +164,7 @@ impl MessagingService {
             created_at,
             edited_at,
             status,
+            attachments,
         };
 
         self.storage.upsert_message(&message).await?;
@@ -118,6 +175,16 @@ impl MessagingService {
         Ok(message)
     }
 
+    /// Store a queued message as the server accepted it and take it off the
+    /// outbox
+    pub async fn complete_send(&self, message: &PersistedMessage) -> Result<(), MessagingError> {
+        self.storage.upsert_message(message).await?;
+        if let Some(client_id) = &message.client_id {
+            self.storage.remove_outbox(client_id).await?;
+        }
+        Ok(())
+    }
+
     pub async fn cache_remote_messages(
         &self,
         messages: &[PersistedMessage],
@@ -128,7 +195,11 @@ impl MessagingService {
         Ok(())
     }
 
-    pub async fn load_messages(
+    /// Cached history for infinite scroll, with the server's `before`/`limit`
+    /// semantics: the newest `limit` messages older than `before`, oldest
+    /// first. `before` is a message id or an RFC 3339 timestamp. A page
+    /// shorter than `limit` is the start of the cached history.
+    pub async fn fetch_history(
         &self,
         target_kind: ConversationKind,
         target_id: &str,
@@ -140,6 +211,167 @@ impl MessagingService {
             .await
     }
 
+    /// Retry the outbox in the background until the returned task is
+    /// aborted. Each message waits `compute_backoff_delay(backoff, attempts)`
+    /// between attempts and fails for good after `backoff.max_attempts`, or
+    /// at once when the server rejects it. While `online` is false nothing is
+    /// sent; going back online retries everything that is queued right away.
+    pub fn start_outbox_worker<S: OutboxSender>(
+        &self,
+        sender: S,
+        online: watch::Receiver<bool>,
+        events: mpsc::UnboundedSender<OutboxEvent>,
+        backoff: BackoffConfig,
+    ) -> tokio::task::JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            service
+                .run_outbox_worker(sender, online, events, backoff)
+                .await
+        })
+    }
+
+    async fn run_outbox_worker<S: OutboxSender>(
+        self,
+        sender: S,
+        mut online: watch::Receiver<bool>,
+        events: mpsc::UnboundedSender<OutboxEvent>,
+        backoff: BackoffConfig,
+    ) {
+        // When each message may be tried again; not persisted, so a restart
+        // retries everything once
+        let mut due: HashMap<String, Instant> = HashMap::new();
+        loop {
+            while !*online.borrow_and_update() {
+                if online.changed().await.is_err() {
+                    return;
+                }
+                due.clear();
+            }
+
+            if let Err(e) = self
+                .retry_outbox_pass(&sender, &events, backoff, &mut due)
+                .await
+            {
+                tracing::warn!(component = "outbox", "outbox retry pass failed: {}", e);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(OUTBOX_POLL_INTERVAL) => {}
+                changed = online.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                    due.clear();
+                }
+            }
+        }
+    }
+
+    async fn retry_outbox_pass<S: OutboxSender>(
+        &self,
+        sender: &S,
+        events: &mpsc::UnboundedSender<OutboxEvent>,
+        backoff: BackoffConfig,
+        due: &mut HashMap<String, Instant>,
+    ) -> Result<(), MessagingError> {
+        let items = self.storage.list_outbox(OUTBOX_BATCH).await?;
+        due.retain(|client_id, _| items.iter().any(|item| &item.client_id == client_id));
+
+        for item in items {
+            if i64::from(backoff.max_attempts) <= item.attempts {
+                let reason = item
+                    .last_error
+                    .clone()
+                    .unwrap_or_else(|| "Too many attempts".to_string());
+                self.give_up_send(&item, reason, events).await?;
+                continue;
+            }
+            if due
+                .get(&item.client_id)
+                .is_some_and(|at| Instant::now() < *at)
+            {
+                continue;
+            }
+
+            match sender.send(&item).await {
+                Ok(message) => {
+                    due.remove(&item.client_id);
+                    let message_id = message.server_id.clone();
+                    self.storage.upsert_message(&message).await?;
+                    self.storage.remove_outbox(&item.client_id).await?;
+                    let _ = events.send(OutboxEvent {
+                        status: MessageStatus::Sent,
+                        message_id,
+                        attempts: item.attempts + 1,
+                        ..outbox_event(&item)
+                    });
+                }
+                Err(SendFailure::Offline(reason)) => {
+                    tracing::debug!(component = "outbox", "outbox paused: {}", reason);
+                    break;
+                }
+                Err(SendFailure::Rejected(reason)) => {
+                    due.remove(&item.client_id);
+                    self.give_up_send(&item, reason, events).await?;
+                }
+                Err(SendFailure::Retryable(reason)) => {
+                    self.storage
+                        .update_outbox_error(&item.client_id, &reason)
+                        .await?;
+                    let attempts = item.attempts + 1;
+                    if i64::from(backoff.max_attempts) <= attempts {
+                        due.remove(&item.client_id);
+                        self.give_up_send(&item, reason, events).await?;
+                        continue;
+                    }
+                    let delay = compute_backoff_delay(backoff, attempts as u32);
+                    due.insert(item.client_id.clone(), Instant::now() + delay);
+                    self.storage
+                        .update_status_by_server_id(
+                            &format!("local-{}", item.client_id),
+                            MessageStatus::Sending,
+                        )
+                        .await?;
+                    let _ = events.send(OutboxEvent {
+                        status: MessageStatus::Sending,
+                        attempts,
+                        error: Some(reason),
+                        retry_in_ms: Some(delay.as_millis() as u64),
+                        ..outbox_event(&item)
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop retrying a message: it leaves the outbox and stays `failed`
+    async fn give_up_send(
+        &self,
+        item: &OutboxMessage,
+        reason: String,
+        events: &mpsc::UnboundedSender<OutboxEvent>,
+    ) -> Result<(), MessagingError> {
+        tracing::warn!(
+            component = "outbox",
+            client_id = %item.client_id,
+            attempts = item.attempts,
+            "giving up on queued message: {}",
+            reason
+        );
+        self.storage
+            .update_status_by_server_id(&format!("local-{}", item.client_id), MessageStatus::Failed)
+            .await?;
+        self.storage.remove_outbox(&item.client_id).await?;
+        let _ = events.send(OutboxEvent {
+            status: MessageStatus::Failed,
+            error: Some(reason),
+            ..outbox_event(item)
+        });
+        Ok(())
+    }
+
     pub async fn list_outbox(&self, limit: i64) -> Result<Vec<OutboxMessage>, MessagingError> {
         self.storage.list_outbox(limit).await
     }
@@ -148,6 +380,33 @@ impl MessagingService {
         self.storage.remove_outbox(client_id).await
     }
 
+    pub async fn store_stats(&self) -> Result<StoreStats, MessagingError> {
+        self.storage.stats().await
+    }
+
+    pub async fn prune_messages(&self, keep_per_conversation: i64) -> Result<u64, MessagingError> {
+        self.storage.prune_messages(keep_per_conversation).await
+    }
+
+    /// Search cached messages, best match first; works offline. See
+    /// [`MessagingStorage::search_messages`] for how `query` is matched.
+    pub async fn search_local(
+        &self,
+        query: &str,
+        target_kind: Option<ConversationKind>,
+        target_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<PersistedMessage>, MessagingError> {
+        self.storage
+            .search_messages(query, target_kind, target_id, limit)
+            .await
+    }
+
+    /// Drop a message deleted on the server from the cache
+    pub async fn remove_message(&self, message_id: &str) -> Result<bool, MessagingError> {
+        self.storage.delete_message(message_id).await
+    }
+
     pub async fn set_status_by_server_id(
         &self,
         message_id: &str,
@@ -159,6 +418,19 @@ impl MessagingService {
     }
 }
 
+fn outbox_event(item: &OutboxMessage) -> OutboxEvent {
+    OutboxEvent {
+        client_id: item.client_id.clone(),
+        target_kind: item.target_kind,
+        target_id: item.target_id.clone(),
+        status: MessageStatus::Sending,
+        message_id: None,
+        attempts: item.attempts,
+        error: None,
+        retry_in_ms: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -184,6 +456,7 @@ mod tests {
                 "hello".to_string(),
                 None,
                 "c3".to_string(),
+                Vec::new(),
             )
             .await
             .expect("create pending");
@@ -203,6 +476,7 @@ mod tests {
                 "2026-02-12T00:10:00Z".to_string(),
                 None,
                 MessageStatus::Sent,
+                Vec::new(),
             )
             .await
             .expect("mark send success");
@@ -211,7 +485,7 @@ mod tests {
         assert!(outbox.is_empty());
 
         let loaded = service
-            .load_messages(ConversationKind::Dm, "room-1", None, 50)
+            .fetch_history(ConversationKind::Dm, "room-1", None, 50)
             .await
             .expect("load messages");
         assert_eq!(loaded.len(), 1);
@@ -237,6 +511,7 @@ mod tests {
                 "retry me".to_string(),
                 None,
                 "c4".to_string(),
+                Vec::new(),
             )
             .await
             .expect("create pending");
@@ -252,7 +527,7 @@ mod tests {
         assert_eq!(outbox[0].last_error.as_deref(), Some("network down"));
 
         let loaded = service
-            .load_messages(ConversationKind::Channel, "ch-1", None, 50)
+            .fetch_history(ConversationKind::Channel, "ch-1", None, 50)
             .await
             .expect("load messages");
         assert_eq!(loaded.len(), 1);
@@ -261,6 +536,212 @@ mod tests {
         let _ = std::fs::remove_file(db_path);
     }
 
+    /// Answers each client id with its scripted results in turn, and counts calls
+    #[derive(Default)]
+    struct ScriptedSender {
+        results: std::sync::Mutex<HashMap<String, Vec<Result<(), SendFailure>>>>,
+        calls: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl ScriptedSender {
+        fn script(self, client_id: &str, results: Vec<Result<(), SendFailure>>) -> Self {
+            self.results
+                .lock()
+                .unwrap()
+                .insert(client_id.to_string(), results);
+            self
+        }
+
+        fn calls(&self, client_id: &str) -> usize {
+            let calls = self.calls.lock().unwrap();
+            calls.iter().filter(|id| *id == client_id).count()
+        }
+    }
+
+    impl OutboxSender for ScriptedSender {
+        async fn send(&self, item: &OutboxMessage) -> Result<PersistedMessage, SendFailure> {
+            self.calls.lock().unwrap().push(item.client_id.clone());
+            let result = self
+                .results
+                .lock()
+                .unwrap()
+                .get_mut(&item.client_id)
+                .filter(|results| !results.is_empty())
+                .map(|results| results.remove(0))
+                .unwrap_or(Ok(()));
+            result.map(|()| PersistedMessage {
+                local_id: format!("srv-{}", item.client_id),
+                server_id: Some(format!("srv-{}", item.client_id)),
+                client_id: Some(item.client_id.clone()),
+                sender_id: item.sender_id.clone(),
+                sender_username: None,
+                target_kind: item.target_kind,
+                target_id: item.target_id.clone(),
+                content: item.content.clone(),
+                nonce: item.nonce.clone(),
+                created_at: item.created_at.clone(),
+                edited_at: None,
+                status: MessageStatus::Sent,
+                attachments: item.attachments.clone(),
+            })
+        }
+    }
+
+    async fn queue(service: &MessagingService, client_id: &str) {
+        service
+            .create_pending_message(
+                ConversationKind::Dm,
+                "room-1",
+                None,
+                Some("u1".to_string()),
+                "queued".to_string(),
+                None,
+                client_id.to_string(),
+                Vec::new(),
+            )
+            .await
+            .expect("create pending");
+    }
+
+    fn drain_events(
+        events: &mut mpsc::UnboundedReceiver<OutboxEvent>,
+    ) -> Vec<(String, MessageStatus)> {
+        std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| (event.client_id, event.status))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn outbox_worker_gives_up_after_max_attempts_and_never_retries_rejections() {
+        let db_path = temp_db_path("messaging-service-outbox-give-up");
+        let service = MessagingService::new(db_path.clone())
+            .await
+            .expect("service init");
+        queue(&service, "flaky").await;
+        queue(&service, "invalid").await;
+
+        let retry = || Err(SendFailure::Retryable("503".to_string()));
+        let sender = ScriptedSender::default()
+            .script("flaky", vec![retry(), retry(), retry()])
+            .script(
+                "invalid",
+                vec![Err(SendFailure::Rejected("too long".to_string()))],
+            );
+        let backoff = BackoffConfig {
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            jitter_ratio: 0.0,
+            max_attempts: 2,
+        };
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        let mut due = HashMap::new();
+
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("first pass");
+        assert_eq!(
+            drain_events(&mut events),
+            vec![
+                ("flaky".to_string(), MessageStatus::Sending),
+                ("invalid".to_string(), MessageStatus::Failed),
+            ]
+        );
+
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("second pass");
+        assert_eq!(
+            drain_events(&mut events),
+            vec![("flaky".to_string(), MessageStatus::Failed)]
+        );
+        assert_eq!((sender.calls("flaky"), sender.calls("invalid")), (2, 1));
+
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("third pass");
+        assert!(drain_events(&mut events).is_empty());
+        assert!(service
+            .list_outbox(10)
+            .await
+            .expect("list outbox")
+            .is_empty());
+        let loaded = service
+            .fetch_history(ConversationKind::Dm, "room-1", None, 50)
+            .await
+            .expect("load messages");
+        assert!(loaded.iter().all(|m| m.status == MessageStatus::Failed));
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    #[tokio::test]
+    async fn outbox_worker_waits_out_backoff_and_pauses_offline() {
+        let db_path = temp_db_path("messaging-service-outbox-backoff");
+        let service = MessagingService::new(db_path.clone())
+            .await
+            .expect("service init");
+        queue(&service, "c6").await;
+
+        let sender = ScriptedSender::default().script(
+            "c6",
+            vec![
+                Err(SendFailure::Retryable("502".to_string())),
+                Err(SendFailure::Offline("connection refused".to_string())),
+            ],
+        );
+        let backoff = BackoffConfig {
+            base_delay: Duration::from_secs(60),
+            ..BackoffConfig::outbox_default()
+        };
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        let mut due = HashMap::new();
+
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("first pass");
+        // Not due yet
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("second pass");
+        assert_eq!(sender.calls("c6"), 1);
+
+        // Back online: everything is due again. Offline does not count.
+        due.clear();
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("offline pass");
+        assert_eq!(
+            service.list_outbox(10).await.expect("list outbox")[0].attempts,
+            1
+        );
+
+        service
+            .retry_outbox_pass(&sender, &events_tx, backoff, &mut due)
+            .await
+            .expect("sending pass");
+        assert_eq!(sender.calls("c6"), 3);
+        assert_eq!(
+            drain_events(&mut events),
+            vec![
+                ("c6".to_string(), MessageStatus::Sending),
+                ("c6".to_string(), MessageStatus::Sent),
+            ]
+        );
+        assert!(service
+            .list_outbox(10)
+            .await
+            .expect("list outbox")
+            .is_empty());
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
     #[tokio::test]
     async fn set_status_updates_cached_message_by_server_id() {
         let db_path = temp_db_path("messaging-service-status");
@@ -281,6 +762,7 @@ mod tests {
                 "2026-02-12T00:20:00Z".to_string(),
                 None,
                 MessageStatus::Sent,
+                Vec::new(),
             )
             .await
             .expect("insert sent message");
@@ -291,7 +773,7 @@ mod tests {
             .expect("set read status");
 
         let loaded = service
-            .load_messages(ConversationKind::Dm, "room-1", None, 50)
+            .fetch_history(ConversationKind::Dm, "room-1", None, 50)
             .await
             .expect("load messages");
         assert_eq!(loaded.len(), 1);
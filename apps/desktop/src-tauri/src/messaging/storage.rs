@@ -1,11 +1,12 @@
 use std::path::PathBuf;
 
+use serde::Serialize;
 use sqlx::{
     sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
     SqlitePool,
 };
 
-use super::domain::{ConversationKind, MessageStatus, OutboxMessage, PersistedMessage};
+use super::domain::{Attachment, ConversationKind, MessageStatus, OutboxMessage, PersistedMessage};
 use super::error::MessagingError;
 
 #[derive(Debug, sqlx::FromRow)]
@@ -22,6 +23,7 @@ struct MessageRow {
     created_at: String,
     edited_at: Option<String>,
     status: String,
+    attachments: String,
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -36,6 +38,17 @@ struct OutboxRow {
     created_at: String,
     attempts: i64,
     last_error: Option<String>,
+    attachments: String,
+}
+
+/// Row counts and database size of the local store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct StoreStats {
+    pub messages: i64,
+    pub outbox: i64,
+    /// Main database file size; pages freed by pruning are reused, not
+    /// returned to the OS
+    pub db_bytes: i64,
 }
 
 #[derive(Clone)]
@@ -81,15 +94,28 @@ impl MessagingStorage {
                 created_at TEXT NOT NULL,
                 edited_at TEXT,
                 status TEXT NOT NULL,
-                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                attachments TEXT NOT NULL DEFAULT '[]'
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
+        self.add_column_if_missing(
+            "local_messages",
+            "attachments",
+            "TEXT NOT NULL DEFAULT '[]'",
+        )
+        .await?;
+
+        // History pages order by local_id within the same created_at, which
+        // the original (target, created_at) index did not cover
+        sqlx::query("DROP INDEX IF EXISTS idx_local_messages_target_time")
+            .execute(&self.pool)
+            .await?;
 
         sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_local_messages_target_time ON local_messages(target_kind, target_id, created_at DESC)",
+            "CREATE INDEX IF NOT EXISTS idx_local_messages_history ON local_messages(target_kind, target_id, created_at DESC, local_id DESC)",
         )
         .execute(&self.pool)
         .await?;
@@ -106,17 +132,121 @@ impl MessagingStorage {
                 nonce TEXT,
                 created_at TEXT NOT NULL,
                 attempts INTEGER NOT NULL DEFAULT 0,
-                last_error TEXT
+                last_error TEXT,
+                attachments TEXT NOT NULL DEFAULT '[]'
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
+        self.add_column_if_missing("outbox", "attachments", "TEXT NOT NULL DEFAULT '[]'")
+            .await?;
 
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_outbox_created_at ON outbox(created_at ASC)")
             .execute(&self.pool)
             .await?;
 
+        self.init_search_index().await?;
+
+        Ok(())
+    }
+
+    /// Columns added after a table was first created; `CREATE TABLE IF NOT
+    /// EXISTS` leaves older databases without them
+    async fn add_column_if_missing(
+        &self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<(), MessagingError> {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM pragma_table_info(?) WHERE name = ?)")
+                .bind(table)
+                .bind(column)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Full-text index over `local_messages.content`, keyed by its rowid and
+    /// kept in sync by triggers, so every write path (upserts, pruning)
+    /// updates it. Encrypted messages (with a nonce) hold ciphertext and are
+    /// left out. A database from before the index existed is indexed in
+    /// full the first time it is opened.
+    async fn init_search_index(&self) -> Result<(), MessagingError> {
+        let mut tx = self.pool.begin().await?;
+
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'local_messages_fts')",
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS local_messages_fts USING fts5(content, tokenize = 'unicode61 remove_diacritics 2')",
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS local_messages_fts_insert
+            AFTER INSERT ON local_messages WHEN new.nonce IS NULL
+            BEGIN
+                INSERT INTO local_messages_fts (rowid, content) VALUES (new.rowid, new.content);
+            END
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS local_messages_fts_update
+            AFTER UPDATE OF content, nonce ON local_messages
+            BEGIN
+                DELETE FROM local_messages_fts WHERE rowid = old.rowid;
+                INSERT INTO local_messages_fts (rowid, content)
+                SELECT new.rowid, new.content WHERE new.nonce IS NULL;
+            END
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TRIGGER IF NOT EXISTS local_messages_fts_delete
+            AFTER DELETE ON local_messages
+            BEGIN
+                DELETE FROM local_messages_fts WHERE rowid = old.rowid;
+            END
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if !exists {
+            sqlx::query(
+                r#"
+                INSERT INTO local_messages_fts (rowid, content)
+                SELECT rowid, content FROM local_messages WHERE nonce IS NULL
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
@@ -126,9 +256,9 @@ impl MessagingStorage {
                 r#"
                 INSERT INTO local_messages (
                     local_id, server_id, client_id, sender_id, sender_username,
-                    target_kind, target_id, content, nonce, created_at, edited_at, status
+                    target_kind, target_id, content, nonce, created_at, edited_at, status, attachments
                 )
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                 ON CONFLICT(client_id) DO UPDATE SET
                     local_id = excluded.local_id,
                     server_id = excluded.server_id,
@@ -141,6 +271,7 @@ impl MessagingStorage {
                     created_at = excluded.created_at,
                     edited_at = excluded.edited_at,
                     status = excluded.status,
+                    attachments = excluded.attachments,
                     updated_at = datetime('now')
                 "#,
             )
@@ -156,6 +287,7 @@ impl MessagingStorage {
             .bind(&msg.created_at)
             .bind(&msg.edited_at)
             .bind(msg.status.as_str())
+            .bind(attachments_json(&msg.attachments))
             .execute(&self.pool)
             .await?;
             return Ok(());
@@ -166,9 +298,9 @@ impl MessagingStorage {
                 r#"
                 INSERT INTO local_messages (
                     local_id, server_id, client_id, sender_id, sender_username,
-                    target_kind, target_id, content, nonce, created_at, edited_at, status
+                    target_kind, target_id, content, nonce, created_at, edited_at, status, attachments
                 )
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                 ON CONFLICT(server_id) DO UPDATE SET
                     local_id = excluded.local_id,
                     sender_id = excluded.sender_id,
@@ -180,6 +312,7 @@ impl MessagingStorage {
                     created_at = excluded.created_at,
                     edited_at = excluded.edited_at,
                     status = excluded.status,
+                    attachments = excluded.attachments,
                     updated_at = datetime('now')
                 "#,
             )
@@ -195,6 +328,7 @@ impl MessagingStorage {
             .bind(&msg.created_at)
             .bind(&msg.edited_at)
             .bind(msg.status.as_str())
+            .bind(attachments_json(&msg.attachments))
             .execute(&self.pool)
             .await?;
             return Ok(());
@@ -204,9 +338,9 @@ impl MessagingStorage {
             r#"
             INSERT INTO local_messages (
                 local_id, server_id, client_id, sender_id, sender_username,
-                target_kind, target_id, content, nonce, created_at, edited_at, status
+                target_kind, target_id, content, nonce, created_at, edited_at, status, attachments
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(local_id) DO UPDATE SET
                 sender_id = excluded.sender_id,
                 sender_username = excluded.sender_username,
@@ -217,6 +351,7 @@ impl MessagingStorage {
                 created_at = excluded.created_at,
                 edited_at = excluded.edited_at,
                 status = excluded.status,
+                attachments = excluded.attachments,
                 updated_at = datetime('now')
             "#,
         )
@@ -232,57 +367,69 @@ impl MessagingStorage {
         .bind(&msg.created_at)
         .bind(&msg.edited_at)
         .bind(msg.status.as_str())
+        .bind(attachments_json(&msg.attachments))
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// A page of history in chronological order: the newest `limit`
+    /// messages, or the newest older than `before`. Messages sent in the same
+    /// instant are ordered by `local_id`, so pages neither skip nor repeat
+    /// them. `before` is a cached message's server or local id, or an
+    /// RFC 3339 timestamp; an id that is not cached is an error rather than
+    /// an empty page, which would read as the start of history.
     pub async fn load_messages(
         &self,
         target_kind: ConversationKind,
         target_id: &str,
-        before_local_id: Option<&str>,
+        before: Option<&str>,
         limit: i64,
     ) -> Result<Vec<PersistedMessage>, MessagingError> {
-        let rows = if let Some(before) = before_local_id {
-            sqlx::query_as::<_, MessageRow>(
-                r#"
-                SELECT local_id, server_id, client_id, sender_id, sender_username, target_kind, target_id,
-                       content, nonce, created_at, edited_at, status
-                FROM local_messages
-                WHERE target_kind = ?
-                  AND target_id = ?
-                  AND created_at < (SELECT created_at FROM local_messages WHERE local_id = ? LIMIT 1)
-                ORDER BY created_at DESC
-                LIMIT ?
-                "#,
-            )
-            .bind(target_kind.as_str())
-            .bind(target_id)
-            .bind(before)
-            .bind(limit)
-            .fetch_all(&self.pool)
-            .await?
-        } else {
-            sqlx::query_as::<_, MessageRow>(
-                r#"
-                SELECT local_id, server_id, client_id, sender_id, sender_username, target_kind, target_id,
-                       content, nonce, created_at, edited_at, status
-                FROM local_messages
-                WHERE target_kind = ?
-                  AND target_id = ?
-                ORDER BY created_at DESC
-                LIMIT ?
-                "#,
-            )
-            .bind(target_kind.as_str())
-            .bind(target_id)
-            .bind(limit)
-            .fetch_all(&self.pool)
-            .await?
+        if limit < 1 {
+            return Err(MessagingError::InvalidOperation(format!(
+                "page size must be at least 1, got {}",
+                limit
+            )));
+        }
+
+        let (before_time, before_id) = match before {
+            Some(cursor) => {
+                let (time, id) = self
+                    .resolve_history_cursor(target_kind, target_id, cursor)
+                    .await?;
+                (Some(time), id)
+            }
+            None => (None, None),
         };
 
+        let rows = sqlx::query_as::<_, MessageRow>(
+            r#"
+            SELECT local_id, server_id, client_id, sender_id, sender_username, target_kind, target_id,
+                   content, nonce, created_at, edited_at, status, attachments
+            FROM local_messages
+            WHERE target_kind = ?
+              AND target_id = ?
+              AND (
+                  ? IS NULL
+                  OR created_at < ?
+                  OR (created_at = ? AND local_id < ?)
+              )
+            ORDER BY created_at DESC, local_id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(target_kind.as_str())
+        .bind(target_id)
+        .bind(&before_time)
+        .bind(&before_time)
+        .bind(&before_time)
+        .bind(&before_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
         let mut messages = rows
             .into_iter()
             .map(Self::row_to_message)
@@ -291,14 +438,101 @@ impl MessagingStorage {
         Ok(messages)
     }
 
+    /// `(created_at, local_id)` of the message `cursor` names, or just the
+    /// time when it is a timestamp
+    async fn resolve_history_cursor(
+        &self,
+        target_kind: ConversationKind,
+        target_id: &str,
+        cursor: &str,
+    ) -> Result<(String, Option<String>), MessagingError> {
+        let position = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT created_at, local_id
+            FROM local_messages
+            WHERE target_kind = ?
+              AND target_id = ?
+              AND (server_id = ? OR local_id = ?)
+            LIMIT 1
+            "#,
+        )
+        .bind(target_kind.as_str())
+        .bind(target_id)
+        .bind(cursor)
+        .bind(cursor)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match position {
+            Some((created_at, local_id)) => Ok((created_at, Some(local_id))),
+            None if chrono::DateTime::parse_from_rfc3339(cursor).is_ok() => {
+                Ok((cursor.to_string(), None))
+            }
+            None => Err(MessagingError::InvalidOperation(format!(
+                "history cursor {} is neither a cached message nor a timestamp",
+                cursor
+            ))),
+        }
+    }
+
+    /// Messages matching every word of `query`, best match first. Each word
+    /// also matches as a prefix; FTS5 operators in `query` are taken
+    /// literally. `target_kind` and `target_id` narrow the search when given.
+    pub async fn search_messages(
+        &self,
+        query: &str,
+        target_kind: Option<ConversationKind>,
+        target_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<PersistedMessage>, MessagingError> {
+        let Some(pattern) = fts_pattern(query) else {
+            return Ok(Vec::new());
+        };
+
+        let rows = sqlx::query_as::<_, MessageRow>(
+            r#"
+            SELECT m.local_id, m.server_id, m.client_id, m.sender_id, m.sender_username, m.target_kind,
+                   m.target_id, m.content, m.nonce, m.created_at, m.edited_at, m.status, m.attachments
+            FROM local_messages_fts
+            JOIN local_messages m ON m.rowid = local_messages_fts.rowid
+            WHERE local_messages_fts MATCH ?
+              AND (? IS NULL OR m.target_kind = ?)
+              AND (? IS NULL OR m.target_id = ?)
+            ORDER BY bm25(local_messages_fts), m.created_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(&pattern)
+        .bind(target_kind.map(ConversationKind::as_str))
+        .bind(target_kind.map(ConversationKind::as_str))
+        .bind(target_id)
+        .bind(target_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Self::row_to_message).collect())
+    }
+
+    /// Delete a cached message by server or local id. Returns whether one
+    /// was there.
+    pub async fn delete_message(&self, message_id: &str) -> Result<bool, MessagingError> {
+        let result = sqlx::query("DELETE FROM local_messages WHERE server_id = ? OR local_id = ?")
+            .bind(message_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn enqueue_outbox(&self, item: &OutboxMessage) -> Result<(), MessagingError> {
         sqlx::query(
             r#"
             INSERT INTO outbox (
                 client_id, target_kind, target_id, server_scope_id, sender_id,
-                content, nonce, created_at, attempts, last_error
+                content, nonce, created_at, attempts, last_error, attachments
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(client_id) DO UPDATE SET
                 target_kind = excluded.target_kind,
                 target_id = excluded.target_id,
@@ -308,7 +542,8 @@ impl MessagingStorage {
                 nonce = excluded.nonce,
                 created_at = excluded.created_at,
                 attempts = excluded.attempts,
-                last_error = excluded.last_error
+                last_error = excluded.last_error,
+                attachments = excluded.attachments
             "#,
         )
         .bind(&item.client_id)
@@ -321,6 +556,7 @@ impl MessagingStorage {
         .bind(&item.created_at)
         .bind(item.attempts)
         .bind(&item.last_error)
+        .bind(attachments_json(&item.attachments))
         .execute(&self.pool)
         .await?;
 
@@ -354,7 +590,7 @@ impl MessagingStorage {
         let rows = sqlx::query_as::<_, OutboxRow>(
             r#"
             SELECT client_id, target_kind, target_id, server_scope_id, sender_id,
-                   content, nonce, created_at, attempts, last_error
+                   content, nonce, created_at, attempts, last_error, attachments
             FROM outbox
             ORDER BY created_at ASC
             LIMIT ?
@@ -383,6 +619,55 @@ impl MessagingStorage {
         Ok(())
     }
 
+    pub async fn stats(&self) -> Result<StoreStats, MessagingError> {
+        let stats = sqlx::query_as::<_, StoreStats>(
+            r#"
+            SELECT (SELECT COUNT(*) FROM local_messages) AS messages,
+                   (SELECT COUNT(*) FROM outbox) AS outbox,
+                   (SELECT page_count FROM pragma_page_count())
+                       * (SELECT page_size FROM pragma_page_size()) AS db_bytes
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(stats)
+    }
+
+    /// Delete cached messages beyond the newest `keep_per_conversation` of
+    /// each conversation. Unsent messages (`sending`/`failed`, or still in the
+    /// outbox) are kept whatever their age. Returns how many rows went.
+    pub async fn prune_messages(&self, keep_per_conversation: i64) -> Result<u64, MessagingError> {
+        if keep_per_conversation < 1 {
+            return Err(MessagingError::InvalidOperation(format!(
+                "cache limit must be at least 1, got {}",
+                keep_per_conversation
+            )));
+        }
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM local_messages
+            WHERE local_id IN (
+                SELECT local_id FROM (
+                    SELECT local_id, client_id, status,
+                           ROW_NUMBER() OVER (
+                               PARTITION BY target_kind, target_id
+                               ORDER BY created_at DESC
+                           ) AS position
+                    FROM local_messages
+                )
+                WHERE position > ?
+                  AND status NOT IN ('sending', 'failed')
+                  AND (client_id IS NULL OR client_id NOT IN (SELECT client_id FROM outbox))
+            )
+            "#,
+        )
+        .bind(keep_per_conversation)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
     fn row_to_message(row: MessageRow) -> PersistedMessage {
         PersistedMessage {
             local_id: row.local_id,
@@ -406,6 +691,7 @@ impl MessagingStorage {
                 "failed" => MessageStatus::Failed,
                 _ => MessageStatus::Sent,
             },
+            attachments: parse_attachments(&row.attachments),
         }
     }
 
@@ -424,10 +710,31 @@ impl MessagingStorage {
             created_at: row.created_at,
             attempts: row.attempts,
             last_error: row.last_error,
+            attachments: parse_attachments(&row.attachments),
         }
     }
 }
 
+/// Attachments are stored as a JSON array
+fn attachments_json(attachments: &[Attachment]) -> String {
+    serde_json::to_string(attachments).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn parse_attachments(json: &str) -> Vec<Attachment> {
+    serde_json::from_str(json).unwrap_or_default()
+}
+
+/// Turn free text into an FTS5 query: each word becomes a quoted prefix
+/// term, so punctuation and operators like `OR`, `NEAR` or `-` are plain
+/// text. `None` when there is nothing to search for.
+fn fts_pattern(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -457,6 +764,7 @@ mod tests {
             created_at: "2026-02-12T00:00:00Z".to_string(),
             edited_at: None,
             status: MessageStatus::Sending,
+            attachments: Vec::new(),
         };
 
         let second = PersistedMessage {
@@ -472,6 +780,7 @@ mod tests {
             created_at: "2026-02-12T00:00:01Z".to_string(),
             edited_at: None,
             status: MessageStatus::Sent,
+            attachments: Vec::new(),
         };
 
         storage
@@ -512,6 +821,7 @@ mod tests {
             created_at: "2026-02-12T00:01:00Z".to_string(),
             attempts: 0,
             last_error: None,
+            attachments: Vec::new(),
         };
 
         storage
@@ -558,6 +868,7 @@ mod tests {
             created_at: "2026-02-12T00:00:00Z".to_string(),
             edited_at: None,
             status: MessageStatus::Sent,
+            attachments: Vec::new(),
         };
 
         let m2 = PersistedMessage {
@@ -573,6 +884,7 @@ mod tests {
             created_at: "2026-02-12T00:00:01Z".to_string(),
             edited_at: None,
             status: MessageStatus::Sent,
+            attachments: Vec::new(),
         };
 
         let m3 = PersistedMessage {
@@ -588,6 +900,7 @@ mod tests {
             created_at: "2026-02-12T00:00:02Z".to_string(),
             edited_at: None,
             status: MessageStatus::Sent,
+            attachments: Vec::new(),
         };
 
         storage.upsert_message(&m1).await.expect("insert m1");
@@ -611,4 +924,353 @@ mod tests {
 
         let _ = std::fs::remove_file(db_path);
     }
+
+    #[tokio::test]
+    async fn history_pages_through_ties_to_the_start() {
+        let db_path = temp_db_path("messaging-storage-history");
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage init");
+
+        // Five messages, the middle three sent in the same second
+        for (id, second) in [("a", 0), ("b", 1), ("c", 1), ("d", 1), ("e", 2)] {
+            storage
+                .upsert_message(&PersistedMessage {
+                    local_id: id.to_string(),
+                    server_id: Some(format!("srv-{}", id)),
+                    client_id: None,
+                    sender_id: Some("u1".to_string()),
+                    sender_username: None,
+                    target_kind: ConversationKind::Dm,
+                    target_id: "room-1".to_string(),
+                    content: id.to_string(),
+                    nonce: None,
+                    created_at: format!("2026-02-12T00:00:0{}Z", second),
+                    edited_at: None,
+                    status: MessageStatus::Sent,
+                    attachments: Vec::new(),
+                })
+                .await
+                .expect("insert");
+        }
+
+        let page = |before: Option<&'static str>| {
+            let storage = storage.clone();
+            async move {
+                storage
+                    .load_messages(ConversationKind::Dm, "room-1", before, 2)
+                    .await
+                    .map(|page| page.into_iter().map(|m| m.local_id).collect::<Vec<_>>())
+            }
+        };
+
+        assert_eq!(page(None).await.expect("newest"), vec!["d", "e"]);
+        // Server ids and local ids both work as cursors
+        assert_eq!(page(Some("srv-d")).await.expect("older"), vec!["b", "c"]);
+        assert_eq!(page(Some("b")).await.expect("oldest"), vec!["a"]);
+        assert!(page(Some("a")).await.expect("start").is_empty());
+
+        // A timestamp cursor takes everything strictly before it
+        assert_eq!(
+            page(Some("2026-02-12T00:00:01Z")).await.expect("by time"),
+            vec!["a"]
+        );
+        assert!(page(Some("srv-unknown")).await.is_err());
+        assert!(storage
+            .load_messages(ConversationKind::Dm, "room-1", None, 0)
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    #[tokio::test]
+    async fn prune_keeps_newest_per_conversation_and_unsent_messages() {
+        let db_path = temp_db_path("messaging-storage-prune");
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage init");
+
+        let message =
+            |id: &str, target: &str, minute: u32, status: MessageStatus| PersistedMessage {
+                local_id: id.to_string(),
+                server_id: (status != MessageStatus::Sending).then(|| id.to_string()),
+                client_id: Some(format!("client-{}", id)),
+                sender_id: Some("u1".to_string()),
+                sender_username: None,
+                target_kind: ConversationKind::Dm,
+                target_id: target.to_string(),
+                content: id.to_string(),
+                nonce: None,
+                created_at: format!("2026-02-12T00:{:02}:00Z", minute),
+                edited_at: None,
+                status,
+                attachments: Vec::new(),
+            };
+
+        // room-1: an old pending message, an old queued one and four sent
+        storage
+            .upsert_message(&message("pending", "room-1", 0, MessageStatus::Sending))
+            .await
+            .expect("insert pending");
+        storage
+            .upsert_message(&message("queued", "room-1", 1, MessageStatus::Sent))
+            .await
+            .expect("insert queued");
+        storage
+            .enqueue_outbox(&OutboxMessage {
+                client_id: "client-queued".to_string(),
+                target_kind: ConversationKind::Dm,
+                target_id: "room-1".to_string(),
+                server_scope_id: None,
+                sender_id: Some("u1".to_string()),
+                content: "queued".to_string(),
+                nonce: None,
+                created_at: "2026-02-12T00:01:00Z".to_string(),
+                attempts: 0,
+                last_error: None,
+                attachments: Vec::new(),
+            })
+            .await
+            .expect("enqueue outbox");
+        for minute in 2..6 {
+            let id = format!("r1-{}", minute);
+            storage
+                .upsert_message(&message(&id, "room-1", minute, MessageStatus::Read))
+                .await
+                .expect("insert room-1");
+        }
+        // room-2 is already under the limit
+        storage
+            .upsert_message(&message("r2-0", "room-2", 0, MessageStatus::Sent))
+            .await
+            .expect("insert room-2");
+
+        let before = storage.stats().await.expect("stats");
+        assert_eq!((before.messages, before.outbox), (7, 1));
+        assert!(before.db_bytes > 0);
+
+        let pruned = storage.prune_messages(2).await.expect("prune");
+        assert_eq!(pruned, 2);
+
+        let kept: Vec<String> = storage
+            .load_messages(ConversationKind::Dm, "room-1", None, 50)
+            .await
+            .expect("load room-1")
+            .into_iter()
+            .map(|m| m.local_id)
+            .collect();
+        assert_eq!(kept, vec!["pending", "queued", "r1-4", "r1-5"]);
+        assert_eq!(storage.stats().await.expect("stats").messages, 5);
+
+        assert!(storage.prune_messages(0).await.is_err());
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    fn search_fixture(
+        id: &str,
+        target: &str,
+        content: &str,
+        nonce: Option<&str>,
+    ) -> PersistedMessage {
+        PersistedMessage {
+            local_id: id.to_string(),
+            server_id: Some(id.to_string()),
+            client_id: None,
+            sender_id: Some("u1".to_string()),
+            sender_username: None,
+            target_kind: ConversationKind::Dm,
+            target_id: target.to_string(),
+            content: content.to_string(),
+            nonce: nonce.map(str::to_string),
+            created_at: "2026-02-12T00:00:00Z".to_string(),
+            edited_at: None,
+            status: MessageStatus::Sent,
+            attachments: Vec::new(),
+        }
+    }
+
+    async fn search_ids(
+        storage: &MessagingStorage,
+        query: &str,
+        target_id: Option<&str>,
+    ) -> Vec<String> {
+        storage
+            .search_messages(query, Some(ConversationKind::Dm), target_id, 50)
+            .await
+            .expect("search")
+            .into_iter()
+            .map(|m| m.local_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn search_follows_edits_and_deletes_and_skips_encrypted() {
+        let db_path = temp_db_path("messaging-storage-search");
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage init");
+
+        for message in [
+            search_fixture("m1", "room-1", "Lunch at the café tomorrow?", None),
+            search_fixture("m2", "room-1", "lunch lunch lunch", None),
+            search_fixture("m3", "room-2", "no lunch today", None),
+            search_fixture("m4", "room-1", "bHVuY2g=", Some("nonce")),
+        ] {
+            storage.upsert_message(&message).await.expect("insert");
+        }
+
+        // Prefixes, accents and case are ignored; more hits rank first
+        let hits = search_ids(&storage, "lun", None).await;
+        assert_eq!((hits.len(), hits[0].as_str()), (3, "m2"));
+        assert_eq!(
+            search_ids(&storage, "CAFE tomorrow", None).await,
+            vec!["m1"]
+        );
+        assert_eq!(
+            search_ids(&storage, "lunch", Some("room-2")).await,
+            vec!["m3"]
+        );
+        assert!(search_ids(&storage, "bHVuY2g", None).await.is_empty());
+        // Operators and stray quotes are searched as text
+        assert!(search_ids(&storage, "lunch NOT \"today", None)
+            .await
+            .is_empty());
+        assert!(search_ids(&storage, "   ", None).await.is_empty());
+
+        let mut edited = search_fixture("m1", "room-1", "dinner instead", None);
+        edited.edited_at = Some("2026-02-12T00:05:00Z".to_string());
+        storage.upsert_message(&edited).await.expect("edit");
+        assert_eq!(search_ids(&storage, "dinner", None).await, vec!["m1"]);
+        assert_eq!(search_ids(&storage, "lunch", None).await, vec!["m2", "m3"]);
+
+        assert!(storage.delete_message("m2").await.expect("delete"));
+        assert!(!storage.delete_message("m2").await.expect("delete again"));
+        assert_eq!(search_ids(&storage, "lunch", None).await, vec!["m3"]);
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    #[tokio::test]
+    async fn search_index_is_built_for_an_existing_cache() {
+        let db_path = temp_db_path("messaging-storage-search-migrate");
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage init");
+        storage
+            .upsert_message(&search_fixture("m1", "room-1", "old message", None))
+            .await
+            .expect("insert");
+
+        // Roll back to a cache from before the index existed
+        for statement in [
+            "DROP TRIGGER local_messages_fts_insert",
+            "DROP TRIGGER local_messages_fts_update",
+            "DROP TRIGGER local_messages_fts_delete",
+            "DROP TABLE local_messages_fts",
+        ] {
+            sqlx::query(statement)
+                .execute(&storage.pool)
+                .await
+                .expect("drop index");
+        }
+        storage.pool.close().await;
+
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage reopen");
+        assert_eq!(search_ids(&storage, "old", None).await, vec!["m1"]);
+
+        // Opening again does not index twice
+        storage.pool.close().await;
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage reopen");
+        assert_eq!(search_ids(&storage, "message", None).await, vec!["m1"]);
+
+        let _ = std::fs::remove_file(db_path);
+    }
+
+    #[tokio::test]
+    async fn attachments_round_trip_and_older_databases_gain_the_column() {
+        let db_path = temp_db_path("messaging-storage-attachments");
+
+        // A cache written before attachments existed
+        {
+            let options = SqliteConnectOptions::new()
+                .filename(&db_path)
+                .create_if_missing(true);
+            let pool = SqlitePool::connect_with(options).await.expect("legacy db");
+            for statement in [
+                "CREATE TABLE local_messages (local_id TEXT PRIMARY KEY, server_id TEXT UNIQUE, client_id TEXT UNIQUE, sender_id TEXT, sender_username TEXT, target_kind TEXT NOT NULL, target_id TEXT NOT NULL, content TEXT NOT NULL, nonce TEXT, created_at TEXT NOT NULL, edited_at TEXT, status TEXT NOT NULL, updated_at TEXT NOT NULL DEFAULT (datetime('now')))",
+                "CREATE TABLE outbox (client_id TEXT PRIMARY KEY, target_kind TEXT NOT NULL, target_id TEXT NOT NULL, server_scope_id TEXT, sender_id TEXT, content TEXT NOT NULL, nonce TEXT, created_at TEXT NOT NULL, attempts INTEGER NOT NULL DEFAULT 0, last_error TEXT)",
+                "INSERT INTO local_messages (local_id, server_id, target_kind, target_id, content, created_at, status) VALUES ('old', 'old', 'channel', 'ch-1', 'before attachments', '2026-02-12T00:00:00Z', 'sent')",
+            ] {
+                sqlx::query(statement)
+                    .execute(&pool)
+                    .await
+                    .expect("legacy schema");
+            }
+            pool.close().await;
+        }
+
+        let storage = MessagingStorage::new(db_path.clone())
+            .await
+            .expect("storage init");
+        let attachment = Attachment {
+            id: "a1".to_string(),
+            mime: "image/png".to_string(),
+            size: 2048,
+            encrypted_url: "https://files.example.org/blob/a1".to_string(),
+            nonce: "bm9uY2U=".to_string(),
+        };
+        storage
+            .upsert_message(&PersistedMessage {
+                local_id: "new".to_string(),
+                server_id: Some("new".to_string()),
+                client_id: None,
+                sender_id: Some("u1".to_string()),
+                sender_username: None,
+                target_kind: ConversationKind::Channel,
+                target_id: "ch-1".to_string(),
+                content: "with a picture".to_string(),
+                nonce: None,
+                created_at: "2026-02-12T00:00:01Z".to_string(),
+                edited_at: None,
+                status: MessageStatus::Sent,
+                attachments: vec![attachment.clone()],
+            })
+            .await
+            .expect("insert");
+        storage
+            .enqueue_outbox(&OutboxMessage {
+                client_id: "c1".to_string(),
+                target_kind: ConversationKind::Channel,
+                target_id: "ch-1".to_string(),
+                server_scope_id: Some("srv-1".to_string()),
+                sender_id: Some("u1".to_string()),
+                content: "queued picture".to_string(),
+                nonce: None,
+                created_at: "2026-02-12T00:00:02Z".to_string(),
+                attempts: 0,
+                last_error: None,
+                attachments: vec![attachment.clone()],
+            })
+            .await
+            .expect("enqueue");
+
+        let loaded = storage
+            .load_messages(ConversationKind::Channel, "ch-1", None, 50)
+            .await
+            .expect("load messages");
+        assert!(loaded[0].attachments.is_empty());
+        assert_eq!(loaded[1].attachments, vec![attachment.clone()]);
+        assert_eq!(
+            storage.list_outbox(10).await.expect("list outbox")[0].attachments,
+            vec![attachment]
+        );
+
+        let _ = std::fs::remove_file(db_path);
+    }
 }
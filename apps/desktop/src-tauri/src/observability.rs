@@ -1,9 +1,10 @@
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
 
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
 static SESSION_TRACE_ID: OnceLock<String> = OnceLock::new();
+static CURRENT_CALL_ID: Mutex<Option<String>> = Mutex::new(None);
 
 pub fn init_tracing() {
     let env_filter = std::env::var("APP_LOG_LEVEL")
@@ -36,3 +37,38 @@ pub fn trace_id() -> &'static str {
 pub fn request_id() -> String {
     Uuid::new_v4().to_string()
 }
+
+/// Mint the id for a call we are placing. Every signaling message and log
+/// line for the call carries it until `finish_call`.
+pub fn begin_call() -> String {
+    let call_id = Uuid::new_v4().to_string();
+    set_call_id(Some(call_id.clone()));
+    call_id
+}
+
+/// Take over the id the server relayed for an incoming or accepted call.
+pub fn adopt_call_id(call_id: Option<&str>) {
+    if let Some(call_id) = call_id {
+        set_call_id(Some(call_id.to_string()));
+    }
+}
+
+/// Forget the current call id. A `call_id` naming some other call (a late
+/// message for one already replaced) leaves the current id alone.
+pub fn finish_call(call_id: Option<&str>) {
+    let mut current = CURRENT_CALL_ID.lock().unwrap_or_else(|e| e.into_inner());
+    if call_id.is_none() || current.as_deref() == call_id {
+        *current = None;
+    }
+}
+
+pub fn call_id() -> Option<String> {
+    CURRENT_CALL_ID
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+fn set_call_id(call_id: Option<String>) {
+    *CURRENT_CALL_ID.lock().unwrap_or_else(|e| e.into_inner()) = call_id;
+}
@@ -1,10 +1,11 @@
 use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use futures_util::{SinkExt, StreamExt};
 use serde::Serialize;
 use shared_proto::signaling::SignalingMessage;
 use tauri::Emitter;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 
 use crate::backoff::{compute_backoff_delay, BackoffConfig};
@@ -29,6 +30,10 @@ type WsReadHalf = futures_util::stream::SplitStream<
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
 >;
 
+/// The server pings every 15s; this long without any frame means the
+/// connection is dead even though the socket never closed
+const SERVER_SILENCE_TIMEOUT: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Clone, Copy, Serialize)]
 #[serde(rename_all = "snake_case")]
 enum WsLifecycleState {
@@ -49,6 +54,18 @@ type IdentifySlot = Arc<Mutex<Option<IdentifyContext>>>;
 
 static IDENTIFY_SLOT: OnceLock<IdentifySlot> = OnceLock::new();
 
+static ONLINE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+fn online_sender() -> &'static watch::Sender<bool> {
+    ONLINE.get_or_init(|| watch::channel(false).0)
+}
+
+/// Follows whether the signaling socket is up, i.e. the same value as the
+/// `ws-status` event, for background work that should pause while offline
+pub fn online_watch() -> watch::Receiver<bool> {
+    online_sender().subscribe()
+}
+
 fn identify_slot() -> IdentifySlot {
     IDENTIFY_SLOT
         .get_or_init(|| Arc::new(Mutex::new(None)))
@@ -56,25 +73,49 @@ fn identify_slot() -> IdentifySlot {
 }
 
 /// Connect to the signaling server with automatic reconnection.
+///
+/// Only an invalid URL is an error. If the first attempt fails, the returned
+/// sender stays empty and the background loop keeps retrying with backoff;
+/// it is filled in whenever a connection is up, so holders of the
+/// `WsSender` always reach the current socket.
 pub async fn connect(server_url: &str, app_handle: tauri::AppHandle) -> AppResult<WsSender> {
     let url = url::Url::parse(server_url)?;
 
-    let sender = Arc::new(Mutex::new(None));
+    let sender: WsSender = Arc::new(Mutex::new(None));
     let state = Arc::new(Mutex::new(WsLifecycleState::Disconnected));
 
     transition_ws_state(&app_handle, &state, WsLifecycleState::Connecting, "initial connect").await;
 
-    let (ws_stream, _) = connect_async(url.clone())
-        .await
-        .map_err(|e| AppError::network("Failed to connect signaling websocket").with_details(e.to_string()))?;
-
-    let (write, read) = ws_stream.split();
-    {
-        let mut guard = sender.lock().await;
-        *guard = Some(write);
-    }
-
-    transition_ws_state(&app_handle, &state, WsLifecycleState::Ready, "connected").await;
+    let read = match connect_async(url).await {
+        Ok((ws_stream, _)) => {
+            let (write, read) = ws_stream.split();
+            {
+                let mut guard = sender.lock().await;
+                *guard = Some(write);
+            }
+            transition_ws_state(&app_handle, &state, WsLifecycleState::Ready, "connected").await;
+            send_hello_or_warn(&sender).await;
+            Some(read)
+        }
+        Err(err) => {
+            tracing::warn!(
+                component = "ws",
+                ws_state = "connecting",
+                trace_id = observability::trace_id(),
+                protocol_version = protocol::PROTOCOL_VERSION,
+                error = %err,
+                "initial websocket connect failed, retrying in the background"
+            );
+            transition_ws_state(
+                &app_handle,
+                &state,
+                WsLifecycleState::Reconnecting,
+                "initial connect failed",
+            )
+            .await;
+            None
+        }
+    };
 
     let sender_clone = sender.clone();
     let state_clone = state.clone();
@@ -97,9 +138,12 @@ pub async fn connect(server_url: &str, app_handle: tauri::AppHandle) -> AppResul
     Ok(sender)
 }
 
+/// Read from the current connection until it drops, then reconnect with
+/// backoff, re-identify and emit `ws-reconnected`. Starts in the reconnect
+/// phase when `read` is `None`.
 async fn run_ws_loop(
     server_url: String,
-    mut read: WsReadHalf,
+    mut read: Option<WsReadHalf>,
     sender: WsSender,
     state: Arc<Mutex<WsLifecycleState>>,
     identify: IdentifySlot,
@@ -109,15 +153,18 @@ async fn run_ws_loop(
     let mut reconnect_attempt: u32 = 0;
 
     loop {
-        handle_ws_messages(&mut read, &app_handle).await;
+        if let Some(mut current) = read.take() {
+            handle_ws_messages(&mut current, &app_handle).await;
+            drop_connection(&sender).await;
 
-        transition_ws_state(
-            &app_handle,
-            &state,
-            WsLifecycleState::Reconnecting,
-            "connection dropped",
-        )
-        .await;
+            transition_ws_state(
+                &app_handle,
+                &state,
+                WsLifecycleState::Reconnecting,
+                "connection dropped",
+            )
+            .await;
+        }
 
         loop {
             let delay = compute_backoff_delay(backoff, reconnect_attempt);
@@ -167,6 +214,7 @@ async fn run_ws_loop(
                         let mut guard = sender.lock().await;
                         *guard = Some(new_write);
                     }
+                    send_hello_or_warn(&sender).await;
 
                     let identify_result = maybe_identify_after_reconnect(
                         &sender,
@@ -185,6 +233,7 @@ async fn run_ws_loop(
                             error = %err,
                             "automatic identify after reconnect failed"
                         );
+                        drop_connection(&sender).await;
                         transition_ws_state(
                             &app_handle,
                             &state,
@@ -204,7 +253,7 @@ async fn run_ws_loop(
                     .await;
 
                     emit_resync(&app_handle);
-                    read = new_read;
+                    read = Some(new_read);
                     break;
                 }
                 Err(err) => {
@@ -229,6 +278,15 @@ async fn run_ws_loop(
     }
 }
 
+/// Empty the sender so sends fail straight away with "not connected"
+/// instead of writing into a dead socket until the next one is up
+async fn drop_connection(sender: &WsSender) {
+    let write = sender.lock().await.take();
+    if let Some(mut write) = write {
+        let _ = write.close().await;
+    }
+}
+
 async fn transition_ws_state(
     app_handle: &tauri::AppHandle,
     state: &Arc<Mutex<WsLifecycleState>>,
@@ -251,6 +309,7 @@ async fn transition_ws_state(
 
     let _ = app_handle.emit("ws-state", payload);
     let _ = app_handle.emit("ws-status", ws_connected);
+    online_sender().send_replace(ws_connected);
 
     tracing::info!(
         component = "ws",
@@ -293,12 +352,37 @@ fn emit_resync(app_handle: &tauri::AppHandle) {
 
 /// Handle incoming WebSocket messages.
 async fn handle_ws_messages(read: &mut WsReadHalf, app_handle: &tauri::AppHandle) {
-    while let Some(msg) = read.next().await {
+    loop {
+        let msg = match tokio::time::timeout(SERVER_SILENCE_TIMEOUT, read.next()).await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(_) => {
+                tracing::warn!(
+                    component = "ws",
+                    ws_state = "disconnected",
+                    trace_id = observability::trace_id(),
+                    silence_secs = SERVER_SILENCE_TIMEOUT.as_secs(),
+                    "no frame from server, treating websocket as dead"
+                );
+                break;
+            }
+        };
         match msg {
             Ok(Message::Text(text)) => {
                 let _ = app_handle.emit("ws-message", text.clone());
 
                 if let Ok(signal) = serde_json::from_str::<SignalingMessage>(&text) {
+                    if let SignalingMessage::ServerHello {
+                        chosen_version,
+                        min,
+                        max,
+                        ..
+                    } = signal
+                    {
+                        handle_server_hello(app_handle, chosen_version, min, max);
+                        continue;
+                    }
+
                     if !protocol::is_supported_protocol_version(signal.version()) {
                         tracing::warn!(
                             component = "ws",
@@ -336,6 +420,15 @@ async fn handle_ws_messages(read: &mut WsReadHalf, app_handle: &tauri::AppHandle
                             });
                             let _ = app_handle.emit("webrtc-answer", payload);
                         }
+                        SignalingMessage::CallRestart {
+                            target_id, sdp, ..
+                        } => {
+                            let payload = serde_json::json!({
+                                "peerId": target_id,
+                                "sdp": sdp,
+                            });
+                            let _ = app_handle.emit("call-restart", payload);
+                        }
                         SignalingMessage::Candidate {
                             target_id,
                             candidate,
@@ -351,59 +444,143 @@ async fn handle_ws_messages(read: &mut WsReadHalf, app_handle: &tauri::AppHandle
                             });
                             let _ = app_handle.emit("webrtc-candidate", payload);
                         }
+                        SignalingMessage::AudioParamsOffer {
+                            target_id,
+                            frame_duration_ms,
+                            channels,
+                            ..
+                        } => {
+                            let payload = serde_json::json!({
+                                "peerId": target_id,
+                                "params": {
+                                    "frameDurationMs": frame_duration_ms,
+                                    "channels": channels,
+                                },
+                            });
+                            let _ = app_handle.emit("audio-params-offer", payload);
+                        }
+                        SignalingMessage::AudioParamsAnswer {
+                            target_id,
+                            accepted,
+                            frame_duration_ms,
+                            channels,
+                            ..
+                        } => {
+                            let payload = serde_json::json!({
+                                "peerId": target_id,
+                                "accepted": accepted,
+                                "params": {
+                                    "frameDurationMs": frame_duration_ms,
+                                    "channels": channels,
+                                },
+                            });
+                            let _ = app_handle.emit("audio-params-answer", payload);
+                        }
                         SignalingMessage::IncomingCall {
                             caller_id,
                             caller_name,
                             public_key,
+                            caller_status,
+                            call_id,
+                            while_busy,
+                            ring_timeout_secs,
                             ..
                         } => {
+                            // A call waiting on the current one keeps its own
+                            // id until it is answered
+                            if !while_busy {
+                                observability::adopt_call_id(call_id.as_deref());
+                            }
+                            log_call_signal("incoming_call", call_id.as_deref());
                             let payload = serde_json::json!({
                                 "callerId": caller_id,
                                 "callerName": caller_name,
                                 "publicKey": public_key,
+                                "callerStatus": caller_status,
+                                "callId": call_id,
+                                "whileBusy": while_busy,
+                                "ringTimeoutSecs": ring_timeout_secs,
                             });
                             let _ = app_handle.emit("incoming-call", payload);
                         }
                         SignalingMessage::CallAccepted {
                             target_id,
                             public_key,
+                            call_id,
                             ..
                         } => {
+                            observability::adopt_call_id(call_id.as_deref());
+                            log_call_signal("call_accepted", call_id.as_deref());
                             let payload = serde_json::json!({
                                 "peerId": target_id,
                                 "publicKey": public_key,
                             });
                             let _ = app_handle.emit("call-accepted", payload);
                         }
-                        SignalingMessage::CallDeclined { target_id, .. } => {
+                        SignalingMessage::CallDeclined {
+                            target_id, call_id, ..
+                        } => {
+                            log_call_signal("call_declined", call_id.as_deref());
+                            observability::finish_call(call_id.as_deref());
                             let _ = app_handle.emit("call-declined", target_id);
                         }
-                        SignalingMessage::CallEnded { peer_id, .. } => {
+                        SignalingMessage::CallEnded {
+                            peer_id, call_id, ..
+                        } => {
+                            log_call_signal("call_ended", call_id.as_deref());
+                            observability::finish_call(call_id.as_deref());
                             let _ = app_handle.emit("call-ended", peer_id);
                         }
                         SignalingMessage::CallBusy {
                             caller_id: busy_user,
+                            call_id,
                             ..
                         } => {
+                            log_call_signal("call_busy", call_id.as_deref());
+                            observability::finish_call(call_id.as_deref());
                             let _ = app_handle.emit("call-busy", busy_user);
                         }
-                        SignalingMessage::CallCancelled { caller_id, .. } => {
+                        SignalingMessage::CallCancelled {
+                            caller_id, call_id, ..
+                        } => {
+                            log_call_signal("call_cancelled", call_id.as_deref());
+                            observability::finish_call(call_id.as_deref());
                             let _ = app_handle.emit("call-cancelled", caller_id);
                         }
                         SignalingMessage::CallUnavailable {
-                            target_id, reason, ..
+                            target_id,
+                            reason,
+                            call_id,
+                            ..
                         } => {
+                            log_call_signal("call_unavailable", call_id.as_deref());
+                            observability::finish_call(call_id.as_deref());
                             let payload = serde_json::json!({
                                 "targetId": target_id,
                                 "reason": reason,
                             });
                             let _ = app_handle.emit("call-unavailable", payload);
                         }
+                        SignalingMessage::CallStateSnapshot {
+                            active_peer,
+                            pending_peer,
+                            call_id,
+                            ..
+                        } => {
+                            let payload = serde_json::json!({
+                                "activePeer": active_peer,
+                                "pendingPeer": pending_peer,
+                                "callId": call_id,
+                            });
+                            let _ = app_handle.emit("call-state-snapshot", payload);
+                        }
                         _ => {}
                     }
                 }
             }
             Ok(Message::Ping(_)) => {
+                // tungstenite queues the pong itself and flushes it on the
+                // next read, so the server's heartbeat sees us as alive
                 tracing::debug!(component = "ws", ws_state = "ready", "received ping");
             }
             Ok(Message::Close(_)) => {
@@ -426,6 +603,16 @@ async fn handle_ws_messages(read: &mut WsReadHalf, app_handle: &tauri::AppHandle
     }
 }
 
+fn log_call_signal(event: &'static str, call_id: Option<&str>) {
+    tracing::info!(
+        component = "call",
+        call_id = call_id.unwrap_or("missing"),
+        trace_id = observability::trace_id(),
+        event,
+        "call signal received"
+    );
+}
+
 /// Send a signaling message to a peer via the server.
 pub async fn send_signal(sender: &WsSender, message: SignalingMessage) -> AppResult<()> {
     let message = with_message_metadata(message);
@@ -450,6 +637,63 @@ pub async fn send_signal(sender: &WsSender, message: SignalingMessage) -> AppRes
     }
 }
 
+/// Open the version handshake; the server answers with `ServerHello`.
+async fn send_hello(sender: &WsSender) -> AppResult<()> {
+    let hello = SignalingMessage::Hello {
+        trace_id: Some(observability::trace_id().to_string()),
+        client_version: protocol::PROTOCOL_VERSION,
+        min: protocol::LEGACY_PROTOCOL_VERSION,
+        max: protocol::PROTOCOL_VERSION,
+    };
+    send_signal(sender, hello).await
+}
+
+async fn send_hello_or_warn(sender: &WsSender) {
+    if let Err(err) = send_hello(sender).await {
+        tracing::warn!(
+            component = "ws",
+            trace_id = observability::trace_id(),
+            protocol_version = protocol::PROTOCOL_VERSION,
+            error = %err,
+            "failed to send protocol hello"
+        );
+    }
+}
+
+/// Log the version the server settled on, or report that there is none.
+/// Servers that predate the handshake never answer, which is fine: messages
+/// then keep their per-message version check.
+fn handle_server_hello(app_handle: &tauri::AppHandle, chosen: Option<u8>, min: u8, max: u8) {
+    match chosen {
+        Some(version) => tracing::info!(
+            component = "ws",
+            trace_id = observability::trace_id(),
+            protocol_version = version,
+            server_min = min,
+            server_max = max,
+            "negotiated protocol version"
+        ),
+        None => {
+            tracing::warn!(
+                component = "ws",
+                trace_id = observability::trace_id(),
+                protocol_version = protocol::PROTOCOL_VERSION,
+                server_min = min,
+                server_max = max,
+                "no protocol version in common with server"
+            );
+            let _ = app_handle.emit(
+                "ws-protocol-error",
+                serde_json::json!({
+                    "serverMin": min,
+                    "serverMax": max,
+                    "supported": [protocol::LEGACY_PROTOCOL_VERSION, protocol::PROTOCOL_VERSION],
+                }),
+            );
+        }
+    }
+}
+
 /// Send identification message to server.
 pub async fn send_identify(sender: &WsSender, user_id: &str, token: &str) -> AppResult<()> {
     {
@@ -467,37 +711,132 @@ pub async fn send_identify(sender: &WsSender, user_id: &str, token: &str) -> App
         user_id: user_id.to_string(),
         token: token.to_string(),
     };
-    send_signal(sender, identify).await
+    send_signal(sender, identify).await?;
+
+    // Sent right behind identify, which the server handles first, so the
+    // UI can drop or keep its call after a restart or reconnect
+    let query = SignalingMessage::CallStateQuery {
+        version: protocol::PROTOCOL_VERSION,
+        trace_id: Some(observability::trace_id().to_string()),
+    };
+    send_signal(sender, query).await
 }
 
 fn with_message_metadata(message: SignalingMessage) -> SignalingMessage {
     let trace = Some(observability::trace_id().to_string());
 
     match message {
+        SignalingMessage::Hello {
+            trace_id,
+            client_version,
+            min,
+            max,
+        } => SignalingMessage::Hello {
+            trace_id: trace_id.or(trace.clone()),
+            client_version,
+            min,
+            max,
+        },
+        SignalingMessage::ServerHello {
+            trace_id,
+            chosen_version,
+            min,
+            max,
+        } => SignalingMessage::ServerHello {
+            trace_id: trace_id.or(trace.clone()),
+            chosen_version,
+            min,
+            max,
+        },
         SignalingMessage::Offer {
             trace_id,
+            call_id,
             target_id,
             sdp,
             ..
         } => SignalingMessage::Offer {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
             sdp,
         },
         SignalingMessage::Answer {
             trace_id,
+            call_id,
             target_id,
             sdp,
             ..
         } => SignalingMessage::Answer {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
             sdp,
         },
+        SignalingMessage::CallRestart {
+            trace_id,
+            call_id,
+            target_id,
+            sdp,
+            ..
+        } => SignalingMessage::CallRestart {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
+            target_id,
+            sdp,
+        },
+        SignalingMessage::Renegotiate {
+            trace_id,
+            call_id,
+            target_id,
+            sdp,
+            kind,
+            ..
+        } => SignalingMessage::Renegotiate {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
+            target_id,
+            sdp,
+            kind,
+        },
+        SignalingMessage::AudioParamsOffer {
+            trace_id,
+            call_id,
+            target_id,
+            frame_duration_ms,
+            channels,
+            ..
+        } => SignalingMessage::AudioParamsOffer {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
+            target_id,
+            frame_duration_ms,
+            channels,
+        },
+        SignalingMessage::AudioParamsAnswer {
+            trace_id,
+            call_id,
+            target_id,
+            accepted,
+            frame_duration_ms,
+            channels,
+            ..
+        } => SignalingMessage::AudioParamsAnswer {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
+            target_id,
+            accepted,
+            frame_duration_ms,
+            channels,
+        },
         SignalingMessage::Candidate {
             trace_id,
+            call_id,
             target_id,
             candidate,
             sdp_mid,
@@ -506,6 +845,7 @@ fn with_message_metadata(message: SignalingMessage) -> SignalingMessage {
         } => SignalingMessage::Candidate {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
             candidate,
             sdp_mid,
@@ -524,119 +864,200 @@ fn with_message_metadata(message: SignalingMessage) -> SignalingMessage {
         },
         SignalingMessage::CallInitiate {
             trace_id,
+            call_id,
             target_id,
             public_key,
+            ring_timeout_secs,
             ..
         } => SignalingMessage::CallInitiate {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
             public_key,
+            ring_timeout_secs,
         },
         SignalingMessage::IncomingCall {
             trace_id,
+            call_id,
             caller_id,
             caller_name,
             public_key,
+            caller_status,
+            while_busy,
+            ring_timeout_secs,
             ..
         } => SignalingMessage::IncomingCall {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             caller_id,
             caller_name,
             public_key,
+            caller_status,
+            while_busy,
+            ring_timeout_secs,
         },
         SignalingMessage::CallAccept {
             trace_id,
+            call_id,
             caller_id,
             public_key,
             ..
         } => SignalingMessage::CallAccept {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             caller_id,
             public_key,
         },
         SignalingMessage::CallAccepted {
             trace_id,
+            call_id,
             target_id,
             public_key,
             ..
         } => SignalingMessage::CallAccepted {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
             public_key,
         },
         SignalingMessage::CallDecline {
             trace_id,
+            call_id,
             caller_id,
             ..
         } => SignalingMessage::CallDecline {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             caller_id,
         },
         SignalingMessage::CallDeclined {
             trace_id,
+            call_id,
             target_id,
             ..
         } => SignalingMessage::CallDeclined {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
         },
         SignalingMessage::CallEnd {
-            trace_id, peer_id, ..
+            trace_id,
+            call_id,
+            peer_id,
+            ..
         } => SignalingMessage::CallEnd {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             peer_id,
         },
         SignalingMessage::CallEnded {
-            trace_id, peer_id, ..
+            trace_id,
+            call_id,
+            peer_id,
+            ..
         } => SignalingMessage::CallEnded {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             peer_id,
         },
         SignalingMessage::CallBusy {
             trace_id,
+            call_id,
             caller_id,
             ..
         } => SignalingMessage::CallBusy {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             caller_id,
         },
         SignalingMessage::CallCancel {
             trace_id,
+            call_id,
             target_id,
             ..
         } => SignalingMessage::CallCancel {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
         },
+        SignalingMessage::CallWaiting {
+            trace_id,
+            call_id,
+            caller_id,
+            accept,
+            public_key,
+            ..
+        } => SignalingMessage::CallWaiting {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
+            caller_id,
+            accept,
+            public_key,
+        },
         SignalingMessage::CallCancelled {
             trace_id,
+            call_id,
             caller_id,
             ..
         } => SignalingMessage::CallCancelled {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             caller_id,
         },
         SignalingMessage::CallUnavailable {
             trace_id,
+            call_id,
             target_id,
             reason,
             ..
         } => SignalingMessage::CallUnavailable {
             version: protocol::PROTOCOL_VERSION,
             trace_id: trace_id.or(trace.clone()),
+            call_id: call_id.or_else(observability::call_id),
             target_id,
             reason,
         },
+        SignalingMessage::CallStateQuery { trace_id, .. } => SignalingMessage::CallStateQuery {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+        },
+        SignalingMessage::VoiceState {
+            trace_id,
+            channel_id,
+            muted,
+            speaking,
+            ..
+        } => SignalingMessage::VoiceState {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            channel_id,
+            muted,
+            speaking,
+        },
+        SignalingMessage::CallStateSnapshot {
+            trace_id,
+            call_id,
+            active_peer,
+            pending_peer,
+            ..
+        } => SignalingMessage::CallStateSnapshot {
+            version: protocol::PROTOCOL_VERSION,
+            trace_id: trace_id.or(trace.clone()),
+            call_id,
+            active_peer,
+            pending_peer,
+        },
     }
 }
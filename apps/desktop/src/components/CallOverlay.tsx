@@ -1,8 +1,9 @@
 import { useEffect, useMemo, useState } from 'react';
-import { PhoneOff, Mic, MicOff, Settings, ChevronDown, Volume2, VolumeX, Ear } from 'lucide-react';
+import { Phone, PhoneOff, Mic, MicOff, Settings, ChevronDown, Volume2, VolumeX, Ear, Circle, Square } from 'lucide-react';
 import { useAppStore } from '../store';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
+import type { CallQuality, ConnectivityReport, IceStatePayload } from '../types';
 
 interface AudioDevice {
     id: string;
@@ -11,6 +12,25 @@ interface AudioDevice {
 
 type VoiceMode = 'mute' | 'push_to_talk' | 'voice_activity';
 type AudioMode = 'headphones' | 'speakers';
+type CaptureStage = 'gain' | 'noise_gate';
+type NoiseSuppressionMode = 'light' | 'heavy';
+
+// Values pinned by the user over what the audio mode would pick; null leaves it to the mode
+interface ProfileOverrides {
+    aec: boolean | null;
+    noise_gate: boolean | null;
+    noise_gate_threshold: number | null;
+    max_output_volume: number | null;
+}
+
+// What the audio mode and overrides resolve to in the engine
+interface AudioProfile {
+    mode: AudioMode;
+    aec: boolean;
+    noise_gate: boolean;
+    noise_gate_threshold: number;
+    max_output_volume: number;
+}
 
 interface AudioSettings {
     mic_gain: number;
@@ -19,16 +39,46 @@ interface AudioSettings {
     voice_mode: VoiceMode;
     vad_threshold: number;
     noise_suppression: boolean;
+    noise_suppression_mode: NoiseSuppressionMode;
     aec: boolean;
     agc: boolean;
+    agc_target_rms: number;
+    agc_min_gain: number;
+    agc_max_gain: number;
     noise_gate: boolean;
     noise_gate_threshold: number;
+    noise_gate_hold_ms: number;
+    noise_gate_high_band_bias: number;
     limiter: boolean;
     deafen: boolean;
     ptt_key: string;
     audio_mode: AudioMode;
+    profile_overrides: ProfileOverrides;
+    capture_stage_order: CaptureStage[];
+    nat_keepalive_interval: number;
+    send_buffer_limit_bytes: number;
+    minimal_processing: boolean;
+    jitter_buffer_ms: number;
+    stereo: boolean;
+    dtx: boolean;
+    frame_duration_ms: number;
+    compressor: boolean;
+    compressor_threshold_db: number;
+    compressor_ratio: number;
+    compressor_attack_ms: number;
+    compressor_release_ms: number;
 }
 
+// Opus frame durations the engine accepts
+const FRAME_DURATIONS_MS = [2.5, 5, 10, 20, 40, 60];
+
+const CALL_QUALITY_STYLES: Record<CallQuality, { color: string; label: string }> = {
+    good: { color: 'bg-green-400', label: 'Good' },
+    fair: { color: 'bg-yellow-400', label: 'Fair' },
+    poor: { color: 'bg-orange-400', label: 'Poor' },
+    critical: { color: 'bg-red-500', label: 'Critical' },
+};
+
 const DEFAULT_AUDIO_SETTINGS: AudioSettings = {
     mic_gain: 1,
     output_volume: 1,
@@ -36,16 +86,47 @@ const DEFAULT_AUDIO_SETTINGS: AudioSettings = {
     voice_mode: 'voice_activity',
     vad_threshold: 0.02,
     noise_suppression: true,
+    noise_suppression_mode: 'light',
     aec: true,
     agc: true,
+    agc_target_rms: 0.12,
+    agc_min_gain: 0.3,
+    agc_max_gain: 3.5,
     noise_gate: true,
     noise_gate_threshold: 0.01,
+    noise_gate_hold_ms: 50,
+    noise_gate_high_band_bias: 2,
     limiter: true,
     deafen: false,
     ptt_key: 'V',
     audio_mode: 'headphones',
+    profile_overrides: { aec: null, noise_gate: null, noise_gate_threshold: null, max_output_volume: null },
+    capture_stage_order: ['gain', 'noise_gate'],
+    nat_keepalive_interval: 15,
+    send_buffer_limit_bytes: 4096,
+    minimal_processing: false,
+    jitter_buffer_ms: 60,
+    stereo: false,
+    dtx: false,
+    frame_duration_ms: 20,
+    compressor: false,
+    compressor_threshold_db: -18,
+    compressor_ratio: 3,
+    compressor_attack_ms: 5,
+    compressor_release_ms: 120,
 };
 
+function coerceProfileOverrides(input: unknown): ProfileOverrides {
+    const value = (input && typeof input === 'object' ? input : {}) as Partial<ProfileOverrides>;
+    return {
+        aec: typeof value.aec === 'boolean' ? value.aec : null,
+        noise_gate: typeof value.noise_gate === 'boolean' ? value.noise_gate : null,
+        noise_gate_threshold:
+            typeof value.noise_gate_threshold === 'number' ? clamp(value.noise_gate_threshold, 0, 0.2) : null,
+        max_output_volume: typeof value.max_output_volume === 'number' ? clamp(value.max_output_volume, 0, 2) : null,
+    };
+}
+
 function coerceAudioSettings(input: unknown): AudioSettings {
     if (!input || typeof input !== 'object') {
         return { ...DEFAULT_AUDIO_SETTINGS };
@@ -54,6 +135,10 @@ function coerceAudioSettings(input: unknown): AudioSettings {
     const value = input as Partial<AudioSettings>;
     const voiceMode = value.voice_mode;
     const audioMode = value.audio_mode;
+    const stageOrder = value.capture_stage_order;
+    const noiseSuppressionMode = value.noise_suppression_mode;
+    const agcMaxGain =
+        typeof value.agc_max_gain === 'number' ? clamp(value.agc_max_gain, 0, 8) : DEFAULT_AUDIO_SETTINGS.agc_max_gain;
 
     return {
         mic_gain: typeof value.mic_gain === 'number' ? clamp(value.mic_gain, 0, 3) : DEFAULT_AUDIO_SETTINGS.mic_gain,
@@ -70,21 +155,98 @@ function coerceAudioSettings(input: unknown): AudioSettings {
             typeof value.vad_threshold === 'number' ? clamp(value.vad_threshold, 0, 0.3) : DEFAULT_AUDIO_SETTINGS.vad_threshold,
         noise_suppression:
             typeof value.noise_suppression === 'boolean' ? value.noise_suppression : DEFAULT_AUDIO_SETTINGS.noise_suppression,
+        noise_suppression_mode:
+            noiseSuppressionMode === 'light' || noiseSuppressionMode === 'heavy'
+                ? noiseSuppressionMode
+                : DEFAULT_AUDIO_SETTINGS.noise_suppression_mode,
         aec: typeof value.aec === 'boolean' ? value.aec : DEFAULT_AUDIO_SETTINGS.aec,
         agc: typeof value.agc === 'boolean' ? value.agc : DEFAULT_AUDIO_SETTINGS.agc,
+        agc_target_rms:
+            typeof value.agc_target_rms === 'number'
+                ? clamp(value.agc_target_rms, 0, 0.5)
+                : DEFAULT_AUDIO_SETTINGS.agc_target_rms,
+        agc_min_gain: clamp(
+            typeof value.agc_min_gain === 'number' ? value.agc_min_gain : DEFAULT_AUDIO_SETTINGS.agc_min_gain,
+            0,
+            agcMaxGain,
+        ),
+        agc_max_gain: agcMaxGain,
         noise_gate: typeof value.noise_gate === 'boolean' ? value.noise_gate : DEFAULT_AUDIO_SETTINGS.noise_gate,
         noise_gate_threshold:
             typeof value.noise_gate_threshold === 'number'
                 ? clamp(value.noise_gate_threshold, 0, 0.2)
                 : DEFAULT_AUDIO_SETTINGS.noise_gate_threshold,
+        noise_gate_hold_ms:
+            typeof value.noise_gate_hold_ms === 'number'
+                ? Math.round(clamp(value.noise_gate_hold_ms, 0, 500))
+                : DEFAULT_AUDIO_SETTINGS.noise_gate_hold_ms,
+        noise_gate_high_band_bias:
+            typeof value.noise_gate_high_band_bias === 'number'
+                ? clamp(value.noise_gate_high_band_bias, 0, 4)
+                : DEFAULT_AUDIO_SETTINGS.noise_gate_high_band_bias,
         limiter: typeof value.limiter === 'boolean' ? value.limiter : DEFAULT_AUDIO_SETTINGS.limiter,
         deafen: typeof value.deafen === 'boolean' ? value.deafen : DEFAULT_AUDIO_SETTINGS.deafen,
         ptt_key: typeof value.ptt_key === 'string' && value.ptt_key.trim() ? value.ptt_key : DEFAULT_AUDIO_SETTINGS.ptt_key,
         audio_mode:
             audioMode === 'headphones' || audioMode === 'speakers' ? audioMode : DEFAULT_AUDIO_SETTINGS.audio_mode,
+        profile_overrides: coerceProfileOverrides(value.profile_overrides),
+        capture_stage_order:
+            Array.isArray(stageOrder) &&
+            stageOrder.length === 2 &&
+            stageOrder.includes('gain') &&
+            stageOrder.includes('noise_gate')
+                ? [...stageOrder]
+                : [...DEFAULT_AUDIO_SETTINGS.capture_stage_order],
+        nat_keepalive_interval:
+            typeof value.nat_keepalive_interval === 'number'
+                ? Math.round(clamp(value.nat_keepalive_interval, 0, 120))
+                : DEFAULT_AUDIO_SETTINGS.nat_keepalive_interval,
+        send_buffer_limit_bytes:
+            typeof value.send_buffer_limit_bytes === 'number'
+                ? Math.round(clamp(value.send_buffer_limit_bytes, 0, 1048576))
+                : DEFAULT_AUDIO_SETTINGS.send_buffer_limit_bytes,
+        minimal_processing:
+            typeof value.minimal_processing === 'boolean'
+                ? value.minimal_processing
+                : DEFAULT_AUDIO_SETTINGS.minimal_processing,
+        jitter_buffer_ms:
+            typeof value.jitter_buffer_ms === 'number'
+                ? Math.round(clamp(value.jitter_buffer_ms, 20, 200))
+                : DEFAULT_AUDIO_SETTINGS.jitter_buffer_ms,
+        stereo: typeof value.stereo === 'boolean' ? value.stereo : DEFAULT_AUDIO_SETTINGS.stereo,
+        dtx: typeof value.dtx === 'boolean' ? value.dtx : DEFAULT_AUDIO_SETTINGS.dtx,
+        frame_duration_ms:
+            typeof value.frame_duration_ms === 'number' && FRAME_DURATIONS_MS.includes(value.frame_duration_ms)
+                ? value.frame_duration_ms
+                : DEFAULT_AUDIO_SETTINGS.frame_duration_ms,
+        compressor: typeof value.compressor === 'boolean' ? value.compressor : DEFAULT_AUDIO_SETTINGS.compressor,
+        compressor_threshold_db:
+            typeof value.compressor_threshold_db === 'number'
+                ? clamp(value.compressor_threshold_db, -60, 0)
+                : DEFAULT_AUDIO_SETTINGS.compressor_threshold_db,
+        compressor_ratio:
+            typeof value.compressor_ratio === 'number'
+                ? clamp(value.compressor_ratio, 1, 20)
+                : DEFAULT_AUDIO_SETTINGS.compressor_ratio,
+        compressor_attack_ms:
+            typeof value.compressor_attack_ms === 'number'
+                ? clamp(value.compressor_attack_ms, 0, 200)
+                : DEFAULT_AUDIO_SETTINGS.compressor_attack_ms,
+        compressor_release_ms:
+            typeof value.compressor_release_ms === 'number'
+                ? clamp(value.compressor_release_ms, 0, 2000)
+                : DEFAULT_AUDIO_SETTINGS.compressor_release_ms,
     };
 }
 
+// Commands reject with the serialized AppError ({ code, kind, message, ... }).
+function deviceErrorMessage(error: unknown): string {
+    if (error && typeof error === 'object' && 'message' in error) {
+        return String((error as { message: unknown }).message);
+    }
+    return String(error);
+}
+
 function clamp(value: number, min: number, max: number): number {
     return Math.max(min, Math.min(max, value));
 }
@@ -93,6 +255,10 @@ export function CallOverlay() {
     const activeCall = useAppStore((s) => s.activeCall);
     const endCall = useAppStore((s) => s.endCall);
     const cancelOutgoingCall = useAppStore((s) => s.cancelOutgoingCall);
+    const waitingCall = useAppStore((s) => s.waitingCall);
+    const acceptWaitingCall = useAppStore((s) => s.acceptWaitingCall);
+    const declineWaitingCall = useAppStore((s) => s.declineWaitingCall);
+    const wsConnected = useAppStore((s) => s.wsConnected);
 
     const [isMuted, setIsMuted] = useState(false);
     const [callDuration, setCallDuration] = useState(0);
@@ -102,14 +268,36 @@ export function CallOverlay() {
     const [outputDevices, setOutputDevices] = useState<AudioDevice[]>([]);
     const [selectedInputDevice, setSelectedInputDevice] = useState('');
     const [selectedOutputDevice, setSelectedOutputDevice] = useState('');
+    const [selectedRingtoneDevice, setSelectedRingtoneDevice] = useState('');
     const [isLoadingDevices, setIsLoadingDevices] = useState(false);
     const [isSwitchingInput, setIsSwitchingInput] = useState(false);
     const [isSwitchingOutput, setIsSwitchingOutput] = useState(false);
+    const [isSwitchingRingtone, setIsSwitchingRingtone] = useState(false);
+    const [followDefaultDevice, setFollowDefaultDevice] = useState(true);
 
     const [settings, setSettings] = useState<AudioSettings>(DEFAULT_AUDIO_SETTINGS);
+    const [audioProfile, setAudioProfile] = useState<AudioProfile | null>(null);
+    const [peerVolume, setPeerVolume] = useState(1);
     const [isSavingSettings, setIsSavingSettings] = useState(false);
     const [vuLevel, setVuLevel] = useState(0);
+    const [remoteVuLevel, setRemoteVuLevel] = useState(0);
+    // Whether capture is actually transmitting, debounced in the engine
+    const [isSpeaking, setIsSpeaking] = useState(false);
+    // Remote streams currently carrying speech; '' is the 1:1 peer
+    const [remoteSpeaking, setRemoteSpeaking] = useState<Set<string>>(() => new Set());
     const [isPttPressed, setIsPttPressed] = useState(false);
+    const [deviceError, setDeviceError] = useState<string | null>(null);
+    const [sasCode, setSasCode] = useState<string | null>(null);
+    // The key is only ever shown here; without it the recording cannot be opened
+    const [recording, setRecording] = useState<{ path: string; key: string } | null>(null);
+    const [iceGathering, setIceGathering] = useState<string | null>(null);
+    const [soundPath, setSoundPath] = useState('');
+    const [soundReplacesMic, setSoundReplacesMic] = useState(false);
+    const [isPlayingSound, setIsPlayingSound] = useState(false);
+    const [iceConnection, setIceConnection] = useState<string | null>(null);
+    const [callQuality, setCallQuality] = useState<CallQuality>('good');
+    const [connectivity, setConnectivity] = useState<ConnectivityReport | null>(null);
+    const [isTestingIce, setIsTestingIce] = useState(false);
 
     const peerName = typeof activeCall?.peerName === 'string' && activeCall.peerName.trim()
         ? activeCall.peerName
@@ -128,6 +316,82 @@ export function CallOverlay() {
         setCallDuration(0);
     }, [activeCall?.status, activeCall?.startTime]);
 
+    // Keys are exchanged by the time media connects
+    useEffect(() => {
+        if (activeCall?.status !== 'connected') {
+            setSasCode(null);
+            return;
+        }
+        invoke<string | null>('get_sas_code')
+            .then(setSasCode)
+            .catch((e) => console.warn('[CallOverlay] Security code not available:', e));
+    }, [activeCall?.status]);
+
+    // ICE progress of this call, for a finer status than "Connected"
+    useEffect(() => {
+        setIceGathering(null);
+        setIceConnection(null);
+        if (!activeCall?.peerId) return;
+
+        let unlisten: (() => void) | null = null;
+        listen<IceStatePayload>('ice-state', (event) => {
+            if (event.payload.kind === 'gathering') {
+                setIceGathering(event.payload.state);
+            } else {
+                setIceConnection(event.payload.state);
+            }
+        }).then((fn) => {
+            unlisten = fn;
+        });
+        return () => {
+            if (unlisten) unlisten();
+        };
+    }, [activeCall?.peerId]);
+
+    // Settings the engine switched to after an audio params exchange
+    useEffect(() => {
+        let unlisten: (() => void) | null = null;
+        listen<AudioSettings>('audio-settings-changed', (event) => {
+            setSettings(coerceAudioSettings(event.payload));
+        }).then((fn) => {
+            unlisten = fn;
+        });
+        return () => {
+            if (unlisten) unlisten();
+        };
+    }, []);
+
+    // Call quality, already smoothed by the engine so it does not flicker
+    useEffect(() => {
+        setCallQuality('good');
+        if (!activeCall?.peerId) return;
+
+        let unlisten: (() => void) | null = null;
+        listen<CallQuality>('call-quality', (event) => {
+            setCallQuality(event.payload);
+        }).then((fn) => {
+            unlisten = fn;
+        });
+        return () => {
+            if (unlisten) unlisten();
+        };
+    }, [activeCall?.peerId]);
+
+    // Recording ends with the call; the engine finalizes the file on reset
+    useEffect(() => {
+        if (activeCall?.status !== 'connected') {
+            setRecording(null);
+            return;
+        }
+        let unlisten: (() => void) | null = null;
+        listen('recording-stopped', () => setRecording(null)).then((fn) => {
+            unlisten = fn;
+        });
+        return () => {
+            if (unlisten) unlisten();
+        };
+    }, [activeCall?.status]);
+
     // Listen for VU meter events
     useEffect(() => {
         if (activeCall?.status !== 'connected') return;
@@ -140,13 +404,69 @@ export function CallOverlay() {
             unlisten = fn;
         });
 
+        let unlistenRemoteVu: (() => void) | null = null;
+        listen<number>('remote-vu-level', (event) => {
+            setRemoteVuLevel(Math.min(1, event.payload * 6));
+        }).then((fn) => {
+            unlistenRemoteVu = fn;
+        });
+
+        let unlistenSpeaking: (() => void) | null = null;
+        listen<{ active: boolean }>('speaking-changed', (event) => {
+            setIsSpeaking(event.payload.active);
+        }).then((fn) => {
+            unlistenSpeaking = fn;
+        });
+
+        let unlistenRemoteSpeaking: (() => void) | null = null;
+        listen<{ peer_id: string | null; active: boolean }>('remote-speaking-changed', (event) => {
+            const key = event.payload.peer_id ?? '';
+            setRemoteSpeaking((prev) => {
+                const next = new Set(prev);
+                if (event.payload.active) next.add(key);
+                else next.delete(key);
+                return next;
+            });
+        }).then((fn) => {
+            unlistenRemoteSpeaking = fn;
+        });
+
+        let unlistenDeviceError: (() => void) | null = null;
+        listen<{ message: string; kind: string }>('audio-device-error', (event) => {
+            setDeviceError(event.payload.message);
+        }).then((fn) => {
+            unlistenDeviceError = fn;
+        });
+
+        // The lost stream is stopped; picking a device in the settings panel restarts it
+        let unlistenDeviceLost: (() => void) | null = null;
+        listen<{ is_input: boolean }>('audio-device-lost', (event) => {
+            setDeviceError(
+                event.payload.is_input
+                    ? 'Microphone disconnected, select another input device'
+                    : 'Audio output disconnected, select another output device',
+            );
+            setShowSettings(true);
+        }).then((fn) => {
+            unlistenDeviceLost = fn;
+        });
+
         invoke('start_vu_meter').catch((e) => {
             console.warn('[CallOverlay] VU meter not available:', e);
         });
 
         return () => {
             if (unlisten) unlisten();
+            if (unlistenRemoteVu) unlistenRemoteVu();
+            if (unlistenSpeaking) unlistenSpeaking();
+            if (unlistenRemoteSpeaking) unlistenRemoteSpeaking();
+            if (unlistenDeviceError) unlistenDeviceError();
+            if (unlistenDeviceLost) unlistenDeviceLost();
             setVuLevel(0);
+            setRemoteVuLevel(0);
+            setIsSpeaking(false);
+            setRemoteSpeaking(new Set());
+            setDeviceError(null);
         };
     }, [activeCall?.status]);
 
@@ -154,6 +474,11 @@ export function CallOverlay() {
         try {
             const audioSettings = await invoke<AudioSettings>('get_audio_settings');
             setSettings(coerceAudioSettings(audioSettings));
+            setAudioProfile(await invoke<AudioProfile>('get_audio_profile'));
+            if (activeCall?.peerId) {
+                const volume = await invoke<number>('get_peer_volume', { peerId: activeCall.peerId });
+                setPeerVolume(clamp(volume, 0, 2));
+            }
         } catch (e) {
             console.warn('[CallOverlay] Using default audio settings:', e);
             setSettings({ ...DEFAULT_AUDIO_SETTINGS });
@@ -178,13 +503,24 @@ export function CallOverlay() {
                 setSelectedInputDevice(defaultMic?.id || micDevices[0]?.id || '');
             }
 
-            const selectedSpeaker = await invoke<AudioDevice | null>('get_selected_output_device');
-            if (selectedSpeaker?.id && speakerDevices.some((d) => d.id === selectedSpeaker.id)) {
-                setSelectedOutputDevice(selectedSpeaker.id);
-            } else {
-                const defaultSpeaker = await invoke<AudioDevice>('get_default_output_device');
-                setSelectedOutputDevice(defaultSpeaker?.id || speakerDevices[0]?.id || '');
-            }
+            const [selectedSpeaker, selectedRingtone, follow] = await Promise.all([
+                invoke<AudioDevice | null>('get_selected_output_device'),
+                invoke<AudioDevice | null>('get_selected_ringtone_device'),
+                invoke<boolean>('get_auto_device_follow'),
+            ]);
+            setFollowDefaultDevice(follow);
+            const defaultSpeaker = await invoke<AudioDevice>('get_default_output_device').catch(() => null);
+            const fallbackSpeaker = defaultSpeaker?.id || speakerDevices[0]?.id || '';
+            setSelectedOutputDevice(
+                selectedSpeaker?.id && speakerDevices.some((d) => d.id === selectedSpeaker.id)
+                    ? selectedSpeaker.id
+                    : fallbackSpeaker,
+            );
+            setSelectedRingtoneDevice(
+                selectedRingtone?.id && speakerDevices.some((d) => d.id === selectedRingtone.id)
+                    ? selectedRingtone.id
+                    : fallbackSpeaker,
+            );
         } catch (e) {
             console.error('[CallOverlay] Failed to load devices:', e);
         }
@@ -202,7 +538,11 @@ export function CallOverlay() {
         setSettings(normalized);
         setIsSavingSettings(true);
         try {
-            await invoke('update_audio_settings', { settings: normalized });
+            // Frame duration and stereo stay as they are until the peer
+            // agrees, see `audio-settings-changed`
+            const effective = await invoke<AudioSettings>('update_audio_settings', { settings: normalized });
+            setSettings(coerceAudioSettings(effective));
+            setAudioProfile(await invoke<AudioProfile>('get_audio_profile'));
         } catch (e) {
             console.error('[CallOverlay] Failed to update audio settings:', e);
         } finally {
@@ -220,8 +560,10 @@ export function CallOverlay() {
         setIsSwitchingInput(true);
         try {
             await invoke('set_audio_device', { deviceId: nextDeviceId });
+            setDeviceError(null);
         } catch (e) {
             console.error('[CallOverlay] Failed to switch input device:', e);
+            setDeviceError(deviceErrorMessage(e));
             setSelectedInputDevice(previous);
         } finally {
             setIsSwitchingInput(false);
@@ -234,14 +576,43 @@ export function CallOverlay() {
         setIsSwitchingOutput(true);
         try {
             await invoke('set_output_device', { deviceId: nextDeviceId });
+            setDeviceError(null);
         } catch (e) {
             console.error('[CallOverlay] Failed to switch output device:', e);
+            setDeviceError(deviceErrorMessage(e));
             setSelectedOutputDevice(previous);
         } finally {
             setIsSwitchingOutput(false);
         }
     };
 
+    const toggleFollowDefaultDevice = async () => {
+        const next = !followDefaultDevice;
+        setFollowDefaultDevice(next);
+        try {
+            await invoke('set_auto_device_follow', { enabled: next });
+        } catch (e) {
+            console.error('[CallOverlay] Failed to set default device follow:', e);
+            setFollowDefaultDevice(!next);
+        }
+    };
+
+    const switchRingtoneDevice = async (nextDeviceId: string) => {
+        const previous = selectedRingtoneDevice;
+        setSelectedRingtoneDevice(nextDeviceId);
+        setIsSwitchingRingtone(true);
+        try {
+            await invoke('set_ringtone_device', { deviceId: nextDeviceId });
+            setDeviceError(null);
+        } catch (e) {
+            console.error('[CallOverlay] Failed to switch ringtone device:', e);
+            setDeviceError(deviceErrorMessage(e));
+            setSelectedRingtoneDevice(previous);
+        } finally {
+            setIsSwitchingRingtone(false);
+        }
+    };
+
     useEffect(() => {
         if (activeCall?.status !== 'connected') return;
 
@@ -315,25 +686,81 @@ export function CallOverlay() {
         }
     };
 
+    // Plays a WAV file to the peer through the mic path; mute still applies
+    const playSound = async () => {
+        try {
+            const durationMs = await invoke<number>('play_sound_into_call', {
+                path: soundPath.trim(),
+                replaceMic: soundReplacesMic,
+            });
+            setIsPlayingSound(true);
+            setTimeout(() => setIsPlayingSound(false), durationMs);
+        } catch (e) {
+            console.error('[CallOverlay] sound clip failed:', e);
+            setDeviceError(deviceErrorMessage(e));
+        }
+    };
+
+    const stopSound = async () => {
+        await invoke('stop_sound_into_call').catch((e) => console.warn('[CallOverlay] stop sound failed:', e));
+        setIsPlayingSound(false);
+    };
+
+    // Gathers against the configured STUN/TURN servers; takes up to a few seconds
+    const testIceServers = async () => {
+        setIsTestingIce(true);
+        try {
+            setConnectivity(await invoke<ConnectivityReport>('test_ice_servers'));
+        } catch (e) {
+            console.error('[CallOverlay] ICE server check failed:', e);
+            setDeviceError(deviceErrorMessage(e));
+        } finally {
+            setIsTestingIce(false);
+        }
+    };
+
+    const toggleRecording = async () => {
+        try {
+            if (recording) {
+                await invoke('stop_recording');
+                setRecording(null);
+            } else {
+                setRecording(await invoke<{ path: string; key: string }>('start_recording'));
+            }
+        } catch (e) {
+            console.error('[CallOverlay] recording failed:', e);
+            setDeviceError(deviceErrorMessage(e));
+        }
+    };
+
     const toggleDeafen = () => {
         void saveSettings({ ...settings, deafen: !settings.deafen });
     };
 
     const vuWidth = Math.max(2, vuLevel * 100);
+    const remoteVuWidth = Math.max(2, remoteVuLevel * 100);
     const vadPercent = clamp((settings.vad_threshold / 0.3) * 100, 0, 100);
 
     const savingLabel = useMemo(() => {
         if (isSwitchingInput) return 'Switching microphone...';
         if (isSwitchingOutput) return 'Switching output device...';
+        if (isSwitchingRingtone) return 'Switching ringtone device...';
         if (isSavingSettings) return 'Applying audio settings...';
+        if (deviceError) return deviceError;
         return 'Changes apply live during call';
-    }, [isSwitchingInput, isSwitchingOutput, isSavingSettings]);
+    }, [isSwitchingInput, isSwitchingOutput, isSwitchingRingtone, isSavingSettings, deviceError]);
 
     return (
         <div className="fixed top-4 right-4 z-50 w-[420px] max-h-[92vh] bg-surface/95 backdrop-blur-lg rounded-xl border border-white/10 shadow-2xl overflow-hidden flex flex-col">
             <div className="p-4 bg-gradient-to-r from-primary/20 to-secondary/20 border-b border-white/5">
                 <div className="flex items-center gap-3">
-                    <div className="w-12 h-12 rounded-full bg-gradient-to-br from-primary to-secondary flex items-center justify-center flex-shrink-0">
+                    <div
+                        className={`w-12 h-12 rounded-full bg-gradient-to-br from-primary to-secondary flex items-center justify-center flex-shrink-0 transition-shadow ${
+                            remoteSpeaking.has('') || (activeCall.peerId && remoteSpeaking.has(activeCall.peerId))
+                                ? 'ring-2 ring-green-400 shadow-[0_0_12px_rgba(74,222,128,0.6)]'
+                                : ''
+                        }`}
+                    >
                         <span className="text-lg font-bold">{peerInitial}</span>
                     </div>
 
@@ -342,8 +769,31 @@ export function CallOverlay() {
                         <div className="flex items-center gap-2 text-sm">
                             {activeCall.status === 'connected' ? (
                                 <>
-                                    <span className="text-green-400">● Connected</span>
+                                    {iceConnection === 'failed' ? (
+                                        <span className="text-red-400">● Failed</span>
+                                    ) : iceConnection === 'disconnected' ? (
+                                        <span className="text-yellow-400 animate-pulse">● Reconnecting...</span>
+                                    ) : iceConnection === 'new' || iceConnection === 'checking' ? (
+                                        <span className="text-yellow-400 animate-pulse">
+                                            {iceGathering === 'gathering' ? 'Gathering candidates...' : 'Connecting...'}
+                                        </span>
+                                    ) : (
+                                        <span className="text-green-400">● Connected</span>
+                                    )}
+                                    <span
+                                        className={`inline-block w-2 h-2 rounded-full ${CALL_QUALITY_STYLES[callQuality].color}`}
+                                        title={`Call quality: ${CALL_QUALITY_STYLES[callQuality].label}`}
+                                        aria-label={`Call quality: ${CALL_QUALITY_STYLES[callQuality].label}`}
+                                    />
                                     <span className="text-gray-400">{formatDuration(callDuration)}</span>
+                                    {!wsConnected && (
+                                        <span
+                                            className="text-yellow-400"
+                                            title="Audio is peer-to-peer and keeps flowing while the server reconnects"
+                                        >
+                                            Reconnecting to server...
+                                        </span>
+                                    )}
                                 </>
                             ) : activeCall.status === 'calling' ? (
                                 <span className="text-yellow-400 animate-pulse">Calling...</span>
@@ -351,6 +801,26 @@ export function CallOverlay() {
                                 <span className="text-yellow-400 animate-pulse">Connecting...</span>
                             )}
                         </div>
+                        {sasCode && (
+                            <div
+                                className="text-xs text-gray-400"
+                                title={`Read this code aloud with ${peerName}. If yours differ, someone may be intercepting the call.`}
+                            >
+                                Security code:{' '}
+                                <span className="font-mono text-gray-200">
+                                    {sasCode.slice(0, 3)} {sasCode.slice(3)}
+                                </span>
+                            </div>
+                        )}
+                        {recording && (
+                            <div className="text-xs text-red-400" title={recording.path}>
+                                <span className="animate-pulse">● Enregistrement</span> - cle:{' '}
+                                <span className="font-mono text-gray-200 select-all">{recording.key}</span>
+                                <div className="text-[11px] text-gray-500">
+                                    Fichier chiffre; conservez la cle, elle n'est enregistree nulle part.
+                                </div>
+                            </div>
+                        )}
                     </div>
 
                     <button
@@ -363,10 +833,35 @@ export function CallOverlay() {
                 </div>
             </div>
 
+            {waitingCall && (
+                <div className="px-4 py-2 flex items-center gap-3 bg-yellow-500/10 border-b border-white/5 text-sm">
+                    <span className="flex-1 min-w-0 truncate">
+                        <span className="font-semibold">{waitingCall.callerName}</span> is calling...
+                    </span>
+                    <button
+                        onClick={declineWaitingCall}
+                        className="w-8 h-8 rounded-full bg-red-500 hover:bg-red-600 flex items-center justify-center flex-shrink-0"
+                        title="Decline"
+                    >
+                        <PhoneOff className="w-4 h-4" />
+                    </button>
+                    <button
+                        onClick={acceptWaitingCall}
+                        className="w-8 h-8 rounded-full bg-green-500 hover:bg-green-600 flex items-center justify-center flex-shrink-0"
+                        title="End this call and answer"
+                    >
+                        <Phone className="w-4 h-4" />
+                    </button>
+                </div>
+            )}
+
             {activeCall.status === 'connected' && (
                 <div className="px-4 pt-3">
                     <div className="flex items-center gap-2">
-                        <Mic className="w-3 h-3 text-gray-400 flex-shrink-0" />
+                        <Mic
+                            className={`w-3 h-3 flex-shrink-0 ${isSpeaking ? 'text-green-400' : 'text-gray-400'}`}
+                            aria-label={isSpeaking ? 'Speaking' : undefined}
+                        />
                         <div className="flex-1 h-2 bg-white/5 rounded-full overflow-hidden relative">
                             <div
                                 className="h-full rounded-full transition-all duration-75"
@@ -389,6 +884,15 @@ export function CallOverlay() {
                             )}
                         </div>
                     </div>
+                    <div className="flex items-center gap-2 mt-1.5">
+                        <Volume2 className="w-3 h-3 flex-shrink-0 text-gray-400" aria-label="Remote level" />
+                        <div className="flex-1 h-2 bg-white/5 rounded-full overflow-hidden">
+                            <div
+                                className="h-full rounded-full transition-all duration-75 bg-sky-400"
+                                style={{ width: `${remoteVuWidth}%` }}
+                            />
+                        </div>
+                    </div>
                 </div>
             )}
 
@@ -481,8 +985,72 @@ export function CallOverlay() {
                             <Toggle label="Annulation d'echo (AEC)" checked={settings.aec} onToggle={() => updateSetting('aec', !settings.aec)} />
                             <Toggle label="AGC" checked={settings.agc} onToggle={() => updateSetting('agc', !settings.agc)} />
                             <Toggle label="Noise gate" checked={settings.noise_gate} onToggle={() => updateSetting('noise_gate', !settings.noise_gate)} />
+                            <Toggle label="Mode CPU minimal" checked={settings.minimal_processing} onToggle={() => updateSetting('minimal_processing', !settings.minimal_processing)} />
+                            <Toggle label="DTX (economie de bande)" checked={settings.dtx} onToggle={() => updateSetting('dtx', !settings.dtx)} />
                         </div>
 
+                        {settings.agc && !settings.minimal_processing && (
+                            <>
+                                <label className="text-xs text-gray-400 mt-3 block">
+                                    Niveau cible AGC: {(settings.agc_target_rms * 100).toFixed(0)}%
+                                </label>
+                                <input
+                                    type="range"
+                                    min={0.02}
+                                    max={0.5}
+                                    step={0.01}
+                                    value={settings.agc_target_rms}
+                                    onChange={(e) => updateSetting('agc_target_rms', clamp(Number(e.target.value), 0, 0.5))}
+                                    className="w-full accent-primary mt-1"
+                                />
+                                <label className="text-xs text-gray-400 mt-2 block">
+                                    Gain AGC: x{settings.agc_min_gain.toFixed(1)} - x{settings.agc_max_gain.toFixed(1)}
+                                </label>
+                                <div className="flex gap-2">
+                                    <input
+                                        type="range"
+                                        min={0}
+                                        max={1}
+                                        step={0.05}
+                                        value={settings.agc_min_gain}
+                                        onChange={(e) =>
+                                            updateSetting('agc_min_gain', clamp(Number(e.target.value), 0, settings.agc_max_gain))
+                                        }
+                                        className="w-full accent-primary mt-1"
+                                    />
+                                    <input
+                                        type="range"
+                                        min={1}
+                                        max={8}
+                                        step={0.1}
+                                        value={settings.agc_max_gain}
+                                        onChange={(e) => updateSetting('agc_max_gain', clamp(Number(e.target.value), 1, 8))}
+                                        className="w-full accent-primary mt-1"
+                                    />
+                                </div>
+                            </>
+                        )}
+
+                        {settings.noise_suppression && !settings.minimal_processing && (
+                            <>
+                                <label className="text-xs text-gray-400 mt-3 block">Suppression de bruit</label>
+                                <select
+                                    value={settings.noise_suppression_mode}
+                                    onChange={(e) => updateSetting('noise_suppression_mode', e.target.value as NoiseSuppressionMode)}
+                                    className="w-full mt-1 px-3 py-2 bg-white/5 border border-white/10 rounded-lg text-sm focus:outline-none focus:border-primary/50"
+                                >
+                                    <option value="light">Legere (filtre simple)</option>
+                                    <option value="heavy">Forte (ventilateurs, souffle)</option>
+                                </select>
+                            </>
+                        )}
+
+                        {settings.minimal_processing && (
+                            <div className="text-[11px] mt-2 text-gray-500">
+                                Traitements desactives (bruit, AEC, AGC, gate, compresseur, limiter). Seul le gain est applique.
+                            </div>
+                        )}
+
                         {settings.noise_gate && (
                             <>
                                 <label className="text-xs text-gray-400 mt-3 block">
@@ -497,6 +1065,30 @@ export function CallOverlay() {
                                     onChange={(e) => updateSetting('noise_gate_threshold', clamp(Number(e.target.value), 0, 0.2))}
                                     className="w-full accent-orange-400 mt-1"
                                 />
+                                <label className="text-xs text-gray-400 mt-2 block">
+                                    Maintien: {settings.noise_gate_hold_ms} ms
+                                </label>
+                                <input
+                                    type="range"
+                                    min={0}
+                                    max={500}
+                                    step={10}
+                                    value={settings.noise_gate_hold_ms}
+                                    onChange={(e) => updateSetting('noise_gate_hold_ms', Math.round(clamp(Number(e.target.value), 0, 500)))}
+                                    className="w-full accent-orange-400 mt-1"
+                                />
+                                <label className="text-xs text-gray-400 mt-2 block">
+                                    Sensibilite aigus (s, f): x{settings.noise_gate_high_band_bias.toFixed(1)}
+                                </label>
+                                <input
+                                    type="range"
+                                    min={0}
+                                    max={4}
+                                    step={0.1}
+                                    value={settings.noise_gate_high_band_bias}
+                                    onChange={(e) => updateSetting('noise_gate_high_band_bias', clamp(Number(e.target.value), 0, 4))}
+                                    className="w-full accent-orange-400 mt-1"
+                                />
                             </>
                         )}
                     </div>
@@ -527,6 +1119,36 @@ export function CallOverlay() {
                             <ChevronDown className="absolute right-3 top-1/2 -translate-y-1/2 w-4 h-4 text-gray-400 pointer-events-none" />
                         </div>
 
+                        <div className="mb-3">
+                            <Toggle label="Suivre le peripherique par defaut" checked={followDefaultDevice} onToggle={() => void toggleFollowDefaultDevice()} />
+                        </div>
+
+                        <label className="text-xs text-gray-400">Peripherique de sonnerie</label>
+                        <div className="relative mt-1 mb-1">
+                            <select
+                                value={selectedRingtoneDevice}
+                                onChange={(e) => void switchRingtoneDevice(e.target.value)}
+                                className="w-full px-3 py-2 bg-white/5 border border-white/10 rounded-lg text-sm appearance-none cursor-pointer focus:outline-none focus:border-primary/50"
+                                disabled={isLoadingDevices || isSwitchingRingtone}
+                            >
+                                {isLoadingDevices || isSwitchingRingtone ? (
+                                    <option>Loading...</option>
+                                ) : outputDevices.length === 0 ? (
+                                    <option>No output device found</option>
+                                ) : (
+                                    outputDevices.map((device) => (
+                                        <option key={device.id} value={device.id}>
+                                            {device.name}
+                                        </option>
+                                    ))
+                                )}
+                            </select>
+                            <ChevronDown className="absolute right-3 top-1/2 -translate-y-1/2 w-4 h-4 text-gray-400 pointer-events-none" />
+                        </div>
+                        <div className="text-[11px] text-gray-500 mb-3">
+                            Les appels entrants sonnent ici; l'audio de l'appel reste sur la sortie ci-dessus.
+                        </div>
+
                         <label className="text-xs text-gray-400">Volume sortie global: {(settings.output_volume * 100).toFixed(0)}%</label>
                         <input
                             type="range"
@@ -539,22 +1161,48 @@ export function CallOverlay() {
                         />
 
                         <label className="text-xs text-gray-400">
-                            Volume {remoteName}: {(settings.remote_user_volume * 100).toFixed(0)}%
+                            Volume {remoteName}: {(peerVolume * 100).toFixed(0)}%
                         </label>
                         <input
                             type="range"
                             min={0}
                             max={2}
                             step={0.01}
-                            value={settings.remote_user_volume}
+                            value={peerVolume}
                             onChange={(e) => {
                                 const value = clamp(Number(e.target.value), 0, 2);
-                                updateSetting('remote_user_volume', value);
-                                void invoke('set_remote_user_volume', { volume: value }).catch(() => undefined);
+                                setPeerVolume(value);
+                                if (activeCall.peerId) {
+                                    void invoke('set_peer_volume', { peerId: activeCall.peerId, volume: value }).catch(() => undefined);
+                                }
                             }}
                             className="w-full accent-cyan-400 mt-1 mb-3"
                         />
 
+                        <label className="text-xs text-gray-400">Tampon anti-gigue: {settings.jitter_buffer_ms} ms</label>
+                        <input
+                            type="range"
+                            min={20}
+                            max={200}
+                            step={10}
+                            value={settings.jitter_buffer_ms}
+                            onChange={(e) => updateSetting('jitter_buffer_ms', Math.round(clamp(Number(e.target.value), 20, 200)))}
+                            className="w-full accent-primary mt-1 mb-3"
+                        />
+
+                        <label className="text-xs text-gray-400">Taille de trame (latence / bande passante)</label>
+                        <select
+                            value={settings.frame_duration_ms}
+                            onChange={(e) => updateSetting('frame_duration_ms', Number(e.target.value))}
+                            className="w-full mt-1 mb-3 px-3 py-2 bg-white/5 border border-white/10 rounded-lg text-sm focus:outline-none focus:border-primary/50"
+                        >
+                            {FRAME_DURATIONS_MS.map((ms) => (
+                                <option key={ms} value={ms}>
+                                    {ms} ms
+                                </option>
+                            ))}
+                        </select>
+
                         <label className="text-xs text-gray-400">Mode audio</label>
                         <select
                             value={settings.audio_mode}
@@ -564,11 +1212,122 @@ export function CallOverlay() {
                             <option value="headphones">Casque</option>
                             <option value="speakers">Haut-parleurs</option>
                         </select>
+                        {audioProfile && (
+                            <div className="text-[11px] -mt-2 mb-3 text-gray-500">
+                                Actif: AEC {audioProfile.aec ? 'on' : 'off'}, gate{' '}
+                                {audioProfile.noise_gate ? `${(audioProfile.noise_gate_threshold * 100).toFixed(1)}%` : 'off'}, volume max{' '}
+                                {Math.round(audioProfile.max_output_volume * 100)}%
+                            </div>
+                        )}
 
                         <div className="grid grid-cols-2 gap-2 text-xs">
                             <Toggle label="Limiter / protection oreilles" checked={settings.limiter} onToggle={() => updateSetting('limiter', !settings.limiter)} />
                             <Toggle label="Deafen (sortie + micro)" checked={settings.deafen} onToggle={toggleDeafen} />
+                            <Toggle label="Stereo (musique)" checked={settings.stereo} onToggle={() => updateSetting('stereo', !settings.stereo)} />
+                            <Toggle label="Compresseur" checked={settings.compressor} onToggle={() => updateSetting('compressor', !settings.compressor)} />
                         </div>
+
+                        {settings.compressor && (
+                            <div className="mt-3">
+                                <label className="text-xs text-gray-400">Seuil: {settings.compressor_threshold_db.toFixed(0)} dB</label>
+                                <input
+                                    type="range"
+                                    min={-60}
+                                    max={0}
+                                    step={1}
+                                    value={settings.compressor_threshold_db}
+                                    onChange={(e) => updateSetting('compressor_threshold_db', clamp(Number(e.target.value), -60, 0))}
+                                    className="w-full accent-primary mt-1 mb-3"
+                                />
+
+                                <label className="text-xs text-gray-400">Ratio: {settings.compressor_ratio.toFixed(1)}:1</label>
+                                <input
+                                    type="range"
+                                    min={1}
+                                    max={20}
+                                    step={0.5}
+                                    value={settings.compressor_ratio}
+                                    onChange={(e) => updateSetting('compressor_ratio', clamp(Number(e.target.value), 1, 20))}
+                                    className="w-full accent-primary mt-1 mb-3"
+                                />
+
+                                <label className="text-xs text-gray-400">Attaque: {settings.compressor_attack_ms.toFixed(0)} ms</label>
+                                <input
+                                    type="range"
+                                    min={0}
+                                    max={200}
+                                    step={1}
+                                    value={settings.compressor_attack_ms}
+                                    onChange={(e) => updateSetting('compressor_attack_ms', clamp(Number(e.target.value), 0, 200))}
+                                    className="w-full accent-primary mt-1 mb-3"
+                                />
+
+                                <label className="text-xs text-gray-400">Relachement: {settings.compressor_release_ms.toFixed(0)} ms</label>
+                                <input
+                                    type="range"
+                                    min={0}
+                                    max={2000}
+                                    step={10}
+                                    value={settings.compressor_release_ms}
+                                    onChange={(e) => updateSetting('compressor_release_ms', clamp(Number(e.target.value), 0, 2000))}
+                                    className="w-full accent-primary mt-1"
+                                />
+                            </div>
+                        )}
+
+                        {settings.stereo && (
+                            <div className="text-[11px] mt-2 text-gray-500">
+                                Micro et sortie en stereo; le micro s'ouvre en stereo au prochain appel ou changement de peripherique.
+                            </div>
+                        )}
+                    </div>
+
+                    <div>
+                        <div className="text-xs uppercase tracking-wide text-gray-400 mb-2">Soundboard</div>
+
+                        <label className="text-xs text-gray-400">Fichier WAV</label>
+                        <input
+                            type="text"
+                            value={soundPath}
+                            onChange={(e) => setSoundPath(e.target.value)}
+                            placeholder="/chemin/vers/son.wav"
+                            className="w-full mt-1 mb-3 px-3 py-2 bg-white/5 border border-white/10 rounded-lg text-sm focus:outline-none focus:border-primary/50"
+                        />
+
+                        <div className="grid grid-cols-2 gap-2 text-xs">
+                            <Toggle label="Remplacer le micro" checked={soundReplacesMic} onToggle={() => setSoundReplacesMic(!soundReplacesMic)} />
+                            <button
+                                onClick={() => void (isPlayingSound ? stopSound() : playSound())}
+                                disabled={activeCall.status !== 'connected' || (!isPlayingSound && !soundPath.trim())}
+                                className="px-2 py-2 rounded-lg border border-white/10 bg-white/5 hover:bg-white/10 transition disabled:opacity-40"
+                            >
+                                {isPlayingSound ? 'Arreter' : 'Jouer dans l\'appel'}
+                            </button>
+                        </div>
+                    </div>
+
+                    <div>
+                        <div className="text-xs uppercase tracking-wide text-gray-400 mb-2">Serveurs ICE</div>
+
+                        <button
+                            onClick={() => void testIceServers()}
+                            disabled={isTestingIce}
+                            className="w-full px-2 py-2 mb-2 rounded-lg border border-white/10 bg-white/5 hover:bg-white/10 transition text-xs disabled:opacity-40"
+                        >
+                            {isTestingIce ? 'Test en cours...' : 'Tester STUN / TURN'}
+                        </button>
+
+                        {connectivity && (
+                            <div className="grid grid-cols-2 gap-2 text-xs">
+                                <IceCheck label="STUN" ok={connectivity.stun} configured={connectivity.stun_configured} />
+                                <IceCheck label="TURN" ok={connectivity.turn} configured={connectivity.turn_configured} />
+                                <div className="col-span-2 text-gray-500">
+                                    {connectivity.timed_out
+                                        ? `Delai depasse apres ${(connectivity.elapsed_ms / 1000).toFixed(1)} s`
+                                        : `Termine en ${(connectivity.elapsed_ms / 1000).toFixed(1)} s`}
+                                </div>
+                            </div>
+                        )}
                     </div>
 
                     <div className="text-xs text-gray-500">
@@ -600,6 +1359,18 @@ export function CallOverlay() {
                     {settings.deafen ? <VolumeX className="w-5 h-5" /> : <Ear className="w-5 h-5" />}
                 </button>
 
+                <button
+                    onClick={() => void toggleRecording()}
+                    disabled={activeCall.status !== 'connected'}
+                    className={`w-12 h-12 rounded-full flex items-center justify-center transition disabled:opacity-40 ${recording
+                        ? 'bg-red-500/20 text-red-400 hover:bg-red-500/30'
+                        : 'bg-white/10 hover:bg-white/20'
+                        }`}
+                    title={recording ? 'Stop recording' : 'Record call'}
+                >
+                    {recording ? <Square className="w-5 h-5" /> : <Circle className="w-5 h-5" />}
+                </button>
+
                 <button
                     onClick={handleEndCall}
                     className="w-12 h-12 rounded-full bg-red-500 hover:bg-red-600 flex items-center justify-center transition shadow-lg"
@@ -624,6 +1395,20 @@ export function CallOverlay() {
     );
 }
 
+function IceCheck({ label, ok, configured }: { label: string; ok: boolean; configured: boolean }) {
+    const [color, status] = ok
+        ? ['text-green-400', 'OK']
+        : configured
+            ? ['text-red-400', 'Echec']
+            : ['text-gray-500', 'Non configure'];
+    return (
+        <div className="px-2 py-2 rounded-lg border border-white/10 bg-white/5 flex justify-between">
+            <span>{label}</span>
+            <span className={color}>{ok ? '✓' : configured ? '✗' : '–'} {status}</span>
+        </div>
+    );
+}
+
 function Toggle({ label, checked, onToggle }: { label: string; checked: boolean; onToggle: () => void }) {
     return (
         <button
@@ -1,5 +1,6 @@
-import { useState } from 'react';
-import { Hash, Volume2, Plus, Settings, ChevronDown, Mic, MicOff, LogOut, PhoneCall, PhoneOff } from 'lucide-react';
+import { useEffect, useState } from 'react';
+import { Hash, Volume2, Plus, Settings, ChevronDown, Mic, MicOff, LogOut, PhoneCall, PhoneOff, Headphones } from 'lucide-react';
+import { listen } from '@tauri-apps/api/event';
 import { useAppStore } from '../store';
 import type { Channel } from '../types';
 
@@ -98,6 +99,7 @@ export function ChannelList() {
     const activeChannel = useAppStore((s) => s.activeChannel);
     const setActiveChannel = useAppStore((s) => s.setActiveChannel);
     const voicePresenceByChannel = useAppStore((s) => s.voicePresenceByChannel);
+    const voiceListenersByChannel = useAppStore((s) => s.voiceListenersByChannel);
     const activeVoiceChannel = useAppStore((s) => s.activeVoiceChannel);
     const joinVoiceChannel = useAppStore((s) => s.joinVoiceChannel);
     const leaveVoiceChannel = useAppStore((s) => s.leaveVoiceChannel);
@@ -107,7 +109,28 @@ export function ChannelList() {
     const isOwner = currentServer?.owner_id === user?.id;
 
     const logout = useAppStore((s) => s.logout);
+    const reportVoiceState = useAppStore((s) => s.reportVoiceState);
     const [isMuted, setIsMuted] = useState(false);
+    const [isSpeaking, setIsSpeaking] = useState(false);
+
+    useEffect(() => {
+        if (!activeVoiceChannel) return;
+        let unlisten: (() => void) | null = null;
+        listen<{ active: boolean }>('speaking-changed', (event) => {
+            setIsSpeaking(event.payload.active);
+        }).then((fn) => {
+            unlisten = fn;
+        });
+        return () => {
+            unlisten?.();
+            setIsSpeaking(false);
+        };
+    }, [activeVoiceChannel]);
+
+    useEffect(() => {
+        if (!activeVoiceChannel) return;
+        reportVoiceState({ muted: isMuted, speaking: isSpeaking && !isMuted });
+    }, [activeVoiceChannel, isMuted, isSpeaking, reportVoiceState]);
 
     const textChannels = channels.filter((c) => c.channel_type === 'text');
     const voiceChannels = channels.filter((c) => c.channel_type === 'voice');
@@ -173,8 +196,10 @@ export function ChannelList() {
                                     key={channel.id}
                                     channel={channel}
                                     participantCount={(voicePresenceByChannel[channel.id] || []).length}
+                                    listenerCount={(voiceListenersByChannel[channel.id] || []).length}
                                     joined={activeVoiceChannel === channel.id}
                                     onJoin={() => joinVoiceChannel(activeServer, channel.id)}
+                                    onListen={() => joinVoiceChannel(activeServer, channel.id, 'listen')}
                                     onLeave={() => leaveVoiceChannel(activeServer, channel.id)}
                                 />
                             ))}
@@ -223,14 +248,18 @@ export function ChannelList() {
 function VoiceChannelButton({
     channel,
     participantCount,
+    listenerCount,
     joined,
     onJoin,
+    onListen,
     onLeave,
 }: {
     channel: Channel;
     participantCount: number;
+    listenerCount: number;
     joined: boolean;
     onJoin: () => void;
+    onListen: () => void;
     onLeave: () => void;
 }) {
     return (
@@ -243,6 +272,21 @@ function VoiceChannelButton({
             <Volume2 className="w-4 h-4 flex-shrink-0" />
             <span className="truncate flex-1">{channel.name}</span>
             <span className="text-xs text-gray-400">{participantCount}</span>
+            {listenerCount > 0 && (
+                <span className="flex items-center gap-0.5 text-xs text-gray-500" title={`${listenerCount} listening`}>
+                    <Headphones className="w-3 h-3" />
+                    {listenerCount}
+                </span>
+            )}
+            {!joined && (
+                <button
+                    onClick={onListen}
+                    className="p-1 rounded transition hover:bg-white/10 text-gray-300"
+                    title="Join listen-only"
+                >
+                    <Headphones className="w-4 h-4" />
+                </button>
+            )}
             <button
                 onClick={joined ? onLeave : onJoin}
                 className={`p-1 rounded transition ${joined ? 'hover:bg-red-500/20 text-red-300' : 'hover:bg-green-500/20 text-green-300'}`}
@@ -1,3 +1,4 @@
+import { useEffect, useState } from 'react';
 import { Phone, PhoneOff } from 'lucide-react';
 import { useAppStore } from '../store';
 
@@ -6,9 +7,22 @@ export function IncomingCallModal() {
     const acceptIncomingCall = useAppStore((s) => s.acceptIncomingCall);
     const declineIncomingCall = useAppStore((s) => s.declineIncomingCall);
 
+    const ringDeadline = activeCall?.status === 'ringing' ? activeCall.ringDeadline ?? null : null;
+    const [now, setNow] = useState(() => Date.now());
+
+    // Tick the countdown while ringing
+    useEffect(() => {
+        if (ringDeadline === null) return;
+        setNow(Date.now());
+        const interval = setInterval(() => setNow(Date.now()), 1000);
+        return () => clearInterval(interval);
+    }, [ringDeadline]);
+
     // Only show for ringing state
     if (!activeCall || activeCall.status !== 'ringing') return null;
 
+    const secondsLeft = ringDeadline !== null ? Math.max(0, Math.ceil((ringDeadline - now) / 1000)) : null;
+
     return (
         <div className="fixed inset-0 bg-black/80 backdrop-blur-md flex items-center justify-center z-50">
             <div className="bg-surface rounded-2xl p-8 w-80 text-center border border-white/10 shadow-2xl">
@@ -25,7 +39,13 @@ export function IncomingCallModal() {
 
                 {/* Caller name */}
                 <h2 className="text-xl font-bold mb-2">{activeCall.peerName}</h2>
-                <p className="text-gray-400 mb-8">Incoming voice call...</p>
+                {activeCall.peerStatus && (
+                    <p className="text-sm text-gray-300 italic mb-2 truncate">“{activeCall.peerStatus}”</p>
+                )}
+                <p className="text-gray-400 mb-8">
+                    Incoming voice call...
+                    {secondsLeft !== null && <span className="ml-1 tabular-nums">({secondsLeft}s)</span>}
+                </p>
 
                 {/* Action buttons */}
                 <div className="flex justify-center gap-6">
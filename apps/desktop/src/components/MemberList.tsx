@@ -1,12 +1,25 @@
-import { Crown, Shield } from 'lucide-react';
+import { Crown, Hand, MicOff, Shield } from 'lucide-react';
 import { useAppStore } from '../store';
+import type { VoiceParticipantState } from '../types';
 
 export function MemberList() {
     const serverMembers = useAppStore((s) => s.serverMembers);
     const activeServer = useAppStore((s) => s.activeServer);
+    const voiceStatesByUser = useAppStore((s) => s.voiceStatesByUser);
+    const voicePresenceByChannel = useAppStore((s) => s.voicePresenceByChannel);
+    const activeVoiceChannel = useAppStore((s) => s.activeVoiceChannel);
+    const nudgeVoiceParticipant = useAppStore((s) => s.nudgeVoiceParticipant);
+    const user = useAppStore((s) => s.user);
 
     if (!activeServer) return null;
 
+    // Only someone in our own voice channel can be nudged
+    const inMyVoiceChannel = activeVoiceChannel ? voicePresenceByChannel[activeVoiceChannel] || [] : [];
+    const nudgeHandler = (userId: string) =>
+        activeVoiceChannel && userId !== user?.id && inMyVoiceChannel.includes(userId)
+            ? () => void nudgeVoiceParticipant(activeServer, activeVoiceChannel, userId)
+            : undefined;
+
     // Group members by role
     const owners = serverMembers.filter((m) => m.role === 'owner');
     const admins = serverMembers.filter((m) => m.role === 'admin');
@@ -38,7 +51,13 @@ export function MemberList() {
                             Online — {onlineMembers.length}
                         </div>
                         {onlineMembers.map((member) => (
-                            <MemberItem key={member.user_id} member={member} isOnline={true} />
+                            <MemberItem
+                                key={member.user_id}
+                                member={member}
+                                isOnline={true}
+                                voiceState={voiceStatesByUser[member.user_id]}
+                                onNudge={nudgeHandler(member.user_id)}
+                            />
                         ))}
                     </div>
                 )}
@@ -50,7 +69,13 @@ export function MemberList() {
                             Offline — {offlineMembers.length}
                         </div>
                         {offlineMembers.map((member) => (
-                            <MemberItem key={member.user_id} member={member} isOnline={false} />
+                            <MemberItem
+                                key={member.user_id}
+                                member={member}
+                                isOnline={false}
+                                voiceState={voiceStatesByUser[member.user_id]}
+                                onNudge={nudgeHandler(member.user_id)}
+                            />
                         ))}
                     </div>
                 )}
@@ -62,9 +87,15 @@ export function MemberList() {
 function MemberItem({
     member,
     isOnline,
+    voiceState,
+    onNudge,
 }: {
     member: { user_id: string; username: string; avatar_url: string | null; role: string };
     isOnline: boolean;
+    /** Set while they are in a voice channel */
+    voiceState?: VoiceParticipantState;
+    /** Set while they are in our voice channel */
+    onNudge?: () => void;
 }) {
     const getRoleIcon = () => {
         switch (member.role) {
@@ -83,7 +114,10 @@ function MemberItem({
                 }`}
         >
             <div className="relative">
-                <div className="w-8 h-8 rounded-full bg-gradient-to-br from-primary to-secondary" />
+                <div
+                    className={`w-8 h-8 rounded-full bg-gradient-to-br from-primary to-secondary ${voiceState?.speaking ? 'ring-2 ring-green-400' : ''
+                        }`}
+                />
                 <div
                     className={`absolute bottom-0 right-0 w-3 h-3 rounded-full border-2 border-surface ${isOnline ? 'bg-green-500' : 'bg-gray-500'
                         }`}
@@ -93,8 +127,25 @@ function MemberItem({
                 <div className="flex items-center gap-1">
                     <span className="text-sm truncate">{member.username}</span>
                     {getRoleIcon()}
+                    {voiceState?.muted && (
+                        <span title="Muted">
+                            <MicOff className="w-3 h-3 text-red-400" />
+                        </span>
+                    )}
                 </div>
+                {member.status_message && (
+                    <div className="text-xs text-gray-400 truncate">{member.status_message}</div>
+                )}
             </div>
+            {onNudge && (
+                <button
+                    onClick={onNudge}
+                    className="p-1 rounded text-gray-400 transition hover:bg-white/10 hover:text-amber-300"
+                    title={`Nudge ${member.username}`}
+                >
+                    <Hand className="w-4 h-4" />
+                </button>
+            )}
         </div>
     );
 }
@@ -7,6 +7,15 @@ interface CreateServerModalProps {
     onClose: () => void;
 }
 
+function MentionBadge({ count }: { count: number }) {
+    if (count === 0) return null;
+    return (
+        <div className="absolute -bottom-1 -right-1 bg-red-500 text-white text-[10px] font-bold rounded-full min-w-[18px] h-[18px] flex items-center justify-center px-1 border-2 border-[#0d0d12]">
+            {count > 99 ? '99+' : count}
+        </div>
+    );
+}
+
 function CreateServerModal({ isOpen, onClose }: CreateServerModalProps) {
     const [mode, setMode] = useState<'create' | 'join'>('create');
     const [name, setName] = useState('');
@@ -108,6 +117,11 @@ export function ServerSidebar() {
     const servers = useAppStore((s) => s.servers);
     const activeServer = useAppStore((s) => s.activeServer);
     const setActiveServer = useAppStore((s) => s.setActiveServer);
+    const unreadMentions = useAppStore((s) => s.unreadMentions);
+
+    const dmMentionCount = unreadMentions.filter((m) => m.room_id).length;
+    const serverMentionCount = (serverId: string) =>
+        unreadMentions.filter((m) => m.server_id === serverId).length;
 
     return (
         <>
@@ -115,13 +129,14 @@ export function ServerSidebar() {
                 {/* DM Button */}
                 <button
                     onClick={() => setActiveServer(null)}
-                    className={`w-12 h-12 rounded-2xl flex items-center justify-center transition-all duration-200 ${activeServer === null
+                    className={`w-12 h-12 rounded-2xl flex items-center justify-center transition-all duration-200 relative ${activeServer === null
                             ? 'bg-primary rounded-xl'
                             : 'bg-surface hover:bg-primary/20 hover:rounded-xl'
                         }`}
                     title="Direct Messages"
                 >
                     <MessageCircle className="w-5 h-5" />
+                    <MentionBadge count={dmMentionCount} />
                 </button>
 
                 <div className="w-8 h-0.5 bg-white/10 rounded-full my-1" />
@@ -154,6 +169,8 @@ export function ServerSidebar() {
                             <div className="absolute left-0 w-1 h-8 bg-white rounded-r-full -translate-x-3" />
                         )}
 
+                        <MentionBadge count={serverMentionCount(server.id)} />
+
                         {/* Tooltip */}
                         <div className="absolute left-full ml-4 px-3 py-2 bg-black rounded-lg text-sm font-medium whitespace-nowrap opacity-0 group-hover:opacity-100 pointer-events-none transition-opacity z-50">
                             {server.name}
@@ -0,0 +1,43 @@
+import { useEffect } from 'react';
+import { Hand, X } from 'lucide-react';
+import { useAppStore } from '../store';
+
+// How long a nudge stays on screen unless dismissed
+const NUDGE_TOAST_MS = 6000;
+
+export function VoiceNudgeToast() {
+    const nudge = useAppStore((s) => s.voiceNudge);
+    const dismiss = useAppStore((s) => s.dismissVoiceNudge);
+    const channels = useAppStore((s) => s.channels);
+
+    useEffect(() => {
+        if (!nudge) return;
+        const timer = window.setTimeout(dismiss, NUDGE_TOAST_MS);
+        return () => window.clearTimeout(timer);
+    }, [nudge, dismiss]);
+
+    if (!nudge) return null;
+
+    const channelName = channels.find((c) => c.id === nudge.channelId)?.name;
+
+    return (
+        <div
+            role="status"
+            className="fixed top-4 right-4 z-[120] flex w-[min(92vw,22rem)] items-center gap-3 rounded-xl border border-zinc-700 bg-zinc-900/95 p-3 shadow-xl backdrop-blur"
+        >
+            <Hand className="w-5 h-5 flex-shrink-0 text-amber-300" />
+            <p className="flex-1 text-sm text-white">
+                <span className="font-semibold">{nudge.fromUsername || 'Someone'}</span> nudged you
+                {channelName ? ` in ${channelName}` : ''}
+            </p>
+            <button
+                type="button"
+                onClick={dismiss}
+                className="p-1 rounded text-zinc-400 transition hover:bg-white/10 hover:text-white"
+                title="Dismiss"
+            >
+                <X className="w-4 h-4" />
+            </button>
+        </div>
+    );
+}
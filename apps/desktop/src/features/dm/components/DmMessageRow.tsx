@@ -1,5 +1,6 @@
 import { Check, Copy, Pencil, Trash2, X } from 'lucide-react';
 import { MessageContent } from '../../../components/MessageContent';
+import { useMessageFilter } from '../../../hooks/useMessageFilter';
 import type { Message } from '../../../types';
 
 interface DmMessageRowProps {
@@ -35,6 +36,8 @@ export function DmMessageRow({
     onSaveEditedMessage,
     onCancelEditingMessage,
 }: DmMessageRowProps) {
+    const redact = useMessageFilter();
+
     return (
         <div
             className="flex group hover:bg-white/[0.02] -mx-4 px-4 py-1.5 transition relative"
@@ -94,7 +97,7 @@ export function DmMessageRow({
                     </div>
                 ) : (
                     <div className="text-gray-300">
-                        <MessageContent content={displayContent} isEncrypted={!!message.nonce} />
+                        <MessageContent content={redact(displayContent)} isEncrypted={!!message.nonce} />
                     </div>
                 )}
             </div>
@@ -58,6 +58,7 @@ export function DmSidebar({
                     const unreadCount = getUnreadCount(friend.id);
                     const isActive = activeDM === friend.id;
                     const isOnline = onlineFriends.includes(friend.id);
+                    const isDnd = isOnline && friend.presence_status === 'dnd';
 
                     return (
                         <button
@@ -70,14 +71,17 @@ export function DmSidebar({
                         >
                             <div className="relative flex-shrink-0">
                                 <div className="w-12 h-12 rounded-full bg-gradient-to-br from-primary to-secondary" />
-                                <div className={`absolute bottom-0 right-0 w-3.5 h-3.5 rounded-full border-2 border-surface ${isOnline ? 'bg-green-500' : 'bg-gray-500'}`} />
+                                <div className={`absolute bottom-0 right-0 w-3.5 h-3.5 rounded-full border-2 border-surface ${isDnd ? 'bg-red-500' : isOnline ? 'bg-green-500' : 'bg-gray-500'}`} />
                             </div>
 
                             <div className="flex-1 text-left">
                                 <div className="font-medium text-sm">{friend.username}</div>
-                                <div className={`text-xs ${isOnline ? 'text-green-400' : 'text-gray-500'}`}>
-                                    {isOnline ? 'Online' : 'Offline'}
+                                <div className={`text-xs ${isDnd ? 'text-red-400' : isOnline ? 'text-green-400' : 'text-gray-500'}`}>
+                                    {isDnd ? 'Do not disturb' : isOnline ? 'Online' : 'Offline'}
                                 </div>
+                                {friend.status_message && (
+                                    <div className="text-xs text-gray-400 truncate">{friend.status_message}</div>
+                                )}
                             </div>
 
                             {unreadCount > 0 && (
@@ -1,4 +1,5 @@
-import { SmilePlus } from 'lucide-react';
+import { Pin, SmilePlus, Trash2 } from 'lucide-react';
+import { useMessageFilter } from '../../../hooks/useMessageFilter';
 import type { ChannelMessage, MessageReaction } from '../../../types';
 
 interface ServerChatMessageRowProps {
@@ -8,6 +9,11 @@ interface ServerChatMessageRowProps {
     reactions: MessageReaction[];
     onToggleReaction: (emoji: string) => void;
     onQuickReaction: () => void;
+    pinned: boolean;
+    /** Only set for members allowed to pin (owner/admin) */
+    onTogglePin?: () => void;
+    /** Set for the sender and for owners/admins */
+    onDelete?: () => void;
 }
 
 export function ServerChatMessageRow({
@@ -17,7 +23,12 @@ export function ServerChatMessageRow({
     reactions,
     onToggleReaction,
     onQuickReaction,
+    pinned,
+    onTogglePin,
+    onDelete,
 }: ServerChatMessageRowProps) {
+    const redact = useMessageFilter();
+
     return (
         <div className="flex gap-3 hover:bg-white/5 p-2 rounded-lg">
             <div className="w-10 h-10 rounded-full bg-gradient-to-br from-primary to-secondary flex-shrink-0" />
@@ -28,9 +39,10 @@ export function ServerChatMessageRow({
                     {message.status && message.sender_id === userId && (
                         <span className="text-xs text-gray-500 capitalize">{message.status}</span>
                     )}
+                    {pinned && <Pin className="w-3 h-3 text-yellow-400" aria-label="Pinned" />}
                 </div>
 
-                <p className="text-gray-200">{message.content}</p>
+                <p className="text-gray-200">{redact(message.content)}</p>
 
                 <div className="mt-2 flex items-center flex-wrap gap-1.5">
                     {reactions.map((reaction) => {
@@ -60,6 +72,26 @@ export function ServerChatMessageRow({
                             <SmilePlus className="w-4 h-4" />
                         </button>
                     )}
+
+                    {onTogglePin && !message.id.startsWith('local-') && (
+                        <button
+                            onClick={onTogglePin}
+                            className="p-1 rounded-full text-gray-400 hover:text-white hover:bg-white/10 transition"
+                            title={pinned ? 'Unpin message' : 'Pin message'}
+                        >
+                            <Pin className="w-4 h-4" />
+                        </button>
+                    )}
+
+                    {onDelete && !message.id.startsWith('local-') && (
+                        <button
+                            onClick={onDelete}
+                            className="p-1 rounded-full text-gray-400 hover:text-red-400 hover:bg-white/10 transition"
+                            title="Delete message"
+                        >
+                            <Trash2 className="w-4 h-4" />
+                        </button>
+                    )}
                 </div>
             </div>
         </div>
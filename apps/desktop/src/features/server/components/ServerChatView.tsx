@@ -1,7 +1,8 @@
 import { useEffect, useRef, useState } from 'react';
-import { Hash, Send } from 'lucide-react';
+import { Hash, Pin, Send } from 'lucide-react';
 import { invoke } from '@tauri-apps/api/core';
 import { useAppStore } from '../../../store';
+import { useMessageFilter } from '../../../hooks/useMessageFilter';
 import type { ChannelMessage, MessageReaction } from '../../../types';
 import { ServerChatMessageRow } from './ServerChatMessageRow';
 
@@ -20,6 +21,11 @@ export function ServerChatView() {
     const loadOlderChannelMessages = useAppStore((s) => s.loadOlderChannelMessages);
     const fetchChannelMessageReactions = useAppStore((s) => s.fetchChannelMessageReactions);
     const toggleChannelReaction = useAppStore((s) => s.toggleChannelReaction);
+    const channelPins = useAppStore((s) => s.channelPins);
+    const fetchChannelPins = useAppStore((s) => s.fetchChannelPins);
+    const toggleChannelPin = useAppStore((s) => s.toggleChannelPin);
+    const deleteChannelMessage = useAppStore((s) => s.deleteChannelMessage);
+    const redact = useMessageFilter();
 
     const [input, setInput] = useState('');
     const messagesContainerRef = useRef<HTMLDivElement>(null);
@@ -37,6 +43,14 @@ export function ServerChatView() {
     const typingMemberName = typingUserIds
         .filter((id) => id !== user?.id)
         .map((id) => serverMembers.find((m) => m.user_id === id)?.username || 'Someone')[0];
+    const pins = channelPins[activeChannel] || [];
+    const pinnedIds = new Set(pins.map((pin) => pin.message_id));
+    const myRole = serverMembers.find((m) => m.user_id === user?.id)?.role;
+    const canModerate = myRole === 'owner' || myRole === 'admin';
+
+    useEffect(() => {
+        void fetchChannelPins(activeServer, activeChannel);
+    }, [activeServer, activeChannel, fetchChannelPins]);
 
     useEffect(() => {
         const isTyping = input.trim().length > 0;
@@ -127,6 +141,15 @@ export function ServerChatView() {
             <div className="h-14 px-4 flex items-center border-b border-white/5">
                 <Hash className="w-5 h-5 text-gray-400 mr-2" />
                 <h2 className="font-bold">{channel?.name || 'Channel'}</h2>
+                {pins.length > 0 && (
+                    <span
+                        className="ml-auto flex items-center gap-1 text-xs text-gray-400"
+                        title={pins.map((pin) => `${pin.sender_username || 'Member'}: ${redact(pin.content)}`).join('\n')}
+                    >
+                        <Pin className="w-3.5 h-3.5" />
+                        {pins.length} pinned
+                    </span>
+                )}
             </div>
 
             <div
@@ -203,6 +226,11 @@ export function ServerChatView() {
                         reactions={renderMessageReactions(message)}
                         onToggleReaction={(emoji) => toggleChannelReaction(activeServer, activeChannel, message.id, emoji)}
                         onQuickReaction={() => toggleChannelReaction(activeServer, activeChannel, message.id, '👍')}
+                        pinned={pinnedIds.has(message.id)}
+                        onTogglePin={canModerate ? () => toggleChannelPin(activeServer, activeChannel, message.id) : undefined}
+                        onDelete={canModerate || message.sender_id === user?.id
+                            ? () => deleteChannelMessage(activeServer, activeChannel, message.id)
+                            : undefined}
                     />
                 ))}
 
@@ -1,11 +1,12 @@
 import { useEffect } from 'react';
-import { invoke } from '@tauri-apps/api/core';
 import { useAppStore } from '../store';
 
 export function useAppStartup() {
     const isAuthenticated = useAppStore((s) => s.isAuthenticated);
     const fetchFriends = useAppStore((s) => s.fetchFriends);
     const fetchServers = useAppStore((s) => s.fetchServers);
+    const fetchFilteredWords = useAppStore((s) => s.fetchFilteredWords);
+    const fetchUnreadMentions = useAppStore((s) => s.fetchUnreadMentions);
 
     useEffect(() => {
         if (!isAuthenticated) {
@@ -14,6 +15,7 @@ export function useAppStartup() {
 
         fetchFriends();
         fetchServers();
-        invoke<number>('api_drain_outbox', { limit: 200 }).catch(() => undefined);
-    }, [isAuthenticated, fetchFriends, fetchServers]);
+        fetchFilteredWords();
+        fetchUnreadMentions();
+    }, [isAuthenticated, fetchFriends, fetchServers, fetchFilteredWords, fetchUnreadMentions]);
 }
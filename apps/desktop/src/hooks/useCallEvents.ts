@@ -2,7 +2,13 @@ import { useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useAppStore } from '../store';
-import type { CallAcceptedPayload, CallUnavailablePayload, IncomingCallPayload } from '../types';
+import type {
+    CallAcceptedPayload,
+    CallStateSnapshotPayload,
+    CallUnavailablePayload,
+    IceStatePayload,
+    IncomingCallPayload,
+} from '../types';
 
 interface WebRtcOfferPayload {
     peerId: string;
@@ -15,6 +21,25 @@ interface WebRtcAnswerPayload {
 
 type WebRtcCandidatePayload = Record<string, unknown>;
 
+interface CallAudioParams {
+    frameDurationMs: number;
+    channels: number;
+}
+
+interface AudioParamsOfferPayload {
+    peerId: string;
+    params: CallAudioParams;
+}
+
+interface AudioParamsAnswerPayload {
+    peerId: string;
+    accepted: boolean;
+    params: CallAudioParams;
+}
+
+// How long an ICE restart may take to reconnect before the call is dropped
+const ICE_RESTART_TIMEOUT_MS = 15000;
+
 export function useCallEvents() {
     const isAuthenticated = useAppStore((s) => s.isAuthenticated);
     const handleIncomingCall = useAppStore((s) => s.handleIncomingCall);
@@ -117,7 +142,13 @@ export function useCallEvents() {
                 resetActiveCall();
             });
 
-            const unlistenCancelled = await listen<string>('call-cancelled', () => {
+            const unlistenCancelled = await listen<string>('call-cancelled', (event) => {
+                // A waiting call going away leaves the current call alone
+                if (useAppStore.getState().waitingCall?.callerId === event.payload) {
+                    console.log('[App] 🚫 Waiting call withdrawn by', event.payload);
+                    useAppStore.setState({ waitingCall: null });
+                    return;
+                }
                 console.log('[App] 🚫 Call cancelled by caller');
                 resetActiveCall();
             });
@@ -127,7 +158,142 @@ export function useCallEvents() {
                 resetActiveCall();
             });
 
+            // Answer to the query sent after every identify: line the UI up
+            // with the server, e.g. after a restart mid-call
+            const unlistenSnapshot = await listen<CallStateSnapshotPayload>('call-state-snapshot', (event) => {
+                const { activePeer, pendingPeer } = event.payload;
+                const { activeCall } = useAppStore.getState();
+
+                if (!activeCall) {
+                    // Keys and media did not survive; end what the server still holds
+                    const serverPeer = activePeer ?? pendingPeer;
+                    if (serverPeer) {
+                        console.warn('[Call] Server still has a call with', serverPeer, '- ending it');
+                        invoke('end_call', { peerId: serverPeer }).catch(() => undefined);
+                    }
+                    return;
+                }
+
+                const ringing = activeCall.status === 'calling' || activeCall.status === 'ringing';
+                const serverPeer = ringing ? pendingPeer : activePeer;
+                if (serverPeer !== activeCall.peerId) {
+                    console.warn('[Call] Server no longer has our call with', activeCall.peerId);
+                    resetActiveCall();
+                }
+            });
+
+            // A failed path is first given an ICE restart; the call only ends
+            // if the restart does not reconnect in time
+            let restartTimer: ReturnType<typeof setTimeout> | null = null;
+
+            const clearRestart = () => {
+                if (restartTimer) {
+                    clearTimeout(restartTimer);
+                    restartTimer = null;
+                }
+            };
+
+            const recoverCall = async () => {
+                const { activeCall, user } = useAppStore.getState();
+                if (!activeCall || restartTimer) {
+                    return;
+                }
+                restartTimer = setTimeout(async () => {
+                    restartTimer = null;
+                    console.warn('[WEBRTC] ICE restart did not reconnect, ending call');
+                    await endCall();
+                }, ICE_RESTART_TIMEOUT_MS);
+
+                // Only one side sends the restart offer, so the two offers
+                // never cross; the other side answers it
+                if (user && user.id < activeCall.peerId) {
+                    console.warn('[WEBRTC] Connection failed, restarting ICE');
+                    try {
+                        await invoke('restart_ice', { targetId: activeCall.peerId });
+                    } catch (error) {
+                        console.error('[WEBRTC] ICE restart failed:', error);
+                        clearRestart();
+                        await endCall();
+                    }
+                } else {
+                    console.warn('[WEBRTC] Connection failed, waiting for ICE restart');
+                }
+            };
+
+            const unlistenRestart = await listen<WebRtcOfferPayload>('call-restart', async (event) => {
+                console.log('[WEBRTC] Received ICE restart, answering...');
+                try {
+                    await invoke('handle_ice_restart', {
+                        targetId: event.payload.peerId,
+                        sdp: event.payload.sdp,
+                    });
+                } catch (error) {
+                    console.error('[WEBRTC] Failed to handle ICE restart:', error);
+                }
+            });
+
+            // Media liveness comes from the peer connection, not the signaling
+            // socket: a dropped socket leaves the call running, a failed peer
+            // connection is recovered or ends it.
+            const unlistenMediaState = await listen<string>('media-state', async (event) => {
+                if (event.payload === 'connected') {
+                    clearRestart();
+                    return;
+                }
+                if (event.payload !== 'failed') {
+                    return;
+                }
+                await recoverCall();
+            });
+
+            // ICE gives up before the peer connection reports it; still
+            // gathering candidates is not a failure
+            const unlistenIceState = await listen<IceStatePayload>('ice-state', async (event) => {
+                const { kind, state } = event.payload;
+                if (kind !== 'connection') {
+                    return;
+                }
+                if (state === 'connected' || state === 'completed') {
+                    clearRestart();
+                } else if (state === 'failed') {
+                    await recoverCall();
+                }
+            });
+
+            // Frame duration and channels change mid-call only once both
+            // peers agree; the engine switches and reports the new settings.
+            // Our user id settles offers that cross.
+            const unlistenParamsOffer = await listen<AudioParamsOfferPayload>('audio-params-offer', async (event) => {
+                console.log('[Call] Peer offered audio params', event.payload.params);
+                const { user } = useAppStore.getState();
+                if (!user) {
+                    return;
+                }
+                try {
+                    await invoke('answer_audio_params_offer', {
+                        selfId: user.id,
+                        peerId: event.payload.peerId,
+                        params: event.payload.params,
+                    });
+                } catch (error) {
+                    console.error('[Call] Failed to answer audio params offer:', error);
+                }
+            });
+
+            const unlistenParamsAnswer = await listen<AudioParamsAnswerPayload>('audio-params-answer', async (event) => {
+                const { accepted, params } = event.payload;
+                if (!accepted) {
+                    console.warn('[Call] Peer rejected audio params, keeping', params);
+                }
+                try {
+                    await invoke('finish_audio_params_offer', { accepted, params });
+                } catch (error) {
+                    console.error('[Call] Failed to apply audio params answer:', error);
+                }
+            });
+
             return () => {
+                clearRestart();
                 unlistenIncoming();
                 unlistenAccepted();
                 unlistenOffer();
@@ -138,6 +304,12 @@ export function useCallEvents() {
                 unlistenBusy();
                 unlistenCancelled();
                 unlistenUnavailable();
+                unlistenSnapshot();
+                unlistenRestart();
+                unlistenMediaState();
+                unlistenIceState();
+                unlistenParamsOffer();
+                unlistenParamsAnswer();
             };
         };
 
@@ -0,0 +1,14 @@
+import { useCallback, useMemo } from 'react';
+import { useAppStore } from '../store';
+import { buildWordFilter, redactFilteredWords } from '../services/messages/filters';
+
+/**
+ * Returns a function that redacts the current user's filtered words from
+ * message text. Only affects what is shown; stored content is untouched.
+ */
+export function useMessageFilter() {
+    const filteredWords = useAppStore((s) => s.filteredWords);
+    const filter = useMemo(() => buildWordFilter(filteredWords), [filteredWords]);
+
+    return useCallback((content: string) => redactFilteredWords(content, filter), [filter]);
+}
@@ -3,25 +3,40 @@ import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useAppStore } from '../store';
 import { shouldPromoteStatus } from '../services/messages/status';
-import type { ChannelMessage, Message, MessageReaction, MessageStatus } from '../types';
+import type { ChannelMessage, Message, MessageReaction, MessageStatus, OutboxStatusPayload, PresenceStatus } from '../types';
 
 interface WsEventPayload {
     type: string;
     message?: Message | ChannelMessage;
     message_id?: string;
+    /** Messages read at once, on `MESSAGE_READ` */
+    message_ids?: string[];
     room_id?: string;
+    server_id?: string;
     channel_id?: string;
     user_id?: string;
+    from_user_id?: string;
+    from_username?: string;
     is_typing?: boolean;
     joined?: boolean;
+    mode?: string;
     status?: string;
+    status_message?: string | null;
+    /** Whether the user is connected, on `PRESENCE_UPDATE` */
+    online?: boolean;
     reactions?: MessageReaction[];
+    /** Thread replies removed along with a deleted message */
+    reply_ids?: string[];
+    /** Pinned messages removed along with a deleted channel message */
+    unpinned_ids?: string[];
+    sender_id?: string;
+    sender_username?: string;
 }
 
-const MESSAGE_STATUSES: MessageStatus[] = ['sending', 'failed', 'sent', 'delivered', 'read'];
+const PRESENCE_STATUSES: PresenceStatus[] = ['online', 'away', 'dnd'];
 
-const isMessageStatus = (value: string): value is MessageStatus => {
-    return MESSAGE_STATUSES.includes(value as MessageStatus);
+const isPresenceStatus = (value: string): value is PresenceStatus => {
+    return PRESENCE_STATUSES.includes(value as PresenceStatus);
 };
 
 export function useWebSocketEvents() {
@@ -37,7 +52,12 @@ export function useWebSocketEvents() {
     const clearMessages = useAppStore((s) => s.clearMessages);
     const setChannelTyping = useAppStore((s) => s.setChannelTyping);
     const setVoicePresence = useAppStore((s) => s.setVoicePresence);
+    const setVoiceState = useAppStore((s) => s.setVoiceState);
     const setChannelMessageReactions = useAppStore((s) => s.setChannelMessageReactions);
+    const fetchChannelPins = useAppStore((s) => s.fetchChannelPins);
+    const removeChannelMessages = useAppStore((s) => s.removeChannelMessages);
+    const addMention = useAppStore((s) => s.addMention);
+    const markChannelRead = useAppStore((s) => s.markChannelRead);
 
     useEffect(() => {
         if (!isAuthenticated) {
@@ -88,26 +108,26 @@ export function useWebSocketEvents() {
                             }
 
                             console.log('[App] ✏️ MESSAGE_EDITED via WebSocket');
-                            updateMessage(message);
-                        } else if (payload.type === 'MESSAGE_STATUS') {
-                            const nextStatus = typeof payload.status === 'string' && isMessageStatus(payload.status)
-                                ? payload.status
-                                : undefined;
+                            updateMessage(payload.reactions ? { ...message, reactions: payload.reactions } : message);
+                        } else if (payload.type === 'MESSAGE_DELIVERED' || payload.type === 'MESSAGE_READ') {
+                            // Only the sender of these messages receives their receipts
+                            const nextStatus: MessageStatus = payload.type === 'MESSAGE_READ' ? 'read' : 'delivered';
+                            const ids = new Set(payload.message_ids ?? (payload.message_id ? [payload.message_id] : []));
 
                             useAppStore.setState((state) => ({
                                 messages: state.messages.map((message) =>
-                                    message.id === payload.message_id && shouldPromoteStatus(message.status, nextStatus)
+                                    ids.has(message.id) && shouldPromoteStatus(message.status, nextStatus)
                                         ? { ...message, status: nextStatus }
                                         : message
                                 ),
                             }));
 
-                            if (payload.message_id && nextStatus) {
+                            ids.forEach((messageId) => {
                                 invoke('api_cache_message_status', {
-                                    messageId: payload.message_id,
+                                    messageId,
                                     status: nextStatus,
                                 }).catch(() => undefined);
-                            }
+                            });
                         } else if (payload.type === 'TYPING') {
                             if (payload.room_id && payload.user_id) {
                                 setTyping(payload.room_id, payload.user_id, !!payload.is_typing);
@@ -118,6 +138,7 @@ export function useWebSocketEvents() {
                             }
                             console.log('[App] 🗑️ MESSAGE_DELETED via WebSocket');
                             removeMessage(payload.message_id);
+                            (payload.reply_ids || []).forEach(removeMessage);
                         } else if (payload.type === 'ALL_MESSAGES_DELETED') {
                             console.log('[App] 🗑️ ALL_MESSAGES_DELETED via WebSocket');
                             if (payload.room_id === activeRoom) {
@@ -147,11 +168,64 @@ export function useWebSocketEvents() {
                             }
                         } else if (payload.type === 'VOICE_PRESENCE') {
                             if (payload.channel_id && payload.user_id) {
-                                setVoicePresence(payload.channel_id, payload.user_id, !!payload.joined);
+                                setVoicePresence(
+                                    payload.channel_id,
+                                    payload.user_id,
+                                    !!payload.joined,
+                                    payload.mode === 'listen' ? 'listen' : 'speak',
+                                );
                                 if (payload.user_id === user?.id && !payload.joined) {
                                     useAppStore.setState({ activeVoiceChannel: null });
                                 }
                             }
+                        } else if (payload.type === 'VOICE_STATE_UPDATE') {
+                            if (payload.user_id) {
+                                setVoiceState(payload.user_id, {
+                                    muted: !!payload.muted,
+                                    speaking: !!payload.speaking,
+                                });
+                            }
+                        } else if (payload.type === 'VOICE_NUDGE') {
+                            console.log(
+                                `[App] 👋 ${payload.from_username || payload.from_user_id} nudged you in voice channel ${payload.channel_id}`,
+                            );
+                            if (payload.server_id && payload.channel_id && payload.from_user_id) {
+                                useAppStore.getState().showVoiceNudge({
+                                    serverId: payload.server_id,
+                                    channelId: payload.channel_id,
+                                    fromUserId: payload.from_user_id,
+                                    fromUsername: payload.from_username ?? null,
+                                });
+                            }
+                        } else if (payload.type === 'PRESENCE_UPDATE') {
+                            const presence = payload.status;
+                            if (!payload.user_id || !presence || !isPresenceStatus(presence)) {
+                                return;
+                            }
+                            const userId = payload.user_id;
+                            const online = payload.online ?? null;
+
+                            useAppStore.setState((state) => ({
+                                friends: state.friends.map((friend) =>
+                                    friend.id === payload.user_id
+                                        ? {
+                                            ...friend,
+                                            presence_status: presence,
+                                            status_message: payload.status_message ?? null,
+                                            last_seen: online === false
+                                                ? new Date().toISOString()
+                                                : friend.last_seen,
+                                        }
+                                        : friend
+                                ),
+                                // Older servers only send status changes; keep the
+                                // fetched online list then
+                                onlineFriends: online === null
+                                    ? state.onlineFriends
+                                    : online
+                                        ? Array.from(new Set([...state.onlineFriends, userId]))
+                                        : state.onlineFriends.filter((id) => id !== userId),
+                            }));
                         } else if (payload.type === 'CHANNEL_MESSAGE_EDITED') {
                             const message = payload.message as ChannelMessage | undefined;
                             if (!message) {
@@ -163,12 +237,50 @@ export function useWebSocketEvents() {
                                     existing.id === message.id ? { ...existing, ...message } : existing
                                 ),
                             }));
+                            if (payload.reactions) {
+                                setChannelMessageReactions(message.id, payload.reactions);
+                            }
+                        } else if (payload.type === 'CHANNEL_MESSAGE_DELETED') {
+                            if (payload.message_id && payload.channel_id) {
+                                removeChannelMessages(payload.channel_id, [
+                                    payload.message_id,
+                                    ...(payload.reply_ids || []),
+                                ]);
+                            }
                         } else if (payload.type === 'CHANNEL_MESSAGE_REACTIONS') {
                             if (payload.message_id) {
                                 setChannelMessageReactions(payload.message_id, payload.reactions || []);
                             }
+                        } else if (payload.type === 'MESSAGE_PINNED' || payload.type === 'MESSAGE_UNPINNED') {
+                            if (payload.server_id && payload.channel_id && payload.channel_id === activeChannel) {
+                                void fetchChannelPins(payload.server_id, payload.channel_id);
+                            }
                         } else if (payload.type === 'MENTION_ALERT') {
                             console.log('[App] 🔔 Mention alert:', payload);
+                            if (!payload.message_id) {
+                                return;
+                            }
+                            const inView = payload.channel_id
+                                ? payload.channel_id === activeChannel
+                                : payload.room_id === activeRoom;
+                            if (inView && payload.channel_id && payload.server_id) {
+                                markChannelRead(payload.server_id, payload.channel_id, payload.message_id);
+                            } else if (inView) {
+                                invoke('api_mark_mentions_read', {
+                                    roomId: payload.room_id,
+                                }).catch(() => undefined);
+                            } else {
+                                addMention({
+                                    id: payload.message_id,
+                                    message_id: payload.message_id,
+                                    sender_id: payload.sender_id,
+                                    sender_username: payload.sender_username,
+                                    room_id: payload.room_id,
+                                    server_id: payload.server_id,
+                                    channel_id: payload.channel_id,
+                                    created_at: new Date().toISOString(),
+                                });
+                            }
                         }
                     } catch (error) {
                         console.error('[App] ❌ Failed to parse WS message:', error);
@@ -190,13 +302,46 @@ export function useWebSocketEvents() {
                         }
                     }
 
-                    try {
-                        const retried = await invoke<number>('api_drain_outbox', { limit: 200 });
-                        if (retried > 0) {
-                            console.log(`[App] 🔁 Drained ${retried} queued messages after reconnect`);
+                    // The server drops our voice session with the old socket: rejoin
+                    // the channel we were in and refresh who else is in each one
+                    const {
+                        activeServer,
+                        activeVoiceChannel,
+                        channels,
+                        voiceListenersByChannel,
+                        joinVoiceChannel,
+                        fetchVoiceChannelPresence,
+                    } = useAppStore.getState();
+                    if (!activeServer) return;
+                    if (activeVoiceChannel && user?.id) {
+                        const mode = (voiceListenersByChannel[activeVoiceChannel] || []).includes(user.id)
+                            ? 'listen'
+                            : 'speak';
+                        // Read before rejoining: the refreshed presence resets it
+                        const ownVoiceState = useAppStore.getState().voiceStatesByUser[user.id];
+                        await joinVoiceChannel(activeServer, activeVoiceChannel, mode);
+                        if (ownVoiceState) {
+                            await useAppStore.getState().reportVoiceState(ownVoiceState);
                         }
-                    } catch (error) {
-                        console.warn('[App] Failed to drain outbox after reconnect:', error);
+                    }
+                    await Promise.all(
+                        channels
+                            .filter((channel) => channel.channel_type === 'voice' && channel.id !== activeVoiceChannel)
+                            .map((channel) => fetchVoiceChannelPresence(activeServer, channel.id)),
+                    );
+                });
+
+                // The outbox worker retries queued messages on its own, resuming on reconnect
+                const unlistenOutbox = await listen<OutboxStatusPayload>('outbox-status', (event) => {
+                    const { client_id: clientId, message_id: messageId, status } = event.payload;
+                    const apply = <T extends { id: string; client_id?: string | null; status?: MessageStatus }>(message: T): T =>
+                        message.client_id === clientId
+                            ? { ...message, id: messageId ?? message.id, status }
+                            : message;
+                    if (event.payload.target_kind === 'dm') {
+                        useAppStore.setState((state) => ({ messages: state.messages.map(apply) }));
+                    } else {
+                        useAppStore.setState((state) => ({ channelMessages: state.channelMessages.map(apply) }));
                     }
                 });
 
@@ -207,6 +352,7 @@ export function useWebSocketEvents() {
                     unlisten();
                     unlistenStatus();
                     unlistenReconnected();
+                    unlistenOutbox();
                 };
             } catch (error) {
                 console.error('[App] ❌ Failed to setup WS listener:', error);
@@ -238,7 +384,12 @@ export function useWebSocketEvents() {
         clearMessages,
         setChannelTyping,
         setVoicePresence,
+        setVoiceState,
         setChannelMessageReactions,
+        fetchChannelPins,
+        removeChannelMessages,
+        addMention,
+        markChannelRead,
         setWsConnected,
     ]);
 }
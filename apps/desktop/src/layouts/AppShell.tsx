@@ -2,6 +2,7 @@ import type { ReactNode } from 'react';
 import { IncomingCallModal } from '../components/IncomingCallModal';
 import { SafeCallOverlay } from '../components/SafeCallOverlay';
 import { ServerSidebar } from '../components/ServerSidebar';
+import { VoiceNudgeToast } from '../components/VoiceNudgeToast';
 
 interface AppShellProps {
     children: ReactNode;
@@ -13,6 +14,7 @@ export function AppShell({ children, callOverlayResetKey }: AppShellProps) {
         <div className="flex h-screen w-full bg-background text-white overflow-hidden font-sans">
             <IncomingCallModal />
             <SafeCallOverlay resetKey={callOverlayResetKey} />
+            <VoiceNudgeToast />
             <ServerSidebar />
             {children}
         </div>
@@ -0,0 +1,17 @@
+const escapeRegExp = (value: string): string => value.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+
+/**
+ * Build a matcher for the user's filtered words. Matches are case-insensitive
+ * and limited to whole words, so filtering "ass" leaves "class" alone.
+ */
+export const buildWordFilter = (words: string[]): RegExp | null => {
+    const terms = words.map((word) => word.trim()).filter(Boolean).map(escapeRegExp);
+    if (terms.length === 0) return null;
+    return new RegExp(`(?<![\\p{L}\\p{N}_])(?:${terms.join('|')})(?![\\p{L}\\p{N}_])`, 'giu');
+};
+
+/** Replace each filtered word with bullets of the same length. Display only. */
+export const redactFilteredWords = (content: string, filter: RegExp | null): string => {
+    if (!filter || !content) return content;
+    return content.replace(filter, (match) => '•'.repeat([...match].length));
+};
@@ -7,6 +7,7 @@ import type {
     Room,
     CallState,
     Message,
+    MessageStatusEntry,
     Server,
     Channel,
     ServerMember,
@@ -14,9 +15,17 @@ import type {
     IncomingCallPayload,
     CallAcceptedPayload,
     VoiceChannelParticipant,
+    VoiceNudge,
+    VoiceParticipantState,
+    VoiceSessionMode,
     MessageReaction,
+    PinnedChannelMessage,
+    UserSettings,
+    Mention,
+    MentionsResponse,
 } from './types';
 import * as crypto from './crypto';
+import { shouldPromoteStatus } from './services/messages/status';
 
 // Note: All HTTP API calls now go through Rust Tauri commands (no CORS issues)
 
@@ -71,12 +80,20 @@ interface AppState {
     typingByRoom: Record<string, string[]>;
     typingByChannel: Record<string, string[]>;
 
+    /** Personal word filters, redacted on display only (synced via user settings) */
+    filteredWords: string[];
+
+    /** Unread @mentions of the current user, newest first */
+    unreadMentions: Mention[];
+
     // Connection state
     wsConnected: boolean;
     lastMessageTimestamp: string | null;
 
     // Call
     activeCall: CallState | null;
+    /** Second call ringing while `activeCall` is in progress */
+    waitingCall: IncomingCallPayload | null;
 
     // Servers
     servers: Server[];
@@ -88,8 +105,16 @@ interface AppState {
     hasMoreChannelMessages: boolean;
     isLoadingMoreChannelMessages: boolean;
     voicePresenceByChannel: Record<string, string[]>;
+    /** Subset of `voicePresenceByChannel` that joined listen-only */
+    voiceListenersByChannel: Record<string, string[]>;
+    /** Mute/speaking state per user id, for users in a voice channel */
+    voiceStatesByUser: Record<string, VoiceParticipantState>;
     activeVoiceChannel: string | null;
+    /** Latest nudge for us, shown as a toast until dismissed */
+    voiceNudge: VoiceNudge | null;
     channelReactions: Record<string, MessageReaction[]>;
+    /** Pinned messages per channel id, newest pin first */
+    channelPins: Record<string, PinnedChannelMessage[]>;
 
     // Actions
     login: (email: string, password: string) => Promise<void>;
@@ -100,12 +125,15 @@ interface AppState {
     sendFriendRequest: (username: string) => Promise<void>;
     acceptFriend: (friendId: string) => Promise<void>;
     setActiveRoom: (roomId: string | null) => void;
-    startCall: (peerId: string) => Promise<void>;
+    /** `ringTimeoutSecs` shortens or lengthens the ring (clamped by the server) */
+    startCall: (peerId: string, ringTimeoutSecs?: number) => Promise<void>;
     endCall: () => Promise<void>;
     handleIncomingCall: (payload: IncomingCallPayload) => void;
     acceptIncomingCall: () => Promise<void>;
     declineIncomingCall: () => Promise<void>;
     cancelOutgoingCall: () => Promise<void>;
+    acceptWaitingCall: () => Promise<void>;
+    declineWaitingCall: () => Promise<void>;
 
     // Chat Actions
     createOrGetDm: (friendId: string) => Promise<void>;
@@ -130,6 +158,18 @@ interface AppState {
     fetchFriendPublicKey: (friendId: string) => Promise<string | null>;
     decryptMessageContent: (message: Message) => string;
 
+    // Settings Actions
+    fetchFilteredWords: () => Promise<void>;
+    setFilteredWords: (words: string[]) => Promise<void>;
+
+    // Mention Actions
+    fetchUnreadMentions: () => Promise<void>;
+    addMention: (mention: Mention) => void;
+    /** Mark mentions read in one DM room or channel */
+    clearMentions: (scope: { roomId?: string; channelId?: string }) => void;
+    /** Move the channel read marker forward; mentions up to it become read */
+    markChannelRead: (serverId: string, channelId: string, uptoMessageId?: string) => void;
+
     // Connection Actions
     setWsConnected: (connected: boolean) => void;
 
@@ -149,10 +189,21 @@ interface AppState {
     fetchChannelMessageReactions: (serverId: string, channelId: string, messageId: string) => Promise<void>;
     toggleChannelReaction: (serverId: string, channelId: string, messageId: string, emoji: string) => Promise<void>;
     setChannelMessageReactions: (messageId: string, reactions: MessageReaction[]) => void;
-    setVoicePresence: (channelId: string, userId: string, joined: boolean) => void;
+    fetchChannelPins: (serverId: string, channelId: string) => Promise<void>;
+    toggleChannelPin: (serverId: string, channelId: string, messageId: string) => Promise<void>;
+    deleteChannelMessage: (serverId: string, channelId: string, messageId: string) => Promise<void>;
+    /** Drop messages (and their reactions/pins) deleted on the server */
+    removeChannelMessages: (channelId: string, messageIds: string[]) => void;
+    setVoicePresence: (channelId: string, userId: string, joined: boolean, mode?: VoiceSessionMode) => void;
+    setVoiceState: (userId: string, voiceState: VoiceParticipantState) => void;
+    /** Tell the other members of our voice channel whether we're muted or talking */
+    reportVoiceState: (voiceState: VoiceParticipantState) => Promise<void>;
     fetchVoiceChannelPresence: (serverId: string, channelId: string) => Promise<void>;
-    joinVoiceChannel: (serverId: string, channelId: string) => Promise<void>;
+    joinVoiceChannel: (serverId: string, channelId: string, mode?: VoiceSessionMode) => Promise<void>;
     leaveVoiceChannel: (serverId: string, channelId?: string) => Promise<void>;
+    nudgeVoiceParticipant: (serverId: string, channelId: string, targetUserId: string) => Promise<void>;
+    showVoiceNudge: (nudge: VoiceNudge) => void;
+    dismissVoiceNudge: () => void;
 }
 
 export const useAppStore = create<AppState>()(
@@ -171,6 +222,7 @@ export const useAppStore = create<AppState>()(
             activeRoom: null,
             activeFriendId: null,
             activeCall: null,
+            waitingCall: null,
             messages: [],
             hasMoreMessages: true,
             isLoadingMoreMessages: false,
@@ -190,8 +242,14 @@ export const useAppStore = create<AppState>()(
             hasMoreChannelMessages: true,
             isLoadingMoreChannelMessages: false,
             voicePresenceByChannel: {},
+            voiceListenersByChannel: {},
+            voiceStatesByUser: {},
             activeVoiceChannel: null,
+            voiceNudge: null,
             channelReactions: {},
+            channelPins: {},
+            filteredWords: [],
+            unreadMentions: [],
 
             // Connection Actions
             setWsConnected: (connected) => {
@@ -275,8 +333,14 @@ export const useAppStore = create<AppState>()(
                     hasMoreChannelMessages: true,
                     isLoadingMoreChannelMessages: false,
                     voicePresenceByChannel: {},
+                    voiceListenersByChannel: {},
+                    voiceStatesByUser: {},
                     activeVoiceChannel: null,
+                    voiceNudge: null,
                     channelReactions: {},
+                    channelPins: {},
+                    filteredWords: [],
+                    unreadMentions: [],
                     typingByRoom: {},
                     typingByChannel: {},
                     wsConnected: false,
@@ -428,7 +492,8 @@ export const useAppStore = create<AppState>()(
 
             sendFriendRequest: async (username) => {
                 try {
-                    await invoke('api_send_friend_request', { username });
+                    const clientId = globalThis.crypto.randomUUID();
+                    await invoke('api_send_friend_request', { username, clientId });
                 } catch (e) {
                     console.error('[Store] sendFriendRequest exception:', e);
                     throw e;
@@ -525,6 +590,26 @@ export const useAppStore = create<AppState>()(
                         const lastMsg = normalized[normalized.length - 1];
                         set({ lastMessageTimestamp: lastMsg.created_at || null });
                     }
+
+                    // Checkmarks for our own messages in one request instead of one per message
+                    const ownIds = normalized
+                        .filter((m) => m.sender_id === get().user?.id && m.status !== 'read')
+                        .map((m) => m.id);
+                    if (ownIds.length > 0) {
+                        invoke<MessageStatusEntry[]>('api_fetch_message_statuses', { roomId, messageIds: ownIds.slice(-200) })
+                            .then((statuses) => {
+                                const byId = new Map(statuses.map((s) => [s.message_id, s.status]));
+                                set((state) => ({
+                                    messages: state.messages.map((message) => {
+                                        const next = byId.get(message.id);
+                                        return next && shouldPromoteStatus(message.status, next)
+                                            ? { ...message, status: next }
+                                            : message;
+                                    }),
+                                }));
+                            })
+                            .catch((e) => console.warn('[Store] Failed to fetch message statuses:', e));
+                    }
                 } catch (e) {
                     console.error('[Store] fetchMessages exception:', e);
                     if (append) {
@@ -827,10 +912,15 @@ export const useAppStore = create<AppState>()(
             },
 
             // Room actions
-            setActiveRoom: (roomId) => set({ activeRoom: roomId }),
+            setActiveRoom: (roomId) => {
+                set({ activeRoom: roomId });
+                if (roomId) {
+                    get().clearMentions({ roomId });
+                }
+            },
 
             // Call actions
-            startCall: async (peerId) => {
+            startCall: async (peerId, ringTimeoutSecs) => {
                 console.log('[CALL-DEBUG] ===== START CALL =====');
                 console.log('[CALL-DEBUG] Target peerId:', peerId);
                 const { friends } = get();
@@ -850,7 +940,10 @@ export const useAppStore = create<AppState>()(
 
                 try {
                     console.log('[CALL-DEBUG] Invoking start_call command...');
-                    const result = await invoke('start_call', { targetId: peerId });
+                    const result = await invoke('start_call', {
+                        targetId: peerId,
+                        ringTimeoutSecs: ringTimeoutSecs ?? null,
+                    });
                     console.log('[CALL-DEBUG] ✅ start_call returned:', result);
                     console.log('[CALL-DEBUG] Call initiated, waiting for acceptance...');
                 } catch (e) {
@@ -874,16 +967,29 @@ export const useAppStore = create<AppState>()(
 
             handleIncomingCall: (payload) => {
                 console.log('[Call] Incoming call from', payload.callerName);
+                if (payload.whileBusy) {
+                    // Rings alongside the current call instead of replacing it
+                    set({ waitingCall: payload });
+                    return;
+                }
                 set({
+                    waitingCall: null,
                     activeCall: {
                         status: 'ringing',
                         peerId: payload.callerId,
                         peerName: payload.callerName,
                         peerPublicKey: payload.publicKey,
+                        peerStatus: payload.callerStatus ?? null,
+                        ringDeadline:
+                            payload.ringTimeoutSecs != null ? Date.now() + payload.ringTimeoutSecs * 1000 : null,
                         isMuted: false,
                         startTime: null,
                     },
                 });
+                // Open audio devices while ringing so accepting is instant
+                invoke('prewarm_call_audio').catch((e) => console.warn('[Call] Audio pre-warm failed:', e));
+                // Stops on accept (accept_call) or on any reset of the call media
+                invoke('play_ringtone').catch((e) => console.warn('[Call] Ringtone failed:', e));
             },
 
             acceptIncomingCall: async () => {
@@ -938,6 +1044,120 @@ export const useAppStore = create<AppState>()(
                 set({ activeCall: null });
             },
 
+            acceptWaitingCall: async () => {
+                const { waitingCall } = get();
+                if (!waitingCall) return;
+
+                // The current call is dropped by the desktop side and the server
+                set({
+                    waitingCall: null,
+                    activeCall: {
+                        status: 'connecting',
+                        peerId: waitingCall.callerId,
+                        peerName: waitingCall.callerName,
+                        peerPublicKey: waitingCall.publicKey,
+                        peerStatus: waitingCall.callerStatus ?? null,
+                        isMuted: false,
+                        startTime: null,
+                    },
+                });
+
+                try {
+                    await invoke('answer_waiting_call', {
+                        callerId: waitingCall.callerId,
+                        callId: waitingCall.callId ?? null,
+                        callerPublicKey: waitingCall.publicKey,
+                        accept: true,
+                    });
+                    // Connected state follows the WebRTC offer, as for accept_call
+                } catch (e) {
+                    console.error('[Call] Failed to accept waiting call:', e);
+                    invoke('reset_call_media').catch(() => undefined);
+                    set({ activeCall: null });
+                }
+            },
+
+            declineWaitingCall: async () => {
+                const { waitingCall } = get();
+                if (!waitingCall) return;
+
+                set({ waitingCall: null });
+                try {
+                    await invoke('answer_waiting_call', {
+                        callerId: waitingCall.callerId,
+                        callId: waitingCall.callId ?? null,
+                        callerPublicKey: waitingCall.publicKey,
+                        accept: false,
+                    });
+                } catch (e) {
+                    console.error('[Call] Failed to decline waiting call:', e);
+                }
+            },
+
+            // Settings Actions
+            fetchFilteredWords: async () => {
+                try {
+                    const settings = await invoke<UserSettings>('api_fetch_my_settings');
+                    set({ filteredWords: settings.filtered_words || [] });
+                } catch (e) {
+                    console.error('[Store] fetchFilteredWords error:', e);
+                }
+            },
+
+            setFilteredWords: async (words) => {
+                try {
+                    const settings = await invoke<UserSettings>('api_update_my_settings', {
+                        filteredWords: words,
+                    });
+                    set({ filteredWords: settings.filtered_words || [] });
+                } catch (e) {
+                    console.error('[Store] setFilteredWords error:', e);
+                    throw e;
+                }
+            },
+
+            // Mention Actions
+            fetchUnreadMentions: async () => {
+                try {
+                    const data = await invoke<MentionsResponse>('api_fetch_my_mentions', {
+                        unreadOnly: true,
+                        limit: 200,
+                    });
+                    set({ unreadMentions: data.mentions });
+                } catch (e) {
+                    console.error('[Store] fetchUnreadMentions error:', e);
+                }
+            },
+
+            addMention: (mention) => {
+                const { unreadMentions } = get();
+                if (unreadMentions.some((m) => m.message_id === mention.message_id)) {
+                    return;
+                }
+                set({ unreadMentions: [mention, ...unreadMentions] });
+            },
+
+            clearMentions: ({ roomId, channelId }) => {
+                const { unreadMentions } = get();
+                const remaining = unreadMentions.filter((m) =>
+                    roomId ? m.room_id !== roomId : m.channel_id !== channelId,
+                );
+                if (remaining.length === unreadMentions.length) {
+                    return;
+                }
+                set({ unreadMentions: remaining });
+                invoke('api_mark_mentions_read', { roomId, channelId }).catch(() => undefined);
+            },
+
+            markChannelRead: (serverId, channelId, uptoMessageId) => {
+                const { unreadMentions } = get();
+                const remaining = unreadMentions.filter((m) => m.channel_id !== channelId);
+                if (remaining.length !== unreadMentions.length) {
+                    set({ unreadMentions: remaining });
+                }
+                invoke('api_mark_channel_read', { serverId, channelId, uptoMessageId }).catch(() => undefined);
+            },
+
             // Server Actions
             fetchServers: async () => {
                 try {
@@ -952,7 +1172,8 @@ export const useAppStore = create<AppState>()(
             createServer: async (name, iconUrl) => {
                 const { fetchServers } = get();
                 try {
-                    await invoke('api_create_server', { name, iconUrl });
+                    const clientId = globalThis.crypto.randomUUID();
+                    await invoke('api_create_server', { name, iconUrl, clientId });
                     console.log('[Store] Server created');
                     await fetchServers();
                 } catch (e) {
@@ -993,8 +1214,11 @@ export const useAppStore = create<AppState>()(
                             hasMoreChannelMessages: true,
                             isLoadingMoreChannelMessages: false,
                             voicePresenceByChannel: {},
+                            voiceListenersByChannel: {},
+                            voiceStatesByUser: {},
                             activeVoiceChannel: null,
                             channelReactions: {},
+                            channelPins: {},
                         });
                     }
                     await fetchServers();
@@ -1021,8 +1245,11 @@ export const useAppStore = create<AppState>()(
                     hasMoreChannelMessages: true,
                     isLoadingMoreChannelMessages: false,
                     voicePresenceByChannel: {},
+                    voiceListenersByChannel: {},
+                    voiceStatesByUser: {},
                     activeVoiceChannel: null,
                     channelReactions: {},
+                    channelPins: {},
                 });
                 if (serverId) {
                     get().fetchChannels(serverId);
@@ -1055,7 +1282,8 @@ export const useAppStore = create<AppState>()(
             createChannel: async (serverId, name, type = 'text') => {
                 const { fetchChannels } = get();
                 try {
-                    await invoke('api_create_channel', { serverId, name, channelType: type });
+                    const clientId = globalThis.crypto.randomUUID();
+                    await invoke('api_create_channel', { serverId, name, channelType: type, clientId });
                     console.log('[Store] Channel created');
                     await fetchChannels(serverId);
                 } catch (e) {
@@ -1074,6 +1302,7 @@ export const useAppStore = create<AppState>()(
                 });
                 if (channelId && activeServer) {
                     get().fetchChannelMessages(activeServer, channelId, { limit: 100 });
+                    get().markChannelRead(activeServer, channelId);
                 }
             },
 
@@ -1257,21 +1486,110 @@ export const useAppStore = create<AppState>()(
                 }
             },
 
-            setVoicePresence: (channelId, userId, joined) => {
+            fetchChannelPins: async (serverId, channelId) => {
+                try {
+                    const pins = await invoke<PinnedChannelMessage[]>('api_fetch_channel_pins', {
+                        serverId,
+                        channelId,
+                    });
+                    set((state) => ({
+                        channelPins: { ...state.channelPins, [channelId]: pins },
+                    }));
+                } catch (e) {
+                    console.error('[Store] fetchChannelPins error:', e);
+                }
+            },
+
+            toggleChannelPin: async (serverId, channelId, messageId) => {
+                const pinned = (get().channelPins[channelId] || []).some((pin) => pin.message_id === messageId);
+
+                try {
+                    const pins = await invoke<PinnedChannelMessage[]>(
+                        pinned ? 'api_unpin_channel_message' : 'api_pin_channel_message',
+                        { serverId, channelId, messageId },
+                    );
+                    set((state) => ({
+                        channelPins: { ...state.channelPins, [channelId]: pins },
+                    }));
+                } catch (e) {
+                    console.error('[Store] toggleChannelPin error:', e);
+                }
+            },
+
+            deleteChannelMessage: async (serverId, channelId, messageId) => {
+                try {
+                    await invoke('api_delete_channel_message', { serverId, channelId, messageId });
+                    get().removeChannelMessages(channelId, [messageId]);
+                } catch (e) {
+                    console.error('[Store] deleteChannelMessage error:', e);
+                }
+            },
+
+            removeChannelMessages: (channelId, messageIds) => {
+                const removed = new Set(messageIds);
+                set((state) => {
+                    const channelReactions = { ...state.channelReactions };
+                    messageIds.forEach((id) => delete channelReactions[id]);
+                    const pins = state.channelPins[channelId];
+
+                    return {
+                        channelMessages: state.channelMessages.filter((message) => !removed.has(message.id)),
+                        channelReactions,
+                        channelPins: pins
+                            ? { ...state.channelPins, [channelId]: pins.filter((pin) => !removed.has(pin.message_id)) }
+                            : state.channelPins,
+                    };
+                });
+            },
+
+            setVoicePresence: (channelId, userId, joined, mode = 'speak') => {
                 const current = get().voicePresenceByChannel;
                 const existing = current[channelId] || [];
                 const next = joined
                     ? Array.from(new Set([...existing, userId]))
                     : existing.filter((id) => id !== userId);
 
+                const listeners = get().voiceListenersByChannel;
+                const existingListeners = (listeners[channelId] || []).filter((id) => id !== userId);
+                const nextListeners = joined && mode === 'listen'
+                    ? [...existingListeners, userId]
+                    : existingListeners;
+
+                const voiceStatesByUser = { ...get().voiceStatesByUser };
+                if (!joined) {
+                    delete voiceStatesByUser[userId];
+                }
+
                 set({
                     voicePresenceByChannel: {
                         ...current,
                         [channelId]: next,
                     },
+                    voiceListenersByChannel: {
+                        ...listeners,
+                        [channelId]: nextListeners,
+                    },
+                    voiceStatesByUser,
                 });
             },
 
+            setVoiceState: (userId, voiceState) => {
+                set({ voiceStatesByUser: { ...get().voiceStatesByUser, [userId]: voiceState } });
+            },
+
+            reportVoiceState: async (voiceState) => {
+                const { activeVoiceChannel, setVoiceState, user } = get();
+                if (!activeVoiceChannel) return;
+                if (user?.id) {
+                    setVoiceState(user.id, voiceState);
+                }
+                try {
+                    await invoke('send_voice_state', { channelId: activeVoiceChannel, ...voiceState });
+                } catch (e) {
+                    console.error('[Store] reportVoiceState error:', e);
+                }
+            },
+
             fetchVoiceChannelPresence: async (serverId, channelId) => {
                 try {
                     const participants = await invoke<VoiceChannelParticipant[]>('api_fetch_voice_channel_presence', {
@@ -1283,23 +1601,34 @@ export const useAppStore = create<AppState>()(
                             ...get().voicePresenceByChannel,
                             [channelId]: participants.map((p) => p.user_id),
                         },
+                        voiceListenersByChannel: {
+                            ...get().voiceListenersByChannel,
+                            [channelId]: participants.filter((p) => p.mode === 'listen').map((p) => p.user_id),
+                        },
+                        voiceStatesByUser: {
+                            ...get().voiceStatesByUser,
+                            ...Object.fromEntries(participants.map((p) => [
+                                p.user_id,
+                                { muted: !!p.muted, speaking: !!p.speaking },
+                            ])),
+                        },
                     });
                 } catch (e) {
                     console.error('[Store] fetchVoiceChannelPresence error:', e);
                 }
             },
 
-            joinVoiceChannel: async (serverId, channelId) => {
+            joinVoiceChannel: async (serverId, channelId, mode = 'speak') => {
                 const { activeVoiceChannel, leaveVoiceChannel, setVoicePresence, user } = get();
                 try {
                     if (activeVoiceChannel && activeVoiceChannel !== channelId) {
                         await leaveVoiceChannel(serverId, activeVoiceChannel);
                     }
 
-                    await invoke('api_join_voice_channel', { serverId, channelId });
+                    await invoke('api_join_voice_channel', { serverId, channelId, mode });
                     set({ activeVoiceChannel: channelId });
                     if (user?.id) {
-                        setVoicePresence(channelId, user.id, true);
+                        setVoicePresence(channelId, user.id, true, mode);
                     }
                     await get().fetchVoiceChannelPresence(serverId, channelId);
                 } catch (e) {
@@ -1327,6 +1656,18 @@ export const useAppStore = create<AppState>()(
                     console.error('[Store] leaveVoiceChannel error:', e);
                 }
             },
+
+            nudgeVoiceParticipant: async (serverId, channelId, targetUserId) => {
+                try {
+                    await invoke('api_nudge_voice_participant', { serverId, channelId, targetUserId });
+                } catch (e) {
+                    console.error('[Store] nudgeVoiceParticipant error:', e);
+                }
+            },
+
+            showVoiceNudge: (nudge) => set({ voiceNudge: nudge }),
+
+            dismissVoiceNudge: () => set({ voiceNudge: null }),
         }),
         {
             name: 'p2p-nitro-store',
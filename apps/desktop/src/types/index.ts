@@ -3,10 +3,46 @@ export interface User {
     username: string;
     avatar_url?: string;
     last_seen?: string;
+    status_message?: string | null;
 }
 
+export type PresenceStatus = 'online' | 'away' | 'dnd';
+
 export interface Friend extends User {
     status: string;
+    presence_status?: PresenceStatus | null;
+}
+
+export interface UserSettings {
+    user_id: string;
+    allow_dm_from_strangers: boolean;
+    enable_mention_notifications: boolean;
+    enable_sound_notifications: boolean;
+    presence_status?: PresenceStatus | null;
+    /** Lowercased words to redact when displaying messages */
+    filtered_words: string[];
+    /** Let a second call ring through during a call instead of answering busy */
+    call_waiting?: boolean;
+    /** Let senders see when their messages were read; delivery receipts are always sent */
+    send_read_receipts?: boolean;
+}
+
+/** A persisted @mention of the current user (DM when `room_id` is set) */
+export interface Mention {
+    id: string;
+    message_id: string;
+    sender_id?: string | null;
+    sender_username?: string | null;
+    room_id?: string | null;
+    server_id?: string | null;
+    channel_id?: string | null;
+    created_at: string;
+    read_at?: string | null;
+}
+
+export interface MentionsResponse {
+    unread_count: number;
+    mentions: Mention[];
 }
 
 export interface AuthResponse {
@@ -36,6 +72,30 @@ export interface Message {
 
 export type MessageStatus = 'sending' | 'sent' | 'delivered' | 'read' | 'failed';
 
+export interface DmSearchMatch {
+    message_id: string;
+    sender_id?: string | null;
+    snippet: string;
+    created_at?: string | null;
+}
+
+export interface DmSearchResults {
+    conversations: {
+        room_id: string;
+        peer_id?: string | null;
+        peer_username?: string | null;
+        matches: DmSearchMatch[];
+    }[];
+    next_before?: string | null;
+}
+
+export interface MessageStatusEntry {
+    message_id: string;
+    status: 'sent' | 'delivered' | 'read';
+    delivered_at?: string | null;
+    read_at?: string | null;
+}
+
 export interface Conversation {
     id: string;
     kind: 'dm' | 'channel';
@@ -50,14 +110,92 @@ export interface CallState {
     peerId: string | null;
     peerName: string | null;
     peerPublicKey: string | null;
+    /** Caller's status message while ringing */
+    peerStatus?: string | null;
+    /** When the server stops an incoming call ringing (ms since epoch) */
+    ringDeadline?: number | null;
     isMuted: boolean;
     startTime: number | null;
 }
 
+/** Returned by the `get_call_stats` command; it rejects outside a call */
+export interface CallStats {
+    rtt_ms: number | null;
+    packets_sent: number;
+    packets_received: number;
+    bytes_sent: number;
+    bytes_received: number;
+    packets_lost: number;
+    packet_loss_percent: number;
+    packets_duplicate: number;
+    packets_out_of_order: number;
+    jitter_ms: number;
+    data_channel_buffered_amount: number;
+    frames_encoded: number;
+    frames_vad_dropped: number;
+    frames_dtx: number;
+    frames_backpressure_dropped: number;
+    frames_concealed: number;
+    buffer_underruns: number;
+    buffer_overruns: number;
+}
+
+/** `ice-state` event: ICE candidate gathering or connectivity of the 1:1 call */
+export interface IceStatePayload {
+    kind: 'gathering' | 'connection';
+    /** e.g. `gathering`/`complete`, or `checking`/`connected`/`disconnected`/`failed` */
+    state: string;
+}
+
+/** `outbox-status` event: the outbox worker retried, sent or gave up on a queued message */
+export interface OutboxStatusPayload {
+    client_id: string;
+    target_kind: 'dm' | 'channel';
+    target_id: string;
+    /** `sending` while retries are scheduled, then `sent` or `failed` */
+    status: MessageStatus;
+    /** Server id, once sent */
+    message_id: string | null;
+    attempts: number;
+    error: string | null;
+    retry_in_ms: number | null;
+}
+
+/** `call-quality` event: loss, jitter and RTT of the 1:1 call as a traffic light */
+export type CallQuality = 'good' | 'fair' | 'poor' | 'critical';
+
+/** Returned by the `get_call_quality` command; null outside a call */
+export interface CallQualityScore {
+    /** MOS-like score from 1.0 to 4.5 */
+    score: number;
+    /** The level `call-quality` last reported */
+    category: CallQuality;
+}
+
+/** `test_ice_servers` result: which candidate kinds the configured ICE servers produced */
+export interface ConnectivityReport {
+    host: boolean;
+    /** A server-reflexive candidate: STUN answered */
+    stun: boolean;
+    /** A relay candidate: TURN gave an allocation */
+    turn: boolean;
+    stun_configured: boolean;
+    turn_configured: boolean;
+    /** Gathering did not finish in time; what was found before is still reported */
+    timed_out: boolean;
+    elapsed_ms: number;
+}
+
 export interface IncomingCallPayload {
     callerId: string;
     callerName: string;
     publicKey: string;
+    callerStatus?: string | null;
+    callId?: string | null;
+    /** Rang while we were already in a call (call waiting) */
+    whileBusy?: boolean;
+    /** Seconds until the server gives up ringing */
+    ringTimeoutSecs?: number | null;
 }
 
 export interface CallAcceptedPayload {
@@ -70,6 +208,13 @@ export interface CallUnavailablePayload {
     reason: string;
 }
 
+/** `call-state-snapshot` event: the server's record of our call, after identify */
+export interface CallStateSnapshotPayload {
+    activePeer: string | null;
+    pendingPeer: string | null;
+    callId: string | null;
+}
+
 export interface Server {
     id: string;
     name: string;
@@ -94,12 +239,33 @@ export interface ServerMember {
     avatar_url: string | null;
     role: 'owner' | 'admin' | 'member';
     last_seen: string | null;
+    status_message?: string | null;
 }
 
+/** `listen` members receive channel audio but never send any */
+export type VoiceSessionMode = 'speak' | 'listen';
+
 export interface VoiceChannelParticipant {
     user_id: string;
     username: string;
     joined_at?: string | null;
+    mode?: VoiceSessionMode;
+    muted?: boolean;
+    speaking?: boolean;
+}
+
+/** Mic state of someone in a voice channel, from `VOICE_STATE_UPDATE` */
+export interface VoiceParticipantState {
+    muted: boolean;
+    speaking: boolean;
+}
+
+/** `VOICE_NUDGE`: someone in our voice channel asked for our attention */
+export interface VoiceNudge {
+    serverId: string;
+    channelId: string;
+    fromUserId: string;
+    fromUsername: string | null;
 }
 
 export interface ChannelMessage {
@@ -115,6 +281,31 @@ export interface ChannelMessage {
     status?: MessageStatus;
     _decryptedContent?: string;
     reactions?: MessageReaction[];
+    /** Absent from servers without attachment support */
+    attachments?: Attachment[];
+}
+
+/** Metadata of an encrypted file attached to a channel message */
+export interface Attachment {
+    id: string;
+    mime: string;
+    /** Plaintext size in bytes */
+    size: number;
+    encrypted_url: string;
+    nonce: string;
+}
+
+export interface PinnedChannelMessage {
+    message_id: string;
+    channel_id: string;
+    sender_id?: string | null;
+    sender_username?: string | null;
+    content: string;
+    created_at?: string | null;
+    edited_at?: string | null;
+    pinned_by: string;
+    pinned_by_username: string;
+    pinned_at: string;
 }
 
 export interface MessageReaction {
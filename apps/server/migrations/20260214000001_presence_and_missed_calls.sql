@@ -0,0 +1,22 @@
+-- User-selected presence (online / away / dnd)
+ALTER TABLE user_settings
+ADD COLUMN IF NOT EXISTS presence_status VARCHAR(16) NOT NULL DEFAULT 'online';
+
+ALTER TABLE user_settings
+DROP CONSTRAINT IF EXISTS user_settings_presence_status_check;
+
+ALTER TABLE user_settings
+ADD CONSTRAINT user_settings_presence_status_check
+CHECK (presence_status IN ('online', 'away', 'dnd'));
+
+-- Calls that never rang (e.g. callee in do-not-disturb)
+CREATE TABLE IF NOT EXISTS missed_calls (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    caller_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    callee_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason VARCHAR(32) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_missed_calls_callee
+ON missed_calls(callee_id, created_at DESC);
@@ -0,0 +1,3 @@
+-- Free-text status shown next to the user ("commuting", "heads-down")
+ALTER TABLE users
+ADD COLUMN IF NOT EXISTS status_message VARCHAR(128);
@@ -0,0 +1,10 @@
+-- Listen-only voice sessions
+ALTER TABLE voice_channel_sessions
+ADD COLUMN IF NOT EXISTS mode VARCHAR(16) NOT NULL DEFAULT 'speak';
+
+ALTER TABLE voice_channel_sessions
+DROP CONSTRAINT IF EXISTS voice_channel_sessions_mode_check;
+
+ALTER TABLE voice_channel_sessions
+ADD CONSTRAINT voice_channel_sessions_mode_check
+CHECK (mode IN ('speak', 'listen'));
@@ -0,0 +1,11 @@
+-- Pinned channel messages
+CREATE TABLE IF NOT EXISTS pinned_messages (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    pinned_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    pinned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel_id, message_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pinned_messages_channel
+ON pinned_messages(channel_id, pinned_at DESC);
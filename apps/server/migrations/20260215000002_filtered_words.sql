@@ -0,0 +1,3 @@
+-- Personal word filters, synced across devices; redaction happens on the client
+ALTER TABLE user_settings
+ADD COLUMN IF NOT EXISTS filtered_words TEXT[] NOT NULL DEFAULT '{}';
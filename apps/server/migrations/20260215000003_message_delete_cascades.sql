@@ -0,0 +1,43 @@
+-- Deleting a message must take its children with it. These tables were created
+-- with IF NOT EXISTS, so databases that had them before the cascades were added
+-- may still hold plain foreign keys. Drop orphans, then re-assert ON DELETE CASCADE.
+DELETE FROM message_reactions r
+WHERE NOT EXISTS (SELECT 1 FROM messages m WHERE m.id = r.message_id);
+
+DELETE FROM message_receipts r
+WHERE NOT EXISTS (SELECT 1 FROM messages m WHERE m.id = r.message_id);
+
+DELETE FROM message_attachments a
+WHERE NOT EXISTS (SELECT 1 FROM messages m WHERE m.id = a.message_id);
+
+DELETE FROM pinned_messages p
+WHERE NOT EXISTS (SELECT 1 FROM messages m WHERE m.id = p.message_id);
+
+DELETE FROM messages r
+WHERE r.parent_message_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM messages m WHERE m.id = r.parent_message_id);
+
+ALTER TABLE message_reactions DROP CONSTRAINT IF EXISTS message_reactions_message_id_fkey;
+ALTER TABLE message_reactions
+ADD CONSTRAINT message_reactions_message_id_fkey
+FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE;
+
+ALTER TABLE message_receipts DROP CONSTRAINT IF EXISTS message_receipts_message_id_fkey;
+ALTER TABLE message_receipts
+ADD CONSTRAINT message_receipts_message_id_fkey
+FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE;
+
+ALTER TABLE message_attachments DROP CONSTRAINT IF EXISTS message_attachments_message_id_fkey;
+ALTER TABLE message_attachments
+ADD CONSTRAINT message_attachments_message_id_fkey
+FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE;
+
+ALTER TABLE pinned_messages DROP CONSTRAINT IF EXISTS pinned_messages_message_id_fkey;
+ALTER TABLE pinned_messages
+ADD CONSTRAINT pinned_messages_message_id_fkey
+FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE;
+
+ALTER TABLE messages DROP CONSTRAINT IF EXISTS messages_parent_message_id_fkey;
+ALTER TABLE messages
+ADD CONSTRAINT messages_parent_message_id_fkey
+FOREIGN KEY (parent_message_id) REFERENCES messages(id) ON DELETE CASCADE;
@@ -0,0 +1,15 @@
+-- Multiple invite codes per server, each with optional expiry and use limit.
+-- servers.invite_code stays as the legacy, never-expiring code.
+CREATE TABLE IF NOT EXISTS server_invites (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    server_id UUID NOT NULL REFERENCES servers(id) ON DELETE CASCADE,
+    code VARCHAR(16) UNIQUE NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ,
+    max_uses INT CHECK (max_uses IS NULL OR max_uses > 0),
+    uses INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_server_invites_server
+ON server_invites(server_id, created_at DESC);
@@ -0,0 +1,22 @@
+-- Persisted @mentions, so unread mentions survive while the user is offline.
+-- Exactly one of room_id (DM) or channel_id (server channel) is set.
+CREATE TABLE IF NOT EXISTS mentions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    mentioned_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    sender_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    room_id UUID REFERENCES rooms(id) ON DELETE CASCADE,
+    server_id UUID REFERENCES servers(id) ON DELETE CASCADE,
+    channel_id UUID REFERENCES channels(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMPTZ,
+    UNIQUE (message_id, mentioned_user_id),
+    CHECK ((room_id IS NULL) <> (channel_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_mentions_user_unread
+ON mentions(mentioned_user_id, created_at DESC)
+WHERE read_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_mentions_user
+ON mentions(mentioned_user_id, created_at DESC);
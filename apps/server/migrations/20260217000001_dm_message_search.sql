@@ -0,0 +1,27 @@
+-- Trigram index so the cross-conversation DM search (content ILIKE '%q%')
+-- does not scan every message. Channel messages keep their own search.
+-- End-to-end encrypted DMs store base64 ciphertext in `content` and carry a
+-- `nonce`; the search skips them, so the index leaves them out too.
+--
+-- Creating pg_trgm needs a superuser, or CREATE on the database on
+-- PostgreSQL 13+ where pg_trgm is trusted. Without that the migration still
+-- applies and the search runs unindexed; see docs/messaging-lifecycle.md for
+-- adding the index later.
+DO $$
+BEGIN
+    CREATE EXTENSION IF NOT EXISTS pg_trgm;
+EXCEPTION
+    WHEN insufficient_privilege OR undefined_file THEN
+        RAISE NOTICE 'pg_trgm unavailable (%), DM search stays unindexed', SQLERRM;
+END
+$$;
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_trgm') THEN
+        CREATE INDEX IF NOT EXISTS idx_messages_dm_content_trgm
+        ON messages USING gin (content gin_trgm_ops)
+        WHERE room_id IS NOT NULL AND nonce IS NULL;
+    END IF;
+END
+$$;
@@ -0,0 +1,4 @@
+-- Opt-in call waiting: a second caller rings through while the user is
+-- already in a call instead of getting a busy signal
+ALTER TABLE user_settings
+ADD COLUMN IF NOT EXISTS call_waiting BOOLEAN NOT NULL DEFAULT FALSE;
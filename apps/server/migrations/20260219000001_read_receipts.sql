@@ -0,0 +1,4 @@
+-- Privacy toggle: with it off, the user's read receipts are not pushed to
+-- senders and their reads show as delivered. Delivery receipts still go out.
+ALTER TABLE user_settings
+ADD COLUMN IF NOT EXISTS send_read_receipts BOOLEAN NOT NULL DEFAULT TRUE;
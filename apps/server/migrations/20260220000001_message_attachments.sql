@@ -0,0 +1,11 @@
+-- Attachment metadata only: each entry points at an encrypted blob stored
+-- elsewhere ({id, mime, size, encrypted_url, nonce}).
+ALTER TABLE messages
+ADD COLUMN IF NOT EXISTS attachments JSONB NOT NULL DEFAULT '[]'::jsonb;
+
+-- The feature pack's message_attachments table was never written or read by
+-- any route, and it has no nonce or mime, so its rows could not be carried
+-- over as encrypted attachments. messages.attachments replaces it as the one
+-- place attachment metadata lives; it goes away with its message like the
+-- table's cascade did.
+DROP TABLE IF EXISTS message_attachments;
@@ -0,0 +1,23 @@
+-- Client-generated idempotency keys, so a retried create returns the row the
+-- first attempt made instead of inserting a second one. Same scheme as
+-- messages.client_id.
+ALTER TABLE servers
+ADD COLUMN IF NOT EXISTS client_id UUID;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_servers_owner_client_id
+ON servers(owner_id, client_id)
+WHERE client_id IS NOT NULL;
+
+ALTER TABLE channels
+ADD COLUMN IF NOT EXISTS client_id UUID;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_channels_server_client_id
+ON channels(server_id, client_id)
+WHERE client_id IS NOT NULL;
+
+ALTER TABLE friendships
+ADD COLUMN IF NOT EXISTS client_id UUID;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_friendships_user_client_id
+ON friendships(user_id, client_id)
+WHERE client_id IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Per-role overrides for a voice channel. No row means the role may join
+-- and speak; the owner is never restricted.
+CREATE TABLE IF NOT EXISTS channel_permissions (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('admin', 'member')),
+    can_join BOOLEAN NOT NULL DEFAULT TRUE,
+    can_speak BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel_id, role)
+);
@@ -0,0 +1,9 @@
+-- How far each member has read a server channel. Channel mentions up to the
+-- marker are read, as DM mentions are up to `POST /chat/:room_id/read`.
+CREATE TABLE IF NOT EXISTS channel_read_markers (
+    channel_id UUID NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    last_read_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (channel_id, user_id)
+);
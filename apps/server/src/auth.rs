@@ -44,8 +44,21 @@ pub struct Claims {
     pub username: String,
     pub exp: i64,
     pub iat: i64,
+    /// Set on guest tokens only. Guests have no account: REST routes reject
+    /// them and the WebSocket only relays their call signaling to `target_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guest: Option<GuestScope>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuestScope {
+    /// The only user the guest may call: whoever created the link
+    pub target_id: String,
+}
+
+/// Guest tokens never outlive this, whatever the link asked for
+pub const MAX_GUEST_TOKEN_SECONDS: i64 = 24 * 60 * 60;
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct RegisterRequest {
     #[validate(length(min = 3, max = 32), custom(function = "validate_username"))]
@@ -134,6 +147,7 @@ pub fn generate_token(user: &User) -> Result<String, AuthError> {
         username: user.username.clone(),
         exp: exp.timestamp(),
         iat: now.timestamp(),
+        guest: None,
     };
 
     encode(
@@ -144,6 +158,36 @@ pub fn generate_token(user: &User) -> Result<String, AuthError> {
     .map_err(|_| AuthError::InvalidToken)
 }
 
+/// Mint a token for a guest with a fresh id who may only call `target_id`.
+/// Returns the token and the guest's id.
+pub fn generate_guest_token(
+    target_id: Uuid,
+    display_name: &str,
+    lifetime: Duration,
+) -> Result<(String, Uuid), AuthError> {
+    let now = Utc::now();
+    let lifetime = lifetime.min(Duration::seconds(MAX_GUEST_TOKEN_SECONDS));
+    let guest_id = Uuid::new_v4();
+
+    let claims = Claims {
+        sub: guest_id.to_string(),
+        username: display_name.to_string(),
+        exp: (now + lifetime).timestamp(),
+        iat: now.timestamp(),
+        guest: Some(GuestScope {
+            target_id: target_id.to_string(),
+        }),
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&JWT_SECRET),
+    )
+    .map_err(|_| AuthError::InvalidToken)?;
+    Ok((token, guest_id))
+}
+
 /// Validate JWT token and return claims
 pub fn validate_token(token: &str) -> Result<Claims, AuthError> {
     decode::<Claims>(
@@ -253,6 +297,10 @@ where
             .map_err(|_| AuthError::InvalidToken)?;
 
         let claims = validate_token(bearer.token())?;
+        // Guest tokens only work for call signaling
+        if claims.guest.is_some() {
+            return Err(AuthError::InvalidToken);
+        }
         let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AuthError::InvalidToken)?;
 
         Ok(AuthUser {
@@ -261,3 +309,34 @@ where
         })
     }
 }
+
+/// Like [`AuthUser`] but also accepts guest tokens. Only for the few routes
+/// a guest needs to place a call, such as the ICE server list.
+#[derive(Debug, Clone)]
+pub struct CallParticipant {
+    pub id: Uuid,
+    pub guest: bool,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CallParticipant
+where
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        let claims = validate_token(bearer.token())?;
+        let id = Uuid::parse_str(&claims.sub).map_err(|_| AuthError::InvalidToken)?;
+
+        Ok(CallParticipant {
+            id,
+            guest: claims.guest.is_some(),
+        })
+    }
+}
@@ -0,0 +1,198 @@
+use axum::{
+    body::Body,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// JSON body of every error response. `trace_id` echoes the request's
+/// `x-trace-id` header so a failure can be found in the server logs.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// Error returned by route handlers, rendered as an [`ErrorBody`] with the
+/// matching status
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Gone(String),
+    #[error("{0}")]
+    TooManyRequests(String),
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) | ApiError::Database(sqlx::Error::RowNotFound) => {
+                StatusCode::NOT_FOUND
+            }
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Database(err) if is_unique_violation(err) => StatusCode::CONFLICT,
+            ApiError::Gone(_) => StatusCode::GONE,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Database(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) | ApiError::Database(sqlx::Error::RowNotFound) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Database(err) if is_unique_violation(err) => "conflict",
+            ApiError::Gone(_) => "gone",
+            ApiError::TooManyRequests(_) => "rate_limited",
+            ApiError::Database(_) => "database_error",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Message shown to the client. Database errors are logged in full but
+    /// only described generically, so queries and constraint names stay
+    /// server-side.
+    fn public_message(&self) -> String {
+        match self {
+            ApiError::Database(sqlx::Error::RowNotFound) => "Not found".to_string(),
+            ApiError::Database(err) if is_unique_violation(err) => "Already exists".to_string(),
+            ApiError::Database(_) => "Database error".to_string(),
+            ApiError::Internal(_) => "Server error".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+fn is_unique_violation(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(db) if db.is_unique_violation())
+}
+
+impl From<validator::ValidationErrors> for ApiError {
+    fn from(err: validator::ValidationErrors) -> Self {
+        ApiError::Validation(err.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!(code = self.code(), error = %self, "request failed");
+        }
+
+        let body = ErrorBody {
+            code: self.code(),
+            message: self.public_message(),
+            details: None,
+            trace_id: None,
+        };
+        let mut response = (status, Json(body.clone())).into_response();
+        // Picked up by `with_trace_id` once the request's trace id is known
+        response.extensions_mut().insert(body);
+        response
+    }
+}
+
+/// Fill in `trace_id` on an error response produced by [`ApiError`]; other
+/// responses are returned as they are
+pub fn with_trace_id(response: Response, trace_id: Option<String>) -> Response {
+    let Some(trace_id) = trace_id else {
+        return response;
+    };
+    let (mut parts, body) = response.into_parts();
+    let Some(mut error) = parts.extensions.remove::<ErrorBody>() else {
+        return Response::from_parts(parts, body);
+    };
+
+    error.trace_id = Some(trace_id);
+    match serde_json::to_vec(&error) {
+        Ok(json) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(json))
+        }
+        Err(_) => Response::from_parts(parts, body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        serde_json::from_slice(&bytes).expect("json body")
+    }
+
+    #[tokio::test]
+    async fn errors_map_to_status_and_code() {
+        let cases = [
+            (
+                ApiError::Forbidden("Not a member of this server".to_string()),
+                StatusCode::FORBIDDEN,
+                "forbidden",
+            ),
+            (
+                ApiError::Database(sqlx::Error::RowNotFound),
+                StatusCode::NOT_FOUND,
+                "not_found",
+            ),
+            (
+                ApiError::Database(sqlx::Error::PoolTimedOut),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "database_error",
+            ),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!((error.status(), error.code()), (status, code));
+        }
+
+        let body = body_json(ApiError::Database(sqlx::Error::PoolTimedOut).into_response()).await;
+        assert_eq!(body["message"], "Database error");
+        assert!(body.get("trace_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn trace_id_is_added_to_error_bodies_only() {
+        let response = with_trace_id(
+            ApiError::NotFound("Channel not found".to_string()).into_response(),
+            Some("trace-123".to_string()),
+        );
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "Channel not found");
+        assert_eq!(body["trace_id"], "trace-123");
+
+        let ok = with_trace_id(
+            Json(serde_json::json!({ "ok": true })).into_response(),
+            Some("trace-123".to_string()),
+        );
+        assert_eq!(body_json(ok).await, serde_json::json!({ "ok": true }));
+    }
+}
@@ -0,0 +1,191 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+
+/// Lifetime of minted TURN credentials unless `ICE_TURN_TTL_SECONDS` says
+/// otherwise
+pub const DEFAULT_TURN_CREDENTIAL_TTL_SECONDS: i64 = 12 * 60 * 60;
+
+/// One STUN/TURN entry handed to clients, in the same shape as the desktop's
+/// `media::IceServerConfig`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+fn csv_env(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|raw| parse_csv(&raw))
+        .unwrap_or_default()
+}
+
+fn parse_csv(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Canonical ICE servers pushed to clients, read once at startup.
+///
+/// `ICE_SERVERS_JSON` wins when it parses to a non-empty list. Otherwise
+/// `ICE_STUN_URLS`, `ICE_TURN_URLS`, `ICE_TURN_USERNAME` and
+/// `ICE_TURN_CREDENTIAL` are used. With nothing set the list is empty and
+/// clients keep their local configuration.
+pub fn ice_servers_from_env() -> Vec<IceServer> {
+    if let Some(raw_json) = non_empty_env("ICE_SERVERS_JSON") {
+        match serde_json::from_str::<Vec<IceServer>>(&raw_json) {
+            Ok(parsed) if !parsed.is_empty() => return parsed,
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Ignoring invalid ICE_SERVERS_JSON: {}", e),
+        }
+    }
+
+    build_ice_servers(
+        csv_env("ICE_STUN_URLS"),
+        csv_env("ICE_TURN_URLS"),
+        non_empty_env("ICE_TURN_USERNAME"),
+        non_empty_env("ICE_TURN_CREDENTIAL"),
+    )
+}
+
+/// Shared secret for minting time-limited TURN credentials, the scheme
+/// coturn implements as `use-auth-secret`. The secret never leaves the
+/// server; clients only see the minted username and credential.
+#[derive(Clone)]
+pub struct TurnSecret {
+    secret: String,
+    urls: Vec<String>,
+    ttl_seconds: i64,
+}
+
+impl TurnSecret {
+    /// Read from `ICE_TURN_SECRET` and `ICE_TURN_URLS`, with the lifetime
+    /// from `ICE_TURN_TTL_SECONDS`. `None` unless both are set.
+    pub fn from_env() -> Option<Self> {
+        let secret = non_empty_env("ICE_TURN_SECRET")?;
+        let urls = csv_env("ICE_TURN_URLS");
+        if urls.is_empty() {
+            tracing::warn!(
+                "ICE_TURN_SECRET is set without ICE_TURN_URLS; not minting TURN credentials"
+            );
+            return None;
+        }
+        let ttl_seconds = non_empty_env("ICE_TURN_TTL_SECONDS")
+            .and_then(|raw| raw.trim().parse::<i64>().ok())
+            .filter(|ttl| *ttl > 0)
+            .unwrap_or(DEFAULT_TURN_CREDENTIAL_TTL_SECONDS);
+        Some(Self {
+            secret,
+            urls,
+            ttl_seconds,
+        })
+    }
+
+    /// TURN entry for `user_id`, valid until `now + ttl` (Unix seconds)
+    pub fn mint(&self, user_id: &str, now: i64) -> IceServer {
+        let username = turn_username(now + self.ttl_seconds, user_id);
+        let credential = turn_credential(&self.secret, &username);
+        IceServer {
+            urls: self.urls.clone(),
+            username: Some(username),
+            credential: Some(credential),
+        }
+    }
+}
+
+/// `expiry:user_id`, the username the TURN server checks the expiry of
+fn turn_username(expiry: i64, user_id: &str) -> String {
+    format!("{expiry}:{user_id}")
+}
+
+/// base64(HMAC-SHA1(secret, username))
+fn turn_credential(secret: &str, username: &str) -> String {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(username.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+fn build_ice_servers(
+    stun_urls: Vec<String>,
+    turn_urls: Vec<String>,
+    turn_username: Option<String>,
+    turn_credential: Option<String>,
+) -> Vec<IceServer> {
+    let mut servers = Vec::new();
+    if !stun_urls.is_empty() {
+        servers.push(IceServer {
+            urls: stun_urls,
+            username: None,
+            credential: None,
+        });
+    }
+    if !turn_urls.is_empty() {
+        servers.push(IceServer {
+            urls: turn_urls,
+            username: turn_username,
+            credential: turn_credential,
+        });
+    }
+    servers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_stun_and_turn_entries_and_nothing_when_unset() {
+        assert!(build_ice_servers(Vec::new(), Vec::new(), None, None).is_empty());
+
+        let servers = build_ice_servers(
+            parse_csv("stun:a.example:3478, ,stun:b.example:3478"),
+            parse_csv("turn:t.example:3478?transport=udp"),
+            Some("alice".to_string()),
+            Some("secret".to_string()),
+        );
+        assert_eq!(servers.len(), 2);
+        assert_eq!(
+            servers[0].urls,
+            vec!["stun:a.example:3478", "stun:b.example:3478"]
+        );
+        assert_eq!(servers[0].username, None);
+        assert_eq!(servers[1].username.as_deref(), Some("alice"));
+
+        // STUN entries serialize without credential fields
+        let json = serde_json::to_value(&servers[0]).unwrap();
+        assert!(json.get("username").is_none());
+    }
+
+    #[test]
+    fn turn_credential_is_base64_hmac_sha1_of_the_username() {
+        // RFC 2202-style vector, checked against an independent implementation
+        assert_eq!(
+            turn_credential("key", "The quick brown fox jumps over the lazy dog"),
+            "3nybhbi3iqa8ino29wqQcBydtNk="
+        );
+
+        let turn = TurnSecret {
+            secret: "turn-secret".to_string(),
+            urls: vec!["turn:t.example:3478".to_string()],
+            ttl_seconds: 3600,
+        };
+        let server = turn.mint("alice", 1_700_000_000);
+        assert_eq!(server.urls, vec!["turn:t.example:3478"]);
+        assert_eq!(server.username.as_deref(), Some("1700003600:alice"));
+        assert_eq!(
+            server.credential.as_deref(),
+            Some("E4vPY/Y6LWBxrSVVafd9LKs2lHA=")
+        );
+    }
+}
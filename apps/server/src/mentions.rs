@@ -0,0 +1,46 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Where a mention happened.
+#[derive(Debug, Clone, Copy)]
+pub enum MentionScope {
+    Dm { room_id: Uuid },
+    Channel { server_id: Uuid, channel_id: Uuid },
+}
+
+/// Persist a mention next to the live `MENTION_ALERT`, so it can still be
+/// counted as unread after the user was offline. Recording the same message
+/// twice for a user is a no-op.
+pub async fn record_mention(
+    db: &PgPool,
+    scope: MentionScope,
+    message_id: Uuid,
+    mentioned_user_id: Uuid,
+    sender_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let (room_id, server_id, channel_id) = match scope {
+        MentionScope::Dm { room_id } => (Some(room_id), None, None),
+        MentionScope::Channel {
+            server_id,
+            channel_id,
+        } => (None, Some(server_id), Some(channel_id)),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO mentions (message_id, mentioned_user_id, sender_id, room_id, server_id, channel_id)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (message_id, mentioned_user_id) DO NOTHING
+        "#,
+    )
+    .bind(message_id)
+    .bind(mentioned_user_id)
+    .bind(sender_id)
+    .bind(room_id)
+    .bind(server_id)
+    .bind(channel_id)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
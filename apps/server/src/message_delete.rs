@@ -0,0 +1,68 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// What went away with a deleted message, so clients can drop it from
+/// their caches without refetching.
+#[derive(Debug, Default)]
+pub struct DeletedMessage {
+    /// Thread replies removed with the message (any depth)
+    pub reply_ids: Vec<Uuid>,
+    /// Ids among the message and its replies that were pinned
+    pub unpinned_ids: Vec<Uuid>,
+}
+
+/// Delete a message together with its thread replies, reactions, receipts,
+/// attachments and pins in one transaction. The children go through the
+/// `ON DELETE CASCADE` foreign keys; the ids are read first, inside the same
+/// transaction, so the broadcast matches what was actually removed.
+///
+/// Returns `None` when the message no longer exists.
+pub async fn delete_message_tree(
+    db: &PgPool,
+    message_id: Uuid,
+) -> Result<Option<DeletedMessage>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+
+    let locked = sqlx::query_scalar::<_, Uuid>("SELECT id FROM messages WHERE id = $1 FOR UPDATE")
+        .bind(message_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if locked.is_none() {
+        return Ok(None);
+    }
+
+    let reply_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        WITH RECURSIVE replies AS (
+            SELECT id FROM messages WHERE parent_message_id = $1
+            UNION
+            SELECT m.id FROM messages m JOIN replies r ON m.parent_message_id = r.id
+        )
+        SELECT id FROM replies
+        "#,
+    )
+    .bind(message_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut tree = reply_ids.clone();
+    tree.push(message_id);
+    let unpinned_ids = sqlx::query_scalar::<_, Uuid>(
+        "SELECT DISTINCT message_id FROM pinned_messages WHERE message_id = ANY($1)",
+    )
+    .bind(&tree)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM messages WHERE id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(DeletedMessage {
+        reply_ids,
+        unpinned_ids,
+    }))
+}
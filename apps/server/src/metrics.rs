@@ -0,0 +1,257 @@
+use dashmap::DashMap;
+use std::fmt::Write;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Counter that also keeps the number of events seen in the last full minute.
+#[derive(Debug, Default)]
+pub struct RateCounter {
+    total: AtomicU64,
+    /// (minute index, events in that minute, events in the minute before)
+    window: Mutex<(u64, u64, u64)>,
+}
+
+impl RateCounter {
+    fn record(&self, minute: u64) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        let mut window = self.window.lock().unwrap();
+        roll_window(&mut window, minute);
+        window.1 += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    fn last_minute(&self, minute: u64) -> u64 {
+        let mut window = self.window.lock().unwrap();
+        roll_window(&mut window, minute);
+        window.2
+    }
+}
+
+fn roll_window(window: &mut (u64, u64, u64), minute: u64) {
+    if minute == window.0 {
+        return;
+    }
+    window.2 = if minute == window.0 + 1 { window.1 } else { 0 };
+    window.0 = minute;
+    window.1 = 0;
+}
+
+/// Signaling numbers read from `AppState` when `/metrics` is scraped
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SignalingGauges {
+    pub connected_peers: usize,
+    pub active_calls: usize,
+    pub pending_calls: usize,
+}
+
+/// Process-wide metrics registry exposed on `/metrics`.
+#[derive(Debug)]
+pub struct Metrics {
+    started_at: Instant,
+    pub reactions_added: RateCounter,
+    pub reactions_removed: RateCounter,
+    pub messages_sent: RateCounter,
+    /// Signaling messages relayed from one peer to another
+    signals_forwarded: AtomicU64,
+    /// WebSocket messages dropped for their protocol version, and `hello`s
+    /// with no version in common
+    protocol_rejections: AtomicU64,
+    /// Per-user (minute, events) used for the anomaly log
+    user_activity: DashMap<Uuid, (u64, u32)>,
+    anomaly_threshold: Option<u32>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Metrics {
+    pub fn new(anomaly_threshold: Option<u32>) -> Self {
+        Self {
+            started_at: Instant::now(),
+            reactions_added: RateCounter::default(),
+            reactions_removed: RateCounter::default(),
+            messages_sent: RateCounter::default(),
+            signals_forwarded: AtomicU64::new(0),
+            protocol_rejections: AtomicU64::new(0),
+            user_activity: DashMap::new(),
+            anomaly_threshold,
+        }
+    }
+
+    /// Reads `ABUSE_EVENTS_PER_MINUTE` (unset or 0 disables the anomaly log).
+    pub fn from_env() -> Self {
+        let threshold = std::env::var("ABUSE_EVENTS_PER_MINUTE")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .filter(|v| *v > 0);
+        Self::new(threshold)
+    }
+
+    fn current_minute(&self) -> u64 {
+        self.started_at.elapsed().as_secs() / 60
+    }
+
+    pub fn record_reaction_added(&self, user_id: Uuid) {
+        let minute = self.current_minute();
+        self.reactions_added.record(minute);
+        self.track_user(user_id, minute, "reaction_added");
+    }
+
+    pub fn record_reaction_removed(&self, user_id: Uuid) {
+        let minute = self.current_minute();
+        self.reactions_removed.record(minute);
+        self.track_user(user_id, minute, "reaction_removed");
+    }
+
+    pub fn record_message_sent(&self, user_id: Uuid) {
+        let minute = self.current_minute();
+        self.messages_sent.record(minute);
+        self.track_user(user_id, minute, "message_sent");
+    }
+
+    pub fn record_signal_forwarded(&self) {
+        self.signals_forwarded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_protocol_rejection(&self) {
+        self.protocol_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a user's reaction/message events for the current minute and log
+    /// once when they cross the threshold. Returns true when the log fired.
+    fn track_user(&self, user_id: Uuid, minute: u64, kind: &'static str) -> bool {
+        let Some(threshold) = self.anomaly_threshold else {
+            return false;
+        };
+
+        let events = {
+            let mut entry = self.user_activity.entry(user_id).or_insert((minute, 0));
+            let (window_minute, count) = entry.value_mut();
+            if *window_minute != minute {
+                *window_minute = minute;
+                *count = 0;
+            }
+            *count += 1;
+            *count
+        };
+
+        if self.user_activity.len() > 50_000 {
+            self.user_activity
+                .retain(|_, (window_minute, _)| *window_minute + 1 >= minute);
+        }
+
+        if events == threshold + 1 {
+            tracing::warn!(
+                component = "abuse",
+                user_id = %user_id,
+                events_this_minute = events,
+                threshold,
+                last_event = kind,
+                "user exceeded reaction/message rate threshold"
+            );
+            return true;
+        }
+        false
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self, gauges: &SignalingGauges) -> String {
+        let minute = self.current_minute();
+        let mut out = String::new();
+        for (name, help, counter) in [
+            (
+                "reactions_added",
+                "Message reactions added",
+                &self.reactions_added,
+            ),
+            (
+                "reactions_removed",
+                "Message reactions removed",
+                &self.reactions_removed,
+            ),
+            ("messages_sent", "Messages sent", &self.messages_sent),
+        ] {
+            let _ = writeln!(out, "# HELP {name}_total {help}.");
+            let _ = writeln!(out, "# TYPE {name}_total counter");
+            let _ = writeln!(out, "{name}_total {}", counter.total());
+            let _ = writeln!(
+                out,
+                "# HELP {name}_last_minute {help} in the last full minute."
+            );
+            let _ = writeln!(out, "# TYPE {name}_last_minute gauge");
+            let _ = writeln!(out, "{name}_last_minute {}", counter.last_minute(minute));
+        }
+        for (name, help, value) in [
+            (
+                "signals_forwarded_total",
+                "Signaling messages relayed between peers",
+                &self.signals_forwarded,
+            ),
+            (
+                "protocol_version_rejections_total",
+                "Signaling messages rejected for their protocol version",
+                &self.protocol_rejections,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {name} {help}.");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+        }
+        for (name, help, value) in [
+            (
+                "connected_peers",
+                "Identified signaling sockets",
+                gauges.connected_peers,
+            ),
+            ("active_calls", "Calls in progress", gauges.active_calls),
+            ("pending_calls", "Calls still ringing", gauges.pending_calls),
+        ] {
+            let _ = writeln!(out, "# HELP {name} {help}.");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            let _ = writeln!(out, "{name} {value}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_counter_reports_previous_full_minute() {
+        let counter = RateCounter::default();
+        counter.record(0);
+        counter.record(0);
+        counter.record(1);
+
+        assert_eq!(counter.total(), 3);
+        assert_eq!(counter.last_minute(1), 2);
+        assert_eq!(counter.last_minute(2), 1);
+        assert_eq!(counter.last_minute(5), 0);
+    }
+
+    #[test]
+    fn anomaly_fires_once_per_minute_above_threshold() {
+        let metrics = Metrics::new(Some(2));
+        let user = Uuid::new_v4();
+
+        let fired: Vec<bool> = (0..4)
+            .map(|_| metrics.track_user(user, 0, "message_sent"))
+            .collect();
+        assert_eq!(fired, vec![false, false, true, false]);
+
+        // A new minute resets the user's count.
+        assert!(!metrics.track_user(user, 1, "message_sent"));
+    }
+}
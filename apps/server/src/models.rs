@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 use uuid::Uuid;
 
@@ -35,15 +36,6 @@ impl From<User> for UserPublic {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
-pub struct Friendship {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub friend_id: Uuid,
-    pub status: String,
-    pub created_at: Option<DateTime<Utc>>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Room {
     pub id: Uuid,
@@ -84,14 +76,6 @@ pub struct Channel {
     pub created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
-pub struct ServerMember {
-    pub server_id: Uuid,
-    pub user_id: Uuid,
-    pub role: String,
-    pub joined_at: Option<DateTime<Utc>>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ServerMemberWithUser {
     pub user_id: Uuid,
@@ -99,6 +83,7 @@ pub struct ServerMemberWithUser {
     pub avatar_url: Option<String>,
     pub role: String,
     pub last_seen: Option<DateTime<Utc>>,
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -112,4 +97,18 @@ pub struct ChannelMessage {
     pub nonce: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub edited_at: Option<DateTime<Utc>>,
+    pub attachments: Json<Vec<Attachment>>,
+}
+
+/// A file attached to a message. The server keeps this metadata only; the
+/// file itself is encrypted by the sender and uploaded elsewhere.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub mime: String,
+    /// Plaintext size in bytes
+    pub size: u64,
+    pub encrypted_url: String,
+    /// Nonce the file was encrypted with
+    pub nonce: String,
 }
@@ -0,0 +1,198 @@
+use axum::extract::ws::Message;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// User-selected presence stored in `user_settings.presence_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresenceStatus {
+    #[default]
+    Online,
+    Away,
+    /// Do not disturb: incoming calls are declined without ringing
+    Dnd,
+}
+
+impl PresenceStatus {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "online" => Some(Self::Online),
+            "away" => Some(Self::Away),
+            "dnd" => Some(Self::Dnd),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Online => "online",
+            Self::Away => "away",
+            Self::Dnd => "dnd",
+        }
+    }
+}
+
+/// Look up a user's presence; users without a settings row are online.
+pub async fn presence_status(state: &AppState, user_id: Uuid) -> PresenceStatus {
+    let stored = sqlx::query_scalar::<_, String>(
+        "SELECT presence_status FROM user_settings WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await;
+
+    match stored {
+        Ok(Some(value)) => PresenceStatus::parse(&value).unwrap_or_default(),
+        Ok(None) => PresenceStatus::Online,
+        Err(e) => {
+            tracing::warn!("Failed to load presence for {}: {}", user_id, e);
+            PresenceStatus::Online
+        }
+    }
+}
+
+/// Record a call that was turned away before ringing.
+pub async fn record_missed_call(state: &AppState, caller_id: Uuid, callee_id: Uuid, reason: &str) {
+    if let Err(e) =
+        sqlx::query("INSERT INTO missed_calls (caller_id, callee_id, reason) VALUES ($1, $2, $3)")
+            .bind(caller_id)
+            .bind(callee_id)
+            .bind(reason)
+            .execute(&state.db)
+            .await
+    {
+        tracing::warn!(
+            "Failed to record missed call {} -> {}: {}",
+            caller_id,
+            callee_id,
+            e
+        );
+    }
+}
+
+/// The user's free-text status, if they set one.
+pub async fn status_message(state: &AppState, user_id: Uuid) -> Option<String> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT status_message FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .flatten()
+}
+
+/// Whether the user lets a second call ring through while in a call.
+pub async fn call_waiting_enabled(state: &AppState, user_id: Uuid) -> bool {
+    sqlx::query_scalar::<_, bool>("SELECT call_waiting FROM user_settings WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// Push a `PRESENCE_UPDATE` event with the user's current presence, status
+/// message and whether they are connected to their online friends. Called
+/// on status changes and when the user's signaling socket comes or goes.
+pub async fn broadcast_presence(state: &AppState, user_id: Uuid) {
+    // One round trip however many friends the user has: their settings and
+    // every accepted friend id come back in a single row
+    let row = sqlx::query_as::<_, (Option<String>, Option<String>, Vec<Uuid>)>(
+        r#"
+        SELECT s.presence_status, u.status_message,
+            ARRAY(
+                SELECT CASE
+                    WHEN f.user_id = $1 THEN f.friend_id
+                    ELSE f.user_id
+                END
+                FROM friendships f
+                WHERE (f.user_id = $1 OR f.friend_id = $1) AND f.status = 'accepted'
+            ) as friend_ids
+        FROM users u
+        LEFT JOIN user_settings s ON s.user_id = u.id
+        WHERE u.id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await;
+
+    let (stored_status, status_message, friend_ids) = match row {
+        Ok(Some(row)) => row,
+        // Guests have no account and no friends
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load friends for presence broadcast: {}", e);
+            return;
+        }
+    };
+
+    let status = stored_status
+        .as_deref()
+        .and_then(PresenceStatus::parse)
+        .unwrap_or_default();
+    let online = state.peers.contains_key(&user_id.to_string());
+    let ws_text = presence_payload(user_id, status, status_message, online).to_string();
+
+    for friend_id in friend_ids {
+        if let Some(peer) = state.peers.get(&friend_id.to_string()) {
+            let _ = peer.send(Message::Text(ws_text.clone()));
+        }
+    }
+}
+
+fn presence_payload(
+    user_id: Uuid,
+    status: PresenceStatus,
+    status_message: Option<String>,
+    online: bool,
+) -> serde_json::Value {
+    serde_json::json!({
+        "type": "PRESENCE_UPDATE",
+        "user_id": user_id,
+        "status": status.as_str(),
+        "status_message": status_message,
+        "online": online,
+    })
+}
+
+/// Stamp `users.last_seen` when a user's signaling socket goes away.
+pub async fn touch_last_seen(state: &AppState, user_id: Uuid) {
+    if let Err(e) = sqlx::query("UPDATE users SET last_seen = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+    {
+        tracing::warn!("Failed to update last_seen for {}: {}", user_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presence_status_round_trips_and_rejects_unknown_values() {
+        for status in [
+            PresenceStatus::Online,
+            PresenceStatus::Away,
+            PresenceStatus::Dnd,
+        ] {
+            assert_eq!(PresenceStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(PresenceStatus::parse(" DND "), Some(PresenceStatus::Dnd));
+        assert_eq!(PresenceStatus::parse("invisible"), None);
+    }
+
+    #[test]
+    fn presence_payload_carries_online_flag() {
+        let user_id = Uuid::new_v4();
+        let payload = presence_payload(user_id, PresenceStatus::Away, None, false);
+        assert_eq!(payload["type"], "PRESENCE_UPDATE");
+        assert_eq!(payload["user_id"], user_id.to_string());
+        assert_eq!(payload["status"], "away");
+        assert_eq!(payload["online"], false);
+        assert!(payload["status_message"].is_null());
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Above this many buckets the in-memory limiter drops stale ones.
+const MAX_IN_MEMORY_BUCKETS: usize = 50_000;
+
+/// Fixed-window request limiter shared by every route.
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Count one request against `key` and return whether it is allowed,
+    /// i.e. at most `max_requests` were seen in the current `window`.
+    async fn check(&self, key: &str, max_requests: u32, window: Duration) -> bool;
+}
+
+/// Per-process buckets. The default, fine for single-node dev; limits reset
+/// on restart and are not shared between instances.
+#[derive(Default)]
+pub struct InMemoryRateLimiter {
+    buckets: DashMap<String, (u32, Instant)>,
+}
+
+#[async_trait]
+impl RateLimiter for InMemoryRateLimiter {
+    async fn check(&self, key: &str, max_requests: u32, window: Duration) -> bool {
+        let now = Instant::now();
+        let allowed = {
+            let mut entry = self.buckets.entry(key.to_string()).or_insert((0, now));
+            let (count, window_start) = entry.value_mut();
+
+            if now.duration_since(*window_start) >= window {
+                *count = 0;
+                *window_start = now;
+            }
+
+            if *count >= max_requests {
+                false
+            } else {
+                *count += 1;
+                true
+            }
+        };
+
+        if self.buckets.len() > MAX_IN_MEMORY_BUCKETS {
+            let stale_after = window + window;
+            self.buckets
+                .retain(|_, (_, started_at)| now.duration_since(*started_at) < stale_after);
+        }
+
+        allowed
+    }
+}
+
+/// Buckets stored in Redis, so limits hold across restarts and are shared by
+/// every instance pointing at the same Redis.
+#[cfg(feature = "redis")]
+pub struct RedisRateLimiter {
+    conn: redis::aio::ConnectionManager,
+    script: redis::Script,
+}
+
+#[cfg(feature = "redis")]
+impl RedisRateLimiter {
+    pub async fn connect(url: &str) -> redis::RedisResult<Self> {
+        let client = redis::Client::open(url)?;
+        let conn = redis::aio::ConnectionManager::new(client).await?;
+        // INCR and the first PEXPIRE must be atomic, or a crash in between
+        // leaves a bucket that never expires
+        let script = redis::Script::new(
+            r#"
+            local count = redis.call('INCR', KEYS[1])
+            if count == 1 then
+                redis.call('PEXPIRE', KEYS[1], ARGV[1])
+            end
+            return count
+            "#,
+        );
+        Ok(Self { conn, script })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn check(&self, key: &str, max_requests: u32, window: Duration) -> bool {
+        let mut conn = self.conn.clone();
+        let result: redis::RedisResult<u64> = self
+            .script
+            .key(format!("ratelimit:{}", key))
+            .arg(window.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await;
+
+        match result {
+            Ok(count) => count <= u64::from(max_requests),
+            Err(e) => {
+                // Fail open: a Redis outage should not take the API down
+                tracing::warn!("Rate limit check failed, allowing request: {}", e);
+                true
+            }
+        }
+    }
+}
+
+/// Pick the backend from `RATE_LIMIT_REDIS_URL`, falling back to in-memory
+/// buckets when it is unset, unreachable or the `redis` feature is off.
+pub async fn from_env() -> Arc<dyn RateLimiter> {
+    let Some(url) = std::env::var("RATE_LIMIT_REDIS_URL")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    else {
+        return Arc::new(InMemoryRateLimiter::default());
+    };
+
+    #[cfg(feature = "redis")]
+    match RedisRateLimiter::connect(url.trim()).await {
+        Ok(limiter) => {
+            tracing::info!("Rate limiting backed by Redis");
+            return Arc::new(limiter);
+        }
+        Err(e) => {
+            tracing::error!(
+                "Could not connect to rate limit Redis, using in-memory buckets: {}",
+                e
+            );
+        }
+    }
+
+    #[cfg(not(feature = "redis"))]
+    {
+        let _ = url;
+        tracing::warn!(
+            "RATE_LIMIT_REDIS_URL is set but the server was built without the `redis` feature; \
+             using in-memory buckets"
+        );
+    }
+
+    Arc::new(InMemoryRateLimiter::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn in_memory_limiter_blocks_past_budget_and_resets_per_window() {
+        let limiter = InMemoryRateLimiter::default();
+        let window = Duration::from_millis(50);
+
+        assert!(limiter.check("api:a", 2, window).await);
+        assert!(limiter.check("api:a", 2, window).await);
+        assert!(!limiter.check("api:a", 2, window).await);
+        // Other keys have their own budget
+        assert!(limiter.check("api:b", 2, window).await);
+
+        tokio::time::sleep(window).await;
+        assert!(limiter.check("api:a", 2, window).await);
+    }
+}
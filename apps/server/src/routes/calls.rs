@@ -0,0 +1,132 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::auth::{generate_guest_token, AuthUser, CallParticipant, MAX_GUEST_TOKEN_SECONDS};
+use crate::ice::IceServer;
+use crate::state::AppState;
+
+/// Lifetime of a guest link when the request does not pick one
+const DEFAULT_GUEST_TOKEN_SECONDS: i64 = 60 * 60;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/ice-servers", get(get_ice_servers))
+        .route("/ice/credentials", get(get_ice_credentials))
+        .route("/guest-links", post(create_guest_link))
+}
+
+#[derive(Debug, Serialize)]
+struct IceServersResponse {
+    /// Empty when the server has no ICE configuration; clients then keep
+    /// their own.
+    ice_servers: Vec<IceServer>,
+}
+
+/// The server's STUN/TURN list. Authenticated, since TURN entries carry
+/// credentials; guests may fetch it too so their link calls can relay.
+async fn get_ice_servers(
+    State(state): State<AppState>,
+    participant: CallParticipant,
+) -> Json<IceServersResponse> {
+    tracing::debug!(
+        user_id = %participant.id,
+        guest = participant.guest,
+        "serving ICE servers"
+    );
+    Json(IceServersResponse {
+        ice_servers: state.ice_servers.as_ref().clone(),
+    })
+}
+
+/// Short-lived TURN credentials minted for the caller, after the server's
+/// STUN entries. A bare list in the shape of the desktop's
+/// `IceServerConfig`. 404 when no `ICE_TURN_SECRET` is configured.
+async fn get_ice_credentials(
+    State(state): State<AppState>,
+    participant: CallParticipant,
+) -> Result<Json<Vec<IceServer>>, StatusCode> {
+    let turn = state.turn_secret.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut servers: Vec<IceServer> = state
+        .ice_servers
+        .iter()
+        .filter(|server| server.username.is_none())
+        .cloned()
+        .collect();
+    servers.push(turn.mint(&participant.id.to_string(), Utc::now().timestamp()));
+
+    tracing::debug!(
+        user_id = %participant.id,
+        guest = participant.guest,
+        "minted TURN credentials"
+    );
+    Ok(Json(servers))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct CreateGuestLinkRequest {
+    /// Token lifetime in seconds; defaults to one hour.
+    #[validate(range(min = 60, max = 86_400))]
+    expires_in_seconds: Option<i64>,
+    /// Shown to the host when the guest calls; defaults to "Guest".
+    #[validate(length(min = 1, max = 32))]
+    guest_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct GuestLinkResponse {
+    /// Bearer token the guest identifies with on the WebSocket
+    token: String,
+    guest_id: Uuid,
+    /// The only user the guest can call: the link's creator
+    target_id: Uuid,
+    expires_at: DateTime<Utc>,
+}
+
+/// Mint a guest token for a "join my call" link. The guest can only exchange
+/// call signaling with the caller of this endpoint; chat, servers and every
+/// other REST route reject the token.
+async fn create_guest_link(
+    user: AuthUser,
+    Json(req): Json<CreateGuestLinkRequest>,
+) -> Result<Json<GuestLinkResponse>, StatusCode> {
+    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let guest_name = req
+        .guest_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or("Guest");
+    let lifetime = Duration::seconds(
+        req.expires_in_seconds
+            .unwrap_or(DEFAULT_GUEST_TOKEN_SECONDS)
+            .min(MAX_GUEST_TOKEN_SECONDS),
+    );
+
+    let (token, guest_id) = generate_guest_token(user.id, guest_name, lifetime).map_err(|e| {
+        tracing::error!("Failed to mint guest token: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!(
+        host_id = %user.id,
+        guest_id = %guest_id,
+        "guest call link created"
+    );
+
+    Ok(Json(GuestLinkResponse {
+        token,
+        guest_id,
+        target_id: user.id,
+        expires_at: Utc::now() + lifetime,
+    }))
+}
@@ -5,22 +5,28 @@ use axum::{
     routing::{delete, get, post},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::auth::{AuthError, AuthUser};
+use crate::mentions::{record_mention, MentionScope};
+use crate::message_delete::delete_message_tree;
 use crate::models::{Message, Room};
+use crate::search::{contains_pattern, snippet};
 use crate::state::AppState;
 use crate::validation::{extract_mentions, validate_emoji, validate_message_content};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/dm", post(create_or_get_dm))
+        .route("/search", get(search_all_dms))
         .route("/:room_id/typing", post(send_typing))
         .route("/:room_id/read", post(mark_room_read))
         .route("/:room_id/messages/search", get(search_messages))
+        .route("/:room_id/messages/status", post(get_message_statuses))
         .route(
             "/:room_id/messages",
             get(get_messages)
@@ -92,6 +98,27 @@ struct ReadRequest {
     upto_message_id: Option<Uuid>,
 }
 
+/// Either explicit ids or a page shaped like `GET /messages` (`before` +
+/// `limit`); ids win when both are given.
+#[derive(Deserialize, Validate)]
+struct MessageStatusRequest {
+    #[validate(length(min = 1, max = 200))]
+    message_ids: Option<Vec<Uuid>>,
+    before: Option<Uuid>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct MessageStatusEntry {
+    message_id: Uuid,
+    /// "sent", "delivered" or "read". Reads by users who turned read
+    /// receipts off show as "delivered".
+    status: String,
+    /// Earliest receipt from anyone other than the sender
+    delivered_at: Option<DateTime<Utc>>,
+    read_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Deserialize, Validate)]
 struct SearchMessagesQuery {
     #[validate(length(min = 1, max = 128))]
@@ -99,6 +126,51 @@ struct SearchMessagesQuery {
     limit: Option<i64>,
 }
 
+#[derive(Deserialize, Validate)]
+struct DmSearchQuery {
+    #[validate(length(min = 1, max = 128))]
+    q: String,
+    limit: Option<i64>,
+    /// Cursor: `next_before` from the previous page
+    before: Option<Uuid>,
+}
+
+#[derive(sqlx::FromRow)]
+struct DmSearchRow {
+    id: Uuid,
+    room_id: Uuid,
+    sender_id: Option<Uuid>,
+    content: String,
+    created_at: Option<DateTime<Utc>>,
+    peer_id: Option<Uuid>,
+    peer_username: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DmSearchMatch {
+    message_id: Uuid,
+    sender_id: Option<Uuid>,
+    snippet: String,
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+struct DmSearchConversation {
+    room_id: Uuid,
+    /// The other member of the DM
+    peer_id: Option<Uuid>,
+    peer_username: Option<String>,
+    matches: Vec<DmSearchMatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct DmSearchResponse {
+    /// Ordered by each conversation's newest match
+    conversations: Vec<DmSearchConversation>,
+    /// Pass as `before` for the next page; `None` on the last one
+    next_before: Option<Uuid>,
+}
+
 #[derive(Deserialize, Validate)]
 struct ReactionRequest {
     #[validate(length(min = 1, max = 32), custom(function = "validate_emoji"))]
@@ -341,10 +413,11 @@ async fn send_message(
     .bind(req.parent_message_id)
     .fetch_one(&state.db)
     .await?;
+    state.metrics.record_message_sent(user.id);
 
     // Log if message is encrypted
     if req.nonce.is_some() {
-        println!("🔐 Encrypted message sent (nonce present)");
+        tracing::info!("🔐 Encrypted message sent (nonce present)");
     }
 
     // Broadcast via WebSocket
@@ -354,12 +427,12 @@ async fn send_message(
     });
     let ws_text = serde_json::to_string(&ws_payload).unwrap();
 
-    println!("📢 Broadcasting message to {} room members", members.len());
+    tracing::info!("📢 Broadcasting message to {} room members", members.len());
     for member_id in members {
         if let Some(peer_tx) = state.peers.get(&member_id.to_string()) {
             match peer_tx.send(WsMessage::Text(ws_text.clone())) {
-                Ok(_) => println!("    ✅ Sent to {}", member_id),
-                Err(e) => eprintln!("    ❌ Failed to send to {}: {}", member_id, e),
+                Ok(_) => tracing::debug!("✅ Sent to {}", member_id),
+                Err(e) => tracing::warn!("❌ Failed to send to {}: {}", member_id, e),
             }
         }
     }
@@ -385,6 +458,17 @@ async fn send_message(
             if mentioned_user_id == user.id {
                 continue;
             }
+            if let Err(e) = record_mention(
+                &state.db,
+                MentionScope::Dm { room_id },
+                message.id,
+                mentioned_user_id,
+                user.id,
+            )
+            .await
+            {
+                tracing::warn!("Failed to record mention: {}", e);
+            }
             if let Some(peer_tx) = state.peers.get(&mentioned_user_id.to_string()) {
                 let mention_payload = serde_json::json!({
                     "type": "MENTION_ALERT",
@@ -484,17 +568,16 @@ async fn mark_message_delivered(
 
     if let Some(sender_id) = sender_id {
         if sender_id != user.id {
-            if let Some(peer_tx) = state.peers.get(&sender_id.to_string()) {
-                let ws_payload = serde_json::json!({
-                    "type": "MESSAGE_STATUS",
+            send_receipt(
+                &state,
+                sender_id,
+                serde_json::json!({
+                    "type": "MESSAGE_DELIVERED",
                     "room_id": room_id,
                     "message_id": message_id,
-                    "status": "delivered",
                     "user_id": user.id,
-                });
-                let ws_text = serde_json::to_string(&ws_payload).unwrap();
-                let _ = peer_tx.send(WsMessage::Text(ws_text));
-            }
+                }),
+            );
         }
     }
 
@@ -557,6 +640,7 @@ async fn mark_room_read(
         .await?
     };
 
+    let mut read_by_sender: BTreeMap<Uuid, Vec<Uuid>> = BTreeMap::new();
     for (message_id, sender_id) in rows {
         sqlx::query(
             r#"
@@ -574,23 +658,162 @@ async fn mark_room_read(
         .await?;
 
         if let Some(sender_id) = sender_id {
-            if let Some(peer_tx) = state.peers.get(&sender_id.to_string()) {
-                let ws_payload = serde_json::json!({
-                    "type": "MESSAGE_STATUS",
+            read_by_sender
+                .entry(sender_id)
+                .or_default()
+                .push(message_id);
+        }
+    }
+
+    // The read is recorded either way, for the reader's own unread state;
+    // only telling the senders is up to the privacy setting
+    if read_receipts_enabled(&state, user.id).await {
+        for (sender_id, message_ids) in read_by_sender {
+            send_receipt(
+                &state,
+                sender_id,
+                serde_json::json!({
+                    "type": "MESSAGE_READ",
                     "room_id": room_id,
-                    "message_id": message_id,
-                    "status": "read",
+                    "message_ids": message_ids,
                     "user_id": user.id,
-                });
-                let ws_text = serde_json::to_string(&ws_payload).unwrap();
-                let _ = peer_tx.send(WsMessage::Text(ws_text));
-            }
+                }),
+            );
         }
     }
 
+    // Reading a DM also reads the mentions in it, up to the same message
+    sqlx::query(
+        r#"
+        UPDATE mentions mn SET read_at = NOW()
+        FROM messages m
+        WHERE m.id = mn.message_id
+          AND mn.mentioned_user_id = $1
+          AND mn.room_id = $2
+          AND mn.read_at IS NULL
+          AND ($3::uuid IS NULL OR m.created_at <= (SELECT created_at FROM messages WHERE id = $3))
+        "#,
+    )
+    .bind(user.id)
+    .bind(room_id)
+    .bind(req.upto_message_id)
+    .execute(&state.db)
+    .await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Push a `MESSAGE_DELIVERED`/`MESSAGE_READ` event to the sender of the
+/// messages it is about, if they are connected. Nobody else gets it.
+fn send_receipt(state: &AppState, sender_id: Uuid, payload: serde_json::Value) {
+    if let Some(peer_tx) = state.peers.get(&sender_id.to_string()) {
+        let ws_text = serde_json::to_string(&payload).unwrap();
+        let _ = peer_tx.send(WsMessage::Text(ws_text));
+    }
+}
+
+/// Whether the user lets senders see that they read a message. On unless
+/// turned off in their settings.
+async fn read_receipts_enabled(state: &AppState, user_id: Uuid) -> bool {
+    sqlx::query_scalar::<_, bool>("SELECT send_read_receipts FROM user_settings WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(true)
+}
+
+/// Delivery/read status for many messages in one round-trip, so a client
+/// opening a long conversation does not ask message by message. Ids that are
+/// not in this room are left out of the response.
+async fn get_message_statuses(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(room_id): Path<Uuid>,
+    Json(req): Json<MessageStatusRequest>,
+) -> Result<Json<Vec<MessageStatusEntry>>, AuthError> {
+    req.validate()
+        .map_err(|e| AuthError::Validation(e.to_string()))?;
+
+    let is_member = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM room_members WHERE room_id = $1 AND user_id = $2",
+    )
+    .bind(room_id)
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await?
+        > 0;
+
+    if !is_member {
+        return Err(AuthError::InvalidToken);
+    }
+
+    // Receipts the sender left on their own message do not count, nor do
+    // reads by users who turned read receipts off
+    let statuses = if let Some(message_ids) = req.message_ids {
+        sqlx::query_as::<_, MessageStatusEntry>(
+            r#"
+            SELECT m.id AS message_id,
+                   CASE
+                       WHEN MIN(mr.read_at) FILTER (WHERE COALESCE(us.send_read_receipts, TRUE)) IS NOT NULL THEN 'read'
+                       WHEN MIN(mr.delivered_at) IS NOT NULL THEN 'delivered'
+                       ELSE 'sent'
+                   END AS status,
+                   MIN(mr.delivered_at) AS delivered_at,
+                   MIN(mr.read_at) FILTER (WHERE COALESCE(us.send_read_receipts, TRUE)) AS read_at
+            FROM messages m
+            LEFT JOIN message_receipts mr
+                   ON mr.message_id = m.id AND mr.user_id IS DISTINCT FROM m.sender_id
+            LEFT JOIN user_settings us ON us.user_id = mr.user_id
+            WHERE m.room_id = $1 AND m.id = ANY($2)
+            GROUP BY m.id, m.created_at
+            ORDER BY m.created_at ASC
+            "#,
+        )
+        .bind(room_id)
+        .bind(&message_ids)
+        .fetch_all(&state.db)
+        .await?
+    } else {
+        let limit = req.limit.unwrap_or(100).clamp(1, 200);
+        sqlx::query_as::<_, MessageStatusEntry>(
+            r#"
+            WITH page AS (
+                SELECT id, sender_id, created_at
+                FROM messages
+                WHERE room_id = $1
+                  AND parent_message_id IS NULL
+                  AND ($2::uuid IS NULL OR created_at < (SELECT created_at FROM messages WHERE id = $2))
+                ORDER BY created_at DESC
+                LIMIT $3
+            )
+            SELECT p.id AS message_id,
+                   CASE
+                       WHEN MIN(mr.read_at) FILTER (WHERE COALESCE(us.send_read_receipts, TRUE)) IS NOT NULL THEN 'read'
+                       WHEN MIN(mr.delivered_at) IS NOT NULL THEN 'delivered'
+                       ELSE 'sent'
+                   END AS status,
+                   MIN(mr.delivered_at) AS delivered_at,
+                   MIN(mr.read_at) FILTER (WHERE COALESCE(us.send_read_receipts, TRUE)) AS read_at
+            FROM page p
+            LEFT JOIN message_receipts mr
+                   ON mr.message_id = p.id AND mr.user_id IS DISTINCT FROM p.sender_id
+            LEFT JOIN user_settings us ON us.user_id = mr.user_id
+            GROUP BY p.id, p.created_at
+            ORDER BY p.created_at ASC
+            "#,
+        )
+        .bind(room_id)
+        .bind(req.before)
+        .bind(limit)
+        .fetch_all(&state.db)
+        .await?
+    };
+
+    Ok(Json(statuses))
+}
+
 /// Search messages in a DM room.
 async fn search_messages(
     State(state): State<AppState>,
@@ -630,6 +853,88 @@ async fn search_messages(
     Ok(Json(messages))
 }
 
+/// Search every DM the user is a member of. Matches are paged newest first
+/// across all conversations, then grouped by conversation with a snippet
+/// around the match. End-to-end encrypted messages (those with a nonce) are
+/// ciphertext here and are left out; the client's local index covers them.
+async fn search_all_dms(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(params): Query<DmSearchQuery>,
+) -> Result<Json<DmSearchResponse>, AuthError> {
+    params
+        .validate()
+        .map_err(|e| AuthError::Validation(e.to_string()))?;
+
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err(AuthError::Validation("Search query is empty".to_string()));
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    // One extra row tells us whether there is another page
+    let mut rows = sqlx::query_as::<_, DmSearchRow>(
+        r#"
+        SELECT m.id, m.room_id, m.sender_id, m.content, m.created_at,
+               peer.id AS peer_id, peer.username AS peer_username
+        FROM messages m
+        JOIN rooms r ON r.id = m.room_id AND r.is_dm = TRUE
+        JOIN room_members me ON me.room_id = m.room_id AND me.user_id = $1
+        LEFT JOIN LATERAL (
+            SELECT u.id, u.username
+            FROM room_members pm
+            JOIN users u ON u.id = pm.user_id
+            WHERE pm.room_id = m.room_id AND pm.user_id <> $1
+            LIMIT 1
+        ) peer ON TRUE
+        WHERE m.content ILIKE $2
+          AND m.nonce IS NULL
+          AND ($3::uuid IS NULL OR (m.created_at, m.id) < (
+              SELECT created_at, id FROM messages WHERE id = $3
+          ))
+        ORDER BY m.created_at DESC, m.id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(user.id)
+    .bind(contains_pattern(query))
+    .bind(params.before)
+    .bind(limit + 1)
+    .fetch_all(&state.db)
+    .await?;
+
+    let next_before = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| row.id)
+    } else {
+        None
+    };
+
+    let mut conversations: Vec<DmSearchConversation> = Vec::new();
+    for row in rows {
+        let matched = DmSearchMatch {
+            message_id: row.id,
+            sender_id: row.sender_id,
+            snippet: snippet(&row.content, query, 40),
+            created_at: row.created_at,
+        };
+        match conversations.iter_mut().find(|c| c.room_id == row.room_id) {
+            Some(conversation) => conversation.matches.push(matched),
+            None => conversations.push(DmSearchConversation {
+                room_id: row.room_id,
+                peer_id: row.peer_id,
+                peer_username: row.peer_username,
+                matches: vec![matched],
+            }),
+        }
+    }
+
+    Ok(Json(DmSearchResponse {
+        conversations,
+        next_before,
+    }))
+}
+
 /// List reactions for a DM message.
 async fn get_message_reactions(
     State(state): State<AppState>,
@@ -694,6 +999,7 @@ async fn add_message_reaction(
     .bind(req.emoji.trim())
     .execute(&state.db)
     .await?;
+    state.metrics.record_reaction_added(user.id);
 
     let reactions = fetch_message_reactions(&state, message_id).await?;
     let payload = serde_json::json!({
@@ -737,6 +1043,7 @@ async fn remove_message_reaction(
     .bind(emoji.trim())
     .execute(&state.db)
     .await?;
+    state.metrics.record_reaction_removed(user.id);
 
     let reactions = fetch_message_reactions(&state, message_id).await?;
     let payload = serde_json::json!({
@@ -816,20 +1123,27 @@ async fn delete_message(
     }
 
     // Delete only if user is the sender
-    let result =
-        sqlx::query("DELETE FROM messages WHERE id = $1 AND room_id = $2 AND sender_id = $3")
-            .bind(message_id)
-            .bind(room_id)
-            .bind(user.id)
-            .execute(&state.db)
-            .await?;
+    let owned = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM messages WHERE id = $1 AND room_id = $2 AND sender_id = $3",
+    )
+    .bind(message_id)
+    .bind(room_id)
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await?
+        > 0;
 
-    if result.rows_affected() == 0 {
-        println!("⚠️ Delete failed: message not found or not owned by user");
+    let deleted = if owned {
+        delete_message_tree(&state.db, message_id).await?
+    } else {
+        None
+    };
+    let Some(deleted) = deleted else {
+        tracing::warn!("⚠️ Delete failed: message not found or not owned by user");
         return Err(AuthError::InvalidToken);
-    }
+    };
 
-    println!("🗑️ Message {} deleted by user {}", message_id, user.id);
+    tracing::info!("🗑️ Message {} deleted by user {}", message_id, user.id);
 
     // Broadcast deletion via WebSocket
     let members =
@@ -841,7 +1155,8 @@ async fn delete_message(
     let ws_payload = serde_json::json!({
         "type": "MESSAGE_DELETED",
         "message_id": message_id,
-        "room_id": room_id
+        "room_id": room_id,
+        "reply_ids": deleted.reply_ids
     });
     let ws_text = serde_json::to_string(&ws_payload).unwrap();
 
@@ -893,7 +1208,7 @@ async fn delete_all_messages(
         .execute(&state.db)
         .await?;
 
-    println!(
+    tracing::info!(
         "🗑️ Deleted {} messages from room {} (by user {})",
         result.rows_affected(),
         room_id,
@@ -960,6 +1275,12 @@ async fn edit_message(
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    // Reactions stay attached to the edited message; send them along so
+    // clients re-render them against the new content
+    let reactions = fetch_message_reactions(&state, message_id)
+        .await
+        .unwrap_or_default();
+
     // Broadcast edit to room members via WebSocket
     let member_ids: Vec<Uuid> =
         sqlx::query_scalar("SELECT user_id FROM room_members WHERE room_id = $1")
@@ -970,7 +1291,8 @@ async fn edit_message(
 
     let ws_payload = serde_json::json!({
         "type": "MESSAGE_EDITED",
-        "message": updated
+        "message": updated,
+        "reactions": reactions
     });
     let ws_text = serde_json::to_string(&ws_payload).unwrap();
 
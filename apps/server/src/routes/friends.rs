@@ -33,12 +33,18 @@ pub struct FriendInfo {
     pub avatar_url: Option<String>,
     pub status: String,
     pub last_seen: Option<DateTime<Utc>>,
+    /// Chosen presence (`online`/`away`/`dnd`); only shared with accepted friends
+    pub presence_status: Option<String>,
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct FriendRequest {
     #[validate(length(min = 3, max = 32), custom(function = "validate_username"))]
     pub username: String,
+    /// Idempotency key: a retry with the same key reports the first request
+    /// as sent instead of as a duplicate
+    pub client_id: Option<Uuid>,
 }
 
 /// Get all accepted friends
@@ -48,12 +54,16 @@ async fn list_friends(
 ) -> Result<Json<Vec<FriendInfo>>, AuthError> {
     let friends = sqlx::query_as::<_, FriendInfo>(
         r#"
-        SELECT u.id, u.username, u.avatar_url, f.status, u.last_seen
+        SELECT
+            u.id, u.username, u.avatar_url, f.status, u.last_seen,
+            COALESCE(us.presence_status, 'online') AS presence_status,
+            u.status_message
         FROM friendships f
         JOIN users u ON (
             (f.friend_id = u.id AND f.user_id = $1)
             OR (f.user_id = u.id AND f.friend_id = $1)
         )
+        LEFT JOIN user_settings us ON us.user_id = u.id
         WHERE f.status = 'accepted'
         AND u.id != $1
         "#,
@@ -85,6 +95,10 @@ async fn send_request(
         return Ok(Json(serde_json::json!({ "error": "Cannot add yourself" })));
     }
 
+    if sent_with_client_id(&state, user.id, target, req.client_id).await? {
+        return Ok(Json(request_sent()));
+    }
+
     // Check if friendship exists
     let existing = sqlx::query_scalar::<_, i64>(
         r#"
@@ -103,16 +117,55 @@ async fn send_request(
         ));
     }
 
-    // Create friendship request
-    sqlx::query("INSERT INTO friendships (user_id, friend_id, status) VALUES ($1, $2, 'pending')")
-        .bind(user.id)
-        .bind(target)
-        .execute(&state.db)
-        .await?;
+    // Create friendship request. Concurrent retries race on the unique
+    // indexes; only one of them inserts.
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO friendships (user_id, friend_id, status, client_id)
+        VALUES ($1, $2, 'pending', $3)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(user.id)
+    .bind(target)
+    .bind(req.client_id)
+    .execute(&state.db)
+    .await?
+    .rows_affected()
+        > 0;
 
-    Ok(Json(
-        serde_json::json!({ "success": true, "message": "Friend request sent" }),
-    ))
+    if !inserted && !sent_with_client_id(&state, user.id, target, req.client_id).await? {
+        return Ok(Json(
+            serde_json::json!({ "error": "Friend request already exists" }),
+        ));
+    }
+
+    Ok(Json(request_sent()))
+}
+
+fn request_sent() -> serde_json::Value {
+    serde_json::json!({ "success": true, "message": "Friend request sent" })
+}
+
+/// Whether `user_id` already sent `target` a request under `client_id`
+async fn sent_with_client_id(
+    state: &AppState,
+    user_id: Uuid,
+    target: Uuid,
+    client_id: Option<Uuid>,
+) -> Result<bool, AuthError> {
+    let Some(client_id) = client_id else {
+        return Ok(false);
+    };
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM friendships WHERE user_id = $1 AND friend_id = $2 AND client_id = $3",
+    )
+    .bind(user_id)
+    .bind(target)
+    .bind(client_id)
+    .fetch_one(&state.db)
+    .await?;
+    Ok(count > 0)
 }
 
 /// Accept a friend request
@@ -192,7 +245,10 @@ async fn list_pending(
 ) -> Result<Json<Vec<FriendInfo>>, AuthError> {
     let pending = sqlx::query_as::<_, FriendInfo>(
         r#"
-        SELECT u.id, u.username, u.avatar_url, f.status, u.last_seen
+        SELECT
+            u.id, u.username, u.avatar_url, f.status, u.last_seen,
+            NULL::VARCHAR AS presence_status,
+            NULL::VARCHAR AS status_message
         FROM friendships f
         JOIN users u ON f.user_id = u.id
         WHERE f.friend_id = $1 AND f.status = 'pending'
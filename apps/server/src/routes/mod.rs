@@ -1,5 +1,6 @@
 pub mod app;
 pub mod auth;
+pub mod calls;
 pub mod chat;
 pub mod friends;
 pub mod servers;
@@ -1,6 +1,6 @@
 use axum::extract::ws::Message as WsMessage;
 use axum::{
-    extract::{Path, Query, State},
+    extract::{rejection::JsonRejection, Path, Query, State},
     http::StatusCode,
     routing::{delete, get, post, put},
     Json, Router,
@@ -12,11 +12,14 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::auth::AuthUser;
-use crate::models::{Channel, ChannelMessage, Server, ServerMemberWithUser};
+use crate::error::ApiError;
+use crate::mentions::{record_mention, MentionScope};
+use crate::message_delete::delete_message_tree;
+use crate::models::{Attachment, Channel, ChannelMessage, Server, ServerMemberWithUser};
 use crate::state::AppState;
 use crate::validation::{
-    extract_mentions, validate_avatar_url, validate_channel_name, validate_emoji,
-    validate_message_content, validate_server_name,
+    extract_mentions, validate_attachments, validate_avatar_url, validate_channel_name,
+    validate_emoji, validate_message_content, validate_server_name,
 };
 
 pub fn router() -> Router<AppState> {
@@ -24,6 +27,11 @@ pub fn router() -> Router<AppState> {
         .route("/", get(list_servers).post(create_server))
         .route("/:id", get(get_server).delete(delete_server))
         .route("/:id/invite/regenerate", post(regenerate_invite_code))
+        .route(
+            "/:id/invites",
+            get(list_server_invites).post(create_server_invite),
+        )
+        .route("/:id/invites/:invite_id", delete(revoke_server_invite))
         .route("/:id/members", get(get_members))
         .route("/:id/members/:member_id/role", put(update_member_role))
         .route("/:id/members/:member_id/kick", post(kick_member))
@@ -51,6 +59,16 @@ pub fn router() -> Router<AppState> {
             "/:id/channels/:channel_id/voice/leave",
             post(leave_voice_channel),
         )
+        .route(
+            "/:id/channels/:channel_id/nudge",
+            post(nudge_voice_participant),
+        )
+        .route(
+            "/:id/channels/:channel_id/permissions",
+            get(list_channel_permissions).put(update_channel_permission),
+        )
+        .route("/:id/channels/:channel_id/pins", get(list_channel_pins))
+        .route("/:id/channels/:channel_id/read", post(mark_channel_read))
         .route(
             "/:id/channels/:channel_id/messages/search",
             get(search_channel_messages),
@@ -71,9 +89,13 @@ pub fn router() -> Router<AppState> {
             "/:id/channels/:channel_id/messages/:message_id/thread",
             get(get_channel_thread_messages).post(send_channel_thread_message),
         )
+        .route(
+            "/:id/channels/:channel_id/messages/:message_id/pin",
+            post(pin_channel_message).delete(unpin_channel_message),
+        )
         .route(
             "/:id/channels/:channel_id/messages/:message_id",
-            put(edit_channel_message),
+            put(edit_channel_message).delete(delete_channel_message),
         )
         .route("/join/:code", post(join_server))
         .route("/:id/leave", post(leave_server))
@@ -84,6 +106,12 @@ fn generate_invite_code() -> String {
     Uuid::new_v4().to_string()[..8].to_uppercase()
 }
 
+// `server_invites` codes are longer than the legacy 8-character
+// `servers.invite_code`, so the two can never collide.
+fn generate_server_invite_code() -> String {
+    Uuid::new_v4().simple().to_string()[..SERVER_INVITE_CODE_LEN].to_uppercase()
+}
+
 // === Request/Response types ===
 
 #[derive(Deserialize, Validate)]
@@ -91,6 +119,8 @@ pub struct CreateServerRequest {
     #[validate(length(min = 2, max = 100), custom(function = "validate_server_name"))]
     pub name: String,
     pub icon_url: Option<String>,
+    /// Idempotency key: a retry with the same key returns the first server
+    pub client_id: Option<Uuid>,
 }
 
 #[derive(Deserialize, Validate)]
@@ -98,6 +128,8 @@ pub struct CreateChannelRequest {
     #[validate(length(min = 1, max = 64), custom(function = "validate_channel_name"))]
     pub name: String,
     pub channel_type: Option<String>,
+    /// Idempotency key: a retry with the same key returns the first channel
+    pub client_id: Option<Uuid>,
 }
 
 #[derive(Deserialize, Validate)]
@@ -110,6 +142,10 @@ pub struct SendMessageRequest {
     pub nonce: Option<String>,
     pub client_id: Option<Uuid>,
     pub parent_message_id: Option<Uuid>,
+    /// Older clients send no attachments
+    #[serde(default)]
+    #[validate(custom(function = "validate_attachments"))]
+    pub attachments: Vec<Attachment>,
 }
 
 #[derive(Deserialize, Validate)]
@@ -133,6 +169,41 @@ pub struct TypingRequest {
     pub is_typing: bool,
 }
 
+/// How a member takes part in a voice channel. Listeners receive audio but
+/// never send any, so the mesh skips their outbound track.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceSessionMode {
+    #[default]
+    Speak,
+    Listen,
+}
+
+impl VoiceSessionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VoiceSessionMode::Speak => "speak",
+            VoiceSessionMode::Listen => "listen",
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct JoinVoiceRequest {
+    #[serde(default)]
+    pub mode: VoiceSessionMode,
+}
+
+#[derive(Deserialize)]
+pub struct ChannelReadRequest {
+    pub upto_message_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct NudgeRequest {
+    pub target_user_id: Uuid,
+}
+
 #[derive(Deserialize, Validate)]
 pub struct SearchChannelMessagesQuery {
     #[validate(length(min = 1, max = 128))]
@@ -146,11 +217,40 @@ pub struct UpdateChannelRequest {
     pub position: Option<i32>,
 }
 
+#[derive(Deserialize, Validate)]
+pub struct CreateInviteRequest {
+    /// Lifetime in seconds; omitted means the invite never expires.
+    #[validate(range(min = 60, max = 2_592_000))]
+    pub expires_in_seconds: Option<i64>,
+    /// Omitted means unlimited uses.
+    #[validate(range(min = 1, max = 1000))]
+    pub max_uses: Option<i32>,
+}
+
 #[derive(Deserialize)]
 pub struct UpdateMemberRoleRequest {
     pub role: String,
 }
 
+#[derive(Deserialize)]
+pub struct UpdateChannelPermissionRequest {
+    /// `admin` or `member`; the owner is never restricted
+    pub role: String,
+    pub can_join: bool,
+    pub can_speak: bool,
+}
+
+/// What one role may do in a voice channel. Roles without a row may do
+/// everything.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ChannelPermission {
+    pub channel_id: Uuid,
+    pub role: String,
+    pub can_join: bool,
+    pub can_speak: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
 #[derive(Deserialize)]
 pub struct BanMemberRequest {
     pub reason: Option<String>,
@@ -174,6 +274,55 @@ pub struct VoiceChannelParticipant {
     pub user_id: Uuid,
     pub username: String,
     pub joined_at: DateTime<Utc>,
+    /// `speak` or `listen`
+    pub mode: String,
+    /// Last reported over signaling; `false` until the client reports
+    #[sqlx(skip)]
+    pub muted: bool,
+    #[sqlx(skip)]
+    pub speaking: bool,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PinnedChannelMessage {
+    pub message_id: Uuid,
+    pub channel_id: Uuid,
+    pub sender_id: Option<Uuid>,
+    pub sender_username: Option<String>,
+    pub content: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub pinned_by: Uuid,
+    pub pinned_by_username: String,
+    pub pinned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ServerInvite {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub code: String,
+    pub created_by: Option<Uuid>,
+    pub created_by_username: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct InviteRedemption {
+    id: Uuid,
+    server_id: Uuid,
+    expires_at: Option<DateTime<Utc>>,
+    max_uses: Option<i32>,
+    uses: i32,
+}
+
+impl InviteRedemption {
+    fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|at| at > now) && self.max_uses.is_none_or(|max| self.uses < max)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
@@ -193,6 +342,14 @@ pub struct MessageReactionSummary {
     pub count: usize,
 }
 
+/// Most pins a single channel can hold.
+const MAX_PINS_PER_CHANNEL: i64 = 50;
+
+/// Most invites (expired ones included) a server can hold at once.
+const MAX_INVITES_PER_SERVER: i64 = 100;
+
+const SERVER_INVITE_CODE_LEN: usize = 10;
+
 fn can_manage_members(role: &str) -> bool {
     role == "owner" || role == "admin"
 }
@@ -205,11 +362,54 @@ fn can_manage_target(actor_role: &str, target_role: &str) -> bool {
     }
 }
 
+/// `404` unless `channel_id` is a voice channel of `server_id`
+async fn ensure_voice_channel(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), ApiError> {
+    let is_voice = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM channels WHERE id = $1 AND server_id = $2 AND channel_type = 'voice'",
+    )
+    .bind(channel_id)
+    .bind(server_id)
+    .fetch_one(&state.db)
+    .await?
+        > 0;
+    if !is_voice {
+        return Err(ApiError::NotFound("Voice channel not found".to_string()));
+    }
+    Ok(())
+}
+
+async fn ensure_channel_in_server(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), ApiError> {
+    let in_server = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM channels WHERE id = $1 AND server_id = $2",
+    )
+    .bind(channel_id)
+    .bind(server_id)
+    .fetch_one(&state.db)
+    .await?
+        > 0;
+    if !in_server {
+        return Err(ApiError::NotFound("Channel not found".to_string()));
+    }
+    Ok(())
+}
+
+fn not_a_member() -> ApiError {
+    ApiError::Forbidden("Not a member of this server".to_string())
+}
+
 async fn fetch_server_role(
     state: &AppState,
     server_id: Uuid,
     user_id: Uuid,
-) -> Result<Option<String>, StatusCode> {
+) -> Result<Option<String>, ApiError> {
     sqlx::query_scalar::<_, String>(
         "SELECT role FROM server_members WHERE server_id = $1 AND user_id = $2",
     )
@@ -217,20 +417,43 @@ async fn fetch_server_role(
     .bind(user_id)
     .fetch_optional(&state.db)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    .map_err(ApiError::from)
+}
+
+/// Sender of a message in the given channel of the given server; `404` when
+/// the message is not there.
+async fn fetch_channel_message_sender(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> Result<Option<Uuid>, ApiError> {
+    sqlx::query_scalar::<_, Option<Uuid>>(
+        r#"
+        SELECT m.sender_id
+        FROM messages m
+        JOIN channels c ON c.id = m.channel_id
+        WHERE m.id = $1 AND m.channel_id = $2 AND c.server_id = $3
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(server_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Message not found".to_string()))
 }
 
 async fn fetch_message_reactions(
     state: &AppState,
     message_id: Uuid,
-) -> Result<Vec<MessageReactionSummary>, StatusCode> {
+) -> Result<Vec<MessageReactionSummary>, ApiError> {
     let rows = sqlx::query_as::<_, (String, Uuid)>(
         "SELECT emoji, user_id FROM message_reactions WHERE message_id = $1 ORDER BY created_at ASC"
     )
     .bind(message_id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     let mut grouped: BTreeMap<String, Vec<Uuid>> = BTreeMap::new();
     for (emoji, user_id) in rows {
@@ -250,19 +473,120 @@ async fn fetch_message_reactions(
         .collect())
 }
 
+async fn fetch_channel_pins(
+    state: &AppState,
+    channel_id: Uuid,
+) -> Result<Vec<PinnedChannelMessage>, ApiError> {
+    sqlx::query_as::<_, PinnedChannelMessage>(
+        r#"
+        SELECT
+            p.message_id,
+            p.channel_id,
+            m.sender_id,
+            sender.username as sender_username,
+            m.content,
+            m.created_at,
+            m.edited_at,
+            p.pinned_by,
+            pinner.username as pinned_by_username,
+            p.pinned_at
+        FROM pinned_messages p
+        JOIN messages m ON m.id = p.message_id
+        LEFT JOIN users sender ON sender.id = m.sender_id
+        JOIN users pinner ON pinner.id = p.pinned_by
+        WHERE p.channel_id = $1
+        ORDER BY p.pinned_at DESC
+        "#,
+    )
+    .bind(channel_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(ApiError::from)
+}
+
+/// Owners and admins may pin; the message must live in this server's channel.
+async fn authorize_pin_change(
+    state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> Result<(), ApiError> {
+    let role = fetch_server_role(state, server_id, user_id)
+        .await?
+        .ok_or_else(not_a_member)?;
+    if !can_manage_members(&role) {
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can pin messages".to_string(),
+        ));
+    }
+
+    let in_channel = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*)
+        FROM messages m
+        JOIN channels c ON c.id = m.channel_id
+        WHERE m.id = $1 AND m.channel_id = $2 AND c.server_id = $3
+        "#,
+    )
+    .bind(message_id)
+    .bind(channel_id)
+    .bind(server_id)
+    .fetch_one(&state.db)
+    .await?
+        > 0;
+    if !in_channel {
+        return Err(ApiError::NotFound("Message not found".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn broadcast_message_pin(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    pinned: bool,
+) -> Result<(), ApiError> {
+    let members =
+        sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM server_members WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_all(&state.db)
+            .await?;
+
+    let ws_payload = serde_json::json!({
+        "type": if pinned { "MESSAGE_PINNED" } else { "MESSAGE_UNPINNED" },
+        "server_id": server_id,
+        "channel_id": channel_id,
+        "message_id": message_id,
+        "user_id": user_id,
+    });
+    let ws_text = serde_json::to_string(&ws_payload).unwrap();
+
+    for member_id in members {
+        if let Some(peer_tx) = state.peers.get(&member_id.to_string()) {
+            let _ = peer_tx.send(WsMessage::Text(ws_text.clone()));
+        }
+    }
+
+    Ok(())
+}
+
 async fn broadcast_voice_presence(
     state: &AppState,
     server_id: Uuid,
     channel_id: Uuid,
     user_id: Uuid,
     joined: bool,
-) -> Result<(), StatusCode> {
+    mode: Option<VoiceSessionMode>,
+) -> Result<(), ApiError> {
     let members =
         sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM server_members WHERE server_id = $1")
             .bind(server_id)
             .fetch_all(&state.db)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .await?;
 
     let ws_payload = serde_json::json!({
         "type": "VOICE_PRESENCE",
@@ -270,6 +594,7 @@ async fn broadcast_voice_presence(
         "channel_id": channel_id,
         "user_id": user_id,
         "joined": joined,
+        "mode": mode.map(VoiceSessionMode::as_str),
     });
     let ws_text = serde_json::to_string(&ws_payload).unwrap();
 
@@ -282,13 +607,83 @@ async fn broadcast_voice_presence(
     Ok(())
 }
 
+/// Apply the channel permission overrides to voice sessions already open:
+/// members whose role lost `can_join` are removed from the channel, and
+/// speakers whose role lost `can_speak` are moved to listen. Narrowed to one
+/// channel or one member when given.
+async fn enforce_voice_permissions(
+    state: &AppState,
+    server_id: Uuid,
+    channel_id: Option<Uuid>,
+    user_id: Option<Uuid>,
+) -> Result<(), ApiError> {
+    let removed = sqlx::query_as::<_, (Uuid, Uuid)>(
+        r#"
+        DELETE FROM voice_channel_sessions vcs
+        USING server_members sm, channel_permissions cp
+        WHERE sm.server_id = vcs.server_id AND sm.user_id = vcs.user_id
+          AND cp.channel_id = vcs.channel_id AND cp.role = sm.role
+          AND vcs.server_id = $1
+          AND ($2::uuid IS NULL OR vcs.channel_id = $2)
+          AND ($3::uuid IS NULL OR vcs.user_id = $3)
+          AND NOT cp.can_join
+        RETURNING vcs.channel_id, vcs.user_id
+        "#,
+    )
+    .bind(server_id)
+    .bind(channel_id)
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let demoted = sqlx::query_as::<_, (Uuid, Uuid)>(
+        r#"
+        UPDATE voice_channel_sessions vcs SET mode = $4
+        FROM server_members sm, channel_permissions cp
+        WHERE sm.server_id = vcs.server_id AND sm.user_id = vcs.user_id
+          AND cp.channel_id = vcs.channel_id AND cp.role = sm.role
+          AND vcs.server_id = $1
+          AND ($2::uuid IS NULL OR vcs.channel_id = $2)
+          AND ($3::uuid IS NULL OR vcs.user_id = $3)
+          AND vcs.mode = $5
+          AND NOT cp.can_speak
+        RETURNING vcs.channel_id, vcs.user_id
+        "#,
+    )
+    .bind(server_id)
+    .bind(channel_id)
+    .bind(user_id)
+    .bind(VoiceSessionMode::Listen.as_str())
+    .bind(VoiceSessionMode::Speak.as_str())
+    .fetch_all(&state.db)
+    .await?;
+
+    for (channel_id, user_id) in removed {
+        state.clear_voice_state(user_id);
+        let _ = broadcast_voice_presence(state, server_id, channel_id, user_id, false, None).await;
+    }
+    for (channel_id, user_id) in demoted {
+        let _ = broadcast_voice_presence(
+            state,
+            server_id,
+            channel_id,
+            user_id,
+            true,
+            Some(VoiceSessionMode::Listen),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
 // === Handlers ===
 
 /// List all servers the user is a member of
 async fn list_servers(
     State(state): State<AppState>,
     user: AuthUser,
-) -> Result<Json<Vec<Server>>, StatusCode> {
+) -> Result<Json<Vec<Server>>, ApiError> {
     let servers = sqlx::query_as::<_, Server>(
         r#"
         SELECT s.* FROM servers s
@@ -299,11 +694,7 @@ async fn list_servers(
     )
     .bind(user.id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to list servers: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .await?;
 
     Ok(Json(servers))
 }
@@ -313,21 +704,26 @@ async fn create_server(
     State(state): State<AppState>,
     user: AuthUser,
     Json(req): Json<CreateServerRequest>,
-) -> Result<Json<Server>, StatusCode> {
-    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<Server>, ApiError> {
+    req.validate()?;
 
     if let Some(icon_url) = req.icon_url.as_deref() {
-        validate_avatar_url(icon_url).map_err(|_| StatusCode::BAD_REQUEST)?;
+        validate_avatar_url(icon_url)
+            .map_err(|_| ApiError::Validation("Invalid icon URL".to_string()))?;
     }
 
     let normalized_name = req.name.trim();
     let invite_code = generate_invite_code();
 
-    // Create server
-    let server = sqlx::query_as::<_, Server>(
+    let mut tx = state.db.begin().await?;
+
+    // A concurrent request with the same client_id waits on the unique index
+    // and then inserts nothing
+    let created = sqlx::query_as::<_, Server>(
         r#"
-        INSERT INTO servers (name, icon_url, owner_id, invite_code)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO servers (name, icon_url, owner_id, invite_code, client_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (owner_id, client_id) WHERE client_id IS NOT NULL DO NOTHING
         RETURNING *
         "#,
     )
@@ -335,35 +731,38 @@ async fn create_server(
     .bind(&req.icon_url)
     .bind(user.id)
     .bind(&invite_code)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to create server: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .bind(req.client_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(server) = created else {
+        tx.rollback().await?;
+        let existing = sqlx::query_as::<_, Server>(
+            "SELECT * FROM servers WHERE owner_id = $1 AND client_id = $2",
+        )
+        .bind(user.id)
+        .bind(req.client_id)
+        .fetch_one(&state.db)
+        .await?;
+        return Ok(Json(existing));
+    };
 
     // Add owner as member
     sqlx::query("INSERT INTO server_members (server_id, user_id, role) VALUES ($1, $2, 'owner')")
         .bind(server.id)
         .bind(user.id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to add owner as member: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .execute(&mut *tx)
+        .await?;
 
     // Create default #general channel
     sqlx::query(
         "INSERT INTO channels (server_id, name, channel_type, position) VALUES ($1, 'general', 'text', 0)"
     )
     .bind(server.id)
-    .execute(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to create default channel: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
 
     tracing::info!("Server '{}' created by user {}", server.name, user.id);
     Ok(Json(server))
@@ -374,7 +773,7 @@ async fn get_server(
     State(state): State<AppState>,
     user: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<ServerWithChannels>, StatusCode> {
+) -> Result<Json<ServerWithChannels>, ApiError> {
     // Verify membership
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
@@ -387,23 +786,21 @@ async fn get_server(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let server = sqlx::query_as::<_, Server>("SELECT * FROM servers WHERE id = $1")
         .bind(id)
         .fetch_optional(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Server not found".to_string()))?;
 
     let channels = sqlx::query_as::<_, Channel>(
         "SELECT * FROM channels WHERE server_id = $1 ORDER BY position, name",
     )
     .bind(id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(ServerWithChannels { server, channels }))
 }
@@ -413,7 +810,7 @@ async fn get_members(
     State(state): State<AppState>,
     user: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<Json<Vec<ServerMemberWithUser>>, StatusCode> {
+) -> Result<Json<Vec<ServerMemberWithUser>>, ApiError> {
     // Verify membership
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
@@ -426,12 +823,12 @@ async fn get_members(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let members = sqlx::query_as::<_, ServerMemberWithUser>(
         r#"
-        SELECT u.id as user_id, u.username, u.avatar_url, sm.role, u.last_seen
+        SELECT u.id as user_id, u.username, u.avatar_url, sm.role, u.last_seen, u.status_message
         FROM server_members sm
         INNER JOIN users u ON sm.user_id = u.id
         WHERE sm.server_id = $1
@@ -446,27 +843,44 @@ async fn get_members(
     )
     .bind(id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to get members: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .await?;
 
     Ok(Json(members))
 }
 
-/// Join server by invite code
+/// Join server by invite code. Codes from `server_invites` are checked for
+/// expiry and use limit (`410 Gone` once spent); the legacy
+/// `servers.invite_code` always works.
 async fn join_server(
     State(state): State<AppState>,
     user: AuthUser,
     Path(code): Path<String>,
-) -> Result<Json<Server>, StatusCode> {
-    let server = sqlx::query_as::<_, Server>("SELECT * FROM servers WHERE invite_code = $1")
-        .bind(&code)
-        .fetch_optional(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+) -> Result<Json<Server>, ApiError> {
+    let invite = sqlx::query_as::<_, InviteRedemption>(
+        "SELECT id, server_id, expires_at, max_uses, uses FROM server_invites WHERE code = $1",
+    )
+    .bind(&code)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let server = match &invite {
+        Some(invite) => {
+            if !invite.is_usable(Utc::now()) {
+                return Err(ApiError::Gone(
+                    "Invite has expired or is used up".to_string(),
+                ));
+            }
+            sqlx::query_as::<_, Server>("SELECT * FROM servers WHERE id = $1")
+                .bind(invite.server_id)
+                .fetch_one(&state.db)
+                .await?
+        }
+        None => sqlx::query_as::<_, Server>("SELECT * FROM servers WHERE invite_code = $1")
+            .bind(&code)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Invite not found".to_string()))?,
+    };
 
     // Check if already a member
     let is_member = sqlx::query_scalar::<_, i64>(
@@ -494,19 +908,43 @@ async fn join_server(
         > 0;
 
     if is_banned {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "You are banned from this server".to_string(),
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    // Claim a use in the same statement that re-checks the limits, so
+    // concurrent joins can't push an invite past `max_uses`.
+    if let Some(invite) = &invite {
+        let claimed = sqlx::query(
+            r#"
+            UPDATE server_invites SET uses = uses + 1
+            WHERE id = $1
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND (max_uses IS NULL OR uses < max_uses)
+            "#,
+        )
+        .bind(invite.id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Err(ApiError::Gone(
+                "Invite has expired or is used up".to_string(),
+            ));
+        }
     }
 
     // Add as member
     sqlx::query("INSERT INTO server_members (server_id, user_id, role) VALUES ($1, $2, 'member')")
         .bind(server.id)
         .bind(user.id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to join server: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
 
     tracing::info!("User {} joined server '{}'", user.id, server.name);
     Ok(Json(server))
@@ -517,7 +955,7 @@ async fn leave_server(
     State(state): State<AppState>,
     user: AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     // Check if user is owner
     let is_owner = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM servers WHERE id = $1 AND owner_id = $2",
@@ -531,7 +969,9 @@ async fn leave_server(
 
     if is_owner {
         // Owner can't leave, must delete or transfer
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "The owner cannot leave their server".to_string(),
+        ));
     }
 
     // If user is currently in a voice channel for this server, remove and broadcast leave.
@@ -541,19 +981,18 @@ async fn leave_server(
     .bind(id)
     .bind(user.id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     if !active_voice_channels.is_empty() {
         sqlx::query("DELETE FROM voice_channel_sessions WHERE server_id = $1 AND user_id = $2")
             .bind(id)
             .bind(user.id)
             .execute(&state.db)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .await?;
+        state.clear_server_voice_state(user.id, id);
 
         for channel_id in active_voice_channels {
-            let _ = broadcast_voice_presence(&state, id, channel_id, user.id, false).await;
+            let _ = broadcast_voice_presence(&state, id, channel_id, user.id, false, None).await;
         }
     }
 
@@ -561,8 +1000,7 @@ async fn leave_server(
         .bind(id)
         .bind(user.id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -573,8 +1011,8 @@ async fn create_channel(
     user: AuthUser,
     Path(id): Path<Uuid>,
     Json(req): Json<CreateChannelRequest>,
-) -> Result<Json<Channel>, StatusCode> {
-    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<Channel>, ApiError> {
+    req.validate()?;
 
     // Check if user is owner or admin
     let role = sqlx::query_scalar::<_, String>(
@@ -583,17 +1021,20 @@ async fn create_channel(
     .bind(id)
     .bind(user.id)
     .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::FORBIDDEN)?;
+    .await?
+    .ok_or_else(not_a_member)?;
 
     if role != "owner" && role != "admin" {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can create channels".to_string(),
+        ));
     }
 
     let channel_type = req.channel_type.unwrap_or_else(|| "text".to_string());
     if channel_type != "text" && channel_type != "voice" {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::BadRequest(
+            "Channel type must be text or voice".to_string(),
+        ));
     }
 
     // Get max position
@@ -606,10 +1047,11 @@ async fn create_channel(
     .unwrap_or(Some(0))
     .unwrap_or(0);
 
-    let channel = sqlx::query_as::<_, Channel>(
+    let created = sqlx::query_as::<_, Channel>(
         r#"
-        INSERT INTO channels (server_id, name, channel_type, position)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO channels (server_id, name, channel_type, position, client_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (server_id, client_id) WHERE client_id IS NOT NULL DO NOTHING
         RETURNING *
         "#,
     )
@@ -617,12 +1059,23 @@ async fn create_channel(
     .bind(req.name.trim())
     .bind(&channel_type)
     .bind(max_pos + 1)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to create channel: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .bind(req.client_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let channel = match created {
+        Some(channel) => channel,
+        // Retry of a create that already went through
+        None => {
+            sqlx::query_as::<_, Channel>(
+                "SELECT * FROM channels WHERE server_id = $1 AND client_id = $2",
+            )
+            .bind(id)
+            .bind(req.client_id)
+            .fetch_one(&state.db)
+            .await?
+        }
+    };
 
     Ok(Json(channel))
 }
@@ -632,7 +1085,7 @@ async fn get_voice_channel_presence(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
-) -> Result<Json<Vec<VoiceChannelParticipant>>, StatusCode> {
+) -> Result<Json<Vec<VoiceChannelParticipant>>, ApiError> {
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
     )
@@ -644,7 +1097,7 @@ async fn get_voice_channel_presence(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let is_voice = sqlx::query_scalar::<_, i64>(
@@ -658,12 +1111,12 @@ async fn get_voice_channel_presence(
         > 0;
 
     if !is_voice {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::NotFound("Voice channel not found".to_string()));
     }
 
-    let participants = sqlx::query_as::<_, VoiceChannelParticipant>(
+    let mut participants = sqlx::query_as::<_, VoiceChannelParticipant>(
         r#"
-        SELECT vcs.user_id, u.username, vcs.joined_at
+        SELECT vcs.user_id, u.username, vcs.joined_at, vcs.mode
         FROM voice_channel_sessions vcs
         INNER JOIN users u ON u.id = vcs.user_id
         WHERE vcs.server_id = $1 AND vcs.channel_id = $2
@@ -673,44 +1126,74 @@ async fn get_voice_channel_presence(
     .bind(server_id)
     .bind(channel_id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+
+    for participant in &mut participants {
+        if let Some(voice_state) = state.voice_state(participant.user_id, channel_id) {
+            participant.muted = voice_state.muted;
+            participant.speaking = voice_state.speaking;
+        }
+    }
 
     Ok(Json(participants))
 }
 
-/// Join (or move to) a voice channel.
+/// Join (or move to) a voice channel, optionally as a listener. The body is
+/// optional; without one the member joins as a speaker, but a body that is
+/// sent has to parse.
 async fn join_voice_channel(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
-) -> Result<StatusCode, StatusCode> {
-    let is_member = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
+    req: Result<Json<JoinVoiceRequest>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let req = match req {
+        Ok(Json(req)) => req,
+        Err(JsonRejection::MissingJsonContentType(_)) => JoinVoiceRequest::default(),
+        Err(rejection) => return Err(ApiError::BadRequest(rejection.body_text())),
+    };
+
+    // Membership and this channel's override for the member's role in one
+    // query
+    let (role, can_join, can_speak) = sqlx::query_as::<_, (String, Option<bool>, Option<bool>)>(
+        r#"
+        SELECT sm.role, cp.can_join, cp.can_speak
+        FROM server_members sm
+        LEFT JOIN channel_permissions cp ON cp.channel_id = $3 AND cp.role = sm.role
+        WHERE sm.server_id = $1 AND sm.user_id = $2
+        "#,
     )
     .bind(server_id)
     .bind(user.id)
-    .fetch_one(&state.db)
-    .await
-    .unwrap_or(0)
-        > 0;
-
-    if !is_member {
-        return Err(StatusCode::FORBIDDEN);
-    }
+    .bind(channel_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(not_a_member)?;
 
-    let is_voice = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM channels WHERE id = $1 AND server_id = $2 AND channel_type = 'voice'",
+    let channel_type = sqlx::query_scalar::<_, String>(
+        "SELECT channel_type FROM channels WHERE id = $1 AND server_id = $2",
     )
     .bind(channel_id)
     .bind(server_id)
-    .fetch_one(&state.db)
-    .await
-    .unwrap_or(0)
-        > 0;
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Channel not found".to_string()))?;
 
-    if !is_voice {
-        return Err(StatusCode::BAD_REQUEST);
+    if channel_type != "voice" {
+        return Err(ApiError::BadRequest("Not a voice channel".to_string()));
+    }
+
+    if role != "owner" {
+        if can_join == Some(false) {
+            return Err(ApiError::Forbidden(
+                "Your role cannot join this voice channel".to_string(),
+            ));
+        }
+        if can_speak == Some(false) && req.mode == VoiceSessionMode::Speak {
+            return Err(ApiError::Forbidden(
+                "Your role can only listen in this voice channel".to_string(),
+            ));
+        }
     }
 
     let previous = sqlx::query_as::<_, (Uuid, Uuid)>(
@@ -718,36 +1201,44 @@ async fn join_voice_channel(
     )
     .bind(user.id)
     .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     sqlx::query(
         r#"
-        INSERT INTO voice_channel_sessions (channel_id, server_id, user_id)
-        VALUES ($1, $2, $3)
+        INSERT INTO voice_channel_sessions (channel_id, server_id, user_id, mode)
+        VALUES ($1, $2, $3, $4)
         ON CONFLICT (user_id)
         DO UPDATE SET
             channel_id = EXCLUDED.channel_id,
             server_id = EXCLUDED.server_id,
+            mode = EXCLUDED.mode,
             joined_at = NOW()
         "#,
     )
     .bind(channel_id)
     .bind(server_id)
     .bind(user.id)
+    .bind(req.mode.as_str())
     .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     if let Some((prev_server_id, prev_channel_id)) = previous {
         if prev_server_id != server_id || prev_channel_id != channel_id {
-            let _ =
-                broadcast_voice_presence(&state, prev_server_id, prev_channel_id, user.id, false)
-                    .await;
+            state.clear_voice_state(user.id);
+            let _ = broadcast_voice_presence(
+                &state,
+                prev_server_id,
+                prev_channel_id,
+                user.id,
+                false,
+                None,
+            )
+            .await;
         }
     }
 
-    let _ = broadcast_voice_presence(&state, server_id, channel_id, user.id, true).await;
+    let _ = broadcast_voice_presence(&state, server_id, channel_id, user.id, true, Some(req.mode))
+        .await;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -756,19 +1247,74 @@ async fn leave_voice_channel(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let result = sqlx::query(
         "DELETE FROM voice_channel_sessions WHERE server_id = $1 AND channel_id = $2 AND user_id = $3"
     )
     .bind(server_id)
     .bind(channel_id)
     .bind(user.id)
-    .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .execute(&state.db)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        state.clear_voice_state(user.id);
+        let _ = broadcast_voice_presence(&state, server_id, channel_id, user.id, false, None).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Nudge another participant of the voice channel the caller is in.
+async fn nudge_voice_participant(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<NudgeRequest>,
+) -> Result<StatusCode, ApiError> {
+    if req.target_user_id == user.id {
+        return Err(ApiError::BadRequest("Cannot nudge yourself".to_string()));
+    }
+
+    let participants = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT user_id FROM voice_channel_sessions
+        WHERE server_id = $1 AND channel_id = $2 AND user_id IN ($3, $4)
+        "#,
+    )
+    .bind(server_id)
+    .bind(channel_id)
+    .bind(user.id)
+    .bind(req.target_user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    // Sessions are removed on leave, kick and ban, so sharing the channel
+    // also implies both are still server members.
+    if !participants.contains(&user.id) {
+        return Err(ApiError::Forbidden(
+            "You are not in this voice channel".to_string(),
+        ));
+    }
+    if !participants.contains(&req.target_user_id) {
+        return Err(ApiError::NotFound(
+            "User is not in this voice channel".to_string(),
+        ));
+    }
 
-    if result.rows_affected() > 0 {
-        let _ = broadcast_voice_presence(&state, server_id, channel_id, user.id, false).await;
+    if !state.allow_voice_nudge(user.id, req.target_user_id) {
+        return Err(ApiError::TooManyRequests("Nudged too recently".to_string()));
+    }
+
+    let ws_payload = serde_json::json!({
+        "type": "VOICE_NUDGE",
+        "server_id": server_id,
+        "channel_id": channel_id,
+        "from_user_id": user.id,
+        "from_username": user.username,
+    });
+    if let Some(peer_tx) = state.peers.get(&req.target_user_id.to_string()) {
+        let _ = peer_tx.send(WsMessage::Text(ws_payload.to_string()));
     }
 
     Ok(StatusCode::NO_CONTENT)
@@ -780,7 +1326,7 @@ async fn get_channel_messages(
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
     Query(params): Query<ChannelPaginationParams>,
-) -> Result<Json<Vec<ChannelMessage>>, StatusCode> {
+) -> Result<Json<Vec<ChannelMessage>>, ApiError> {
     // Verify membership
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
@@ -793,7 +1339,7 @@ async fn get_channel_messages(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let limit = params.limit.unwrap_or(100).clamp(1, 200);
@@ -810,7 +1356,8 @@ async fn get_channel_messages(
                 m.content,
                 m.nonce,
                 m.created_at,
-                m.edited_at
+                m.edited_at,
+                m.attachments
             FROM messages m
             LEFT JOIN users u ON u.id = m.sender_id
             WHERE m.channel_id = $1
@@ -837,7 +1384,8 @@ async fn get_channel_messages(
                 m.content,
                 m.nonce,
                 m.created_at,
-                m.edited_at
+                m.edited_at,
+                m.attachments
             FROM messages m
             LEFT JOIN users u ON u.id = m.sender_id
             WHERE m.channel_id = $1
@@ -850,11 +1398,7 @@ async fn get_channel_messages(
         .bind(limit)
         .fetch_all(&state.db)
         .await
-    }
-    .map_err(|e| {
-        tracing::error!("Failed to get channel messages: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    }?;
 
     messages.reverse();
 
@@ -867,8 +1411,8 @@ async fn send_channel_message(
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<SendMessageRequest>,
-) -> Result<Json<ChannelMessage>, StatusCode> {
-    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<ChannelMessage>, ApiError> {
+    req.validate()?;
     let content = req.content.trim().to_string();
 
     // Verify membership
@@ -883,7 +1427,7 @@ async fn send_channel_message(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     if let Some(parent_message_id) = req.parent_message_id {
@@ -898,7 +1442,9 @@ async fn send_channel_message(
             > 0;
 
         if !parent_in_channel {
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(ApiError::BadRequest(
+                "Parent message is not in this channel".to_string(),
+            ));
         }
     }
 
@@ -914,7 +1460,8 @@ async fn send_channel_message(
                 m.content,
                 m.nonce,
                 m.created_at,
-                m.edited_at
+                m.edited_at,
+                m.attachments
             FROM messages m
             LEFT JOIN users u ON u.id = m.sender_id
             WHERE m.channel_id = $1 AND m.sender_id = $2 AND m.client_id = $3
@@ -925,8 +1472,7 @@ async fn send_channel_message(
         .bind(user.id)
         .bind(client_id)
         .fetch_optional(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .await?
         {
             return Ok(Json(existing));
         }
@@ -935,9 +1481,9 @@ async fn send_channel_message(
     let message = sqlx::query_as::<_, ChannelMessage>(
         r#"
         WITH inserted AS (
-            INSERT INTO messages (channel_id, sender_id, content, nonce, client_id, parent_message_id)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, client_id, channel_id, sender_id, content, nonce, created_at, edited_at
+            INSERT INTO messages (channel_id, sender_id, content, nonce, client_id, parent_message_id, attachments)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, client_id, channel_id, sender_id, content, nonce, created_at, edited_at, attachments
         )
         SELECT
             i.id,
@@ -948,7 +1494,8 @@ async fn send_channel_message(
             i.content,
             i.nonce,
             i.created_at,
-            i.edited_at
+            i.edited_at,
+            i.attachments
         FROM inserted i
         LEFT JOIN users u ON u.id = i.sender_id
         "#
@@ -959,12 +1506,11 @@ async fn send_channel_message(
     .bind(&req.nonce)
     .bind(req.client_id)
     .bind(req.parent_message_id)
+    .bind(sqlx::types::Json(&req.attachments))
     .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to send channel message: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .await?;
+    state.metrics.record_message_sent(user.id);
+
     // Broadcast via WebSocket to all server members
     let members =
         sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM server_members WHERE server_id = $1")
@@ -1002,13 +1548,26 @@ async fn send_channel_message(
         .bind(server_id)
         .bind(&mention_username)
         .fetch_optional(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
         if let Some((mentioned_user_id, mentioned_username)) = target_user {
             if mentioned_user_id == user.id {
                 continue;
             }
+            if let Err(e) = record_mention(
+                &state.db,
+                MentionScope::Channel {
+                    server_id,
+                    channel_id,
+                },
+                message.id,
+                mentioned_user_id,
+                user.id,
+            )
+            .await
+            {
+                tracing::warn!("Failed to record mention: {}", e);
+            }
             if let Some(peer_tx) = state.peers.get(&mentioned_user_id.to_string()) {
                 let mention_payload = serde_json::json!({
                     "type": "MENTION_ALERT",
@@ -1036,7 +1595,7 @@ async fn send_channel_typing(
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<TypingRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
     )
@@ -1048,7 +1607,7 @@ async fn send_channel_typing(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let members =
@@ -1083,10 +1642,10 @@ async fn send_channel_typing(
 async fn edit_channel_message(
     State(state): State<AppState>,
     user: AuthUser,
-    Path((server_id, _channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
+    Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
     Json(req): Json<EditChannelMessageRequest>,
-) -> Result<Json<ChannelMessage>, StatusCode> {
-    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<ChannelMessage>, ApiError> {
+    req.validate()?;
 
     // Verify membership
     let is_member = sqlx::query_scalar::<_, i64>(
@@ -1100,20 +1659,15 @@ async fn edit_channel_message(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     // Verify sender ownership
-    let existing =
-        sqlx::query_scalar::<_, Option<Uuid>>("SELECT sender_id FROM messages WHERE id = $1")
-            .bind(message_id)
-            .fetch_optional(&state.db)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            .ok_or(StatusCode::NOT_FOUND)?;
-
+    let existing = fetch_channel_message_sender(&state, server_id, channel_id, message_id).await?;
     if existing != Some(user.id) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the sender can edit a message".to_string(),
+        ));
     }
 
     let updated = sqlx::query_as::<_, ChannelMessage>(
@@ -1122,7 +1676,7 @@ async fn edit_channel_message(
             UPDATE messages
             SET content = $1, nonce = $2, edited_at = NOW()
             WHERE id = $3
-            RETURNING id, client_id, channel_id, sender_id, content, nonce, created_at, edited_at
+            RETURNING id, client_id, channel_id, sender_id, content, nonce, created_at, edited_at, attachments
         )
         SELECT
             u2.id,
@@ -1133,7 +1687,8 @@ async fn edit_channel_message(
             u2.content,
             u2.nonce,
             u2.created_at,
-            u2.edited_at
+            u2.edited_at,
+            u2.attachments
         FROM updated u2
         LEFT JOIN users u ON u.id = u2.sender_id
         "#,
@@ -1142,8 +1697,13 @@ async fn edit_channel_message(
     .bind(&req.nonce)
     .bind(message_id)
     .fetch_one(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+
+    // Reactions survive the edit; include them so clients re-render the
+    // message and its reactions together
+    let reactions = fetch_message_reactions(&state, message_id)
+        .await
+        .unwrap_or_default();
 
     // Broadcast to server members
     let members =
@@ -1156,7 +1716,8 @@ async fn edit_channel_message(
     let ws_payload = serde_json::json!({
         "type": "CHANNEL_MESSAGE_EDITED",
         "server_id": server_id,
-        "message": updated
+        "message": updated,
+        "reactions": reactions
     });
     let ws_text = serde_json::to_string(&ws_payload).unwrap();
 
@@ -1169,28 +1730,77 @@ async fn edit_channel_message(
     Ok(Json(updated))
 }
 
+/// Delete a channel message with its replies, reactions and pins (sender, or
+/// owner/admin for moderation)
+async fn delete_channel_message(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    let role = fetch_server_role(&state, server_id, user.id)
+        .await?
+        .ok_or_else(not_a_member)?;
+
+    let sender = fetch_channel_message_sender(&state, server_id, channel_id, message_id).await?;
+    if sender != Some(user.id) && !can_manage_members(&role) {
+        return Err(ApiError::Forbidden(
+            "Only the sender, the owner or admins can delete a message".to_string(),
+        ));
+    }
+
+    let deleted = delete_message_tree(&state.db, message_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Message not found".to_string()))?;
+
+    let members =
+        sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM server_members WHERE server_id = $1")
+            .bind(server_id)
+            .fetch_all(&state.db)
+            .await
+            .unwrap_or_default();
+
+    let ws_payload = serde_json::json!({
+        "type": "CHANNEL_MESSAGE_DELETED",
+        "server_id": server_id,
+        "channel_id": channel_id,
+        "message_id": message_id,
+        "reply_ids": deleted.reply_ids,
+        "unpinned_ids": deleted.unpinned_ids,
+        "user_id": user.id,
+    });
+    let ws_text = serde_json::to_string(&ws_payload).unwrap();
+
+    for member_id in members {
+        if let Some(peer_tx) = state.peers.get(&member_id.to_string()) {
+            let _ = peer_tx.send(WsMessage::Text(ws_text.clone()));
+        }
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Delete a server (owner only).
 async fn delete_server(
     State(state): State<AppState>,
     user: AuthUser,
     Path(server_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let member_ids =
         sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM server_members WHERE server_id = $1")
             .bind(server_id)
             .fetch_all(&state.db)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .await?;
 
     let result = sqlx::query("DELETE FROM servers WHERE id = $1 AND owner_id = $2")
         .bind(server_id)
         .bind(user.id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     if result.rows_affected() == 0 {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner can delete a server".to_string(),
+        ));
     }
 
     let ws_payload = serde_json::json!({
@@ -1212,13 +1822,15 @@ async fn regenerate_invite_code(
     State(state): State<AppState>,
     user: AuthUser,
     Path(server_id): Path<Uuid>,
-) -> Result<Json<Server>, StatusCode> {
+) -> Result<Json<Server>, ApiError> {
     let role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
 
     if !can_manage_members(&role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can regenerate the invite code".to_string(),
+        ));
     }
 
     for _ in 0..5 {
@@ -1238,11 +1850,135 @@ async fn regenerate_invite_code(
             {
                 continue;
             }
-            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(ApiError::Internal(
+        "Could not generate a unique invite code".to_string(),
+    ))
+}
+
+async fn authorize_invite_management(
+    state: &AppState,
+    server_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), ApiError> {
+    let role = fetch_server_role(state, server_id, user_id)
+        .await?
+        .ok_or_else(not_a_member)?;
+    if !can_manage_members(&role) {
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can manage invites".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// List a server's invites, newest first (owner/admin). Expired and used-up
+/// invites are included so their usage stays visible until revoked.
+async fn list_server_invites(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Vec<ServerInvite>>, ApiError> {
+    authorize_invite_management(&state, server_id, user.id).await?;
+
+    let invites = sqlx::query_as::<_, ServerInvite>(
+        r#"
+        SELECT si.id, si.server_id, si.code, si.created_by, u.username AS created_by_username,
+               si.expires_at, si.max_uses, si.uses, si.created_at
+        FROM server_invites si
+        LEFT JOIN users u ON u.id = si.created_by
+        WHERE si.server_id = $1
+        ORDER BY si.created_at DESC
+        "#,
+    )
+    .bind(server_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(invites))
+}
+
+/// Create an invite (owner/admin). A server with `MAX_INVITES_PER_SERVER`
+/// invites gets `409 Conflict` until some are revoked.
+async fn create_server_invite(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(server_id): Path<Uuid>,
+    Json(req): Json<CreateInviteRequest>,
+) -> Result<Json<ServerInvite>, ApiError> {
+    req.validate()?;
+    authorize_invite_management(&state, server_id, user.id).await?;
+
+    let expires_at = req
+        .expires_in_seconds
+        .map(|secs| Utc::now() + chrono::Duration::seconds(secs));
+
+    for _ in 0..5 {
+        let code = generate_server_invite_code();
+        let inserted = sqlx::query_as::<_, ServerInvite>(
+            r#"
+            INSERT INTO server_invites (server_id, code, created_by, expires_at, max_uses)
+            SELECT $1, $2, $3, $4, $5
+            WHERE (SELECT COUNT(*) FROM server_invites WHERE server_id = $1) < $6
+            RETURNING id, server_id, code, created_by,
+                      (SELECT username FROM users WHERE id = $3) AS created_by_username,
+                      expires_at, max_uses, uses, created_at
+            "#,
+        )
+        .bind(server_id)
+        .bind(&code)
+        .bind(user.id)
+        .bind(expires_at)
+        .bind(req.max_uses)
+        .bind(MAX_INVITES_PER_SERVER)
+        .fetch_optional(&state.db)
+        .await;
+
+        match inserted {
+            Ok(Some(invite)) => return Ok(Json(invite)),
+            Ok(None) => {
+                return Err(ApiError::Conflict(
+                    "Server has too many invites".to_string(),
+                ))
+            }
+            Err(sqlx::Error::Database(db_err))
+                if db_err.constraint() == Some("server_invites_code_key") =>
+            {
+                continue;
+            }
+            Err(e) => return Err(e.into()),
         }
     }
 
-    Err(StatusCode::INTERNAL_SERVER_ERROR)
+    Err(ApiError::Internal(
+        "Could not generate a unique invite code".to_string(),
+    ))
+}
+
+/// Revoke an invite (owner/admin). The code stops working immediately;
+/// members who joined with it stay.
+async fn revoke_server_invite(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, invite_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    authorize_invite_management(&state, server_id, user.id).await?;
+
+    let deleted = sqlx::query("DELETE FROM server_invites WHERE id = $1 AND server_id = $2")
+        .bind(invite_id)
+        .bind(server_id)
+        .execute(&state.db)
+        .await?
+        .rows_affected();
+
+    if deleted == 0 {
+        return Err(ApiError::NotFound("Invite not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
 /// Update a member role (owner only).
@@ -1251,24 +1987,30 @@ async fn update_member_role(
     user: AuthUser,
     Path((server_id, member_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<UpdateMemberRoleRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if actor_role != "owner" {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner can change roles".to_string(),
+        ));
     }
 
     let target_role = fetch_server_role(&state, server_id, member_id)
         .await?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or_else(|| ApiError::NotFound("Member not found".to_string()))?;
     if target_role == "owner" {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "The owner's role cannot be changed".to_string(),
+        ));
     }
 
     let role = req.role.trim().to_ascii_lowercase();
     if role != "admin" && role != "member" {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::BadRequest(
+            "Role must be admin or member".to_string(),
+        ));
     }
 
     sqlx::query("UPDATE server_members SET role = $1 WHERE server_id = $2 AND user_id = $3")
@@ -1276,52 +2018,137 @@ async fn update_member_role(
         .bind(server_id)
         .bind(member_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
+
+    // The new role may be barred from the voice channel the member is in
+    enforce_voice_permissions(&state, server_id, None, Some(member_id)).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Per-role overrides for a voice channel; visible to every member.
+async fn list_channel_permissions(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<ChannelPermission>>, ApiError> {
+    fetch_server_role(&state, server_id, user.id)
+        .await?
+        .ok_or_else(not_a_member)?;
+    ensure_voice_channel(&state, server_id, channel_id).await?;
+
+    let permissions = sqlx::query_as::<_, ChannelPermission>(
+        r#"
+        SELECT channel_id, role, can_join, can_speak, updated_at
+        FROM channel_permissions
+        WHERE channel_id = $1
+        ORDER BY role
+        "#,
+    )
+    .bind(channel_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(permissions))
+}
+
+/// Set what a role may do in a voice channel (owner/admin). Admins can only
+/// restrict members.
+async fn update_channel_permission(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateChannelPermissionRequest>,
+) -> Result<Json<ChannelPermission>, ApiError> {
+    let actor_role = fetch_server_role(&state, server_id, user.id)
+        .await?
+        .ok_or_else(not_a_member)?;
+    if !can_manage_members(&actor_role) {
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can change channel permissions".to_string(),
+        ));
+    }
+
+    let role = req.role.trim().to_ascii_lowercase();
+    if role != "admin" && role != "member" {
+        return Err(ApiError::BadRequest(
+            "Role must be admin or member".to_string(),
+        ));
+    }
+    if !can_manage_target(&actor_role, &role) {
+        return Err(ApiError::Forbidden(
+            "You cannot change permissions for this role".to_string(),
+        ));
+    }
+    ensure_voice_channel(&state, server_id, channel_id).await?;
+
+    let permission = sqlx::query_as::<_, ChannelPermission>(
+        r#"
+        INSERT INTO channel_permissions (channel_id, role, can_join, can_speak)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (channel_id, role)
+        DO UPDATE SET
+            can_join = EXCLUDED.can_join,
+            can_speak = EXCLUDED.can_speak,
+            updated_at = NOW()
+        RETURNING channel_id, role, can_join, can_speak, updated_at
+        "#,
+    )
+    .bind(channel_id)
+    .bind(&role)
+    .bind(req.can_join)
+    .bind(req.can_speak)
+    .fetch_one(&state.db)
+    .await?;
+
+    enforce_voice_permissions(&state, server_id, Some(channel_id), None).await?;
+
+    Ok(Json(permission))
+}
+
 /// Kick member from server (owner/admin).
 async fn kick_member(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, member_id)): Path<(Uuid, Uuid)>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     if member_id == user.id {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::BadRequest("Cannot kick yourself".to_string()));
     }
 
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if !can_manage_members(&actor_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can kick members".to_string(),
+        ));
     }
 
     let target_role = fetch_server_role(&state, server_id, member_id)
         .await?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or_else(|| ApiError::NotFound("Member not found".to_string()))?;
     if !can_manage_target(&actor_role, &target_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "You cannot kick this member".to_string(),
+        ));
     }
 
     sqlx::query("DELETE FROM voice_channel_sessions WHERE server_id = $1 AND user_id = $2")
         .bind(server_id)
         .bind(member_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
+    state.clear_server_voice_state(member_id, server_id);
 
     let result = sqlx::query("DELETE FROM server_members WHERE server_id = $1 AND user_id = $2")
         .bind(server_id)
         .bind(member_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::NotFound("Member not found".to_string()));
     }
 
     if let Some(peer_tx) = state.peers.get(&member_id.to_string()) {
@@ -1342,21 +2169,25 @@ async fn ban_member(
     user: AuthUser,
     Path((server_id, member_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<BanMemberRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     if member_id == user.id {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::BadRequest("Cannot ban yourself".to_string()));
     }
 
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if !can_manage_members(&actor_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can ban members".to_string(),
+        ));
     }
 
     if let Some(target_role) = fetch_server_role(&state, server_id, member_id).await? {
         if !can_manage_target(&actor_role, &target_role) {
-            return Err(StatusCode::FORBIDDEN);
+            return Err(ApiError::Forbidden(
+                "You cannot ban this member".to_string(),
+            ));
         }
     }
 
@@ -1379,22 +2210,20 @@ async fn ban_member(
     .bind(user.id)
     .bind(reason)
     .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     sqlx::query("DELETE FROM voice_channel_sessions WHERE server_id = $1 AND user_id = $2")
         .bind(server_id)
         .bind(member_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
+    state.clear_server_voice_state(member_id, server_id);
 
     sqlx::query("DELETE FROM server_members WHERE server_id = $1 AND user_id = $2")
         .bind(server_id)
         .bind(member_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     if let Some(peer_tx) = state.peers.get(&member_id.to_string()) {
         let banned_payload = serde_json::json!({
@@ -1414,12 +2243,14 @@ async fn list_server_bans(
     State(state): State<AppState>,
     user: AuthUser,
     Path(server_id): Path<Uuid>,
-) -> Result<Json<Vec<ServerBanEntry>>, StatusCode> {
+) -> Result<Json<Vec<ServerBanEntry>>, ApiError> {
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if !can_manage_members(&actor_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can see bans".to_string(),
+        ));
     }
 
     let bans = sqlx::query_as::<_, ServerBanEntry>(
@@ -1440,8 +2271,7 @@ async fn list_server_bans(
     )
     .bind(server_id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(bans))
 }
@@ -1451,23 +2281,24 @@ async fn unban_member(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, member_id)): Path<(Uuid, Uuid)>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if !can_manage_members(&actor_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can unban members".to_string(),
+        ));
     }
 
     let result = sqlx::query("DELETE FROM server_bans WHERE server_id = $1 AND user_id = $2")
         .bind(server_id)
         .bind(member_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::NotFound("Ban not found".to_string()));
     }
 
     Ok(StatusCode::NO_CONTENT)
@@ -1479,20 +2310,23 @@ async fn update_channel(
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
     Json(req): Json<UpdateChannelRequest>,
-) -> Result<Json<Channel>, StatusCode> {
+) -> Result<Json<Channel>, ApiError> {
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if !can_manage_members(&actor_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can edit channels".to_string(),
+        ));
     }
 
     if req.name.is_none() && req.position.is_none() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::BadRequest("Nothing to update".to_string()));
     }
 
     let (name_set, name_value) = if let Some(name) = req.name.as_deref() {
-        validate_channel_name(name).map_err(|_| StatusCode::BAD_REQUEST)?;
+        validate_channel_name(name)
+            .map_err(|_| ApiError::Validation("Invalid channel name".to_string()))?;
         (true, Some(name.trim().to_string()))
     } else {
         (false, None)
@@ -1514,9 +2348,8 @@ async fn update_channel(
     .bind(channel_id)
     .bind(server_id)
     .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Channel not found".to_string()))?;
 
     Ok(Json(updated))
 }
@@ -1526,12 +2359,14 @@ async fn delete_channel(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, ApiError> {
     let actor_role = fetch_server_role(&state, server_id, user.id)
         .await?
-        .ok_or(StatusCode::FORBIDDEN)?;
+        .ok_or_else(not_a_member)?;
     if !can_manage_members(&actor_role) {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden(
+            "Only the owner and admins can delete channels".to_string(),
+        ));
     }
 
     let channel_type = sqlx::query_scalar::<_, String>(
@@ -1540,9 +2375,8 @@ async fn delete_channel(
     .bind(channel_id)
     .bind(server_id)
     .fetch_optional(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Channel not found".to_string()))?;
 
     if channel_type == "text" {
         let text_count = sqlx::query_scalar::<_, i64>(
@@ -1553,7 +2387,9 @@ async fn delete_channel(
         .await
         .unwrap_or(1);
         if text_count <= 1 {
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(ApiError::BadRequest(
+                "A server needs at least one text channel".to_string(),
+            ));
         }
     }
 
@@ -1561,8 +2397,7 @@ async fn delete_channel(
         .bind(channel_id)
         .bind(server_id)
         .execute(&state.db)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1573,8 +2408,8 @@ async fn search_channel_messages(
     user: AuthUser,
     Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
     Query(params): Query<SearchChannelMessagesQuery>,
-) -> Result<Json<Vec<ChannelMessage>>, StatusCode> {
-    params.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<Vec<ChannelMessage>>, ApiError> {
+    params.validate()?;
 
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
@@ -1587,7 +2422,7 @@ async fn search_channel_messages(
         > 0;
 
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let limit = params.limit.unwrap_or(50).clamp(1, 100);
@@ -1604,7 +2439,8 @@ async fn search_channel_messages(
             m.content,
             m.nonce,
             m.created_at,
-            m.edited_at
+            m.edited_at,
+            m.attachments
         FROM messages m
         LEFT JOIN users u ON u.id = m.sender_id
         WHERE m.channel_id = $1
@@ -1617,8 +2453,7 @@ async fn search_channel_messages(
     .bind(query)
     .bind(limit)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(messages))
 }
@@ -1628,7 +2463,7 @@ async fn get_channel_message_reactions(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
-) -> Result<Json<Vec<MessageReactionSummary>>, StatusCode> {
+) -> Result<Json<Vec<MessageReactionSummary>>, ApiError> {
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
     )
@@ -1639,7 +2474,7 @@ async fn get_channel_message_reactions(
     .unwrap_or(0)
         > 0;
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let exists = sqlx::query_scalar::<_, i64>(
@@ -1652,7 +2487,7 @@ async fn get_channel_message_reactions(
     .unwrap_or(0)
         > 0;
     if !exists {
-        return Err(StatusCode::NOT_FOUND);
+        return Err(ApiError::NotFound("Message not found".to_string()));
     }
 
     let reactions = fetch_message_reactions(&state, message_id).await?;
@@ -1665,8 +2500,8 @@ async fn add_channel_message_reaction(
     user: AuthUser,
     Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
     Json(req): Json<ReactionRequest>,
-) -> Result<Json<Vec<MessageReactionSummary>>, StatusCode> {
-    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<Vec<MessageReactionSummary>>, ApiError> {
+    req.validate()?;
 
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
@@ -1678,7 +2513,7 @@ async fn add_channel_message_reaction(
     .unwrap_or(0)
         > 0;
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     sqlx::query(
@@ -1692,8 +2527,8 @@ async fn add_channel_message_reaction(
     .bind(user.id)
     .bind(req.emoji.trim())
     .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+    state.metrics.record_reaction_added(user.id);
 
     let reactions = fetch_message_reactions(&state, message_id).await?;
 
@@ -1725,8 +2560,8 @@ async fn remove_channel_message_reaction(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id, message_id, emoji)): Path<(Uuid, Uuid, Uuid, String)>,
-) -> Result<Json<Vec<MessageReactionSummary>>, StatusCode> {
-    validate_emoji(&emoji).map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> Result<Json<Vec<MessageReactionSummary>>, ApiError> {
+    validate_emoji(&emoji).map_err(|_| ApiError::Validation("Invalid emoji".to_string()))?;
 
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
@@ -1738,7 +2573,7 @@ async fn remove_channel_message_reaction(
     .unwrap_or(0)
         > 0;
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     sqlx::query(
@@ -1748,8 +2583,8 @@ async fn remove_channel_message_reaction(
     .bind(user.id)
     .bind(emoji.trim())
     .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+    state.metrics.record_reaction_removed(user.id);
 
     let reactions = fetch_message_reactions(&state, message_id).await?;
     let members =
@@ -1775,13 +2610,167 @@ async fn remove_channel_message_reaction(
     Ok(Json(fetch_message_reactions(&state, message_id).await?))
 }
 
+/// List pinned messages of a channel, newest pin first.
+async fn list_channel_pins(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<PinnedChannelMessage>>, ApiError> {
+    if fetch_server_role(&state, server_id, user.id)
+        .await?
+        .is_none()
+    {
+        return Err(not_a_member());
+    }
+    ensure_channel_in_server(&state, server_id, channel_id).await?;
+
+    Ok(Json(fetch_channel_pins(&state, channel_id).await?))
+}
+
+/// Move the caller's read marker in a channel forward, up to a message or to
+/// now, and mark their mentions up to it read. The marker never moves back.
+async fn mark_channel_read(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<ChannelReadRequest>,
+) -> Result<StatusCode, ApiError> {
+    if fetch_server_role(&state, server_id, user.id)
+        .await?
+        .is_none()
+    {
+        return Err(not_a_member());
+    }
+    ensure_channel_in_server(&state, server_id, channel_id).await?;
+
+    let read_upto = match req.upto_message_id {
+        Some(message_id) => sqlx::query_scalar::<_, DateTime<Utc>>(
+            "SELECT created_at FROM messages WHERE id = $1 AND channel_id = $2",
+        )
+        .bind(message_id)
+        .bind(channel_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Message not found".to_string()))?,
+        None => Utc::now(),
+    };
+
+    let last_read_at = sqlx::query_scalar::<_, DateTime<Utc>>(
+        r#"
+        INSERT INTO channel_read_markers (channel_id, user_id, last_read_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (channel_id, user_id)
+        DO UPDATE SET
+            last_read_at = GREATEST(channel_read_markers.last_read_at, EXCLUDED.last_read_at),
+            updated_at = NOW()
+        RETURNING last_read_at
+        "#,
+    )
+    .bind(channel_id)
+    .bind(user.id)
+    .bind(read_upto)
+    .fetch_one(&state.db)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE mentions mn SET read_at = NOW()
+        FROM messages m
+        WHERE m.id = mn.message_id
+          AND mn.mentioned_user_id = $1
+          AND mn.channel_id = $2
+          AND mn.read_at IS NULL
+          AND m.created_at <= $3
+        "#,
+    )
+    .bind(user.id)
+    .bind(channel_id)
+    .bind(last_read_at)
+    .execute(&state.db)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Pin a channel message (owner/admin). Pinning twice is a no-op; a full
+/// channel gets `409 Conflict`.
+async fn pin_channel_message(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<Json<Vec<PinnedChannelMessage>>, ApiError> {
+    authorize_pin_change(&state, user.id, server_id, channel_id, message_id).await?;
+
+    let already_pinned = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM pinned_messages WHERE channel_id = $1 AND message_id = $2",
+    )
+    .bind(channel_id)
+    .bind(message_id)
+    .fetch_one(&state.db)
+    .await?
+        > 0;
+    if already_pinned {
+        return Ok(Json(fetch_channel_pins(&state, channel_id).await?));
+    }
+
+    // The cap is checked in the insert itself so concurrent pins can't
+    // overshoot it.
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO pinned_messages (channel_id, message_id, pinned_by)
+        SELECT $1, $2, $3
+        WHERE (SELECT COUNT(*) FROM pinned_messages WHERE channel_id = $1) < $4
+        ON CONFLICT (channel_id, message_id) DO NOTHING
+        "#,
+    )
+    .bind(channel_id)
+    .bind(message_id)
+    .bind(user.id)
+    .bind(MAX_PINS_PER_CHANNEL)
+    .execute(&state.db)
+    .await?
+    .rows_affected();
+    if inserted == 0 {
+        return Err(ApiError::Conflict(
+            "Channel has too many pinned messages".to_string(),
+        ));
+    }
+
+    broadcast_message_pin(&state, server_id, channel_id, message_id, user.id, true).await?;
+
+    Ok(Json(fetch_channel_pins(&state, channel_id).await?))
+}
+
+/// Unpin a channel message (owner/admin).
+async fn unpin_channel_message(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<Json<Vec<PinnedChannelMessage>>, ApiError> {
+    authorize_pin_change(&state, user.id, server_id, channel_id, message_id).await?;
+
+    let removed =
+        sqlx::query("DELETE FROM pinned_messages WHERE channel_id = $1 AND message_id = $2")
+            .bind(channel_id)
+            .bind(message_id)
+            .execute(&state.db)
+            .await?
+            .rows_affected();
+
+    if removed > 0 {
+        broadcast_message_pin(&state, server_id, channel_id, message_id, user.id, false).await?;
+    }
+
+    Ok(Json(fetch_channel_pins(&state, channel_id).await?))
+}
+
 /// Send a reply in a channel thread.
 async fn send_channel_thread_message(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
     Json(mut req): Json<SendMessageRequest>,
-) -> Result<Json<ChannelMessage>, StatusCode> {
+) -> Result<Json<ChannelMessage>, ApiError> {
     req.parent_message_id = Some(message_id);
     send_channel_message(State(state), user, Path((server_id, channel_id)), Json(req)).await
 }
@@ -1791,7 +2780,7 @@ async fn get_channel_thread_messages(
     State(state): State<AppState>,
     user: AuthUser,
     Path((server_id, channel_id, message_id)): Path<(Uuid, Uuid, Uuid)>,
-) -> Result<Json<Vec<ChannelMessage>>, StatusCode> {
+) -> Result<Json<Vec<ChannelMessage>>, ApiError> {
     let is_member = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM server_members WHERE server_id = $1 AND user_id = $2",
     )
@@ -1802,7 +2791,7 @@ async fn get_channel_thread_messages(
     .unwrap_or(0)
         > 0;
     if !is_member {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(not_a_member());
     }
 
     let messages = sqlx::query_as::<_, ChannelMessage>(
@@ -1816,7 +2805,8 @@ async fn get_channel_thread_messages(
             m.content,
             m.nonce,
             m.created_at,
-            m.edited_at
+            m.edited_at,
+            m.attachments
         FROM messages m
         LEFT JOIN users u ON u.id = m.sender_id
         WHERE m.channel_id = $1 AND m.parent_message_id = $2
@@ -1826,8 +2816,7 @@ async fn get_channel_thread_messages(
     .bind(channel_id)
     .bind(message_id)
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(messages))
 }
@@ -1,21 +1,30 @@
 use axum::{
     extract::{Path, Query, State},
-    routing::{get, put},
+    http::StatusCode,
+    routing::{get, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use shared_proto::redact::Redacted;
 use sqlx::FromRow;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::auth::{AuthError, AuthUser};
+use crate::presence::{broadcast_presence, PresenceStatus};
 use crate::state::AppState;
-use crate::validation::{normalize_username, validate_avatar_url, validate_username};
+use crate::validation::{
+    normalize_filtered_words, normalize_username, validate_avatar_url, validate_status_message,
+    validate_username,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/me", get(get_my_profile).put(update_my_profile))
         .route("/me/settings", get(get_my_settings).put(update_my_settings))
+        .route("/me/missed-calls", get(list_my_missed_calls))
+        .route("/me/mentions", get(list_my_mentions))
+        .route("/me/mentions/read", post(mark_my_mentions_read))
         .route("/search", get(search_users))
         .route("/:id", get(get_user))
         .route("/:id/public-key", get(get_user_public_key))
@@ -42,6 +51,7 @@ pub struct UserPublicResult {
     pub avatar_url: Option<String>,
     pub last_seen: Option<chrono::DateTime<chrono::Utc>>,
     pub public_key: Option<String>,
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -59,6 +69,8 @@ pub struct SetPublicKeyRequest {
 pub struct UpdateProfileRequest {
     pub username: Option<String>,
     pub avatar_url: Option<String>,
+    /// Empty string clears the status
+    pub status_message: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,6 +78,12 @@ pub struct UpdateSettingsRequest {
     pub allow_dm_from_strangers: Option<bool>,
     pub enable_mention_notifications: Option<bool>,
     pub enable_sound_notifications: Option<bool>,
+    /// `online`, `away` or `dnd`
+    pub presence_status: Option<String>,
+    /// Replaces the whole list; an empty array clears it
+    pub filtered_words: Option<Vec<String>>,
+    pub call_waiting: Option<bool>,
+    pub send_read_receipts: Option<bool>,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -74,10 +92,63 @@ pub struct UserSettingsResponse {
     pub allow_dm_from_strangers: bool,
     pub enable_mention_notifications: bool,
     pub enable_sound_notifications: bool,
+    pub presence_status: String,
+    /// Words the client redacts when displaying messages
+    pub filtered_words: Vec<String>,
+    /// Ring through while already in a call instead of answering busy
+    pub call_waiting: bool,
+    /// Let senders see when this user read their messages
+    pub send_read_receipts: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, Serialize, FromRow)]
+pub struct MissedCall {
+    pub id: Uuid,
+    pub caller_id: Uuid,
+    pub caller_username: String,
+    pub reason: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MentionsQuery {
+    /// Only list mentions that were not read yet
+    #[serde(default)]
+    pub unread: bool,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct Mention {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub sender_id: Option<Uuid>,
+    pub sender_username: Option<String>,
+    /// Set for DM mentions
+    pub room_id: Option<Uuid>,
+    /// Set for channel mentions
+    pub server_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub read_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MentionsResponse {
+    /// All unread mentions, not only the ones in `mentions`
+    pub unread_count: i64,
+    pub mentions: Vec<Mention>,
+}
+
+/// Scope for marking mentions read; both unset marks every mention read.
+#[derive(Debug, Deserialize)]
+pub struct MarkMentionsReadRequest {
+    pub room_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+}
+
 /// Search users by username
 async fn search_users(
     State(state): State<AppState>,
@@ -118,7 +189,7 @@ async fn get_my_profile(
 ) -> Result<Json<UserPublicResult>, AuthError> {
     let profile = sqlx::query_as::<_, UserPublicResult>(
         r#"
-        SELECT id, username, avatar_url, last_seen, public_key
+        SELECT id, username, avatar_url, last_seen, public_key, status_message
         FROM users
         WHERE id = $1
         "#,
@@ -137,7 +208,10 @@ async fn update_my_profile(
     user: AuthUser,
     Json(payload): Json<UpdateProfileRequest>,
 ) -> Result<Json<UserPublicResult>, AuthError> {
-    if payload.username.is_none() && payload.avatar_url.is_none() {
+    if payload.username.is_none()
+        && payload.avatar_url.is_none()
+        && payload.status_message.is_none()
+    {
         return Err(AuthError::Validation(
             "No profile fields provided".to_string(),
         ));
@@ -162,6 +236,18 @@ async fn update_my_profile(
         (false, None)
     };
 
+    let (status_set, status_value) = if let Some(raw_status) = payload.status_message.as_deref() {
+        let trimmed = raw_status.trim();
+        if trimmed.is_empty() {
+            (true, None)
+        } else {
+            validate_status_message(trimmed).map_err(|e| AuthError::Validation(e.to_string()))?;
+            (true, Some(trimmed.to_string()))
+        }
+    } else {
+        (false, None)
+    };
+
     let username_set = username_value.is_some();
 
     let updated = sqlx::query_as::<_, UserPublicResult>(
@@ -169,15 +255,18 @@ async fn update_my_profile(
         UPDATE users
         SET
             username = CASE WHEN $1 THEN $2 ELSE username END,
-            avatar_url = CASE WHEN $3 THEN $4 ELSE avatar_url END
-        WHERE id = $5
-        RETURNING id, username, avatar_url, last_seen, public_key
+            avatar_url = CASE WHEN $3 THEN $4 ELSE avatar_url END,
+            status_message = CASE WHEN $5 THEN $6 ELSE status_message END
+        WHERE id = $7
+        RETURNING id, username, avatar_url, last_seen, public_key, status_message
         "#,
     )
     .bind(username_set)
     .bind(username_value)
     .bind(avatar_set)
     .bind(avatar_value)
+    .bind(status_set)
+    .bind(status_value)
     .bind(user.id)
     .fetch_one(&state.db)
     .await
@@ -190,6 +279,10 @@ async fn update_my_profile(
         AuthError::Database(e)
     })?;
 
+    if status_set {
+        broadcast_presence(&state, user.id).await;
+    }
+
     Ok(Json(updated))
 }
 
@@ -207,6 +300,10 @@ async fn get_my_settings(
             allow_dm_from_strangers,
             enable_mention_notifications,
             enable_sound_notifications,
+            presence_status,
+            filtered_words,
+            call_waiting,
+            send_read_receipts,
             created_at,
             updated_at
         FROM user_settings
@@ -226,6 +323,27 @@ async fn update_my_settings(
     user: AuthUser,
     Json(payload): Json<UpdateSettingsRequest>,
 ) -> Result<Json<UserSettingsResponse>, AuthError> {
+    let presence = payload
+        .presence_status
+        .as_deref()
+        .map(|value| {
+            PresenceStatus::parse(value).ok_or_else(|| {
+                AuthError::Validation("presence_status must be online, away or dnd".to_string())
+            })
+        })
+        .transpose()?;
+    let filtered_words = payload
+        .filtered_words
+        .as_deref()
+        .map(normalize_filtered_words)
+        .transpose()
+        .map_err(|_| {
+            AuthError::Validation(
+                "filtered_words allows up to 100 single-line words of at most 64 characters"
+                    .to_string(),
+            )
+        })?;
+
     ensure_settings_row(&state, user.id).await?;
 
     let settings = sqlx::query_as::<_, UserSettingsResponse>(
@@ -235,13 +353,21 @@ async fn update_my_settings(
             allow_dm_from_strangers = COALESCE($1, allow_dm_from_strangers),
             enable_mention_notifications = COALESCE($2, enable_mention_notifications),
             enable_sound_notifications = COALESCE($3, enable_sound_notifications),
+            presence_status = COALESCE($4, presence_status),
+            filtered_words = COALESCE($5, filtered_words),
+            call_waiting = COALESCE($6, call_waiting),
+            send_read_receipts = COALESCE($7, send_read_receipts),
             updated_at = NOW()
-        WHERE user_id = $4
+        WHERE user_id = $8
         RETURNING
             user_id,
             allow_dm_from_strangers,
             enable_mention_notifications,
             enable_sound_notifications,
+            presence_status,
+            filtered_words,
+            call_waiting,
+            send_read_receipts,
             created_at,
             updated_at
         "#,
@@ -249,13 +375,108 @@ async fn update_my_settings(
     .bind(payload.allow_dm_from_strangers)
     .bind(payload.enable_mention_notifications)
     .bind(payload.enable_sound_notifications)
+    .bind(presence.map(PresenceStatus::as_str))
+    .bind(filtered_words)
+    .bind(payload.call_waiting)
+    .bind(payload.send_read_receipts)
     .bind(user.id)
     .fetch_one(&state.db)
     .await?;
 
+    if presence.is_some() {
+        broadcast_presence(&state, user.id).await;
+    }
+
     Ok(Json(settings))
 }
 
+/// List calls the current user missed without ringing (most recent first)
+async fn list_my_missed_calls(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<Json<Vec<MissedCall>>, AuthError> {
+    let missed = sqlx::query_as::<_, MissedCall>(
+        r#"
+        SELECT mc.id, mc.caller_id, u.username AS caller_username, mc.reason, mc.created_at
+        FROM missed_calls mc
+        JOIN users u ON u.id = mc.caller_id
+        WHERE mc.callee_id = $1
+        ORDER BY mc.created_at DESC
+        LIMIT 50
+        "#,
+    )
+    .bind(user.id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(missed))
+}
+
+/// List mentions of the current user across servers and DMs, newest first,
+/// with the total unread count for the app badge
+async fn list_my_mentions(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(query): Query<MentionsQuery>,
+) -> Result<Json<MentionsResponse>, AuthError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let unread_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM mentions WHERE mentioned_user_id = $1 AND read_at IS NULL",
+    )
+    .bind(user.id)
+    .fetch_one(&state.db)
+    .await?;
+
+    let mentions = sqlx::query_as::<_, Mention>(
+        r#"
+        SELECT mn.id, mn.message_id, mn.sender_id, u.username AS sender_username,
+               mn.room_id, mn.server_id, mn.channel_id, mn.created_at, mn.read_at
+        FROM mentions mn
+        LEFT JOIN users u ON u.id = mn.sender_id
+        WHERE mn.mentioned_user_id = $1
+          AND (NOT $2 OR mn.read_at IS NULL)
+        ORDER BY mn.created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user.id)
+    .bind(query.unread)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(MentionsResponse {
+        unread_count,
+        mentions,
+    }))
+}
+
+/// Mark the current user's mentions read, in one DM room or channel or all
+/// of them
+async fn mark_my_mentions_read(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(req): Json<MarkMentionsReadRequest>,
+) -> Result<StatusCode, AuthError> {
+    sqlx::query(
+        r#"
+        UPDATE mentions SET read_at = NOW()
+        WHERE mentioned_user_id = $1
+          AND read_at IS NULL
+          AND ($2::uuid IS NULL OR room_id = $2)
+          AND ($3::uuid IS NULL OR channel_id = $3)
+        "#,
+    )
+    .bind(user.id)
+    .bind(req.room_id)
+    .bind(req.channel_id)
+    .execute(&state.db)
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Get a specific user's public profile
 async fn get_user(
     State(state): State<AppState>,
@@ -264,7 +485,7 @@ async fn get_user(
 ) -> Result<Json<UserPublicResult>, AuthError> {
     let user = sqlx::query_as::<_, UserPublicResult>(
         r#"
-        SELECT id, username, avatar_url, last_seen, public_key
+        SELECT id, username, avatar_url, last_seen, public_key, status_message
         FROM users
         WHERE id = $1
         "#,
@@ -316,7 +537,11 @@ async fn set_my_public_key(
         .execute(&state.db)
         .await?;
 
-    println!("🔐 User {} set their public key", user.id);
+    tracing::info!(
+        user_id = %user.id,
+        public_key = %Redacted(&payload.public_key),
+        "user set their public key"
+    );
 
     Ok(Json(PublicKeyResponse {
         user_id: user.id,
@@ -0,0 +1,79 @@
+/// `ILIKE` pattern matching `query` anywhere, with `%`, `_` and `\` in the
+/// query taken literally.
+pub fn contains_pattern(query: &str) -> String {
+    let mut pattern = String::with_capacity(query.len() + 2);
+    pattern.push('%');
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Up to `radius` characters either side of the first case-insensitive
+/// match of `query`, with `…` where the content was cut. Falls back to the
+/// start of the content when the match cannot be located (e.g. case folding
+/// changed its length).
+pub fn snippet(content: &str, query: &str, radius: usize) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let needle: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let lowered: Vec<char> = chars
+        .iter()
+        .map(|c| {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(l), None) => l,
+                _ => *c,
+            }
+        })
+        .collect();
+
+    let start = if needle.is_empty() {
+        None
+    } else {
+        lowered
+            .windows(needle.len())
+            .position(|window| window == needle.as_slice())
+    };
+    let (from, to) = match start {
+        Some(start) => (
+            start.saturating_sub(radius),
+            (start + needle.len() + radius).min(chars.len()),
+        ),
+        None => (0, (2 * radius).min(chars.len())),
+    };
+
+    let mut out = String::new();
+    if from > 0 {
+        out.push('…');
+    }
+    out.extend(&chars[from..to]);
+    if to < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_escapes_like_wildcards() {
+        assert_eq!(contains_pattern("50%_off\\"), "%50\\%\\_off\\\\%");
+        assert_eq!(contains_pattern("hello"), "%hello%");
+    }
+
+    #[test]
+    fn snippet_centers_on_the_match() {
+        let content = "the quick brown fox jumps over the lazy dog";
+        assert_eq!(snippet(content, "FOX", 6), "…brown fox jumps…");
+        assert_eq!(snippet(content, "the", 4), "the qui…");
+        assert_eq!(snippet("short", "sh", 40), "short");
+        assert_eq!(snippet("héllo wörld", "WÖR", 2), "…o wörld");
+        assert_eq!(snippet(content, "cat", 5), "the quick …");
+    }
+}
@@ -1,7 +1,16 @@
 use std::collections::HashSet;
 use validator::ValidationError;
 
+use crate::models::Attachment;
+
 const MAX_MESSAGE_LEN: usize = 4000;
+const MAX_STATUS_MESSAGE_LEN: usize = 128;
+const MAX_FILTERED_WORDS: usize = 100;
+const MAX_FILTERED_WORD_LEN: usize = 64;
+const MAX_ATTACHMENTS: usize = 10;
+const MAX_ATTACHMENT_MIME_LEN: usize = 255;
+const MAX_ATTACHMENT_URL_LEN: usize = 2048;
+const MAX_ATTACHMENT_NONCE_LEN: usize = 128;
 
 pub fn validate_username(value: &str) -> Result<(), ValidationError> {
     let trimmed = value.trim();
@@ -51,6 +60,49 @@ pub fn validate_message_content(value: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// Status messages follow the message content rules, with a shorter cap
+/// (in characters) and no line breaks.
+pub fn validate_status_message(value: &str) -> Result<(), ValidationError> {
+    validate_message_content(value)?;
+    let trimmed = value.trim();
+    if trimmed.chars().count() > MAX_STATUS_MESSAGE_LEN {
+        return Err(ValidationError::new("status_message_length"));
+    }
+    if trimmed.chars().any(char::is_control) {
+        return Err(ValidationError::new("status_message_chars"));
+    }
+    Ok(())
+}
+
+/// Trim, lowercase and deduplicate a personal word filter list, dropping
+/// blank entries. Rejects oversized lists, long words and control characters.
+pub fn normalize_filtered_words(words: &[String]) -> Result<Vec<String>, ValidationError> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+
+    for word in words {
+        let word = word.trim().to_lowercase();
+        if word.is_empty() {
+            continue;
+        }
+        if word.chars().count() > MAX_FILTERED_WORD_LEN {
+            return Err(ValidationError::new("filtered_word_length"));
+        }
+        if word.chars().any(char::is_control) {
+            return Err(ValidationError::new("filtered_word_chars"));
+        }
+        if seen.insert(word.clone()) {
+            out.push(word);
+        }
+    }
+
+    if out.len() > MAX_FILTERED_WORDS {
+        return Err(ValidationError::new("filtered_words_count"));
+    }
+
+    Ok(out)
+}
+
 pub fn validate_avatar_url(value: &str) -> Result<(), ValidationError> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -65,6 +117,43 @@ pub fn validate_avatar_url(value: &str) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// At most `MAX_ATTACHMENTS`, each with a distinct id, a `type/subtype`
+/// mime, a non-zero size, an http(s) URL and a nonce
+pub fn validate_attachments(attachments: &[Attachment]) -> Result<(), ValidationError> {
+    if attachments.len() > MAX_ATTACHMENTS {
+        return Err(ValidationError::new("attachments_count"));
+    }
+
+    let mut ids = HashSet::new();
+    for attachment in attachments {
+        if !ids.insert(attachment.id) {
+            return Err(ValidationError::new("attachment_duplicate_id"));
+        }
+        let mime = attachment.mime.as_str();
+        if mime.len() > MAX_ATTACHMENT_MIME_LEN
+            || !mime.split_once('/').is_some_and(|(kind, subtype)| {
+                !kind.is_empty() && !subtype.is_empty() && !mime.chars().any(char::is_whitespace)
+            })
+        {
+            return Err(ValidationError::new("attachment_mime"));
+        }
+        if attachment.size == 0 {
+            return Err(ValidationError::new("attachment_size"));
+        }
+        let url = attachment.encrypted_url.as_str();
+        if url.len() > MAX_ATTACHMENT_URL_LEN
+            || !(url.starts_with("https://") || url.starts_with("http://"))
+        {
+            return Err(ValidationError::new("attachment_url"));
+        }
+        if attachment.nonce.is_empty() || attachment.nonce.len() > MAX_ATTACHMENT_NONCE_LEN {
+            return Err(ValidationError::new("attachment_nonce"));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn validate_emoji(value: &str) -> Result<(), ValidationError> {
     let trimmed = value.trim();
     if trimmed.is_empty() || trimmed.len() > 32 {
@@ -147,4 +236,74 @@ mod tests {
         assert!(validate_message_content("hello").is_ok());
         assert!(validate_message_content("   ").is_err());
     }
+
+    #[test]
+    fn status_message_validation_caps_length_and_line_breaks() {
+        assert!(validate_status_message("  heads-down 🎧 ").is_ok());
+        assert!(validate_status_message(" ").is_err());
+        assert!(validate_status_message("line one\nline two").is_err());
+        assert!(validate_status_message(&"é".repeat(128)).is_ok());
+        assert!(validate_status_message(&"a".repeat(129)).is_err());
+    }
+
+    #[test]
+    fn filtered_words_are_normalized_and_capped() {
+        let words = vec![
+            " Spoiler ".to_string(),
+            "spoiler".to_string(),
+            "".to_string(),
+            "Ünïcode".to_string(),
+        ];
+        assert_eq!(
+            normalize_filtered_words(&words).unwrap(),
+            vec!["spoiler", "ünïcode"]
+        );
+
+        assert!(normalize_filtered_words(&["a\tb".to_string()]).is_err());
+        assert!(normalize_filtered_words(&["x".repeat(65)]).is_err());
+        let too_many = (0..101).map(|i| format!("w{i}")).collect::<Vec<_>>();
+        assert!(normalize_filtered_words(&too_many).is_err());
+    }
+
+    #[test]
+    fn attachments_need_a_mime_size_url_and_nonce() {
+        let attachment = || Attachment {
+            id: uuid::Uuid::new_v4(),
+            mime: "image/png".to_string(),
+            size: 2048,
+            encrypted_url: "https://files.example.org/blob/1".to_string(),
+            nonce: "bm9uY2U=".to_string(),
+        };
+        assert!(validate_attachments(&[]).is_ok());
+        assert!(validate_attachments(&[attachment(), attachment()]).is_ok());
+
+        let duplicate = attachment();
+        assert!(validate_attachments(&[duplicate.clone(), duplicate]).is_err());
+        for bad in [
+            Attachment {
+                mime: "png".to_string(),
+                ..attachment()
+            },
+            Attachment {
+                mime: "image/ png".to_string(),
+                ..attachment()
+            },
+            Attachment {
+                size: 0,
+                ..attachment()
+            },
+            Attachment {
+                encrypted_url: "ftp://files.example.org/1".to_string(),
+                ..attachment()
+            },
+            Attachment {
+                nonce: String::new(),
+                ..attachment()
+            },
+        ] {
+            assert!(validate_attachments(&[bad]).is_err());
+        }
+        let too_many = (0..11).map(|_| attachment()).collect::<Vec<_>>();
+        assert!(validate_attachments(&too_many).is_err());
+    }
 }
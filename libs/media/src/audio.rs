@@ -1,27 +1,202 @@
-use crate::crypto::CryptoContext;
+use crate::compressor::{Compressor, CompressorSettings};
+use crate::crypto::{CryptoContext, CryptoError};
+use crate::denoise::NoiseSuppressor;
+use crate::echo::{EchoCanceller, EchoReference};
+use crate::recording::{RecordingSide, RecordingSink, RecordingTap};
+use crate::soundboard::{ClipInjection, SoundClip};
 use anyhow::Result;
 use audiopus::{
-    coder::Decoder, coder::Encoder, packet::Packet, Application, Channels, MutSignals, SampleRate,
+    coder::Decoder, coder::Encoder, packet::Packet, Application, Bitrate, Channels, ErrorCode,
+    MutSignals, SampleRate,
 };
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, StreamConfig, SupportedStreamConfig};
-use std::collections::VecDeque;
+use cpal::{SampleFormat, StreamConfig, SupportedStreamConfig, SupportedStreamConfigRange};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::{
-    atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering},
-    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    Arc, Mutex, MutexGuard, RwLock,
 };
 use std::thread;
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
 
 /// Audio configuration
 pub const SAMPLE_RATE: u32 = 48000;
 pub const CHANNELS: u16 = 1; // Mono
 pub const FRAME_SIZE: usize = 960; // 20ms at 48kHz
 
+/// Capture frame durations Opus can encode, in ms (see
+/// [`AudioCapture::set_frame_duration_ms`])
+pub const OPUS_FRAME_DURATIONS_MS: [f32; 6] = [2.5, 5.0, 10.0, 20.0, 40.0, 60.0];
+pub const DEFAULT_FRAME_DURATION_MS: f32 = 20.0;
+
 const VOICE_MODE_MUTE: u8 = 0;
 const VOICE_MODE_PTT: u8 = 1;
 const VOICE_MODE_VAD: u8 = 2;
 
+const STAGE_ORDER_GAIN_THEN_GATE: u8 = 0;
+const STAGE_ORDER_GATE_THEN_GAIN: u8 = 1;
+
+const NOISE_SUPPRESSION_LIGHT: u8 = 0;
+const NOISE_SUPPRESSION_HEAVY: u8 = 1;
+
+/// Upper bounds of the runtime controls. Setters clamp to these; configs
+/// passed to `new_with_config` are rejected outside them.
+const MAX_INPUT_GAIN: f32 = 6.0;
+const MAX_VAD_THRESHOLD: f32 = 0.3;
+const MAX_NOISE_GATE_THRESHOLD: f32 = 0.2;
+const MAX_VOLUME: f32 = 2.0;
+const MAX_NOISE_GATE_HOLD_MS: u32 = 500;
+const MAX_NOISE_GATE_HIGH_BAND_BIAS: f32 = 4.0;
+const MAX_AGC_TARGET_RMS: f32 = 0.5;
+/// Also the ceiling of `agc_min_gain`, which must not exceed `agc_max_gain`
+const MAX_AGC_GAIN: f32 = 8.0;
+
+/// How long the noise gate stays open after the level drops, and how much
+/// high-band energy counts towards opening its high band (see
+/// [`AudioCapture::set_noise_gate_high_band_bias`])
+pub const DEFAULT_NOISE_GATE_HOLD_MS: u32 = 50;
+pub const DEFAULT_NOISE_GATE_HIGH_BAND_BIAS: f32 = 2.0;
+
+/// Split between the noise gate's two bands. Sibilants and fricatives
+/// ("s", "f", "sh") carry most of their energy above it.
+const NOISE_GATE_CROSSOVER_HZ: f32 = 3000.0;
+
+/// Level AGC steers towards, and the range its gain stays within
+pub const DEFAULT_AGC_TARGET_RMS: f32 = 0.12;
+pub const DEFAULT_AGC_MIN_GAIN: f32 = 0.3;
+pub const DEFAULT_AGC_MAX_GAIN: f32 = 3.5;
+
+/// How much later than its predecessor's audio length a capture callback may
+/// arrive before it counts as jitter
+const CAPTURE_JITTER_MARGIN: Duration = Duration::from_millis(15);
+
+/// Decoded samples playback may hold (~1s) before it drops the oldest
+/// frames to catch up; this bounds the queue's memory
+pub const PLAYBACK_QUEUE_MAX_SAMPLES: usize = FRAME_SIZE * 50;
+
+/// Capture moves the media key to its next epoch after this long or this
+/// many packets (~4 minutes of 20ms frames), whichever comes first
+const KEY_ROTATION_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const KEY_ROTATION_PACKETS: u64 = 12_000;
+
+/// A capture callback that arrived late: the OS stalled the audio thread
+/// rather than the network dropping packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureJitter {
+    /// Wall-clock time since the previous callback
+    pub interval: Duration,
+    /// Audio length the previous callback delivered, i.e. when this one was due
+    pub expected: Duration,
+}
+
+/// How long the speaking indicator stays on after the last transmitted
+/// block, so the pauses between words do not switch it off
+pub const SPEAKING_HOLD_MS: u32 = 300;
+const SPEAKING_HOLD_SAMPLES: usize = (SAMPLE_RATE * SPEAKING_HOLD_MS / 1000) as usize;
+
+/// The local user started or stopped speaking, by the same decision that
+/// lets capture audio through (voice mode, VAD threshold, mute)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SpeakingState {
+    pub active: bool,
+}
+
+/// A remote stream started or stopped carrying speech. `peer_id` is the
+/// group call peer, or `None` for the main stream of a 1:1 call.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RemoteSpeakingState {
+    pub peer_id: Option<String>,
+    pub active: bool,
+}
+
+/// Debounces the per-block transmit decision into [`SpeakingState`]
+/// transitions: on at once, off after [`SPEAKING_HOLD_MS`] of silence
+#[derive(Debug, Default)]
+struct SpeakingDetector {
+    active: bool,
+    /// Samples per channel held back since the last transmitted block
+    silent_samples: usize,
+}
+
+impl SpeakingDetector {
+    /// Feed a block of `samples` per channel; returns the new state when it
+    /// changes
+    fn update(&mut self, transmitting: bool, samples: usize) -> Option<SpeakingState> {
+        if transmitting {
+            self.silent_samples = 0;
+            if self.active {
+                return None;
+            }
+            self.active = true;
+            return Some(SpeakingState { active: true });
+        }
+        if !self.active {
+            return None;
+        }
+        self.silent_samples += samples;
+        if self.silent_samples < SPEAKING_HOLD_SAMPLES {
+            return None;
+        }
+        self.active = false;
+        Some(SpeakingState { active: false })
+    }
+
+    /// Start over, e.g. when the stream stops being decoded; returns the
+    /// off transition if it was on
+    fn reset(&mut self) -> Option<SpeakingState> {
+        let was_active = self.active;
+        *self = Self::default();
+        was_active.then_some(SpeakingState { active: false })
+    }
+}
+
+/// Playback queue depth, for memory reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct PlaybackBufferStats {
+    /// Interleaved samples, so twice the duration in mono while decoding
+    /// stereo
+    pub queued_samples: usize,
+    /// Deepest the queue got since the last reset
+    pub peak_samples: usize,
+    /// Depth the jitter buffer is aiming for
+    pub target_samples: usize,
+    pub max_samples: usize,
+    /// Memory currently allocated for the queue
+    pub allocated_bytes: usize,
+}
+
+/// Capture counters for call statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct CaptureStats {
+    pub frames_encoded: u64,
+    /// Frames sent as silence because the voice mode held them back
+    pub frames_vad_dropped: u64,
+    /// Silent frames DTX left unsent
+    pub frames_dtx: u64,
+}
+
+/// Incoming stream counters for call statistics, reset with the decoder
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct PlaybackStats {
+    /// Audio packets decoded
+    pub packets_received: u64,
+    /// Sequence numbers skipped over, concealed or not
+    pub packets_lost: u64,
+    /// Lost frames rebuilt with FEC or PLC
+    pub frames_concealed: u64,
+    /// Duplicates and packets behind the decode position
+    pub packets_discarded: u64,
+    /// Discarded packets already received: a replayed nonce or a repeated
+    /// sequence number
+    pub packets_duplicate: u64,
+    /// Discarded packets that arrived after a later one was decoded. Their
+    /// slot was already counted lost.
+    pub packets_out_of_order: u64,
+    /// Smoothed variation in packet spacing, as in RFC 3550
+    pub jitter_ms: f64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VoiceMode {
     Mute,
@@ -39,6 +214,388 @@ impl VoiceMode {
     }
 }
 
+/// Level-dependent capture stages that can be reordered.
+///
+/// Echo cancellation and noise suppression always run first and the
+/// voice-mode decision always runs last; only the stages in between are
+/// configurable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureStage {
+    /// Input gain and AGC
+    Gain,
+    NoiseGate,
+}
+
+impl CaptureStage {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "gain" => Ok(CaptureStage::Gain),
+            "noise_gate" => Ok(CaptureStage::NoiseGate),
+            other => Err(anyhow::anyhow!("Unknown capture stage: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaptureStage::Gain => "gain",
+            CaptureStage::NoiseGate => "noise_gate",
+        }
+    }
+}
+
+/// Supported orderings of the configurable capture stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureStageOrder {
+    #[default]
+    GainThenGate,
+    /// Gate first, so AGC only adapts to audio the gate lets through
+    GateThenGain,
+}
+
+impl CaptureStageOrder {
+    pub fn stages(self) -> [CaptureStage; 2] {
+        match self {
+            CaptureStageOrder::GainThenGate => [CaptureStage::Gain, CaptureStage::NoiseGate],
+            CaptureStageOrder::GateThenGain => [CaptureStage::NoiseGate, CaptureStage::Gain],
+        }
+    }
+
+    /// Resolve a requested stage list, rejecting anything that isn't a
+    /// supported permutation.
+    pub fn from_stages(stages: &[CaptureStage]) -> Result<Self> {
+        [
+            CaptureStageOrder::GainThenGate,
+            CaptureStageOrder::GateThenGain,
+        ]
+        .into_iter()
+        .find(|order| order.stages().as_slice() == stages)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported capture stage order: {:?}", stages))
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            CaptureStageOrder::GainThenGate => STAGE_ORDER_GAIN_THEN_GATE,
+            CaptureStageOrder::GateThenGain => STAGE_ORDER_GATE_THEN_GAIN,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            STAGE_ORDER_GATE_THEN_GAIN => CaptureStageOrder::GateThenGain,
+            _ => CaptureStageOrder::GainThenGate,
+        }
+    }
+}
+
+/// What noise suppression does when it is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseSuppressionMode {
+    /// DC blocker and gentle low-pass. Next to no CPU, but steady noise such
+    /// as fans goes through.
+    #[default]
+    Light,
+    /// Spectral suppression ([`NoiseSuppressor`]) that learns the background
+    /// noise between sentences and removes it. Adds 20 ms of latency.
+    Heavy,
+}
+
+impl NoiseSuppressionMode {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "light" => Ok(NoiseSuppressionMode::Light),
+            "heavy" => Ok(NoiseSuppressionMode::Heavy),
+            other => Err(anyhow::anyhow!("Unknown noise suppression mode: {}", other)),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NoiseSuppressionMode::Light => "light",
+            NoiseSuppressionMode::Heavy => "heavy",
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            NoiseSuppressionMode::Light => NOISE_SUPPRESSION_LIGHT,
+            NoiseSuppressionMode::Heavy => NOISE_SUPPRESSION_HEAVY,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            NOISE_SUPPRESSION_HEAVY => NoiseSuppressionMode::Heavy,
+            _ => NoiseSuppressionMode::Light,
+        }
+    }
+}
+
+fn check_frame_duration(ms: f32) -> Result<usize> {
+    frame_size_for_ms(ms).ok_or_else(|| {
+        anyhow::anyhow!(
+            "frame_duration_ms must be one of {:?}, got {}",
+            OPUS_FRAME_DURATIONS_MS,
+            ms
+        )
+    })
+}
+
+fn check_range(name: &str, value: f32, max: f32) -> Result<()> {
+    if !(0.0..=max).contains(&value) {
+        anyhow::bail!("{} must be between 0 and {}, got {}", name, max, value);
+    }
+    Ok(())
+}
+
+/// Initial settings for [`AudioCapture::new_with_config`]. The defaults
+/// match what `AudioCapture::new` starts with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioCaptureConfig {
+    pub input_gain: f32,
+    pub voice_mode: VoiceMode,
+    pub vad_threshold: f32,
+    pub noise_gate_threshold: f32,
+    pub noise_suppression: bool,
+    pub noise_suppression_mode: NoiseSuppressionMode,
+    pub aec_enabled: bool,
+    pub agc_enabled: bool,
+    /// See [`AudioCapture::set_agc_target_rms`]
+    pub agc_target_rms: f32,
+    /// See [`AudioCapture::set_agc_gain_range`]
+    pub agc_min_gain: f32,
+    pub agc_max_gain: f32,
+    pub noise_gate_enabled: bool,
+    /// See [`AudioCapture::set_noise_gate_hold_ms`]
+    pub noise_gate_hold_ms: u32,
+    /// See [`AudioCapture::set_noise_gate_high_band_bias`]
+    pub noise_gate_high_band_bias: f32,
+    pub stage_order: CaptureStageOrder,
+    pub muted: bool,
+    /// Send stereo, see [`AudioCapture::set_stereo`]
+    pub stereo: bool,
+    /// Stop sending during silence, see [`AudioCapture::set_dtx`]
+    pub dtx: bool,
+    /// Audio per packet, see [`AudioCapture::set_frame_duration_ms`]
+    pub frame_duration_ms: f32,
+}
+
+impl Default for AudioCaptureConfig {
+    fn default() -> Self {
+        Self {
+            input_gain: 1.0,
+            voice_mode: VoiceMode::VoiceActivity,
+            vad_threshold: 0.02,
+            noise_gate_threshold: 0.01,
+            noise_suppression: true,
+            noise_suppression_mode: NoiseSuppressionMode::Light,
+            aec_enabled: true,
+            agc_enabled: true,
+            agc_target_rms: DEFAULT_AGC_TARGET_RMS,
+            agc_min_gain: DEFAULT_AGC_MIN_GAIN,
+            agc_max_gain: DEFAULT_AGC_MAX_GAIN,
+            noise_gate_enabled: true,
+            noise_gate_hold_ms: DEFAULT_NOISE_GATE_HOLD_MS,
+            noise_gate_high_band_bias: DEFAULT_NOISE_GATE_HIGH_BAND_BIAS,
+            stage_order: CaptureStageOrder::GainThenGate,
+            muted: false,
+            stereo: false,
+            dtx: false,
+            frame_duration_ms: DEFAULT_FRAME_DURATION_MS,
+        }
+    }
+}
+
+impl AudioCaptureConfig {
+    /// Pull levels into range the way the setters do, for configs built
+    /// from user input.
+    pub fn clamped(mut self) -> Self {
+        self.input_gain = self.input_gain.clamp(0.0, MAX_INPUT_GAIN);
+        self.vad_threshold = self.vad_threshold.clamp(0.0, MAX_VAD_THRESHOLD);
+        self.noise_gate_threshold = self
+            .noise_gate_threshold
+            .clamp(0.0, MAX_NOISE_GATE_THRESHOLD);
+        self.noise_gate_hold_ms = self.noise_gate_hold_ms.min(MAX_NOISE_GATE_HOLD_MS);
+        self.noise_gate_high_band_bias = self
+            .noise_gate_high_band_bias
+            .clamp(0.0, MAX_NOISE_GATE_HIGH_BAND_BIAS);
+        self.agc_target_rms = self.agc_target_rms.clamp(0.0, MAX_AGC_TARGET_RMS);
+        self.agc_max_gain = self.agc_max_gain.clamp(0.0, MAX_AGC_GAIN);
+        self.agc_min_gain = self.agc_min_gain.clamp(0.0, self.agc_max_gain);
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        check_range("input_gain", self.input_gain, MAX_INPUT_GAIN)?;
+        check_range("vad_threshold", self.vad_threshold, MAX_VAD_THRESHOLD)?;
+        check_range(
+            "noise_gate_threshold",
+            self.noise_gate_threshold,
+            MAX_NOISE_GATE_THRESHOLD,
+        )?;
+        check_range(
+            "noise_gate_hold_ms",
+            self.noise_gate_hold_ms as f32,
+            MAX_NOISE_GATE_HOLD_MS as f32,
+        )?;
+        check_range(
+            "noise_gate_high_band_bias",
+            self.noise_gate_high_band_bias,
+            MAX_NOISE_GATE_HIGH_BAND_BIAS,
+        )?;
+        check_range("agc_target_rms", self.agc_target_rms, MAX_AGC_TARGET_RMS)?;
+        check_range("agc_max_gain", self.agc_max_gain, MAX_AGC_GAIN)?;
+        check_range("agc_min_gain", self.agc_min_gain, self.agc_max_gain)?;
+        check_frame_duration(self.frame_duration_ms).map(|_| ())
+    }
+}
+
+/// Initial settings for [`AudioPlayback::new_with_config`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioPlaybackConfig {
+    pub output_volume: f32,
+    pub remote_volume: f32,
+    pub limiter_enabled: bool,
+    pub muted: bool,
+    /// Skip decrypting and decoding, see [`AudioPlayback::set_deafened`]
+    pub deafened: bool,
+    /// Jitter buffer depth, see [`AudioPlayback::set_jitter_target_ms`]
+    pub jitter_target_ms: u32,
+    /// Decode to stereo, see [`AudioPlayback::set_stereo`]
+    pub stereo: bool,
+    /// See [`AudioPlayback::set_compressor`]
+    pub compressor: CompressorSettings,
+}
+
+impl Default for AudioPlaybackConfig {
+    fn default() -> Self {
+        Self {
+            output_volume: 1.0,
+            remote_volume: 1.0,
+            limiter_enabled: true,
+            muted: false,
+            deafened: false,
+            jitter_target_ms: DEFAULT_JITTER_TARGET_MS,
+            stereo: false,
+            compressor: CompressorSettings::default(),
+        }
+    }
+}
+
+impl AudioPlaybackConfig {
+    pub fn clamped(mut self) -> Self {
+        self.output_volume = self.output_volume.clamp(0.0, MAX_VOLUME);
+        self.remote_volume = self.remote_volume.clamp(0.0, MAX_VOLUME);
+        self.jitter_target_ms = self
+            .jitter_target_ms
+            .clamp(MIN_JITTER_TARGET_MS, MAX_JITTER_TARGET_MS);
+        self.compressor = self.compressor.clamped();
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        check_range("output_volume", self.output_volume, MAX_VOLUME)?;
+        check_range("remote_volume", self.remote_volume, MAX_VOLUME)?;
+        if !(MIN_JITTER_TARGET_MS..=MAX_JITTER_TARGET_MS).contains(&self.jitter_target_ms) {
+            anyhow::bail!(
+                "jitter_target_ms must be between {} and {}, got {}",
+                MIN_JITTER_TARGET_MS,
+                MAX_JITTER_TARGET_MS,
+                self.jitter_target_ms
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Session key of a pipeline. Empty while the streams are pre-warmed during
+/// ringing, filled once the key exchange completes.
+#[derive(Default)]
+struct CryptoSlot(RwLock<Option<Arc<CryptoContext>>>);
+
+impl CryptoSlot {
+    fn new(crypto: Option<Arc<CryptoContext>>) -> Self {
+        Self(RwLock::new(crypto))
+    }
+
+    fn get(&self) -> Option<Arc<CryptoContext>> {
+        self.0.read().ok().and_then(|crypto| crypto.clone())
+    }
+
+    fn set(&self, crypto: Arc<CryptoContext>) {
+        if let Ok(mut slot) = self.0.write() {
+            *slot = Some(crypto);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PlaybackControls {
+    output_volume_bits: AtomicU32,
+    remote_volume_bits: AtomicU32,
+    limiter_enabled: AtomicBool,
+    /// Ahead of the limiter; its envelope belongs to the output stream and
+    /// starts over with each one
+    compressor: Mutex<Compressor>,
+    /// Mixed 48 kHz audio to the device rate; replaced with each stream
+    output_resampler: Mutex<OutputResampler>,
+    muted: AtomicBool,
+    /// Incoming packets only move the sequence on; nothing is decrypted or
+    /// decoded, and the output is silent
+    deafened: AtomicBool,
+    raw_mode: AtomicBool,
+    /// Stream open but silent; incoming packets are dropped
+    paused: AtomicBool,
+    /// Receives everything played, for the capture side's echo canceller
+    echo_reference: EchoReference,
+    /// Output callbacks that found the jitter buffer run dry
+    underruns: AtomicU64,
+    /// Times the jitter buffer dropped queued audio because it held too much
+    overruns: AtomicU64,
+    /// Group call peers mixed in with the main stream, by peer id
+    peers: RwLock<HashMap<String, Arc<PeerStream>>>,
+    /// Call recording, fed the mix as played
+    recording: RecordingTap,
+    /// Remote speaking transitions, see [`RemoteSpeakingState`]
+    speaking_tx: Mutex<Option<mpsc::UnboundedSender<RemoteSpeakingState>>>,
+    /// Output level of each callback, for the remote VU meter; only the
+    /// latest matters, as on the capture side
+    output_rms_tx: watch::Sender<f32>,
+}
+
+/// One remote peer of a group call: its sender key, decoder and jitter
+/// buffer, played at its own volume on top of the other peers
+struct PeerStream {
+    crypto: CryptoSlot,
+    decoder: Mutex<OpusDecoder>,
+    stream: Mutex<StreamState>,
+    queue: Mutex<JitterBuffer>,
+    volume_bits: AtomicU32,
+}
+
+impl PlaybackControls {
+    fn report_speaking(&self, peer_id: Option<&str>, state: SpeakingState) {
+        if let Ok(speaking_tx) = self.speaking_tx.lock() {
+            if let Some(speaking_tx) = speaking_tx.as_ref() {
+                let _ = speaking_tx.send(RemoteSpeakingState {
+                    peer_id: peer_id.map(str::to_string),
+                    active: state.active,
+                });
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for PeerStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeerStream")
+            .field(
+                "volume",
+                &f32::from_bits(self.volume_bits.load(Ordering::Relaxed)),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug)]
 struct CaptureControls {
     input_gain_bits: AtomicU32,
@@ -47,32 +604,101 @@ struct CaptureControls {
     voice_mode: AtomicU8,
     ptt_active: AtomicBool,
     noise_suppression: AtomicBool,
+    noise_suppression_mode: AtomicU8,
     aec_enabled: AtomicBool,
     agc_enabled: AtomicBool,
+    agc_target_rms_bits: AtomicU32,
+    agc_min_gain_bits: AtomicU32,
+    agc_max_gain_bits: AtomicU32,
     noise_gate_enabled: AtomicBool,
-    shared_playback_rms_bits: Arc<AtomicU32>,
+    noise_gate_hold_ms: AtomicU32,
+    noise_gate_high_band_bias_bits: AtomicU32,
+    stage_order: AtomicU8,
+    /// Encode left/right instead of a mono downmix
+    stereo: AtomicBool,
+    /// Samples per channel in each encoded frame
+    frame_size: AtomicUsize,
+    /// What playback played, for echo cancellation
+    echo_reference: EchoReference,
+    /// Stream open but frames are dropped before encoding
+    paused: AtomicBool,
+    /// Late capture callbacks seen since the capture was created
+    jitter_events: AtomicU64,
+    /// Frames encoded and handed to the send loop
+    frames_encoded: AtomicU64,
+    /// Samples replaced with silence because the voice mode held them back
+    /// (VAD below threshold, PTT released, voice mode mute)
+    vad_gated_samples: AtomicU64,
+    /// Frames the encoder marked as DTX and that were not sent
+    frames_dtx: AtomicU64,
+    jitter_tx: Mutex<Option<mpsc::UnboundedSender<CaptureJitter>>>,
+    /// Speaking indicator transitions, see [`SpeakingDetector`]
+    speaking_tx: Mutex<Option<mpsc::UnboundedSender<SpeakingState>>>,
+    /// Sound clip being played into the call
+    clip: Mutex<Option<ClipInjection>>,
+    /// Group call peers each encrypted frame also goes to, by peer id
+    recipients: RwLock<HashMap<String, mpsc::UnboundedSender<AudioPacket>>>,
+    /// Call recording, fed what is about to be encoded
+    recording: RecordingTap,
 }
 
 struct CapturePipelineState {
     sample_buffer: Vec<i16>,
-    resample_pos: f64,
-    dc_prev_x: f32,
-    dc_prev_y: f32,
-    lowpass_prev: f32,
+    /// Resampler read position within the next callback, in 1/48000ths of
+    /// an input sample (see `resample_to_48k`)
+    resample_phase: u64,
+    /// Arrival time and audio length of the previous callback
+    last_callback: Option<(Instant, Duration)>,
+    /// Channel layout of `sample_buffer`, 1 or 2
+    channels: usize,
+    /// Light noise suppression filters, one per channel
+    noise_filters: [NoiseFilter; 2],
+    /// Heavy noise suppressors, one per channel
+    noise_suppressors: [NoiseSuppressor; 2],
+    /// Whether the heavy suppressors processed the previous block. Their
+    /// buffered audio is stale once they sat out a block.
+    heavy_suppression_active: bool,
+    /// Voice activity of the previous block, by the VAD threshold whatever
+    /// the voice mode. The heavy suppressor learns noise while it is false.
+    speech: bool,
+    /// Whether the user counts as speaking, with hold time
+    speaking: SpeakingDetector,
+    /// Echo cancellers, one per channel; their taps are the learned echo
+    /// path, so they live as long as the stream
+    echo_cancellers: [EchoCanceller; 2],
     agc_gain: f32,
+    /// Noise gate gain below the crossover, and over the full band as far
+    /// as AGC is concerned
     gate_gain: f32,
+    /// Noise gate gain above the crossover
+    gate_high_gain: f32,
+    /// Samples per channel each gate band stays open for after its level
+    /// last reached the threshold
+    gate_hold_remaining: usize,
+    gate_high_hold_remaining: usize,
+    /// Crossover low-pass state, one per channel
+    gate_crossover: [f32; 2],
 }
 
 impl CapturePipelineState {
     fn new() -> Self {
         Self {
             sample_buffer: Vec::with_capacity(FRAME_SIZE * 3),
-            resample_pos: 0.0,
-            dc_prev_x: 0.0,
-            dc_prev_y: 0.0,
-            lowpass_prev: 0.0,
+            resample_phase: 0,
+            last_callback: None,
+            channels: 1,
+            noise_filters: [NoiseFilter::default(); 2],
+            noise_suppressors: Default::default(),
+            heavy_suppression_active: false,
+            speech: false,
+            speaking: SpeakingDetector::default(),
+            echo_cancellers: Default::default(),
             agc_gain: 1.0,
             gate_gain: 1.0,
+            gate_high_gain: 1.0,
+            gate_hold_remaining: 0,
+            gate_high_hold_remaining: 0,
+            gate_crossover: [0.0; 2],
         }
     }
 }
@@ -80,53 +706,315 @@ impl CapturePipelineState {
 /// Opus encoder wrapper
 pub struct OpusEncoder {
     encoder: Encoder,
+    channels: usize,
+    dtx: bool,
 }
 
+/// With DTX on, libopus encodes silence as packets this small. They carry
+/// nothing the decoder needs and are not sent.
+const OPUS_DTX_PACKET_MAX_BYTES: usize = 2;
+
+/// Output buffer libopus recommends for one `opus_encode` call. Any single
+/// packet fits, whatever the bitrate, channel count or frame size.
+const OPUS_MAX_PACKET_SIZE: usize = 4000;
+
+/// Upper bound for growing the output buffer on `BufferTooSmall`. A 60 ms
+/// frame, the longest in `OPUS_FRAME_SIZES`, is at most 3 x 1275 bytes plus
+/// framing at the 510 kbps maximum bitrate; this is about twice that.
+const OPUS_MAX_PACKET_SIZE_LIMIT: usize = 8000;
+
+/// Frame sizes Opus accepts at 48 kHz (2.5 to 60 ms), per channel
+const OPUS_FRAME_SIZES: [usize; 6] = [120, 240, 480, 960, 1920, 2880];
+const OPUS_MAX_FRAME_SIZE: usize = 2880;
+
+/// Samples per channel in a frame of `ms`, if Opus can encode frames that
+/// long
+pub fn frame_size_for_ms(ms: f32) -> Option<usize> {
+    OPUS_FRAME_DURATIONS_MS
+        .iter()
+        .zip(OPUS_FRAME_SIZES)
+        .find(|(&duration, _)| duration == ms)
+        .map(|(_, size)| size)
+}
+
+/// Loss rate the encoder plans its in-band FEC for. Opus only spends bits on
+/// FEC when this is above zero.
+const OPUS_EXPECTED_LOSS_PERC: u8 = 10;
+
+/// Range `BitrateController` keeps the encoder in
+pub const MIN_OPUS_BITRATE: u32 = 8_000;
+pub const MAX_OPUS_BITRATE: u32 = 64_000;
+
+/// Weight of the newest interval in the smoothed loss rate
+const LOSS_SMOOTHING: f32 = 0.3;
+/// Loss above which the bitrate steps down, and below which it may recover
+const LOSS_HIGH_PERCENT: f32 = 5.0;
+const LOSS_LOW_PERCENT: f32 = 1.0;
+/// Consecutive lossy intervals before stepping down. A single spike only
+/// moves the average, so it never changes the bitrate by itself.
+const BITRATE_DOWN_AFTER: u32 = 2;
+/// Consecutive clean intervals before stepping back up; slower than going
+/// down so a recovering link is not pushed straight back into loss
+const BITRATE_UP_AFTER: u32 = 5;
+const BITRATE_UP_STEP: u32 = 4_000;
+
+/// Longest run of lost packets playback synthesizes audio for. Opus PLC
+/// fades to silence well within this, so longer gaps are left to the jitter
+/// buffer.
+const MAX_CONCEALED_FRAMES: u32 = 5;
+
 impl OpusEncoder {
     pub fn new() -> Result<Self> {
-        let encoder = Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Voip)
+        Self::with_config(Channels::Mono, Bitrate::Auto)
+    }
+
+    pub fn with_config(channels: Channels, bitrate: Bitrate) -> Result<Self> {
+        let mut encoder = Encoder::new(SampleRate::Hz48000, channels, Application::Voip)
             .map_err(|e| anyhow::anyhow!("Failed to create Opus encoder: {:?}", e))?;
+        encoder
+            .set_bitrate(bitrate)
+            .map_err(|e| anyhow::anyhow!("Failed to set Opus bitrate: {:?}", e))?;
+        // Each packet carries a low-bitrate copy of the previous frame, so
+        // the receiver can rebuild a single lost packet from the next one
+        encoder
+            .set_inband_fec(true)
+            .map_err(|e| anyhow::anyhow!("Failed to enable Opus FEC: {:?}", e))?;
+        encoder
+            .set_packet_loss_perc(OPUS_EXPECTED_LOSS_PERC)
+            .map_err(|e| anyhow::anyhow!("Failed to set Opus packet loss: {:?}", e))?;
 
-        Ok(Self { encoder })
+        Ok(Self {
+            encoder,
+            channels: channels as usize,
+            dtx: false,
+        })
     }
 
-    /// Encode audio samples to Opus
-    pub fn encode(&mut self, samples: &[i16]) -> Result<Vec<u8>> {
-        let mut output = vec![0u8; 1024]; // Max Opus packet size
-        let len = self
+    /// Change the target bitrate; takes effect from the next frame
+    pub fn set_bitrate(&mut self, bits_per_sec: u32) -> Result<()> {
+        let bits_per_sec = i32::try_from(bits_per_sec)
+            .map_err(|_| anyhow::anyhow!("Opus bitrate out of range: {}", bits_per_sec))?;
+        self.encoder
+            .set_bitrate(Bitrate::BitsPerSecond(bits_per_sec))
+            .map_err(|e| anyhow::anyhow!("Failed to set Opus bitrate: {:?}", e))
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Rebuild the encoder for 1 or 2 channels, keeping its bitrate. The
+    /// next packet starts a fresh stream on the receiving decoder.
+    pub fn set_channels(&mut self, channels: usize) -> Result<()> {
+        let bitrate = self
             .encoder
-            .encode(samples, &mut output[..])
-            .map_err(|e| anyhow::anyhow!("Encode error: {:?}", e))?;
-        output.truncate(len);
-        Ok(output)
+            .bitrate()
+            .map_err(|e| anyhow::anyhow!("Failed to read Opus bitrate: {:?}", e))?;
+        let dtx = self.dtx;
+        *self = Self::with_config(opus_channels(channels)?, bitrate)?;
+        self.set_dtx(dtx)
+    }
+
+    /// Discontinuous transmission: once the input has been silent for a
+    /// while, frames come out as 1-byte packets that need not be sent, with
+    /// a comfort noise update every 400 ms.
+    pub fn set_dtx(&mut self, enabled: bool) -> Result<()> {
+        self.encoder
+            .set_dtx(enabled)
+            .map_err(|e| anyhow::anyhow!("Failed to set Opus DTX: {:?}", e))?;
+        self.dtx = enabled;
+        Ok(())
+    }
+
+    pub fn dtx(&self) -> bool {
+        self.dtx
+    }
+
+    /// Whether `packet`, returned by `encode`, is a DTX frame to leave unsent
+    pub fn is_dtx_packet(&self, packet: &[u8]) -> bool {
+        self.dtx && packet.len() <= OPUS_DTX_PACKET_MAX_BYTES
+    }
+
+    /// Encode one frame of (interleaved) samples to an Opus packet
+    pub fn encode(&mut self, samples: &[i16]) -> Result<Vec<u8>> {
+        let per_channel = samples.len() / self.channels;
+        if !samples.len().is_multiple_of(self.channels) || !OPUS_FRAME_SIZES.contains(&per_channel)
+        {
+            anyhow::bail!(
+                "Invalid Opus frame: {} samples for {} channel(s)",
+                samples.len(),
+                self.channels
+            );
+        }
+
+        let mut output = vec![0u8; OPUS_MAX_PACKET_SIZE];
+        loop {
+            match self.encoder.encode(samples, &mut output[..]) {
+                Ok(len) => {
+                    output.truncate(len);
+                    return Ok(output);
+                }
+                Err(audiopus::Error::Opus(ErrorCode::BufferTooSmall))
+                    if output.len() < OPUS_MAX_PACKET_SIZE_LIMIT =>
+                {
+                    output.resize(output.len() * 2, 0);
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!(
+                        "Encode error ({} samples, {} byte buffer): {:?}",
+                        samples.len(),
+                        output.len(),
+                        e
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Picks an encoder bitrate from periodic packet loss measurements. Loss is
+/// smoothed and a change needs several intervals in a row pointing the same
+/// way, so brief spikes do not make the bitrate flap.
+#[derive(Debug, Clone)]
+pub struct BitrateController {
+    bitrate: u32,
+    smoothed_loss: f32,
+    lossy_intervals: u32,
+    clean_intervals: u32,
+}
+
+impl Default for BitrateController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitrateController {
+    /// Start at the top of the range and back off once loss shows up
+    pub fn new() -> Self {
+        Self {
+            bitrate: MAX_OPUS_BITRATE,
+            smoothed_loss: 0.0,
+            lossy_intervals: 0,
+            clean_intervals: 0,
+        }
+    }
+
+    pub fn bitrate(&self) -> u32 {
+        self.bitrate
+    }
+
+    /// Feed the loss rate of the last interval, in percent. Returns the new
+    /// bitrate when it changed.
+    pub fn update(&mut self, loss_percent: f32) -> Option<u32> {
+        let loss = if loss_percent.is_finite() {
+            loss_percent.clamp(0.0, 100.0)
+        } else {
+            0.0
+        };
+        self.smoothed_loss += (loss - self.smoothed_loss) * LOSS_SMOOTHING;
+
+        if loss > LOSS_HIGH_PERCENT && self.smoothed_loss > LOSS_HIGH_PERCENT {
+            self.lossy_intervals += 1;
+        } else {
+            self.lossy_intervals = 0;
+        }
+        if self.smoothed_loss < LOSS_LOW_PERCENT {
+            self.clean_intervals += 1;
+        } else {
+            self.clean_intervals = 0;
+        }
+
+        let previous = self.bitrate;
+        if self.lossy_intervals >= BITRATE_DOWN_AFTER {
+            // Multiplicative decrease: heavy loss gets out of the way fast
+            self.bitrate = (self.bitrate * 3 / 4).max(MIN_OPUS_BITRATE);
+            self.lossy_intervals = 0;
+        } else if self.clean_intervals >= BITRATE_UP_AFTER {
+            self.bitrate = (self.bitrate + BITRATE_UP_STEP).min(MAX_OPUS_BITRATE);
+            self.clean_intervals = 0;
+        }
+        (self.bitrate != previous).then_some(self.bitrate)
+    }
+}
+
+fn opus_channels(channels: usize) -> Result<Channels> {
+    match channels {
+        1 => Ok(Channels::Mono),
+        2 => Ok(Channels::Stereo),
+        _ => anyhow::bail!("Unsupported channel count: {}", channels),
     }
 }
 
 /// Opus decoder wrapper
 pub struct OpusDecoder {
     decoder: Decoder,
+    channels: usize,
 }
 
 impl OpusDecoder {
-    pub fn new() -> Result<Self> {
-        let decoder = Decoder::new(SampleRate::Hz48000, Channels::Mono)
+    /// Decoder producing `channels` (1 or 2) interleaved channels. Opus
+    /// packets say whether they were encoded in stereo and libopus converts
+    /// to the decoder's layout, so either decoder plays either kind of packet.
+    pub fn with_channels(channels: usize) -> Result<Self> {
+        let decoder = Decoder::new(SampleRate::Hz48000, opus_channels(channels)?)
             .map_err(|e| anyhow::anyhow!("Failed to create Opus decoder: {:?}", e))?;
 
-        Ok(Self { decoder })
+        Ok(Self { decoder, channels })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
     }
 
-    /// Decode Opus packet to audio samples
+    /// Decode Opus packet to audio samples. Packets carry their own frame
+    /// size, so the buffer fits the longest and is cut to what was decoded.
     pub fn decode(&mut self, packet: &[u8]) -> Result<Vec<i16>> {
-        let mut output = vec![0i16; FRAME_SIZE];
-        let opus_packet =
-            Packet::try_from(packet).map_err(|e| anyhow::anyhow!("Invalid packet: {:?}", e))?;
+        self.decode_into(Some(packet), OPUS_MAX_FRAME_SIZE, false)
+    }
+
+    /// Synthesize audio for a lost packet from the decoder's history. Calls
+    /// in a row fade out, so long gaps end in silence rather than a buzz.
+    pub fn decode_plc(&mut self) -> Result<Vec<i16>> {
+        self.decode_into(None, self.last_frame_size(), false)
+    }
+
+    /// Rebuild the frame lost just before `next_packet` from the FEC copy
+    /// it carries. Opus falls back to PLC when the packet has none, e.g.
+    /// from an encoder without FEC or a CELT-only frame.
+    pub fn decode_fec(&mut self, next_packet: &[u8]) -> Result<Vec<i16>> {
+        self.decode_into(Some(next_packet), self.last_frame_size(), true)
+    }
+
+    /// Concealment must match the length of the frame it replaces; assume
+    /// the stream kept the size of the last decoded frame.
+    fn last_frame_size(&self) -> usize {
+        self.decoder
+            .last_packet_duration()
+            .ok()
+            .map(|duration| duration as usize)
+            .filter(|size| OPUS_FRAME_SIZES.contains(size))
+            .unwrap_or(FRAME_SIZE)
+    }
+
+    fn decode_into(
+        &mut self,
+        packet: Option<&[u8]>,
+        frame_size: usize,
+        fec: bool,
+    ) -> Result<Vec<i16>> {
+        let mut output = vec![0i16; frame_size * self.channels];
+        let opus_packet = packet
+            .map(Packet::try_from)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid packet: {:?}", e))?;
         let signals = MutSignals::try_from(&mut output[..])
             .map_err(|e| anyhow::anyhow!("Signal buffer error: {:?}", e))?;
         let len = self
             .decoder
-            .decode(Some(opus_packet), signals, false)
+            .decode(opus_packet, signals, fec)
             .map_err(|e| anyhow::anyhow!("Decode error: {:?}", e))?;
-        output.truncate(len);
+        output.truncate(len * self.channels);
         Ok(output)
     }
 }
@@ -136,14 +1024,29 @@ impl OpusDecoder {
 pub struct AudioPacket {
     /// Sequence number
     pub seq: u32,
-    /// Encrypted Opus data (nonce + ciphertext)
+    /// Encrypted Opus data (nonce + ciphertext); empty for keepalives
     pub data: Vec<u8>,
 }
 
+impl AudioPacket {
+    /// Payload-less packet sent during long silences to keep the path warm.
+    /// Real audio always carries a nonce and tag, so it is never empty.
+    pub fn keepalive() -> Self {
+        Self {
+            seq: 0,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn is_keepalive(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
 /// Audio capture pipeline (Mic -> Opus -> Encrypt -> Channel)
 pub struct AudioCapture {
     encoder: Arc<Mutex<OpusEncoder>>,
-    crypto: Arc<CryptoContext>,
+    crypto: Arc<CryptoSlot>,
     controls: Arc<CaptureControls>,
     packet_tx: mpsc::UnboundedSender<AudioPacket>,
     // We hold the receiver until it's taken by the WebRTC engine
@@ -155,93 +1058,303 @@ pub struct AudioCapture {
     run_token: Arc<AtomicU64>,
     // Mute flag - when true, send silence instead of mic data
     muted: Arc<AtomicBool>,
-    // VU meter RMS emission
-    rms_tx: mpsc::UnboundedSender<f32>,
-    rms_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<f32>>>>,
+    // VU meter RMS emission; only the latest level matters, so a slow or
+    // absent meter just misses intermediate values
+    rms_tx: Arc<watch::Sender<f32>>,
+    rms_rx: Arc<Mutex<Option<watch::Receiver<f32>>>>,
+    speaking_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<SpeakingState>>>>,
+    device_events: Arc<DeviceEvents>,
 }
 
 impl AudioCapture {
-    pub fn new(
+    pub fn new(crypto: Arc<CryptoContext>, echo_reference: EchoReference) -> Result<Self> {
+        Self::new_with_config(crypto, echo_reference, AudioCaptureConfig::default())
+    }
+
+    /// Create a capture already carrying `config`, so the first frame is
+    /// processed with the intended settings. Out-of-range values are an
+    /// error here; the `set_*` methods clamp them for runtime changes.
+    pub fn new_with_config(
         crypto: Arc<CryptoContext>,
-        shared_playback_rms_bits: Arc<AtomicU32>,
+        echo_reference: EchoReference,
+        config: AudioCaptureConfig,
+    ) -> Result<Self> {
+        Self::build(Some(crypto), echo_reference, config, false)
+    }
+
+    /// Create a capture with no session key yet, paused. Starting it opens
+    /// the input device without sending anything; call `set_crypto` and
+    /// `resume` once the call connects.
+    pub fn new_prewarmed(
+        echo_reference: EchoReference,
+        config: AudioCaptureConfig,
+    ) -> Result<Self> {
+        Self::build(None, echo_reference, config, true)
+    }
+
+    fn build(
+        crypto: Option<Arc<CryptoContext>>,
+        echo_reference: EchoReference,
+        config: AudioCaptureConfig,
+        paused: bool,
     ) -> Result<Self> {
+        config.validate()?;
         let (packet_tx, packet_rx) = mpsc::unbounded_channel();
-        let (rms_tx, rms_rx) = mpsc::unbounded_channel();
+        let (rms_tx, rms_rx) = watch::channel(0.0f32);
+        let (speaking_tx, speaking_rx) = mpsc::unbounded_channel();
         let controls = Arc::new(CaptureControls {
-            input_gain_bits: AtomicU32::new(1.0f32.to_bits()),
-            vad_threshold_bits: AtomicU32::new(0.02f32.to_bits()),
-            noise_gate_threshold_bits: AtomicU32::new(0.01f32.to_bits()),
-            voice_mode: AtomicU8::new(VOICE_MODE_VAD),
+            input_gain_bits: AtomicU32::new(config.input_gain.to_bits()),
+            vad_threshold_bits: AtomicU32::new(config.vad_threshold.to_bits()),
+            noise_gate_threshold_bits: AtomicU32::new(config.noise_gate_threshold.to_bits()),
+            voice_mode: AtomicU8::new(config.voice_mode.to_u8()),
             ptt_active: AtomicBool::new(false),
-            noise_suppression: AtomicBool::new(true),
-            aec_enabled: AtomicBool::new(true),
-            agc_enabled: AtomicBool::new(true),
-            noise_gate_enabled: AtomicBool::new(true),
-            shared_playback_rms_bits,
+            noise_suppression: AtomicBool::new(config.noise_suppression),
+            noise_suppression_mode: AtomicU8::new(config.noise_suppression_mode.to_u8()),
+            aec_enabled: AtomicBool::new(config.aec_enabled),
+            agc_enabled: AtomicBool::new(config.agc_enabled),
+            agc_target_rms_bits: AtomicU32::new(config.agc_target_rms.to_bits()),
+            agc_min_gain_bits: AtomicU32::new(config.agc_min_gain.to_bits()),
+            agc_max_gain_bits: AtomicU32::new(config.agc_max_gain.to_bits()),
+            noise_gate_enabled: AtomicBool::new(config.noise_gate_enabled),
+            noise_gate_hold_ms: AtomicU32::new(config.noise_gate_hold_ms),
+            noise_gate_high_band_bias_bits: AtomicU32::new(
+                config.noise_gate_high_band_bias.to_bits(),
+            ),
+            stage_order: AtomicU8::new(config.stage_order.to_u8()),
+            stereo: AtomicBool::new(config.stereo),
+            frame_size: AtomicUsize::new(check_frame_duration(config.frame_duration_ms)?),
+            echo_reference,
+            paused: AtomicBool::new(paused),
+            jitter_events: AtomicU64::new(0),
+            frames_encoded: AtomicU64::new(0),
+            vad_gated_samples: AtomicU64::new(0),
+            frames_dtx: AtomicU64::new(0),
+            jitter_tx: Mutex::new(None),
+            speaking_tx: Mutex::new(Some(speaking_tx)),
+            clip: Mutex::new(None),
+            recipients: RwLock::default(),
+            recording: RecordingTap::default(),
         });
+        let mut encoder = OpusEncoder::new()?;
+        encoder.set_dtx(config.dtx)?;
         Ok(Self {
-            encoder: Arc::new(Mutex::new(OpusEncoder::new()?)),
-            crypto,
+            encoder: Arc::new(Mutex::new(encoder)),
+            crypto: Arc::new(CryptoSlot::new(crypto)),
             controls,
             packet_tx,
             packet_rx: Arc::new(Mutex::new(Some(packet_rx))),
             seq: Arc::new(std::sync::atomic::AtomicU32::new(0)),
             running: Arc::new(AtomicBool::new(false)),
             run_token: Arc::new(AtomicU64::new(0)),
-            muted: Arc::new(AtomicBool::new(false)),
-            rms_tx,
+            muted: Arc::new(AtomicBool::new(config.muted)),
+            rms_tx: Arc::new(rms_tx),
             rms_rx: Arc::new(Mutex::new(Some(rms_rx))),
+            speaking_rx: Arc::new(Mutex::new(Some(speaking_rx))),
+            device_events: Arc::default(),
         })
     }
 
-    pub fn take_packet_receiver(&self) -> Option<mpsc::UnboundedReceiver<AudioPacket>> {
-        self.packet_rx.lock().unwrap().take()
+    /// Apply every setting in `config` through the runtime setters.
+    pub fn apply_config(&self, config: &AudioCaptureConfig) {
+        self.set_input_gain(config.input_gain);
+        self.set_voice_mode(config.voice_mode);
+        self.set_vad_threshold(config.vad_threshold);
+        self.set_noise_gate_threshold(config.noise_gate_threshold);
+        self.set_noise_suppression(config.noise_suppression);
+        self.set_noise_suppression_mode(config.noise_suppression_mode);
+        self.set_aec_enabled(config.aec_enabled);
+        self.set_agc_enabled(config.agc_enabled);
+        self.set_agc_target_rms(config.agc_target_rms);
+        self.set_agc_gain_range(config.agc_min_gain, config.agc_max_gain);
+        self.set_noise_gate_enabled(config.noise_gate_enabled);
+        self.set_noise_gate_hold_ms(config.noise_gate_hold_ms);
+        self.set_noise_gate_high_band_bias(config.noise_gate_high_band_bias);
+        self.set_stage_order(config.stage_order);
+        self.set_stereo(config.stereo);
+        if let Err(e) = self.set_dtx(config.dtx) {
+            tracing::warn!("Failed to apply DTX setting: {}", e);
+        }
+        if let Err(e) = self.set_frame_duration_ms(config.frame_duration_ms) {
+            tracing::warn!("Failed to apply frame duration: {}", e);
+        }
+        self.set_muted(config.muted);
     }
 
-    pub fn take_rms_receiver(&self) -> Option<mpsc::UnboundedReceiver<f32>> {
-        self.rms_rx.lock().unwrap().take()
+    /// Attach the session key, e.g. to a pre-warmed capture.
+    pub fn set_crypto(&self, crypto: Arc<CryptoContext>) {
+        self.crypto.set(crypto);
     }
 
-    pub fn set_muted(&self, muted: bool) {
-        self.muted.store(muted, Ordering::SeqCst);
-        tracing::info!("Audio capture muted: {}", muted);
+    /// Also send every packet to `peer_id`, a group call peer. Packets are
+    /// encrypted once, with the capture's key. Returns the receiver for that
+    /// peer's copies; adding the same peer again replaces it.
+    pub fn add_recipient(&self, peer_id: &str) -> mpsc::UnboundedReceiver<AudioPacket> {
+        let (packet_tx, packet_rx) = mpsc::unbounded_channel();
+        if let Ok(mut recipients) = self.controls.recipients.write() {
+            recipients.insert(peer_id.to_string(), packet_tx);
+        }
+        packet_rx
     }
 
-    pub fn is_muted(&self) -> bool {
-        self.muted.load(Ordering::SeqCst)
+    /// Stop sending to `peer_id`; its receiver then ends
+    pub fn remove_recipient(&self, peer_id: &str) {
+        if let Ok(mut recipients) = self.controls.recipients.write() {
+            recipients.remove(peer_id);
+        }
     }
 
-    pub fn set_input_gain(&self, gain: f32) {
-        let clamped = gain.clamp(0.0, 6.0);
-        self.controls
-            .input_gain_bits
-            .store(clamped.to_bits(), Ordering::SeqCst);
+    /// Keep the input stream open but stop encoding and sending frames.
+    pub fn pause(&self) {
+        self.controls.paused.store(true, Ordering::SeqCst);
     }
 
-    pub fn input_gain(&self) -> f32 {
-        f32::from_bits(self.controls.input_gain_bits.load(Ordering::SeqCst))
+    pub fn resume(&self) {
+        self.controls.paused.store(false, Ordering::SeqCst);
     }
 
-    pub fn set_voice_mode(&self, mode: VoiceMode) {
-        self.controls
-            .voice_mode
-            .store(mode.to_u8(), Ordering::SeqCst);
+    pub fn is_paused(&self) -> bool {
+        self.controls.paused.load(Ordering::SeqCst)
     }
 
-    pub fn voice_mode(&self) -> VoiceMode {
-        match self.controls.voice_mode.load(Ordering::SeqCst) {
-            VOICE_MODE_MUTE => VoiceMode::Mute,
-            VOICE_MODE_PTT => VoiceMode::PushToTalk,
-            _ => VoiceMode::VoiceActivity,
-        }
+    pub fn take_packet_receiver(&self) -> Option<mpsc::UnboundedReceiver<AudioPacket>> {
+        self.packet_rx.lock().unwrap().take()
     }
 
-    pub fn set_ptt_active(&self, active: bool) {
-        self.controls.ptt_active.store(active, Ordering::SeqCst);
+    pub fn take_rms_receiver(&self) -> Option<watch::Receiver<f32>> {
+        self.rms_rx.lock().unwrap().take()
+    }
+
+    /// Play `clip` into the call, mixed over the microphone or, with
+    /// `replace_mic`, instead of it. Replaces a clip already playing.
+    pub fn play_clip(&self, clip: SoundClip, replace_mic: bool) {
+        if let Ok(mut slot) = self.controls.clip.lock() {
+            *slot = Some(ClipInjection::new(clip, replace_mic));
+        }
+    }
+
+    /// Cut the clip short. The fade only covers a clip that ends by itself.
+    pub fn stop_clip(&self) {
+        if let Ok(mut slot) = self.controls.clip.lock() {
+            *slot = None;
+        }
+    }
+
+    pub fn is_playing_clip(&self) -> bool {
+        self.controls
+            .clip
+            .lock()
+            .map(|clip| clip.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Debounced speaking indicator, unlike the raw level on the RMS
+    /// receiver. Only transitions are sent.
+    pub fn take_speaking_receiver(&self) -> Option<mpsc::UnboundedReceiver<SpeakingState>> {
+        self.speaking_rx.lock().unwrap().take()
+    }
+
+    /// Report each late capture callback on `tx` (see [`CaptureJitter`]).
+    /// They are counted either way.
+    pub fn set_jitter_sender(&self, tx: mpsc::UnboundedSender<CaptureJitter>) {
+        if let Ok(mut slot) = self.controls.jitter_tx.lock() {
+            *slot = Some(tx);
+        }
+    }
+
+    /// Late capture callbacks so far
+    pub fn jitter_events(&self) -> u64 {
+        self.controls.jitter_events.load(Ordering::Relaxed)
+    }
+
+    /// Report the microphone disappearing mid-stream on `tx`
+    pub fn set_device_event_sender(&self, tx: mpsc::UnboundedSender<AudioDeviceEvent>) {
+        self.device_events.set_sender(tx);
+    }
+
+    /// The device was lost and the stream stopped, with no `start` since
+    pub fn device_lost(&self) -> bool {
+        self.device_events.lost.load(Ordering::SeqCst)
+    }
+
+    /// Retune the encoder mid-call, e.g. from a [`BitrateController`]
+    pub fn set_bitrate(&self, bits_per_sec: u32) -> Result<()> {
+        self.encoder
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?
+            .set_bitrate(bits_per_sec)
+    }
+
+    /// Stop sending while the input is silent. The peer plays comfort
+    /// noise through the gap; see [`OpusEncoder::set_dtx`].
+    pub fn set_dtx(&self, enabled: bool) -> Result<()> {
+        self.encoder
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?
+            .set_dtx(enabled)
+    }
+
+    pub fn dtx(&self) -> bool {
+        self.encoder
+            .lock()
+            .map(|encoder| encoder.dtx())
+            .unwrap_or(false)
+    }
+
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            frames_encoded: self.controls.frames_encoded.load(Ordering::Relaxed),
+            frames_vad_dropped: self.controls.vad_gated_samples.load(Ordering::Relaxed)
+                / self.controls.frame_size.load(Ordering::Relaxed) as u64,
+            frames_dtx: self.controls.frames_dtx.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::SeqCst);
+        tracing::info!("Audio capture muted: {}", muted);
+    }
+
+    /// Send the microphone, as it is about to be encoded, to a recording;
+    /// `None` stops
+    pub(crate) fn set_recording(&self, sink: Option<RecordingSink>) {
+        self.controls.recording.set(sink);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::SeqCst)
+    }
+
+    pub fn set_input_gain(&self, gain: f32) {
+        let clamped = gain.clamp(0.0, MAX_INPUT_GAIN);
+        self.controls
+            .input_gain_bits
+            .store(clamped.to_bits(), Ordering::SeqCst);
+    }
+
+    pub fn input_gain(&self) -> f32 {
+        f32::from_bits(self.controls.input_gain_bits.load(Ordering::SeqCst))
+    }
+
+    pub fn set_voice_mode(&self, mode: VoiceMode) {
+        self.controls
+            .voice_mode
+            .store(mode.to_u8(), Ordering::SeqCst);
+    }
+
+    pub fn voice_mode(&self) -> VoiceMode {
+        match self.controls.voice_mode.load(Ordering::SeqCst) {
+            VOICE_MODE_MUTE => VoiceMode::Mute,
+            VOICE_MODE_PTT => VoiceMode::PushToTalk,
+            _ => VoiceMode::VoiceActivity,
+        }
+    }
+
+    pub fn set_ptt_active(&self, active: bool) {
+        self.controls.ptt_active.store(active, Ordering::SeqCst);
     }
 
     pub fn set_vad_threshold(&self, threshold: f32) {
-        let clamped = threshold.clamp(0.0, 0.3);
+        let clamped = threshold.clamp(0.0, MAX_VAD_THRESHOLD);
         self.controls
             .vad_threshold_bits
             .store(clamped.to_bits(), Ordering::SeqCst);
@@ -252,7 +1365,7 @@ impl AudioCapture {
     }
 
     pub fn set_noise_gate_threshold(&self, threshold: f32) {
-        let clamped = threshold.clamp(0.0, 0.2);
+        let clamped = threshold.clamp(0.0, MAX_NOISE_GATE_THRESHOLD);
         self.controls
             .noise_gate_threshold_bits
             .store(clamped.to_bits(), Ordering::SeqCst);
@@ -276,6 +1389,20 @@ impl AudioCapture {
         self.controls.noise_suppression.load(Ordering::SeqCst)
     }
 
+    /// Pick the suppressor used while noise suppression is on. Switching to
+    /// heavy starts it from scratch; it needs ~200 ms to learn the noise.
+    pub fn set_noise_suppression_mode(&self, mode: NoiseSuppressionMode) {
+        self.controls
+            .noise_suppression_mode
+            .store(mode.to_u8(), Ordering::SeqCst);
+    }
+
+    pub fn noise_suppression_mode(&self) -> NoiseSuppressionMode {
+        NoiseSuppressionMode::from_u8(self.controls.noise_suppression_mode.load(Ordering::SeqCst))
+    }
+
+    /// Switch between the NLMS echo canceller and passthrough. The learned
+    /// echo path is kept while off.
     pub fn set_aec_enabled(&self, enabled: bool) {
         self.controls.aec_enabled.store(enabled, Ordering::SeqCst);
     }
@@ -292,6 +1419,38 @@ impl AudioCapture {
         self.controls.agc_enabled.load(Ordering::SeqCst)
     }
 
+    /// RMS level AGC steers the signal towards
+    pub fn set_agc_target_rms(&self, target: f32) {
+        let clamped = target.clamp(0.0, MAX_AGC_TARGET_RMS);
+        self.controls
+            .agc_target_rms_bits
+            .store(clamped.to_bits(), Ordering::SeqCst);
+    }
+
+    pub fn agc_target_rms(&self) -> f32 {
+        f32::from_bits(self.controls.agc_target_rms_bits.load(Ordering::SeqCst))
+    }
+
+    /// Bounds on the gain AGC may apply. A `min` above `max` is lowered to it.
+    pub fn set_agc_gain_range(&self, min: f32, max: f32) {
+        let max = max.clamp(0.0, MAX_AGC_GAIN);
+        let min = min.clamp(0.0, max);
+        self.controls
+            .agc_min_gain_bits
+            .store(min.to_bits(), Ordering::SeqCst);
+        self.controls
+            .agc_max_gain_bits
+            .store(max.to_bits(), Ordering::SeqCst);
+    }
+
+    /// `(min, max)` gain AGC may apply
+    pub fn agc_gain_range(&self) -> (f32, f32) {
+        (
+            f32::from_bits(self.controls.agc_min_gain_bits.load(Ordering::SeqCst)),
+            f32::from_bits(self.controls.agc_max_gain_bits.load(Ordering::SeqCst)),
+        )
+    }
+
     pub fn set_noise_gate_enabled(&self, enabled: bool) {
         self.controls
             .noise_gate_enabled
@@ -302,16 +1461,86 @@ impl AudioCapture {
         self.controls.noise_gate_enabled.load(Ordering::SeqCst)
     }
 
+    /// Keep the gate open this long after the level drops under the
+    /// threshold, so word endings and short pauses are not cut
+    pub fn set_noise_gate_hold_ms(&self, ms: u32) {
+        self.controls
+            .noise_gate_hold_ms
+            .store(ms.min(MAX_NOISE_GATE_HOLD_MS), Ordering::SeqCst);
+    }
+
+    pub fn noise_gate_hold_ms(&self) -> u32 {
+        self.controls.noise_gate_hold_ms.load(Ordering::SeqCst)
+    }
+
+    /// Weight of energy above the crossover in opening the gate's high
+    /// band. Above 1, a quiet "s" or "f" opens it while the level as a
+    /// whole is still under the threshold; 0 makes both bands follow the
+    /// full-band level.
+    pub fn set_noise_gate_high_band_bias(&self, bias: f32) {
+        let clamped = bias.clamp(0.0, MAX_NOISE_GATE_HIGH_BAND_BIAS);
+        self.controls
+            .noise_gate_high_band_bias_bits
+            .store(clamped.to_bits(), Ordering::SeqCst);
+    }
+
+    pub fn noise_gate_high_band_bias(&self) -> f32 {
+        f32::from_bits(
+            self.controls
+                .noise_gate_high_band_bias_bits
+                .load(Ordering::SeqCst),
+        )
+    }
+
+    pub fn set_stage_order(&self, order: CaptureStageOrder) {
+        self.controls
+            .stage_order
+            .store(order.to_u8(), Ordering::SeqCst);
+    }
+
+    pub fn stage_order(&self) -> CaptureStageOrder {
+        CaptureStageOrder::from_u8(self.controls.stage_order.load(Ordering::SeqCst))
+    }
+
+    /// Encode the first two input channels as left/right instead of a mono
+    /// downmix. Takes effect on the next callback; the device itself is
+    /// opened in stereo from the next `start`, until then a mono microphone
+    /// is sent on both sides.
+    pub fn set_stereo(&self, stereo: bool) {
+        self.controls.stereo.store(stereo, Ordering::SeqCst);
+    }
+
+    pub fn stereo(&self) -> bool {
+        self.controls.stereo.load(Ordering::SeqCst)
+    }
+
+    /// Audio per encoded packet: one of `OPUS_FRAME_DURATIONS_MS`. Shorter
+    /// frames cut latency, longer ones save the per-packet overhead. Takes
+    /// effect on the next frame; the peer decodes any size. Anything else
+    /// is an error and leaves the size unchanged.
+    pub fn set_frame_duration_ms(&self, ms: f32) -> Result<()> {
+        let size = check_frame_duration(ms)?;
+        self.controls.frame_size.store(size, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn frame_duration_ms(&self) -> f32 {
+        self.controls.frame_size.load(Ordering::SeqCst) as f32 * 1000.0 / SAMPLE_RATE as f32
+    }
+
     /// Start capture with the default input device
     pub fn start(&self) -> Result<()> {
-        self.start_with_device(None)
+        self.start_with_device(None).map(|_| ())
     }
 
-    /// Start capture with a specific device by name, or default if None
-    pub fn start_with_device(&self, device_name: Option<&str>) -> Result<()> {
+    /// Start capture with a specific device by name, or default if None.
+    /// When the named device is missing or fails to open, the default is
+    /// tried once instead; returns whether that fallback was used.
+    pub fn start_with_device(&self, device_name: Option<&str>) -> Result<bool> {
         if self.running.swap(true, Ordering::SeqCst) {
-            return Ok(());
+            return Ok(false);
         }
+        self.device_events.lost.store(false, Ordering::SeqCst);
 
         let encoder = self.encoder.clone();
         let crypto = self.crypto.clone();
@@ -322,371 +1551,433 @@ impl AudioCapture {
         let muted = self.muted.clone();
         let controls = self.controls.clone();
         let rms_tx = self.rms_tx.clone();
+        let device_events = self.device_events.clone();
         let device_name_owned = device_name.map(|s| s.to_string());
         let current_token = run_token.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
 
+        let (startup_tx, startup_rx) = std::sync::mpsc::sync_channel(1);
+
         thread::spawn(move || {
             let host = cpal::default_host();
-            let device = if let Some(ref name) = device_name_owned {
-                match host.input_devices() {
-                    Ok(devices) => {
-                        if let Some(device) = devices
-                            .into_iter()
-                            .find(|d| d.name().map(|n| n == *name).unwrap_or(false))
-                        {
-                            device
-                        } else {
-                            tracing::warn!("Input device '{}' not found, using default", name);
-                            match host.default_input_device() {
-                                Some(d) => d,
-                                None => {
-                                    tracing::error!("No input device available");
-                                    if run_token.load(Ordering::SeqCst) == current_token {
-                                        running.store(false, Ordering::SeqCst);
-                                    }
-                                    return;
+            let candidates =
+                device_candidates(&host, AudioDirection::Input, device_name_owned.as_deref());
+            let mut first_error: Option<AudioDeviceError> = None;
+            let mut opened = None;
+
+            for (attempt, device) in candidates.iter().enumerate() {
+                let device_label = device.name().unwrap_or_else(|_| "unknown".to_string());
+                if attempt > 0 {
+                    tracing::warn!("Falling back to default input device '{}'", device_label);
+                }
+
+                let config = match choose_supported_format(
+                    device,
+                    AudioDirection::Input,
+                    capture_layout(&controls) as u16,
+                ) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        tracing::error!("Failed to pick input config: {}", e);
+                        first_error.get_or_insert(AudioDeviceError::from_details(
+                            AudioDirection::Input,
+                            &device_label,
+                            e.to_string(),
+                        ));
+                        continue;
+                    }
+                };
+
+                let sample_format = config.sample_format();
+                let stream_config: StreamConfig = config.into();
+                let input_channels = stream_config.channels as usize;
+                let input_rate = stream_config.sample_rate.0;
+
+                tracing::info!(
+                    "Using input device '{}' ({:?}, {}ch @ {}Hz)",
+                    device_label,
+                    sample_format,
+                    input_channels,
+                    input_rate
+                );
+
+                let pipeline_state = Arc::new(Mutex::new(CapturePipelineState::new()));
+
+                let stream_result = match sample_format {
+                    SampleFormat::F32 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[f32], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
                                 }
-                            }
-                        }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
                     }
-                    Err(e) => {
-                        tracing::error!("Failed to enumerate input devices: {}", e);
-                        match host.default_input_device() {
-                            Some(d) => d,
-                            None => {
-                                tracing::error!("No input device available");
-                                if run_token.load(Ordering::SeqCst) == current_token {
-                                    running.store(false, Ordering::SeqCst);
+                    SampleFormat::F64 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[f64], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_f64_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
                                 }
-                                return;
-                            }
-                        }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
                     }
-                }
-            } else {
-                match host.default_input_device() {
-                    Some(d) => d,
-                    None => {
-                        tracing::error!("No input device available");
-                        if run_token.load(Ordering::SeqCst) == current_token {
-                            running.store(false, Ordering::SeqCst);
-                        }
-                        return;
+                    SampleFormat::I16 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[i16], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_i16_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
+                                }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
                     }
-                }
-            };
+                    SampleFormat::I8 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[i8], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_i8_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
+                                }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::I32 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[i32], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_i32_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
+                                }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::U16 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[u16], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_u16_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
+                                }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::U8 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[u8], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_u8_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
+                                }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::U32 => {
+                        let encoder = encoder.clone();
+                        let crypto = crypto.clone();
+                        let packet_tx = packet_tx.clone();
+                        let seq = seq.clone();
+                        let muted = muted.clone();
+                        let rms_tx = rms_tx.clone();
+                        let pipeline_state = pipeline_state.clone();
+                        let controls = controls.clone();
+                        device.build_input_stream(
+                            &stream_config,
+                            move |data: &[u32], _info| {
+                                let layout = capture_layout(&controls);
+                                let samples = downmix_u32_to_f32(data, input_channels, layout);
+                                if let Ok(mut state) = pipeline_state.lock() {
+                                    process_capture_samples(
+                                        &samples,
+                                        layout,
+                                        input_rate,
+                                        muted.load(Ordering::Relaxed),
+                                        &rms_tx,
+                                        &encoder,
+                                        &crypto,
+                                        &seq,
+                                        &packet_tx,
+                                        &controls,
+                                        &mut state,
+                                    );
+                                }
+                            },
+                            stream_error_callback(
+                                AudioDirection::Input,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    _ => {
+                        tracing::error!("Unsupported input sample format: {:?}", sample_format);
+                        Err(cpal::BuildStreamError::StreamConfigNotSupported)
+                    }
+                };
 
-            let config = match pick_input_config(&device) {
-                Ok(c) => c,
-                Err(e) => {
-                    tracing::error!("Failed to pick input config: {}", e);
-                    if run_token.load(Ordering::SeqCst) == current_token {
-                        running.store(false, Ordering::SeqCst);
+                let stream = match stream_result {
+                    Ok(s) => s,
+                    Err(e) => {
+                        tracing::error!("Failed to build input stream: {}", e);
+                        first_error.get_or_insert(classify_build_error(
+                            AudioDirection::Input,
+                            &device_label,
+                            &e,
+                        ));
+                        continue;
                     }
-                    return;
+                };
+
+                if let Err(e) = stream.play() {
+                    tracing::error!("Failed to play input stream: {}", e);
+                    first_error.get_or_insert(classify_play_error(
+                        AudioDirection::Input,
+                        &device_label,
+                        &e,
+                    ));
+                    continue;
                 }
-            };
 
-            let sample_format = config.sample_format();
-            let stream_config: StreamConfig = config.into();
-            let input_channels = stream_config.channels as usize;
-            let input_rate = stream_config.sample_rate.0;
-
-            tracing::info!(
-                "Using input device '{}' ({:?}, {}ch @ {}Hz)",
-                device.name().unwrap_or_else(|_| "unknown".to_string()),
-                sample_format,
-                input_channels,
-                input_rate
-            );
+                let fell_back = device_name_owned
+                    .as_deref()
+                    .is_some_and(|requested| requested != device_label);
+                opened = Some((stream, fell_back));
+                break;
+            }
 
-            let pipeline_state = Arc::new(Mutex::new(CapturePipelineState::new()));
-
-            let stream_result = match sample_format {
-                SampleFormat::F32 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[f32], _info| {
-                            let mono = downmix_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
+            let _stream = match opened {
+                Some((stream, fell_back)) => {
+                    let _ = startup_tx.send(Ok(fell_back));
+                    stream
                 }
-                SampleFormat::F64 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[f64], _info| {
-                            let mono = downmix_f64_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::I16 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[i16], _info| {
-                            let mono = downmix_i16_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::I8 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[i8], _info| {
-                            let mono = downmix_i8_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::I32 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[i32], _info| {
-                            let mono = downmix_i32_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::U16 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[u16], _info| {
-                            let mono = downmix_u16_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::U8 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[u8], _info| {
-                            let mono = downmix_u8_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::U32 => {
-                    let encoder = encoder.clone();
-                    let crypto = crypto.clone();
-                    let packet_tx = packet_tx.clone();
-                    let seq = seq.clone();
-                    let muted = muted.clone();
-                    let rms_tx = rms_tx.clone();
-                    let pipeline_state = pipeline_state.clone();
-                    let controls = controls.clone();
-                    device.build_input_stream(
-                        &stream_config,
-                        move |data: &[u32], _info| {
-                            let mono = downmix_u32_to_f32(data, input_channels);
-                            if let Ok(mut state) = pipeline_state.lock() {
-                                process_mono_samples(
-                                    &mono,
-                                    input_rate,
-                                    muted.load(Ordering::Relaxed),
-                                    &rms_tx,
-                                    &encoder,
-                                    &crypto,
-                                    &seq,
-                                    &packet_tx,
-                                    &controls,
-                                    &mut state,
-                                );
-                            }
-                        },
-                        |err| tracing::error!("Capture stream error: {}", err),
-                        None,
-                    )
-                }
-                _ => {
-                    tracing::error!("Unsupported input sample format: {:?}", sample_format);
-                    if run_token.load(Ordering::SeqCst) == current_token {
-                        running.store(false, Ordering::SeqCst);
-                    }
-                    return;
-                }
-            };
-
-            let stream = match stream_result {
-                Ok(s) => s,
-                Err(e) => {
-                    tracing::error!("Failed to build input stream: {}", e);
+                None => {
+                    let error = first_error.unwrap_or_else(|| AudioDeviceError::NotFound {
+                        direction: AudioDirection::Input,
+                        device: device_name_owned.unwrap_or_else(|| "default".to_string()),
+                    });
+                    tracing::error!("{}", error);
                     if run_token.load(Ordering::SeqCst) == current_token {
                         running.store(false, Ordering::SeqCst);
                     }
+                    let _ = startup_tx.send(Err(error));
                     return;
                 }
             };
 
-            if let Err(e) = stream.play() {
-                tracing::error!("Failed to play input stream: {}", e);
-                if run_token.load(Ordering::SeqCst) == current_token {
-                    running.store(false, Ordering::SeqCst);
-                }
-                return;
-            }
-
             while running.load(Ordering::SeqCst)
                 && run_token.load(Ordering::SeqCst) == current_token
             {
@@ -698,7 +1989,7 @@ impl AudioCapture {
             }
         });
 
-        Ok(())
+        wait_for_stream_start(&startup_rx, AudioDirection::Input)
     }
 
     pub fn stop(&self) {
@@ -711,161 +2002,577 @@ impl AudioCapture {
     }
 }
 
-fn pick_input_config(device: &cpal::Device) -> Result<SupportedStreamConfig> {
-    let mut best_48k: Option<SupportedStreamConfig> = None;
+/// The capture DSP chain without an input device. Samples fed to
+/// [`DspChain::process`] go through the same function as a live capture
+/// callback: resampling, echo cancellation, noise suppression, gain and
+/// gate, VAD, then encoding and encryption. For tests and tools that run
+/// recorded audio and check what would have been sent.
+pub struct DspChain {
+    capture: AudioCapture,
+    state: CapturePipelineState,
+    packet_rx: mpsc::UnboundedReceiver<AudioPacket>,
+}
 
-    if let Ok(configs) = device.supported_input_configs() {
-        for cfg in configs {
-            if cfg.min_sample_rate().0 <= SAMPLE_RATE && cfg.max_sample_rate().0 >= SAMPLE_RATE {
-                let candidate = cfg.with_sample_rate(cpal::SampleRate(SAMPLE_RATE));
-                let better = match &best_48k {
-                    None => true,
-                    Some(best) => {
-                        if candidate.channels() == CHANNELS {
-                            best.channels() != CHANNELS
-                        } else {
-                            false
-                        }
-                    }
-                };
-                if better {
-                    best_48k = Some(candidate);
-                }
-            }
-        }
+impl DspChain {
+    pub fn new(crypto: Arc<CryptoContext>, config: AudioCaptureConfig) -> Result<Self> {
+        let capture = AudioCapture::new_with_config(crypto, EchoReference::default(), config)?;
+        let packet_rx = capture
+            .take_packet_receiver()
+            .ok_or_else(|| anyhow::anyhow!("Capture packet receiver already taken"))?;
+        Ok(Self {
+            capture,
+            state: CapturePipelineState::new(),
+            packet_rx,
+        })
     }
 
-    if let Some(cfg) = best_48k {
-        return Ok(cfg);
+    /// The capture the chain runs as. Its setters, stats and meter and
+    /// speaking receivers behave as they do for a live call.
+    pub fn capture(&self) -> &AudioCapture {
+        &self.capture
     }
 
-    device
-        .default_input_config()
-        .map_err(|e| anyhow::anyhow!("No usable input config: {}", e))
-}
-
-fn downmix_f32(input: &[f32], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input.to_vec();
+    /// Run `input`, recorded at `rate` Hz, through the chain and return the
+    /// packets it completed. `input` is mono, or interleaved left/right
+    /// while the capture is stereo. Less than a frame left over waits for
+    /// the next call, as it would for the next callback.
+    pub fn process(&mut self, input: &[f32], rate: u32) -> Vec<AudioPacket> {
+        let capture = &self.capture;
+        process_capture_samples(
+            input,
+            capture_layout(&capture.controls),
+            rate,
+            capture.is_muted(),
+            &capture.rms_tx,
+            &capture.encoder,
+            &capture.crypto,
+            &capture.seq,
+            &capture.packet_tx,
+            &capture.controls,
+            &mut self.state,
+        );
+        std::iter::from_fn(|| self.packet_rx.try_recv().ok()).collect()
     }
+}
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-        .collect()
+/// Which side of the audio path a device error belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioDirection {
+    Input,
+    Output,
 }
 
-fn downmix_f64_to_f32(input: &[f64], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input.iter().map(|&s| s as f32).collect();
+impl AudioDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AudioDirection::Input => "input",
+            AudioDirection::Output => "output",
+        }
     }
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| (frame.iter().sum::<f64>() / channels as f64) as f32)
-        .collect()
+    fn device_noun(self) -> &'static str {
+        match self {
+            AudioDirection::Input => "Microphone",
+            AudioDirection::Output => "Audio output device",
+        }
+    }
 }
 
-fn downmix_i8_to_f32(input: &[i8], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input.iter().map(|&s| s as f32 / 128.0).collect();
-    }
+/// Something that happened to an open audio stream's device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioDeviceEvent {
+    /// The device went away mid-stream, e.g. a USB headset was unplugged.
+    /// The stream has stopped; start it again to open another device.
+    Disconnected { is_input: bool },
+}
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| {
-            let sum: f32 = frame.iter().map(|&s| s as f32 / 128.0).sum();
-            sum / channels as f32
-        })
-        .collect()
+/// Where a stream reports device events, and whether its device was lost.
+/// Shared with the stream's error callback.
+#[derive(Debug, Default)]
+struct DeviceEvents {
+    tx: Mutex<Option<mpsc::UnboundedSender<AudioDeviceEvent>>>,
+    lost: AtomicBool,
 }
 
-fn downmix_i16_to_f32(input: &[i16], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input.iter().map(|&s| s as f32 / 32768.0).collect();
+impl DeviceEvents {
+    fn set_sender(&self, tx: mpsc::UnboundedSender<AudioDeviceEvent>) {
+        if let Ok(mut slot) = self.tx.lock() {
+            *slot = Some(tx);
+        }
     }
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| {
-            let sum: f32 = frame.iter().map(|&s| s as f32 / 32768.0).sum();
-            sum / channels as f32
-        })
-        .collect()
+    fn send(&self, event: AudioDeviceEvent) {
+        if let Ok(tx) = self.tx.lock() {
+            if let Some(tx) = tx.as_ref() {
+                let _ = tx.send(event);
+            }
+        }
+    }
 }
 
-fn downmix_i32_to_f32(input: &[i32], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input.iter().map(|&s| s as f32 / 2147483648.0).collect();
+/// Error callback for the stream opened under run token `token`. Losing the
+/// device stops that run and is reported once; other errors are logged.
+fn stream_error_callback(
+    direction: AudioDirection,
+    device_events: Arc<DeviceEvents>,
+    running: Arc<AtomicBool>,
+    run_token: Arc<AtomicU64>,
+    token: u64,
+) -> impl FnMut(cpal::StreamError) + Send + 'static {
+    move |err| {
+        tracing::error!("{} stream error: {}", direction.as_str(), err);
+        if !matches!(err, cpal::StreamError::DeviceNotAvailable)
+            || run_token.load(Ordering::SeqCst) != token
+        {
+            return;
+        }
+        // Stopping the run ends its thread, which drops the dead stream
+        if running.swap(false, Ordering::SeqCst) {
+            device_events.lost.store(true, Ordering::SeqCst);
+            device_events.send(AudioDeviceEvent::Disconnected {
+                is_input: direction == AudioDirection::Input,
+            });
+        }
     }
+}
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| {
-            let sum: f32 = frame.iter().map(|&s| s as f32 / 2147483648.0).sum();
-            sum / channels as f32
-        })
-        .collect()
+/// Why an audio stream could not be opened. `Busy` covers devices held in
+/// exclusive mode by another application; `NotFound` covers unplugged or
+/// unknown devices.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AudioDeviceError {
+    #[error("{} is in use by another application", .direction.device_noun())]
+    Busy {
+        direction: AudioDirection,
+        device: String,
+    },
+    #[error("{} '{device}' is not available", .direction.device_noun())]
+    NotFound {
+        direction: AudioDirection,
+        device: String,
+    },
+    #[error("Failed to open {} device '{device}': {details}", .direction.as_str())]
+    Failed {
+        direction: AudioDirection,
+        device: String,
+        details: String,
+    },
 }
 
-fn downmix_u8_to_f32(input: &[u8], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input
-            .iter()
-            .map(|&s| (s as f32 / 255.0) * 2.0 - 1.0)
-            .collect();
+impl AudioDeviceError {
+    pub(crate) fn from_details(direction: AudioDirection, device: &str, details: String) -> Self {
+        let device = device.to_string();
+        if is_device_busy_message(&details) {
+            AudioDeviceError::Busy { direction, device }
+        } else {
+            AudioDeviceError::Failed {
+                direction,
+                device,
+                details,
+            }
+        }
     }
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| {
-            let sum: f32 = frame.iter().map(|&s| (s as f32 / 255.0) * 2.0 - 1.0).sum();
-            sum / channels as f32
-        })
-        .collect()
-}
-
-fn downmix_u16_to_f32(input: &[u16], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input
-            .iter()
-            .map(|&s| (s as f32 / 65535.0) * 2.0 - 1.0)
-            .collect();
+    pub fn direction(&self) -> AudioDirection {
+        match self {
+            AudioDeviceError::Busy { direction, .. }
+            | AudioDeviceError::NotFound { direction, .. }
+            | AudioDeviceError::Failed { direction, .. } => *direction,
+        }
     }
+}
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| {
-            let sum: f32 = frame
-                .iter()
-                .map(|&s| (s as f32 / 65535.0) * 2.0 - 1.0)
-                .sum();
-            sum / channels as f32
-        })
-        .collect()
+/// Backends only report "device busy" as free text (WASAPI
+/// `AUDCLNT_E_DEVICE_IN_USE`, ALSA `EBUSY`, CoreAudio hog mode).
+fn is_device_busy_message(details: &str) -> bool {
+    let lowered = details.to_ascii_lowercase();
+    [
+        "in use",
+        "busy",
+        "exclusive",
+        "0x8889000a",
+        "device_in_use",
+        "hog mode",
+    ]
+    .iter()
+    .any(|needle| lowered.contains(needle))
 }
 
-fn downmix_u32_to_f32(input: &[u32], channels: usize) -> Vec<f32> {
-    if channels <= 1 {
-        return input
-            .iter()
-            .map(|&s| (s as f64 / 4294967295.0 * 2.0 - 1.0) as f32)
-            .collect();
+pub(crate) fn classify_build_error(
+    direction: AudioDirection,
+    device: &str,
+    error: &cpal::BuildStreamError,
+) -> AudioDeviceError {
+    match error {
+        cpal::BuildStreamError::DeviceNotAvailable => AudioDeviceError::NotFound {
+            direction,
+            device: device.to_string(),
+        },
+        cpal::BuildStreamError::BackendSpecific { err } => {
+            AudioDeviceError::from_details(direction, device, err.description.clone())
+        }
+        other => AudioDeviceError::from_details(direction, device, other.to_string()),
     }
+}
 
-    input
-        .chunks_exact(channels)
-        .map(|frame| {
-            let sum: f64 = frame
-                .iter()
-                .map(|&s| s as f64 / 4294967295.0 * 2.0 - 1.0)
-                .sum();
-            (sum / channels as f64) as f32
-        })
+pub(crate) fn classify_play_error(
+    direction: AudioDirection,
+    device: &str,
+    error: &cpal::PlayStreamError,
+) -> AudioDeviceError {
+    match error {
+        cpal::PlayStreamError::DeviceNotAvailable => AudioDeviceError::NotFound {
+            direction,
+            device: device.to_string(),
+        },
+        cpal::PlayStreamError::BackendSpecific { err } => {
+            AudioDeviceError::from_details(direction, device, err.description.clone())
+        }
+    }
+}
+
+/// Devices to try, in order: the requested device (when it exists), then
+/// the host default. A busy or broken device falls through to the next one.
+pub(crate) fn device_candidates(
+    host: &cpal::Host,
+    direction: AudioDirection,
+    device_name: Option<&str>,
+) -> Vec<cpal::Device> {
+    let mut candidates = Vec::new();
+
+    if let Some(name) = device_name {
+        let devices = match direction {
+            AudioDirection::Input => host.input_devices().map(|d| d.collect::<Vec<_>>()),
+            AudioDirection::Output => host.output_devices().map(|d| d.collect::<Vec<_>>()),
+        };
+        match devices {
+            Ok(devices) => {
+                match devices
+                    .into_iter()
+                    .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+                {
+                    Some(device) => candidates.push(device),
+                    None => tracing::warn!(
+                        "{} device '{}' not found, using default",
+                        direction.as_str(),
+                        name
+                    ),
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to enumerate {} devices: {}", direction.as_str(), e)
+            }
+        }
+    }
+
+    let default = match direction {
+        AudioDirection::Input => host.default_input_device(),
+        AudioDirection::Output => host.default_output_device(),
+    };
+    if let Some(default) = default {
+        let default_name = default.name().ok();
+        let already_listed = candidates
+            .first()
+            .is_some_and(|d| d.name().ok().is_some() && d.name().ok() == default_name);
+        if !already_listed {
+            candidates.push(default);
+        }
+    } else {
+        tracing::error!("No {} device available", direction.as_str());
+    }
+
+    candidates
+}
+
+/// Polls a new default device has to survive before a stream follows it,
+/// so a burst of changes while devices (re)enumerate causes one switch
+pub(crate) const DEFAULT_DEVICE_SETTLE_POLLS: u32 = 2;
+
+/// Debounced view of one direction's system default device.
+#[derive(Debug, Default)]
+pub(crate) struct DefaultDeviceWatch {
+    /// Default the stream is assumed to be on
+    current: Option<String>,
+    /// Different default seen on the last `seen` polls
+    candidate: Option<String>,
+    seen: u32,
+}
+
+impl DefaultDeviceWatch {
+    pub(crate) fn new(current: Option<String>) -> Self {
+        Self {
+            current,
+            ..Self::default()
+        }
+    }
+
+    /// Feed the default reported by this poll. Returns the new default once
+    /// it has differed from the current one for
+    /// `DEFAULT_DEVICE_SETTLE_POLLS` polls in a row. No default at all (the
+    /// last device was unplugged) is never something to switch to.
+    pub(crate) fn observe(&mut self, default: Option<String>) -> Option<String> {
+        let Some(default) = default.filter(|name| self.current.as_ref() != Some(name)) else {
+            self.candidate = None;
+            self.seen = 0;
+            return None;
+        };
+        if self.candidate.as_ref() == Some(&default) {
+            self.seen += 1;
+        } else {
+            self.candidate = Some(default);
+            self.seen = 1;
+        }
+        if self.seen < DEFAULT_DEVICE_SETTLE_POLLS {
+            return None;
+        }
+        self.seen = 0;
+        self.current = self.candidate.take();
+        self.current.clone()
+    }
+}
+
+/// How long `start_with_device` waits for the audio thread to report
+/// whether the stream opened before returning optimistically.
+const STREAM_START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Block until the audio thread reports whether the stream opened, for up
+/// to `STREAM_START_TIMEOUT`. Async code must not call this on a runtime
+/// worker; the engine's DataChannel tasks open streams via `spawn_blocking`.
+pub(crate) fn wait_for_stream_start<T: Default>(
+    startup_rx: &std::sync::mpsc::Receiver<std::result::Result<T, AudioDeviceError>>,
+    direction: AudioDirection,
+) -> Result<T> {
+    match startup_rx.recv_timeout(STREAM_START_TIMEOUT) {
+        Ok(Ok(started)) => Ok(started),
+        Ok(Err(e)) => Err(e.into()),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            tracing::warn!(
+                "{} stream still opening after {:?}",
+                direction.as_str(),
+                STREAM_START_TIMEOUT
+            );
+            Ok(T::default())
+        }
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err(anyhow::anyhow!(
+            "{} audio thread exited before opening a stream",
+            direction.as_str()
+        )),
+    }
+}
+
+/// Prefer a 48 kHz config with `channels`, the layout being encoded
+/// Sample formats the capture callbacks convert from
+const CAPTURE_SAMPLE_FORMATS: [SampleFormat; 8] = [
+    SampleFormat::F32,
+    SampleFormat::F64,
+    SampleFormat::I16,
+    SampleFormat::I8,
+    SampleFormat::I32,
+    SampleFormat::U16,
+    SampleFormat::U8,
+    SampleFormat::U32,
+];
+
+/// Sample formats the playback and ringtone callbacks convert to
+const PLAYBACK_SAMPLE_FORMATS: [SampleFormat; 6] = [
+    SampleFormat::F32,
+    SampleFormat::F64,
+    SampleFormat::I16,
+    SampleFormat::I32,
+    SampleFormat::U16,
+    SampleFormat::U32,
+];
+
+/// Stream config for `device` in a sample format the stream callbacks can
+/// convert, preferably at 48 kHz with `channels` channels (at least that
+/// many for output). Interfaces whose default is a format we cannot
+/// handle, e.g. 64-bit integer, get one of their other formats instead of
+/// an error; the choice is logged.
+pub(crate) fn choose_supported_format(
+    device: &cpal::Device,
+    direction: AudioDirection,
+    channels: u16,
+) -> Result<SupportedStreamConfig> {
+    let (ranges, default): (Vec<_>, _) = match direction {
+        AudioDirection::Input => (
+            device
+                .supported_input_configs()
+                .map(Iterator::collect)
+                .unwrap_or_default(),
+            device.default_input_config(),
+        ),
+        AudioDirection::Output => (
+            device
+                .supported_output_configs()
+                .map(Iterator::collect)
+                .unwrap_or_default(),
+            device.default_output_config(),
+        ),
+    };
+    let default =
+        default.map_err(|e| anyhow::anyhow!("No usable {} config: {}", direction.as_str(), e));
+    let config = choose_config(&ranges, default.as_ref().ok(), direction, channels);
+
+    match (config, default) {
+        (Some(config), Ok(default)) => {
+            if default.sample_format() != config.sample_format()
+                && !supported_formats(direction).contains(&default.sample_format())
+            {
+                tracing::warn!(
+                    "Default {} format {:?} is not supported, using {:?}",
+                    direction.as_str(),
+                    default.sample_format(),
+                    config.sample_format()
+                );
+            }
+            Ok(config)
+        }
+        (Some(config), Err(_)) => Ok(config),
+        (None, Ok(default)) => Err(anyhow::anyhow!(
+            "No supported {} sample format (device default is {:?})",
+            direction.as_str(),
+            default.sample_format()
+        )),
+        (None, Err(e)) => Err(e),
+    }
+}
+
+fn supported_formats(direction: AudioDirection) -> &'static [SampleFormat] {
+    match direction {
+        AudioDirection::Input => &CAPTURE_SAMPLE_FORMATS,
+        AudioDirection::Output => &PLAYBACK_SAMPLE_FORMATS,
+    }
+}
+
+/// Pick from `ranges` the way [`choose_supported_format`] describes:
+/// 48 kHz in a supported format, the channel count fitting if possible;
+/// then `default` if its format is supported; then any supported range at
+/// its rate nearest 48 kHz.
+fn choose_config(
+    ranges: &[SupportedStreamConfigRange],
+    default: Option<&SupportedStreamConfig>,
+    direction: AudioDirection,
+    channels: u16,
+) -> Option<SupportedStreamConfig> {
+    let formats = supported_formats(direction);
+    let fits = |count: u16| match direction {
+        AudioDirection::Input => count == channels,
+        AudioDirection::Output => count >= channels,
+    };
+    let usable = || {
+        ranges
+            .iter()
+            .filter(|range| formats.contains(&range.sample_format()))
+    };
+
+    let mut best_48k: Option<SupportedStreamConfig> = None;
+    for range in usable() {
+        if range.min_sample_rate().0 <= SAMPLE_RATE && range.max_sample_rate().0 >= SAMPLE_RATE {
+            let candidate = range
+                .clone()
+                .with_sample_rate(cpal::SampleRate(SAMPLE_RATE));
+            let better = match &best_48k {
+                None => true,
+                Some(best) => fits(candidate.channels()) && !fits(best.channels()),
+            };
+            if better {
+                best_48k = Some(candidate);
+            }
+        }
+    }
+    if best_48k.is_some() {
+        return best_48k;
+    }
+
+    if let Some(default) = default.filter(|default| formats.contains(&default.sample_format())) {
+        return Some(default.clone());
+    }
+
+    usable().next().map(|range| {
+        let rate = SAMPLE_RATE.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+        range.clone().with_sample_rate(cpal::SampleRate(rate))
+    })
+}
+
+/// Fold an interleaved capture buffer into the `layout` the pipeline
+/// encodes: mono averages every input channel, stereo keeps the first two.
+/// A mono device feeds both sides of a stereo layout.
+fn downmix<T: Copy>(
+    input: &[T],
+    channels: usize,
+    layout: usize,
+    to_f32: impl Fn(T) -> f32,
+) -> Vec<f32> {
+    let channels = channels.max(1);
+    if channels == layout {
+        return input.iter().map(|&s| to_f32(s)).collect();
+    }
+
+    let frames = input.chunks_exact(channels);
+    if layout == 2 {
+        let mut out = Vec::with_capacity(frames.len() * 2);
+        for frame in frames {
+            let left = to_f32(frame[0]);
+            out.push(left);
+            out.push(frame.get(1).map_or(left, |&s| to_f32(s)));
+        }
+        return out;
+    }
+
+    frames
+        .map(|frame| frame.iter().map(|&s| to_f32(s)).sum::<f32>() / channels as f32)
         .collect()
 }
 
-fn resample_to_48k(input: &[f32], input_rate: u32, pos: &mut f64) -> Vec<f32> {
+fn downmix_f32(input: &[f32], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| s)
+}
+
+fn downmix_f64_to_f32(input: &[f64], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| s as f32)
+}
+
+fn downmix_i8_to_f32(input: &[i8], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| s as f32 / 128.0)
+}
+
+fn downmix_i16_to_f32(input: &[i16], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| s as f32 / 32768.0)
+}
+
+fn downmix_i32_to_f32(input: &[i32], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| s as f32 / 2147483648.0)
+}
+
+fn downmix_u8_to_f32(input: &[u8], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| (s as f32 / 255.0) * 2.0 - 1.0)
+}
+
+fn downmix_u16_to_f32(input: &[u16], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| {
+        (s as f32 / 65535.0) * 2.0 - 1.0
+    })
+}
+
+fn downmix_u32_to_f32(input: &[u32], channels: usize, layout: usize) -> Vec<f32> {
+    downmix(input, channels, layout, |s| {
+        (s as f64 / 4294967295.0 * 2.0 - 1.0) as f32
+    })
+}
+
+/// Nearest-sample resampler to 48 kHz for `channels` interleaved channels,
+/// carrying its read position across callbacks in `phase`.
+///
+/// The position is counted in 1/48000ths of an input frame, so each output
+/// frame advances it by exactly `input_rate` units. Integer steps keep the
+/// output count exact however long the call runs; a float position would
+/// slowly drift and skew capture latency.
+pub(crate) fn resample_to_48k(
+    input: &[f32],
+    channels: usize,
+    input_rate: u32,
+    phase: &mut u64,
+) -> Vec<f32> {
     if input.is_empty() {
         return Vec::new();
     }
@@ -873,110 +2580,412 @@ fn resample_to_48k(input: &[f32], input_rate: u32, pos: &mut f64) -> Vec<f32> {
         return input.to_vec();
     }
 
-    let step = input_rate as f64 / SAMPLE_RATE as f64;
+    let frames = input.len() / channels;
+    let step = input_rate as u64;
+    let end = frames as u64 * SAMPLE_RATE as u64;
     let mut out = Vec::with_capacity(
-        ((input.len() as u64 * SAMPLE_RATE as u64) / input_rate as u64 + 2) as usize,
+        ((frames as u64 * SAMPLE_RATE as u64) / input_rate as u64 + 2) as usize * channels,
     );
 
-    while *pos < input.len() as f64 {
-        let idx = (*pos).floor() as usize;
-        out.push(input[idx]);
-        *pos += step;
+    while *phase < end {
+        let idx = (*phase / SAMPLE_RATE as u64) as usize * channels;
+        out.extend_from_slice(&input[idx..idx + channels]);
+        *phase += step;
     }
 
-    *pos -= input.len() as f64;
+    *phase -= end;
     out
 }
 
-fn apply_noise_suppression(samples: &mut [f32], state: &mut CapturePipelineState) {
-    for sample in samples.iter_mut() {
+/// Nearest-sample resampler from 48 kHz to the output device rate, the
+/// reverse of [`resample_to_48k`]. It pulls 48 kHz frames one at a time as
+/// the device asks for its own, so it sits between the mix and the device
+/// and keeps its position across callbacks.
+///
+/// The position is counted in 1/`device_rate`ths of a 48 kHz frame; each
+/// device frame advances it by exactly 48000 units. As on the capture side,
+/// integer steps keep the pull rate exact however long the call runs.
+#[derive(Debug, Clone)]
+struct OutputResampler {
+    device_rate: u32,
+    phase: u64,
+    /// 48 kHz frame being played, `None` before the first pull
+    current: Option<(f32, f32)>,
+}
+
+impl Default for OutputResampler {
+    fn default() -> Self {
+        Self::new(SAMPLE_RATE)
+    }
+}
+
+impl OutputResampler {
+    fn new(device_rate: u32) -> Self {
+        Self {
+            device_rate: device_rate.max(1),
+            phase: 0,
+            current: None,
+        }
+    }
+
+    /// Next device frame, pulling 48 kHz frames from `pull` as the
+    /// position passes them
+    fn next_frame(&mut self, mut pull: impl FnMut() -> (f32, f32)) -> (f32, f32) {
+        if self.device_rate == SAMPLE_RATE {
+            return pull();
+        }
+        let rate = u64::from(self.device_rate);
+        let frame = *self.current.get_or_insert_with(&mut pull);
+        self.phase += u64::from(SAMPLE_RATE);
+        while self.phase >= rate {
+            self.phase -= rate;
+            self.current = Some(pull());
+        }
+        frame
+    }
+}
+
+/// Filter history of one channel of noise suppression
+#[derive(Debug, Default, Clone, Copy)]
+struct NoiseFilter {
+    dc_prev_x: f32,
+    dc_prev_y: f32,
+    lowpass_prev: f32,
+}
+
+impl NoiseFilter {
+    fn apply(&mut self, sample: f32) -> f32 {
         // DC blocker
-        let hp = *sample - state.dc_prev_x + 0.995 * state.dc_prev_y;
-        state.dc_prev_x = *sample;
-        state.dc_prev_y = hp;
+        let hp = sample - self.dc_prev_x + 0.995 * self.dc_prev_y;
+        self.dc_prev_x = sample;
+        self.dc_prev_y = hp;
 
         // Gentle low-pass to reduce high-frequency hiss/squeal artifacts
-        let lp = 0.22 * hp + 0.78 * state.lowpass_prev;
-        state.lowpass_prev = lp;
-
-        *sample = lp;
+        let lp = 0.22 * hp + 0.78 * self.lowpass_prev;
+        self.lowpass_prev = lp;
+        lp
     }
 }
 
-fn process_mono_samples(
-    mono_samples: &[f32],
-    input_rate: u32,
-    muted: bool,
-    rms_tx: &mpsc::UnboundedSender<f32>,
-    encoder: &Arc<Mutex<OpusEncoder>>,
-    crypto: &Arc<CryptoContext>,
-    seq: &Arc<std::sync::atomic::AtomicU32>,
-    packet_tx: &mpsc::UnboundedSender<AudioPacket>,
-    controls: &Arc<CaptureControls>,
+/// Subtract the echo of `reference` (mono, as played) from every channel
+fn apply_echo_cancellation(
+    samples: &mut [f32],
+    reference: &[f32],
     state: &mut CapturePipelineState,
 ) {
-    let mut processed = resample_to_48k(mono_samples, input_rate, &mut state.resample_pos);
-    if processed.is_empty() {
+    let channels = state.channels;
+    if channels == 1 {
+        state.echo_cancellers[0].process(samples, reference);
         return;
     }
+    for (channel, canceller) in state.echo_cancellers.iter_mut().enumerate() {
+        let mut mono: Vec<f32> = samples
+            .iter()
+            .skip(channel)
+            .step_by(channels)
+            .copied()
+            .collect();
+        canceller.process(&mut mono, reference);
+        for (out, sample) in samples.iter_mut().skip(channel).step_by(channels).zip(mono) {
+            *out = sample;
+        }
+    }
+}
 
-    if controls.noise_suppression.load(Ordering::Relaxed) {
-        apply_noise_suppression(&mut processed, state);
+fn apply_noise_suppression(
+    samples: &mut [f32],
+    mode: NoiseSuppressionMode,
+    state: &mut CapturePipelineState,
+) {
+    if mode == NoiseSuppressionMode::Light {
+        state.heavy_suppression_active = false;
+        for frame in samples.chunks_mut(state.channels) {
+            for (sample, filter) in frame.iter_mut().zip(state.noise_filters.iter_mut()) {
+                *sample = filter.apply(*sample);
+            }
+        }
+        return;
     }
 
-    let rms = calculate_rms(&processed);
-    let _ = rms_tx.send(rms);
+    if !state.heavy_suppression_active {
+        state.noise_suppressors = Default::default();
+        state.heavy_suppression_active = true;
+    }
+    let channels = state.channels;
+    let speech = state.speech;
+    if channels == 1 {
+        state.noise_suppressors[0].process(samples, speech);
+        return;
+    }
+    for (channel, suppressor) in state.noise_suppressors.iter_mut().enumerate() {
+        let mut mono: Vec<f32> = samples
+            .iter()
+            .skip(channel)
+            .step_by(channels)
+            .copied()
+            .collect();
+        suppressor.process(&mut mono, speech);
+        for (out, sample) in samples.iter_mut().skip(channel).step_by(channels).zip(mono) {
+            *out = sample;
+        }
+    }
+}
 
+/// Input gain and AGC. AGC adapts to the level of whatever
+/// reaches this stage; when the noise gate already ran and is closed, the
+/// AGC gain is held so gated noise doesn't pump it up.
+fn apply_gain_stage(
+    samples: &mut [f32],
+    controls: &CaptureControls,
+    state: &mut CapturePipelineState,
+    gate_applied: bool,
+) {
     let input_gain = f32::from_bits(controls.input_gain_bits.load(Ordering::Relaxed));
 
     if controls.agc_enabled.load(Ordering::Relaxed) {
-        let measured = rms.max(1e-4);
-        let desired = (0.12 / measured).clamp(0.3, 3.5);
-        state.agc_gain += (desired - state.agc_gain) * 0.08;
+        let gate_closed = gate_applied && state.gate_gain < 0.5;
+        if !gate_closed {
+            let target = f32::from_bits(controls.agc_target_rms_bits.load(Ordering::Relaxed));
+            let min_gain = f32::from_bits(controls.agc_min_gain_bits.load(Ordering::Relaxed));
+            let max_gain = f32::from_bits(controls.agc_max_gain_bits.load(Ordering::Relaxed));
+            let measured = calculate_rms(samples).max(1e-4);
+            // Not `clamp`: a range update racing this read may briefly
+            // leave min above max
+            let desired = (target / measured).max(min_gain).min(max_gain);
+            state.agc_gain += (desired - state.agc_gain) * 0.08;
+        }
     } else {
         state.agc_gain += (1.0 - state.agc_gain) * 0.12;
     }
 
-    let mut total_gain = (input_gain * state.agc_gain).clamp(0.0, 8.0);
+    let total_gain = (input_gain * state.agc_gain).clamp(0.0, 8.0);
 
-    if controls.aec_enabled.load(Ordering::Relaxed) {
-        let playback_rms =
-            f32::from_bits(controls.shared_playback_rms_bits.load(Ordering::Relaxed));
-        let duck = (1.0 - (playback_rms * 1.6)).clamp(0.25, 1.0);
-        total_gain *= duck;
+    for sample in samples.iter_mut() {
+        *sample = (*sample * total_gain).clamp(-1.0, 1.0);
     }
+}
 
-    for sample in &mut processed {
-        *sample = (*sample * total_gain).clamp(-1.0, 1.0);
+/// Noise gate keyed on the post-suppression level. Returns whether the gate
+/// is enabled and was applied to this block.
+fn apply_noise_gate_stage(
+    samples: &mut [f32],
+    controls: &CaptureControls,
+    state: &mut CapturePipelineState,
+    rms: f32,
+) -> bool {
+    if !controls.noise_gate_enabled.load(Ordering::Relaxed) {
+        return false;
     }
 
-    if controls.noise_gate_enabled.load(Ordering::Relaxed) {
-        let gate_threshold =
-            f32::from_bits(controls.noise_gate_threshold_bits.load(Ordering::Relaxed));
-        let desired_gate = if rms >= gate_threshold { 1.0 } else { 0.0 };
-        let slew = if desired_gate > state.gate_gain {
-            0.35
-        } else {
-            0.15
-        };
-        state.gate_gain += (desired_gate - state.gate_gain) * slew;
+    let gate_threshold = f32::from_bits(controls.noise_gate_threshold_bits.load(Ordering::Relaxed));
+    let hold_ms = controls.noise_gate_hold_ms.load(Ordering::Relaxed);
+    let high_band_bias = f32::from_bits(
+        controls
+            .noise_gate_high_band_bias_bits
+            .load(Ordering::Relaxed),
+    );
+    let layout = state.channels.max(1);
+    let frames = samples.len() / layout;
+
+    // Split each channel with a one-pole low-pass; the high band is the
+    // remainder, so equal band gains give back the input exactly
+    let coeff =
+        1.0 - (-2.0 * std::f32::consts::PI * NOISE_GATE_CROSSOVER_HZ / SAMPLE_RATE as f32).exp();
+    let mut low = Vec::with_capacity(samples.len());
+    let mut high_sq_sum = 0.0f32;
+    for (i, &sample) in samples.iter().enumerate() {
+        let lp = &mut state.gate_crossover[(i % layout).min(1)];
+        *lp += (sample - *lp) * coeff;
+        low.push(*lp);
+        high_sq_sum += (sample - *lp).powi(2);
+    }
+    let high_rms = (high_sq_sum / samples.len().max(1) as f32).sqrt();
+
+    let hold = hold_ms as usize * SAMPLE_RATE as usize / 1000;
+    let open = rms >= gate_threshold;
+    // The high band also opens on its own energy, weighted up, and so
+    // lets through consonants too quiet to open the gate as a whole
+    let high_open = open || high_rms * high_band_bias >= gate_threshold;
+    let low_desired = hold_gate(open, hold, frames, &mut state.gate_hold_remaining);
+    let high_desired = hold_gate(high_open, hold, frames, &mut state.gate_high_hold_remaining);
+
+    let slew = if low_desired > state.gate_gain {
+        0.35
+    } else {
+        0.15
+    };
+    state.gate_gain += (low_desired - state.gate_gain) * slew;
+    // A consonant lasts only tens of ms, so the high band opens at once
+    // instead of ramping up over several blocks
+    state.gate_high_gain = if high_desired > state.gate_high_gain {
+        high_desired
+    } else {
+        state.gate_high_gain + (high_desired - state.gate_high_gain) * 0.15
+    };
+
+    for (sample, low) in samples.iter_mut().zip(low) {
+        *sample = low * state.gate_gain + (*sample - low) * state.gate_high_gain;
+    }
+    true
+}
+
+/// Whether a gate band should be open for a block of `frames`: open while
+/// the level is over the threshold and for `hold` samples after
+fn hold_gate(open: bool, hold: usize, frames: usize, remaining: &mut usize) -> f32 {
+    if open {
+        *remaining = hold;
+        return 1.0;
+    }
+    let held = *remaining > 0;
+    *remaining = remaining.saturating_sub(frames);
+    if held {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Record this callback's arrival and report it if it came more than
+/// `CAPTURE_JITTER_MARGIN` after the previous callback's audio ran out.
+fn check_callback_timing(
+    state: &mut CapturePipelineState,
+    now: Instant,
+    samples: usize,
+    input_rate: u32,
+) -> Option<CaptureJitter> {
+    let length = Duration::from_secs_f64(samples as f64 / input_rate.max(1) as f64);
+    let previous = state.last_callback.replace((now, length));
+    let (last_at, expected) = previous?;
+    let interval = now.saturating_duration_since(last_at);
+    (interval > expected + CAPTURE_JITTER_MARGIN).then_some(CaptureJitter { interval, expected })
+}
+
+/// Channels the capture pipeline currently encodes
+fn capture_layout(controls: &CaptureControls) -> usize {
+    if controls.stereo.load(Ordering::Relaxed) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Run one callback's worth of `samples`, interleaved in `layout` channels,
+/// through the pipeline and send every frame completed.
+#[allow(clippy::too_many_arguments)]
+fn process_capture_samples(
+    samples: &[f32],
+    layout: usize,
+    input_rate: u32,
+    muted: bool,
+    rms_tx: &watch::Sender<f32>,
+    encoder: &Arc<Mutex<OpusEncoder>>,
+    crypto: &CryptoSlot,
+    seq: &Arc<std::sync::atomic::AtomicU32>,
+    packet_tx: &mpsc::UnboundedSender<AudioPacket>,
+    controls: &Arc<CaptureControls>,
+    state: &mut CapturePipelineState,
+) {
+    if let Some(jitter) =
+        check_callback_timing(state, Instant::now(), samples.len() / layout, input_rate)
+    {
+        controls.jitter_events.fetch_add(1, Ordering::Relaxed);
+        if let Ok(jitter_tx) = controls.jitter_tx.lock() {
+            if let Some(jitter_tx) = jitter_tx.as_ref() {
+                let _ = jitter_tx.send(jitter);
+            }
+        }
+    }
+
+    if controls.paused.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if state.channels != layout {
+        // Half a frame of the old layout would misalign every frame after it
+        state.sample_buffer.clear();
+        state.noise_filters = Default::default();
+        state.gate_crossover = [0.0; 2];
+        state.heavy_suppression_active = false;
+        state.channels = layout;
+    }
+
+    let mut processed = resample_to_48k(samples, layout, input_rate, &mut state.resample_phase);
+    if processed.is_empty() {
+        return;
+    }
+
+    // Drained even with AEC off, so the reference stays in step with the
+    // microphone for when it is turned on
+    let reference = controls.echo_reference.take(processed.len() / layout);
+    if controls.aec_enabled.load(Ordering::Relaxed) {
+        apply_echo_cancellation(&mut processed, &reference, state);
+    }
+
+    if controls.noise_suppression.load(Ordering::Relaxed) {
+        let mode =
+            NoiseSuppressionMode::from_u8(controls.noise_suppression_mode.load(Ordering::Relaxed));
+        apply_noise_suppression(&mut processed, mode, state);
+    } else {
+        state.heavy_suppression_active = false;
+    }
 
-        for sample in &mut processed {
-            *sample *= state.gate_gain;
+    let rms = calculate_rms(&processed);
+    rms_tx.send_replace(rms);
+    let vad_threshold = f32::from_bits(controls.vad_threshold_bits.load(Ordering::Relaxed));
+    state.speech = rms >= vad_threshold;
+
+    let stage_order = CaptureStageOrder::from_u8(controls.stage_order.load(Ordering::Relaxed));
+    let mut gate_applied = false;
+    for stage in stage_order.stages() {
+        match stage {
+            CaptureStage::Gain => apply_gain_stage(&mut processed, controls, state, gate_applied),
+            CaptureStage::NoiseGate => {
+                gate_applied = apply_noise_gate_stage(&mut processed, controls, state, rms);
+            }
         }
     }
 
+    // After the gate, so a clip is not cut with the room noise. It plays
+    // on while muted, just unsent, so unmuting resumes it in time.
+    let clip_playing = match controls.clip.lock() {
+        Ok(mut clip) => {
+            let playing = clip
+                .as_mut()
+                .is_some_and(|clip| clip.apply(&mut processed, layout));
+            if clip.as_ref().is_some_and(ClipInjection::is_finished) {
+                *clip = None;
+            }
+            playing
+        }
+        Err(_) => false,
+    };
+
     let mode = controls.voice_mode.load(Ordering::Relaxed);
     let ptt_active = controls.ptt_active.load(Ordering::Relaxed);
-    let vad_threshold = f32::from_bits(controls.vad_threshold_bits.load(Ordering::Relaxed));
     let transmit_by_mode = match mode {
         VOICE_MODE_MUTE => false,
-        VOICE_MODE_PTT => ptt_active,
-        _ => rms >= vad_threshold,
+        VOICE_MODE_PTT => ptt_active || clip_playing,
+        _ => state.speech || clip_playing,
     };
 
     let should_send_audio = !muted && transmit_by_mode;
+    if !transmit_by_mode {
+        controls
+            .vad_gated_samples
+            .fetch_add((processed.len() / layout) as u64, Ordering::Relaxed);
+    }
+    if let Some(change) = state
+        .speaking
+        .update(should_send_audio, processed.len() / layout)
+    {
+        if let Ok(speaking_tx) = controls.speaking_tx.lock() {
+            if let Some(speaking_tx) = speaking_tx.as_ref() {
+                let _ = speaking_tx.send(change);
+            }
+        }
+    }
 
+    let block_start = state.sample_buffer.len();
     if !should_send_audio {
         state.sample_buffer.extend(vec![0i16; processed.len()]);
     } else {
@@ -985,17 +2994,58 @@ fn process_mono_samples(
             (clamped * 32767.0) as i16
         }));
     }
+    controls.recording.push_with(RecordingSide::Local, || {
+        state.sample_buffer[block_start..]
+            .chunks(layout)
+            .map(|frame| {
+                (frame.iter().map(|&s| i32::from(s)).sum::<i32>() / frame.len() as i32) as i16
+            })
+            .collect()
+    });
+
+    let Some(crypto) = crypto.get() else {
+        // Nothing can be sent without a session key
+        state.sample_buffer.clear();
+        return;
+    };
 
-    while state.sample_buffer.len() >= FRAME_SIZE {
-        let frame: Vec<i16> = state.sample_buffer.drain(..FRAME_SIZE).collect();
+    // Read once per callback: a size changed mid-drain only applies from
+    // the next one, and whatever is left over is less than a frame of
+    // either size
+    let frame_len = controls.frame_size.load(Ordering::Relaxed) * layout;
+    while state.sample_buffer.len() >= frame_len {
+        let frame: Vec<i16> = state.sample_buffer.drain(..frame_len).collect();
         if let Ok(mut enc) = encoder.lock() {
+            if enc.channels() != layout {
+                if let Err(e) = enc.set_channels(layout) {
+                    tracing::warn!("Failed to switch Opus encoder channels: {}", e);
+                    continue;
+                }
+            }
             if let Ok(encoded) = enc.encode(&frame) {
+                if enc.is_dtx_packet(&encoded) {
+                    // Not sent and no sequence number used: the peer sees
+                    // a pause, not loss
+                    controls.frames_dtx.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                match crypto.ratchet_if_due(KEY_ROTATION_INTERVAL, KEY_ROTATION_PACKETS) {
+                    Ok(true) => tracing::debug!("Media key moved to epoch {}", crypto.epoch()),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Media key rotation failed: {}", e),
+                }
                 if let Ok(encrypted) = crypto.encrypt(&encoded) {
                     let sequence = seq.fetch_add(1, Ordering::SeqCst);
                     let packet = AudioPacket {
                         seq: sequence,
                         data: encrypted,
                     };
+                    controls.frames_encoded.fetch_add(1, Ordering::Relaxed);
+                    if let Ok(recipients) = controls.recipients.read() {
+                        for recipient in recipients.values() {
+                            let _ = recipient.send(packet.clone());
+                        }
+                    }
                     let _ = packet_tx.send(packet);
                 }
             }
@@ -1003,927 +3053,3404 @@ fn process_mono_samples(
     }
 }
 
-/// Audio playback pipeline (Channel -> Decrypt -> Opus -> Speaker)
-pub struct AudioPlayback {
-    decoder: Arc<Mutex<OpusDecoder>>,
-    crypto: Arc<CryptoContext>,
-    // Buffer for decoded samples waiting to be played
-    sample_queue: Arc<Mutex<VecDeque<i16>>>,
-    // Flag to keep playback thread alive
-    running: Arc<AtomicBool>,
-    // Monotonic token to invalidate old playback threads
-    run_token: Arc<AtomicU64>,
-    // Runtime controls
-    output_volume_bits: Arc<AtomicU32>,
-    remote_volume_bits: Arc<AtomicU32>,
-    limiter_enabled: Arc<AtomicBool>,
-    muted: Arc<AtomicBool>,
-    // Shared RMS for pseudo AEC feedback
-    output_rms_bits: Arc<AtomicU32>,
+/// Bounds of the jitter buffer's target depth. The configured target is
+/// clamped to these and the adaptive target never leaves them.
+pub const MIN_JITTER_TARGET_MS: u32 = 20;
+pub const MAX_JITTER_TARGET_MS: u32 = 200;
+pub const DEFAULT_JITTER_TARGET_MS: u32 = 60;
+
+/// How far the adaptive target moves per late frame, or per steady stretch
+const JITTER_ADAPT_STEP_MS: u32 = 10;
+/// Audio played in a row without a late arrival before the target eases
+/// back toward the configured depth. In time rather than frames, so it does
+/// not depend on the sender's frame size.
+const JITTER_RELAX_MS: u32 = 5000;
+/// Loudest decoded frame, as i16 RMS (about -40 dBFS), taken for
+/// background noise. When the stream runs dry after such a frame, e.g. a
+/// sender in DTX, playback fills the gap with noise at that level instead
+/// of cutting to silence.
+const COMFORT_NOISE_MAX_RMS: f32 = 330.0;
+
+/// RMS of a decoded frame if it is quiet enough to be background noise
+fn background_noise_rms(samples: &[i16]) -> Option<f32> {
+    if samples.is_empty() {
+        return None;
+    }
+    let sum: f64 = samples.iter().map(|&s| f64::from(s).powi(2)).sum();
+    let rms = (sum / samples.len() as f64).sqrt() as f32;
+    (rms <= COMFORT_NOISE_MAX_RMS).then_some(rms)
 }
 
-impl AudioPlayback {
-    pub fn new(crypto: Arc<CryptoContext>) -> Result<Self> {
-        Ok(Self {
-            decoder: Arc::new(Mutex::new(OpusDecoder::new()?)),
-            crypto,
-            sample_queue: Arc::new(Mutex::new(VecDeque::with_capacity(FRAME_SIZE * 10))),
-            running: Arc::new(AtomicBool::new(false)),
-            run_token: Arc::new(AtomicU64::new(0)),
-            output_volume_bits: Arc::new(AtomicU32::new(1.0f32.to_bits())),
-            remote_volume_bits: Arc::new(AtomicU32::new(1.0f32.to_bits())),
-            limiter_enabled: Arc::new(AtomicBool::new(true)),
-            muted: Arc::new(AtomicBool::new(false)),
-            output_rms_bits: Arc::new(AtomicU32::new(0.0f32.to_bits())),
-        })
-    }
+/// Span of [`AudioPlayback::packet_loss_percent`]
+pub const LOSS_WINDOW: Duration = Duration::from_secs(5);
+/// The loss window is kept in buckets of this length and slides a bucket at
+/// a time
+const LOSS_BUCKET: Duration = Duration::from_secs(1);
 
-    /// Process incoming encrypted packet
-    pub fn process_packet(&self, packet: AudioPacket) -> Result<()> {
-        let decrypted = self
-            .crypto
-            .decrypt(&packet.data)
-            .map_err(|e| anyhow::anyhow!("Decrypt error: {:?}", e))?;
+/// Concealed sequence numbers remembered so a frame arriving after its slot
+/// played counts as late rather than as a duplicate
+const JITTER_CONCEALED_HISTORY: usize = 64;
 
-        let mut decoder = self
-            .decoder
-            .lock()
-            .map_err(|_| anyhow::anyhow!("Lock error"))?;
-        let samples = decoder.decode(&decrypted)?;
+fn ms_to_samples(ms: u32) -> usize {
+    SAMPLE_RATE as usize * ms as usize / 1000
+}
 
-        let mut queue = self
-            .sample_queue
-            .lock()
-            .map_err(|_| anyhow::anyhow!("Lock error"))?;
+/// Decoded frames ordered by packet sequence number. Playback starts once
+/// the target depth is buffered, so bursty or reordered arrival plays out
+/// evenly. A missing frame is covered with silence, or skipped when enough
+/// audio is queued behind it.
+///
+/// Sequence numbers are unwrapped into a u64 relative to the highest one
+/// seen, so the u32 wrapping around keeps its order. The first frame after a
+/// reset, or one further from the play position than the buffer can hold,
+/// sets a new baseline.
+#[derive(Debug)]
+pub struct JitterBuffer {
+    frames: BTreeMap<u64, Vec<i16>>,
+    /// Samples held in `frames`
+    buffered: usize,
+    /// Frame being played out
+    current: VecDeque<i16>,
+    /// Unwrapped sequence number of the next frame to play
+    next_seq: Option<u64>,
+    highest_seq: u64,
+    /// Length of the last played frame, used to size concealment
+    frame_len: usize,
+    /// Frames hold this many interleaved channels; depths below are in
+    /// samples, so they scale with it
+    channels: usize,
+    /// Configured depth in samples
+    base_target: usize,
+    /// Depth being aimed for: grows when frames arrive late, relaxes back
+    /// to `base_target` while the network is steady
+    target: usize,
+    /// False until `target` samples are buffered, and again after an underrun
+    primed: bool,
+    /// Samples played since the target last moved
+    steady_samples: usize,
+    concealed: VecDeque<u64>,
+    peak: usize,
+    /// Level of the comfort noise played on underrun, taken from the last
+    /// frame played; 0 when that was louder than background noise
+    comfort_rms: f32,
+    noise_state: u32,
+    /// Ran dry since the last `take_underrun`
+    underran: bool,
+    /// Dropped queued audio for space or latency since the last
+    /// `take_overrun`
+    overran: bool,
+}
 
-        // Simple buffer management - avoid unlimited growth
-        if queue.len() > FRAME_SIZE * 50 {
-            // ~1s buffer max
-            // If too full, drain half to catch up (latency optimization)
-            queue.drain(..FRAME_SIZE * 25);
-        }
+impl JitterBuffer {
+    pub fn new(target_ms: u32) -> Self {
+        let mut buffer = Self {
+            frames: BTreeMap::new(),
+            buffered: 0,
+            current: VecDeque::with_capacity(FRAME_SIZE),
+            next_seq: None,
+            highest_seq: 0,
+            frame_len: FRAME_SIZE,
+            channels: 1,
+            base_target: 0,
+            target: 0,
+            primed: false,
+            steady_samples: 0,
+            concealed: VecDeque::with_capacity(JITTER_CONCEALED_HISTORY),
+            peak: 0,
+            comfort_rms: 0.0,
+            noise_state: 0x9E37_79B9,
+            underran: false,
+            overran: false,
+        };
+        buffer.set_target_latency_ms(target_ms);
+        buffer
+    }
 
-        queue.extend(samples);
-        Ok(())
+    /// Set the depth playback waits for before starting, clamped to
+    /// `MIN_JITTER_TARGET_MS..=MAX_JITTER_TARGET_MS`. Also drops any
+    /// adaptive growth.
+    pub fn set_target_latency_ms(&mut self, ms: u32) {
+        self.base_target = self.ms_to_samples(ms.clamp(MIN_JITTER_TARGET_MS, MAX_JITTER_TARGET_MS));
+        self.target = self.base_target;
+        self.steady_samples = 0;
     }
 
-    pub fn start(&self) -> Result<()> {
-        self.start_with_device(None)
+    /// Depth currently aimed for, including adaptive growth
+    pub fn target_latency_ms(&self) -> u32 {
+        (self.target * 1000 / (SAMPLE_RATE as usize * self.channels)) as u32
     }
 
-    pub fn start_with_device(&self, device_name: Option<&str>) -> Result<()> {
-        if self.running.swap(true, Ordering::SeqCst) {
-            return Ok(());
-        }
-
-        let sample_queue = self.sample_queue.clone();
-        let running = self.running.clone();
-        let run_token = self.run_token.clone();
-        let output_volume_bits = self.output_volume_bits.clone();
-        let remote_volume_bits = self.remote_volume_bits.clone();
-        let limiter_enabled = self.limiter_enabled.clone();
-        let muted = self.muted.clone();
-        let output_rms_bits = self.output_rms_bits.clone();
-        let device_name_owned = device_name.map(|s| s.to_string());
-        let current_token = run_token.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
-
-        thread::spawn(move || {
-            let host = cpal::default_host();
-            let device = if let Some(ref name) = device_name_owned {
-                match host.output_devices() {
-                    Ok(devices) => {
-                        if let Some(device) = devices
-                            .into_iter()
-                            .find(|d| d.name().map(|n| n == *name).unwrap_or(false))
-                        {
-                            device
-                        } else {
-                            tracing::warn!("Output device '{}' not found, using default", name);
-                            match host.default_output_device() {
-                                Some(d) => d,
-                                None => {
-                                    tracing::error!("No output device available");
-                                    if run_token.load(Ordering::SeqCst) == current_token {
-                                        running.store(false, Ordering::SeqCst);
-                                    }
-                                    return;
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to enumerate output devices: {}", e);
-                        match host.default_output_device() {
-                            Some(d) => d,
-                            None => {
-                                tracing::error!("No output device available");
-                                if run_token.load(Ordering::SeqCst) == current_token {
-                                    running.store(false, Ordering::SeqCst);
-                                }
-                                return;
-                            }
-                        }
-                    }
-                }
-            } else {
-                match host.default_output_device() {
-                    Some(d) => d,
-                    None => {
-                        tracing::error!("No output device available");
-                        if run_token.load(Ordering::SeqCst) == current_token {
-                            running.store(false, Ordering::SeqCst);
-                        }
-                        return;
-                    }
-                }
-            };
-
-            let config = match pick_output_config(&device) {
-                Ok(c) => c,
-                Err(e) => {
-                    tracing::error!("Failed to pick output config: {}", e);
-                    if run_token.load(Ordering::SeqCst) == current_token {
-                        running.store(false, Ordering::SeqCst);
-                    }
-                    return;
-                }
-            };
-
-            let sample_format = config.sample_format();
-            let stream_config: StreamConfig = config.into();
-            let output_channels = stream_config.channels as usize;
-
-            tracing::info!(
-                "Using output device '{}' ({:?}, {}ch @ {}Hz)",
-                device.name().unwrap_or_else(|_| "unknown".to_string()),
-                sample_format,
-                output_channels,
-                stream_config.sample_rate.0
-            );
-
-            let stream_result = match sample_format {
-                SampleFormat::F32 => {
-                    let sample_queue = sample_queue.clone();
-                    let output_volume_bits = output_volume_bits.clone();
-                    let remote_volume_bits = remote_volume_bits.clone();
-                    let limiter_enabled = limiter_enabled.clone();
-                    let muted = muted.clone();
-                    let output_rms_bits = output_rms_bits.clone();
-                    device.build_output_stream(
-                        &stream_config,
-                        move |data: &mut [f32], _info| {
-                            fill_output_f32(
-                                data,
-                                output_channels,
-                                &sample_queue,
-                                &output_volume_bits,
-                                &remote_volume_bits,
-                                &limiter_enabled,
-                                &muted,
-                                &output_rms_bits,
-                            );
-                        },
-                        |err| tracing::error!("Playback stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::F64 => {
-                    let sample_queue = sample_queue.clone();
-                    let output_volume_bits = output_volume_bits.clone();
-                    let remote_volume_bits = remote_volume_bits.clone();
-                    let limiter_enabled = limiter_enabled.clone();
-                    let muted = muted.clone();
-                    let output_rms_bits = output_rms_bits.clone();
-                    device.build_output_stream(
-                        &stream_config,
-                        move |data: &mut [f64], _info| {
-                            fill_output_f64(
-                                data,
-                                output_channels,
-                                &sample_queue,
-                                &output_volume_bits,
-                                &remote_volume_bits,
-                                &limiter_enabled,
-                                &muted,
-                                &output_rms_bits,
-                            );
-                        },
-                        |err| tracing::error!("Playback stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::I16 => {
-                    let sample_queue = sample_queue.clone();
-                    let output_volume_bits = output_volume_bits.clone();
-                    let remote_volume_bits = remote_volume_bits.clone();
-                    let limiter_enabled = limiter_enabled.clone();
-                    let muted = muted.clone();
-                    let output_rms_bits = output_rms_bits.clone();
-                    device.build_output_stream(
-                        &stream_config,
-                        move |data: &mut [i16], _info| {
-                            fill_output_i16(
-                                data,
-                                output_channels,
-                                &sample_queue,
-                                &output_volume_bits,
-                                &remote_volume_bits,
-                                &limiter_enabled,
-                                &muted,
-                                &output_rms_bits,
-                            );
-                        },
-                        |err| tracing::error!("Playback stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::I32 => {
-                    let sample_queue = sample_queue.clone();
-                    let output_volume_bits = output_volume_bits.clone();
-                    let remote_volume_bits = remote_volume_bits.clone();
-                    let limiter_enabled = limiter_enabled.clone();
-                    let muted = muted.clone();
-                    let output_rms_bits = output_rms_bits.clone();
-                    device.build_output_stream(
-                        &stream_config,
-                        move |data: &mut [i32], _info| {
-                            fill_output_i32(
-                                data,
-                                output_channels,
-                                &sample_queue,
-                                &output_volume_bits,
-                                &remote_volume_bits,
-                                &limiter_enabled,
-                                &muted,
-                                &output_rms_bits,
-                            );
-                        },
-                        |err| tracing::error!("Playback stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::U16 => {
-                    let sample_queue = sample_queue.clone();
-                    let output_volume_bits = output_volume_bits.clone();
-                    let remote_volume_bits = remote_volume_bits.clone();
-                    let limiter_enabled = limiter_enabled.clone();
-                    let muted = muted.clone();
-                    let output_rms_bits = output_rms_bits.clone();
-                    device.build_output_stream(
-                        &stream_config,
-                        move |data: &mut [u16], _info| {
-                            fill_output_u16(
-                                data,
-                                output_channels,
-                                &sample_queue,
-                                &output_volume_bits,
-                                &remote_volume_bits,
-                                &limiter_enabled,
-                                &muted,
-                                &output_rms_bits,
-                            );
-                        },
-                        |err| tracing::error!("Playback stream error: {}", err),
-                        None,
-                    )
-                }
-                SampleFormat::U32 => {
-                    let sample_queue = sample_queue.clone();
-                    let output_volume_bits = output_volume_bits.clone();
-                    let remote_volume_bits = remote_volume_bits.clone();
-                    let limiter_enabled = limiter_enabled.clone();
-                    let muted = muted.clone();
-                    let output_rms_bits = output_rms_bits.clone();
-                    device.build_output_stream(
-                        &stream_config,
-                        move |data: &mut [u32], _info| {
-                            fill_output_u32(
-                                data,
-                                output_channels,
-                                &sample_queue,
-                                &output_volume_bits,
-                                &remote_volume_bits,
-                                &limiter_enabled,
-                                &muted,
-                                &output_rms_bits,
-                            );
-                        },
-                        |err| tracing::error!("Playback stream error: {}", err),
-                        None,
-                    )
-                }
-                _ => {
-                    tracing::error!("Unsupported output sample format: {:?}", sample_format);
-                    if run_token.load(Ordering::SeqCst) == current_token {
-                        running.store(false, Ordering::SeqCst);
-                    }
-                    return;
-                }
-            };
-
-            let stream = match stream_result {
-                Ok(s) => s,
-                Err(e) => {
-                    tracing::error!("Failed to build output stream: {}", e);
-                    if run_token.load(Ordering::SeqCst) == current_token {
-                        running.store(false, Ordering::SeqCst);
-                    }
-                    return;
-                }
-            };
-
-            if let Err(e) = stream.play() {
-                tracing::error!("Failed to play output stream: {}", e);
-                if run_token.load(Ordering::SeqCst) == current_token {
-                    running.store(false, Ordering::SeqCst);
-                }
-                return;
-            }
-
-            while running.load(Ordering::SeqCst)
-                && run_token.load(Ordering::SeqCst) == current_token
-            {
-                thread::sleep(std::time::Duration::from_millis(100));
-            }
-
-            if run_token.load(Ordering::SeqCst) == current_token {
-                running.store(false, Ordering::SeqCst);
-            }
-        });
-
-        Ok(())
-    }
-
-    pub fn set_output_volume(&self, volume: f32) {
-        let clamped = volume.clamp(0.0, 2.0);
-        self.output_volume_bits
-            .store(clamped.to_bits(), Ordering::SeqCst);
+    /// Configured depth, without adaptive growth
+    pub fn base_latency_ms(&self) -> u32 {
+        (self.base_target * 1000 / (SAMPLE_RATE as usize * self.channels)) as u32
     }
 
-    pub fn output_volume(&self) -> f32 {
-        f32::from_bits(self.output_volume_bits.load(Ordering::SeqCst))
+    /// Switch to frames of `channels` interleaved channels, dropping what is
+    /// buffered in the old layout. The configured depth is kept.
+    pub fn set_channels(&mut self, channels: usize) {
+        let channels = channels.max(1);
+        if channels == self.channels {
+            return;
+        }
+        let base_ms = self.base_latency_ms();
+        self.frame_len = self.frame_len / self.channels * channels;
+        self.channels = channels;
+        self.set_target_latency_ms(base_ms);
+        self.clear();
     }
 
-    pub fn set_remote_volume(&self, volume: f32) {
-        let clamped = volume.clamp(0.0, 2.0);
-        self.remote_volume_bits
-            .store(clamped.to_bits(), Ordering::SeqCst);
+    pub fn channels(&self) -> usize {
+        self.channels
     }
 
-    pub fn remote_volume(&self) -> f32 {
-        f32::from_bits(self.remote_volume_bits.load(Ordering::SeqCst))
+    /// Most samples held before the oldest are dropped (~1s)
+    pub fn max_samples(&self) -> usize {
+        PLAYBACK_QUEUE_MAX_SAMPLES * self.channels
     }
 
-    pub fn set_limiter_enabled(&self, enabled: bool) {
-        self.limiter_enabled.store(enabled, Ordering::SeqCst);
+    fn ms_to_samples(&self, ms: u32) -> usize {
+        ms_to_samples(ms) * self.channels
     }
 
-    pub fn limiter_enabled(&self) -> bool {
-        self.limiter_enabled.load(Ordering::SeqCst)
+    /// Samples waiting to be played
+    pub fn len(&self) -> usize {
+        self.buffered + self.current.len()
     }
 
-    pub fn set_muted(&self, muted: bool) {
-        self.muted.store(muted, Ordering::SeqCst);
+    /// Deepest the buffer got since the last clear
+    pub fn peak(&self) -> usize {
+        self.peak
     }
 
-    pub fn is_muted(&self) -> bool {
-        self.muted.load(Ordering::SeqCst)
+    pub fn allocated_bytes(&self) -> usize {
+        let samples =
+            self.current.capacity() + self.frames.values().map(Vec::capacity).sum::<usize>();
+        samples * std::mem::size_of::<i16>()
     }
 
-    pub fn output_rms_shared(&self) -> Arc<AtomicU32> {
-        self.output_rms_bits.clone()
+    /// Forget the stream; the next frame sets a new baseline. The configured
+    /// target is kept, adaptive growth is not.
+    pub fn clear(&mut self) {
+        self.drop_frames();
+        self.target = self.base_target;
+        self.steady_samples = 0;
+        self.peak = 0;
+        self.comfort_rms = 0.0;
     }
 
-    pub fn output_rms(&self) -> f32 {
-        f32::from_bits(self.output_rms_bits.load(Ordering::SeqCst))
+    fn drop_frames(&mut self) {
+        self.frames.clear();
+        self.buffered = 0;
+        self.current.clear();
+        self.next_seq = None;
+        self.primed = false;
+        self.concealed.clear();
     }
 
-    pub fn is_running(&self) -> bool {
-        self.running.load(Ordering::SeqCst)
+    fn unwrap_seq(&self, seq: u32) -> u64 {
+        let delta = seq.wrapping_sub(self.highest_seq as u32) as i32;
+        self.highest_seq.wrapping_add_signed(i64::from(delta))
     }
 
-    pub fn stop(&self) {
-        self.running.store(false, Ordering::SeqCst);
-        self.run_token.fetch_add(1, Ordering::SeqCst);
-        if let Ok(mut queue) = self.sample_queue.lock() {
-            queue.clear();
+    /// Queue a decoded frame. Duplicates and frames whose slot already
+    /// played are dropped; returns whether the frame was kept.
+    pub fn push(&mut self, seq: u32, samples: Vec<i16>) -> bool {
+        if samples.is_empty() {
+            return false;
         }
-        self.output_rms_bits
-            .store(0.0f32.to_bits(), Ordering::SeqCst);
-    }
-}
 
-fn pick_output_config(device: &cpal::Device) -> Result<SupportedStreamConfig> {
-    let mut best_48k: Option<SupportedStreamConfig> = None;
-
-    if let Ok(configs) = device.supported_output_configs() {
-        for cfg in configs {
-            if cfg.min_sample_rate().0 <= SAMPLE_RATE && cfg.max_sample_rate().0 >= SAMPLE_RATE {
-                let candidate = cfg.with_sample_rate(cpal::SampleRate(SAMPLE_RATE));
-                let better = match &best_48k {
-                    None => true,
-                    Some(best) => {
-                        if candidate.channels() >= CHANNELS {
-                            best.channels() < CHANNELS
-                        } else {
-                            false
-                        }
+        let ext = match self.next_seq {
+            Some(next) => {
+                let ext = self.unwrap_seq(seq);
+                let window = (self.max_samples() / self.frame_len.max(1)) as u64;
+                if ext.abs_diff(next) > window {
+                    // Nothing buffered can bridge a jump this large (a long
+                    // outage or a restarted sender), so start over from here
+                    self.drop_frames();
+                    self.rebase(seq)
+                } else if ext < next {
+                    if self.concealed.contains(&ext) {
+                        self.grow_target();
                     }
-                };
-                if better {
-                    best_48k = Some(candidate);
+                    return false;
+                } else if self.frames.contains_key(&ext) {
+                    return false;
+                } else {
+                    ext
                 }
             }
+            None => self.rebase(seq),
+        };
+
+        if self.comfort_rms > 0.0 {
+            // Noise updates that arrive during the pause keep its level current
+            if let Some(rms) = background_noise_rms(&samples) {
+                self.comfort_rms = rms;
+            }
+        }
+        self.highest_seq = self.highest_seq.max(ext);
+        self.buffered += samples.len();
+        self.frames.insert(ext, samples);
+        self.peak = self.peak.max(self.len());
+
+        // Hard cap on memory: play catches up by losing the oldest audio
+        while self.buffered > self.max_samples() {
+            self.pop_oldest();
+            self.overran = true;
         }
+        true
     }
 
-    if let Some(cfg) = best_48k {
-        return Ok(cfg);
+    /// Make `seq` the first frame of a new stream. The high bits keep
+    /// sequence numbers just before it from underflowing.
+    fn rebase(&mut self, seq: u32) -> u64 {
+        let ext = (1u64 << 32) | u64::from(seq);
+        self.highest_seq = ext;
+        self.next_seq = Some(ext);
+        ext
     }
 
-    device
-        .default_output_config()
-        .map_err(|e| anyhow::anyhow!("No usable output config: {}", e))
-}
-
-fn next_i16_sample(queue: &mut VecDeque<i16>) -> i16 {
-    queue.pop_front().unwrap_or(0)
-}
-
-fn apply_limiter(sample: f32) -> f32 {
-    (sample * 1.6).tanh() / 1.6_f32.tanh()
-}
+    fn pop_oldest(&mut self) -> Option<(u64, Vec<i16>)> {
+        let (seq, frame) = self.frames.pop_first()?;
+        self.buffered -= frame.len();
+        if let Some(next) = self.next_seq {
+            self.remember_concealed(next..seq);
+        }
+        self.next_seq = Some(seq + 1);
+        Some((seq, frame))
+    }
 
-fn playback_sample_from_queue(
-    queue: &mut VecDeque<i16>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-) -> f32 {
-    if muted.load(Ordering::Relaxed) {
-        return 0.0;
+    fn remember_concealed(&mut self, seqs: std::ops::Range<u64>) {
+        let start = seqs
+            .start
+            .max(seqs.end.saturating_sub(JITTER_CONCEALED_HISTORY as u64));
+        for seq in start..seqs.end {
+            if self.concealed.len() == JITTER_CONCEALED_HISTORY {
+                self.concealed.pop_front();
+            }
+            self.concealed.push_back(seq);
+        }
     }
 
-    let sample = next_i16_sample(queue) as f32 / 32767.0;
-    let output_volume = f32::from_bits(output_volume_bits.load(Ordering::Relaxed));
-    let remote_volume = f32::from_bits(remote_volume_bits.load(Ordering::Relaxed));
-    let mut out = sample * output_volume * remote_volume;
-    if limiter_enabled.load(Ordering::Relaxed) {
-        out = apply_limiter(out);
+    /// White noise at `comfort_rms`, or silence when the last frame was
+    /// louder than background noise
+    fn comfort_noise(&mut self) -> i16 {
+        if self.comfort_rms == 0.0 {
+            return 0;
+        }
+        // xorshift32; uniform over +-a has an RMS of a / sqrt(3)
+        let mut x = self.noise_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise_state = x;
+        let uniform = x as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        (uniform * self.comfort_rms * 3f32.sqrt()) as i16
     }
-    out.clamp(-1.0, 1.0)
-}
 
-fn store_output_rms(output_rms_bits: &Arc<AtomicU32>, squared_sum: f32, sample_count: usize) {
-    let rms = if sample_count == 0 {
-        0.0
-    } else {
-        (squared_sum / sample_count as f32).sqrt()
-    };
-    output_rms_bits.store(rms.to_bits(), Ordering::Relaxed);
-}
+    fn grow_target(&mut self) {
+        self.target = (self.target + self.ms_to_samples(JITTER_ADAPT_STEP_MS))
+            .min(self.ms_to_samples(MAX_JITTER_TARGET_MS));
+        self.steady_samples = 0;
+    }
 
-fn fill_output_f32(
-    data: &mut [f32],
-    channels: usize,
-    sample_queue: &Arc<Mutex<VecDeque<i16>>>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-    output_rms_bits: &Arc<AtomicU32>,
-) {
-    let mut queue = match sample_queue.lock() {
-        Ok(q) => q,
-        Err(_) => return,
-    };
+    /// Load the next frame into `current`; false on underrun.
+    fn advance(&mut self) -> bool {
+        // Well past the target: drop a frame so latency eases back down
+        if self.buffered > self.target * 2 {
+            self.pop_oldest();
+            self.overran = true;
+        }
 
-    let mut sq_sum = 0.0f32;
-    let mut count = 0usize;
+        let (Some(next), Some(&first)) = (self.next_seq, self.frames.keys().next()) else {
+            // Rebuild the cushion before playing again. A sender whose
+            // capture stalled or paused looks the same, so this alone does
+            // not grow the target.
+            self.primed = false;
+            return false;
+        };
 
-    if channels <= 1 {
-        for sample in data.iter_mut() {
-            let value = playback_sample_from_queue(
-                &mut queue,
-                output_volume_bits,
-                remote_volume_bits,
-                limiter_enabled,
-                muted,
-            );
-            sq_sum += value * value;
-            count += 1;
-            *sample = value;
+        if first == next || self.buffered > self.target {
+            // In order, or enough is queued that waiting for the missing
+            // frames would only add latency
+            if let Some((_, frame)) = self.pop_oldest() {
+                self.comfort_rms = background_noise_rms(&frame).unwrap_or(0.0);
+                self.frame_len = frame.len();
+                self.current = VecDeque::from(frame);
+            }
+        } else {
+            self.remember_concealed(next..next + 1);
+            self.next_seq = Some(next + 1);
+            self.current.resize(self.frame_len, 0);
         }
-        store_output_rms(output_rms_bits, sq_sum, count);
-        return;
-    }
 
-    for frame in data.chunks_mut(channels) {
-        let value = playback_sample_from_queue(
-            &mut queue,
-            output_volume_bits,
-            remote_volume_bits,
-            limiter_enabled,
-            muted,
-        );
-        sq_sum += value * value;
-        count += 1;
-        for out in frame.iter_mut() {
-            *out = value;
+        self.steady_samples += self.current.len();
+        if self.steady_samples >= self.ms_to_samples(JITTER_RELAX_MS) {
+            self.steady_samples = 0;
+            self.target = self
+                .target
+                .saturating_sub(self.ms_to_samples(JITTER_ADAPT_STEP_MS))
+                .max(self.base_target);
         }
+        true
     }
-
-    store_output_rms(output_rms_bits, sq_sum, count);
 }
 
-fn fill_output_f64(
-    data: &mut [f64],
-    channels: usize,
-    sample_queue: &Arc<Mutex<VecDeque<i16>>>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-    output_rms_bits: &Arc<AtomicU32>,
-) {
-    let mut queue = match sample_queue.lock() {
-        Ok(q) => q,
-        Err(_) => return,
-    };
-
-    let mut sq_sum = 0.0f32;
-    let mut count = 0usize;
+/// Where the output callbacks pull decoded samples from
+trait PlaybackSource {
+    /// Next sample to play; silence when nothing is ready
+    fn next_sample(&mut self) -> i16;
 
-    if channels <= 1 {
-        for sample in data.iter_mut() {
-            let value = playback_sample_from_queue(
-                &mut queue,
-                output_volume_bits,
-                remote_volume_bits,
-                limiter_enabled,
-                muted,
-            );
-            sq_sum += value * value;
-            count += 1;
-            *sample = value as f64;
-        }
-        store_output_rms(output_rms_bits, sq_sum, count);
-        return;
+    /// Interleaved channels `next_sample` walks through
+    fn channels(&self) -> usize {
+        1
     }
 
-    for frame in data.chunks_mut(channels) {
-        let value = playback_sample_from_queue(
-            &mut queue,
-            output_volume_bits,
-            remote_volume_bits,
-            limiter_enabled,
-            muted,
-        );
-        sq_sum += value * value;
-        count += 1;
-        for out in frame.iter_mut() {
-            *out = value as f64;
-        }
+    /// Whether the source ran dry since the last call
+    fn take_underrun(&mut self) -> bool {
+        false
     }
 
-    store_output_rms(output_rms_bits, sq_sum, count);
+    /// Whether the source dropped queued audio since the last call
+    fn take_overrun(&mut self) -> bool {
+        false
+    }
 }
 
-fn fill_output_i16(
-    data: &mut [i16],
-    channels: usize,
-    sample_queue: &Arc<Mutex<VecDeque<i16>>>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-    output_rms_bits: &Arc<AtomicU32>,
-) {
-    let mut queue = match sample_queue.lock() {
-        Ok(q) => q,
-        Err(_) => return,
-    };
-
-    let mut sq_sum = 0.0f32;
-    let mut count = 0usize;
+impl PlaybackSource for VecDeque<i16> {
+    fn next_sample(&mut self) -> i16 {
+        self.pop_front().unwrap_or(0)
+    }
+}
 
-    if channels <= 1 {
-        for sample in data.iter_mut() {
-            let value = playback_sample_from_queue(
-                &mut queue,
-                output_volume_bits,
-                remote_volume_bits,
-                limiter_enabled,
-                muted,
-            );
-            sq_sum += value * value;
-            count += 1;
-            *sample = (value * 32767.0) as i16;
+impl PlaybackSource for JitterBuffer {
+    fn next_sample(&mut self) -> i16 {
+        if let Some(sample) = self.current.pop_front() {
+            return sample;
         }
-        store_output_rms(output_rms_bits, sq_sum, count);
-        return;
+        if !self.primed {
+            if self.buffered < self.target {
+                return self.comfort_noise();
+            }
+            self.primed = true;
+        }
+        if !self.advance() {
+            self.underran = true;
+            return self.comfort_noise();
+        }
+        self.current.pop_front().unwrap_or(0)
     }
 
-    for frame in data.chunks_mut(channels) {
-        let value = playback_sample_from_queue(
-            &mut queue,
-            output_volume_bits,
-            remote_volume_bits,
-            limiter_enabled,
-            muted,
-        );
-        sq_sum += value * value;
-        count += 1;
-        let i16_val = (value * 32767.0) as i16;
-        for out in frame.iter_mut() {
-            *out = i16_val;
-        }
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn take_underrun(&mut self) -> bool {
+        std::mem::take(&mut self.underran)
     }
 
-    store_output_rms(output_rms_bits, sq_sum, count);
+    fn take_overrun(&mut self) -> bool {
+        std::mem::take(&mut self.overran)
+    }
 }
 
-fn fill_output_i32(
-    data: &mut [i32],
-    channels: usize,
-    sample_queue: &Arc<Mutex<VecDeque<i16>>>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-    output_rms_bits: &Arc<AtomicU32>,
-) {
-    let mut queue = match sample_queue.lock() {
-        Ok(q) => q,
-        Err(_) => return,
-    };
+/// Packets seen during one `LOSS_BUCKET`
+#[derive(Debug, Clone, Copy)]
+struct LossBucket {
+    start: Instant,
+    received: u32,
+    lost: u32,
+    /// Packets counted lost that turned up after all
+    late: u32,
+}
 
-    let mut sq_sum = 0.0f32;
-    let mut count = 0usize;
+#[derive(Debug, Default)]
+struct StreamState {
+    /// Sequence number of the last decoded packet
+    last_seq: Option<u32>,
+    /// When that packet arrived
+    last_arrival: Option<Instant>,
+    /// That packet decoded to background noise. The sender may be in DTX,
+    /// so the wait for the next packet says nothing about the network.
+    after_silence: bool,
+    /// Whether the decoded audio carries speech, with hold time
+    speaking: SpeakingDetector,
+    stats: PlaybackStats,
+    /// Recent arrivals for the rolling loss rate, oldest first
+    loss_window: VecDeque<LossBucket>,
+}
 
-    if channels <= 1 {
-        for sample in data.iter_mut() {
-            let value = playback_sample_from_queue(
-                &mut queue,
-                output_volume_bits,
-                remote_volume_bits,
-                limiter_enabled,
-                muted,
-            );
-            sq_sum += value * value;
-            count += 1;
-            *sample = (value * 2_147_483_647.0) as i32;
+impl StreamState {
+    /// Count a decoded packet of `frame_size` samples per channel, `gap`
+    /// sequence numbers past the previous one, with `concealed` lost frames
+    /// synthesized in front of it.
+    fn record(&mut self, seq: u32, gap: u32, concealed: usize, frame_size: usize, now: Instant) {
+        let stats = &mut self.stats;
+        stats.packets_received += 1;
+        stats.packets_lost += u64::from(gap.saturating_sub(1));
+        stats.frames_concealed += concealed as u64;
+
+        if let Some(last_arrival) = self.last_arrival.filter(|_| !self.after_silence) {
+            let frame_ms = frame_size as f64 * 1000.0 / SAMPLE_RATE as f64;
+            let spacing_ms = now.saturating_duration_since(last_arrival).as_secs_f64() * 1000.0;
+            let deviation = (spacing_ms - frame_ms * f64::from(gap)).abs();
+            stats.jitter_ms += (deviation - stats.jitter_ms) / 16.0;
         }
-        store_output_rms(output_rms_bits, sq_sum, count);
-        return;
+        self.last_seq = Some(seq);
+        self.last_arrival = Some(now);
+
+        let bucket = self.loss_bucket(now);
+        bucket.received += 1;
+        bucket.lost += gap.saturating_sub(1);
     }
 
-    for frame in data.chunks_mut(channels) {
-        let value = playback_sample_from_queue(
-            &mut queue,
-            output_volume_bits,
-            remote_volume_bits,
-            limiter_enabled,
-            muted,
-        );
-        sq_sum += value * value;
-        count += 1;
-        let i32_val = (value * 2_147_483_647.0) as i32;
-        for out in frame.iter_mut() {
-            *out = i32_val;
+    /// Count a packet dropped for arriving `behind` sequence numbers after
+    /// the last decoded one: 0 for a repeat of it, more for a late arrival
+    /// Note a packet passed over undecoded while deafened. The position
+    /// moves on, so undeafening counts no loss, and the first packet decoded
+    /// after is not measured for jitter.
+    fn record_skipped(&mut self, seq: u32, now: Instant) {
+        let newer = self
+            .last_seq
+            .is_none_or(|last| (seq.wrapping_sub(last) as i32) > 0);
+        if newer {
+            self.last_seq = Some(seq);
+            self.last_arrival = Some(now);
         }
+        self.after_silence = true;
     }
 
-    store_output_rms(output_rms_bits, sq_sum, count);
-}
-
-fn fill_output_u16(
-    data: &mut [u16],
-    channels: usize,
-    sample_queue: &Arc<Mutex<VecDeque<i16>>>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-    output_rms_bits: &Arc<AtomicU32>,
-) {
-    let mut queue = match sample_queue.lock() {
-        Ok(q) => q,
-        Err(_) => return,
-    };
-
-    let mut sq_sum = 0.0f32;
-    let mut count = 0usize;
-
-    if channels <= 1 {
-        for sample in data.iter_mut() {
-            let value = playback_sample_from_queue(
-                &mut queue,
-                output_volume_bits,
-                remote_volume_bits,
-                limiter_enabled,
-                muted,
-            );
-            sq_sum += value * value;
-            count += 1;
-            *sample = (((value * 0.5 + 0.5).clamp(0.0, 1.0)) * 65535.0) as u16;
+    fn record_discarded(&mut self, behind: u32, now: Instant) {
+        self.stats.packets_discarded += 1;
+        if behind == 0 {
+            self.stats.packets_duplicate += 1;
+        } else {
+            self.stats.packets_out_of_order += 1;
+            // It crossed the network, just too late to play
+            let bucket = self.loss_bucket(now);
+            bucket.received += 1;
+            bucket.late += 1;
         }
-        store_output_rms(output_rms_bits, sq_sum, count);
-        return;
     }
 
-    for frame in data.chunks_mut(channels) {
-        let value = playback_sample_from_queue(
-            &mut queue,
-            output_volume_bits,
-            remote_volume_bits,
-            limiter_enabled,
-            muted,
-        );
-        sq_sum += value * value;
-        count += 1;
-        let u16_val = (((value * 0.5 + 0.5).clamp(0.0, 1.0)) * 65535.0) as u16;
-        for out in frame.iter_mut() {
-            *out = u16_val;
+    /// Bucket for `now`, dropping the ones that slid out of the window
+    fn loss_bucket(&mut self, now: Instant) -> &mut LossBucket {
+        while self
+            .loss_window
+            .front()
+            .is_some_and(|bucket| now.saturating_duration_since(bucket.start) >= LOSS_WINDOW)
+        {
+            self.loss_window.pop_front();
         }
+        let fresh = self
+            .loss_window
+            .back()
+            .is_none_or(|bucket| now.saturating_duration_since(bucket.start) >= LOSS_BUCKET);
+        if fresh {
+            self.loss_window.push_back(LossBucket {
+                start: now,
+                received: 0,
+                lost: 0,
+                late: 0,
+            });
+        }
+        self.loss_window.back_mut().expect("bucket just ensured")
     }
 
-    store_output_rms(output_rms_bits, sq_sum, count);
+    /// Share of packets over the last `LOSS_WINDOW` that never arrived, in
+    /// percent. Late packets count as arrived.
+    fn loss_percent(&self, now: Instant) -> f32 {
+        let (mut received, mut lost, mut late) = (0u64, 0u64, 0u64);
+        for bucket in self
+            .loss_window
+            .iter()
+            .filter(|bucket| now.saturating_duration_since(bucket.start) < LOSS_WINDOW)
+        {
+            received += u64::from(bucket.received);
+            lost += u64::from(bucket.lost);
+            late += u64::from(bucket.late);
+        }
+        let lost = lost.saturating_sub(late);
+        let expected = received + lost;
+        if expected == 0 {
+            return 0.0;
+        }
+        lost as f32 * 100.0 / expected as f32
+    }
 }
 
-fn fill_output_u32(
-    data: &mut [u32],
-    channels: usize,
-    sample_queue: &Arc<Mutex<VecDeque<i16>>>,
-    output_volume_bits: &Arc<AtomicU32>,
-    remote_volume_bits: &Arc<AtomicU32>,
-    limiter_enabled: &Arc<AtomicBool>,
-    muted: &Arc<AtomicBool>,
-    output_rms_bits: &Arc<AtomicU32>,
-) {
-    let mut queue = match sample_queue.lock() {
-        Ok(q) => q,
-        Err(_) => return,
+/// Decrypt and decode one packet of a stream into its jitter buffer,
+/// concealing the frames lost in front of it
+fn decode_packet(
+    crypto: &CryptoContext,
+    decoder: &Mutex<OpusDecoder>,
+    stream: &Mutex<StreamState>,
+    queue: &Mutex<JitterBuffer>,
+    packet: AudioPacket,
+    controls: &PlaybackControls,
+    peer_id: Option<&str>,
+) -> Result<()> {
+    let decrypted = match crypto.decrypt(&packet.data) {
+        Ok(decrypted) => decrypted,
+        Err(CryptoError::Replayed(counter)) => {
+            // Already played, or too old to tell; not worth a warning each
+            tracing::debug!("Dropped replayed audio packet (nonce counter {})", counter);
+            if let Ok(mut stream) = stream.lock() {
+                stream.record_discarded(0, Instant::now());
+            }
+            return Ok(());
+        }
+        Err(e) => return Err(anyhow::anyhow!("Decrypt error: {:?}", e)),
     };
 
-    let mut sq_sum = 0.0f32;
-    let mut count = 0usize;
-
-    if channels <= 1 {
-        for sample in data.iter_mut() {
-            let value = playback_sample_from_queue(
-                &mut queue,
-                output_volume_bits,
-                remote_volume_bits,
-                limiter_enabled,
-                muted,
-            );
-            sq_sum += value * value;
-            count += 1;
-            *sample = (((value * 0.5 + 0.5).clamp(0.0, 1.0)) * 4_294_967_295.0) as u32;
-        }
-        store_output_rms(output_rms_bits, sq_sum, count);
-        return;
+    let mut decoder = decoder.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let mut stream = stream.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let gap = stream
+        .last_seq
+        .map_or(1, |last| packet.seq.wrapping_sub(last) as i32);
+    if gap <= 0 {
+        // Duplicate, or later than a packet already decoded. The decoder
+        // only runs forward and this slot was already concealed.
+        stream.record_discarded(gap.unsigned_abs(), Instant::now());
+        return Ok(());
     }
 
-    for frame in data.chunks_mut(channels) {
-        let value = playback_sample_from_queue(
-            &mut queue,
-            output_volume_bits,
-            remote_volume_bits,
-            limiter_enabled,
-            muted,
-        );
-        sq_sum += value * value;
-        count += 1;
-        let u32_val = (((value * 0.5 + 0.5).clamp(0.0, 1.0)) * 4_294_967_295.0) as u32;
-        for out in frame.iter_mut() {
-            *out = u32_val;
+    let mut frames = Vec::new();
+    let missing = gap as u32 - 1;
+    if (1..=MAX_CONCEALED_FRAMES).contains(&missing) {
+        // PLC for all but the last lost frame, which this packet's FEC
+        // copy may recover
+        for _ in 1..missing {
+            frames.push(decoder.decode_plc()?);
         }
+        frames.push(decoder.decode_fec(&decrypted)?);
+    }
+    let concealed = frames.len();
+    let decoded = decoder.decode(&decrypted)?;
+    let frame_size = decoded.len() / decoder.channels();
+    let now = Instant::now();
+    let silent = background_noise_rms(&decoded).is_some();
+    // A quiet frame stands for the whole wait since the previous packet,
+    // which is long when the sender is in DTX and only sends noise updates
+    let waited = stream.last_arrival.map_or(0, |last| {
+        (now.saturating_duration_since(last).as_secs_f64() * f64::from(SAMPLE_RATE)) as usize
+    });
+    let speaking = stream.speaking.update(!silent, frame_size.max(waited));
+    stream.record(packet.seq, gap as u32, concealed, frame_size, now);
+    stream.after_silence = silent;
+    frames.push(decoded);
+    drop(stream);
+
+    if let Some(change) = speaking {
+        controls.report_speaking(peer_id, change);
     }
 
-    store_output_rms(output_rms_bits, sq_sum, count);
+    let mut queue = queue.lock().map_err(|_| anyhow::anyhow!("Lock error"))?;
+    let first_seq = packet.seq.wrapping_sub(frames.len() as u32 - 1);
+    for (offset, samples) in frames.into_iter().enumerate() {
+        queue.push(first_seq.wrapping_add(offset as u32), samples);
+    }
+    if queue.take_overrun() {
+        controls.overruns.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(())
 }
 
-/// Calculate RMS volume from samples (for VU meter)
-pub fn calculate_rms(samples: &[f32]) -> f32 {
-    if samples.is_empty() {
+/// Audio playback pipeline (Channel -> Decrypt -> Opus -> Speaker)
+pub struct AudioPlayback {
+    decoder: Arc<Mutex<OpusDecoder>>,
+    crypto: CryptoSlot,
+    // Decoded frames waiting to be played, in sequence order
+    sample_queue: Arc<Mutex<JitterBuffer>>,
+    // Flag to keep playback thread alive
+    running: Arc<AtomicBool>,
+    // Monotonic token to invalidate old playback threads
+    run_token: Arc<AtomicU64>,
+    // Runtime controls
+    controls: Arc<PlaybackControls>,
+    // Output level for meters
+    output_rms_bits: Arc<AtomicU32>,
+    // Position in the incoming stream, reset with the decoder
+    stream: Arc<Mutex<StreamState>>,
+    device_events: Arc<DeviceEvents>,
+    speaking_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<RemoteSpeakingState>>>>,
+    output_rms_rx: Arc<Mutex<Option<watch::Receiver<f32>>>>,
+}
+
+impl AudioPlayback {
+    pub fn new(crypto: Arc<CryptoContext>) -> Result<Self> {
+        Self::new_with_config(crypto, AudioPlaybackConfig::default())
+    }
+
+    /// Create a playback already carrying `config`. Out-of-range volumes
+    /// are an error here; the `set_*` methods clamp them.
+    pub fn new_with_config(
+        crypto: Arc<CryptoContext>,
+        config: AudioPlaybackConfig,
+    ) -> Result<Self> {
+        Self::build(Some(crypto), config, false)
+    }
+
+    /// Create a playback with no session key yet, paused. Starting it opens
+    /// the output device and plays silence until `set_crypto` and `resume`.
+    pub fn new_prewarmed(config: AudioPlaybackConfig) -> Result<Self> {
+        Self::build(None, config, true)
+    }
+
+    fn build(
+        crypto: Option<Arc<CryptoContext>>,
+        config: AudioPlaybackConfig,
+        paused: bool,
+    ) -> Result<Self> {
+        config.validate()?;
+        let channels = if config.stereo { 2 } else { 1 };
+        let mut queue = JitterBuffer::new(config.jitter_target_ms);
+        queue.set_channels(channels);
+        let (speaking_tx, speaking_rx) = mpsc::unbounded_channel();
+        let (output_rms_tx, output_rms_rx) = watch::channel(0.0f32);
+        Ok(Self {
+            decoder: Arc::new(Mutex::new(OpusDecoder::with_channels(channels)?)),
+            crypto: CryptoSlot::new(crypto),
+            sample_queue: Arc::new(Mutex::new(queue)),
+            running: Arc::new(AtomicBool::new(false)),
+            run_token: Arc::new(AtomicU64::new(0)),
+            controls: Arc::new(PlaybackControls {
+                output_volume_bits: AtomicU32::new(config.output_volume.to_bits()),
+                remote_volume_bits: AtomicU32::new(config.remote_volume.to_bits()),
+                limiter_enabled: AtomicBool::new(config.limiter_enabled),
+                compressor: Mutex::new(Compressor::new(config.compressor)),
+                output_resampler: Mutex::default(),
+                muted: AtomicBool::new(config.muted),
+                deafened: AtomicBool::new(config.deafened),
+                raw_mode: AtomicBool::new(false),
+                paused: AtomicBool::new(paused),
+                echo_reference: EchoReference::default(),
+                underruns: AtomicU64::new(0),
+                overruns: AtomicU64::new(0),
+                peers: RwLock::default(),
+                recording: RecordingTap::default(),
+                speaking_tx: Mutex::new(Some(speaking_tx)),
+                output_rms_tx,
+            }),
+            output_rms_bits: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+            stream: Arc::new(Mutex::new(StreamState::default())),
+            device_events: Arc::default(),
+            speaking_rx: Arc::new(Mutex::new(Some(speaking_rx))),
+            output_rms_rx: Arc::new(Mutex::new(Some(output_rms_rx))),
+        })
+    }
+
+    /// Apply every setting in `config` through the runtime setters.
+    pub fn apply_config(&self, config: &AudioPlaybackConfig) {
+        self.set_output_volume(config.output_volume);
+        self.set_remote_volume(config.remote_volume);
+        self.set_limiter_enabled(config.limiter_enabled);
+        self.set_muted(config.muted);
+        self.set_deafened(config.deafened);
+        self.set_jitter_target_ms(config.jitter_target_ms);
+        self.set_stereo(config.stereo);
+        self.set_compressor(config.compressor);
+    }
+
+    /// Process incoming encrypted packet
+    pub fn process_packet(&self, packet: AudioPacket) -> Result<()> {
+        if packet.is_keepalive() || self.is_paused() {
+            return Ok(());
+        }
+        if self.is_deafened() {
+            skip_packet(&self.stream, &packet);
+            return Ok(());
+        }
+
+        let crypto = self
+            .crypto
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("No session key for playback"))?;
+        decode_packet(
+            &crypto,
+            &self.decoder,
+            &self.stream,
+            &self.sample_queue,
+            packet,
+            &self.controls,
+            None,
+        )
+    }
+
+    /// Process an incoming packet from `peer_id`, a group call peer added
+    /// with `add_peer`
+    pub fn process_peer_packet(&self, peer_id: &str, packet: AudioPacket) -> Result<()> {
+        if packet.is_keepalive() || self.is_paused() {
+            return Ok(());
+        }
+
+        let peer = self
+            .controls
+            .peers
+            .read()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?
+            .get(peer_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown peer {}", peer_id))?;
+        if self.is_deafened() {
+            skip_packet(&peer.stream, &packet);
+            return Ok(());
+        }
+        let crypto = peer
+            .crypto
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("No sender key for {}", peer_id))?;
+        decode_packet(
+            &crypto,
+            &peer.decoder,
+            &peer.stream,
+            &peer.queue,
+            packet,
+            &self.controls,
+            Some(peer_id),
+        )
+    }
+
+    /// Mix `peer_id`'s stream, decrypted with its sender key, into the
+    /// output at `volume`. Adding the same peer again only swaps in the new
+    /// key, e.g. after that peer rekeyed.
+    pub fn add_peer(&self, peer_id: &str, crypto: Arc<CryptoContext>, volume: f32) -> Result<()> {
+        if let Some(peer) = self.peer(peer_id) {
+            peer.crypto.set(crypto);
+            return Ok(());
+        }
+        let (channels, target_ms) = {
+            let queue = self
+                .sample_queue
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Lock error"))?;
+            (queue.channels(), queue.base_latency_ms())
+        };
+        let mut queue = JitterBuffer::new(target_ms);
+        queue.set_channels(channels);
+        let peer = PeerStream {
+            crypto: CryptoSlot::new(Some(crypto)),
+            decoder: Mutex::new(OpusDecoder::with_channels(channels)?),
+            stream: Mutex::new(StreamState::default()),
+            queue: Mutex::new(queue),
+            volume_bits: AtomicU32::new(volume.clamp(0.0, MAX_VOLUME).to_bits()),
+        };
+        self.controls
+            .peers
+            .write()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?
+            .insert(peer_id.to_string(), Arc::new(peer));
+        Ok(())
+    }
+
+    /// Stop playing `peer_id`'s stream
+    pub fn remove_peer(&self, peer_id: &str) {
+        if let Ok(mut peers) = self.controls.peers.write() {
+            peers.remove(peer_id);
+        }
+    }
+
+    /// Volume of one group call peer; no-op for a peer not added
+    pub fn set_peer_volume(&self, peer_id: &str, volume: f32) {
+        let clamped = volume.clamp(0.0, MAX_VOLUME);
+        if let Some(peer) = self.peer(peer_id) {
+            peer.volume_bits.store(clamped.to_bits(), Ordering::SeqCst);
+        }
+    }
+
+    /// Receive statistics of one group call peer's stream
+    pub fn peer_stats(&self, peer_id: &str) -> Option<PlaybackStats> {
+        let peer = self.peer(peer_id)?;
+        let stream = peer.stream.lock().ok()?;
+        Some(stream.stats)
+    }
+
+    fn peer(&self, peer_id: &str) -> Option<Arc<PeerStream>> {
+        self.controls.peers.read().ok()?.get(peer_id).cloned()
+    }
+
+    fn peer_streams(&self) -> Vec<Arc<PeerStream>> {
+        self.controls
+            .peers
+            .read()
+            .map(|peers| peers.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn start(&self) -> Result<()> {
+        self.start_with_device(None).map(|_| ())
+    }
+
+    /// Start playback on the named device, or the default if None. Like
+    /// capture, falls back to the default once and returns whether it did.
+    pub fn start_with_device(&self, device_name: Option<&str>) -> Result<bool> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        self.device_events.lost.store(false, Ordering::SeqCst);
+
+        let sample_queue = self.sample_queue.clone();
+        let running = self.running.clone();
+        let run_token = self.run_token.clone();
+        let controls = self.controls.clone();
+        let output_rms_bits = self.output_rms_bits.clone();
+        let device_events = self.device_events.clone();
+        let device_name_owned = device_name.map(|s| s.to_string());
+        let current_token = run_token.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
+
+        let (startup_tx, startup_rx) = std::sync::mpsc::sync_channel(1);
+
+        thread::spawn(move || {
+            let host = cpal::default_host();
+            let candidates =
+                device_candidates(&host, AudioDirection::Output, device_name_owned.as_deref());
+            let mut first_error: Option<AudioDeviceError> = None;
+            let mut opened = None;
+
+            for (attempt, device) in candidates.iter().enumerate() {
+                let device_label = device.name().unwrap_or_else(|_| "unknown".to_string());
+                if attempt > 0 {
+                    tracing::warn!("Falling back to default output device '{}'", device_label);
+                }
+
+                let config = match choose_supported_format(device, AudioDirection::Output, CHANNELS)
+                {
+                    Ok(c) => c,
+                    Err(e) => {
+                        tracing::error!("Failed to pick output config: {}", e);
+                        first_error.get_or_insert(AudioDeviceError::from_details(
+                            AudioDirection::Output,
+                            &device_label,
+                            e.to_string(),
+                        ));
+                        continue;
+                    }
+                };
+
+                let sample_format = config.sample_format();
+                let stream_config: StreamConfig = config.into();
+                let output_channels = stream_config.channels as usize;
+                // Mixing, the compressor and the echo reference stay at
+                // 48 kHz; only the final frames are resampled
+                if let Ok(mut resampler) = controls.output_resampler.lock() {
+                    *resampler = OutputResampler::new(stream_config.sample_rate.0);
+                }
+
+                tracing::info!(
+                    "Using output device '{}' ({:?}, {}ch @ {}Hz)",
+                    device_label,
+                    sample_format,
+                    output_channels,
+                    stream_config.sample_rate.0
+                );
+
+                let stream_result = match sample_format {
+                    SampleFormat::F32 => {
+                        let sample_queue = sample_queue.clone();
+                        let controls = controls.clone();
+                        let output_rms_bits = output_rms_bits.clone();
+                        device.build_output_stream(
+                            &stream_config,
+                            move |data: &mut [f32], _info| {
+                                fill_output_f32(
+                                    data,
+                                    output_channels,
+                                    &sample_queue,
+                                    &controls,
+                                    &output_rms_bits,
+                                );
+                            },
+                            stream_error_callback(
+                                AudioDirection::Output,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::F64 => {
+                        let sample_queue = sample_queue.clone();
+                        let controls = controls.clone();
+                        let output_rms_bits = output_rms_bits.clone();
+                        device.build_output_stream(
+                            &stream_config,
+                            move |data: &mut [f64], _info| {
+                                fill_output_f64(
+                                    data,
+                                    output_channels,
+                                    &sample_queue,
+                                    &controls,
+                                    &output_rms_bits,
+                                );
+                            },
+                            stream_error_callback(
+                                AudioDirection::Output,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::I16 => {
+                        let sample_queue = sample_queue.clone();
+                        let controls = controls.clone();
+                        let output_rms_bits = output_rms_bits.clone();
+                        device.build_output_stream(
+                            &stream_config,
+                            move |data: &mut [i16], _info| {
+                                fill_output_i16(
+                                    data,
+                                    output_channels,
+                                    &sample_queue,
+                                    &controls,
+                                    &output_rms_bits,
+                                );
+                            },
+                            stream_error_callback(
+                                AudioDirection::Output,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::I32 => {
+                        let sample_queue = sample_queue.clone();
+                        let controls = controls.clone();
+                        let output_rms_bits = output_rms_bits.clone();
+                        device.build_output_stream(
+                            &stream_config,
+                            move |data: &mut [i32], _info| {
+                                fill_output_i32(
+                                    data,
+                                    output_channels,
+                                    &sample_queue,
+                                    &controls,
+                                    &output_rms_bits,
+                                );
+                            },
+                            stream_error_callback(
+                                AudioDirection::Output,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::U16 => {
+                        let sample_queue = sample_queue.clone();
+                        let controls = controls.clone();
+                        let output_rms_bits = output_rms_bits.clone();
+                        device.build_output_stream(
+                            &stream_config,
+                            move |data: &mut [u16], _info| {
+                                fill_output_u16(
+                                    data,
+                                    output_channels,
+                                    &sample_queue,
+                                    &controls,
+                                    &output_rms_bits,
+                                );
+                            },
+                            stream_error_callback(
+                                AudioDirection::Output,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    SampleFormat::U32 => {
+                        let sample_queue = sample_queue.clone();
+                        let controls = controls.clone();
+                        let output_rms_bits = output_rms_bits.clone();
+                        device.build_output_stream(
+                            &stream_config,
+                            move |data: &mut [u32], _info| {
+                                fill_output_u32(
+                                    data,
+                                    output_channels,
+                                    &sample_queue,
+                                    &controls,
+                                    &output_rms_bits,
+                                );
+                            },
+                            stream_error_callback(
+                                AudioDirection::Output,
+                                device_events.clone(),
+                                running.clone(),
+                                run_token.clone(),
+                                current_token,
+                            ),
+                            None,
+                        )
+                    }
+                    _ => {
+                        tracing::error!("Unsupported output sample format: {:?}", sample_format);
+                        Err(cpal::BuildStreamError::StreamConfigNotSupported)
+                    }
+                };
+
+                let stream = match stream_result {
+                    Ok(s) => s,
+                    Err(e) => {
+                        tracing::error!("Failed to build output stream: {}", e);
+                        first_error.get_or_insert(classify_build_error(
+                            AudioDirection::Output,
+                            &device_label,
+                            &e,
+                        ));
+                        continue;
+                    }
+                };
+
+                if let Err(e) = stream.play() {
+                    tracing::error!("Failed to play output stream: {}", e);
+                    first_error.get_or_insert(classify_play_error(
+                        AudioDirection::Output,
+                        &device_label,
+                        &e,
+                    ));
+                    continue;
+                }
+
+                let fell_back = device_name_owned
+                    .as_deref()
+                    .is_some_and(|requested| requested != device_label);
+                opened = Some((stream, fell_back));
+                break;
+            }
+
+            let _stream = match opened {
+                Some((stream, fell_back)) => {
+                    let _ = startup_tx.send(Ok(fell_back));
+                    stream
+                }
+                None => {
+                    let error = first_error.unwrap_or_else(|| AudioDeviceError::NotFound {
+                        direction: AudioDirection::Output,
+                        device: device_name_owned.unwrap_or_else(|| "default".to_string()),
+                    });
+                    tracing::error!("{}", error);
+                    if run_token.load(Ordering::SeqCst) == current_token {
+                        running.store(false, Ordering::SeqCst);
+                    }
+                    let _ = startup_tx.send(Err(error));
+                    return;
+                }
+            };
+
+            while running.load(Ordering::SeqCst)
+                && run_token.load(Ordering::SeqCst) == current_token
+            {
+                thread::sleep(std::time::Duration::from_millis(100));
+            }
+
+            if run_token.load(Ordering::SeqCst) == current_token {
+                running.store(false, Ordering::SeqCst);
+            }
+        });
+
+        wait_for_stream_start(&startup_rx, AudioDirection::Output)
+    }
+
+    pub fn set_output_volume(&self, volume: f32) {
+        let clamped = volume.clamp(0.0, MAX_VOLUME);
+        self.controls
+            .output_volume_bits
+            .store(clamped.to_bits(), Ordering::SeqCst);
+    }
+
+    pub fn output_volume(&self) -> f32 {
+        f32::from_bits(self.controls.output_volume_bits.load(Ordering::SeqCst))
+    }
+
+    pub fn set_remote_volume(&self, volume: f32) {
+        let clamped = volume.clamp(0.0, MAX_VOLUME);
+        self.controls
+            .remote_volume_bits
+            .store(clamped.to_bits(), Ordering::SeqCst);
+    }
+
+    pub fn remote_volume(&self) -> f32 {
+        f32::from_bits(self.controls.remote_volume_bits.load(Ordering::SeqCst))
+    }
+
+    pub fn set_limiter_enabled(&self, enabled: bool) {
+        self.controls
+            .limiter_enabled
+            .store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn limiter_enabled(&self) -> bool {
+        self.controls.limiter_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Compress the mix ahead of the limiter, evening out loud and quiet
+    /// passages. Disabled, it leaves samples untouched. Out-of-range
+    /// parameters are clamped.
+    pub fn set_compressor(&self, settings: CompressorSettings) {
+        if let Ok(mut compressor) = self.controls.compressor.lock() {
+            compressor.set_settings(settings);
+        }
+    }
+
+    pub fn compressor(&self) -> CompressorSettings {
+        self.controls
+            .compressor
+            .lock()
+            .map(|compressor| compressor.settings())
+            .unwrap_or_default()
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.controls.muted.store(muted, Ordering::SeqCst);
+    }
+
+    /// Send the mix, as played, to a recording; `None` stops
+    pub(crate) fn set_recording(&self, sink: Option<RecordingSink>) {
+        self.controls.recording.set(sink);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.controls.muted.load(Ordering::SeqCst)
+    }
+
+    /// Stop decrypting and decoding incoming audio, not just playing it, to
+    /// save CPU. Packets still move each stream's sequence on, so stats
+    /// count no loss. Buffered audio and decoder state are dropped on the
+    /// way in, so undeafening starts from a fresh buffer rather than
+    /// playing stale audio. Pair it with `set_muted`.
+    pub fn set_deafened(&self, deafened: bool) {
+        let was = self.controls.deafened.swap(deafened, Ordering::SeqCst);
+        if !deafened || was {
+            return;
+        }
+        flush_stream(
+            &self.decoder,
+            &self.stream,
+            &self.sample_queue,
+            &self.controls,
+            None,
+        );
+        if let Ok(peers) = self.controls.peers.read() {
+            for (peer_id, peer) in peers.iter() {
+                flush_stream(
+                    &peer.decoder,
+                    &peer.stream,
+                    &peer.queue,
+                    &self.controls,
+                    Some(peer_id),
+                );
+            }
+        }
+    }
+
+    pub fn is_deafened(&self) -> bool {
+        self.controls.deafened.load(Ordering::SeqCst)
+    }
+
+    /// Diagnostic "raw monitor": play decoded samples exactly as they
+    /// arrive from the peer, skipping output/remote volume and the
+    /// limiter. Mute and deafen still apply. Not meant for normal calls;
+    /// it is there to tell bad peer audio apart from our own processing.
+    pub fn set_raw_mode(&self, enabled: bool) {
+        let was = self.controls.raw_mode.swap(enabled, Ordering::SeqCst);
+        if enabled && !was {
+            tracing::warn!("Playback raw monitor mode enabled: volume and limiter bypassed");
+        } else if !enabled && was {
+            tracing::info!("Playback raw monitor mode disabled");
+        }
+    }
+
+    pub fn raw_mode(&self) -> bool {
+        self.controls.raw_mode.load(Ordering::SeqCst)
+    }
+
+    /// Attach the session key, e.g. to a pre-warmed playback.
+    pub fn set_crypto(&self, crypto: Arc<CryptoContext>) {
+        self.crypto.set(crypto);
+    }
+
+    /// Keep the output stream open but play silence and drop packets.
+    pub fn pause(&self) {
+        self.controls.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.controls.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.controls.paused.load(Ordering::SeqCst)
+    }
+
+    /// Everything this playback plays, as the reference for
+    /// [`AudioCapture`]'s echo canceller
+    pub fn echo_reference(&self) -> EchoReference {
+        self.controls.echo_reference.clone()
+    }
+
+    pub fn output_rms(&self) -> f32 {
+        f32::from_bits(self.output_rms_bits.load(Ordering::SeqCst))
+    }
+
+    /// Debounced remote speaking indicator, per stream. A decoded frame
+    /// counts as speech when it is louder than background noise, so DTX
+    /// and silence frames count as not speaking. Only transitions are sent.
+    pub fn take_remote_speaking_receiver(
+        &self,
+    ) -> Option<mpsc::UnboundedReceiver<RemoteSpeakingState>> {
+        self.speaking_rx.lock().unwrap().take()
+    }
+
+    /// Output level after volume, compressor and limiter, once per output
+    /// callback, for a meter of what the user hears. Reads 0 while muted,
+    /// deafened, paused or stopped.
+    pub fn take_output_rms_receiver(&self) -> Option<watch::Receiver<f32>> {
+        self.output_rms_rx.lock().unwrap().take()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Report the output device disappearing mid-stream on `tx`
+    pub fn set_device_event_sender(&self, tx: mpsc::UnboundedSender<AudioDeviceEvent>) {
+        self.device_events.set_sender(tx);
+    }
+
+    /// The device was lost and the stream stopped, with no `start` since
+    pub fn device_lost(&self) -> bool {
+        self.device_events.lost.load(Ordering::SeqCst)
+    }
+
+    /// Decoded samples waiting to be played.
+    pub fn queued_samples(&self) -> usize {
+        self.sample_queue
+            .lock()
+            .map(|queue| queue.len())
+            .unwrap_or(0)
+    }
+
+    pub fn buffer_stats(&self) -> PlaybackBufferStats {
+        let Ok(queue) = self.sample_queue.lock() else {
+            return PlaybackBufferStats {
+                queued_samples: 0,
+                peak_samples: 0,
+                target_samples: 0,
+                max_samples: PLAYBACK_QUEUE_MAX_SAMPLES,
+                allocated_bytes: 0,
+            };
+        };
+        PlaybackBufferStats {
+            queued_samples: queue.len(),
+            peak_samples: queue.peak(),
+            target_samples: queue.target,
+            max_samples: queue.max_samples(),
+            allocated_bytes: queue.allocated_bytes(),
+        }
+    }
+
+    /// Set how much audio playback buffers before it starts, clamped to
+    /// `MIN_JITTER_TARGET_MS..=MAX_JITTER_TARGET_MS`. Deeper rides out
+    /// burstier networks at the cost of latency.
+    pub fn set_jitter_target_ms(&self, ms: u32) {
+        if let Ok(mut queue) = self.sample_queue.lock() {
+            queue.set_target_latency_ms(ms);
+        }
+        for peer in self.peer_streams() {
+            if let Ok(mut queue) = peer.queue.lock() {
+                queue.set_target_latency_ms(ms);
+            }
+        }
+    }
+
+    /// Jitter buffer depth currently aimed for, including growth after late
+    /// frames
+    pub fn jitter_target_ms(&self) -> u32 {
+        self.sample_queue
+            .lock()
+            .map(|queue| queue.target_latency_ms())
+            .unwrap_or(0)
+    }
+
+    /// Decode to left/right instead of mono. Switching restarts the decoder
+    /// and drops what is buffered, a brief gap in the middle of a call.
+    pub fn set_stereo(&self, stereo: bool) {
+        let channels = if stereo { 2 } else { 1 };
+        let Ok(mut decoder) = self.decoder.lock() else {
+            return;
+        };
+        if decoder.channels() == channels {
+            return;
+        }
+        match OpusDecoder::with_channels(channels) {
+            Ok(fresh) => *decoder = fresh,
+            Err(e) => {
+                tracing::warn!("Failed to switch Opus decoder channels: {}", e);
+                return;
+            }
+        }
+        if let Ok(mut queue) = self.sample_queue.lock() {
+            queue.set_channels(channels);
+        }
+        for peer in self.peer_streams() {
+            reset_decoder(&peer.decoder, channels);
+            if let Ok(mut queue) = peer.queue.lock() {
+                queue.set_channels(channels);
+            }
+        }
+        tracing::info!("Audio playback stereo: {}", stereo);
+    }
+
+    pub fn stereo(&self) -> bool {
+        self.decoder
+            .lock()
+            .map(|decoder| decoder.channels() == 2)
+            .unwrap_or(false)
+    }
+
+    pub fn last_sequence(&self) -> Option<u32> {
+        self.stream.lock().ok().and_then(|stream| stream.last_seq)
+    }
+
+    pub fn stats(&self) -> PlaybackStats {
+        self.stream
+            .lock()
+            .map(|stream| stream.stats)
+            .unwrap_or_default()
+    }
+
+    /// Loss on the incoming stream over the last [`LOSS_WINDOW`], in
+    /// percent; 0 before anything arrived
+    pub fn packet_loss_percent(&self) -> f32 {
+        self.stream
+            .lock()
+            .map(|stream| stream.loss_percent(Instant::now()))
+            .unwrap_or(0.0)
+    }
+
+    /// Fill `out` with mono samples the way the output callback does, to
+    /// drive playback at its real rate without a device
+    #[cfg(any(test, feature = "netsim"))]
+    pub fn pull_output(&self, out: &mut [f32]) {
+        fill_output_f32(
+            out,
+            1,
+            &self.sample_queue,
+            &self.controls,
+            &self.output_rms_bits,
+        );
+    }
+
+    /// Output callbacks that found the jitter buffer empty since the last
+    /// `stop`, i.e. playback starved
+    pub fn underrun_count(&self) -> u64 {
+        self.controls.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Times queued audio was dropped because the jitter buffer held too
+    /// much, since the last `stop`
+    pub fn overrun_count(&self) -> u64 {
+        self.controls.overruns.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.run_token.fetch_add(1, Ordering::SeqCst);
+        self.reset();
+        self.controls.underruns.store(0, Ordering::Relaxed);
+        self.controls.overruns.store(0, Ordering::Relaxed);
+    }
+
+    /// Drop all per-stream state so the next stream starts from a fresh
+    /// decoder instead of carrying over history from the previous call.
+    /// Group call peers stay added, each starting over too.
+    pub fn reset(&self) {
+        reset_stream(&self.decoder, &self.stream, &self.sample_queue);
+        for peer in self.peer_streams() {
+            reset_stream(&peer.decoder, &peer.stream, &peer.queue);
+        }
+        self.output_rms_bits
+            .store(0.0f32.to_bits(), Ordering::SeqCst);
+        self.controls.output_rms_tx.send_replace(0.0);
+        self.controls.echo_reference.clear();
+        if let Ok(mut compressor) = self.controls.compressor.lock() {
+            compressor.reset();
+        }
+    }
+}
+
+/// Fresh decoder of `channels` channels, e.g. for a stereo switch
+fn reset_decoder(decoder: &Mutex<OpusDecoder>, channels: usize) {
+    if let Ok(mut decoder) = decoder.lock() {
+        match OpusDecoder::with_channels(channels) {
+            Ok(fresh) => *decoder = fresh,
+            Err(e) => tracing::warn!("Failed to reset Opus decoder: {}", e),
+        }
+    }
+}
+
+/// Count a packet that arrived while deafened without decoding it
+fn skip_packet(stream: &Mutex<StreamState>, packet: &AudioPacket) {
+    if let Ok(mut stream) = stream.lock() {
+        stream.record_skipped(packet.seq, Instant::now());
+    }
+}
+
+/// Drop what a stream has buffered and its decoder state, keeping its
+/// position and stats, e.g. on deafen
+fn flush_stream(
+    decoder: &Mutex<OpusDecoder>,
+    stream: &Mutex<StreamState>,
+    queue: &Mutex<JitterBuffer>,
+    controls: &PlaybackControls,
+    peer_id: Option<&str>,
+) {
+    if let Ok(mut queue) = queue.lock() {
+        queue.clear();
+    }
+    let channels = decoder
+        .lock()
+        .map(|decoder| decoder.channels())
+        .unwrap_or(1);
+    reset_decoder(decoder, channels);
+    let stopped = stream
+        .lock()
+        .ok()
+        .and_then(|mut stream| stream.speaking.reset());
+    if let Some(change) = stopped {
+        controls.report_speaking(peer_id, change);
+    }
+}
+
+/// Start a stream over: empty buffer, fresh decoder, no history
+fn reset_stream(
+    decoder: &Mutex<OpusDecoder>,
+    stream: &Mutex<StreamState>,
+    queue: &Mutex<JitterBuffer>,
+) {
+    if let Ok(mut queue) = queue.lock() {
+        queue.clear();
+    }
+    let channels = decoder
+        .lock()
+        .map(|decoder| decoder.channels())
+        .unwrap_or(1);
+    reset_decoder(decoder, channels);
+    if let Ok(mut stream) = stream.lock() {
+        *stream = StreamState::default();
+    }
+}
+
+fn apply_limiter(sample: f32) -> f32 {
+    (sample * 1.6).tanh() / 1.6_f32.tanh()
+}
+
+/// Output volume, compressor and limiter applied to the mixed remote audio
+fn shape_output(
+    left: f32,
+    right: f32,
+    controls: &PlaybackControls,
+    compressor: &mut Compressor,
+) -> (f32, f32) {
+    let output_volume = f32::from_bits(controls.output_volume_bits.load(Ordering::Relaxed));
+    let (mut left, mut right) = compressor.process(left * output_volume, right * output_volume);
+    if controls.limiter_enabled.load(Ordering::Relaxed) {
+        left = apply_limiter(left);
+        right = apply_limiter(right);
+    }
+    (left.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0))
+}
+
+/// A group call peer's buffer, locked for one output callback
+struct PeerMix<'a> {
+    queue: MutexGuard<'a, JitterBuffer>,
+    volume: f32,
+}
+
+/// Next frame of one source as left/right at `volume`; a mono source plays
+/// on both sides
+fn source_frame(queue: &mut impl PlaybackSource, volume: f32) -> (f32, f32) {
+    let left = queue.next_sample() as f32 / 32767.0 * volume;
+    if queue.channels() < 2 {
+        return (left, left);
+    }
+    (left, queue.next_sample() as f32 / 32767.0 * volume)
+}
+
+fn store_output_rms(
+    output_rms_bits: &Arc<AtomicU32>,
+    controls: &PlaybackControls,
+    squared_sum: f32,
+    sample_count: usize,
+) {
+    let rms = if sample_count == 0 {
+        0.0
+    } else {
+        (squared_sum / sample_count as f32).sqrt()
+    };
+    output_rms_bits.store(rms.to_bits(), Ordering::Relaxed);
+    controls.output_rms_tx.send_replace(rms);
+}
+
+/// Next output frame as left/right: the main stream plus every group call
+/// peer, each at its own volume
+fn playback_frame_from_queue(
+    queue: &mut impl PlaybackSource,
+    peers: &mut [PeerMix<'_>],
+    controls: &PlaybackControls,
+    compressor: &mut Compressor,
+) -> (f32, f32) {
+    if controls.muted.load(Ordering::Relaxed)
+        || controls.deafened.load(Ordering::Relaxed)
+        || controls.paused.load(Ordering::Relaxed)
+    {
+        return (0.0, 0.0);
+    }
+
+    let raw = controls.raw_mode.load(Ordering::Relaxed);
+    let remote_volume = if raw {
+        1.0
+    } else {
+        f32::from_bits(controls.remote_volume_bits.load(Ordering::Relaxed))
+    };
+    let (mut left, mut right) = source_frame(queue, remote_volume);
+    for peer in peers.iter_mut() {
+        let volume = if raw { 1.0 } else { peer.volume };
+        let (peer_left, peer_right) = source_frame(&mut *peer.queue, volume);
+        left += peer_left;
+        right += peer_right;
+    }
+    if raw {
+        // A single decoded stream is already within [-1, 1]; only a mix of
+        // several can leave it
+        return (left.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0));
+    }
+    shape_output(left, right, controls, compressor)
+}
+
+/// Fill an interleaved output buffer. Stereo sources map to the first two
+/// device channels; a mono device gets their average, and any extra device
+/// channels get the same average.
+fn fill_output<T, S: PlaybackSource>(
+    data: &mut [T],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+    convert: impl Fn(f32) -> T,
+) {
+    let mut queue = match sample_queue.lock() {
+        Ok(q) => q,
+        Err(_) => return,
+    };
+    let peers = controls.peers.read().ok();
+    let mut peer_mix: Vec<PeerMix<'_>> = peers
+        .iter()
+        .flat_map(|peers| peers.values())
+        .filter_map(|peer| {
+            Some(PeerMix {
+                queue: peer.queue.lock().ok()?,
+                volume: f32::from_bits(peer.volume_bits.load(Ordering::Relaxed)),
+            })
+        })
+        .collect();
+    let Ok(mut compressor) = controls.compressor.lock() else {
+        return;
+    };
+    let Ok(mut resampler) = controls.output_resampler.lock() else {
+        return;
+    };
+
+    let mut sq_sum = 0.0f32;
+    let mut count = 0usize;
+    // At 48 kHz, as pulled from the mix, whatever the device rate
+    let mut played = Vec::with_capacity(data.len() / channels.max(1));
+
+    for frame in data.chunks_mut(channels.max(1)) {
+        let (left, right) = resampler.next_frame(|| {
+            let (left, right) =
+                playback_frame_from_queue(&mut *queue, &mut peer_mix, controls, &mut compressor);
+            sq_sum += (left * left + right * right) * 0.5;
+            count += 1;
+            played.push((left + right) * 0.5);
+            (left, right)
+        });
+        let mid = (left + right) * 0.5;
+        match frame {
+            [mono] => *mono = convert(mid),
+            [out_left, out_right, rest @ ..] => {
+                *out_left = convert(left);
+                *out_right = convert(right);
+                for out in rest {
+                    *out = convert(mid);
+                }
+            }
+            [] => {}
+        }
+    }
+
+    let mut underran = queue.take_underrun();
+    let mut overran = queue.take_overrun();
+    for peer in &mut peer_mix {
+        underran |= peer.queue.take_underrun();
+        overran |= peer.queue.take_overrun();
+    }
+    if underran {
+        controls.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+    if overran {
+        controls.overruns.fetch_add(1, Ordering::Relaxed);
+    }
+    drop(resampler);
+    drop(compressor);
+    drop(peer_mix);
+    drop(peers);
+    drop(queue);
+
+    controls.recording.push_with(RecordingSide::Remote, || {
+        played
+            .iter()
+            .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
+            .collect()
+    });
+    controls.echo_reference.push(&played);
+    store_output_rms(output_rms_bits, controls, sq_sum, count);
+}
+
+fn fill_output_f32<S: PlaybackSource>(
+    data: &mut [f32],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+) {
+    fill_output(
+        data,
+        channels,
+        sample_queue,
+        controls,
+        output_rms_bits,
+        |value| value,
+    );
+}
+
+fn fill_output_f64<S: PlaybackSource>(
+    data: &mut [f64],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+) {
+    fill_output(
+        data,
+        channels,
+        sample_queue,
+        controls,
+        output_rms_bits,
+        |value| value as f64,
+    );
+}
+
+fn fill_output_i16<S: PlaybackSource>(
+    data: &mut [i16],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+) {
+    fill_output(
+        data,
+        channels,
+        sample_queue,
+        controls,
+        output_rms_bits,
+        |value| (value * 32767.0) as i16,
+    );
+}
+
+fn fill_output_i32<S: PlaybackSource>(
+    data: &mut [i32],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+) {
+    fill_output(
+        data,
+        channels,
+        sample_queue,
+        controls,
+        output_rms_bits,
+        |value| (value * 2_147_483_647.0) as i32,
+    );
+}
+
+fn fill_output_u16<S: PlaybackSource>(
+    data: &mut [u16],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+) {
+    fill_output(
+        data,
+        channels,
+        sample_queue,
+        controls,
+        output_rms_bits,
+        |value| (((value * 0.5 + 0.5).clamp(0.0, 1.0)) * 65535.0) as u16,
+    );
+}
+
+fn fill_output_u32<S: PlaybackSource>(
+    data: &mut [u32],
+    channels: usize,
+    sample_queue: &Arc<Mutex<S>>,
+    controls: &PlaybackControls,
+    output_rms_bits: &Arc<AtomicU32>,
+) {
+    fill_output(
+        data,
+        channels,
+        sample_queue,
+        controls,
+        output_rms_bits,
+        |value| (((value * 0.5 + 0.5).clamp(0.0, 1.0)) * 4_294_967_295.0) as u32,
+    );
+}
+
+pub fn calculate_rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
         return 0.0;
     }
-    let sum: f32 = samples.iter().map(|&s| s * s).sum();
-    (sum / samples.len() as f32).sqrt()
-}
+    let sum: f32 = samples.iter().map(|&s| s * s).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+/// Convert RMS to dB
+pub fn rms_to_db(rms: f32) -> f32 {
+    if rms <= 0.0 {
+        return -100.0;
+    }
+    20.0 * rms.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn downmix_stereo_f32_to_mono() {
+        let stereo = vec![1.0f32, -1.0f32, 0.5f32, 0.5f32];
+        let mono = downmix_f32(&stereo, 2, 1);
+        assert_eq!(mono.len(), 2);
+        assert!((mono[0] - 0.0).abs() < 1e-6);
+        assert!((mono[1] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn downmix_keeps_two_channels_for_stereo_and_doubles_mono() {
+        let quad = vec![0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+        assert_eq!(downmix_f32(&quad, 4, 2), vec![0.1, 0.2, 0.5, 0.6]);
+        assert_eq!(
+            downmix_i16_to_f32(&[16384, -16384], 1, 2),
+            vec![0.5, 0.5, -0.5, -0.5]
+        );
+    }
+
+    #[test]
+    fn resample_44100_to_48000_produces_expected_count() {
+        let input = vec![0.0f32; 441]; // ~10ms at 44.1kHz
+        let mut phase = 0;
+        let out = resample_to_48k(&input, 1, 44_100, &mut phase);
+        assert!((470..=490).contains(&out.len()));
+    }
+
+    #[test]
+    fn resample_output_count_does_not_drift_over_long_calls() {
+        // Odd callback sizes so chunk boundaries never line up with the ratio
+        for (input_rate, chunk) in [
+            (44_100u32, 441usize),
+            (44_100, 509),
+            (16_000, 317),
+            (96_000, 1021),
+        ] {
+            let input = vec![0.25f32; chunk];
+            let mut phase = 0;
+            let mut produced = 0u64;
+            let mut consumed = 0u64;
+
+            // About 20 minutes of audio at each rate
+            while consumed < input_rate as u64 * 60 * 20 {
+                produced += resample_to_48k(&input, 1, input_rate, &mut phase).len() as u64;
+                consumed += chunk as u64;
+            }
+
+            let expected = (consumed * SAMPLE_RATE as u64).div_ceil(input_rate as u64);
+            assert_eq!(
+                produced, expected,
+                "{} Hz in chunks of {}",
+                input_rate, chunk
+            );
+            assert!(phase < input_rate as u64);
+        }
+    }
+
+    #[test]
+    fn late_capture_callbacks_are_reported_as_jitter() {
+        let mut state = CapturePipelineState::new();
+        let start = Instant::now();
+        let ten_ms = Duration::from_millis(10);
+
+        // 480 samples at 48 kHz is 10 ms of audio per callback
+        assert_eq!(check_callback_timing(&mut state, start, 480, 48_000), None);
+        assert_eq!(
+            check_callback_timing(&mut state, start + ten_ms, 480, 48_000),
+            None
+        );
+        // Slightly late is within the margin
+        assert_eq!(
+            check_callback_timing(&mut state, start + ten_ms * 3, 480, 48_000),
+            None
+        );
+
+        let stalled = start + ten_ms * 3 + Duration::from_millis(40);
+        assert_eq!(
+            check_callback_timing(&mut state, stalled, 480, 48_000),
+            Some(CaptureJitter {
+                interval: Duration::from_millis(40),
+                expected: ten_ms,
+            })
+        );
+    }
+
+    #[test]
+    fn encode_fits_high_bitrate_stereo_frames() {
+        let mut encoder =
+            OpusEncoder::with_config(Channels::Stereo, Bitrate::BitsPerSecond(510_000)).unwrap();
+        encoder.encoder.set_vbr(false).unwrap();
+
+        // 60 ms of interleaved white noise, the hardest input to compress
+        let mut state = 0x1234_5678u32;
+        let frame: Vec<i16> = (0..2880 * 2)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 16) as i16
+            })
+            .collect();
+
+        let packet = encoder.encode(&frame).unwrap();
+        assert!(
+            packet.len() > 1024,
+            "expected a packet past the old 1024 byte buffer, got {}",
+            packet.len()
+        );
+
+        // Odd sample counts are rejected with a clear error, not passed to Opus
+        let err = encoder.encode(&frame[..1001]).unwrap_err();
+        assert!(err.to_string().contains("Invalid Opus frame"));
+    }
+
+    fn playback_controls(volume: f32, limiter: bool) -> PlaybackControls {
+        PlaybackControls {
+            output_volume_bits: AtomicU32::new(volume.to_bits()),
+            remote_volume_bits: AtomicU32::new(1.0f32.to_bits()),
+            limiter_enabled: AtomicBool::new(limiter),
+            compressor: Mutex::default(),
+            output_resampler: Mutex::default(),
+            muted: AtomicBool::new(false),
+            deafened: AtomicBool::new(false),
+            raw_mode: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            echo_reference: EchoReference::default(),
+            underruns: AtomicU64::new(0),
+            overruns: AtomicU64::new(0),
+            peers: RwLock::default(),
+            recording: RecordingTap::default(),
+            speaking_tx: Mutex::new(None),
+            output_rms_tx: watch::channel(0.0).0,
+        }
+    }
+
+    #[test]
+    fn raw_mode_bypasses_volume_and_limiter_but_not_mute() {
+        let controls = playback_controls(0.5, true);
+        let sample = 30_000i16;
+        let expected = sample as f32 / 32767.0;
+
+        let play = |controls: &PlaybackControls| {
+            playback_frame_from_queue(
+                &mut VecDeque::from(vec![sample]),
+                &mut [],
+                controls,
+                &mut Compressor::default(),
+            )
+            .0
+        };
+
+        let processed = play(&controls);
+        assert!((processed - expected).abs() > 0.05);
+
+        controls.raw_mode.store(true, Ordering::Relaxed);
+        let raw = play(&controls);
+        assert!((raw - expected).abs() < 1e-6);
+
+        controls.muted.store(true, Ordering::Relaxed);
+        let muted = play(&controls);
+        assert_eq!(muted, 0.0);
+    }
+
+    #[test]
+    fn unsupported_device_formats_fall_back_to_a_supported_one() {
+        let range = |channels: u16, min: u32, max: u32, format: SampleFormat| {
+            SupportedStreamConfigRange::new(
+                channels,
+                cpal::SampleRate(min),
+                cpal::SampleRate(max),
+                cpal::SupportedBufferSize::Unknown,
+                format,
+            )
+        };
+        // Pro interface whose native format we cannot convert
+        let native =
+            range(2, 44_100, 192_000, SampleFormat::I64).with_sample_rate(cpal::SampleRate(96_000));
+        let ranges = [
+            range(2, 44_100, 192_000, SampleFormat::I64),
+            range(2, 44_100, 192_000, SampleFormat::I32),
+        ];
+        let chosen = choose_config(&ranges, Some(&native), AudioDirection::Input, 1)
+            .expect("supported config");
+        assert_eq!(chosen.sample_format(), SampleFormat::I32);
+        assert_eq!(chosen.sample_rate().0, SAMPLE_RATE);
+
+        // No 48 kHz: the nearest rate of a supported range, not the default
+        let ranges = [
+            range(2, 96_000, 96_000, SampleFormat::I64),
+            range(2, 44_100, 44_100, SampleFormat::F32),
+        ];
+        let chosen = choose_config(&ranges, Some(&native), AudioDirection::Output, CHANNELS)
+            .expect("supported config");
+        assert_eq!(chosen.sample_format(), SampleFormat::F32);
+        assert_eq!(chosen.sample_rate().0, 44_100);
+
+        // 8-bit is fine for capture only
+        let ranges = [range(1, 8_000, 48_000, SampleFormat::U8)];
+        assert!(choose_config(&ranges, None, AudioDirection::Input, 1).is_some());
+        assert!(choose_config(&ranges, None, AudioDirection::Output, CHANNELS).is_none());
+    }
+
+    #[test]
+    fn output_resampler_pulls_48k_frames_at_the_device_rate() {
+        let mut resampler = OutputResampler::new(44_100);
+        let mut pulled = 0u64;
+        let mut pull = || {
+            pulled += 1;
+            (pulled as f32, 0.0)
+        };
+        // One second at 44.1 kHz consumes one second at 48 kHz, plus the
+        // frame pulled ahead
+        let first = resampler.next_frame(&mut pull);
+        for _ in 1..44_100 {
+            resampler.next_frame(&mut pull);
+        }
+        assert_eq!(first.0, 1.0);
+        assert_eq!(pulled, 48_001);
+
+        let mut same_rate = OutputResampler::new(SAMPLE_RATE);
+        let mut count = 0;
+        for _ in 0..100 {
+            same_rate.next_frame(|| {
+                count += 1;
+                (0.0, 0.0)
+            });
+        }
+        assert_eq!(count, 100);
+    }
+
+    #[test]
+    fn fill_output_duplicates_mono_to_stereo() {
+        let queue = Arc::new(Mutex::new(VecDeque::from(vec![1000i16, -1000i16])));
+        let mut out = vec![0.0f32; 4]; // 2 frames, 2 channels
+        let controls = playback_controls(1.0, false);
+        let output_rms = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        fill_output_f32(&mut out, 2, &queue, &controls, &output_rms);
+
+        assert!((out[0] - out[1]).abs() < 1e-6);
+        assert!((out[2] - out[3]).abs() < 1e-6);
+        assert!(out[0] > 0.0);
+        assert!(out[2] < 0.0);
+    }
+
+    #[test]
+    fn output_meter_reports_each_callback_and_zero_when_deafened() {
+        let (output_rms_tx, mut output_rms_rx) = watch::channel(0.0f32);
+        let controls = PlaybackControls {
+            output_rms_tx,
+            ..playback_controls(1.0, false)
+        };
+        let output_rms = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let queue = Arc::new(Mutex::new(VecDeque::from(vec![16_384i16; 8])));
+        let mut out = vec![0.0f32; 4];
+
+        fill_output_f32(&mut out, 1, &queue, &controls, &output_rms);
+        assert!(output_rms_rx.has_changed().unwrap());
+        assert!((*output_rms_rx.borrow_and_update() - 0.5).abs() < 1e-3);
+
+        controls.deafened.store(true, Ordering::Relaxed);
+        fill_output_f32(&mut out, 1, &queue, &controls, &output_rms);
+        assert_eq!(*output_rms_rx.borrow_and_update(), 0.0);
+        assert_eq!(out, vec![0.0; 4]);
+    }
+
+    #[test]
+    fn mono_and_stereo_peers_decode_each_others_packets() {
+        let stereo_frames: Vec<Vec<i16>> = voiced_frames(10)
+            .iter()
+            .map(|frame| frame.iter().flat_map(|&left| [left, 0]).collect())
+            .collect();
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        encoder.set_channels(2).expect("stereo encoder");
+        let packets: Vec<Vec<u8>> = stereo_frames
+            .iter()
+            .map(|frame| encoder.encode(frame).expect("encode"))
+            .collect();
+
+        let mut stereo = OpusDecoder::with_channels(2).expect("stereo decoder");
+        let mut mono = OpusDecoder::with_channels(1).expect("mono decoder");
+        let (mut left, mut right) = (0.0f64, 0.0f64);
+        for packet in &packets {
+            let decoded = stereo.decode(packet).expect("stereo decode");
+            assert_eq!(decoded.len(), 2 * FRAME_SIZE);
+            for pair in decoded.chunks_exact(2) {
+                left += f64::from(pair[0]).powi(2);
+                right += f64::from(pair[1]).powi(2);
+            }
+            assert_eq!(mono.decode(packet).expect("downmixed").len(), FRAME_SIZE);
+        }
+        assert!(left > right * 100.0, "left {} right {}", left, right);
+
+        let mut mono_encoder = OpusEncoder::new().expect("opus encoder");
+        let mono_packet = mono_encoder.encode(&voiced_frames(1)[0]).expect("encode");
+        let mut fresh = OpusDecoder::with_channels(2).expect("stereo decoder");
+        let upmixed = fresh.decode(&mono_packet).expect("upmixed");
+        assert_eq!(upmixed.len(), 2 * FRAME_SIZE);
+        assert!(upmixed.chunks_exact(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn stereo_playback_fills_left_and_right() {
+        let mut buffer = JitterBuffer::new(20);
+        buffer.set_channels(2);
+        assert_eq!(buffer.target_latency_ms(), 20);
+        let frame: Vec<i16> = (0..FRAME_SIZE).flat_map(|_| [8000, -8000]).collect();
+        buffer.push(0, frame);
+        assert_eq!(buffer.len(), 2 * FRAME_SIZE);
+
+        let queue = Arc::new(Mutex::new(buffer));
+        let controls = playback_controls(1.0, false);
+        let output_rms = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+        let mut out = vec![0.0f32; 6]; // 2 frames, 3 channels
+        fill_output_f32(&mut out, 3, &queue, &controls, &output_rms);
+        assert!(out[0] > 0.0 && out[1] < 0.0);
+        assert!(out[2].abs() < 1e-6, "extra channel gets the average");
+
+        let mut mono_out = vec![1.0f32; 2];
+        fill_output_f32(&mut mono_out, 1, &queue, &controls, &output_rms);
+        assert!(mono_out.iter().all(|sample| sample.abs() < 1e-6));
+    }
+
+    fn pull_frame(buffer: &mut JitterBuffer) -> Vec<i16> {
+        (0..FRAME_SIZE).map(|_| buffer.next_sample()).collect()
+    }
+
+    #[test]
+    fn jitter_buffer_reorders_from_the_first_frame_once_primed() {
+        let mut buffer = JitterBuffer::new(40);
+        assert!(buffer.push(10, vec![10; FRAME_SIZE]));
+        // Below the two-frame target: silence, nothing consumed
+        assert_eq!(pull_frame(&mut buffer), vec![0; FRAME_SIZE]);
+        assert_eq!(buffer.len(), FRAME_SIZE);
+
+        // Frame 9 precedes the baseline, so it arrived too late to play
+        assert!(!buffer.push(9, vec![9; FRAME_SIZE]));
+        assert!(buffer.push(12, vec![12; FRAME_SIZE]));
+        assert!(buffer.push(11, vec![11; FRAME_SIZE]));
+
+        for expected in [10, 11, 12] {
+            assert_eq!(pull_frame(&mut buffer), vec![expected; FRAME_SIZE]);
+        }
+        assert_eq!(buffer.len(), 0);
+    }
+
+    #[test]
+    fn jitter_buffer_drops_duplicates_across_seq_wraparound() {
+        let mut buffer = JitterBuffer::new(40);
+        let first = u32::MAX - 1;
+        assert!(buffer.push(first, vec![1; FRAME_SIZE]));
+        assert!(buffer.push(0, vec![3; FRAME_SIZE]));
+        assert!(!buffer.push(0, vec![-3; FRAME_SIZE]));
+        assert!(buffer.push(u32::MAX, vec![2; FRAME_SIZE]));
+        assert!(buffer.push(1, vec![4; FRAME_SIZE]));
+
+        assert_eq!(pull_frame(&mut buffer), vec![1; FRAME_SIZE]);
+        // Already played: a replay is not late audio and leaves the target alone
+        assert!(!buffer.push(first, vec![1; FRAME_SIZE]));
+        assert_eq!(buffer.target_latency_ms(), 40);
+        for expected in [2, 3, 4] {
+            assert_eq!(pull_frame(&mut buffer), vec![expected; FRAME_SIZE]);
+        }
+    }
+
+    #[test]
+    fn jitter_buffer_restarts_on_a_gap_larger_than_it_holds() {
+        let mut buffer = JitterBuffer::new(40);
+        buffer.push(5, vec![5; FRAME_SIZE]);
+        buffer.push(6, vec![6; FRAME_SIZE]);
+
+        let far = 5 + 1_000;
+        assert!(buffer.push(far, vec![7; FRAME_SIZE]));
+        assert_eq!(buffer.len(), FRAME_SIZE);
+        assert!(buffer.push(far + 1, vec![8; FRAME_SIZE]));
+        assert_eq!(pull_frame(&mut buffer), vec![7; FRAME_SIZE]);
+        assert_eq!(pull_frame(&mut buffer), vec![8; FRAME_SIZE]);
+    }
+
+    #[test]
+    fn jitter_buffer_conceals_a_missing_frame_and_grows_when_it_arrives_late() {
+        let mut buffer = JitterBuffer::new(40);
+        buffer.push(0, vec![1; FRAME_SIZE]);
+        buffer.push(1, vec![2; FRAME_SIZE]);
+        assert_eq!(pull_frame(&mut buffer), vec![1; FRAME_SIZE]);
+        assert_eq!(pull_frame(&mut buffer), vec![2; FRAME_SIZE]);
+
+        buffer.push(3, vec![4; FRAME_SIZE]);
+        assert_eq!(pull_frame(&mut buffer), vec![0; FRAME_SIZE]);
+        assert!(!buffer.push(2, vec![3; FRAME_SIZE]));
+        assert_eq!(buffer.target_latency_ms(), 50);
+        assert_eq!(pull_frame(&mut buffer), vec![4; FRAME_SIZE]);
+
+        buffer.set_target_latency_ms(1_000);
+        assert_eq!(buffer.target_latency_ms(), MAX_JITTER_TARGET_MS);
+        buffer.clear();
+        assert_eq!(buffer.target_latency_ms(), MAX_JITTER_TARGET_MS);
+    }
+
+    #[test]
+    fn playback_counts_underruns_and_overruns() {
+        let mut buffer = JitterBuffer::new(20);
+        buffer.push(0, vec![1; FRAME_SIZE]);
+        let queue = Arc::new(Mutex::new(buffer));
+        let controls = playback_controls(1.0, false);
+        let output_rms = Arc::new(AtomicU32::new(0.0f32.to_bits()));
+
+        let mut out = vec![0.0f32; FRAME_SIZE];
+        fill_output_f32(&mut out, 1, &queue, &controls, &output_rms);
+        assert_eq!(controls.underruns.load(Ordering::Relaxed), 0);
+        // Runs dry mid-callback, then waits to re-prime without counting again
+        let mut out = vec![0.0f32; FRAME_SIZE / 2];
+        for _ in 0..3 {
+            fill_output_f32(&mut out, 1, &queue, &controls, &output_rms);
+        }
+        assert_eq!(controls.underruns.load(Ordering::Relaxed), 1);
+
+        let mut buffer = queue.lock().unwrap();
+        let max_frames = (buffer.max_samples() / FRAME_SIZE) as u32;
+        for seq in 1..=max_frames + 1 {
+            buffer.push(seq, vec![2; FRAME_SIZE]);
+        }
+        assert!(buffer.take_overrun());
+        assert!(!buffer.take_overrun());
+    }
+
+    #[test]
+    fn frame_duration_changes_between_callbacks_keep_every_sample() {
+        let ctx = Arc::new(
+            crate::crypto::SenderKey::generate()
+                .unwrap()
+                .context()
+                .unwrap(),
+        );
+        let config = AudioCaptureConfig {
+            noise_suppression: false,
+            aec_enabled: false,
+            agc_enabled: false,
+            noise_gate_enabled: false,
+            ..Default::default()
+        };
+        let mut chain = DspChain::new(ctx, config).expect("dsp chain");
+        let input: Vec<f32> = (0..FRAME_SIZE * 2)
+            .map(|i| ((i as f32 * 2.0 * PI) / 96.0).sin() * 0.2)
+            .collect();
+        let feed = |chain: &mut DspChain, samples: usize| {
+            chain.process(&input[..samples], SAMPLE_RATE).len()
+        };
+
+        // 20 ms frames leave 480 samples over; at 10 ms they and the next
+        // 480 make two frames
+        assert_eq!(feed(&mut chain, 1440), 1);
+        chain.capture().set_frame_duration_ms(10.0).unwrap();
+        assert_eq!(feed(&mut chain, 480), 2);
+
+        // A 60 ms frame waits for enough samples
+        chain.capture().set_frame_duration_ms(60.0).unwrap();
+        assert_eq!(feed(&mut chain, 960), 0);
+        assert_eq!(feed(&mut chain, 1920), 1);
+
+        chain.capture().set_frame_duration_ms(2.5).unwrap();
+        assert_eq!(feed(&mut chain, 360), 3);
+        assert!(chain.capture().set_frame_duration_ms(15.0).is_err());
+        assert_eq!(chain.capture().frame_duration_ms(), 2.5);
+        assert_eq!(chain.capture().stats().frames_encoded, 7);
+    }
+
+    #[test]
+    fn dsp_chain_runs_recorded_audio_like_a_live_capture() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = Arc::new(alice.derive_shared_secret(&bob_pub).expect("sender ctx"));
+        let receiver_ctx = bob.derive_shared_secret(&alice_pub).expect("receiver ctx");
+        let mut chain = DspChain::new(sender_ctx, AudioCaptureConfig::default()).expect("chain");
+        let mut speaking_rx = chain.capture().take_speaking_receiver().expect("speaking");
+
+        // Half a second of silence then half a second of a 440 Hz tone,
+        // recorded at 44.1 kHz and fed in 10 ms callbacks
+        let input: Vec<f32> = (0..44_100)
+            .map(|i| {
+                if i < 22_050 {
+                    0.0
+                } else {
+                    (i as f32 * 2.0 * PI * 440.0 / 44_100.0).sin() * 0.3
+                }
+            })
+            .collect();
+        let packets: Vec<AudioPacket> = input
+            .chunks(441)
+            .flat_map(|block| chain.process(block, 44_100))
+            .collect();
+
+        assert_eq!(packets.len(), 50);
+        assert!(packets.windows(2).all(|w| w[1].seq == w[0].seq + 1));
+        let stats = chain.capture().stats();
+        assert_eq!(stats.frames_vad_dropped, 25);
+        assert_eq!(
+            speaking_rx.try_recv().ok(),
+            Some(SpeakingState { active: true })
+        );
+
+        let mut decoder = OpusDecoder::with_channels(1).expect("opus decoder");
+        let last = receiver_ctx
+            .decrypt(&packets.last().unwrap().data)
+            .expect("decryptable by peer");
+        let decoded = decoder.decode(&last).expect("opus decodes");
+        assert!(
+            background_noise_rms(&decoded).is_none(),
+            "tone comes through"
+        );
+    }
+
+    #[test]
+    fn process_pipeline_produces_decryptable_opus_packet() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+
+        let sender_ctx = Arc::new(alice.derive_shared_secret(&bob_pub).expect("sender ctx"));
+        let receiver_ctx = bob.derive_shared_secret(&alice_pub).expect("receiver ctx");
+
+        let encoder = Arc::new(Mutex::new(OpusEncoder::new().expect("opus encoder")));
+        let (packet_tx, mut packet_rx) = mpsc::unbounded_channel();
+        let (rms_tx, rms_rx) = watch::channel(0.0f32);
+        let seq = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let controls = Arc::new(CaptureControls {
+            input_gain_bits: AtomicU32::new(1.0f32.to_bits()),
+            vad_threshold_bits: AtomicU32::new(0.01f32.to_bits()),
+            noise_gate_threshold_bits: AtomicU32::new(0.001f32.to_bits()),
+            voice_mode: AtomicU8::new(VOICE_MODE_VAD),
+            ptt_active: AtomicBool::new(true),
+            noise_suppression: AtomicBool::new(false),
+            noise_suppression_mode: AtomicU8::new(NoiseSuppressionMode::Light.to_u8()),
+            aec_enabled: AtomicBool::new(false),
+            agc_enabled: AtomicBool::new(false),
+            agc_target_rms_bits: AtomicU32::new(DEFAULT_AGC_TARGET_RMS.to_bits()),
+            agc_min_gain_bits: AtomicU32::new(DEFAULT_AGC_MIN_GAIN.to_bits()),
+            agc_max_gain_bits: AtomicU32::new(DEFAULT_AGC_MAX_GAIN.to_bits()),
+            noise_gate_enabled: AtomicBool::new(false),
+            noise_gate_hold_ms: AtomicU32::new(0),
+            noise_gate_high_band_bias_bits: AtomicU32::new(0.0f32.to_bits()),
+            stage_order: AtomicU8::new(STAGE_ORDER_GAIN_THEN_GATE),
+            stereo: AtomicBool::new(false),
+            frame_size: AtomicUsize::new(FRAME_SIZE),
+            echo_reference: EchoReference::default(),
+            paused: AtomicBool::new(true),
+            jitter_events: AtomicU64::new(0),
+            frames_encoded: AtomicU64::new(0),
+            vad_gated_samples: AtomicU64::new(0),
+            frames_dtx: AtomicU64::new(0),
+            jitter_tx: Mutex::new(None),
+            speaking_tx: Mutex::new(None),
+            clip: Mutex::new(None),
+            recipients: RwLock::default(),
+            recording: RecordingTap::default(),
+        });
+        let crypto = CryptoSlot::new(None);
+        let mut state = CapturePipelineState::new();
+
+        let input: Vec<f32> = (0..FRAME_SIZE)
+            .map(|i| ((i as f32 * 2.0 * PI) / FRAME_SIZE as f32).sin() * 0.2)
+            .collect();
+        let run = |state: &mut CapturePipelineState, crypto: &CryptoSlot| {
+            process_capture_samples(
+                &input,
+                1,
+                SAMPLE_RATE,
+                false,
+                &rms_tx,
+                &encoder,
+                crypto,
+                &seq,
+                &packet_tx,
+                &controls,
+                state,
+            );
+        };
+
+        // Pre-warmed: paused, then resumed but still without a key
+        run(&mut state, &crypto);
+        assert!(
+            packet_rx.try_recv().is_err(),
+            "paused capture sends nothing"
+        );
+        controls.paused.store(false, Ordering::Relaxed);
+        run(&mut state, &crypto);
+        assert!(
+            packet_rx.try_recv().is_err(),
+            "unkeyed capture sends nothing"
+        );
+
+        crypto.set(sender_ctx);
+        run(&mut state, &crypto);
+
+        assert!(*rms_rx.borrow() > 0.0, "meter sees the latest level");
+
+        let packet = packet_rx.try_recv().expect("expected one packet");
+        assert_eq!(controls.frames_encoded.load(Ordering::Relaxed), 1);
+        let decrypted = receiver_ctx
+            .decrypt(&packet.data)
+            .expect("packet decryptable by peer");
+
+        let mut decoder = OpusDecoder::with_channels(1).expect("opus decoder");
+        let decoded = decoder.decode(&decrypted).expect("opus decodes");
+        assert!(!decoded.is_empty());
+
+        // A group call peer gets the very same packet
+        let (recipient_tx, mut recipient_rx) = mpsc::unbounded_channel();
+        controls
+            .recipients
+            .write()
+            .unwrap()
+            .insert("carol".to_string(), recipient_tx);
+        run(&mut state, &crypto);
+        let to_bob = packet_rx.try_recv().expect("bob still gets the frame");
+        let to_carol = recipient_rx.try_recv().expect("carol gets the frame");
+        assert_eq!(to_bob.seq, to_carol.seq);
+        assert_eq!(to_bob.data, to_carol.data);
+    }
+
+    #[test]
+    fn capture_stage_order_accepts_only_supported_permutations() {
+        assert_eq!(
+            CaptureStageOrder::from_stages(&[CaptureStage::NoiseGate, CaptureStage::Gain]).unwrap(),
+            CaptureStageOrder::GateThenGain
+        );
+        assert!(CaptureStageOrder::from_stages(&[CaptureStage::Gain]).is_err());
+        assert!(CaptureStageOrder::from_stages(&[CaptureStage::Gain, CaptureStage::Gain]).is_err());
+        assert!(CaptureStage::parse("limiter").is_err());
+    }
+
+    #[test]
+    fn gate_before_gain_holds_agc_while_gate_is_closed() {
+        let controls = CaptureControls {
+            input_gain_bits: AtomicU32::new(1.0f32.to_bits()),
+            vad_threshold_bits: AtomicU32::new(0.01f32.to_bits()),
+            noise_gate_threshold_bits: AtomicU32::new(0.05f32.to_bits()),
+            voice_mode: AtomicU8::new(VOICE_MODE_VAD),
+            ptt_active: AtomicBool::new(false),
+            noise_suppression: AtomicBool::new(false),
+            noise_suppression_mode: AtomicU8::new(NoiseSuppressionMode::Light.to_u8()),
+            aec_enabled: AtomicBool::new(false),
+            agc_enabled: AtomicBool::new(true),
+            agc_target_rms_bits: AtomicU32::new(DEFAULT_AGC_TARGET_RMS.to_bits()),
+            agc_min_gain_bits: AtomicU32::new(DEFAULT_AGC_MIN_GAIN.to_bits()),
+            agc_max_gain_bits: AtomicU32::new(DEFAULT_AGC_MAX_GAIN.to_bits()),
+            noise_gate_enabled: AtomicBool::new(true),
+            noise_gate_hold_ms: AtomicU32::new(0),
+            noise_gate_high_band_bias_bits: AtomicU32::new(0.0f32.to_bits()),
+            stage_order: AtomicU8::new(STAGE_ORDER_GATE_THEN_GAIN),
+            stereo: AtomicBool::new(false),
+            frame_size: AtomicUsize::new(FRAME_SIZE),
+            echo_reference: EchoReference::default(),
+            paused: AtomicBool::new(false),
+            jitter_events: AtomicU64::new(0),
+            frames_encoded: AtomicU64::new(0),
+            vad_gated_samples: AtomicU64::new(0),
+            frames_dtx: AtomicU64::new(0),
+            jitter_tx: Mutex::new(None),
+            speaking_tx: Mutex::new(None),
+            clip: Mutex::new(None),
+            recipients: RwLock::default(),
+            recording: RecordingTap::default(),
+        };
+        let mut state = CapturePipelineState::new();
+        state.gate_gain = 0.0;
+        let mut noise = vec![0.005f32; FRAME_SIZE];
+        let rms = calculate_rms(&noise);
+
+        let gated = apply_noise_gate_stage(&mut noise, &controls, &mut state, rms);
+        apply_gain_stage(&mut noise, &controls, &mut state, gated);
+        assert!((state.agc_gain - 1.0).abs() < 1e-6);
+
+        // Same noise with the gate after gain drives AGC upwards.
+        let mut noise = vec![0.005f32; FRAME_SIZE];
+        apply_gain_stage(&mut noise, &controls, &mut state, false);
+        assert!(state.agc_gain > 1.0);
+    }
+
+    #[test]
+    fn noise_gate_lets_quiet_sibilants_through_its_high_band() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let ctx = Arc::new(
+            alice
+                .derive_shared_secret(&bob.public_key_bytes)
+                .expect("ctx"),
+        );
+        let capture = AudioCapture::new_with_config(
+            ctx,
+            EchoReference::default(),
+            AudioCaptureConfig {
+                noise_gate_threshold: 0.05,
+                ..AudioCaptureConfig::default()
+            },
+        )
+        .expect("capture");
+        let tone = |hz: f32| -> Vec<f32> {
+            (0..480)
+                .map(|i| (i as f32 * 2.0 * PI * hz / SAMPLE_RATE as f32).sin() * 0.05)
+                .collect()
+        };
+        // Output level of a 10 ms block once the gate has been shut
+        let gated_rms = |block: Vec<f32>| {
+            let mut state = CapturePipelineState::new();
+            state.gate_gain = 0.0;
+            state.gate_high_gain = 0.0;
+            let mut block = block;
+            let rms = calculate_rms(&block);
+            assert!(rms < 0.05, "under the threshold as a whole");
+            apply_noise_gate_stage(&mut block, &capture.controls, &mut state, rms);
+            calculate_rms(&block) / rms
+        };
+
+        assert!(gated_rms(tone(6000.0)) > 0.6, "an \"s\" gets through");
+        assert!(gated_rms(tone(200.0)) < 0.1, "low hum stays gated");
+        capture.set_noise_gate_high_band_bias(0.0);
+        assert!(gated_rms(tone(6000.0)) < 0.1, "full-band gate without bias");
+
+        // Held open for 50 ms after the level drops
+        let mut state = CapturePipelineState::new();
+        let mut loud = vec![0.2f32; 480];
+        apply_noise_gate_stage(&mut loud, &capture.controls, &mut state, 0.2);
+        for _ in 0..5 {
+            apply_noise_gate_stage(&mut vec![0.0; 480], &capture.controls, &mut state, 0.0);
+            assert_eq!(state.gate_gain, 1.0);
+        }
+        apply_noise_gate_stage(&mut vec![0.0; 480], &capture.controls, &mut state, 0.0);
+        assert!(state.gate_gain < 1.0);
+    }
 
-/// Convert RMS to dB
-pub fn rms_to_db(rms: f32) -> f32 {
-    if rms <= 0.0 {
-        return -100.0;
+    #[test]
+    fn agc_gain_stays_within_configured_range() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let ctx = Arc::new(
+            alice
+                .derive_shared_secret(&bob.public_key_bytes)
+                .expect("ctx"),
+        );
+        let capture = AudioCapture::new_with_config(
+            ctx,
+            EchoReference::default(),
+            AudioCaptureConfig {
+                noise_gate_enabled: false,
+                ..AudioCaptureConfig::default()
+            },
+        )
+        .expect("capture");
+        capture.set_agc_target_rms(0.2);
+        capture.set_agc_gain_range(0.5, 1.5);
+
+        let mut state = CapturePipelineState::new();
+        for _ in 0..200 {
+            let mut quiet = vec![0.01f32; FRAME_SIZE];
+            apply_gain_stage(&mut quiet, &capture.controls, &mut state, false);
+        }
+        assert!((state.agc_gain - 1.5).abs() < 1e-3);
+        for _ in 0..200 {
+            let mut loud = vec![0.9f32; FRAME_SIZE];
+            apply_gain_stage(&mut loud, &capture.controls, &mut state, false);
+        }
+        assert!((state.agc_gain - 0.5).abs() < 1e-3);
+
+        // An inverted range collapses onto the maximum
+        capture.set_agc_gain_range(3.0, 2.0);
+        assert_eq!(capture.agc_gain_range(), (2.0, 2.0));
+        capture.set_agc_target_rms(1.0);
+        assert_eq!(capture.agc_target_rms(), MAX_AGC_TARGET_RMS);
+
+        let inverted = AudioCaptureConfig {
+            agc_min_gain: 3.0,
+            agc_max_gain: 2.0,
+            ..AudioCaptureConfig::default()
+        };
+        assert!(inverted.validate().is_err());
+        assert_eq!(inverted.clamped().agc_min_gain, 2.0);
     }
-    20.0 * rms.log10()
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::crypto::KeyPair;
-    use std::f32::consts::PI;
+    #[test]
+    fn prewarmed_playback_drops_packets_until_keyed_and_resumed() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let encoded = encoder.encode(&vec![1000i16; FRAME_SIZE]).expect("encode");
+        let packet = AudioPacket {
+            seq: 7,
+            data: sender_ctx.encrypt(&encoded).expect("encrypt"),
+        };
+
+        let playback =
+            AudioPlayback::new_prewarmed(AudioPlaybackConfig::default()).expect("playback");
+        assert!(playback.is_paused());
+        playback
+            .process_packet(packet.clone())
+            .expect("paused playback ignores packets");
+        assert_eq!(playback.queued_samples(), 0);
+
+        playback.resume();
+        assert!(playback.process_packet(packet.clone()).is_err());
+
+        playback.set_crypto(receiver_ctx);
+        playback.process_packet(packet).expect("keyed packet");
+        assert_eq!(playback.last_sequence(), Some(7));
+        assert!(playback.queued_samples() > 0);
+    }
 
     #[test]
-    fn downmix_stereo_f32_to_mono() {
-        let stereo = vec![1.0f32, -1.0f32, 0.5f32, 0.5f32];
-        let mono = downmix_f32(&stereo, 2);
-        assert_eq!(mono.len(), 2);
-        assert!((mono[0] - 0.0).abs() < 1e-6);
-        assert!((mono[1] - 0.5).abs() < 1e-6);
+    fn group_call_peers_are_mixed_at_their_own_volume() {
+        let controls = playback_controls(1.0, false);
+        let peer_queue = Mutex::new(JitterBuffer::new(MIN_JITTER_TARGET_MS));
+        peer_queue
+            .lock()
+            .unwrap()
+            .push(0, vec![1000i16; FRAME_SIZE]);
+        let mut peers = [PeerMix {
+            queue: peer_queue.lock().unwrap(),
+            volume: 0.5,
+        }];
+
+        let mut compressor = Compressor::default();
+        let (left, right) = playback_frame_from_queue(
+            &mut VecDeque::from(vec![1000i16]),
+            &mut peers,
+            &controls,
+            &mut compressor,
+        );
+        assert!((left - 1500.0 / 32767.0).abs() < 1e-6);
+        assert_eq!(left, right);
+
+        controls.raw_mode.store(true, Ordering::Relaxed);
+        let (raw, _) = playback_frame_from_queue(
+            &mut VecDeque::from(vec![1000i16]),
+            &mut peers,
+            &controls,
+            &mut compressor,
+        );
+        assert!((raw - 2000.0 / 32767.0).abs() < 1e-6);
     }
 
     #[test]
-    fn resample_44100_to_48000_produces_expected_count() {
-        let input = vec![0.0f32; 441]; // ~10ms at 44.1kHz
-        let mut pos = 0.0;
-        let out = resample_to_48k(&input, 44_100, &mut pos);
-        assert!((470..=490).contains(&out.len()));
+    fn peer_packets_decode_with_that_peers_key() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let encoded = encoder.encode(&vec![1000i16; FRAME_SIZE]).expect("encode");
+        let packet = AudioPacket {
+            seq: 3,
+            data: sender_ctx.encrypt(&encoded).expect("encrypt"),
+        };
+
+        let playback =
+            AudioPlayback::new_prewarmed(AudioPlaybackConfig::default()).expect("playback");
+        playback.resume();
+        assert!(playback
+            .process_peer_packet("alice", packet.clone())
+            .is_err());
+
+        playback
+            .add_peer("alice", receiver_ctx, 0.5)
+            .expect("add peer");
+        playback
+            .process_peer_packet("alice", packet.clone())
+            .expect("peer packet");
+        playback
+            .process_peer_packet("alice", packet)
+            .expect("a replay is dropped, not an error");
+        let stats = playback.peer_stats("alice").expect("peer stats");
+        assert_eq!(stats.packets_received, 1);
+        assert_eq!(stats.packets_discarded, 1);
+        assert_eq!(stats.packets_duplicate, 1);
+        assert_eq!(playback.last_sequence(), None, "main stream untouched");
+
+        playback.remove_peer("alice");
+        assert!(playback.peer_stats("alice").is_none());
+    }
+
+    fn voiced_frames(count: usize) -> Vec<Vec<i16>> {
+        // A gliding tone changes every frame, so PLC's extrapolation drifts
+        // from the real audio while FEC tracks it
+        (0..count)
+            .map(|frame| {
+                let freq = 180.0 + frame as f32 * 40.0;
+                (0..FRAME_SIZE)
+                    .map(|i| {
+                        let t = (frame * FRAME_SIZE + i) as f32 / SAMPLE_RATE as f32;
+                        ((t * 2.0 * PI * freq).sin() * 9000.0) as i16
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn squared_error(a: &[i16], b: &[i16]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (f64::from(*x) - f64::from(*y)).powi(2))
+            .sum()
     }
 
     #[test]
-    fn fill_output_duplicates_mono_to_stereo() {
-        let queue = Arc::new(Mutex::new(VecDeque::from(vec![1000i16, -1000i16])));
-        let mut out = vec![0.0f32; 4]; // 2 frames, 2 channels
-        let output_volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
-        let remote_volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
-        let limiter = Arc::new(AtomicBool::new(false));
-        let muted = Arc::new(AtomicBool::new(false));
-        let output_rms = Arc::new(AtomicU32::new(0.0f32.to_bits()));
-        fill_output_f32(
-            &mut out,
-            2,
-            &queue,
-            &output_volume,
-            &remote_volume,
-            &limiter,
-            &muted,
-            &output_rms,
+    fn fec_recovers_a_lost_frame_closer_than_plc() {
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        assert!(encoder.encoder.inband_fec().unwrap());
+        let packets: Vec<Vec<u8>> = voiced_frames(12)
+            .iter()
+            .map(|frame| encoder.encode(frame).expect("encode"))
+            .collect();
+
+        let mut reference = OpusDecoder::with_channels(1).unwrap();
+        let expected: Vec<Vec<i16>> = packets
+            .iter()
+            .map(|packet| reference.decode(packet).unwrap())
+            .collect();
+
+        let lost = 8;
+        let mut with_fec = OpusDecoder::with_channels(1).unwrap();
+        let mut with_plc = OpusDecoder::with_channels(1).unwrap();
+        for packet in &packets[..lost] {
+            with_fec.decode(packet).unwrap();
+            with_plc.decode(packet).unwrap();
+        }
+        let recovered = with_fec.decode_fec(&packets[lost + 1]).unwrap();
+        let concealed = with_plc.decode_plc().unwrap();
+        assert_eq!((recovered.len(), concealed.len()), (FRAME_SIZE, FRAME_SIZE));
+        assert!(
+            squared_error(&recovered, &expected[lost]) < squared_error(&concealed, &expected[lost])
         );
+    }
 
-        assert!((out[0] - out[1]).abs() < 1e-6);
-        assert!((out[2] - out[3]).abs() < 1e-6);
-        assert!(out[0] > 0.0);
-        assert!(out[2] < 0.0);
+    #[test]
+    fn fec_without_redundancy_falls_back_to_plc() {
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        encoder.encoder.set_inband_fec(false).unwrap();
+        let packets: Vec<Vec<u8>> = voiced_frames(6)
+            .iter()
+            .map(|frame| encoder.encode(frame).expect("encode"))
+            .collect();
+
+        let mut with_fec = OpusDecoder::with_channels(1).unwrap();
+        let mut with_plc = OpusDecoder::with_channels(1).unwrap();
+        for packet in &packets[..4] {
+            with_fec.decode(packet).unwrap();
+            with_plc.decode(packet).unwrap();
+        }
+        assert_eq!(
+            with_fec.decode_fec(&packets[5]).unwrap(),
+            with_plc.decode_plc().unwrap()
+        );
     }
 
     #[test]
-    fn process_pipeline_produces_decryptable_opus_packet() {
+    fn bitrate_controller_ignores_a_brief_loss_spike() {
+        let mut controller = BitrateController::new();
+        assert_eq!(controller.update(30.0), None);
+        for _ in 0..20 {
+            assert_eq!(controller.update(0.0), None);
+        }
+        assert_eq!(controller.bitrate(), MAX_OPUS_BITRATE);
+    }
+
+    #[test]
+    fn bitrate_controller_backs_off_under_loss_and_recovers_slowly() {
+        let mut controller = BitrateController::new();
+        assert_eq!(controller.update(12.0), None);
+        assert_eq!(controller.update(12.0), None);
+        assert_eq!(controller.update(12.0), Some(48_000));
+        for _ in 0..40 {
+            controller.update(12.0);
+        }
+        assert_eq!(controller.bitrate(), MIN_OPUS_BITRATE);
+
+        // Moderate loss holds the bitrate either way
+        let mut steps = Vec::new();
+        for _ in 0..20 {
+            steps.extend(controller.update(3.0));
+        }
+        assert!(steps.is_empty());
+
+        let mut steps = Vec::new();
+        for _ in 0..20 {
+            steps.extend(controller.update(0.0));
+        }
+        assert!(!steps.is_empty());
+        assert!(steps.windows(2).all(|w| w[1] == w[0] + BITRATE_UP_STEP));
+        assert!(controller.bitrate() < MAX_OPUS_BITRATE);
+    }
+
+    fn background_noise_frames(count: usize, amplitude: i16) -> Vec<Vec<i16>> {
+        let mut x: u32 = 1;
+        (0..count)
+            .map(|_| {
+                (0..FRAME_SIZE)
+                    .map(|_| {
+                        x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                        ((x >> 16) % (2 * amplitude as u32 + 1)) as i16 - amplitude
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn speaking_holds_through_short_pauses() {
+        let mut detector = SpeakingDetector::default();
+        assert_eq!(detector.update(false, FRAME_SIZE), None);
+        assert_eq!(
+            detector.update(true, FRAME_SIZE),
+            Some(SpeakingState { active: true })
+        );
+        assert_eq!(detector.update(true, FRAME_SIZE), None);
+
+        // A pause shorter than the hold, then speech again: no flicker
+        for _ in 0..(SPEAKING_HOLD_SAMPLES / FRAME_SIZE - 1) {
+            assert_eq!(detector.update(false, FRAME_SIZE), None);
+        }
+        assert_eq!(detector.update(true, FRAME_SIZE), None);
+
+        for _ in 0..(SPEAKING_HOLD_SAMPLES / FRAME_SIZE - 1) {
+            assert_eq!(detector.update(false, FRAME_SIZE), None);
+        }
+        assert_eq!(
+            detector.update(false, FRAME_SIZE),
+            Some(SpeakingState { active: false })
+        );
+        assert_eq!(detector.update(false, FRAME_SIZE), None);
+    }
+
+    #[test]
+    fn dtx_leaves_silence_unsent_apart_from_noise_updates() {
+        let mut plain = OpusEncoder::new().expect("opus encoder");
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        encoder.set_dtx(true).expect("dtx");
+        encoder.set_channels(2).expect("stereo");
+        encoder.set_channels(1).expect("mono");
+        assert!(encoder.dtx(), "rebuilding the encoder keeps DTX");
+
+        let mut sent = Vec::new();
+        for frame in voiced_frames(5)
+            .into_iter()
+            .chain(background_noise_frames(60, 10))
+        {
+            let packet = encoder.encode(&frame).expect("encode");
+            let without_dtx = plain.encode(&frame).expect("encode");
+            assert!(!plain.is_dtx_packet(&without_dtx));
+            sent.push(!encoder.is_dtx_packet(&packet));
+        }
+        assert!(sent[..5].iter().all(|&sent| sent), "speech is always sent");
+        let silence = &sent[5..];
+        assert!(silence.iter().filter(|&&sent| !sent).count() > 30);
+        let first_dtx = silence
+            .iter()
+            .position(|&sent| !sent)
+            .expect("DTX kicks in");
+        assert!(
+            silence[first_dtx..].iter().any(|&sent| sent),
+            "comfort noise updates keep coming"
+        );
+    }
+
+    #[test]
+    fn jitter_buffer_plays_comfort_noise_through_a_pause_in_background_noise() {
+        let mut buffer = JitterBuffer::new(20);
+        buffer.push(0, background_noise_frames(1, 200).remove(0));
+        let heard = pull_frame(&mut buffer);
+        assert_eq!(buffer.len(), 0);
+
+        let rms = |frame: &[i16]| {
+            (frame.iter().map(|&s| f64::from(s).powi(2)).sum::<f64>() / frame.len() as f64).sqrt()
+        };
+        let pause = pull_frame(&mut buffer);
+        let ratio = rms(&pause) / rms(&heard);
+        assert!((0.8..1.25).contains(&ratio), "noise level ratio {}", ratio);
+
+        // Speech cut short by loss stays a cut
+        buffer.push(1, vec![8000; FRAME_SIZE]);
+        pull_frame(&mut buffer);
+        assert_eq!(pull_frame(&mut buffer), vec![0; FRAME_SIZE]);
+    }
+
+    #[test]
+    fn encoder_bitrate_changes_packet_size() {
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let frames = voiced_frames(10);
+        let size_at = |encoder: &mut OpusEncoder, bitrate: u32| {
+            encoder.set_bitrate(bitrate).unwrap();
+            frames
+                .iter()
+                .map(|frame| encoder.encode(frame).unwrap().len())
+                .sum::<usize>()
+        };
+        let high = size_at(&mut encoder, MAX_OPUS_BITRATE);
+        let low = size_at(&mut encoder, MIN_OPUS_BITRATE);
+        assert!(
+            low * 3 < high,
+            "{} bytes at 8 kbps vs {} at 64 kbps",
+            low,
+            high
+        );
+    }
+
+    #[test]
+    fn remote_speaking_follows_decoded_audio() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let mut frames = voiced_frames(5);
+        frames.extend(std::iter::repeat_n(vec![0i16; FRAME_SIZE], 40));
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        let mut speaking_rx = playback
+            .take_remote_speaking_receiver()
+            .expect("speaking receiver");
+        for (seq, frame) in frames.iter().enumerate() {
+            let packet = AudioPacket {
+                seq: seq as u32,
+                data: sender_ctx
+                    .encrypt(&encoder.encode(frame).expect("encode"))
+                    .expect("encrypt"),
+            };
+            playback.process_packet(packet).unwrap();
+        }
+
+        let on = speaking_rx.try_recv().expect("speaking on");
+        assert_eq!(
+            on,
+            RemoteSpeakingState {
+                peer_id: None,
+                active: true
+            }
+        );
+        let off = speaking_rx.try_recv().expect("speaking off");
+        assert!(!off.active);
+        assert!(speaking_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn deafened_playback_skips_decoding_but_keeps_position() {
         let alice = KeyPair::generate().expect("alice keypair");
         let bob = KeyPair::generate().expect("bob keypair");
         let alice_pub = alice.public_key_bytes.clone();
         let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
 
-        let sender_ctx = Arc::new(alice.derive_shared_secret(&bob_pub).expect("sender ctx"));
-        let receiver_ctx = bob.derive_shared_secret(&alice_pub).expect("receiver ctx");
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let packets: Vec<AudioPacket> = voiced_frames(10)
+            .iter()
+            .enumerate()
+            .map(|(seq, frame)| AudioPacket {
+                seq: seq as u32,
+                data: sender_ctx
+                    .encrypt(&encoder.encode(frame).expect("encode"))
+                    .expect("encrypt"),
+            })
+            .collect();
 
-        let encoder = Arc::new(Mutex::new(OpusEncoder::new().expect("opus encoder")));
-        let (packet_tx, mut packet_rx) = mpsc::unbounded_channel();
-        let (rms_tx, _rms_rx) = mpsc::unbounded_channel();
-        let seq = Arc::new(std::sync::atomic::AtomicU32::new(0));
-        let controls = Arc::new(CaptureControls {
-            input_gain_bits: AtomicU32::new(1.0f32.to_bits()),
-            vad_threshold_bits: AtomicU32::new(0.01f32.to_bits()),
-            noise_gate_threshold_bits: AtomicU32::new(0.001f32.to_bits()),
-            voice_mode: AtomicU8::new(VOICE_MODE_VAD),
-            ptt_active: AtomicBool::new(true),
-            noise_suppression: AtomicBool::new(false),
-            aec_enabled: AtomicBool::new(false),
-            agc_enabled: AtomicBool::new(false),
-            noise_gate_enabled: AtomicBool::new(false),
-            shared_playback_rms_bits: Arc::new(AtomicU32::new(0.0f32.to_bits())),
-        });
-        let mut state = CapturePipelineState::new();
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        playback.process_packet(packets[0].clone()).unwrap();
+        playback.process_packet(packets[1].clone()).unwrap();
+        assert_eq!(playback.queued_samples(), 2 * FRAME_SIZE);
 
-        let input: Vec<f32> = (0..FRAME_SIZE)
-            .map(|i| ((i as f32 * 2.0 * PI) / FRAME_SIZE as f32).sin() * 0.2)
+        playback.set_deafened(true);
+        assert_eq!(playback.queued_samples(), 0);
+        for packet in &packets[2..8] {
+            playback.process_packet(packet.clone()).unwrap();
+        }
+        assert_eq!(playback.queued_samples(), 0);
+        assert_eq!(playback.last_sequence(), Some(7));
+
+        playback.set_deafened(false);
+        playback.process_packet(packets[8].clone()).unwrap();
+        assert_eq!(playback.queued_samples(), FRAME_SIZE);
+        let stats = playback.stats();
+        assert_eq!(stats.packets_received, 3);
+        assert_eq!(stats.packets_lost, 0);
+        assert_eq!(stats.frames_concealed, 0);
+    }
+
+    #[test]
+    fn playback_conceals_consecutive_lost_packets() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let packets: Vec<AudioPacket> = voiced_frames(20)
+            .iter()
+            .enumerate()
+            .map(|(seq, frame)| AudioPacket {
+                seq: seq as u32,
+                data: sender_ctx
+                    .encrypt(&encoder.encode(frame).expect("encode"))
+                    .expect("encrypt"),
+            })
             .collect();
 
-        process_mono_samples(
-            &input,
-            SAMPLE_RATE,
-            false,
-            &rms_tx,
-            &encoder,
-            &sender_ctx,
-            &seq,
-            &packet_tx,
-            &controls,
-            &mut state,
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        playback.process_packet(packets[0].clone()).unwrap();
+        playback.process_packet(packets[1].clone()).unwrap();
+        // 2 and 3 lost: PLC for 2, FEC from 4 for 3
+        playback.process_packet(packets[4].clone()).unwrap();
+        assert_eq!(playback.queued_samples(), 5 * FRAME_SIZE);
+        assert_eq!(playback.last_sequence(), Some(4));
+
+        // The real 3 shows up after its slot was filled
+        playback.process_packet(packets[3].clone()).unwrap();
+        assert_eq!(playback.queued_samples(), 5 * FRAME_SIZE);
+        assert_eq!(playback.last_sequence(), Some(4));
+
+        // Too long an outage to synthesize; only the packet itself is queued
+        let after_outage = 5 + MAX_CONCEALED_FRAMES as usize + 1;
+        playback
+            .process_packet(packets[after_outage].clone())
+            .unwrap();
+        assert_eq!(playback.queued_samples(), 6 * FRAME_SIZE);
+
+        let stats = playback.stats();
+        assert_eq!(stats.packets_received, 4);
+        assert_eq!(stats.packets_lost, 2 + 6);
+        assert_eq!(stats.frames_concealed, 2);
+        assert_eq!(stats.packets_discarded, 1);
+        assert_eq!(stats.packets_out_of_order, 1);
+        // 3 arrived late, so 7 of 12 sequence numbers never did
+        assert!((playback.packet_loss_percent() - 7.0 * 100.0 / 12.0).abs() < 1e-3);
+        playback.stop();
+        assert_eq!(playback.stats(), PlaybackStats::default());
+        assert_eq!(playback.packet_loss_percent(), 0.0);
+    }
+
+    #[test]
+    fn stream_jitter_tracks_uneven_packet_spacing() {
+        let start = Instant::now();
+        let mut steady = StreamState::default();
+        for seq in 0..50u32 {
+            steady.record(
+                seq,
+                1,
+                0,
+                FRAME_SIZE,
+                start + Duration::from_millis(20 * u64::from(seq)),
+            );
+        }
+        assert!(steady.stats.jitter_ms < 1e-6);
+
+        // Alternating 10 ms and 30 ms gaps, plus a lost packet arriving on time
+        let mut bursty = StreamState::default();
+        let mut at = start;
+        for seq in 0..50u32 {
+            at += Duration::from_millis(if seq % 2 == 0 { 10 } else { 30 });
+            bursty.record(seq, 1, 0, FRAME_SIZE, at);
+        }
+        assert!(bursty.stats.jitter_ms > 8.0);
+        let jitter = bursty.stats.jitter_ms;
+        bursty.record(51, 2, 1, FRAME_SIZE, at + Duration::from_millis(40));
+        assert!(bursty.stats.jitter_ms < jitter);
+        assert_eq!(bursty.stats.packets_lost, 1);
+    }
+
+    #[test]
+    fn loss_window_counts_gaps_across_wraparound_and_forgets_old_loss() {
+        let start = Instant::now();
+        let mut stream = StreamState::default();
+        let mut seq = u32::MAX - 2;
+        stream.record(seq, 1, 0, FRAME_SIZE, start);
+        // Every fourth packet lost, straight through the u32 wrap
+        for i in 1..40u64 {
+            let gap = if i % 3 == 0 { 2 } else { 1 };
+            seq = seq.wrapping_add(gap);
+            let at = start + Duration::from_millis(20 * i);
+            stream.record(seq, gap, 0, FRAME_SIZE, at);
+        }
+        let now = start + Duration::from_millis(800);
+        assert!((stream.loss_percent(now) - 13.0 * 100.0 / 53.0).abs() < 1e-3);
+
+        // One of them turns up late after all
+        stream.record_discarded(3, now);
+        assert!((stream.loss_percent(now) - 12.0 * 100.0 / 53.0).abs() < 1e-3);
+        stream.record_discarded(0, now);
+        assert_eq!(stream.stats.packets_out_of_order, 1);
+        assert_eq!(stream.stats.packets_duplicate, 1);
+        assert_eq!(stream.stats.packets_discarded, 2);
+
+        // A clean stretch once the lossy one slid out of the window
+        for i in 0..50u64 {
+            seq = seq.wrapping_add(1);
+            let at = now + LOSS_WINDOW + Duration::from_millis(20 * i);
+            stream.record(seq, 1, 0, FRAME_SIZE, at);
+        }
+        assert_eq!(
+            stream.loss_percent(now + LOSS_WINDOW + Duration::from_secs(1)),
+            0.0
         );
+        assert_eq!(stream.stats.packets_lost, 13);
+    }
 
-        let packet = packet_rx.try_recv().expect("expected one packet");
-        let decrypted = receiver_ctx
-            .decrypt(&packet.data)
-            .expect("packet decryptable by peer");
+    #[test]
+    fn playback_stop_resets_decoder_history() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let encoded: Vec<Vec<u8>> = (0..2)
+            .map(|_| {
+                let frame: Vec<i16> = (0..FRAME_SIZE)
+                    .map(|i| {
+                        (((i as f32 * 2.0 * PI * 3.0) / FRAME_SIZE as f32).sin() * 8000.0) as i16
+                    })
+                    .collect();
+                encoder.encode(&frame).expect("encode")
+            })
+            .collect();
+        let packets: Vec<AudioPacket> = encoded
+            .iter()
+            .zip(0u32..)
+            .map(|(encoded, seq)| AudioPacket {
+                seq,
+                data: sender_ctx.encrypt(encoded).expect("encrypt"),
+            })
+            .collect();
 
-        let mut decoder = OpusDecoder::new().expect("opus decoder");
-        let decoded = decoder.decode(&decrypted).expect("opus decodes");
-        assert!(!decoded.is_empty());
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        playback
+            .process_packet(packets[0].clone())
+            .expect("first packet");
+        assert_eq!(playback.last_sequence(), Some(0));
+        playback.stop();
+        assert_eq!(playback.last_sequence(), None);
+        playback
+            .process_packet(packets[1].clone())
+            .expect("second packet");
+
+        let mut fresh = OpusDecoder::with_channels(1).expect("opus decoder");
+        let expected = fresh.decode(&encoded[1]).expect("decode");
+        let queued: Vec<i16> = playback
+            .sample_queue
+            .lock()
+            .unwrap()
+            .frames
+            .values()
+            .flatten()
+            .copied()
+            .collect();
+        assert_eq!(queued, expected);
+    }
+
+    #[test]
+    fn playback_queue_stays_bounded_and_reports_its_peak() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        for seq in 0..80u32 {
+            let encoded = encoder.encode(&vec![500i16; FRAME_SIZE]).expect("encode");
+            playback
+                .process_packet(AudioPacket {
+                    seq,
+                    data: sender_ctx.encrypt(&encoded).expect("encrypt"),
+                })
+                .expect("packet");
+        }
+
+        let stats = playback.buffer_stats();
+        assert!(stats.queued_samples <= PLAYBACK_QUEUE_MAX_SAMPLES + FRAME_SIZE);
+        assert!(stats.peak_samples > PLAYBACK_QUEUE_MAX_SAMPLES);
+        assert!(stats.peak_samples >= stats.queued_samples);
+        assert!(stats.allocated_bytes >= stats.queued_samples * 2);
+
+        playback.stop();
+        let stats = playback.buffer_stats();
+        assert_eq!((stats.queued_samples, stats.peak_samples), (0, 0));
+    }
+
+    #[test]
+    fn busy_backend_errors_are_distinguished_from_missing_devices() {
+        let busy = classify_build_error(
+            AudioDirection::Input,
+            "USB Mic",
+            &cpal::BuildStreamError::BackendSpecific {
+                err: cpal::BackendSpecificError {
+                    description: "ALSA function 'snd_pcm_open' failed with error 'EBUSY: Device or resource busy'".to_string(),
+                },
+            },
+        );
+        assert_eq!(
+            busy,
+            AudioDeviceError::Busy {
+                direction: AudioDirection::Input,
+                device: "USB Mic".to_string(),
+            }
+        );
+        assert_eq!(
+            busy.to_string(),
+            "Microphone is in use by another application"
+        );
+
+        let missing = classify_build_error(
+            AudioDirection::Output,
+            "Headset",
+            &cpal::BuildStreamError::DeviceNotAvailable,
+        );
+        assert!(matches!(missing, AudioDeviceError::NotFound { .. }));
+        assert_eq!(missing.direction(), AudioDirection::Output);
+
+        let other = classify_play_error(
+            AudioDirection::Output,
+            "Headset",
+            &cpal::PlayStreamError::BackendSpecific {
+                err: cpal::BackendSpecificError {
+                    description: "unsupported buffer size".to_string(),
+                },
+            },
+        );
+        assert!(matches!(other, AudioDeviceError::Failed { .. }));
+    }
+
+    #[test]
+    fn wasapi_exclusive_mode_code_counts_as_busy() {
+        assert!(is_device_busy_message(
+            "AUDCLNT_E_DEVICE_IN_USE (0x8889000A)"
+        ));
+        assert!(!is_device_busy_message(
+            "The requested device is not available"
+        ));
+    }
+
+    #[test]
+    fn lost_device_stops_the_current_run_and_is_reported_once() {
+        let device_events = Arc::new(DeviceEvents::default());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        device_events.set_sender(tx);
+        let running = Arc::new(AtomicBool::new(true));
+        let run_token = Arc::new(AtomicU64::new(2));
+
+        let mut stale = stream_error_callback(
+            AudioDirection::Input,
+            device_events.clone(),
+            running.clone(),
+            run_token.clone(),
+            1,
+        );
+        stale(cpal::StreamError::DeviceNotAvailable);
+        assert!(running.load(Ordering::SeqCst));
+
+        let mut current = stream_error_callback(
+            AudioDirection::Input,
+            device_events.clone(),
+            running.clone(),
+            run_token,
+            2,
+        );
+        current(cpal::StreamError::BackendSpecific {
+            err: cpal::BackendSpecificError {
+                description: "buffer overrun".to_string(),
+            },
+        });
+        assert!(running.load(Ordering::SeqCst));
+        assert!(rx.try_recv().is_err());
+
+        current(cpal::StreamError::DeviceNotAvailable);
+        current(cpal::StreamError::DeviceNotAvailable);
+        assert!(!running.load(Ordering::SeqCst));
+        assert!(device_events.lost.load(Ordering::SeqCst));
+        assert_eq!(
+            rx.try_recv(),
+            Ok(AudioDeviceEvent::Disconnected { is_input: true })
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn default_device_change_is_followed_once_it_settles() {
+        let mut watch = DefaultDeviceWatch::new(Some("Speakers".to_string()));
+        assert_eq!(watch.observe(Some("Speakers".to_string())), None);
+
+        // A storm of changes while the OS re-enumerates
+        assert_eq!(watch.observe(Some("AirPods".to_string())), None);
+        assert_eq!(watch.observe(Some("HDMI".to_string())), None);
+        assert_eq!(watch.observe(None), None);
+        assert_eq!(watch.observe(Some("AirPods".to_string())), None);
+        assert_eq!(
+            watch.observe(Some("AirPods".to_string())),
+            Some("AirPods".to_string())
+        );
+        assert_eq!(watch.observe(Some("AirPods".to_string())), None);
+
+        // Flapping back for a single poll is ignored
+        assert_eq!(watch.observe(Some("Speakers".to_string())), None);
+        assert_eq!(watch.observe(Some("AirPods".to_string())), None);
+        assert_eq!(watch.observe(Some("AirPods".to_string())), None);
+    }
+
+    #[test]
+    fn keepalive_packets_are_ignored_by_playback() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let keepalive = AudioPacket::keepalive();
+        let bytes = bincode::serialize(&keepalive).expect("serialize keepalive");
+        assert!(bytes.len() <= 16, "keepalive should stay tiny");
+
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        playback
+            .process_packet(keepalive)
+            .expect("keepalive accepted");
+        assert_eq!(playback.last_sequence(), None);
+        assert_eq!(playback.queued_samples(), 0);
+    }
+
+    #[test]
+    fn config_is_applied_at_creation_and_validated() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let ctx = Arc::new(
+            alice
+                .derive_shared_secret(&bob.public_key_bytes)
+                .expect("ctx"),
+        );
+
+        let config = AudioCaptureConfig {
+            input_gain: 2.5,
+            voice_mode: VoiceMode::PushToTalk,
+            noise_suppression_mode: NoiseSuppressionMode::Heavy,
+            agc_enabled: false,
+            stage_order: CaptureStageOrder::GateThenGain,
+            muted: true,
+            ..AudioCaptureConfig::default()
+        };
+        let capture =
+            AudioCapture::new_with_config(ctx.clone(), EchoReference::default(), config.clone())
+                .expect("capture");
+        assert_eq!(capture.input_gain(), 2.5);
+        assert_eq!(capture.voice_mode(), VoiceMode::PushToTalk);
+        assert_eq!(
+            capture.noise_suppression_mode(),
+            NoiseSuppressionMode::Heavy
+        );
+        assert!(!capture.agc_enabled());
+        assert_eq!(capture.stage_order(), CaptureStageOrder::GateThenGain);
+        assert!(capture.is_muted());
+
+        let too_loud = AudioCaptureConfig {
+            input_gain: 9.0,
+            ..config
+        };
+        assert!(AudioCapture::new_with_config(
+            ctx.clone(),
+            EchoReference::default(),
+            too_loud.clone()
+        )
+        .is_err());
+        assert_eq!(too_loud.clamped().input_gain, MAX_INPUT_GAIN);
+
+        let playback = AudioPlayback::new_with_config(
+            ctx.clone(),
+            AudioPlaybackConfig {
+                output_volume: 0.5,
+                limiter_enabled: false,
+                ..AudioPlaybackConfig::default()
+            },
+        )
+        .expect("playback");
+        assert_eq!(playback.output_volume(), 0.5);
+        assert!(!playback.limiter_enabled());
+
+        let nan_volume = AudioPlaybackConfig {
+            remote_volume: f32::NAN,
+            ..AudioPlaybackConfig::default()
+        };
+        assert!(AudioPlayback::new_with_config(ctx, nan_volume).is_err());
     }
 }
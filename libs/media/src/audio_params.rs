@@ -0,0 +1,219 @@
+//! Agreeing on frame duration and channels with the peer of a 1:1 call.
+//!
+//! A change is an offer the peer accepts or rejects, and applies on both
+//! sides only once accepted. Both users may change a setting at the same
+//! moment, so two offers can cross in flight. The side with the lower user
+//! id then keeps its own offer and refuses the peer's, while the other side
+//! drops its offer and accepts: both end on the lower id's parameters.
+
+use anyhow::Result;
+
+use crate::audio::frame_size_for_ms;
+use crate::{AudioSettings, OPUS_FRAME_DURATIONS_MS};
+
+/// The audio settings both ends of a 1:1 call agree on. Changing them
+/// mid-call is an offer to the peer (`MediaEngine::offer_call_audio_params`)
+/// that takes effect on both sides once the peer accepts it.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallAudioParams {
+    pub frame_duration_ms: f32,
+    /// 1 for mono, 2 for stereo
+    pub channels: u8,
+}
+
+impl CallAudioParams {
+    pub fn of(settings: &AudioSettings) -> Self {
+        Self {
+            frame_duration_ms: settings.frame_duration_ms,
+            channels: if settings.stereo { 2 } else { 1 },
+        }
+    }
+
+    /// Errors for a frame duration Opus cannot encode or a channel count
+    /// other than 1 or 2
+    pub fn validate(&self) -> Result<()> {
+        if frame_size_for_ms(self.frame_duration_ms).is_none() {
+            return Err(anyhow::anyhow!(
+                "Unsupported frame duration {} ms, expected one of {:?}",
+                self.frame_duration_ms,
+                OPUS_FRAME_DURATIONS_MS
+            ));
+        }
+        if !(1..=2).contains(&self.channels) {
+            return Err(anyhow::anyhow!(
+                "Unsupported channel count {}, expected 1 or 2",
+                self.channels
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply_to(&self, settings: &mut AudioSettings) {
+        settings.frame_duration_ms = self.frame_duration_ms;
+        settings.stereo = self.channels == 2;
+    }
+}
+
+/// Our side of the exchange: the offer we are waiting on, if any
+#[derive(Debug, Default)]
+pub(crate) struct ParamsNegotiation {
+    pending: Option<CallAudioParams>,
+}
+
+impl ParamsNegotiation {
+    /// Send `params` to the peer; a newer offer replaces an unanswered one
+    pub(crate) fn offer(&mut self, params: CallAudioParams) {
+        self.pending = Some(params);
+    }
+
+    /// The peer sent an offer. Returns whether to take it: not when it
+    /// crossed one of ours and our id is the lower, since the peer then
+    /// takes ours. Taking it drops our own.
+    pub(crate) fn receive_offer(&mut self, self_id: &str, peer_id: &str) -> bool {
+        if self.pending.is_some() && self_id < peer_id {
+            return false;
+        }
+        self.pending = None;
+        true
+    }
+
+    /// The peer answered. Returns the parameters to switch to: the ones we
+    /// offered, if accepted and still our latest offer. A rejection drops
+    /// the offer.
+    pub(crate) fn receive_answer(
+        &mut self,
+        accepted: bool,
+        params: CallAudioParams,
+    ) -> Option<CallAudioParams> {
+        if !accepted {
+            self.pending = None;
+            return None;
+        }
+        if self.pending != Some(params) {
+            return None;
+        }
+        self.pending = None;
+        Some(params)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOWER: &str = "1b0c6a52-0000-4000-8000-000000000001";
+    const HIGHER: &str = "9f3e21d7-0000-4000-8000-000000000002";
+
+    /// One user: the parameters in use and the exchange in progress
+    struct Side {
+        id: &'static str,
+        peer_id: &'static str,
+        params: CallAudioParams,
+        negotiation: ParamsNegotiation,
+    }
+
+    impl Side {
+        fn new(id: &'static str, peer_id: &'static str) -> Self {
+            Self {
+                id,
+                peer_id,
+                params: CallAudioParams {
+                    frame_duration_ms: 20.0,
+                    channels: 1,
+                },
+                negotiation: ParamsNegotiation::default(),
+            }
+        }
+
+        /// Answer an offer the way the desktop does: accepted, or rejected
+        /// with the parameters kept
+        fn answer(&mut self, offered: CallAudioParams) -> (bool, CallAudioParams) {
+            if self.negotiation.receive_offer(self.id, self.peer_id) {
+                self.params = offered;
+                (true, offered)
+            } else {
+                (false, self.params)
+            }
+        }
+
+        fn finish(&mut self, (accepted, params): (bool, CallAudioParams)) {
+            if let Some(params) = self.negotiation.receive_answer(accepted, params) {
+                self.params = params;
+            }
+        }
+    }
+
+    fn params(frame_duration_ms: f32, channels: u8) -> CallAudioParams {
+        CallAudioParams {
+            frame_duration_ms,
+            channels,
+        }
+    }
+
+    #[test]
+    fn an_accepted_offer_applies_on_both_sides() {
+        let mut alice = Side::new(LOWER, HIGHER);
+        let mut bob = Side::new(HIGHER, LOWER);
+
+        let offer = params(40.0, 2);
+        bob.negotiation.offer(offer);
+        let answer = alice.answer(offer);
+        bob.finish(answer);
+
+        assert_eq!(alice.params, offer);
+        assert_eq!(bob.params, offer);
+    }
+
+    #[test]
+    fn crossing_offers_settle_on_the_lower_id() {
+        let mut lower = Side::new(LOWER, HIGHER);
+        let mut higher = Side::new(HIGHER, LOWER);
+        let lower_offer = params(40.0, 1);
+        let higher_offer = params(10.0, 2);
+
+        // Both offer before either sees the other's. The server keeps each
+        // sender's messages in order, so each side gets the other's offer
+        // before the answer to its own.
+        lower.negotiation.offer(lower_offer);
+        higher.negotiation.offer(higher_offer);
+        let higher_answer = higher.answer(lower_offer);
+        let lower_answer = lower.answer(higher_offer);
+        higher.finish(lower_answer);
+        lower.finish(higher_answer);
+
+        assert!(higher_answer.0, "the higher id takes the lower's offer");
+        assert!(!lower_answer.0, "the lower id refuses the higher's offer");
+        assert_eq!(lower.params, lower_offer);
+        assert_eq!(higher.params, lower_offer);
+    }
+
+    #[test]
+    fn a_rejection_or_stale_answer_changes_nothing() {
+        let mut negotiation = ParamsNegotiation::default();
+        negotiation.offer(params(40.0, 1));
+        assert_eq!(negotiation.receive_answer(false, params(20.0, 1)), None);
+        // The offer is gone, so a late acceptance of it does not apply
+        assert_eq!(negotiation.receive_answer(true, params(40.0, 1)), None);
+
+        negotiation.offer(params(40.0, 1));
+        negotiation.offer(params(60.0, 1));
+        assert_eq!(negotiation.receive_answer(true, params(40.0, 1)), None);
+        assert_eq!(
+            negotiation.receive_answer(true, params(60.0, 1)),
+            Some(params(60.0, 1))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_unsupported_params() {
+        assert!(params(20.0, 1).validate().is_ok());
+        assert!(params(60.0, 2).validate().is_ok());
+        assert!(params(30.0, 1).validate().is_err());
+        assert!(params(20.0, 3).validate().is_err());
+    }
+}
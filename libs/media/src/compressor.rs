@@ -0,0 +1,249 @@
+//! Soft-knee compressor for the playback chain.
+//!
+//! Sits between output volume and the limiter. Where the limiter squashes
+//! every peak the same way, the compressor follows the level of the mix and
+//! brings loud passages down gradually, so a shouting peer and a quiet one
+//! end up closer together without the limiter's distortion.
+
+use crate::audio::SAMPLE_RATE;
+
+/// Width of the soft knee: the ratio phases in over this many dB centred on
+/// the threshold instead of switching on at it
+pub const COMPRESSOR_KNEE_DB: f32 = 6.0;
+
+/// Bounds for [`CompressorSettings`]; values outside are clamped
+pub const MIN_COMPRESSOR_THRESHOLD_DB: f32 = -60.0;
+pub const MAX_COMPRESSOR_RATIO: f32 = 20.0;
+pub const MAX_COMPRESSOR_ATTACK_MS: f32 = 200.0;
+pub const MAX_COMPRESSOR_RELEASE_MS: f32 = 2000.0;
+
+/// Level the detector treats as silence, so `log10` never sees zero
+const SILENCE_DB: f32 = -120.0;
+
+/// Parameters of the playback compressor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressorSettings {
+    pub enabled: bool,
+    /// Level, in dBFS, above which gain is reduced
+    pub threshold_db: f32,
+    /// dB over the threshold in for each dB over it out, 1 or more
+    pub ratio: f32,
+    /// Time to reach the new gain when the level rises
+    pub attack_ms: f32,
+    /// Time to let the gain back up when the level falls
+    pub release_ms: f32,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -18.0,
+            ratio: 3.0,
+            attack_ms: 5.0,
+            release_ms: 120.0,
+        }
+    }
+}
+
+impl CompressorSettings {
+    pub fn clamped(mut self) -> Self {
+        self.threshold_db = self.threshold_db.clamp(MIN_COMPRESSOR_THRESHOLD_DB, 0.0);
+        self.ratio = self.ratio.clamp(1.0, MAX_COMPRESSOR_RATIO);
+        self.attack_ms = self.attack_ms.clamp(0.0, MAX_COMPRESSOR_ATTACK_MS);
+        self.release_ms = self.release_ms.clamp(0.0, MAX_COMPRESSOR_RELEASE_MS);
+        self
+    }
+}
+
+/// Compressor state for one output stream. Left and right share one
+/// envelope so the stereo image does not shift when one side is louder.
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    settings: CompressorSettings,
+    sample_rate: u32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    /// Smoothed gain reduction in dB, 0 or below
+    envelope_db: f32,
+}
+
+impl Default for Compressor {
+    fn default() -> Self {
+        Self::new(CompressorSettings::default())
+    }
+}
+
+impl Compressor {
+    pub fn new(settings: CompressorSettings) -> Self {
+        let mut compressor = Self {
+            settings: settings.clamped(),
+            sample_rate: SAMPLE_RATE,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            envelope_db: 0.0,
+        };
+        compressor.update_coefficients();
+        compressor
+    }
+
+    pub fn settings(&self) -> CompressorSettings {
+        self.settings
+    }
+
+    /// Change the parameters. The envelope carries on, so a change while
+    /// playing does not click; turning the compressor off drops it.
+    pub fn set_settings(&mut self, settings: CompressorSettings) {
+        self.settings = settings.clamped();
+        if !self.settings.enabled {
+            self.envelope_db = 0.0;
+        }
+        self.update_coefficients();
+    }
+
+    /// Rate of the output stream, for the attack and release times
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate.max(1);
+        self.update_coefficients();
+    }
+
+    /// Forget the envelope, e.g. when the stream stops
+    pub fn reset(&mut self) {
+        self.envelope_db = 0.0;
+    }
+
+    /// Gain reduction currently applied, in dB (0 or below)
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.envelope_db
+    }
+
+    fn update_coefficients(&mut self) {
+        self.attack_coeff = smoothing_coeff(self.settings.attack_ms, self.sample_rate);
+        self.release_coeff = smoothing_coeff(self.settings.release_ms, self.sample_rate);
+    }
+
+    /// Compress one left/right frame. Returns it untouched when disabled.
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        if !self.settings.enabled {
+            return (left, right);
+        }
+
+        let peak = left.abs().max(right.abs());
+        let level_db = if peak > 0.0 {
+            (20.0 * peak.log10()).max(SILENCE_DB)
+        } else {
+            SILENCE_DB
+        };
+        let target_db = self.static_gain_db(level_db);
+        let coeff = if target_db < self.envelope_db {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope_db = coeff * self.envelope_db + (1.0 - coeff) * target_db;
+
+        let gain = 10f32.powf(self.envelope_db / 20.0);
+        (left * gain, right * gain)
+    }
+
+    /// Gain reduction for a steady `level_db`, with the knee blending from
+    /// no reduction into the full ratio
+    fn static_gain_db(&self, level_db: f32) -> f32 {
+        let slope = 1.0 / self.settings.ratio - 1.0;
+        let over = level_db - self.settings.threshold_db;
+        let half_knee = COMPRESSOR_KNEE_DB / 2.0;
+        if over <= -half_knee {
+            0.0
+        } else if over < half_knee {
+            slope * (over + half_knee).powi(2) / (2.0 * COMPRESSOR_KNEE_DB)
+        } else {
+            slope * over
+        }
+    }
+}
+
+/// One-pole smoothing coefficient reaching ~63% of a step in `time_ms`
+fn smoothing_coeff(time_ms: f32, sample_rate: u32) -> f32 {
+    let samples = time_ms * sample_rate as f32 / 1000.0;
+    if samples < 1.0 {
+        0.0
+    } else {
+        (-1.0 / samples).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> CompressorSettings {
+        CompressorSettings {
+            enabled: true,
+            threshold_db: -20.0,
+            ratio: 4.0,
+            attack_ms: 1.0,
+            release_ms: 50.0,
+        }
+    }
+
+    fn db(amplitude: f32) -> f32 {
+        20.0 * amplitude.abs().log10()
+    }
+
+    #[test]
+    fn bypass_is_bit_exact() {
+        let mut compressor = Compressor::default();
+        for i in 0..1000 {
+            let left = ((i as f32) * 0.37).sin();
+            let right = ((i as f32) * 0.11).cos() * 0.5;
+            assert_eq!(compressor.process(left, right), (left, right));
+        }
+        assert_eq!(compressor.gain_reduction_db(), 0.0);
+    }
+
+    #[test]
+    fn loud_input_settles_at_the_ratio_quiet_input_is_untouched() {
+        let mut compressor = Compressor::new(enabled());
+        // 0 dBFS is 20 dB over the threshold; 4:1 leaves 5 dB over
+        let mut out = 0.0;
+        for _ in 0..SAMPLE_RATE / 10 {
+            out = compressor.process(1.0, 1.0).0;
+        }
+        assert!((db(out) - -15.0).abs() < 0.1, "got {} dBFS", db(out));
+
+        let mut quiet = Compressor::new(enabled());
+        for _ in 0..SAMPLE_RATE / 10 {
+            let (left, _) = quiet.process(0.01, 0.01);
+            assert!((left - 0.01).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn knee_eases_in_around_the_threshold() {
+        let compressor = Compressor::new(enabled());
+        let at_threshold = compressor.static_gain_db(-20.0);
+        assert!(at_threshold < 0.0 && at_threshold > -1.0);
+        assert_eq!(compressor.static_gain_db(-23.5), 0.0);
+        assert!((compressor.static_gain_db(-10.0) - -7.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn release_lets_the_gain_back_up_and_reset_clears_it() {
+        let mut compressor = Compressor::new(enabled());
+        for _ in 0..SAMPLE_RATE / 10 {
+            compressor.process(1.0, -1.0);
+        }
+        let squeezed = compressor.gain_reduction_db();
+        assert!(squeezed < -10.0);
+
+        // 10 ms of silence: part way back, not all the way
+        for _ in 0..SAMPLE_RATE / 100 {
+            compressor.process(0.0, 0.0);
+        }
+        let recovering = compressor.gain_reduction_db();
+        assert!(recovering > squeezed && recovering < -1.0);
+
+        compressor.reset();
+        assert_eq!(compressor.gain_reduction_db(), 0.0);
+    }
+}
@@ -0,0 +1,192 @@
+//! ICE server pre-check.
+//!
+//! A throwaway peer connection gathers candidates against the configured
+//! ICE servers, without any peer on the other end. A server-reflexive
+//! candidate means a STUN server answered, a relay candidate means a TURN
+//! server gave us an allocation. Settings uses this to flag a broken TURN
+//! setup before a call rings and then never connects.
+
+use crate::{rtc_configuration, IceServerConfig};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use webrtc::api::media_engine::MediaEngine as WebRtcMediaEngine;
+use webrtc::api::APIBuilder;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+
+/// How long gathering may take before the check gives up. TURN allocations
+/// on a slow network take a few seconds; an unreachable server never answers.
+pub const CONNECTIVITY_CHECK_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ConnectivityReport {
+    /// A local interface candidate was gathered
+    pub host: bool,
+    /// A server-reflexive candidate was gathered: STUN works
+    pub stun: bool,
+    /// A relay candidate was gathered: TURN works
+    pub turn: bool,
+    /// Whether any `stun:`/`stuns:` URL is configured
+    pub stun_configured: bool,
+    /// Whether any `turn:`/`turns:` URL is configured. Without one, `turn`
+    /// being false is expected rather than a failure.
+    pub turn_configured: bool,
+    /// Gathering ran into `CONNECTIVITY_CHECK_TIMEOUT`. Whatever was found
+    /// before then is still reported.
+    pub timed_out: bool,
+    pub elapsed_ms: u64,
+}
+
+impl ConnectivityReport {
+    fn for_servers(ice_servers: &[IceServerConfig]) -> Self {
+        let has_scheme = |schemes: &[&str]| {
+            ice_servers
+                .iter()
+                .flat_map(|server| &server.urls)
+                .any(|url| {
+                    let scheme = url.split(':').next().unwrap_or_default();
+                    schemes.iter().any(|s| scheme.eq_ignore_ascii_case(s))
+                })
+        };
+        Self {
+            stun_configured: has_scheme(&["stun", "stuns"]),
+            turn_configured: has_scheme(&["turn", "turns"]),
+            ..Default::default()
+        }
+    }
+
+    /// Count a gathered candidate, given its SDP attribute text
+    fn record(&mut self, candidate: &str) {
+        let typ = candidate
+            .split_whitespace()
+            .skip_while(|&token| token != "typ")
+            .nth(1);
+        match typ {
+            Some("host") => self.host = true,
+            Some("srflx") => self.stun = true,
+            Some("relay") => self.turn = true,
+            _ => {}
+        }
+    }
+
+    /// Every configured kind of server has answered, no need to wait longer
+    fn is_complete(&self) -> bool {
+        (self.stun || !self.stun_configured) && (self.turn || !self.turn_configured)
+    }
+}
+
+/// Gather candidates against `ice_servers` and report which kinds came back
+/// within `timeout`
+pub async fn check_ice_servers(
+    ice_servers: &[IceServerConfig],
+    timeout: Duration,
+) -> Result<ConnectivityReport> {
+    let mut media_engine = WebRtcMediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(media_engine).build();
+    let pc = api
+        .new_peer_connection(rtc_configuration(ice_servers))
+        .await?;
+
+    let result = gather(&pc, ConnectivityReport::for_servers(ice_servers), timeout).await;
+    if let Err(e) = pc.close().await {
+        tracing::debug!("Failed to close connectivity check connection: {}", e);
+    }
+    result
+}
+
+async fn gather(
+    pc: &RTCPeerConnection,
+    mut report: ConnectivityReport,
+    timeout: Duration,
+) -> Result<ConnectivityReport> {
+    let (candidate_tx, mut candidate_rx) = tokio::sync::mpsc::unbounded_channel();
+    pc.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+        if let Some(candidate) = candidate.and_then(|c| c.to_json().ok()) {
+            let _ = candidate_tx.send(candidate.candidate);
+        }
+        Box::pin(async {})
+    }));
+
+    // An offer needs something to negotiate before ICE gathers for it
+    pc.create_data_channel("connectivity-check", None).await?;
+    let mut gathering_complete = pc.gathering_complete_promise().await;
+    let offer: RTCSessionDescription = pc.create_offer(None).await?;
+    pc.set_local_description(offer).await?;
+
+    let started = Instant::now();
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        tokio::select! {
+            candidate = candidate_rx.recv() => match candidate {
+                Some(candidate) => {
+                    report.record(&candidate);
+                    if report.is_complete() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            _ = gathering_complete.recv() => break,
+            _ = tokio::time::sleep_until(deadline) => {
+                report.timed_out = true;
+                break;
+            }
+        }
+    }
+    // Candidates already queued when gathering completed
+    while let Ok(candidate) = candidate_rx.try_recv() {
+        report.record(&candidate);
+    }
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(urls: &[&str]) -> IceServerConfig {
+        IceServerConfig {
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            username: None,
+            credential: None,
+        }
+    }
+
+    #[test]
+    fn candidates_are_counted_by_type() {
+        let mut report = ConnectivityReport::default();
+        report.record("candidate:1 1 udp 2130706431 192.168.1.2 50000 typ host");
+        assert!(report.host && !report.stun && !report.turn);
+        report.record(
+            "candidate:2 1 udp 1694498815 203.0.113.7 50000 typ srflx raddr 192.168.1.2 rport 50000",
+        );
+        assert!(report.stun && !report.turn);
+        report
+            .record("candidate:3 1 udp 16777215 198.51.100.4 3478 typ relay raddr 0.0.0.0 rport 0");
+        assert!(report.turn);
+    }
+
+    #[test]
+    fn stun_without_turn_is_complete_only_when_no_turn_is_configured() {
+        let srflx = "candidate:2 1 udp 1694498815 203.0.113.7 50000 typ srflx";
+
+        let mut stun_only = ConnectivityReport::for_servers(&[server(&["stun:stun.example.org"])]);
+        assert!(stun_only.stun_configured && !stun_only.turn_configured);
+        stun_only.record(srflx);
+        assert!(stun_only.is_complete());
+
+        let mut with_turn = ConnectivityReport::for_servers(&[
+            server(&["stun:stun.example.org"]),
+            server(&[
+                "TURN:turn.example.org:3478?transport=udp",
+                "turns:turn.example.org",
+            ]),
+        ]);
+        assert!(with_turn.turn_configured);
+        with_turn.record(srflx);
+        assert!(with_turn.stun && !with_turn.is_complete());
+    }
+}
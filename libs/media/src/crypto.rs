@@ -4,28 +4,163 @@
 //! 1. Generate ephemeral X25519 keypair
 //! 2. Exchange public keys via signaling server
 //! 3. Derive shared secret using Diffie-Hellman
-//! 4. Use shared secret as AES-256-GCM key for encrypting audio packets
+//! 4. Derive one AES-256-GCM key per direction from the shared secret, so
+//!    the two peers' nonce counters never meet under the same key
+//!
+//! The media key moves to the next one in a chain during long calls (see
+//! `CryptoContext::ratchet`), which bounds how much audio one key seals.
+//! Each ciphertext names the epoch of the key that sealed it.
+//!
+//! Group calls add a sender key: each participant encrypts its audio with a
+//! random key of its own and hands it to every peer sealed under the secret
+//! shared with that peer.
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
 use ring::agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519};
-use ring::rand::SystemRandom;
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 /// Cryptographic context for E2EE communication
 /// Thread-safe wrapper around ring's AES-GCM key
 pub struct CryptoContext {
-    /// AES-GCM key derived from X25519 key exchange, wrapped in Mutex for thread-safety
-    key: Mutex<LessSafeKey>,
+    /// Key our packets are sealed with, starting from the one derived from
+    /// the X25519 key exchange
+    send: Mutex<KeyEpoch>,
+    /// Keys the peer's packets are opened with. The peer ratchets on its
+    /// own schedule, so this follows the epochs it sees.
+    recv: Mutex<RecvKeys>,
     /// Counter for generating unique nonces
     nonce_counter: AtomicU64,
+    /// Nonce counters of packets already decrypted
+    replay: Mutex<ReplayWindow>,
+    /// Short authentication string of the key exchange, if this context
+    /// came from one
+    sas: Option<String>,
 }
 
 // Explicitly implement Send + Sync since we're protecting access with Mutex
 unsafe impl Send for CryptoContext {}
 unsafe impl Sync for CryptoContext {}
 
+/// Length of an encoded X25519 public key
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// Length of a group call sender key
+pub const SENDER_KEY_LEN: usize = 32;
+
+/// How long the key of the previous epoch still opens packets after the
+/// peer moved on, for ones already in flight
+pub const PREVIOUS_EPOCH_GRACE: Duration = Duration::from_secs(5);
+
+/// Bytes in front of the nonce: the key epoch
+const EPOCH_LEN: usize = 1;
+
+/// Decimal digits in a short authentication string
+pub const SAS_DIGITS: usize = 6;
+
+/// How many nonce counters back from the newest `decrypt` still accepts a
+/// packet it has not seen. The window is a 64-bit bitmap, so this is also
+/// the largest size `set_replay_window` takes.
+pub const MAX_REPLAY_WINDOW: u32 = 64;
+
+/// What went wrong in the crypto module. The peer-supplied variants
+/// (`Base64Decode`, `InvalidPublicKeyLength`, `KeyAgreementFailed`,
+/// `InvalidSenderKey`) mean the other side sent something unusable; the
+/// rest are on our side or in transit.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CryptoError {
+    /// The system random source failed while generating a key or nonce
+    #[error("Failed to generate key material")]
+    KeyGenerationFailed,
+    /// Key exchange was asked for before we generated our key pair, or
+    /// after it was used up
+    #[error("No key pair generated for this call")]
+    NoKeyPair,
+    /// A peer's public key was not valid base64
+    #[error("Invalid public key: not valid base64 ({0})")]
+    Base64Decode(String),
+    /// A peer's public key decoded to the wrong number of bytes
+    #[error("Invalid public key: expected {expected} bytes, got {actual}")]
+    InvalidPublicKeyLength { expected: usize, actual: usize },
+    /// X25519 rejected the peer's public key
+    #[error("Key agreement with the peer's public key failed")]
+    KeyAgreementFailed,
+    /// Deriving an AES key, or the next one in the ratchet, failed
+    #[error("Key derivation failed")]
+    KeyDerivationFailed,
+    /// A sealed sender key was not valid base64 or did not open with the
+    /// key shared with its sender
+    #[error("Invalid sender key: {0}")]
+    InvalidSenderKey(String),
+    #[error("Encryption failed")]
+    EncryptFailed,
+    /// A ciphertext shorter than its epoch, nonce and tag
+    #[error("Ciphertext too short ({0} bytes)")]
+    Truncated(usize),
+    /// A ciphertext that did not authenticate under the key of its epoch
+    #[error("Decryption failed: packet did not authenticate")]
+    DecryptAuthFailed,
+    /// A ciphertext from a key epoch we no longer, or never, had
+    #[error("Decryption failed: no key for epoch {0}")]
+    UnknownEpoch(u8),
+    /// A ciphertext whose nonce counter was already decrypted, or is too
+    /// far behind the newest one to tell
+    #[error("Replayed packet (nonce counter {0})")]
+    Replayed(u64),
+    #[error("Crypto state lock poisoned")]
+    LockPoisoned,
+}
+
+/// Sliding window over the nonce counters `decrypt` accepted. Packets may
+/// arrive out of order, so anything within `size` of the newest counter is
+/// accepted once.
+struct ReplayWindow {
+    /// Newest counter accepted
+    newest: Option<u64>,
+    /// Bit `i` is set once counter `newest - i` was accepted
+    seen: u64,
+    size: u32,
+}
+
+impl ReplayWindow {
+    fn is_fresh(&self, counter: u64) -> bool {
+        let Some(newest) = self.newest else {
+            return true;
+        };
+        if counter > newest {
+            return true;
+        }
+        let age = newest - counter;
+        age < u64::from(self.size) && self.seen & (1 << age) == 0
+    }
+
+    fn accept(&mut self, counter: u64) {
+        match self.newest {
+            Some(newest) if counter <= newest => self.seen |= 1 << (newest - counter),
+            Some(newest) => {
+                let shift = counter - newest;
+                self.seen = if shift >= 64 { 0 } else { self.seen << shift } | 1;
+                self.newest = Some(counter);
+            }
+            None => {
+                self.seen = 1;
+                self.newest = Some(counter);
+            }
+        }
+    }
+}
+
+/// Random media key for our audio in a group call. Every peer gets the same
+/// key, sealed with the secret shared with that peer, so each frame is
+/// encrypted once whatever the number of peers.
+pub struct SenderKey {
+    bytes: [u8; SENDER_KEY_LEN],
+}
+
 /// Key pair for X25519 key exchange
 pub struct KeyPair {
     private_key: EphemeralPrivateKey,
@@ -34,10 +169,13 @@ pub struct KeyPair {
 
 impl KeyPair {
     /// Generate a new X25519 key pair
-    pub fn generate() -> Result<Self, ring::error::Unspecified> {
+    pub fn generate() -> Result<Self, CryptoError> {
         let rng = SystemRandom::new();
-        let private_key = EphemeralPrivateKey::generate(&X25519, &rng)?;
-        let public_key = private_key.compute_public_key()?;
+        let private_key = EphemeralPrivateKey::generate(&X25519, &rng)
+            .map_err(|_| CryptoError::KeyGenerationFailed)?;
+        let public_key = private_key
+            .compute_public_key()
+            .map_err(|_| CryptoError::KeyGenerationFailed)?;
 
         Ok(Self {
             private_key,
@@ -54,7 +192,7 @@ impl KeyPair {
     pub fn derive_shared_secret(
         self,
         peer_public_key_bytes: &[u8],
-    ) -> Result<CryptoContext, String> {
+    ) -> Result<CryptoContext, CryptoError> {
         let peer_public_key = UnparsedPublicKey::new(&X25519, peer_public_key_bytes);
 
         let shared_secret =
@@ -64,24 +202,235 @@ impl KeyPair {
                 key_bytes.copy_from_slice(&key_material[..32]);
                 key_bytes
             })
-            .map_err(|_| "Key exchange failed".to_string())?;
+            .map_err(|_| CryptoError::KeyAgreementFailed)?;
+
+        let send = direction_key(
+            &shared_secret,
+            &self.public_key_bytes,
+            peer_public_key_bytes,
+        )?;
+        let recv = direction_key(
+            &shared_secret,
+            peer_public_key_bytes,
+            &self.public_key_bytes,
+        )?;
+        let mut ctx = CryptoContext::with_keys(&send, &recv)?;
+        ctx.sas = Some(sas_code(&self.public_key_bytes, peer_public_key_bytes));
+        Ok(ctx)
+    }
+}
+
+/// Media key for audio from the holder of `from` to the holder of `to`.
+/// Both peers count nonces from zero, so each direction needs a key of its
+/// own for no (key, nonce) pair to seal two packets.
+fn direction_key(
+    shared_secret: &[u8; 32],
+    from: &[u8],
+    to: &[u8],
+) -> Result<[u8; 32], CryptoError> {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, b"p2p-chat media v1").extract(shared_secret);
+    let mut key = [0u8; 32];
+    prk.expand(&[b"direction", from, to], HkdfLen(key.len()))
+        .and_then(|okm| okm.fill(&mut key))
+        .map_err(|_| CryptoError::KeyDerivationFailed)?;
+    Ok(key)
+}
+
+/// `SAS_DIGITS` decimal digits from both public keys through HKDF. The keys
+/// are sorted first, so both sides get the same code.
+///
+/// Neither side commits to its key before seeing the other's, so this only
+/// catches a relay that swapped keys blindly. One that holds both real keys
+/// before sending its own can generate key pairs until the two legs give
+/// the same code, about 10^6 X25519 keygens, which takes seconds.
+fn sas_code(our_public_key: &[u8], peer_public_key: &[u8]) -> String {
+    let (first, second) = if our_public_key <= peer_public_key {
+        (our_public_key, peer_public_key)
+    } else {
+        (peer_public_key, our_public_key)
+    };
+    let mut input = first.to_vec();
+    input.extend_from_slice(second);
+
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, b"p2p-chat sas v1").extract(&input);
+    let mut output = [0u8; 4];
+    prk.expand(&[b"sas"], HkdfLen(output.len()))
+        .and_then(|okm| okm.fill(&mut output))
+        .expect("4 bytes is a valid HKDF-SHA256 output length");
+    let modulus = 10u32.pow(SAS_DIGITS as u32);
+    format!(
+        "{:0width$}",
+        u32::from_be_bytes(output) % modulus,
+        width = SAS_DIGITS
+    )
+}
+
+/// HKDF output length
+struct HkdfLen(usize);
+
+impl hkdf::KeyType for HkdfLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// One key of the ratchet and the epoch it belongs to. Epochs wrap at 256;
+/// they only need to tell neighbours apart.
+struct KeyEpoch {
+    epoch: u8,
+    secret: [u8; 32],
+    key: LessSafeKey,
+    /// When this epoch began, and packets sealed in it, for `ratchet_if_due`
+    started: Instant,
+    packets: u64,
+}
+
+impl KeyEpoch {
+    fn new(epoch: u8, secret: [u8; 32]) -> Result<Self, CryptoError> {
+        let unbound_key = UnboundKey::new(&aead::AES_256_GCM, &secret)
+            .map_err(|_| CryptoError::KeyDerivationFailed)?;
+        Ok(Self {
+            epoch,
+            secret,
+            key: LessSafeKey::new(unbound_key),
+            started: Instant::now(),
+            packets: 0,
+        })
+    }
 
-        CryptoContext::new(&shared_secret)
+    /// The key of the next epoch, derived one way from this one
+    fn next(&self) -> Result<Self, CryptoError> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, b"p2p-chat ratchet v1").extract(&self.secret);
+        let mut secret = [0u8; 32];
+        prk.expand(&[b"media key"], HkdfLen(secret.len()))
+            .and_then(|okm| okm.fill(&mut secret))
+            .map_err(|_| CryptoError::KeyDerivationFailed)?;
+        Self::new(self.epoch.wrapping_add(1), secret)
+    }
+}
+
+/// Receive side of the ratchet
+struct RecvKeys {
+    current: KeyEpoch,
+    /// The epoch before `current` and when it was replaced
+    previous: Option<(KeyEpoch, Instant)>,
+}
+
+impl SenderKey {
+    pub fn generate() -> Result<Self, CryptoError> {
+        let mut bytes = [0u8; SENDER_KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| CryptoError::KeyGenerationFailed)?;
+        Ok(Self { bytes })
+    }
+
+    /// Context that encrypts or decrypts media under this key
+    pub fn context(&self) -> Result<CryptoContext, CryptoError> {
+        CryptoContext::new(&self.bytes)
+    }
+
+    /// This key encrypted for one peer with the secret shared with it, as
+    /// base64. Both sides of a pairwise secret seal with it, so the nonce is
+    /// random rather than counted.
+    pub fn seal(&self, pairwise: &CryptoContext) -> Result<String, CryptoError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| CryptoError::KeyGenerationFailed)?;
+        pairwise
+            .encrypt_with_nonce(nonce, &self.bytes)
+            .map(|sealed| BASE64.encode(sealed))
+    }
+
+    /// Open a key sealed by a peer with `seal`
+    pub fn open(sealed_base64: &str, pairwise: &CryptoContext) -> Result<Self, CryptoError> {
+        let sealed = BASE64
+            .decode(sealed_base64)
+            .map_err(|e| CryptoError::InvalidSenderKey(format!("not valid base64 ({})", e)))?;
+        // Nonces here are random, not counted, so no replay window
+        let opened = pairwise
+            .open(&sealed)
+            .map_err(|e| CryptoError::InvalidSenderKey(e.to_string()))?;
+        let bytes = opened.try_into().map_err(|opened: Vec<u8>| {
+            CryptoError::InvalidSenderKey(format!(
+                "expected {} bytes, got {}",
+                SENDER_KEY_LEN,
+                opened.len()
+            ))
+        })?;
+        Ok(Self { bytes })
     }
 }
 
 impl CryptoContext {
-    /// Create a new crypto context from a 32-byte key
-    fn new(key_bytes: &[u8; 32]) -> Result<Self, String> {
-        let unbound_key = UnboundKey::new(&aead::AES_256_GCM, key_bytes)
-            .map_err(|_| "Failed to create AES key".to_string())?;
+    /// Context sealing and opening with the same 32-byte key, for a group
+    /// call sender key, which only ever seals in one direction
+    fn new(key_bytes: &[u8; 32]) -> Result<Self, CryptoError> {
+        Self::with_keys(key_bytes, key_bytes)
+    }
 
+    /// Context sealing with `send` and opening with `recv`
+    fn with_keys(send: &[u8; 32], recv: &[u8; 32]) -> Result<Self, CryptoError> {
         Ok(Self {
-            key: Mutex::new(LessSafeKey::new(unbound_key)),
+            send: Mutex::new(KeyEpoch::new(0, *send)?),
+            recv: Mutex::new(RecvKeys {
+                current: KeyEpoch::new(0, *recv)?,
+                previous: None,
+            }),
             nonce_counter: AtomicU64::new(0),
+            replay: Mutex::new(ReplayWindow {
+                newest: None,
+                seen: 0,
+                size: MAX_REPLAY_WINDOW,
+            }),
+            sas: None,
         })
     }
 
+    /// Short code both sides of the key exchange can read aloud to compare.
+    /// The same on both sides whoever called whom. Without a commitment to
+    /// the keys it does not stop a relay that searches for a colliding key,
+    /// see the free `sas_code`. None for a group call sender key, which has no
+    /// exchange of its own.
+    pub fn sas_code(&self) -> Option<String> {
+        self.sas.clone()
+    }
+
+    /// How far behind the newest packet an unseen one is still accepted,
+    /// clamped to `1..=MAX_REPLAY_WINDOW`. Smaller rejects more late
+    /// packets along with replays.
+    pub fn set_replay_window(&self, size: u32) {
+        if let Ok(mut replay) = self.replay.lock() {
+            replay.size = size.clamp(1, MAX_REPLAY_WINDOW);
+        }
+    }
+
+    /// Move our packets to the next key epoch. The key is derived from the
+    /// current one through HKDF and the current one is dropped. The peer
+    /// follows from the epoch in each packet.
+    pub fn ratchet(&self) -> Result<(), CryptoError> {
+        let mut send = self.send.lock().map_err(|_| CryptoError::LockPoisoned)?;
+        *send = send.next()?;
+        Ok(())
+    }
+
+    /// `ratchet` if the current epoch is `max_age` old or sealed
+    /// `max_packets` packets. Returns whether it did.
+    pub fn ratchet_if_due(&self, max_age: Duration, max_packets: u64) -> Result<bool, CryptoError> {
+        let mut send = self.send.lock().map_err(|_| CryptoError::LockPoisoned)?;
+        if send.started.elapsed() < max_age && send.packets < max_packets {
+            return Ok(false);
+        }
+        *send = send.next()?;
+        Ok(true)
+    }
+
+    /// Epoch of the key our packets are sealed with
+    pub fn epoch(&self) -> u8 {
+        self.send.lock().map(|send| send.epoch).unwrap_or(0)
+    }
+
     /// Generate a unique nonce for encryption
     fn next_nonce(&self) -> [u8; NONCE_LEN] {
         let counter = self.nonce_counter.fetch_add(1, Ordering::SeqCst);
@@ -92,51 +441,137 @@ impl CryptoContext {
     }
 
     /// Encrypt audio data in-place
-    /// Returns the nonce prepended to the ciphertext
-    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
-        let nonce_bytes = self.next_nonce();
+    /// Returns the key epoch and nonce prepended to the ciphertext
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.encrypt_with_nonce(self.next_nonce(), plaintext)
+    }
+
+    fn encrypt_with_nonce(
+        &self,
+        nonce_bytes: [u8; NONCE_LEN],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
         let nonce = Nonce::assume_unique_for_key(nonce_bytes);
 
         let mut buffer = plaintext.to_vec();
         // Reserve space for the authentication tag
         buffer.extend_from_slice(&[0u8; 16]);
 
-        let key = self.key.lock().map_err(|_| "Lock poisoned")?;
-        key.seal_in_place_separate_tag(nonce, Aad::empty(), &mut buffer[..plaintext.len()])
+        let mut send = self.send.lock().map_err(|_| CryptoError::LockPoisoned)?;
+        let sealed = send
+            .key
+            .seal_in_place_separate_tag(nonce, Aad::empty(), &mut buffer[..plaintext.len()])
             .map(|tag| {
                 buffer[plaintext.len()..].copy_from_slice(tag.as_ref());
-                let mut result = nonce_bytes.to_vec();
+                let mut result = vec![send.epoch];
+                result.extend_from_slice(&nonce_bytes);
                 result.extend_from_slice(&buffer);
                 result
             })
-            .map_err(|_| "Encryption failed".to_string())
+            .map_err(|_| CryptoError::EncryptFailed)?;
+        send.packets += 1;
+        Ok(sealed)
     }
 
     /// Decrypt audio data
-    /// Expects nonce prepended to ciphertext
-    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
-        if ciphertext.len() < NONCE_LEN + 16 {
-            return Err("Ciphertext too short".to_string());
+    /// Expects the key epoch and nonce prepended to ciphertext. A newer
+    /// epoch moves the receive key forward; the previous one still opens
+    /// packets for `PREVIOUS_EPOCH_GRACE`. A packet is accepted once:
+    /// its nonce counter again, or one older than the replay window, is
+    /// `CryptoError::Replayed`.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let counter = nonce_counter(ciphertext)?;
+        if !self
+            .replay
+            .lock()
+            .is_ok_and(|replay| replay.is_fresh(counter))
+        {
+            return Err(CryptoError::Replayed(counter));
         }
 
-        let (nonce_bytes, encrypted) = ciphertext.split_at(NONCE_LEN);
-        let nonce = Nonce::try_assume_unique_for_key(nonce_bytes)
-            .map_err(|_| "Invalid nonce".to_string())?;
+        // Recorded only once authentic, so a forged counter cannot move
+        // the window
+        let plaintext = self.open(ciphertext)?;
+        let mut replay = self.replay.lock().map_err(|_| CryptoError::LockPoisoned)?;
+        if !replay.is_fresh(counter) {
+            // A copy decrypted alongside this one got there first
+            return Err(CryptoError::Replayed(counter));
+        }
+        replay.accept(counter);
+        Ok(plaintext)
+    }
 
-        let mut buffer = encrypted.to_vec();
+    fn open(&self, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if ciphertext.len() < EPOCH_LEN + NONCE_LEN + 16 {
+            return Err(CryptoError::Truncated(ciphertext.len()));
+        }
 
-        let key = self.key.lock().map_err(|_| "Lock poisoned")?;
-        key.open_in_place(nonce, Aad::empty(), &mut buffer)
-            .map(|plaintext| plaintext.to_vec())
-            .map_err(|_| "Decryption failed".to_string())
+        let epoch = ciphertext[0];
+        let (nonce_bytes, encrypted) = ciphertext[EPOCH_LEN..].split_at(NONCE_LEN);
+        let open_with = |key: &LessSafeKey| {
+            let nonce = Nonce::try_assume_unique_for_key(nonce_bytes)
+                .map_err(|_| CryptoError::Truncated(ciphertext.len()))?;
+            let mut buffer = encrypted.to_vec();
+            key.open_in_place(nonce, Aad::empty(), &mut buffer)
+                .map(|plaintext| plaintext.to_vec())
+                .map_err(|_| CryptoError::DecryptAuthFailed)
+        };
+
+        let mut recv = self.recv.lock().map_err(|_| CryptoError::LockPoisoned)?;
+        let ahead = epoch.wrapping_sub(recv.current.epoch);
+        if ahead == 0 {
+            return open_with(&recv.current.key);
+        }
+        if ahead >= 128 {
+            // Behind the current epoch: only the previous one is kept
+            return match &recv.previous {
+                Some((previous, retired))
+                    if previous.epoch == epoch && retired.elapsed() < PREVIOUS_EPOCH_GRACE =>
+                {
+                    open_with(&previous.key)
+                }
+                _ => Err(CryptoError::UnknownEpoch(epoch)),
+            };
+        }
+
+        // The peer ratcheted. Moved to only once a packet authenticates, so
+        // a forged epoch cannot push the keys forward.
+        let mut next = recv.current.next()?;
+        for _ in 1..ahead {
+            next = next.next()?;
+        }
+        let plaintext = open_with(&next.key)?;
+        let previous = std::mem::replace(&mut recv.current, next);
+        recv.previous = Some((previous, Instant::now()));
+        Ok(plaintext)
     }
 }
 
-/// Parse a base64 encoded public key
-pub fn parse_public_key(base64_key: &str) -> Result<Vec<u8>, String> {
-    BASE64
+/// Counter in the nonce `encrypt` put in front of `ciphertext`
+fn nonce_counter(ciphertext: &[u8]) -> Result<u64, CryptoError> {
+    ciphertext
+        .get(EPOCH_LEN + 4..EPOCH_LEN + NONCE_LEN)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+        .ok_or(CryptoError::Truncated(ciphertext.len()))
+}
+
+/// Parse a base64 encoded public key, rejecting anything that is not
+/// exactly `PUBLIC_KEY_LEN` bytes so bad keys fail here rather than during
+/// key agreement.
+pub fn parse_public_key(base64_key: &str) -> Result<Vec<u8>, CryptoError> {
+    let bytes = BASE64
         .decode(base64_key)
-        .map_err(|e| format!("Invalid base64: {}", e))
+        .map_err(|e| CryptoError::Base64Decode(e.to_string()))?;
+
+    if bytes.len() != PUBLIC_KEY_LEN {
+        return Err(CryptoError::InvalidPublicKeyLength {
+            expected: PUBLIC_KEY_LEN,
+            actual: bytes.len(),
+        });
+    }
+
+    Ok(bytes)
 }
 
 #[cfg(test)]
@@ -164,4 +599,225 @@ mod tests {
 
         assert_eq!(plaintext.as_slice(), decrypted.as_slice());
     }
+
+    #[test]
+    fn each_direction_has_its_own_key() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let alice_public = alice.public_key_bytes.clone();
+        let bob_public = bob.public_key_bytes.clone();
+        let alice_ctx = alice.derive_shared_secret(&bob_public).unwrap();
+        let bob_ctx = bob.derive_shared_secret(&alice_public).unwrap();
+
+        // Same plaintext, same nonce counter, different ciphertext
+        let from_alice = alice_ctx.encrypt(b"frame").unwrap();
+        let from_bob = bob_ctx.encrypt(b"frame").unwrap();
+        assert_eq!(from_alice[..1 + NONCE_LEN], from_bob[..1 + NONCE_LEN]);
+        assert_ne!(from_alice, from_bob);
+
+        assert_eq!(bob_ctx.decrypt(&from_alice).unwrap(), b"frame");
+        assert_eq!(alice_ctx.decrypt(&from_bob).unwrap(), b"frame");
+        // Our own packets do not open with the key for the peer's
+        let own = alice_ctx.encrypt(b"frame").unwrap();
+        assert_eq!(alice_ctx.decrypt(&own), Err(CryptoError::DecryptAuthFailed));
+    }
+
+    #[test]
+    fn sender_key_opens_only_with_the_pairwise_secret() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let carol = KeyPair::generate().unwrap();
+        let alice_public = alice.public_key_bytes.clone();
+        let bob_public = bob.public_key_bytes.clone();
+        let alice_ctx = alice.derive_shared_secret(&bob_public).unwrap();
+        let bob_ctx = bob.derive_shared_secret(&alice_public).unwrap();
+        let carol_ctx = carol.derive_shared_secret(&alice_public).unwrap();
+
+        let sender_key = SenderKey::generate().unwrap();
+        let sealed = sender_key.seal(&alice_ctx).unwrap();
+        assert!(matches!(
+            SenderKey::open(&sealed, &carol_ctx),
+            Err(CryptoError::InvalidSenderKey(_))
+        ));
+
+        let opened = SenderKey::open(&sealed, &bob_ctx).unwrap();
+        let ciphertext = sender_key.context().unwrap().encrypt(b"frame").unwrap();
+        let decrypted = opened.context().unwrap().decrypt(&ciphertext).unwrap();
+        assert_eq!(decrypted, b"frame");
+    }
+
+    #[test]
+    fn replayed_packets_are_rejected_apart_from_corrupt_ones() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let alice_public = alice.public_key_bytes.clone();
+        let bob_public = bob.public_key_bytes.clone();
+        let alice_ctx = alice.derive_shared_secret(&bob_public).unwrap();
+        let bob_ctx = bob.derive_shared_secret(&alice_public).unwrap();
+
+        let packets: Vec<Vec<u8>> = (0..70)
+            .map(|_| alice_ctx.encrypt(b"frame").unwrap())
+            .collect();
+
+        // Out of order is fine, twice is not
+        assert!(bob_ctx.decrypt(&packets[1]).is_ok());
+        assert!(bob_ctx.decrypt(&packets[0]).is_ok());
+        assert_eq!(bob_ctx.decrypt(&packets[1]), Err(CryptoError::Replayed(1)));
+
+        let mut corrupt = packets[2].clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            bob_ctx.decrypt(&corrupt),
+            Err(CryptoError::DecryptAuthFailed)
+        );
+        assert!(
+            bob_ctx.decrypt(&packets[2]).is_ok(),
+            "forgery left no trace"
+        );
+
+        // Counter 69 moves the window past 5, which was never seen
+        assert!(bob_ctx.decrypt(&packets[69]).is_ok());
+        assert_eq!(bob_ctx.decrypt(&packets[5]), Err(CryptoError::Replayed(5)));
+        assert!(bob_ctx.decrypt(&packets[6]).is_ok());
+
+        bob_ctx.set_replay_window(4);
+        assert!(bob_ctx.decrypt(&packets[66]).is_ok());
+        assert_eq!(
+            bob_ctx.decrypt(&packets[65]),
+            Err(CryptoError::Replayed(65))
+        );
+    }
+
+    #[test]
+    fn previous_epoch_still_decrypts_after_a_ratchet() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let alice_public = alice.public_key_bytes.clone();
+        let bob_public = bob.public_key_bytes.clone();
+        let alice_ctx = alice.derive_shared_secret(&bob_public).unwrap();
+        let bob_ctx = bob.derive_shared_secret(&alice_public).unwrap();
+
+        let in_flight = alice_ctx.encrypt(b"epoch 0").unwrap();
+        let late = alice_ctx.encrypt(b"epoch 0, late").unwrap();
+        alice_ctx.ratchet().unwrap();
+        assert_eq!(alice_ctx.epoch(), 1);
+        let current = alice_ctx.encrypt(b"epoch 1").unwrap();
+        assert_eq!(current[0], 1);
+
+        // Epoch 1 arrives first and moves Bob on; epoch 0 still opens
+        assert_eq!(bob_ctx.decrypt(&current).unwrap(), b"epoch 1");
+        assert_eq!(bob_ctx.decrypt(&in_flight).unwrap(), b"epoch 0");
+
+        // Skipping epoch 2 keeps epoch 1 as the previous one; epoch 0 is
+        // gone for good
+        let in_flight = alice_ctx.encrypt(b"epoch 1 again").unwrap();
+        alice_ctx.ratchet().unwrap();
+        alice_ctx.ratchet().unwrap();
+        let skipped = alice_ctx.encrypt(b"epoch 3").unwrap();
+        assert_eq!(bob_ctx.decrypt(&skipped).unwrap(), b"epoch 3");
+        assert_eq!(bob_ctx.decrypt(&in_flight).unwrap(), b"epoch 1 again");
+        assert_eq!(bob_ctx.decrypt(&late), Err(CryptoError::UnknownEpoch(0)));
+
+        // Bob's own direction is untouched by Alice's ratchet
+        assert_eq!(bob_ctx.epoch(), 0);
+        let reply = bob_ctx.encrypt(b"reply").unwrap();
+        assert_eq!(alice_ctx.decrypt(&reply).unwrap(), b"reply");
+    }
+
+    #[test]
+    fn ratchet_is_due_by_packets_or_age() {
+        let ctx = SenderKey::generate().unwrap().context().unwrap();
+        for _ in 0..3 {
+            ctx.encrypt(b"frame").unwrap();
+        }
+        assert!(!ctx.ratchet_if_due(Duration::from_secs(60), 4).unwrap());
+        ctx.encrypt(b"frame").unwrap();
+        assert!(ctx.ratchet_if_due(Duration::from_secs(60), 4).unwrap());
+        assert_eq!(ctx.epoch(), 1);
+        assert!(ctx.ratchet_if_due(Duration::ZERO, 4).unwrap());
+        assert_eq!(ctx.epoch(), 2);
+    }
+
+    #[test]
+    fn sas_code_matches_on_both_sides_and_changes_with_the_keys() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let alice_public = alice.public_key_bytes.clone();
+        let bob_public = bob.public_key_bytes.clone();
+        let mallory_public = mallory.public_key_bytes.clone();
+
+        let alice_sas = alice
+            .derive_shared_secret(&bob_public)
+            .unwrap()
+            .sas_code()
+            .unwrap();
+        let bob_sas = bob
+            .derive_shared_secret(&alice_public)
+            .unwrap()
+            .sas_code()
+            .unwrap();
+        assert_eq!(alice_sas, bob_sas);
+        assert_eq!(alice_sas.len(), SAS_DIGITS);
+        assert!(alice_sas.chars().all(|c| c.is_ascii_digit()));
+
+        // Another key in Bob's place gives another code here. A relay that
+        // searches key pairs can still find one that matches; see sas_code.
+        let intercepted = sas_code(&alice_public, &mallory_public);
+        assert_ne!(intercepted, bob_sas);
+        assert!(SenderKey::generate()
+            .unwrap()
+            .context()
+            .unwrap()
+            .sas_code()
+            .is_none());
+    }
+
+    #[test]
+    fn parse_public_key_accepts_generated_keys() {
+        let keypair = KeyPair::generate().unwrap();
+        let parsed = parse_public_key(&keypair.public_key_base64()).unwrap();
+        assert_eq!(parsed, keypair.public_key_bytes);
+    }
+
+    #[test]
+    fn parse_public_key_rejects_wrong_lengths() {
+        let too_short = BASE64.encode([7u8; PUBLIC_KEY_LEN - 1]);
+        let too_long = BASE64.encode([7u8; PUBLIC_KEY_LEN + 1]);
+
+        assert_eq!(
+            parse_public_key(&too_short),
+            Err(CryptoError::InvalidPublicKeyLength {
+                expected: 32,
+                actual: 31
+            })
+        );
+        assert_eq!(
+            parse_public_key(&too_long),
+            Err(CryptoError::InvalidPublicKeyLength {
+                expected: 32,
+                actual: 33
+            })
+        );
+        assert!(parse_public_key("").is_err());
+    }
+
+    #[test]
+    fn low_order_public_key_fails_key_agreement() {
+        let keypair = KeyPair::generate().unwrap();
+        let zero = parse_public_key(&BASE64.encode([0u8; PUBLIC_KEY_LEN])).unwrap();
+        assert!(matches!(
+            keypair.derive_shared_secret(&zero),
+            Err(CryptoError::KeyAgreementFailed)
+        ));
+    }
+
+    #[test]
+    fn parse_public_key_rejects_non_base64() {
+        let err = parse_public_key("not*base64!").unwrap_err();
+        assert!(matches!(err, CryptoError::Base64Decode(_)));
+        assert!(err
+            .to_string()
+            .starts_with("Invalid public key: not valid base64"));
+    }
 }
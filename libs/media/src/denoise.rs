@@ -0,0 +1,361 @@
+//! Spectral noise suppression for the capture path.
+//!
+//! [`NoiseSuppressor`] learns the spectrum of steady background noise (fans,
+//! hum, hiss) while nobody is talking and removes it from every frame with a
+//! per-bin Wiener gain. It works on 20 ms frames overlapping by half and
+//! delays the signal by one frame, [`NOISE_SUPPRESSOR_DELAY_SAMPLES`].
+
+use crate::audio::FRAME_SIZE;
+use std::collections::VecDeque;
+
+/// Analysis frame, 20 ms
+const WINDOW: usize = FRAME_SIZE;
+/// Frames overlap by half; a frame is analysed every hop
+const HOP: usize = WINDOW / 2;
+/// FFT length, the frame zero-padded to a power of two
+const FFT_SIZE: usize = 1024;
+const BINS: usize = FFT_SIZE / 2 + 1;
+
+/// Output lags input by one frame (20 ms): half of it waiting for the rest
+/// of the frame, half because callers hand over blocks that do not line up
+/// with hops
+pub const NOISE_SUPPRESSOR_DELAY_SAMPLES: usize = WINDOW;
+
+/// Hops at the start that train the noise estimate whatever the VAD says,
+/// so suppression works from the first second (~200 ms)
+const STARTUP_HOPS: u32 = 20;
+/// Noise estimate smoothing per hop while the VAD reports silence
+/// (time constant ~200 ms)
+const NOISE_SMOOTHING: f32 = 0.95;
+/// Decision-directed a priori SNR smoothing. Close to 1 is what keeps
+/// residual noise from turning into musical tones: a bin has to stay above
+/// the noise for several frames before its gain opens.
+const PRIORI_SNR_SMOOTHING: f32 = 0.98;
+/// Lowest gain applied to a bin (-20 dB). Leaving some noise in sounds more
+/// natural than gating it out and masks what the estimate gets wrong.
+const GAIN_FLOOR: f32 = 0.1;
+/// A frame this many times louder than both the previous frame and the
+/// noise is an onset. Its bins use the instantaneous SNR, so the slow
+/// decision-directed estimate does not clip the start of plosives.
+const TRANSIENT_RATIO: f32 = 4.0;
+/// Keeps SNRs finite before any noise has been measured
+const NOISE_POWER_MIN: f32 = 1e-10;
+
+/// Radix-2 FFT of one fixed length
+#[derive(Debug, Clone)]
+struct Fft {
+    /// `e^(-2πik/n)` for `k < n/2`
+    twiddles: Vec<(f32, f32)>,
+    bit_reverse: Vec<usize>,
+}
+
+impl Fft {
+    fn new(n: usize) -> Self {
+        debug_assert!(n.is_power_of_two());
+        let bits = n.trailing_zeros();
+        let twiddles = (0..n / 2)
+            .map(|k| {
+                let angle = -2.0 * std::f64::consts::PI * k as f64 / n as f64;
+                (angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+        let bit_reverse = (0..n)
+            .map(|i| i.reverse_bits() >> (usize::BITS - bits))
+            .collect();
+        Self {
+            twiddles,
+            bit_reverse,
+        }
+    }
+
+    /// In-place transform; the inverse is scaled by `1/n`
+    fn transform(&self, re: &mut [f32], im: &mut [f32], inverse: bool) {
+        let n = re.len();
+        for (i, &j) in self.bit_reverse.iter().enumerate() {
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= n {
+            let half = len / 2;
+            let stride = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..half {
+                    let (wr, wi) = self.twiddles[k * stride];
+                    let wi = if inverse { -wi } else { wi };
+                    let (a, b) = (start + k, start + k + half);
+                    let tr = re[b] * wr - im[b] * wi;
+                    let ti = re[b] * wi + im[b] * wr;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            len *= 2;
+        }
+
+        if inverse {
+            let scale = 1.0 / n as f32;
+            re.iter_mut().for_each(|x| *x *= scale);
+            im.iter_mut().for_each(|x| *x *= scale);
+        }
+    }
+}
+
+/// Spectral noise suppressor for one mono channel.
+///
+/// Frames are windowed with a square-root Hann window on both analysis and
+/// synthesis, which overlap-adds back to the input exactly when every gain
+/// is 1. The noise spectrum is learned from frames the caller marks as
+/// non-speech and only ever follows the noise down while someone talks.
+#[derive(Debug, Clone)]
+pub struct NoiseSuppressor {
+    fft: Fft,
+    window: Vec<f32>,
+    /// The last `WINDOW` input samples
+    frame: Vec<f32>,
+    /// Input not yet making up a full hop
+    pending: Vec<f32>,
+    /// Processed samples waiting to be handed out
+    output: VecDeque<f32>,
+    /// Second half of the previous synthesized frame
+    overlap: Vec<f32>,
+    /// Noise power per bin
+    noise: Vec<f32>,
+    /// Clean power over noise of the previous frame, per bin, for the
+    /// decision-directed estimate
+    previous_snr: Vec<f32>,
+    previous_energy: f32,
+    hops: u32,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    gains: Vec<f32>,
+}
+
+impl Default for NoiseSuppressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoiseSuppressor {
+    pub fn new() -> Self {
+        let window = (0..WINDOW)
+            .map(|n| (std::f32::consts::PI * n as f32 / WINDOW as f32).sin())
+            .collect();
+        let mut output = VecDeque::with_capacity(WINDOW * 2);
+        output.resize(HOP, 0.0);
+        Self {
+            fft: Fft::new(FFT_SIZE),
+            window,
+            frame: vec![0.0; WINDOW],
+            pending: Vec::with_capacity(HOP),
+            output,
+            overlap: vec![0.0; HOP],
+            noise: vec![NOISE_POWER_MIN; BINS],
+            previous_snr: vec![0.0; BINS],
+            previous_energy: 0.0,
+            hops: 0,
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            gains: vec![1.0; BINS],
+        }
+    }
+
+    /// Denoise `samples` in place, returning the audio from
+    /// [`NOISE_SUPPRESSOR_DELAY_SAMPLES`] earlier. `speech` is the latest
+    /// voice activity decision; while it is false the noise estimate adapts
+    /// to the input.
+    pub fn process(&mut self, samples: &mut [f32], speech: bool) {
+        for &sample in samples.iter() {
+            self.pending.push(sample);
+            if self.pending.len() == HOP {
+                self.run_hop(speech);
+                self.pending.clear();
+            }
+        }
+        for sample in samples.iter_mut() {
+            *sample = self.output.pop_front().unwrap_or(0.0);
+        }
+    }
+
+    /// Forget the learned noise and any buffered audio
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn run_hop(&mut self, speech: bool) {
+        self.frame.copy_within(HOP.., 0);
+        self.frame[WINDOW - HOP..].copy_from_slice(&self.pending);
+
+        for (n, re) in self.re.iter_mut().enumerate() {
+            *re = if n < WINDOW {
+                self.frame[n] * self.window[n]
+            } else {
+                0.0
+            };
+        }
+        self.im.fill(0.0);
+        self.fft.transform(&mut self.re, &mut self.im, false);
+
+        let power: Vec<f32> = (0..BINS)
+            .map(|k| self.re[k] * self.re[k] + self.im[k] * self.im[k])
+            .collect();
+        self.update_noise(&power, speech);
+
+        let energy: f32 = power.iter().sum();
+        let noise_energy: f32 = self.noise.iter().sum();
+        let transient = energy > TRANSIENT_RATIO * self.previous_energy.max(noise_energy);
+        self.previous_energy = energy;
+
+        for (k, &p) in power.iter().enumerate() {
+            let posterior = p / self.noise[k];
+            let instantaneous = (posterior - 1.0).max(0.0);
+            let mut priori = PRIORI_SNR_SMOOTHING * self.previous_snr[k]
+                + (1.0 - PRIORI_SNR_SMOOTHING) * instantaneous;
+            if transient {
+                priori = priori.max(instantaneous);
+            }
+            let gain = (priori / (1.0 + priori)).max(GAIN_FLOOR);
+            self.previous_snr[k] = gain * gain * posterior;
+            self.gains[k] = gain;
+        }
+
+        // Averaging with the neighbours stops a single bin flickering on
+        // and off, the other source of musical noise
+        let mut previous = self.gains[0];
+        for k in 1..BINS - 1 {
+            let current = self.gains[k];
+            self.gains[k] = (previous + 2.0 * current + self.gains[k + 1]) / 4.0;
+            previous = current;
+        }
+
+        for k in 0..BINS {
+            let gain = self.gains[k];
+            self.re[k] *= gain;
+            self.im[k] *= gain;
+            if k > 0 && k < FFT_SIZE / 2 {
+                self.re[FFT_SIZE - k] *= gain;
+                self.im[FFT_SIZE - k] *= gain;
+            }
+        }
+        self.fft.transform(&mut self.re, &mut self.im, true);
+
+        for n in 0..HOP {
+            self.output
+                .push_back(self.overlap[n] + self.re[n] * self.window[n]);
+            self.overlap[n] = self.re[n + HOP] * self.window[n + HOP];
+        }
+    }
+
+    fn update_noise(&mut self, power: &[f32], speech: bool) {
+        if self.hops < STARTUP_HOPS {
+            self.hops += 1;
+            let weight = 1.0 / self.hops as f32;
+            for (noise, &p) in self.noise.iter_mut().zip(power) {
+                *noise = (*noise + (p - *noise) * weight).max(NOISE_POWER_MIN);
+            }
+            return;
+        }
+
+        for (noise, &p) in self.noise.iter_mut().zip(power) {
+            // Quieter than the estimate cannot be speech, so the estimate
+            // is too high; follow it down even mid-sentence
+            if !speech || p < *noise {
+                *noise =
+                    (NOISE_SMOOTHING * *noise + (1.0 - NOISE_SMOOTHING) * p).max(NOISE_POWER_MIN);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::SAMPLE_RATE;
+
+    fn noise(len: usize, seed: u32, amplitude: f32) -> Vec<f32> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((x >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    fn tone(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|n| {
+                (2.0 * std::f32::consts::PI * 440.0 * n as f32 / SAMPLE_RATE as f32).sin() * 0.3
+            })
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn clean_input_passes_through_delayed() {
+        let mut suppressor = NoiseSuppressor::new();
+        suppressor.process(&mut vec![0.0; SAMPLE_RATE as usize / 2], false);
+
+        let input = tone(SAMPLE_RATE as usize / 2);
+        let mut output = input.clone();
+        // Uneven blocks, as capture callbacks deliver them
+        for block in output.chunks_mut(441) {
+            suppressor.process(block, true);
+        }
+        let delay = NOISE_SUPPRESSOR_DELAY_SAMPLES;
+        let residual: Vec<f32> = output[delay..]
+            .iter()
+            .zip(&input)
+            .map(|(out, original)| out - original)
+            .collect();
+        assert!(energy(&residual) < energy(&input) * 1e-4);
+    }
+
+    #[test]
+    fn steady_noise_is_attenuated() {
+        let background = noise(SAMPLE_RATE as usize * 2, 3, 0.05);
+        let mut output = background.clone();
+        let mut suppressor = NoiseSuppressor::new();
+        for block in output.chunks_mut(FRAME_SIZE) {
+            suppressor.process(block, false);
+        }
+        let second = SAMPLE_RATE as usize;
+        assert!(energy(&output[second..]) < energy(&background[second..]) * 0.05);
+    }
+
+    #[test]
+    fn speech_over_noise_is_kept() {
+        let len = SAMPLE_RATE as usize;
+        let background = noise(len * 2, 5, 0.05);
+        let voice = tone(len);
+        let mut suppressor = NoiseSuppressor::new();
+        for block in background[..len].to_vec().chunks_mut(FRAME_SIZE) {
+            suppressor.process(block, false);
+        }
+
+        let mut mic: Vec<f32> = voice
+            .iter()
+            .zip(&background[len..])
+            .map(|(v, n)| v + n)
+            .collect();
+        for block in mic.chunks_mut(FRAME_SIZE) {
+            suppressor.process(block, true);
+        }
+        let delay = NOISE_SUPPRESSOR_DELAY_SAMPLES;
+        let residual: Vec<f32> = mic[delay..]
+            .iter()
+            .zip(&voice)
+            .map(|(out, v)| out - v)
+            .collect();
+        assert!(energy(&residual) < energy(&background[len..]) * 0.5);
+    }
+}
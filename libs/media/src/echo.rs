@@ -0,0 +1,245 @@
+//! Acoustic echo cancellation for speaker mode.
+//!
+//! Playback records what it hands to the output device in an
+//! [`EchoReference`]; capture pulls the same amount back out for every block
+//! it processes and an [`EchoCanceller`] subtracts its estimate of how that
+//! audio came back through the microphone.
+
+use crate::audio::SAMPLE_RATE;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Longest echo path the filter models: output latency, the trip from
+/// speaker to microphone and input latency together. Echo arriving later is
+/// left in.
+pub const ECHO_TAIL_MS: u32 = 64;
+
+/// NLMS step size. Larger converges faster but leaves more residual echo and
+/// reacts harder to near-end speech the double-talk check misses.
+const NLMS_STEP: f32 = 0.25;
+/// Keeps the normalized step bounded while the far end is nearly silent
+const NLMS_REGULARIZATION: f32 = 1e-6;
+
+/// Far-end peak below which there is nothing to learn from
+const FAR_END_SILENCE: f32 = 1e-3;
+/// Geigel double-talk detector: a microphone sample louder than this share
+/// of the recent far-end peak cannot be echo alone, so the near end is
+/// talking and adaptation pauses
+const DOUBLE_TALK_RATIO: f32 = 0.6;
+/// How long adaptation stays paused after double talk (~30 ms)
+const DOUBLE_TALK_HOLD: usize = SAMPLE_RATE as usize * 30 / 1000;
+
+/// Played audio kept for a capture that is not pulling it (~200 ms)
+const ECHO_REFERENCE_MAX_SAMPLES: usize = SAMPLE_RATE as usize / 5;
+/// Played audio left queued after capture takes a block (~20 ms). Anything
+/// older is dropped so the reference never lags the microphone by more than
+/// the filter tail can absorb.
+const ECHO_REFERENCE_SLACK_SAMPLES: usize = SAMPLE_RATE as usize / 50;
+
+/// Far-end audio as played, queued for the echo canceller. Clones share the
+/// same queue.
+#[derive(Debug, Clone, Default)]
+pub struct EchoReference(Arc<Mutex<VecDeque<f32>>>);
+
+impl EchoReference {
+    /// Record mono samples just handed to the output device
+    pub fn push(&self, samples: &[f32]) {
+        let Ok(mut queue) = self.0.lock() else {
+            return;
+        };
+        queue.extend(samples);
+        let excess = queue.len().saturating_sub(ECHO_REFERENCE_MAX_SAMPLES);
+        queue.drain(..excess);
+    }
+
+    /// The `len` oldest queued samples, padded with silence when playback
+    /// has not produced that much
+    pub fn take(&self, len: usize) -> Vec<f32> {
+        let Ok(mut queue) = self.0.lock() else {
+            return vec![0.0; len];
+        };
+        let available = len.min(queue.len());
+        let mut samples: Vec<f32> = queue.drain(..available).collect();
+        samples.resize(len, 0.0);
+
+        let stale = queue.len().saturating_sub(ECHO_REFERENCE_SLACK_SAMPLES);
+        queue.drain(..stale);
+        samples
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut queue) = self.0.lock() {
+            queue.clear();
+        }
+    }
+}
+
+/// Normalized least-mean-squares adaptive filter. It learns the echo path
+/// from the far-end reference to the microphone and subtracts the predicted
+/// echo. The taps carry over between blocks, so the estimate keeps
+/// improving through the call.
+#[derive(Debug, Clone)]
+pub struct EchoCanceller {
+    weights: Vec<f32>,
+    /// Last `weights.len() - 1` reference samples of the previous block
+    history: Vec<f32>,
+    /// Samples left before adaptation resumes after double talk
+    double_talk_hold: usize,
+}
+
+impl Default for EchoCanceller {
+    fn default() -> Self {
+        Self::new(SAMPLE_RATE as usize * ECHO_TAIL_MS as usize / 1000)
+    }
+}
+
+impl EchoCanceller {
+    /// Filter modelling an echo path up to `taps` samples long
+    pub fn new(taps: usize) -> Self {
+        let taps = taps.max(1);
+        Self {
+            weights: vec![0.0; taps],
+            history: vec![0.0; taps - 1],
+            double_talk_hold: 0,
+        }
+    }
+
+    /// Remove the echo of `reference` from `mic`, both mono and in step:
+    /// `reference[n]` was played when `mic[n]` was captured, give or take
+    /// the device latencies the tail covers. A short reference counts as
+    /// silence for the missing samples.
+    pub fn process(&mut self, mic: &mut [f32], reference: &[f32]) {
+        let taps = self.weights.len();
+        let mut far = Vec::with_capacity(self.history.len() + mic.len());
+        far.extend_from_slice(&self.history);
+        far.extend(
+            reference
+                .iter()
+                .copied()
+                .chain(std::iter::repeat(0.0))
+                .take(mic.len()),
+        );
+
+        let far_peak = far.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let mut energy: f32 = far[..taps].iter().map(|s| s * s).sum();
+
+        for (n, sample) in mic.iter_mut().enumerate() {
+            let window = &far[n..n + taps];
+            if n > 0 {
+                let newest = window[taps - 1];
+                energy = (energy + newest * newest - far[n - 1] * far[n - 1]).max(0.0);
+            }
+
+            let echo: f32 = self.weights.iter().zip(window).map(|(w, x)| w * x).sum();
+            let error = *sample - echo;
+
+            if sample.abs() > DOUBLE_TALK_RATIO * far_peak {
+                self.double_talk_hold = DOUBLE_TALK_HOLD;
+            }
+            if self.double_talk_hold > 0 {
+                self.double_talk_hold -= 1;
+            } else if far_peak > FAR_END_SILENCE {
+                let step = NLMS_STEP * error / (energy + NLMS_REGULARIZATION * taps as f32);
+                for (w, x) in self.weights.iter_mut().zip(window) {
+                    *w += step * x;
+                }
+            }
+
+            *sample = error;
+        }
+
+        let keep = far.len() - self.history.len();
+        self.history.copy_from_slice(&far[keep..]);
+    }
+
+    /// Forget the learned echo path, e.g. after switching output device
+    pub fn reset(&mut self) {
+        self.weights.fill(0.0);
+        self.history.fill(0.0);
+        self.double_talk_hold = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(len: usize, seed: u32) -> Vec<f32> {
+        let mut x = seed;
+        (0..len)
+            .map(|_| {
+                x = x.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (x >> 8) as f32 / (1u32 << 24) as f32 * 0.4 - 0.2
+            })
+            .collect()
+    }
+
+    /// The far end as heard through a room: 10 samples late, at 40%
+    fn room_echo(far: &[f32]) -> Vec<f32> {
+        (0..far.len())
+            .map(|n| n.checked_sub(10).map_or(0.0, |m| far[m] * 0.4))
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn nlms_converges_on_a_fixed_echo_path() {
+        let far = noise(SAMPLE_RATE as usize, 7);
+        let echo = room_echo(&far);
+        let mut canceller = EchoCanceller::new(64);
+
+        let mut last_block = Vec::new();
+        for (mic, reference) in echo.chunks(480).zip(far.chunks(480)) {
+            let mut block = mic.to_vec();
+            canceller.process(&mut block, reference);
+            last_block = block;
+        }
+        let tail = &echo[echo.len() - 480..];
+        assert!(
+            energy(&last_block) < energy(tail) * 0.001,
+            "residual {} vs echo {}",
+            energy(&last_block),
+            energy(tail)
+        );
+    }
+
+    #[test]
+    fn near_end_speech_survives_double_talk() {
+        let far = noise(SAMPLE_RATE as usize, 11);
+        let echo = room_echo(&far);
+        let mut canceller = EchoCanceller::new(64);
+        let half = far.len() / 2;
+        for (mic, reference) in echo[..half].chunks(480).zip(far[..half].chunks(480)) {
+            canceller.process(&mut mic.to_vec(), reference);
+        }
+
+        let voice: Vec<f32> = (0..half).map(|n| (n as f32 * 0.05).sin() * 0.5).collect();
+        let mut mic: Vec<f32> = voice
+            .iter()
+            .zip(&echo[half..])
+            .map(|(v, e)| v + e)
+            .collect();
+        for (block, reference) in mic.chunks_mut(480).zip(far[half..].chunks(480)) {
+            canceller.process(block, reference);
+        }
+        let residual: Vec<f32> = mic.iter().zip(&voice).map(|(m, v)| m - v).collect();
+        assert!(energy(&residual) < energy(&voice) * 0.01);
+    }
+
+    #[test]
+    fn reference_pads_short_reads_and_drops_stale_audio() {
+        let reference = EchoReference::default();
+        reference.push(&[0.5; 4]);
+        assert_eq!(reference.take(6), vec![0.5, 0.5, 0.5, 0.5, 0.0, 0.0]);
+
+        reference.push(&vec![0.1; ECHO_REFERENCE_MAX_SAMPLES * 2]);
+        reference.take(480);
+        assert_eq!(
+            reference.0.lock().unwrap().len(),
+            ECHO_REFERENCE_SLACK_SAMPLES
+        );
+    }
+}
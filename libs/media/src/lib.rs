@@ -5,15 +5,34 @@
 //! Pipeline: cpal (capture) → audiopus (encode) → ring (encrypt) → webrtc-rs (send)
 
 mod audio;
+mod audio_params;
+mod compressor;
+mod connectivity;
 mod crypto;
+mod denoise;
+mod echo;
+#[cfg(any(test, feature = "netsim"))]
+pub mod netsim;
+mod profile;
+mod quality;
+mod recording;
+mod ringtone;
+mod sdp;
+mod send_buffer;
+mod soundboard;
 
 use anyhow::Result;
+use audio::DefaultDeviceWatch;
+use audio_params::ParamsNegotiation;
 use cpal::traits::{DeviceTrait, HostTrait};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc,
+    Arc, Mutex,
 };
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
 use webrtc::api::media_engine::MediaEngine as WebRtcMediaEngine;
 use webrtc::api::APIBuilder;
 use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
@@ -22,16 +41,50 @@ use webrtc::data_channel::RTCDataChannel;
 use webrtc::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
 use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::peer_connection::configuration::RTCConfiguration;
-use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::offer_answer_options::RTCOfferOptions;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::stats::StatsReportType;
 // Required for ICE candidate methods
 use webrtc::peer_connection::policy::ice_transport_policy::RTCIceTransportPolicy;
 
-pub use audio::{AudioCapture, AudioPacket, AudioPlayback, VoiceMode};
-pub use crypto::{CryptoContext, KeyPair};
-
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub use audio::{
+    AudioCapture, AudioCaptureConfig, AudioDeviceError, AudioDeviceEvent, AudioDirection,
+    AudioPacket, AudioPlayback, AudioPlaybackConfig, BitrateController, CaptureJitter,
+    CaptureStage, CaptureStageOrder, CaptureStats, DspChain, NoiseSuppressionMode,
+    PlaybackBufferStats, PlaybackStats, RemoteSpeakingState, SpeakingState, VoiceMode,
+    DEFAULT_AGC_MAX_GAIN, DEFAULT_AGC_MIN_GAIN, DEFAULT_AGC_TARGET_RMS, DEFAULT_FRAME_DURATION_MS,
+    DEFAULT_JITTER_TARGET_MS, DEFAULT_NOISE_GATE_HIGH_BAND_BIAS, DEFAULT_NOISE_GATE_HOLD_MS,
+    LOSS_WINDOW, MAX_JITTER_TARGET_MS, MIN_JITTER_TARGET_MS, OPUS_FRAME_DURATIONS_MS,
+    SPEAKING_HOLD_MS,
+};
+pub use audio_params::CallAudioParams;
+pub use compressor::{Compressor, CompressorSettings};
+pub use connectivity::{check_ice_servers, ConnectivityReport, CONNECTIVITY_CHECK_TIMEOUT};
+pub use crypto::{CryptoContext, CryptoError, KeyPair};
+pub use denoise::{NoiseSuppressor, NOISE_SUPPRESSOR_DELAY_SAMPLES};
+pub use echo::{EchoCanceller, EchoReference, ECHO_TAIL_MS};
+pub use profile::{
+    AudioProfile, ProfileOverrides, SPEAKER_MAX_OUTPUT_VOLUME, SPEAKER_MIN_NOISE_GATE_THRESHOLD,
+};
+pub use quality::{
+    CallQuality, CallQualityScore, QualityMonitor, QualitySample, CALL_QUALITY_INTERVAL,
+    MOS_BASE_DELAY_MS, MOS_LOSS_ROBUSTNESS, QUALITY_DEGRADE_AFTER, QUALITY_JITTER_MS,
+    QUALITY_LOSS_PERCENT, QUALITY_RECOVER_AFTER, QUALITY_RTT_MS,
+};
+pub use recording::{
+    read_recording, CallRecorder, RecordingInfo, RecordingSummary, RECORDING_MAGIC,
+};
+pub use ringtone::{RingbackRegion, RingtoneClip, RingtonePlayer};
+pub use sdp::{cap_opus_bitrate, set_bandwidth_limit, SdpTransform};
+use send_buffer::SendControls;
+pub use send_buffer::DEFAULT_SEND_BUFFER_LIMIT_BYTES;
+pub use soundboard::{SoundClip, MAX_CLIP_DURATION};
+pub use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+pub use webrtc::ice_transport::ice_gatherer_state::RTCIceGathererState;
+pub use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AudioMode {
     Headphones,
@@ -63,14 +116,144 @@ pub struct AudioSettings {
     pub voice_mode: String,
     pub vad_threshold: f32,
     pub noise_suppression: bool,
+    /// `"light"` or `"heavy"`, see [`NoiseSuppressionMode`]
+    #[serde(default = "default_noise_suppression_mode")]
+    pub noise_suppression_mode: String,
     pub aec: bool,
     pub agc: bool,
+    /// RMS level AGC steers the microphone towards
+    #[serde(default = "default_agc_target_rms")]
+    pub agc_target_rms: f32,
+    /// Range the AGC gain stays within; `agc_min_gain` must not exceed
+    /// `agc_max_gain`
+    #[serde(default = "default_agc_min_gain")]
+    pub agc_min_gain: f32,
+    #[serde(default = "default_agc_max_gain")]
+    pub agc_max_gain: f32,
     pub noise_gate: bool,
     pub noise_gate_threshold: f32,
+    /// How long the noise gate stays open after the level drops, in ms
+    #[serde(default = "default_noise_gate_hold_ms")]
+    pub noise_gate_hold_ms: u32,
+    /// Weight of high-frequency energy in opening the gate's upper band, so
+    /// quiet "s" and "f" sounds are not cut; 0 gates the full band as one
+    #[serde(default = "default_noise_gate_high_band_bias")]
+    pub noise_gate_high_band_bias: f32,
     pub limiter: bool,
     pub deafen: bool,
     pub ptt_key: String,
     pub audio_mode: AudioMode,
+    /// Values pinned by the user over what `audio_mode` would pick, see
+    /// [`AudioProfile`]
+    #[serde(default)]
+    pub profile_overrides: ProfileOverrides,
+    /// Order of the configurable capture stages, e.g. `["noise_gate", "gain"]`
+    #[serde(default = "default_capture_stage_order")]
+    pub capture_stage_order: Vec<String>,
+    /// Seconds without outgoing audio before a keepalive packet is sent to
+    /// keep the NAT binding open; 0 disables keepalives
+    #[serde(default = "default_nat_keepalive_interval")]
+    pub nat_keepalive_interval: u32,
+    /// Bytes queued on the audio DataChannel above which new frames are
+    /// dropped rather than sent late; 0 never drops
+    #[serde(default = "default_send_buffer_limit_bytes")]
+    pub send_buffer_limit_bytes: u32,
+    /// Low-CPU mode: turns off noise suppression, AEC, AGC, noise gate,
+    /// compressor and limiter regardless of their own toggles, leaving only gain and volume
+    #[serde(default)]
+    pub minimal_processing: bool,
+    /// Audio the jitter buffer holds before playback starts, in ms. Higher
+    /// rides out burstier networks at the cost of latency.
+    #[serde(default = "default_jitter_buffer_ms")]
+    pub jitter_buffer_ms: u32,
+    /// Capture and play left/right instead of mono, for music or binaural
+    /// sources. Works against a mono peer in either direction.
+    #[serde(default)]
+    pub stereo: bool,
+    /// Opus DTX: stop sending during silence apart from periodic comfort
+    /// noise updates
+    #[serde(default)]
+    pub dtx: bool,
+    /// Audio per packet in ms, one of `OPUS_FRAME_DURATIONS_MS`. Shorter
+    /// lowers latency, longer saves bandwidth.
+    #[serde(default = "default_frame_duration_ms")]
+    pub frame_duration_ms: f32,
+    /// Soft-knee compressor on playback, ahead of the limiter
+    #[serde(default)]
+    pub compressor: bool,
+    /// dBFS above which the compressor reduces gain
+    #[serde(default = "default_compressor_threshold_db")]
+    pub compressor_threshold_db: f32,
+    #[serde(default = "default_compressor_ratio")]
+    pub compressor_ratio: f32,
+    #[serde(default = "default_compressor_attack_ms")]
+    pub compressor_attack_ms: f32,
+    #[serde(default = "default_compressor_release_ms")]
+    pub compressor_release_ms: f32,
+}
+
+fn default_capture_stage_order() -> Vec<String> {
+    CaptureStageOrder::default()
+        .stages()
+        .iter()
+        .map(|stage| stage.as_str().to_string())
+        .collect()
+}
+
+fn default_noise_suppression_mode() -> String {
+    NoiseSuppressionMode::default().as_str().to_string()
+}
+
+fn default_noise_gate_hold_ms() -> u32 {
+    DEFAULT_NOISE_GATE_HOLD_MS
+}
+
+fn default_noise_gate_high_band_bias() -> f32 {
+    DEFAULT_NOISE_GATE_HIGH_BAND_BIAS
+}
+
+fn default_agc_target_rms() -> f32 {
+    DEFAULT_AGC_TARGET_RMS
+}
+
+fn default_agc_min_gain() -> f32 {
+    DEFAULT_AGC_MIN_GAIN
+}
+
+fn default_agc_max_gain() -> f32 {
+    DEFAULT_AGC_MAX_GAIN
+}
+
+fn default_nat_keepalive_interval() -> u32 {
+    15
+}
+
+fn default_send_buffer_limit_bytes() -> u32 {
+    DEFAULT_SEND_BUFFER_LIMIT_BYTES
+}
+
+fn default_jitter_buffer_ms() -> u32 {
+    DEFAULT_JITTER_TARGET_MS
+}
+
+fn default_frame_duration_ms() -> f32 {
+    DEFAULT_FRAME_DURATION_MS
+}
+
+fn default_compressor_threshold_db() -> f32 {
+    CompressorSettings::default().threshold_db
+}
+
+fn default_compressor_ratio() -> f32 {
+    CompressorSettings::default().ratio
+}
+
+fn default_compressor_attack_ms() -> f32 {
+    CompressorSettings::default().attack_ms
+}
+
+fn default_compressor_release_ms() -> f32 {
+    CompressorSettings::default().release_ms
 }
 
 impl Default for AudioSettings {
@@ -82,18 +265,276 @@ impl Default for AudioSettings {
             voice_mode: "voice_activity".to_string(),
             vad_threshold: 0.02,
             noise_suppression: true,
+            noise_suppression_mode: default_noise_suppression_mode(),
             aec: true,
             agc: true,
+            agc_target_rms: default_agc_target_rms(),
+            agc_min_gain: default_agc_min_gain(),
+            agc_max_gain: default_agc_max_gain(),
             noise_gate: true,
             noise_gate_threshold: 0.01,
+            noise_gate_hold_ms: default_noise_gate_hold_ms(),
+            noise_gate_high_band_bias: default_noise_gate_high_band_bias(),
             limiter: true,
             deafen: false,
             ptt_key: "V".to_string(),
             audio_mode: AudioMode::Headphones,
+            profile_overrides: ProfileOverrides::default(),
+            capture_stage_order: default_capture_stage_order(),
+            nat_keepalive_interval: default_nat_keepalive_interval(),
+            send_buffer_limit_bytes: default_send_buffer_limit_bytes(),
+            minimal_processing: false,
+            jitter_buffer_ms: default_jitter_buffer_ms(),
+            stereo: false,
+            dtx: false,
+            frame_duration_ms: default_frame_duration_ms(),
+            compressor: false,
+            compressor_threshold_db: default_compressor_threshold_db(),
+            compressor_ratio: default_compressor_ratio(),
+            compressor_attack_ms: default_compressor_attack_ms(),
+            compressor_release_ms: default_compressor_release_ms(),
+        }
+    }
+}
+
+/// How often the send bitrate is re-evaluated against measured loss
+const BITRATE_ADAPT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the system default devices are checked while following them
+const DEVICE_FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest `start_loopback_test` runs before stopping on its own
+pub const MAX_LOOPBACK_TEST: Duration = Duration::from_secs(10);
+
+/// Whether call streams follow the system default device, shared with the
+/// device-follow task
+#[derive(Debug)]
+struct DeviceFollow {
+    enabled: AtomicBool,
+    /// No input device selected, so capture uses the default
+    input: AtomicBool,
+    /// No output device selected, so playback uses the default
+    output: AtomicBool,
+}
+
+/// Connection and audio statistics for the current call, for a quality
+/// indicator. Transport figures come from the peer connection's stats
+/// report; loss, jitter and frame counts are measured locally.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct CallStats {
+    /// Round trip time of the nominated ICE candidate pair; `None` before
+    /// ICE has measured one
+    pub rtt_ms: Option<f64>,
+    /// Messages on the audio DataChannel, keepalives included
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Audio packets missing from the incoming sequence
+    pub packets_lost: u64,
+    /// Loss over the last few seconds, in percent, late packets not counted
+    pub packet_loss_percent: f32,
+    /// Incoming audio packets received twice
+    pub packets_duplicate: u64,
+    /// Incoming audio packets that arrived too late to play
+    pub packets_out_of_order: u64,
+    pub jitter_ms: f64,
+    /// Bytes queued on the audio DataChannel but not yet sent
+    pub data_channel_buffered_amount: usize,
+    pub frames_encoded: u64,
+    /// Frames sent as silence because the voice mode held them back
+    pub frames_vad_dropped: u64,
+    /// Silent frames DTX left unsent
+    pub frames_dtx: u64,
+    /// Frames dropped because the audio DataChannel was backed up
+    pub frames_backpressure_dropped: u64,
+    /// Lost frames rebuilt with FEC or PLC
+    pub frames_concealed: u64,
+    /// Output callbacks that found the jitter buffer empty
+    pub buffer_underruns: u64,
+    /// Times the jitter buffer dropped queued audio for holding too much
+    pub buffer_overruns: u64,
+}
+
+/// Microphone looped into the speaker by `start_loopback_test`
+struct LoopbackTest {
+    capture: Arc<AudioCapture>,
+    playback: Arc<AudioPlayback>,
+    /// Feeds capture into playback and stops both when the time is up
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl LoopbackTest {
+    fn stop(self) {
+        self.task.abort();
+        self.capture.stop();
+        self.playback.stop();
+    }
+}
+
+/// Keyed capture and playback for the current call. Published through a
+/// watch channel so DataChannel callbacks registered before the key exchange
+/// pick them up once they exist.
+#[derive(Clone)]
+struct CallAudio {
+    capture: Arc<AudioCapture>,
+    playback: Arc<AudioPlayback>,
+}
+
+/// One remote peer of a group call: a peer connection and key of its own
+struct PeerSession {
+    pc: Arc<RTCPeerConnection>,
+    /// Our key pair for this peer, until its public key arrives
+    keypair: Option<crypto::KeyPair>,
+    /// Key shared with this peer. Only seals sender keys; media uses
+    /// those.
+    crypto: watch::Sender<Option<Arc<CryptoContext>>>,
+    link: PeerLink,
+}
+
+/// What a group call peer's DataChannel needs to carry audio
+#[derive(Clone)]
+struct PeerLink {
+    peer_id: String,
+    audio: CallAudio,
+    crypto: watch::Receiver<Option<Arc<CryptoContext>>>,
+    playback_started: Arc<AtomicBool>,
+    preferred_input_device: Option<String>,
+    preferred_output_device: Option<String>,
+    device_errors: mpsc::UnboundedSender<AudioDeviceError>,
+    send_controls: Arc<SendControls>,
+    listen_only: watch::Receiver<bool>,
+}
+
+/// Our sender key sealed for one group call peer, for the signaling layer
+/// to deliver to it. That peer passes it to `set_peer_sender_key`.
+#[derive(Debug, Clone)]
+pub struct SenderKeyUpdate {
+    pub peer_id: String,
+    /// Base64, opened only with the key shared with that peer
+    pub sealed_key: String,
+}
+
+/// What the signaling layer sends a group call peer to connect to it
+pub struct PeerSetup {
+    /// Our public key for this peer, as base64
+    pub public_key: String,
+    /// Local ICE candidates for this peer's connection
+    pub ice_candidates: mpsc::Receiver<String>,
+}
+
+/// A ringtone or ringback still to be opened. Opening waits on the audio
+/// thread for up to `STREAM_START_TIMEOUT`, so a caller keeping the engine
+/// behind an async lock gets one from `prepare_ringtone`,
+/// `prepare_ringtone_device` or `prepare_ringback`, releases the lock, calls
+/// `open` off the runtime (e.g. in `spawn_blocking`) and hands the result to
+/// `install_ringtone`.
+pub struct PendingRingtone {
+    slot: ToneSlot,
+    clip: RingtoneClip,
+    device: Option<String>,
+    volume: f32,
+    generation: u64,
+    /// From `prepare_ringtone_device`: the device is selected once the
+    /// ringtone opened on it
+    select_device: bool,
+}
+
+impl PendingRingtone {
+    pub fn open(self) -> Result<OpenedRingtone> {
+        let player = RingtonePlayer::start(self.clip, self.device.as_deref(), self.volume)?;
+        Ok(OpenedRingtone {
+            slot: self.slot,
+            player,
+            device: self.device,
+            generation: self.generation,
+            select_device: self.select_device,
+        })
+    }
+}
+
+/// A ringtone opened from a `PendingRingtone`, for `install_ringtone`
+pub struct OpenedRingtone {
+    slot: ToneSlot,
+    player: RingtonePlayer,
+    device: Option<String>,
+    generation: u64,
+    select_device: bool,
+}
+
+/// Which of the engine's players a `PendingRingtone` goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToneSlot {
+    Ringtone,
+    Ringback,
+}
+
+/// A change of the call output device, from `prepare_output_device`.
+/// `apply` reopens the ringback and a running call playback on the new
+/// device and blocks like `PendingRingtone::open`; `commit_output_device`
+/// then selects the device.
+pub struct OutputDeviceChange {
+    device: Option<String>,
+    previous: Option<String>,
+    ringback: Option<PendingRingtone>,
+    playback: Option<Arc<AudioPlayback>>,
+}
+
+impl OutputDeviceChange {
+    /// Move the ringback and call playback onto the new device. On failure
+    /// playback is reopened on the previous device and nothing changes.
+    pub fn apply(self) -> Result<AppliedOutputDevice> {
+        let ringback = self.ringback.map(PendingRingtone::open).transpose()?;
+
+        if let Some(playback) = &self.playback {
+            match restart_playback(playback, self.device.as_deref()) {
+                Ok(true) => tracing::warn!(
+                    "Output device {:?} unavailable, switched to default",
+                    self.device.as_deref().unwrap_or("default")
+                ),
+                Ok(false) => tracing::info!(
+                    "Output device switched to {:?}",
+                    self.device.as_deref().unwrap_or("default")
+                ),
+                Err(e) => {
+                    if let Some(opened) = ringback {
+                        opened.player.stop();
+                    }
+                    if let Err(rollback) = restart_playback(playback, self.previous.as_deref()) {
+                        tracing::warn!("Failed to reopen the previous output device: {}", rollback);
+                    }
+                    return Err(e);
+                }
+            }
         }
+
+        Ok(AppliedOutputDevice {
+            device: self.device,
+            ringback,
+            playback: self.playback,
+        })
     }
 }
 
+/// An output device change that `apply` carried out, for
+/// `commit_output_device`
+pub struct AppliedOutputDevice {
+    device: Option<String>,
+    ringback: Option<OpenedRingtone>,
+    playback: Option<Arc<AudioPlayback>>,
+}
+
+/// ICE progress of the 1:1 peer connection, finer grained than
+/// `RTCPeerConnectionState`. Gathering says whether local candidates are
+/// still being collected; the connection state says whether checks are
+/// running, succeeded, dropped (`Disconnected`, often recovers) or gave up
+/// (`Failed`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceStateChange {
+    Gathering(RTCIceGathererState),
+    Connection(RTCIceConnectionState),
+}
+
 /// Media engine state
 pub struct MediaEngine {
     /// Our key pair for E2EE
@@ -103,20 +544,95 @@ pub struct MediaEngine {
 
     // WebRTC components
     rtc_connection: Option<Arc<RTCPeerConnection>>,
+    /// The call's audio DataChannel once created (offerer) or received
+    /// (answerer)
+    audio_channel: Arc<Mutex<Option<Arc<RTCDataChannel>>>>,
 
     // Audio components
     audio_capture: Option<Arc<AudioCapture>>,
     audio_playback: Option<Arc<AudioPlayback>>,
+    /// Same components as above, for the answerer's DataChannel callbacks;
+    /// `None` until `attach_call_audio` runs
+    call_audio: watch::Sender<Option<CallAudio>>,
+    /// Retunes the encoder bitrate from measured loss for the current call
+    bitrate_task: Option<tokio::task::JoinHandle<()>>,
+    /// Moves the current call's streams onto a new default device
+    device_follow_task: Option<tokio::task::JoinHandle<()>>,
+    /// Samples the current call's loss, jitter and RTT into a `CallQuality`
+    quality_task: Option<tokio::task::JoinHandle<()>>,
+    device_follow: Arc<DeviceFollow>,
     // Preferred input device name chosen by user
     selected_input_device: Option<String>,
     // Preferred output device name chosen by user
     selected_output_device: Option<String>,
+    // Preferred device for the incoming-call ringtone; call audio keeps
+    // using `selected_output_device`
+    selected_ringtone_device: Option<String>,
+    ringtone: Option<RingtonePlayer>,
+    /// Bumped whenever the ringtone is stopped or replaced, so a
+    /// `PendingRingtone` opened meanwhile is not installed over it
+    ringtone_generation: u64,
+    /// Ringback the caller hears while the callee rings, on the call output
+    ringback: Option<RingtonePlayer>,
+    /// `ringtone_generation` for the ringback
+    ringback_generation: u64,
     // Runtime audio settings
     audio_settings: AudioSettings,
+    /// Volume chosen for each remote peer, by peer id; kept across calls
+    peer_volumes: HashMap<String, f32>,
+    /// Peer the current call's audio comes from
+    active_peer: Option<String>,
+    /// Remote peers of a group call, by peer id; empty in a 1:1 call
+    peers: HashMap<String, PeerSession>,
+    /// Key our group call audio is encrypted with; replaced when a peer
+    /// leaves
+    sender_key: Option<crypto::SenderKey>,
+    sender_key_tx: mpsc::UnboundedSender<SenderKeyUpdate>,
+    sender_key_rx: Option<mpsc::UnboundedReceiver<SenderKeyUpdate>>,
+    /// Group call listener: peers' audio plays but ours is not sent
+    listen_only: watch::Sender<bool>,
+    /// Our offer of new `CallAudioParams`, until the peer answers it
+    audio_params: ParamsNegotiation,
     // Runtime ICE server configuration
     ice_servers: Vec<IceServerConfig>,
     /// Track whether playback stream has been started
     playback_started: Arc<AtomicBool>,
+    /// Stream start failures from DataChannel callbacks, which have no caller
+    /// to return an error to
+    device_error_tx: mpsc::UnboundedSender<AudioDeviceError>,
+    device_error_rx: Option<mpsc::UnboundedReceiver<AudioDeviceError>>,
+    /// Devices lost mid-call, from either stream's error callback
+    device_event_tx: mpsc::UnboundedSender<AudioDeviceEvent>,
+    device_event_rx: Option<mpsc::UnboundedReceiver<AudioDeviceEvent>>,
+    /// Peer connection state changes ("connected", "disconnected", "failed",
+    /// ...). This is the media liveness signal, independent of signaling.
+    connection_state_tx: mpsc::UnboundedSender<RTCPeerConnectionState>,
+    connection_state_rx: Option<mpsc::UnboundedReceiver<RTCPeerConnectionState>>,
+    /// ICE gathering and connection state changes of the 1:1 call
+    ice_state_tx: mpsc::UnboundedSender<IceStateChange>,
+    ice_state_rx: Option<mpsc::UnboundedReceiver<IceStateChange>>,
+    /// Call quality changes of the 1:1 call, see `QualityMonitor`
+    call_quality_tx: mpsc::UnboundedSender<CallQuality>,
+    call_quality_rx: Option<mpsc::UnboundedReceiver<CallQuality>>,
+    /// Latest score of the 1:1 call, published by its quality task on
+    /// every sample; a fresh channel per call
+    call_quality_score: watch::Receiver<Option<CallQualityScore>>,
+    /// Late capture callbacks from whichever capture is live
+    capture_jitter_tx: mpsc::UnboundedSender<CaptureJitter>,
+    capture_jitter_rx: Option<mpsc::UnboundedReceiver<CaptureJitter>>,
+    /// Keepalive interval and backpressure limit for the send loops
+    send_controls: Arc<SendControls>,
+    /// Debug-only raw monitor: playback skips volume and limiter. Kept out of
+    /// `AudioSettings` so it is never persisted.
+    playback_raw_mode: bool,
+    /// Paused, unkeyed streams opened by `prewarm_audio` while ringing;
+    /// `init_webrtc` adopts them instead of opening new ones
+    prewarmed_audio: Mutex<Option<(Arc<AudioCapture>, Arc<AudioPlayback>)>>,
+    /// Mic test running outside any call; stopped before a call opens the
+    /// devices
+    loopback_test: Mutex<Option<LoopbackTest>>,
+    /// Local recording of the current call, see `start_recording`
+    recording: Mutex<Option<CallRecorder>>,
 }
 
 impl Default for MediaEngine {
@@ -127,17 +643,68 @@ impl Default for MediaEngine {
 
 impl MediaEngine {
     pub fn new() -> Self {
+        let (device_error_tx, device_error_rx) = mpsc::unbounded_channel();
+        let (device_event_tx, device_event_rx) = mpsc::unbounded_channel();
+        let (connection_state_tx, connection_state_rx) = mpsc::unbounded_channel();
+        let (ice_state_tx, ice_state_rx) = mpsc::unbounded_channel();
+        let (call_quality_tx, call_quality_rx) = mpsc::unbounded_channel();
+        let (capture_jitter_tx, capture_jitter_rx) = mpsc::unbounded_channel();
+        let (sender_key_tx, sender_key_rx) = mpsc::unbounded_channel();
         Self {
             keypair: None,
             crypto_ctx: None,
             rtc_connection: None,
+            audio_channel: Arc::new(Mutex::new(None)),
             audio_capture: None,
             audio_playback: None,
+            call_audio: watch::channel(None).0,
+            bitrate_task: None,
+            device_follow_task: None,
+            quality_task: None,
+            device_follow: Arc::new(DeviceFollow {
+                enabled: AtomicBool::new(true),
+                input: AtomicBool::new(true),
+                output: AtomicBool::new(true),
+            }),
             selected_input_device: None,
             selected_output_device: None,
+            selected_ringtone_device: None,
+            ringtone: None,
+            ringtone_generation: 0,
+            ringback: None,
+            ringback_generation: 0,
             audio_settings: AudioSettings::default(),
+            peer_volumes: HashMap::new(),
+            active_peer: None,
+            peers: HashMap::new(),
+            sender_key: None,
+            sender_key_tx,
+            listen_only: watch::channel(false).0,
+            audio_params: ParamsNegotiation::default(),
+            sender_key_rx: Some(sender_key_rx),
             ice_servers: vec![IceServerConfig::default()],
             playback_started: Arc::new(AtomicBool::new(false)),
+            device_error_tx,
+            device_error_rx: Some(device_error_rx),
+            device_event_tx,
+            device_event_rx: Some(device_event_rx),
+            connection_state_tx,
+            connection_state_rx: Some(connection_state_rx),
+            ice_state_tx,
+            ice_state_rx: Some(ice_state_rx),
+            call_quality_tx,
+            call_quality_rx: Some(call_quality_rx),
+            call_quality_score: watch::channel(None).1,
+            capture_jitter_tx,
+            capture_jitter_rx: Some(capture_jitter_rx),
+            send_controls: Arc::new(SendControls::new(
+                default_nat_keepalive_interval(),
+                default_send_buffer_limit_bytes(),
+            )),
+            playback_raw_mode: false,
+            prewarmed_audio: Mutex::new(None),
+            loopback_test: Mutex::new(None),
+            recording: Mutex::new(None),
         }
     }
 
@@ -156,6 +723,24 @@ impl MediaEngine {
     /// Reset the media engine for a new call
     /// Must be called when a call ends to clean up all state
     pub async fn reset(&mut self) {
+        self.stop_ringtone();
+        self.stop_ringback();
+        self.release_prewarmed_audio();
+        self.stop_loopback_test();
+        if let Err(e) = self.stop_recording() {
+            tracing::warn!("Failed to finish call recording: {}", e);
+        }
+        if let Some(task) = self.bitrate_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.device_follow_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.quality_task.take() {
+            task.abort();
+        }
+        self.call_quality_score = watch::channel(None).1;
+
         // Stop audio capture
         if let Some(capture) = &self.audio_capture {
             capture.stop();
@@ -171,40 +756,160 @@ impl MediaEngine {
             let _ = pc.close().await;
             tracing::info!("WebRTC connection closed");
         }
+        for (_, session) in self.peers.drain() {
+            let _ = session.pc.close().await;
+        }
 
+        if let Ok(mut channel) = self.audio_channel.lock() {
+            *channel = None;
+        }
         self.keypair = None;
         self.crypto_ctx = None;
+        self.active_peer = None;
+        self.sender_key = None;
+        self.listen_only.send_replace(false);
+        self.audio_params.clear();
         self.audio_capture = None;
         self.audio_playback = None;
+        // A fresh channel, so DataChannel tasks still waiting on the old
+        // one give up instead of picking up the next call's audio
+        self.call_audio = watch::channel(None).0;
         self.playback_started.store(false, Ordering::SeqCst);
+        self.send_controls.reset_counters();
         tracing::info!("MediaEngine reset for next call");
     }
 
+    /// Open and start the capture and playback streams ahead of time, e.g.
+    /// while a call is ringing, so audio flows as soon as the DataChannel
+    /// opens. The streams stay paused and unkeyed: the microphone is read
+    /// but nothing is encoded or sent, and the speaker plays silence.
+    ///
+    /// Holds the devices until the call connects or `reset` /
+    /// `release_prewarmed_audio` is called. No-op if already warm or a call
+    /// is set up.
+    pub fn prewarm_audio(&self) -> Result<()> {
+        let mut prewarmed = self
+            .prewarmed_audio
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?;
+        if prewarmed.is_some() || self.audio_capture.is_some() {
+            return Ok(());
+        }
+        self.stop_loopback_test();
+
+        let playback = Arc::new(AudioPlayback::new_prewarmed(self.playback_config())?);
+        playback.set_raw_mode(self.playback_raw_mode);
+        let capture = Arc::new(AudioCapture::new_prewarmed(
+            playback.echo_reference(),
+            self.capture_config(),
+        )?);
+        capture.set_jitter_sender(self.capture_jitter_tx.clone());
+        capture.set_device_event_sender(self.device_event_tx.clone());
+        playback.set_device_event_sender(self.device_event_tx.clone());
+
+        let started = playback
+            .start_with_device(self.selected_output_device.as_deref())
+            .and_then(|_| capture.start_with_device(self.selected_input_device.as_deref()));
+        if let Err(e) = started {
+            capture.stop();
+            playback.stop();
+            return Err(e);
+        }
+
+        tracing::info!("Audio streams pre-warmed");
+        *prewarmed = Some((capture, playback));
+        Ok(())
+    }
+
+    /// Stop streams opened by `prewarm_audio` that no call adopted, e.g.
+    /// after a decline or cancel.
+    pub fn release_prewarmed_audio(&self) {
+        let taken = self.prewarmed_audio.lock().ok().and_then(|mut p| p.take());
+        if let Some((capture, playback)) = taken {
+            capture.stop();
+            playback.stop();
+            tracing::info!("Pre-warmed audio streams released");
+        }
+    }
+
     /// Generate a new key pair for E2EE
     /// Returns the public key as base64 to send to the peer
     pub fn generate_keypair(&mut self) -> Result<String> {
-        let keypair = crypto::KeyPair::generate()
-            .map_err(|_| anyhow::anyhow!("Failed to generate keypair"))?;
+        let keypair = crypto::KeyPair::generate()?;
         let public_key = keypair.public_key_base64();
         self.keypair = Some(keypair);
         Ok(public_key)
     }
 
-    /// Complete key exchange with peer's public key
+    /// Complete key exchange with peer's public key. Failures are a
+    /// `CryptoError` underneath: a bad peer key (`Base64Decode`,
+    /// `InvalidPublicKeyLength`, `KeyAgreementFailed`) or `NoKeyPair` when
+    /// `generate_keypair` was not called first.
     pub fn complete_key_exchange(&mut self, peer_public_key_base64: &str) -> Result<()> {
-        let keypair = self
-            .keypair
-            .take()
-            .ok_or_else(|| anyhow::anyhow!("No keypair generated"))?;
+        // Validate before consuming the keypair so a malformed key can be retried.
+        let peer_key_bytes = crypto::parse_public_key(peer_public_key_base64)?;
 
-        let peer_key_bytes =
-            crypto::parse_public_key(peer_public_key_base64).map_err(|e| anyhow::anyhow!(e))?;
-        let ctx = keypair
-            .derive_shared_secret(&peer_key_bytes)
-            .map_err(|e| anyhow::anyhow!(e))?;
+        let keypair = self.keypair.take().ok_or(CryptoError::NoKeyPair)?;
+        let ctx = keypair.derive_shared_secret(&peer_key_bytes)?;
 
         self.crypto_ctx = Some(Arc::new(ctx));
         tracing::info!("E2EE key exchange completed successfully");
+
+        // The answerer may have set up WebRTC before the keys arrived
+        if self.rtc_connection.is_some() {
+            self.attach_call_audio()?;
+        }
+        Ok(())
+    }
+
+    /// Create the call's capture and playback, or adopt the pre-warmed ones,
+    /// keyed with the crypto context. No-op without a crypto context or if
+    /// audio is already attached; `init_webrtc` and `complete_key_exchange`
+    /// both call it, whichever runs last does the work.
+    fn attach_call_audio(&mut self) -> Result<()> {
+        let Some(ctx) = self.crypto_ctx.clone() else {
+            return Ok(());
+        };
+        if self.audio_capture.is_some() {
+            return Ok(());
+        }
+        self.stop_loopback_test();
+
+        let prewarmed = self.prewarmed_audio.lock().ok().and_then(|mut p| p.take());
+        let (capture, playback) = match prewarmed {
+            // Streams are already running; key them and pick up any
+            // settings changed while ringing. They resume on open.
+            Some((capture, playback)) => {
+                playback.set_crypto(ctx.clone());
+                playback.apply_config(&self.playback_config());
+                capture.set_crypto(ctx.clone());
+                capture.apply_config(&self.capture_config());
+                self.playback_started.store(true, Ordering::SeqCst);
+                (capture, playback)
+            }
+            None => {
+                let playback = Arc::new(AudioPlayback::new_with_config(
+                    ctx.clone(),
+                    self.playback_config(),
+                )?);
+                if self.playback_raw_mode {
+                    playback.set_raw_mode(true);
+                }
+                let capture = Arc::new(AudioCapture::new_with_config(
+                    ctx,
+                    playback.echo_reference(),
+                    self.capture_config(),
+                )?);
+                (capture, playback)
+            }
+        };
+        capture.set_jitter_sender(self.capture_jitter_tx.clone());
+        capture.set_device_event_sender(self.device_event_tx.clone());
+        playback.set_device_event_sender(self.device_event_tx.clone());
+        self.audio_playback = Some(playback.clone());
+        self.audio_capture = Some(capture.clone());
+        self.call_audio
+            .send_replace(Some(CallAudio { capture, playback }));
         Ok(())
     }
 
@@ -213,6 +918,12 @@ impl MediaEngine {
         self.crypto_ctx.is_some()
     }
 
+    /// Short authentication string of the call's key exchange, for both
+    /// users to compare; None before the exchange completes
+    pub fn sas_code(&self) -> Option<String> {
+        self.crypto_ctx.as_ref().and_then(|ctx| ctx.sas_code())
+    }
+
     /// Toggle mute on/off. Returns the new mute state.
     pub fn toggle_mute(&self) -> bool {
         if let Some(capture) = &self.audio_capture {
@@ -233,12 +944,165 @@ impl MediaEngine {
     }
 
     /// Take the RMS receiver for VU meter updates
-    pub fn take_rms_receiver(&self) -> Option<tokio::sync::mpsc::UnboundedReceiver<f32>> {
+    pub fn take_rms_receiver(&self) -> Option<tokio::sync::watch::Receiver<f32>> {
         self.audio_capture
             .as_ref()
             .and_then(|c| c.take_rms_receiver())
     }
 
+    /// Take the receiver for the output level of the current call's
+    /// playback, the remote side's VU meter
+    pub fn take_output_rms_receiver(&self) -> Option<tokio::sync::watch::Receiver<f32>> {
+        self.audio_playback
+            .as_ref()
+            .and_then(|p| p.take_output_rms_receiver())
+    }
+
+    /// Take the receiver for the remote speaking indicator of the current
+    /// call's playback
+    pub fn take_remote_speaking_receiver(
+        &self,
+    ) -> Option<mpsc::UnboundedReceiver<RemoteSpeakingState>> {
+        self.audio_playback
+            .as_ref()
+            .and_then(|p| p.take_remote_speaking_receiver())
+    }
+
+    /// Take the receiver for the debounced speaking indicator
+    pub fn take_speaking_receiver(&self) -> Option<mpsc::UnboundedReceiver<SpeakingState>> {
+        self.audio_capture
+            .as_ref()
+            .and_then(|c| c.take_speaking_receiver())
+    }
+
+    /// Take the receiver for audio device errors raised after a call connects
+    /// (e.g. the microphone is held by another application)
+    pub fn take_device_error_receiver(
+        &mut self,
+    ) -> Option<mpsc::UnboundedReceiver<AudioDeviceError>> {
+        self.device_error_rx.take()
+    }
+
+    /// Take the receiver for devices that disappear mid-call (e.g. an
+    /// unplugged headset). The affected stream is stopped; selecting a device
+    /// with `set_input_device` / `set_output_device` restarts it.
+    pub fn take_device_event_receiver(
+        &mut self,
+    ) -> Option<mpsc::UnboundedReceiver<AudioDeviceEvent>> {
+        self.device_event_rx.take()
+    }
+
+    /// Take the receiver for late capture callbacks across calls, to tell OS
+    /// audio stalls apart from network trouble
+    pub fn take_capture_jitter_receiver(
+        &mut self,
+    ) -> Option<mpsc::UnboundedReceiver<CaptureJitter>> {
+        self.capture_jitter_rx.take()
+    }
+
+    /// Late capture callbacks in the current call (0 without one)
+    pub fn capture_jitter_events(&self) -> u64 {
+        self.audio_capture
+            .as_ref()
+            .map(|c| c.jitter_events())
+            .unwrap_or(0)
+    }
+
+    /// Playback queue depth for the current call, `None` outside a call
+    pub fn playback_buffer_stats(&self) -> Option<PlaybackBufferStats> {
+        self.audio_playback
+            .as_ref()
+            .map(|playback| playback.buffer_stats())
+    }
+
+    /// Transport and audio statistics for the current call. Errors when no
+    /// peer connection is active.
+    pub async fn get_call_stats(&self) -> Result<CallStats> {
+        let pc = self
+            .rtc_connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No active call"))?;
+
+        let mut stats = CallStats::default();
+        for report in pc.get_stats().await.reports.into_values() {
+            match report {
+                StatsReportType::CandidatePair(pair)
+                    if pair.nominated && pair.current_round_trip_time > 0.0 =>
+                {
+                    stats.rtt_ms = Some(pair.current_round_trip_time * 1000.0);
+                }
+                StatsReportType::DataChannel(channel) if channel.label == "audio" => {
+                    stats.packets_sent = channel.messages_sent as u64;
+                    stats.packets_received = channel.messages_received as u64;
+                    stats.bytes_sent = channel.bytes_sent as u64;
+                    stats.bytes_received = channel.bytes_received as u64;
+                }
+                _ => {}
+            }
+        }
+
+        let channel = self
+            .audio_channel
+            .lock()
+            .ok()
+            .and_then(|channel| channel.clone());
+        if let Some(channel) = channel {
+            stats.data_channel_buffered_amount = channel.buffered_amount().await;
+        }
+        stats.frames_backpressure_dropped = self.send_controls.frames_dropped();
+
+        if let Some(capture) = &self.audio_capture {
+            let capture = capture.stats();
+            stats.frames_encoded = capture.frames_encoded;
+            stats.frames_vad_dropped = capture.frames_vad_dropped;
+            stats.frames_dtx = capture.frames_dtx;
+        }
+        if let Some(audio_playback) = &self.audio_playback {
+            let playback = audio_playback.stats();
+            stats.packets_lost = playback.packets_lost;
+            stats.packets_duplicate = playback.packets_duplicate;
+            stats.packets_out_of_order = playback.packets_out_of_order;
+            stats.packet_loss_percent = audio_playback.packet_loss_percent();
+            stats.jitter_ms = playback.jitter_ms;
+            stats.frames_concealed = playback.frames_concealed;
+            stats.buffer_underruns = audio_playback.underrun_count();
+            stats.buffer_overruns = audio_playback.overrun_count();
+        }
+        Ok(stats)
+    }
+
+    /// MOS-like score of the 1:1 call with its quality level, as of the
+    /// last `CALL_QUALITY_INTERVAL` sample; see [`QualitySample::mos`] for
+    /// the formula. None outside a call and before the first sample.
+    pub fn call_quality(&self) -> Option<CallQualityScore> {
+        *self.call_quality_score.borrow()
+    }
+
+    /// Take the receiver for peer connection state changes across calls
+    pub fn take_connection_state_receiver(
+        &mut self,
+    ) -> Option<mpsc::UnboundedReceiver<RTCPeerConnectionState>> {
+        self.connection_state_rx.take()
+    }
+
+    /// Take the receiver for call quality changes across calls. Each call
+    /// starts with `Good`.
+    pub fn take_call_quality_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<CallQuality>> {
+        self.call_quality_rx.take()
+    }
+
+    /// Take the receiver for ICE gathering and connection state changes
+    /// across calls
+    pub fn take_ice_state_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<IceStateChange>> {
+        self.ice_state_rx.take()
+    }
+
+    /// Take the receiver for sealed sender keys to deliver to group call
+    /// peers, see `SenderKeyUpdate`
+    pub fn take_sender_key_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<SenderKeyUpdate>> {
+        self.sender_key_rx.take()
+    }
+
     /// List available input (microphone) devices
     pub fn list_input_devices() -> Result<Vec<(String, String)>> {
         let host = cpal::default_host();
@@ -275,17 +1139,25 @@ impl MediaEngine {
             .filter(|d| !d.is_empty());
 
         self.selected_input_device = normalized;
+        self.device_follow
+            .input
+            .store(self.selected_input_device.is_none(), Ordering::SeqCst);
 
         if let Some(capture) = &self.audio_capture {
-            let was_running = capture.is_running();
+            let was_running = capture.is_running() || capture.device_lost();
             if was_running {
-                capture.stop();
-                std::thread::sleep(std::time::Duration::from_millis(120));
-                capture.start_with_device(self.selected_input_device.as_deref())?;
-                tracing::info!(
-                    "Input device switched to {:?}",
-                    self.selected_input_device.as_deref().unwrap_or("default")
-                );
+                let fell_back = restart_capture(capture, self.selected_input_device.as_deref())?;
+                if fell_back {
+                    tracing::warn!(
+                        "Input device {:?} unavailable, switched to default",
+                        self.selected_input_device.as_deref().unwrap_or("default")
+                    );
+                } else {
+                    tracing::info!(
+                        "Input device switched to {:?}",
+                        self.selected_input_device.as_deref().unwrap_or("default")
+                    );
+                }
             }
         }
 
@@ -320,30 +1192,205 @@ impl MediaEngine {
         self.selected_output_device.clone()
     }
 
-    /// Set preferred output device and hot-switch playback if already running
+    /// Set preferred output device and hot-switch playback if already
+    /// running. The ringback and playback are reopened on the new device
+    /// first; if that fails, the old device stays selected and in use.
     pub fn set_output_device(&mut self, device_name: Option<String>) -> Result<()> {
+        let applied = self.prepare_output_device(device_name).apply()?;
+        self.commit_output_device(applied);
+        Ok(())
+    }
+
+    /// `set_output_device` in steps, see `OutputDeviceChange`. Nothing
+    /// changes until `commit_output_device`.
+    pub fn prepare_output_device(&self, device_name: Option<String>) -> OutputDeviceChange {
         let normalized = device_name
             .map(|d| d.trim().to_string())
             .filter(|d| !d.is_empty());
 
-        self.selected_output_device = normalized;
+        let ringback = self.ringback.as_ref().map(|player| PendingRingtone {
+            slot: ToneSlot::Ringback,
+            clip: player.clip().clone(),
+            device: normalized.clone(),
+            volume: self.audio_settings.output_volume,
+            generation: self.ringback_generation,
+            select_device: false,
+        });
+        let playback = self
+            .audio_playback
+            .as_ref()
+            .filter(|playback| playback.is_running() || playback.device_lost())
+            .cloned();
+
+        OutputDeviceChange {
+            device: normalized,
+            previous: self.selected_output_device.clone(),
+            ringback,
+            playback,
+        }
+    }
+
+    /// Select the device of an applied change and swap in its ringback.
+    /// Playback it reopened for a call that has ended since is stopped.
+    pub fn commit_output_device(&mut self, applied: AppliedOutputDevice) {
+        self.selected_output_device = applied.device;
+        self.device_follow
+            .output
+            .store(self.selected_output_device.is_none(), Ordering::SeqCst);
 
-        if let Some(playback) = &self.audio_playback {
-            let was_running = playback.is_running();
-            if was_running {
+        if let Some(opened) = applied.ringback {
+            self.install_ringtone(opened);
+        }
+        if let Some(playback) = applied.playback {
+            let current = self
+                .audio_playback
+                .as_ref()
+                .is_some_and(|p| Arc::ptr_eq(p, &playback));
+            if !current {
                 playback.stop();
-                std::thread::sleep(std::time::Duration::from_millis(120));
-                playback.start_with_device(self.selected_output_device.as_deref())?;
-                tracing::info!(
-                    "Output device switched to {:?}",
-                    self.selected_output_device.as_deref().unwrap_or("default")
-                );
             }
         }
+    }
+
+    /// While a call runs, move capture and playback onto a new system
+    /// default device (e.g. headphones just connected). Only streams with no
+    /// device selected follow it; a selected device stays put. On by
+    /// default.
+    pub fn enable_auto_device_follow(&self, enabled: bool) {
+        self.device_follow.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Whether streams follow the system default device
+    pub fn auto_device_follow(&self) -> bool {
+        self.device_follow.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Return the ringtone device (if set by user)
+    pub fn selected_ringtone_device(&self) -> Option<String> {
+        self.selected_ringtone_device.clone()
+    }
+
+    /// Set the ringtone device and move a ringing ringtone onto it. The
+    /// ringtone is opened on the new device first; if that fails, the old
+    /// device stays selected and keeps ringing.
+    pub fn set_ringtone_device(&mut self, device_name: Option<String>) -> Result<()> {
+        if let Some(pending) = self.prepare_ringtone_device(device_name) {
+            let opened = pending.open()?;
+            self.install_ringtone(opened);
+        }
+        Ok(())
+    }
+
+    /// `set_ringtone_device` in steps, see `PendingRingtone`. Selects the
+    /// device right away when nothing rings; otherwise returns the ringtone
+    /// to open on it, and the device is selected once that succeeded.
+    pub fn prepare_ringtone_device(
+        &mut self,
+        device_name: Option<String>,
+    ) -> Option<PendingRingtone> {
+        let normalized = device_name
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty());
+
+        let Some(player) = &self.ringtone else {
+            self.selected_ringtone_device = normalized;
+            return None;
+        };
+        Some(PendingRingtone {
+            slot: ToneSlot::Ringtone,
+            clip: player.clip().clone(),
+            device: normalized,
+            volume: self.audio_settings.output_volume,
+            generation: self.ringtone_generation,
+            select_device: true,
+        })
+    }
+
+    /// Loop `clip` on the ringtone device until `stop_ringtone` or `reset`.
+    /// Replaces any ringtone already playing.
+    pub fn play_ringtone(&mut self, clip: RingtoneClip) -> Result<()> {
+        let opened = self.prepare_ringtone(clip).open()?;
+        self.install_ringtone(opened);
+        Ok(())
+    }
+
+    /// `play_ringtone` in steps, see `PendingRingtone`. The ringtone playing
+    /// now stops right away.
+    pub fn prepare_ringtone(&mut self, clip: RingtoneClip) -> PendingRingtone {
+        self.stop_ringtone();
+        PendingRingtone {
+            slot: ToneSlot::Ringtone,
+            clip,
+            device: self.selected_ringtone_device.clone(),
+            volume: self.audio_settings.output_volume,
+            generation: self.ringtone_generation,
+            select_device: false,
+        }
+    }
+
+    /// Start ringing with an opened ringtone, replacing the one playing. If
+    /// the ringtone was stopped or replaced since it was prepared (e.g. the
+    /// call was answered), the opened one is stopped instead and `false`
+    /// returned. A device it opened on is selected either way.
+    /// Takes a ringback from `prepare_ringback` the same way.
+    pub fn install_ringtone(&mut self, opened: OpenedRingtone) -> bool {
+        if opened.select_device {
+            self.selected_ringtone_device = opened.device;
+        }
+        let (slot, generation) = match opened.slot {
+            ToneSlot::Ringtone => (&mut self.ringtone, self.ringtone_generation),
+            ToneSlot::Ringback => (&mut self.ringback, self.ringback_generation),
+        };
+        if opened.generation != generation {
+            opened.player.stop();
+            return false;
+        }
+        if let Some(player) = slot.replace(opened.player) {
+            player.stop();
+        }
+        true
+    }
+
+    pub fn stop_ringtone(&mut self) {
+        self.ringtone_generation = self.ringtone_generation.wrapping_add(1);
+        if let Some(player) = self.ringtone.take() {
+            player.stop();
+        }
+    }
 
+    /// Loop the ringback tone for `region` on the call output device until
+    /// `stop_ringback` or `reset`, e.g. while an outgoing call rings.
+    pub fn play_ringback(&mut self, region: RingbackRegion) -> Result<()> {
+        let opened = self.prepare_ringback(region).open()?;
+        self.install_ringtone(opened);
         Ok(())
     }
 
+    /// `play_ringback` in steps, see `PendingRingtone`. The ringback playing
+    /// now stops right away.
+    pub fn prepare_ringback(&mut self, region: RingbackRegion) -> PendingRingtone {
+        self.stop_ringback();
+        PendingRingtone {
+            slot: ToneSlot::Ringback,
+            clip: RingtoneClip::ringback(region),
+            device: self.selected_output_device.clone(),
+            volume: self.audio_settings.output_volume,
+            generation: self.ringback_generation,
+            select_device: false,
+        }
+    }
+
+    pub fn stop_ringback(&mut self) {
+        self.ringback_generation = self.ringback_generation.wrapping_add(1);
+        if let Some(player) = self.ringback.take() {
+            player.stop();
+        }
+    }
+
+    pub fn is_ringing(&self) -> bool {
+        self.ringtone.is_some()
+    }
+
     fn parse_voice_mode(mode: &str) -> VoiceMode {
         match mode {
             "mute" => VoiceMode::Mute,
@@ -352,30 +1399,78 @@ impl MediaEngine {
         }
     }
 
+    fn parse_capture_stage_order(stages: &[String]) -> Result<CaptureStageOrder> {
+        let stages = stages
+            .iter()
+            .map(|stage| CaptureStage::parse(stage))
+            .collect::<Result<Vec<_>>>()?;
+        CaptureStageOrder::from_stages(&stages)
+    }
+
+    fn capture_config(&self) -> AudioCaptureConfig {
+        let settings = &self.audio_settings;
+        let processing = !settings.minimal_processing;
+        let profile = AudioProfile::for_settings(settings);
+
+        AudioCaptureConfig {
+            input_gain: settings.mic_gain,
+            voice_mode: Self::parse_voice_mode(&settings.voice_mode),
+            vad_threshold: settings.vad_threshold,
+            noise_gate_threshold: profile.noise_gate_threshold,
+            noise_suppression: processing && settings.noise_suppression,
+            noise_suppression_mode: NoiseSuppressionMode::parse(&settings.noise_suppression_mode)
+                .unwrap_or_default(),
+            aec_enabled: processing && profile.aec,
+            agc_enabled: processing && settings.agc,
+            agc_target_rms: settings.agc_target_rms,
+            agc_min_gain: settings.agc_min_gain,
+            agc_max_gain: settings.agc_max_gain,
+            noise_gate_enabled: processing && profile.noise_gate,
+            noise_gate_hold_ms: settings.noise_gate_hold_ms,
+            noise_gate_high_band_bias: settings.noise_gate_high_band_bias,
+            stage_order: Self::parse_capture_stage_order(&settings.capture_stage_order)
+                .unwrap_or_default(),
+            muted: settings.deafen || settings.voice_mode == "mute",
+            stereo: settings.stereo,
+            dtx: settings.dtx,
+            frame_duration_ms: settings.frame_duration_ms,
+        }
+        .clamped()
+    }
+
+    fn playback_config(&self) -> AudioPlaybackConfig {
+        let profile = self.audio_profile();
+        AudioPlaybackConfig {
+            output_volume: profile.output_volume(self.audio_settings.output_volume),
+            remote_volume: self.active_peer_volume(),
+            limiter_enabled: self.audio_settings.limiter && !self.audio_settings.minimal_processing,
+            muted: self.audio_settings.deafen,
+            deafened: self.audio_settings.deafen,
+            jitter_target_ms: self.audio_settings.jitter_buffer_ms,
+            stereo: self.audio_settings.stereo,
+            compressor: CompressorSettings {
+                enabled: self.audio_settings.compressor && !self.audio_settings.minimal_processing,
+                threshold_db: self.audio_settings.compressor_threshold_db,
+                ratio: self.audio_settings.compressor_ratio,
+                attack_ms: self.audio_settings.compressor_attack_ms,
+                release_ms: self.audio_settings.compressor_release_ms,
+            },
+        }
+        .clamped()
+    }
+
     fn apply_audio_settings_to_runtime(&self) {
-        let effective_aec = match self.audio_settings.audio_mode {
-            AudioMode::Headphones => false,
-            AudioMode::Speakers => self.audio_settings.aec,
-        };
+        self.send_controls
+            .set_keepalive_interval(self.audio_settings.nat_keepalive_interval);
+        self.send_controls
+            .set_buffer_limit(self.audio_settings.send_buffer_limit_bytes);
 
         if let Some(capture) = &self.audio_capture {
-            capture.set_input_gain(self.audio_settings.mic_gain);
-            capture.set_voice_mode(Self::parse_voice_mode(&self.audio_settings.voice_mode));
-            capture.set_vad_threshold(self.audio_settings.vad_threshold);
-            capture.set_noise_suppression(self.audio_settings.noise_suppression);
-            capture.set_aec_enabled(effective_aec);
-            capture.set_agc_enabled(self.audio_settings.agc);
-            capture.set_noise_gate_enabled(self.audio_settings.noise_gate);
-            capture.set_noise_gate_threshold(self.audio_settings.noise_gate_threshold);
-            capture
-                .set_muted(self.audio_settings.deafen || self.audio_settings.voice_mode == "mute");
+            capture.apply_config(&self.capture_config());
         }
 
         if let Some(playback) = &self.audio_playback {
-            playback.set_output_volume(self.audio_settings.output_volume);
-            playback.set_remote_volume(self.audio_settings.remote_user_volume);
-            playback.set_limiter_enabled(self.audio_settings.limiter);
-            playback.set_muted(self.audio_settings.deafen);
+            playback.apply_config(&self.playback_config());
         }
     }
 
@@ -383,57 +1478,359 @@ impl MediaEngine {
         self.audio_settings.clone()
     }
 
-    pub fn update_audio_settings(&mut self, settings: AudioSettings) {
+    /// Parameters `audio_mode` and the user's overrides currently resolve to
+    pub fn audio_profile(&self) -> AudioProfile {
+        AudioProfile::for_settings(&self.audio_settings)
+    }
+
+    /// Replace the audio settings. During a call the frame duration and
+    /// stereo must stay as they are: those go through
+    /// `offer_call_audio_params` so the peer agrees first.
+    pub fn update_audio_settings(&mut self, settings: AudioSettings) -> Result<()> {
+        Self::parse_capture_stage_order(&settings.capture_stage_order)?;
+        let params = CallAudioParams::of(&settings);
+        params.validate()?;
+        if self.in_call() && params != self.call_audio_params() {
+            return Err(anyhow::anyhow!(
+                "Frame duration and stereo cannot change during a call without the peer's agreement"
+            ));
+        }
+        if settings.agc_min_gain > settings.agc_max_gain {
+            return Err(anyhow::anyhow!(
+                "AGC minimum gain {} is above the maximum {}",
+                settings.agc_min_gain,
+                settings.agc_max_gain
+            ));
+        }
         self.audio_settings = settings;
         self.apply_audio_settings_to_runtime();
+        Ok(())
     }
 
-    pub fn set_ptt_active(&self, active: bool) {
-        if let Some(capture) = &self.audio_capture {
-            capture.set_ptt_active(active);
+    /// Whether a 1:1 or group call has a peer connection
+    pub fn in_call(&self) -> bool {
+        self.rtc_connection.is_some() || !self.peers.is_empty()
+    }
+
+    /// Peer of the current 1:1 call, see `set_active_peer`
+    pub fn active_peer(&self) -> Option<&str> {
+        self.active_peer.as_deref()
+    }
+
+    /// Frame duration and channels currently in use
+    pub fn call_audio_params(&self) -> CallAudioParams {
+        CallAudioParams::of(&self.audio_settings)
+    }
+
+    /// Remember `params` as our offer to the peer of the 1:1 call. Nothing
+    /// changes until `finish_call_audio_params` gets the peer's answer; a
+    /// newer offer replaces an unanswered one. Group calls have no single
+    /// peer to agree with, so they keep the parameters they started with.
+    pub fn offer_call_audio_params(&mut self, params: CallAudioParams) -> Result<()> {
+        params.validate()?;
+        if self.rtc_connection.is_none() || !self.peers.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Audio parameters can only be renegotiated in a 1:1 call"
+            ));
         }
+        self.audio_params.offer(params);
+        Ok(())
     }
 
-    pub fn set_remote_user_volume(&mut self, volume: f32) {
-        self.audio_settings.remote_user_volume = volume.clamp(0.0, 2.0);
-        if let Some(playback) = &self.audio_playback {
-            playback.set_remote_volume(self.audio_settings.remote_user_volume);
+    /// The peer offered `params`: switch the encoder and decoder to them,
+    /// or error, leaving ours in place, when they are unsupported, there is
+    /// no 1:1 call, or the offer crossed one of ours that wins. Offers that
+    /// cross settle on the one from the lower user id (see `audio_params`);
+    /// `self_id` is ours.
+    pub fn accept_call_audio_params(
+        &mut self,
+        self_id: &str,
+        params: CallAudioParams,
+    ) -> Result<()> {
+        params.validate()?;
+        let peer_id = match self.active_peer.as_deref() {
+            Some(peer_id) if self.rtc_connection.is_some() && self.peers.is_empty() => peer_id,
+            _ => return Err(anyhow::anyhow!("No 1:1 call to renegotiate")),
+        };
+        if !self.audio_params.receive_offer(self_id, peer_id) {
+            return Err(anyhow::anyhow!(
+                "Our own offer crossed the peer's and takes precedence"
+            ));
         }
+        self.apply_call_audio_params(params);
+        Ok(())
     }
 
-    // === WebRTC Implementation ===
+    /// The peer answered our offer. Accepted, the offered parameters apply
+    /// now and are returned. A rejection drops the offer and, like an
+    /// acceptance of an offer we no longer have, changes nothing.
+    pub fn finish_call_audio_params(
+        &mut self,
+        accepted: bool,
+        params: CallAudioParams,
+    ) -> Option<CallAudioParams> {
+        let params = self.audio_params.receive_answer(accepted, params)?;
+        self.rtc_connection.as_ref()?;
+        self.apply_call_audio_params(params);
+        Some(params)
+    }
+
+    fn apply_call_audio_params(&mut self, params: CallAudioParams) {
+        params.apply_to(&mut self.audio_settings);
+        self.apply_audio_settings_to_runtime();
+        tracing::info!(
+            "Call audio params -> {} ms, {} channel(s)",
+            params.frame_duration_ms,
+            params.channels
+        );
+    }
+
+    pub fn set_ptt_active(&self, active: bool) {
+        if let Some(capture) = &self.audio_capture {
+            capture.set_ptt_active(active);
+        }
+    }
+
+    /// Toggle the playback raw monitor (see `AudioPlayback::set_raw_mode`).
+    /// Applies to the running call and to the next one.
+    pub fn set_playback_raw_mode(&mut self, enabled: bool) {
+        self.playback_raw_mode = enabled;
+        if let Some(playback) = &self.audio_playback {
+            playback.set_raw_mode(enabled);
+        }
+    }
+
+    /// Volume for peers without one of their own
+    pub fn set_remote_user_volume(&mut self, volume: f32) {
+        self.audio_settings.remote_user_volume = volume.clamp(0.0, 2.0);
+        if let Some(playback) = &self.audio_playback {
+            playback.set_remote_volume(self.active_peer_volume());
+        }
+    }
+
+    /// Set the peer the current call's audio comes from, so playback uses
+    /// its volume. Cleared by `reset`.
+    pub fn set_active_peer(&mut self, peer_id: Option<String>) {
+        self.active_peer = peer_id;
+        if let Some(playback) = &self.audio_playback {
+            playback.set_remote_volume(self.active_peer_volume());
+        }
+    }
+
+    /// Remember the volume for `peer_id`, applying it at once if that peer
+    /// is in the current call. Survives `reset`, so it carries over to the
+    /// next call with the same peer.
+    pub fn set_peer_volume(&mut self, peer_id: &str, volume: f32) {
+        let volume = volume.clamp(0.0, 2.0);
+        self.peer_volumes.insert(peer_id.to_string(), volume);
+        if let Some(playback) = &self.audio_playback {
+            if self.active_peer.as_deref() == Some(peer_id) {
+                playback.set_remote_volume(volume);
+            }
+            // Group call peers each have a stream of their own
+            playback.set_peer_volume(peer_id, volume);
+        }
+    }
+
+    /// Volume for `peer_id`, falling back to the `remote_user_volume`
+    /// setting when none was set for it
+    pub fn get_peer_volume(&self, peer_id: &str) -> f32 {
+        self.peer_volumes
+            .get(peer_id)
+            .copied()
+            .unwrap_or(self.audio_settings.remote_user_volume)
+    }
+
+    fn active_peer_volume(&self) -> f32 {
+        match &self.active_peer {
+            Some(peer_id) => self.get_peer_volume(peer_id),
+            None => self.audio_settings.remote_user_volume,
+        }
+    }
+
+    // === WebRTC Implementation ===
 
     /// Initialize WebRTC PeerConnection
     /// Returns a receiver for local ICE candidates that must be sent to the peer
     pub async fn init_webrtc(&mut self) -> Result<mpsc::Receiver<String>> {
-        let mut media_engine = WebRtcMediaEngine::default();
-        media_engine.register_default_codecs()?;
+        if !self.peers.is_empty() {
+            return Err(anyhow::anyhow!("A group call is active"));
+        }
+        let (pc, ice_rx) = self.new_peer_connection().await?;
 
-        let api = APIBuilder::new().with_media_engine(media_engine).build();
+        let connection_state_tx = self.connection_state_tx.clone();
+        pc.on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
+            tracing::info!("Peer Connection State has changed: {}", s);
+            let _ = connection_state_tx.send(s);
+            Box::pin(async {})
+        }));
 
-        let ice_servers = self
-            .ice_servers
-            .iter()
-            .map(|cfg| {
-                let mut server = RTCIceServer {
-                    urls: cfg.urls.clone(),
-                    ..Default::default()
-                };
-                if let Some(username) = &cfg.username {
-                    server.username = username.clone();
-                }
-                if let Some(credential) = &cfg.credential {
-                    server.credential = credential.clone();
+        let ice_state_tx = self.ice_state_tx.clone();
+        pc.on_ice_gathering_state_change(Box::new(move |s: RTCIceGathererState| {
+            tracing::debug!("ICE gathering state has changed: {}", s);
+            let _ = ice_state_tx.send(IceStateChange::Gathering(s));
+            Box::pin(async {})
+        }));
+        let ice_state_tx = self.ice_state_tx.clone();
+        pc.on_ice_connection_state_change(Box::new(move |s: RTCIceConnectionState| {
+            tracing::info!("ICE connection state has changed: {}", s);
+            let _ = ice_state_tx.send(IceStateChange::Connection(s));
+            Box::pin(async {})
+        }));
+
+        // Audio may not exist yet if the offer beat the key exchange; the
+        // callbacks read it from `call_audio` when they fire
+        self.attach_call_audio()?;
+        if let Some(task) = self
+            .bitrate_task
+            .replace(tokio::spawn(adapt_bitrate(self.call_audio.subscribe())))
+        {
+            task.abort();
+        }
+        if let Some(task) = self
+            .device_follow_task
+            .replace(tokio::spawn(follow_default_devices(
+                self.call_audio.subscribe(),
+                self.device_follow.clone(),
+            )))
+        {
+            task.abort();
+        }
+        let (score_tx, score_rx) = watch::channel(None);
+        self.call_quality_score = score_rx;
+        if let Some(task) = self.quality_task.replace(tokio::spawn(monitor_call_quality(
+            self.call_audio.subscribe(),
+            pc.clone(),
+            self.call_quality_tx.clone(),
+            score_tx,
+        ))) {
+            task.abort();
+        }
+
+        // Clone for on_data_channel closures
+        let call_audio = self.call_audio.subscribe();
+        let playback_started_clone = self.playback_started.clone();
+        let preferred_input_device = self.selected_input_device.clone();
+        let preferred_output_device = self.selected_output_device.clone();
+        let device_errors_clone = self.device_error_tx.clone();
+        let send_controls_clone = self.send_controls.clone();
+        let audio_channel_clone = self.audio_channel.clone();
+
+        // Handle incoming DataChannel (Answerer side receives channel created by Offerer)
+        pc.on_data_channel(Box::new(move |d_channel: Arc<RTCDataChannel>| {
+            let call_audio = call_audio.clone();
+            let playback_started = playback_started_clone.clone();
+            let preferred_input_device = preferred_input_device.clone();
+            let preferred_output_device = preferred_output_device.clone();
+            let device_errors = device_errors_clone.clone();
+            let send_controls = send_controls_clone.clone();
+            if d_channel.label() == "audio" {
+                if let Ok(mut channel) = audio_channel_clone.lock() {
+                    *channel = Some(d_channel.clone());
                 }
-                server
+            }
+
+            Box::pin(async move {
+                tracing::info!("New DataChannel {} {}", d_channel.label(), d_channel.id());
+
+                let d_channel_clone = d_channel.clone();
+                let audio_for_open = call_audio.clone();
+                d_channel.on_open(Box::new(move || {
+                    tracing::info!("Data channel opened (Answerer)");
+                    let dc = d_channel_clone.clone();
+                    let mut call_audio = audio_for_open.clone();
+                    let ps = playback_started.clone();
+                    let preferred_input = preferred_input_device.clone();
+                    let preferred_output = preferred_output_device.clone();
+                    let device_errors = device_errors.clone();
+                    let send_controls = send_controls.clone();
+                    Box::pin(async move {
+                        // Waiting here would hold up the channel's other
+                        // callbacks, so wait for the keys in a task
+                        tokio::spawn(async move {
+                            let audio = match call_audio.wait_for(Option::is_some).await {
+                                Ok(audio) => audio.clone(),
+                                Err(_) => return,
+                            };
+                            let Some(CallAudio { capture, playback }) = audio else {
+                                return;
+                            };
+
+                            // Start playback stream once
+                            if !ps.swap(true, Ordering::SeqCst) {
+                                let started = start_stream_blocking({
+                                    let playback = playback.clone();
+                                    move || playback.start_with_device(preferred_output.as_deref())
+                                })
+                                .await;
+                                match started {
+                                    Ok(_) => tracing::info!("Playback stream started (Answerer)"),
+                                    Err(e) => {
+                                        tracing::error!("Failed to start playback: {}", e);
+                                        report_device_error(&device_errors, &e);
+                                    }
+                                }
+                            }
+
+                            playback.resume();
+
+                            // Start capture
+                            let started = start_stream_blocking({
+                                let capture = capture.clone();
+                                move || capture.start_with_device(preferred_input.as_deref())
+                            })
+                            .await;
+                            if let Err(e) = started {
+                                tracing::error!("Failed to start capture: {}", e);
+                                report_device_error(&device_errors, &e);
+                            }
+                            capture.resume();
+
+                            // Pipe capture -> DC
+                            if let Some(rx) = capture.take_packet_receiver() {
+                                send_audio_packets(rx, dc, send_controls, "Answerer").await;
+                            }
+                        });
+                    })
+                }));
+
+                d_channel.on_message(Box::new(move |msg: DataChannelMessage| {
+                    // Packets that arrive before the keys cannot be decrypted
+                    let playback = call_audio
+                        .borrow()
+                        .as_ref()
+                        .map(|audio| audio.playback.clone());
+                    Box::pin(async move {
+                        let Some(playback) = playback else {
+                            return;
+                        };
+                        if let Ok(packet) = bincode::deserialize::<AudioPacket>(&msg.data) {
+                            if let Err(e) = playback.process_packet(packet) {
+                                tracing::warn!(
+                                    "Failed to process incoming audio packet (Answerer): {}",
+                                    e
+                                );
+                            }
+                        }
+                    })
+                }));
             })
-            .collect::<Vec<_>>();
+        }));
 
-        let config = RTCConfiguration {
-            ice_servers,
-            ice_transport_policy: RTCIceTransportPolicy::All, // Allow both UDP and TCP
-            ..Default::default()
-        };
+        self.rtc_connection = Some(pc);
+        Ok(ice_rx)
+    }
+
+    /// A peer connection on the configured ICE servers, and a receiver for
+    /// its local ICE candidates as JSON
+    async fn new_peer_connection(
+        &self,
+    ) -> Result<(Arc<RTCPeerConnection>, mpsc::Receiver<String>)> {
+        let mut media_engine = WebRtcMediaEngine::default();
+        media_engine.register_default_codecs()?;
+
+        let api = APIBuilder::new().with_media_engine(media_engine).build();
+        let config = rtc_configuration(&self.ice_servers);
 
         let pc = Arc::new(api.new_peer_connection(config).await?);
         let (ice_tx, ice_rx) = mpsc::channel(10);
@@ -454,150 +1851,82 @@ impl MediaEngine {
             })
         }));
 
-        pc.on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
-            tracing::info!("Peer Connection State has changed: {}", s);
-            Box::pin(async {})
-        }));
-
-        // Initialize Audio Components if we have crypto context
-        if let Some(ctx) = &self.crypto_ctx {
-            // Setup Playback
-            let playback = Arc::new(AudioPlayback::new(ctx.clone())?);
-            self.audio_playback = Some(playback.clone());
-            let shared_playback_rms = playback.output_rms_shared();
-
-            // Setup Capture
-            let capture = Arc::new(AudioCapture::new(ctx.clone(), shared_playback_rms)?);
-            self.audio_capture = Some(capture.clone());
-
-            self.apply_audio_settings_to_runtime();
-
-            // Clone for on_data_channel closures
-            let playback_started_clone = self.playback_started.clone();
-            let preferred_input_device = self.selected_input_device.clone();
-            let preferred_output_device = self.selected_output_device.clone();
-
-            // Handle incoming DataChannel (Answerer side receives channel created by Offerer)
-            let playback_clone = playback.clone();
-            let capture_clone = capture.clone();
-            let preferred_input_device_clone = preferred_input_device.clone();
-            let preferred_output_device_clone = preferred_output_device.clone();
-
-            pc.on_data_channel(Box::new(move |d_channel: Arc<RTCDataChannel>| {
-                let playback = playback_clone.clone();
-                let capture = capture_clone.clone();
-                let playback_started = playback_started_clone.clone();
-                let preferred_input_device = preferred_input_device_clone.clone();
-                let preferred_output_device = preferred_output_device_clone.clone();
-
-                Box::pin(async move {
-                    tracing::info!("New DataChannel {} {}", d_channel.label(), d_channel.id());
-
-                    let d_channel_clone = d_channel.clone();
-                    let playback_for_open = playback.clone();
-                    let ps_for_open = playback_started.clone();
-                    let preferred_input_for_open = preferred_input_device.clone();
-                    let preferred_output_for_open = preferred_output_device.clone();
-                    d_channel.on_open(Box::new(move || {
-                        tracing::info!("Data channel opened (Answerer)");
-                        let dc = d_channel_clone.clone();
-                        let capture = capture.clone();
-                        let playback = playback_for_open.clone();
-                        let ps = ps_for_open.clone();
-                        let preferred_input = preferred_input_for_open.clone();
-                        let preferred_output = preferred_output_for_open.clone();
-                        Box::pin(async move {
-                            // Start playback stream once
-                            if !ps.swap(true, Ordering::SeqCst) {
-                                match playback.start_with_device(preferred_output.as_deref()) {
-                                    Ok(()) => tracing::info!("Playback stream started (Answerer)"),
-                                    Err(e) => tracing::error!("Failed to start playback: {}", e),
-                                }
-                            }
+        Ok((pc, ice_rx))
+    }
 
-                            // Start capture
-                            if let Err(e) = capture.start_with_device(preferred_input.as_deref()) {
-                                tracing::error!("Failed to start capture: {}", e);
-                            }
+    /// Gather candidates against the configured ICE servers on a throwaway
+    /// connection and report whether STUN and TURN answered within
+    /// `CONNECTIVITY_CHECK_TIMEOUT`. Takes that long at worst; callers that
+    /// hold the engine behind a lock should clone `get_ice_servers()` and
+    /// use `check_ice_servers` instead.
+    pub async fn check_connectivity(&self) -> Result<ConnectivityReport> {
+        check_ice_servers(&self.ice_servers, CONNECTIVITY_CHECK_TIMEOUT).await
+    }
 
-                            // Pipe capture -> DC
-                            if let Some(mut rx) = capture.take_packet_receiver() {
-                                tokio::spawn(async move {
-                                    while let Some(packet) = rx.recv().await {
-                                        if let Ok(bytes) = bincode::serialize(&packet) {
-                                            if let Err(e) = dc.send(&bytes.into()).await {
-                                                tracing::warn!(
-                                                    "Failed to send audio packet (Answerer): {}",
-                                                    e
-                                                );
-                                            }
-                                        }
-                                    }
-                                });
-                            }
-                        })
-                    }));
+    /// Create an offer for a WebRTC connection.
+    /// `transform` may rewrite the SDP before it is applied locally.
+    pub async fn create_offer(&self, transform: Option<&SdpTransform>) -> Result<String> {
+        let pc = self
+            .rtc_connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
+        create_offer_on(pc, None, transform).await
+    }
 
-                    d_channel.on_message(Box::new(move |msg: DataChannelMessage| {
-                        let playback = playback.clone();
-                        Box::pin(async move {
-                            if let Ok(packet) = bincode::deserialize::<AudioPacket>(&msg.data) {
-                                if let Err(e) = playback.process_packet(packet) {
-                                    tracing::warn!(
-                                        "Failed to process incoming audio packet (Answerer): {}",
-                                        e
-                                    );
-                                }
-                            }
-                        })
-                    }));
-                })
-            }));
+    /// Restart ICE on the current call: gather fresh candidates and return
+    /// an offer carrying new ICE credentials for the peer to answer with
+    /// `accept_ice_restart`. The data channel and codec state are kept, so
+    /// audio resumes on the same channel once the new path connects.
+    pub async fn restart_ice(&self, transform: Option<&SdpTransform>) -> Result<String> {
+        let pc = self
+            .rtc_connection
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
+        if !self.has_audio_channel() {
+            anyhow::bail!("No call in progress to restart");
         }
-
-        self.rtc_connection = Some(pc);
-        Ok(ice_rx)
+        let options = RTCOfferOptions {
+            ice_restart: true,
+            ..Default::default()
+        };
+        create_offer_on(pc, Some(options), transform).await
     }
 
-    /// Create an offer for a WebRTC connection
-    pub async fn create_offer(&self) -> Result<String> {
+    /// Answer an ICE restart offer from the peer on the current call
+    pub async fn accept_ice_restart(
+        &self,
+        offer_sdp: &str,
+        transform: Option<&SdpTransform>,
+    ) -> Result<String> {
         let pc = self
             .rtc_connection
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
+        if !self.has_audio_channel() {
+            anyhow::bail!("No call in progress to restart");
+        }
+        accept_offer_on(pc, offer_sdp, transform).await
+    }
 
-        let offer = pc.create_offer(None).await?;
-        pc.set_local_description(offer).await?;
-
-        // Send the SDP immediately and rely on trickle ICE via on_ice_candidate.
-        let local_desc = pc
-            .local_description()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Failed to get local description"))?;
-
-        Ok(serde_json::to_string(&local_desc)?)
+    fn has_audio_channel(&self) -> bool {
+        self.audio_channel
+            .lock()
+            .map(|channel| channel.is_some())
+            .unwrap_or(false)
     }
 
-    /// Accept an offer from a peer and create an answer
-    pub async fn accept_offer(&self, offer_sdp: &str) -> Result<String> {
+    /// Accept an offer from a peer and create an answer.
+    /// `transform` may rewrite the answer SDP before it is applied locally.
+    pub async fn accept_offer(
+        &self,
+        offer_sdp: &str,
+        transform: Option<&SdpTransform>,
+    ) -> Result<String> {
         let pc = self
             .rtc_connection
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
-
-        let offer = serde_json::from_str::<RTCSessionDescription>(offer_sdp)?;
-        pc.set_remote_description(offer).await?;
-
-        let answer = pc.create_answer(None).await?;
-        pc.set_local_description(answer).await?;
-
-        // Send the SDP immediately and rely on trickle ICE via on_ice_candidate.
-        let local_desc = pc
-            .local_description()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Failed to get local description"))?;
-
-        Ok(serde_json::to_string(&local_desc)?)
+        accept_offer_on(pc, offer_sdp, transform).await
     }
 
     /// Set the remote description (answer or offer)
@@ -606,10 +1935,7 @@ impl MediaEngine {
             .rtc_connection
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
-
-        let remote_desc = serde_json::from_str::<RTCSessionDescription>(sdp)?;
-        pc.set_remote_description(remote_desc).await?;
-        Ok(())
+        set_remote_description_on(pc, sdp).await
     }
 
     /// Add a remote ICE candidate
@@ -618,10 +1944,7 @@ impl MediaEngine {
             .rtc_connection
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
-
-        let ice_candidate_init: RTCIceCandidateInit = serde_json::from_str(candidate_json)?;
-        pc.add_ice_candidate(ice_candidate_init).await?;
-        Ok(())
+        add_ice_candidate_on(pc, candidate_json).await
     }
 
     /// Create DataChannel for audio (Offerer side) and start capture
@@ -631,11 +1954,12 @@ impl MediaEngine {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("WebRTC not initialized"))?;
 
-        let mut options = RTCDataChannelInit::default();
-        options.ordered = Some(false);
-        options.max_retransmits = Some(0); // Unreliable (UDP-like) for audio
-
-        let dc = pc.create_data_channel("audio", Some(options)).await?;
+        let dc = pc
+            .create_data_channel("audio", Some(audio_channel_options()))
+            .await?;
+        if let Ok(mut channel) = self.audio_channel.lock() {
+            *channel = Some(dc.clone());
+        }
 
         let audio_capture = self
             .audio_capture
@@ -665,6 +1989,8 @@ impl MediaEngine {
         let playback_started = self.playback_started.clone();
         let preferred_input_for_open = preferred_input_device.clone();
         let preferred_output_for_open = preferred_output_device.clone();
+        let device_errors_for_open = self.device_error_tx.clone();
+        let send_controls_for_open = self.send_controls.clone();
         dc.on_open(Box::new(move || {
             tracing::info!("DataChannel 'audio' opened (Offerer)");
             let dc = dc_clone.clone();
@@ -673,39 +1999,947 @@ impl MediaEngine {
             let ps = playback_started.clone();
             let preferred_input = preferred_input_for_open.clone();
             let preferred_output = preferred_output_for_open.clone();
+            let device_errors = device_errors_for_open.clone();
+            let send_controls = send_controls_for_open.clone();
 
-            Box::pin(async move {
+            // Opening the streams would hold up the channel's other
+            // callbacks, so it runs in a task
+            tokio::spawn(async move {
                 // Start playback stream once (Offerer side)
                 if !ps.swap(true, Ordering::SeqCst) {
-                    match playback.start_with_device(preferred_output.as_deref()) {
-                        Ok(()) => tracing::info!("Playback stream started (Offerer)"),
-                        Err(e) => tracing::error!("Failed to start playback: {}", e),
+                    let started = start_stream_blocking({
+                        let playback = playback.clone();
+                        move || playback.start_with_device(preferred_output.as_deref())
+                    })
+                    .await;
+                    match started {
+                        Ok(_) => tracing::info!("Playback stream started (Offerer)"),
+                        Err(e) => {
+                            tracing::error!("Failed to start playback: {}", e);
+                            report_device_error(&device_errors, &e);
+                        }
                     }
                 }
 
+                playback.resume();
+
                 // Start capture stream locally
-                if let Err(e) = capture.start_with_device(preferred_input.as_deref()) {
+                let started = start_stream_blocking({
+                    let capture = capture.clone();
+                    move || capture.start_with_device(preferred_input.as_deref())
+                })
+                .await;
+                if let Err(e) = started {
                     tracing::error!("Failed to start capture: {}", e);
+                    report_device_error(&device_errors, &e);
                     return;
                 }
+                capture.resume();
 
                 // Pipe captured audio packets to the DataChannel
-                if let Some(mut rx) = capture.take_packet_receiver() {
-                    tokio::spawn(async move {
-                        while let Some(packet) = rx.recv().await {
-                            if let Ok(bytes) = bincode::serialize(&packet) {
-                                if let Err(e) = dc.send(&bytes.into()).await {
-                                    tracing::warn!("Failed to send audio packet (Offerer): {}", e);
-                                }
-                            }
-                        }
-                    });
+                if let Some(rx) = capture.take_packet_receiver() {
+                    send_audio_packets(rx, dc, send_controls, "Offerer").await;
                 } else {
                     tracing::error!("Failed to take packet receiver - already taken?");
                 }
+            });
+            Box::pin(async {})
+        }));
+
+        Ok(())
+    }
+
+    // === Mic test ===
+
+    /// Loop the microphone into the speaker for `duration` (at most
+    /// `MAX_LOOPBACK_TEST`) so the user hears themselves, without a peer or
+    /// WebRTC. Frames take the call's path through capture and playback,
+    /// processing and Opus included, so what is heard is what a peer
+    /// would get. They are still encrypted, under a throwaway key: that
+    /// costs next to nothing and keeps a plaintext path out of the
+    /// pipeline.
+    ///
+    /// Stops on its own, or with `stop_loopback_test`, `reset` or a call
+    /// opening the devices. Fails during a call or while one rings.
+    pub fn start_loopback_test(&self, duration: Duration) -> Result<()> {
+        if self.audio_capture.is_some() || !self.peers.is_empty() {
+            return Err(anyhow::anyhow!("A call is active"));
+        }
+        if self.prewarmed_audio.lock().is_ok_and(|p| p.is_some()) {
+            return Err(anyhow::anyhow!("Audio devices are held for a ringing call"));
+        }
+        self.stop_loopback_test();
+
+        let ctx = Arc::new(crypto::SenderKey::generate()?.context()?);
+        let playback = Arc::new(AudioPlayback::new_with_config(
+            ctx.clone(),
+            self.playback_config(),
+        )?);
+        playback.set_raw_mode(self.playback_raw_mode);
+        let capture = Arc::new(AudioCapture::new_with_config(
+            ctx,
+            playback.echo_reference(),
+            self.capture_config(),
+        )?);
+        // Muted between calls would make the test sound broken
+        capture.set_muted(false);
+        let mut packets = capture
+            .take_packet_receiver()
+            .ok_or_else(|| anyhow::anyhow!("Capture packet receiver already taken"))?;
+
+        let started = playback
+            .start_with_device(self.selected_output_device.as_deref())
+            .and_then(|_| capture.start_with_device(self.selected_input_device.as_deref()));
+        if let Err(e) = started {
+            capture.stop();
+            playback.stop();
+            return Err(e);
+        }
+
+        let duration = duration.min(MAX_LOOPBACK_TEST);
+        let task = {
+            let capture = capture.clone();
+            let playback = playback.clone();
+            tokio::spawn(async move {
+                let looped = async {
+                    while let Some(packet) = packets.recv().await {
+                        if let Err(e) = playback.process_packet(packet) {
+                            tracing::warn!("Mic test dropped a packet: {}", e);
+                        }
+                    }
+                };
+                let _ = tokio::time::timeout(duration, looped).await;
+                capture.stop();
+                playback.stop();
+                tracing::info!("Mic test finished");
             })
+        };
+        if let Ok(mut loopback) = self.loopback_test.lock() {
+            *loopback = Some(LoopbackTest {
+                capture,
+                playback,
+                task,
+            });
+        }
+        tracing::info!("Mic test started for {:?}", duration);
+        Ok(())
+    }
+
+    /// End a mic test early. No-op if none is running.
+    pub fn stop_loopback_test(&self) {
+        let taken = self.loopback_test.lock().ok().and_then(|mut t| t.take());
+        if let Some(test) = taken {
+            test.stop();
+        }
+    }
+
+    pub fn is_loopback_test_running(&self) -> bool {
+        self.loopback_test
+            .lock()
+            .is_ok_and(|t| t.as_ref().is_some_and(|t| !t.task.is_finished()))
+    }
+
+    // === Recording ===
+
+    /// Record the current call to `path`: our microphone on the left, what
+    /// we hear on the right, sealed with a fresh key returned in the
+    /// [`RecordingInfo`]. The file must not exist. Ends with
+    /// `stop_recording` or with the call.
+    pub fn start_recording(&self, path: impl AsRef<Path>) -> Result<RecordingInfo> {
+        let (Some(capture), Some(playback)) = (&self.audio_capture, &self.audio_playback) else {
+            return Err(anyhow::anyhow!("No active call"));
+        };
+        let mut recording = self
+            .recording
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Lock error"))?;
+        if recording.is_some() {
+            return Err(anyhow::anyhow!("Already recording"));
+        }
+
+        let (recorder, info) = CallRecorder::start(path)?;
+        capture.set_recording(Some(recorder.sink()));
+        playback.set_recording(Some(recorder.sink()));
+        *recording = Some(recorder);
+        tracing::info!("Call recording started: {}", info.path.display());
+        Ok(info)
+    }
+
+    /// Decode the WAV file at `path` and play it to the other side through
+    /// the microphone path, mixed over the microphone or, with
+    /// `replace_mic`, instead of it. Returns the clip's length.
+    pub fn play_file_into_call(
+        &self,
+        path: impl AsRef<Path>,
+        replace_mic: bool,
+    ) -> Result<Duration> {
+        let Some(capture) = &self.audio_capture else {
+            return Err(anyhow::anyhow!("No active call"));
+        };
+        let clip = SoundClip::open(path)?;
+        let duration = clip.duration();
+        capture.play_clip(clip, replace_mic);
+        Ok(duration)
+    }
+
+    /// Stop a clip started with `play_file_into_call`
+    pub fn stop_file_into_call(&self) {
+        if let Some(capture) = &self.audio_capture {
+            capture.stop_clip();
+        }
+    }
+
+    /// Stop recording and finalize the file. `None` if nothing was being
+    /// recorded.
+    pub fn stop_recording(&self) -> Result<Option<RecordingSummary>> {
+        let taken = self.recording.lock().ok().and_then(|mut r| r.take());
+        let Some(recorder) = taken else {
+            return Ok(None);
+        };
+        if let Some(capture) = &self.audio_capture {
+            capture.set_recording(None);
+        }
+        if let Some(playback) = &self.audio_playback {
+            playback.set_recording(None);
+        }
+        let summary = recorder.finish()?;
+        if summary.dropped_blocks > 0 {
+            tracing::warn!(
+                "Call recording lost {} blocks to a slow disk",
+                summary.dropped_blocks
+            );
+        }
+        tracing::info!(
+            "Call recording stopped after {:.1}s: {}",
+            summary.duration_secs,
+            summary.path.display()
+        );
+        Ok(Some(summary))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.lock().is_ok_and(|r| r.is_some())
+    }
+
+    // === Group calls ===
+
+    /// Add `peer_id` to a group call: a peer connection of its own and a key
+    /// pair for it. Capture and playback are shared by every peer and created
+    /// with the first one. Returns our public key and the ICE candidates to
+    /// signal to that peer; `complete_peer_key_exchange` takes its key.
+    pub async fn add_peer(&mut self, peer_id: &str) -> Result<PeerSetup> {
+        if self.rtc_connection.is_some() {
+            return Err(anyhow::anyhow!("A 1:1 call is active"));
+        }
+        if self.peers.contains_key(peer_id) {
+            return Err(anyhow::anyhow!("Peer {} is already in the call", peer_id));
+        }
+
+        let audio = self.attach_group_audio()?;
+        let keypair = crypto::KeyPair::generate()?;
+        let public_key = keypair.public_key_base64();
+        let (pc, ice_candidates) = self.new_peer_connection().await?;
+
+        let state_peer = peer_id.to_string();
+        pc.on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
+            tracing::info!(
+                "Peer Connection State with {} has changed: {}",
+                state_peer,
+                s
+            );
+            Box::pin(async {})
+        }));
+
+        let crypto = watch::channel(None).0;
+        let link = PeerLink {
+            peer_id: peer_id.to_string(),
+            audio,
+            crypto: crypto.subscribe(),
+            playback_started: self.playback_started.clone(),
+            preferred_input_device: self.selected_input_device.clone(),
+            preferred_output_device: self.selected_output_device.clone(),
+            device_errors: self.device_error_tx.clone(),
+            send_controls: self.send_controls.clone(),
+            listen_only: self.listen_only.subscribe(),
+        };
+        // The peer that sent the offer created the channel
+        let link_for_channel = link.clone();
+        pc.on_data_channel(Box::new(move |d_channel: Arc<RTCDataChannel>| {
+            if d_channel.label() == "audio" {
+                link_for_channel.clone().attach(d_channel);
+            }
+            Box::pin(async {})
         }));
 
+        self.peers.insert(
+            peer_id.to_string(),
+            PeerSession {
+                pc,
+                keypair: Some(keypair),
+                crypto,
+                link,
+            },
+        );
+        tracing::info!("Peer {} added to the group call", peer_id);
+        Ok(PeerSetup {
+            public_key,
+            ice_candidates,
+        })
+    }
+
+    /// Derive the key shared with `peer_id` from its public key and seal our
+    /// sender key with it, sent out as a `SenderKeyUpdate`. Our audio goes
+    /// to that peer once the channel is open.
+    pub fn complete_peer_key_exchange(
+        &mut self,
+        peer_id: &str,
+        peer_public_key_base64: &str,
+    ) -> Result<()> {
+        let peer_key_bytes = crypto::parse_public_key(peer_public_key_base64)?;
+        let sender_key = self
+            .sender_key
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No group call audio"))?;
+        let session = self
+            .peers
+            .get_mut(peer_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown peer {}", peer_id))?;
+        let keypair = session
+            .keypair
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Key exchange with {} already done", peer_id))?;
+        let ctx = Arc::new(keypair.derive_shared_secret(&peer_key_bytes)?);
+
+        let sealed_key = sender_key.seal(&ctx)?;
+        session.crypto.send_replace(Some(ctx));
+        let _ = self.sender_key_tx.send(SenderKeyUpdate {
+            peer_id: peer_id.to_string(),
+            sealed_key,
+        });
+        tracing::info!("E2EE key exchange with {} completed", peer_id);
+        Ok(())
+    }
+
+    /// Install the sender key `peer_id` sealed for us, so its audio plays.
+    /// Called again when that peer rekeys.
+    pub fn set_peer_sender_key(&self, peer_id: &str, sealed_key: &str) -> Result<()> {
+        let session = self.peer_session(peer_id)?;
+        let pairwise = session
+            .crypto
+            .borrow()
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Key exchange with {} not done", peer_id))?;
+        let sender_key = crypto::SenderKey::open(sealed_key, &pairwise)?;
+        let ctx = sender_key.context()?;
+        session.link.audio.playback.add_peer(
+            peer_id,
+            Arc::new(ctx),
+            self.get_peer_volume(peer_id),
+        )?;
+        tracing::info!("Sender key from {} installed", peer_id);
+        Ok(())
+    }
+
+    /// Create the audio DataChannel to `peer_id` and an offer for it; the
+    /// peer answers with `accept_peer_offer`
+    pub async fn create_peer_offer(
+        &self,
+        peer_id: &str,
+        transform: Option<&SdpTransform>,
+    ) -> Result<String> {
+        let session = self.peer_session(peer_id)?;
+        let dc = session
+            .pc
+            .create_data_channel("audio", Some(audio_channel_options()))
+            .await?;
+        session.link.clone().attach(dc);
+        create_offer_on(&session.pc, None, transform).await
+    }
+
+    pub async fn accept_peer_offer(
+        &self,
+        peer_id: &str,
+        offer_sdp: &str,
+        transform: Option<&SdpTransform>,
+    ) -> Result<String> {
+        let session = self.peer_session(peer_id)?;
+        accept_offer_on(&session.pc, offer_sdp, transform).await
+    }
+
+    pub async fn set_peer_remote_description(&self, peer_id: &str, sdp: &str) -> Result<()> {
+        let session = self.peer_session(peer_id)?;
+        set_remote_description_on(&session.pc, sdp).await
+    }
+
+    pub async fn add_peer_ice_candidate(&self, peer_id: &str, candidate_json: &str) -> Result<()> {
+        let session = self.peer_session(peer_id)?;
+        add_ice_candidate_on(&session.pc, candidate_json).await
+    }
+
+    /// Drop `peer_id` from the group call. The streams keep running for the
+    /// other peers, or for the next one added, until `reset`.
+    pub async fn remove_peer(&mut self, peer_id: &str) -> Result<()> {
+        let session = self
+            .peers
+            .remove(peer_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown peer {}", peer_id))?;
+        session.link.audio.capture.remove_recipient(peer_id);
+        session.link.audio.playback.remove_peer(peer_id);
+        let _ = session.pc.close().await;
+        tracing::info!("Peer {} left the group call", peer_id);
+        self.rotate_sender_key()
+    }
+
+    /// Switch our audio to a fresh sender key and seal it for every peer
+    /// still in the call, so a peer that left cannot follow it. The others
+    /// drop our audio until the new key reaches them.
+    fn rotate_sender_key(&mut self) -> Result<()> {
+        let Some(capture) = &self.audio_capture else {
+            return Ok(());
+        };
+        let sender_key = crypto::SenderKey::generate()?;
+        capture.set_crypto(Arc::new(sender_key.context()?));
+        for (peer_id, session) in &self.peers {
+            let Some(pairwise) = session.crypto.borrow().clone() else {
+                // Gets the new key when its exchange completes
+                continue;
+            };
+            let sealed_key = sender_key.seal(&pairwise)?;
+            let _ = self.sender_key_tx.send(SenderKeyUpdate {
+                peer_id: peer_id.clone(),
+                sealed_key,
+            });
+        }
+        self.sender_key = Some(sender_key);
+        tracing::info!("Group call sender key rotated");
         Ok(())
     }
+
+    /// Short authentication string of the key exchange with one group call
+    /// peer, see `sas_code`
+    pub fn peer_sas_code(&self, peer_id: &str) -> Option<String> {
+        let session = self.peers.get(peer_id)?;
+        let pairwise = session.crypto.borrow().clone()?;
+        pairwise.sas_code()
+    }
+
+    /// Join group calls as a listener: peers' audio keeps playing, but ours
+    /// goes to nobody and capture is paused. Switching back starts sending to
+    /// every peer whose channel is open. Cleared by `reset`.
+    pub fn set_listen_only(&self, listen_only: bool) {
+        self.listen_only.send_replace(listen_only);
+        if listen_only {
+            if let Some(capture) = &self.audio_capture {
+                capture.pause();
+            }
+        }
+    }
+
+    pub fn is_listen_only(&self) -> bool {
+        *self.listen_only.borrow()
+    }
+
+    /// Peers in the current group call
+    pub fn group_peers(&self) -> Vec<String> {
+        self.peers.keys().cloned().collect()
+    }
+
+    fn peer_session(&self, peer_id: &str) -> Result<&PeerSession> {
+        self.peers
+            .get(peer_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown peer {}", peer_id))
+    }
+
+    /// Capture and playback shared by a group call, or the pre-warmed ones.
+    /// Capture is keyed with a new sender key; playback has no key of its
+    /// own, each peer's sender key is added as it arrives. Both stay paused
+    /// until a peer's channel opens.
+    fn attach_group_audio(&mut self) -> Result<CallAudio> {
+        if let (Some(capture), Some(playback)) = (&self.audio_capture, &self.audio_playback) {
+            return Ok(CallAudio {
+                capture: capture.clone(),
+                playback: playback.clone(),
+            });
+        }
+        self.stop_loopback_test();
+
+        let prewarmed = self.prewarmed_audio.lock().ok().and_then(|mut p| p.take());
+        let (capture, playback) = match prewarmed {
+            Some((capture, playback)) => {
+                playback.apply_config(&self.playback_config());
+                capture.apply_config(&self.capture_config());
+                self.playback_started.store(true, Ordering::SeqCst);
+                (capture, playback)
+            }
+            None => {
+                let playback = Arc::new(AudioPlayback::new_prewarmed(self.playback_config())?);
+                playback.set_raw_mode(self.playback_raw_mode);
+                let capture = Arc::new(AudioCapture::new_prewarmed(
+                    playback.echo_reference(),
+                    self.capture_config(),
+                )?);
+                (capture, playback)
+            }
+        };
+        let sender_key = crypto::SenderKey::generate()?;
+        capture.set_crypto(Arc::new(sender_key.context()?));
+        self.sender_key = Some(sender_key);
+        // Packets reach peers through `add_recipient`; nothing reads the
+        // 1:1 receiver, so drop it rather than let it queue
+        drop(capture.take_packet_receiver());
+        capture.set_jitter_sender(self.capture_jitter_tx.clone());
+        capture.set_device_event_sender(self.device_event_tx.clone());
+        playback.set_device_event_sender(self.device_event_tx.clone());
+        self.audio_playback = Some(playback.clone());
+        self.audio_capture = Some(capture.clone());
+        let audio = CallAudio { capture, playback };
+        self.call_audio.send_replace(Some(audio.clone()));
+        if self.device_follow_task.is_none() {
+            self.device_follow_task = Some(tokio::spawn(follow_default_devices(
+                self.call_audio.subscribe(),
+                self.device_follow.clone(),
+            )));
+        }
+        Ok(audio)
+    }
+}
+
+/// Create an offer on `pc` and apply it locally; see `MediaEngine::create_offer`
+async fn create_offer_on(
+    pc: &RTCPeerConnection,
+    options: Option<RTCOfferOptions>,
+    transform: Option<&SdpTransform>,
+) -> Result<String> {
+    let mut offer = pc.create_offer(options).await?;
+    if let Some(transform) = transform {
+        offer = RTCSessionDescription::offer(transform(offer.sdp))?;
+    }
+    pc.set_local_description(offer).await?;
+
+    // Send the SDP immediately and rely on trickle ICE via on_ice_candidate.
+    let local_desc = pc
+        .local_description()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Failed to get local description"))?;
+
+    Ok(serde_json::to_string(&local_desc)?)
+}
+
+/// Answer `offer_sdp` on `pc`; see `MediaEngine::accept_offer`
+async fn accept_offer_on(
+    pc: &RTCPeerConnection,
+    offer_sdp: &str,
+    transform: Option<&SdpTransform>,
+) -> Result<String> {
+    let offer = serde_json::from_str::<RTCSessionDescription>(offer_sdp)?;
+    pc.set_remote_description(offer).await?;
+
+    let mut answer = pc.create_answer(None).await?;
+    if let Some(transform) = transform {
+        answer = RTCSessionDescription::answer(transform(answer.sdp))?;
+    }
+    pc.set_local_description(answer).await?;
+
+    // Send the SDP immediately and rely on trickle ICE via on_ice_candidate.
+    let local_desc = pc
+        .local_description()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Failed to get local description"))?;
+
+    Ok(serde_json::to_string(&local_desc)?)
+}
+
+async fn set_remote_description_on(pc: &RTCPeerConnection, sdp: &str) -> Result<()> {
+    let remote_desc = serde_json::from_str::<RTCSessionDescription>(sdp)?;
+    pc.set_remote_description(remote_desc).await?;
+    Ok(())
+}
+
+async fn add_ice_candidate_on(pc: &RTCPeerConnection, candidate_json: &str) -> Result<()> {
+    let ice_candidate_init: RTCIceCandidateInit = serde_json::from_str(candidate_json)?;
+    pc.add_ice_candidate(ice_candidate_init).await?;
+    Ok(())
+}
+
+fn audio_channel_options() -> RTCDataChannelInit {
+    RTCDataChannelInit {
+        ordered: Some(false),
+        max_retransmits: Some(0), // Unreliable (UDP-like) for audio
+        ..Default::default()
+    }
+}
+
+/// Forward captured packets to the DataChannel. If nothing has gone out for
+/// the keepalive interval (muted capture, DTX, a stalled device), send an
+/// `AudioPacket::keepalive()` so the NAT binding does not expire. Packets
+/// are dropped while the channel holds more than the send buffer limit.
+async fn send_audio_packets(
+    mut rx: mpsc::UnboundedReceiver<AudioPacket>,
+    dc: Arc<RTCDataChannel>,
+    controls: Arc<SendControls>,
+    side: &'static str,
+) {
+    loop {
+        let interval = controls.keepalive_interval();
+        let packet = if interval == 0 {
+            rx.recv().await
+        } else {
+            tokio::time::timeout(Duration::from_secs(interval.into()), rx.recv())
+                .await
+                .unwrap_or_else(|_| Some(AudioPacket::keepalive()))
+        };
+        let Some(packet) = packet else {
+            break;
+        };
+
+        // Real-time audio: better skip this frame than queue it behind
+        // others that are already late
+        if !controls.admit(dc.buffered_amount().await) {
+            continue;
+        }
+
+        if let Ok(bytes) = bincode::serialize(&packet) {
+            if let Err(e) = dc.send(&bytes.into()).await {
+                tracing::warn!("Failed to send audio packet ({}): {}", side, e);
+            }
+        }
+    }
+}
+
+impl PeerLink {
+    /// Play what arrives on `dc`, and send our audio on it once it is open
+    /// and the peer's key is known
+    fn attach(self, dc: Arc<RTCDataChannel>) {
+        let playback = self.audio.playback.clone();
+        let peer_id = self.peer_id.clone();
+        dc.on_message(Box::new(move |msg: DataChannelMessage| {
+            let playback = playback.clone();
+            let peer_id = peer_id.clone();
+            Box::pin(async move {
+                if let Ok(packet) = bincode::deserialize::<AudioPacket>(&msg.data) {
+                    if let Err(e) = playback.process_peer_packet(&peer_id, packet) {
+                        tracing::warn!(
+                            "Failed to process incoming audio packet from {}: {}",
+                            peer_id,
+                            e
+                        );
+                    }
+                }
+            })
+        }));
+
+        let dc_for_open = dc.clone();
+        dc.on_open(Box::new(move || {
+            tracing::info!("DataChannel 'audio' opened with {}", self.peer_id);
+            // Waiting for the key here would hold up the channel's other
+            // callbacks
+            tokio::spawn(self.send_audio(dc_for_open));
+            Box::pin(async {})
+        }));
+    }
+
+    async fn send_audio(mut self, dc: Arc<RTCDataChannel>) {
+        // Our sender key goes out with the pairwise exchange; audio sent
+        // before it would be undecryptable
+        if self.crypto.wait_for(Option::is_some).await.is_err() {
+            return;
+        }
+        let CallAudio { capture, playback } = &self.audio;
+
+        // The first peer to connect starts the shared streams
+        if !self.playback_started.swap(true, Ordering::SeqCst) {
+            let started = start_stream_blocking({
+                let playback = playback.clone();
+                let device = self.preferred_output_device.clone();
+                move || playback.start_with_device(device.as_deref())
+            })
+            .await;
+            match started {
+                Ok(_) => tracing::info!("Playback stream started (Group)"),
+                Err(e) => {
+                    tracing::error!("Failed to start playback: {}", e);
+                    report_device_error(&self.device_errors, &e);
+                }
+            }
+        }
+        playback.resume();
+
+        // A listener only receives; our audio goes out while it may speak
+        loop {
+            if self
+                .listen_only
+                .wait_for(|listening| !listening)
+                .await
+                .is_err()
+            {
+                return;
+            }
+            let started = start_stream_blocking({
+                let capture = capture.clone();
+                let device = self.preferred_input_device.clone();
+                move || capture.start_with_device(device.as_deref())
+            })
+            .await;
+            if let Err(e) = started {
+                tracing::error!("Failed to start capture: {}", e);
+                report_device_error(&self.device_errors, &e);
+                return;
+            }
+            capture.resume();
+
+            let rx = capture.add_recipient(&self.peer_id);
+            tokio::select! {
+                _ = send_audio_packets(rx, dc.clone(), self.send_controls.clone(), "Group") => return,
+                listening = self.listen_only.wait_for(|listening| *listening) => {
+                    if listening.is_err() {
+                        return;
+                    }
+                    capture.remove_recipient(&self.peer_id);
+                }
+            }
+        }
+    }
+}
+
+/// Every `BITRATE_ADAPT_INTERVAL`, feed the call's loss rate to a
+/// `BitrateController` and retune the encoder. The peer does not report the
+/// loss it sees on our stream, so the loss on its stream to us stands in for
+/// it; most paths lose packets in both directions alike.
+async fn adapt_bitrate(mut call_audio: watch::Receiver<Option<CallAudio>>) {
+    let audio = match call_audio.wait_for(Option::is_some).await {
+        Ok(audio) => audio.clone(),
+        Err(_) => return,
+    };
+    let Some(CallAudio { capture, playback }) = audio else {
+        return;
+    };
+
+    let mut controller = BitrateController::new();
+    let mut previous = playback.stats();
+    let mut ticker = tokio::time::interval(BITRATE_ADAPT_INTERVAL);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let stats = playback.stats();
+        let lost = stats.packets_lost.saturating_sub(previous.packets_lost);
+        let received = stats
+            .packets_received
+            .saturating_sub(previous.packets_received);
+        previous = stats;
+        if lost + received == 0 {
+            // Nothing arrived, so there is nothing to judge the link by
+            continue;
+        }
+
+        let loss_percent = lost as f32 * 100.0 / (lost + received) as f32;
+        if let Some(bitrate) = controller.update(loss_percent) {
+            tracing::info!(
+                "Opus bitrate -> {} bps ({:.1}% loss over the last interval)",
+                bitrate,
+                loss_percent
+            );
+            if let Err(e) = capture.set_bitrate(bitrate) {
+                tracing::warn!("Failed to apply Opus bitrate: {}", e);
+            }
+        }
+    }
+}
+
+/// Peer connection configuration for `ice_servers`
+fn rtc_configuration(ice_servers: &[IceServerConfig]) -> RTCConfiguration {
+    let ice_servers = ice_servers
+        .iter()
+        .map(|cfg| {
+            let mut server = RTCIceServer {
+                urls: cfg.urls.clone(),
+                ..Default::default()
+            };
+            if let Some(username) = &cfg.username {
+                server.username = username.clone();
+            }
+            if let Some(credential) = &cfg.credential {
+                server.credential = credential.clone();
+            }
+            server
+        })
+        .collect::<Vec<_>>();
+
+    RTCConfiguration {
+        ice_servers,
+        ice_transport_policy: RTCIceTransportPolicy::All, // Allow both UDP and TCP
+        ..Default::default()
+    }
+}
+
+/// Every `CALL_QUALITY_INTERVAL`, classify the call's loss, jitter and RTT
+/// and send the quality whenever `QualityMonitor` settles on a new one. The
+/// sample's score and the current level go to `score_tx` every time.
+async fn monitor_call_quality(
+    mut call_audio: watch::Receiver<Option<CallAudio>>,
+    pc: Arc<RTCPeerConnection>,
+    quality_tx: mpsc::UnboundedSender<CallQuality>,
+    score_tx: watch::Sender<Option<CallQualityScore>>,
+) {
+    let audio = match call_audio.wait_for(Option::is_some).await {
+        Ok(audio) => audio.clone(),
+        Err(_) => return,
+    };
+    let Some(CallAudio { playback, .. }) = audio else {
+        return;
+    };
+
+    let mut monitor = QualityMonitor::new();
+    let _ = quality_tx.send(monitor.quality());
+    let mut ticker = tokio::time::interval(CALL_QUALITY_INTERVAL);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let rtt_ms = pc
+            .get_stats()
+            .await
+            .reports
+            .into_values()
+            .find_map(|report| match report {
+                StatsReportType::CandidatePair(pair)
+                    if pair.nominated && pair.current_round_trip_time > 0.0 =>
+                {
+                    Some(pair.current_round_trip_time * 1000.0)
+                }
+                _ => None,
+            });
+        let stats = playback.stats();
+        let sample = QualitySample {
+            loss_percent: playback.packet_loss_percent(),
+            jitter_ms: stats.jitter_ms,
+            rtt_ms,
+            underrun_percent: QualitySample::underrun_percent(
+                playback.underrun_count(),
+                stats.packets_received + stats.packets_lost,
+            ),
+        };
+        let changed = monitor.update(&sample);
+        score_tx.send_replace(Some(CallQualityScore {
+            score: sample.mos(),
+            category: monitor.quality(),
+        }));
+        if let Some(quality) = changed {
+            tracing::info!(
+                "Call quality -> {:?} ({:.1}% loss, {:.0} ms jitter, RTT {:?} ms)",
+                quality,
+                sample.loss_percent,
+                sample.jitter_ms,
+                sample.rtt_ms
+            );
+            let _ = quality_tx.send(quality);
+        }
+    }
+}
+
+/// Open a call stream off the async runtime: `start_with_device` waits up
+/// to `STREAM_START_TIMEOUT` for the audio thread to report back
+async fn start_stream_blocking<F>(start: F) -> Result<bool>
+where
+    F: FnOnce() -> Result<bool> + Send + 'static,
+{
+    tokio::task::spawn_blocking(start)
+        .await
+        .map_err(|e| anyhow::anyhow!("Stream start task failed: {}", e))?
+}
+
+/// Reopen capture on `device`, giving the old stream time to release it
+fn restart_capture(capture: &AudioCapture, device: Option<&str>) -> Result<bool> {
+    capture.stop();
+    std::thread::sleep(Duration::from_millis(120));
+    capture.start_with_device(device)
+}
+
+/// Reopen playback on `device`, giving the old stream time to release it
+fn restart_playback(playback: &AudioPlayback, device: Option<&str>) -> Result<bool> {
+    playback.stop();
+    std::thread::sleep(Duration::from_millis(120));
+    playback.start_with_device(device)
+}
+
+/// Every `DEVICE_FOLLOW_INTERVAL`, look up the system default devices and,
+/// once a new one has settled, reopen the call streams that follow the
+/// default on it. Streams that are not open are left alone; one whose
+/// device was lost is reopened on the new default.
+async fn follow_default_devices(
+    mut call_audio: watch::Receiver<Option<CallAudio>>,
+    follow: Arc<DeviceFollow>,
+) {
+    let audio = match call_audio.wait_for(Option::is_some).await {
+        Ok(audio) => audio.clone(),
+        Err(_) => return,
+    };
+    let Some(CallAudio { capture, playback }) = audio else {
+        return;
+    };
+
+    // Enumerating devices can block for a while on some hosts
+    let defaults = || {
+        (
+            MediaEngine::default_input_device_name().ok(),
+            MediaEngine::default_output_device_name().ok(),
+        )
+    };
+    let Ok((input, output)) = tokio::task::spawn_blocking(defaults).await else {
+        return;
+    };
+    let mut input_watch = DefaultDeviceWatch::new(input);
+    let mut output_watch = DefaultDeviceWatch::new(output);
+
+    let mut ticker = tokio::time::interval(DEVICE_FOLLOW_INTERVAL);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Ok((input, output)) = tokio::task::spawn_blocking(defaults).await else {
+            return;
+        };
+        // Keep watching while disabled so enabling it later does not treat
+        // an old change as new
+        let new_input = input_watch.observe(input);
+        let new_output = output_watch.observe(output);
+        if !follow.enabled.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        if let Some(name) = new_input {
+            if follow.input.load(Ordering::SeqCst)
+                && (capture.is_running() || capture.device_lost())
+            {
+                tracing::info!("Default input device changed to {:?}, following it", name);
+                let capture = capture.clone();
+                let restarted =
+                    tokio::task::spawn_blocking(move || restart_capture(&capture, None)).await;
+                if let Ok(Err(e)) = restarted {
+                    tracing::warn!("Failed to follow the default input device: {}", e);
+                }
+            }
+        }
+        if let Some(name) = new_output {
+            if follow.output.load(Ordering::SeqCst)
+                && (playback.is_running() || playback.device_lost())
+            {
+                tracing::info!("Default output device changed to {:?}, following it", name);
+                let playback = playback.clone();
+                let restarted =
+                    tokio::task::spawn_blocking(move || restart_playback(&playback, None)).await;
+                if let Ok(Err(e)) = restarted {
+                    tracing::warn!("Failed to follow the default output device: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Pass typed device failures on to the device error receiver; anything
+/// else has already been logged by the caller.
+fn report_device_error(
+    device_errors: &mpsc::UnboundedSender<AudioDeviceError>,
+    error: &anyhow::Error,
+) {
+    if let Some(device_error) = error.downcast_ref::<AudioDeviceError>() {
+        let _ = device_errors.send(device_error.clone());
+    }
 }
@@ -0,0 +1,287 @@
+//! Simulated lossy link for exercising the audio path without a real network.
+//!
+//! [`LossyLink`] sits between the capture output and
+//! `AudioPlayback::process_packet` and drops, duplicates, reorders or delays
+//! [`AudioPacket`]s. Time is counted in packets (one tick per 20ms frame), and
+//! the RNG is seeded, so a given seed always yields the same delivery order.
+//! Only built for tests or with the `netsim` feature.
+
+use crate::audio::AudioPacket;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tokio::sync::mpsc;
+
+/// Per-packet impairment probabilities (each in `0.0..=1.0`).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NetworkConditions {
+    pub loss: f64,
+    pub duplicate: f64,
+    /// Chance a packet swaps places with the one after it
+    pub reorder: f64,
+    pub delay: f64,
+    /// Upper bound for a delayed packet's hold time, in packets
+    pub max_delay_packets: u32,
+}
+
+impl NetworkConditions {
+    pub fn lossy(loss: f64) -> Self {
+        Self {
+            loss,
+            ..Self::default()
+        }
+    }
+}
+
+/// Counters for what the link did to the stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    pub sent: u64,
+    pub delivered: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub delayed: u64,
+}
+
+pub struct LossyLink {
+    conditions: NetworkConditions,
+    rng: StdRng,
+    tick: u64,
+    /// Packets held back, with the tick they are released on
+    held: Vec<(u64, AudioPacket)>,
+    stats: LinkStats,
+}
+
+impl LossyLink {
+    pub fn new(conditions: NetworkConditions, seed: u64) -> Self {
+        Self {
+            conditions,
+            rng: StdRng::seed_from_u64(seed),
+            tick: 0,
+            held: Vec::new(),
+            stats: LinkStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+
+    /// Feed one packet into the link and return whatever comes out on this
+    /// tick, in delivery order. Packets held back are released after the
+    /// current one, so a packet held for `n` ticks is overtaken by `n` others.
+    pub fn push(&mut self, packet: AudioPacket) -> Vec<AudioPacket> {
+        self.tick += 1;
+        self.stats.sent += 1;
+        let mut out = Vec::new();
+
+        if self.roll(self.conditions.loss) {
+            self.stats.dropped += 1;
+        } else {
+            let copies = if self.roll(self.conditions.duplicate) {
+                self.stats.duplicated += 1;
+                2
+            } else {
+                1
+            };
+
+            for _ in 0..copies {
+                if self.conditions.max_delay_packets > 0 && self.roll(self.conditions.delay) {
+                    let hold = self.rng.gen_range(1..=self.conditions.max_delay_packets) as u64;
+                    self.stats.delayed += 1;
+                    self.held.push((self.tick + hold, packet.clone()));
+                } else if self.roll(self.conditions.reorder) {
+                    // Held for one tick so the next packet overtakes it.
+                    self.stats.reordered += 1;
+                    self.held.push((self.tick + 1, packet.clone()));
+                } else {
+                    out.push(packet.clone());
+                }
+            }
+        }
+
+        let released = self.release(self.tick);
+        out.extend(released);
+        self.deliver(out)
+    }
+
+    /// Release every packet still held back.
+    pub fn flush(&mut self) -> Vec<AudioPacket> {
+        let out = self.release(u64::MAX);
+        self.deliver(out)
+    }
+
+    fn release(&mut self, up_to: u64) -> Vec<AudioPacket> {
+        let mut due: Vec<(u64, AudioPacket)> = Vec::new();
+        let mut i = 0;
+        while i < self.held.len() {
+            if self.held[i].0 <= up_to {
+                due.push(self.held.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        due.sort_by_key(|(release_at, _)| *release_at);
+        due.into_iter().map(|(_, packet)| packet).collect()
+    }
+
+    fn deliver(&mut self, out: Vec<AudioPacket>) -> Vec<AudioPacket> {
+        self.stats.delivered += out.len() as u64;
+        out
+    }
+
+    fn roll(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.gen_bool(probability.min(1.0))
+    }
+}
+
+/// Run a packet stream through a [`LossyLink`] on a background task, e.g.
+/// between `AudioCapture::take_packet_receiver` and the sender.
+pub fn pipe(
+    mut input: mpsc::UnboundedReceiver<AudioPacket>,
+    conditions: NetworkConditions,
+    seed: u64,
+) -> mpsc::UnboundedReceiver<AudioPacket> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut link = LossyLink::new(conditions, seed);
+        while let Some(packet) = input.recv().await {
+            for delivered in link.push(packet) {
+                if tx.send(delivered).is_err() {
+                    return;
+                }
+            }
+        }
+        for delivered in link.flush() {
+            let _ = tx.send(delivered);
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioPlayback, OpusEncoder, FRAME_SIZE};
+    use crate::crypto::KeyPair;
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+
+    fn packets(count: u32) -> Vec<AudioPacket> {
+        (0..count)
+            .map(|seq| AudioPacket {
+                seq,
+                data: vec![seq as u8],
+            })
+            .collect()
+    }
+
+    fn run(link: &mut LossyLink, input: Vec<AudioPacket>) -> Vec<u32> {
+        let mut seqs = Vec::new();
+        for packet in input {
+            seqs.extend(link.push(packet).into_iter().map(|p| p.seq));
+        }
+        seqs.extend(link.flush().into_iter().map(|p| p.seq));
+        seqs
+    }
+
+    #[test]
+    fn same_seed_gives_same_delivery() {
+        let conditions = NetworkConditions {
+            loss: 0.1,
+            duplicate: 0.05,
+            reorder: 0.1,
+            delay: 0.05,
+            max_delay_packets: 4,
+        };
+
+        let first = run(&mut LossyLink::new(conditions, 7), packets(500));
+        let second = run(&mut LossyLink::new(conditions, 7), packets(500));
+        let other_seed = run(&mut LossyLink::new(conditions, 8), packets(500));
+
+        assert_eq!(first, second);
+        assert_ne!(first, other_seed);
+    }
+
+    #[test]
+    fn clean_link_is_transparent() {
+        let mut link = LossyLink::new(NetworkConditions::default(), 1);
+        assert_eq!(run(&mut link, packets(50)), (0..50).collect::<Vec<_>>());
+        assert_eq!(link.stats().dropped, 0);
+    }
+
+    #[test]
+    fn impairments_show_up_in_delivery_and_stats() {
+        let mut link = LossyLink::new(
+            NetworkConditions {
+                duplicate: 0.2,
+                reorder: 0.2,
+                ..NetworkConditions::default()
+            },
+            42,
+        );
+        let seqs = run(&mut link, packets(200));
+        let stats = link.stats();
+
+        assert!(stats.duplicated > 0 && stats.reordered > 0);
+        assert_eq!(seqs.len() as u64, 200 + stats.duplicated);
+        assert_eq!(stats.delivered, seqs.len() as u64);
+        assert!(seqs.windows(2).any(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn five_percent_loss_keeps_playback_fed() {
+        let alice = KeyPair::generate().expect("alice keypair");
+        let bob = KeyPair::generate().expect("bob keypair");
+        let alice_pub = alice.public_key_bytes.clone();
+        let bob_pub = bob.public_key_bytes.clone();
+        let sender_ctx = alice.derive_shared_secret(&bob_pub).expect("sender ctx");
+        let receiver_ctx = Arc::new(bob.derive_shared_secret(&alice_pub).expect("receiver ctx"));
+
+        let mut encoder = OpusEncoder::new().expect("opus encoder");
+        let frame: Vec<i16> = (0..FRAME_SIZE)
+            .map(|i| (((i as f32 * 2.0 * PI * 4.0) / FRAME_SIZE as f32).sin() * 8000.0) as i16)
+            .collect();
+
+        let playback = AudioPlayback::new(receiver_ctx).expect("playback");
+        let mut link = LossyLink::new(NetworkConditions::lossy(0.05), 2024);
+        let frames = 200u32;
+        let mut out = vec![0.0f32; FRAME_SIZE];
+        // One packet in and one 20ms frame out per tick, as sender and
+        // output device would run
+        for seq in 0..frames {
+            let encoded = encoder.encode(&frame).expect("encode");
+            let packet = AudioPacket {
+                seq,
+                data: sender_ctx.encrypt(&encoded).expect("encrypt"),
+            };
+            for delivered in link.push(packet) {
+                let delivered_seq = delivered.seq;
+                playback
+                    .process_packet(delivered)
+                    .expect("delivered packets decode");
+                assert_eq!(playback.last_sequence(), Some(delivered_seq));
+            }
+            playback.pull_output(&mut out);
+        }
+
+        let stats = link.stats();
+        assert!(stats.dropped > 0, "seeded run should drop some packets");
+        assert!(stats.delivered >= u64::from(frames) * 9 / 10);
+
+        // Concealment covers the gaps, so playback never runs dry past the
+        // initial fill
+        assert!(
+            playback.underrun_count() <= 1,
+            "playback starved {} times",
+            playback.underrun_count()
+        );
+        let concealed = playback.stats().frames_concealed as f64 / f64::from(frames);
+        assert!(concealed > 0.0, "lost frames should be concealed");
+        assert!(
+            concealed < 0.1,
+            "{:.1}% of frames concealed",
+            concealed * 100.0
+        );
+    }
+}
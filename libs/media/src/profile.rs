@@ -0,0 +1,143 @@
+//! Runtime parameters derived from the audio mode.
+//!
+//! On speakers the microphone hears our own playback, so beyond echo
+//! cancellation the gate has to close harder on what leaks through and the
+//! output has to stay quiet enough not to start a feedback loop. Headphones
+//! need none of that. [`AudioProfile`] works out the whole set from
+//! [`AudioSettings`] in one place; anything the user pinned in
+//! [`ProfileOverrides`] wins over what the mode would pick.
+
+use crate::{AudioMode, AudioSettings};
+
+/// Lowest noise gate threshold used on speakers, unless overridden
+pub const SPEAKER_MIN_NOISE_GATE_THRESHOLD: f32 = 0.03;
+
+/// Output volume cap on speakers, unless overridden
+pub const SPEAKER_MAX_OUTPUT_VOLUME: f32 = 0.8;
+
+/// Output volume cap on headphones: the full range `output_volume` allows
+const HEADPHONE_MAX_OUTPUT_VOLUME: f32 = 2.0;
+
+/// Values the user set by hand, taking precedence over the mode's choice.
+/// `None` leaves the field to the profile.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProfileOverrides {
+    #[serde(default)]
+    pub aec: Option<bool>,
+    #[serde(default)]
+    pub noise_gate: Option<bool>,
+    #[serde(default)]
+    pub noise_gate_threshold: Option<f32>,
+    #[serde(default)]
+    pub max_output_volume: Option<f32>,
+}
+
+/// Capture and playback parameters in effect for the current settings
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AudioProfile {
+    pub mode: AudioMode,
+    pub aec: bool,
+    pub noise_gate: bool,
+    pub noise_gate_threshold: f32,
+    /// Ceiling on `output_volume`
+    pub max_output_volume: f32,
+}
+
+impl AudioProfile {
+    /// Profile for `settings.audio_mode` with the user's overrides applied.
+    /// `minimal_processing` is not folded in; it still switches the
+    /// processing stages off on top of this.
+    pub fn for_settings(settings: &AudioSettings) -> Self {
+        let mut profile = match settings.audio_mode {
+            AudioMode::Headphones => Self {
+                mode: AudioMode::Headphones,
+                aec: false,
+                noise_gate: settings.noise_gate,
+                noise_gate_threshold: settings.noise_gate_threshold,
+                max_output_volume: HEADPHONE_MAX_OUTPUT_VOLUME,
+            },
+            AudioMode::Speakers => Self {
+                mode: AudioMode::Speakers,
+                aec: settings.aec,
+                noise_gate: true,
+                noise_gate_threshold: settings
+                    .noise_gate_threshold
+                    .max(SPEAKER_MIN_NOISE_GATE_THRESHOLD),
+                max_output_volume: SPEAKER_MAX_OUTPUT_VOLUME,
+            },
+        };
+
+        let overrides = &settings.profile_overrides;
+        if let Some(aec) = overrides.aec {
+            profile.aec = aec;
+        }
+        if let Some(noise_gate) = overrides.noise_gate {
+            profile.noise_gate = noise_gate;
+        }
+        if let Some(threshold) = overrides.noise_gate_threshold {
+            profile.noise_gate_threshold = threshold;
+        }
+        if let Some(volume) = overrides.max_output_volume {
+            profile.max_output_volume = volume;
+        }
+        profile
+    }
+
+    /// `volume` held under this profile's ceiling
+    pub fn output_volume(&self, volume: f32) -> f32 {
+        volume.min(self.max_output_volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: AudioMode) -> AudioSettings {
+        AudioSettings {
+            audio_mode: mode,
+            output_volume: 1.5,
+            noise_gate: false,
+            noise_gate_threshold: 0.01,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn headphones_leave_gate_and_volume_alone() {
+        let profile = AudioProfile::for_settings(&settings(AudioMode::Headphones));
+        assert!(!profile.aec);
+        assert!(!profile.noise_gate);
+        assert_eq!(profile.noise_gate_threshold, 0.01);
+        assert_eq!(profile.output_volume(1.5), 1.5);
+    }
+
+    #[test]
+    fn speakers_tighten_gate_and_cap_volume() {
+        let profile = AudioProfile::for_settings(&settings(AudioMode::Speakers));
+        assert!(profile.aec);
+        assert!(profile.noise_gate);
+        assert_eq!(
+            profile.noise_gate_threshold,
+            SPEAKER_MIN_NOISE_GATE_THRESHOLD
+        );
+        assert_eq!(profile.output_volume(1.5), SPEAKER_MAX_OUTPUT_VOLUME);
+        assert_eq!(profile.output_volume(0.5), 0.5);
+    }
+
+    #[test]
+    fn overrides_win_over_the_mode() {
+        let mut settings = settings(AudioMode::Speakers);
+        settings.profile_overrides = ProfileOverrides {
+            aec: Some(false),
+            noise_gate: Some(false),
+            noise_gate_threshold: Some(0.005),
+            max_output_volume: Some(2.0),
+        };
+        let profile = AudioProfile::for_settings(&settings);
+        assert!(!profile.aec);
+        assert!(!profile.noise_gate);
+        assert_eq!(profile.noise_gate_threshold, 0.005);
+        assert_eq!(profile.output_volume(1.5), 1.5);
+    }
+}
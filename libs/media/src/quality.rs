@@ -0,0 +1,309 @@
+//! Call quality as a traffic light.
+//!
+//! Every `CALL_QUALITY_INTERVAL` the call's loss, jitter and round trip
+//! time are each mapped to a level, and the worst of them is the level of
+//! that sample. [`QualityMonitor`] only moves to a new level once samples
+//! have pointed there several times in a row: a single lossy interval does
+//! not flash "poor", sustained loss does, and recovering takes a little
+//! longer than degrading so the indicator does not bounce.
+//!
+//! [`QualitySample::mos`] gives the same measures, plus playback underruns,
+//! as a single number on the MOS scale. Each sample's score goes out with
+//! the current level as a [`CallQualityScore`].
+
+use std::time::Duration;
+
+/// How often call quality is sampled
+pub const CALL_QUALITY_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Upper bounds of `Good`, `Fair` and `Poor` for each measure; anything
+/// above the last is `Critical`
+pub const QUALITY_LOSS_PERCENT: [f32; 3] = [2.0, 5.0, 15.0];
+pub const QUALITY_JITTER_MS: [f64; 3] = [30.0, 60.0, 120.0];
+pub const QUALITY_RTT_MS: [f64; 3] = [200.0, 400.0, 800.0];
+
+/// Samples in a row a worse level needs before it is reported
+pub const QUALITY_DEGRADE_AFTER: u32 = 2;
+/// Samples in a row a better level needs before it is reported
+pub const QUALITY_RECOVER_AFTER: u32 = 3;
+
+/// One-way delay the MOS score adds on top of the network: a 20 ms Opus
+/// frame plus the jitter buffer's base depth
+pub const MOS_BASE_DELAY_MS: f64 = 40.0;
+/// How well Opus with FEC and PLC hides loss (`Bpl` in ITU-T G.107); the
+/// higher, the less each lost percent costs
+pub const MOS_LOSS_ROBUSTNESS: f64 = 25.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallQuality {
+    Good,
+    Fair,
+    Poor,
+    Critical,
+}
+
+/// The call's quality as one number and a level, refreshed every
+/// `CALL_QUALITY_INTERVAL`
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct CallQualityScore {
+    /// [`QualitySample::mos`] of the latest sample, from 1.0 to 4.5
+    pub score: f64,
+    /// The level [`QualityMonitor`] holds after that sample, the same one
+    /// the quality change events report
+    pub category: CallQuality,
+}
+
+/// One measurement of the call
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QualitySample {
+    /// Recent loss on the incoming stream, in percent
+    pub loss_percent: f32,
+    pub jitter_ms: f64,
+    /// `None` until ICE has measured one; does not count against quality
+    pub rtt_ms: Option<f64>,
+    /// Output callbacks that found the jitter buffer empty, as a percent of
+    /// packets expected; only the MOS score uses it
+    pub underrun_percent: f32,
+}
+
+impl QualitySample {
+    /// `underruns` as a percent of the `expected` packets (received plus
+    /// lost); 0 before any packet is expected
+    pub fn underrun_percent(underruns: u64, expected: u64) -> f32 {
+        if expected == 0 {
+            0.0
+        } else {
+            (underruns as f64 * 100.0 / expected as f64) as f32
+        }
+    }
+
+    /// Mean opinion score estimate, from 1.0 (unusable) to 4.5 (best).
+    ///
+    /// A simplified ITU-T G.107 E-model:
+    ///
+    /// ```text
+    /// d   = rtt / 2 + 2 * jitter + MOS_BASE_DELAY_MS         one-way delay, ms
+    /// Id  = 0.024 * d + 0.11 * (d - 177.3) if d > 177.3      delay impairment
+    /// p   = min(loss% + underrun%, 100)
+    /// Ie  = 95 * p / (p + MOS_LOSS_ROBUSTNESS)               loss impairment
+    /// R   = 93.2 - Id - Ie
+    /// MOS = 1 + 0.035 * R + 7e-6 * R * (R - 60) * (100 - R)  clamped to 1..=4.5
+    /// ```
+    ///
+    /// An RTT that ICE has not measured yet counts as zero. A clean call
+    /// scores about 4.4.
+    pub fn mos(&self) -> f64 {
+        let finite = |value: f64| {
+            if value.is_finite() {
+                value.max(0.0)
+            } else {
+                0.0
+            }
+        };
+
+        let delay = finite(self.rtt_ms.unwrap_or(0.0)) / 2.0
+            + 2.0 * finite(self.jitter_ms)
+            + MOS_BASE_DELAY_MS;
+        let mut delay_impairment = 0.024 * delay;
+        if delay > 177.3 {
+            delay_impairment += 0.11 * (delay - 177.3);
+        }
+
+        let lost = (finite(f64::from(self.loss_percent))
+            + finite(f64::from(self.underrun_percent)))
+        .min(100.0);
+        let loss_impairment = 95.0 * lost / (lost + MOS_LOSS_ROBUSTNESS);
+
+        let r = 93.2 - delay_impairment - loss_impairment;
+        if r <= 0.0 {
+            return 1.0;
+        }
+        (1.0 + 0.035 * r + 7e-6 * r * (r - 60.0) * (100.0 - r)).clamp(1.0, 4.5)
+    }
+}
+
+impl CallQuality {
+    const LEVELS: [CallQuality; 4] = [
+        CallQuality::Good,
+        CallQuality::Fair,
+        CallQuality::Poor,
+        CallQuality::Critical,
+    ];
+
+    /// Level of a single sample: the worst of its measures
+    pub fn classify(sample: &QualitySample) -> Self {
+        let level = |value: f64, bounds: [f64; 3]| {
+            let index = bounds
+                .iter()
+                .position(|&bound| value < bound)
+                .unwrap_or(bounds.len());
+            Self::LEVELS[index]
+        };
+        let loss = if sample.loss_percent.is_finite() {
+            f64::from(sample.loss_percent)
+        } else {
+            0.0
+        };
+        let loss_bounds = QUALITY_LOSS_PERCENT.map(f64::from);
+
+        level(loss, loss_bounds)
+            .max(level(sample.jitter_ms, QUALITY_JITTER_MS))
+            .max(
+                sample
+                    .rtt_ms
+                    .map_or(CallQuality::Good, |rtt| level(rtt, QUALITY_RTT_MS)),
+            )
+    }
+}
+
+/// Turns samples into a call quality with hysteresis
+#[derive(Debug, Clone)]
+pub struct QualityMonitor {
+    quality: CallQuality,
+    /// Samples in a row on the same side of `quality`, and the level
+    /// closest to it among them
+    pending: Option<(CallQuality, u32)>,
+}
+
+impl Default for QualityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QualityMonitor {
+    pub fn new() -> Self {
+        Self {
+            quality: CallQuality::Good,
+            pending: None,
+        }
+    }
+
+    pub fn quality(&self) -> CallQuality {
+        self.quality
+    }
+
+    /// Feed the latest sample. Returns the new quality when it changed.
+    pub fn update(&mut self, sample: &QualitySample) -> Option<CallQuality> {
+        let level = CallQuality::classify(sample);
+        if level == self.quality {
+            self.pending = None;
+            return None;
+        }
+
+        let worse = level > self.quality;
+        // A run that changes direction starts over. Within a run, the
+        // level reported is the mildest one it held throughout.
+        let (target, count) = match self.pending {
+            Some((target, count)) if (target > self.quality) == worse => {
+                let target = if worse {
+                    target.min(level)
+                } else {
+                    target.max(level)
+                };
+                (target, count + 1)
+            }
+            _ => (level, 1),
+        };
+
+        let needed = if worse {
+            QUALITY_DEGRADE_AFTER
+        } else {
+            QUALITY_RECOVER_AFTER
+        };
+        if count >= needed {
+            self.quality = target;
+            self.pending = None;
+            Some(target)
+        } else {
+            self.pending = Some((target, count));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loss(percent: f32) -> QualitySample {
+        QualitySample {
+            loss_percent: percent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn worst_measure_sets_the_level() {
+        assert_eq!(CallQuality::classify(&loss(0.0)), CallQuality::Good);
+        assert_eq!(CallQuality::classify(&loss(3.0)), CallQuality::Fair);
+        let sample = QualitySample {
+            loss_percent: 1.0,
+            jitter_ms: 10.0,
+            rtt_ms: Some(900.0),
+            ..Default::default()
+        };
+        assert_eq!(CallQuality::classify(&sample), CallQuality::Critical);
+        assert_eq!(CallQuality::classify(&loss(f32::NAN)), CallQuality::Good);
+    }
+
+    #[test]
+    fn a_single_lossy_sample_is_ignored() {
+        let mut monitor = QualityMonitor::new();
+        assert_eq!(monitor.update(&loss(30.0)), None);
+        assert_eq!(monitor.update(&loss(0.0)), None);
+        assert_eq!(monitor.update(&loss(30.0)), None);
+        assert_eq!(monitor.quality(), CallQuality::Good);
+    }
+
+    #[test]
+    fn sustained_loss_degrades_and_recovery_takes_longer() {
+        let mut monitor = QualityMonitor::new();
+        assert_eq!(monitor.update(&loss(30.0)), None);
+        // Critical then poor: the run as a whole was at least poor
+        assert_eq!(monitor.update(&loss(10.0)), Some(CallQuality::Poor));
+
+        assert_eq!(monitor.update(&loss(0.0)), None);
+        assert_eq!(monitor.update(&loss(0.0)), None);
+        assert_eq!(monitor.update(&loss(0.0)), Some(CallQuality::Good));
+    }
+
+    #[test]
+    fn mos_falls_as_each_measure_gets_worse() {
+        let clean = QualitySample::default().mos();
+        assert!((4.3..=4.5).contains(&clean), "clean call scored {clean}");
+
+        let worse = [
+            loss(5.0),
+            QualitySample {
+                jitter_ms: 80.0,
+                ..Default::default()
+            },
+            QualitySample {
+                rtt_ms: Some(600.0),
+                ..Default::default()
+            },
+            QualitySample {
+                underrun_percent: 5.0,
+                ..Default::default()
+            },
+        ];
+        for sample in worse {
+            assert!(sample.mos() < clean, "{sample:?} scored {}", sample.mos());
+        }
+
+        assert!(loss(20.0).mos() < loss(5.0).mos());
+        assert!(loss(5.0).mos() > 3.5);
+        assert!(loss(30.0).mos() < 3.0);
+        assert!(loss(100.0).mos() < 1.5);
+        assert_eq!(loss(f32::NAN).mos(), clean);
+    }
+
+    #[test]
+    fn underruns_count_against_the_packets_expected() {
+        assert_eq!(QualitySample::underrun_percent(0, 0), 0.0);
+        assert_eq!(QualitySample::underrun_percent(5, 0), 0.0);
+        assert_eq!(QualitySample::underrun_percent(5, 100), 5.0);
+    }
+}
@@ -0,0 +1,453 @@
+//! Local call recording.
+//!
+//! Taps the microphone as sent and the mixed playback into a stereo WAV:
+//! local voice on the left, remote audio on the right. The audio callbacks
+//! only hand blocks to a bounded channel; a writer thread interleaves them
+//! and writes the file, so a slow disk drops recorded audio rather than
+//! stalling the call.
+//!
+//! A call recording is as sensitive as the call, so the WAV is never on disk
+//! in the clear. It is sealed with AES-256-GCM under a random key made for
+//! that recording and handed back to the caller, who has to keep it:
+//! without the key the file cannot be opened. [`read_recording`] turns the
+//! file back into WAV bytes.
+//!
+//! File layout: [`RECORDING_MAGIC`], then records of a little-endian u32
+//! ciphertext length, a 12-byte nonce and the ciphertext with its tag. The
+//! first record is the 44-byte WAV header; it is sealed again with the final
+//! sizes when the recording stops, in place, so its record keeps its size.
+
+use crate::audio::SAMPLE_RATE;
+use anyhow::{Context as _, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// First bytes of a recording file
+pub const RECORDING_MAGIC: &[u8; 8] = b"P2PREC1\0";
+
+/// Blocks waiting for the writer thread, about 2.5 s of audio per side at
+/// 10 ms callbacks. Past that, blocks are dropped.
+const RECORDING_QUEUE_BLOCKS: usize = 512;
+/// How far one side may run ahead before the other is taken to be silent
+/// (capture paused, nothing playing) and padded to keep them aligned
+const MAX_SIDE_SKEW_SAMPLES: usize = SAMPLE_RATE as usize;
+/// Interleaved PCM sealed per record
+const RECORD_CHUNK_BYTES: usize = 64 * 1024;
+const WAV_HEADER_LEN: usize = 44;
+const RECORDING_CHANNELS: u16 = 2;
+const BYTES_PER_FRAME: u32 = 2 * RECORDING_CHANNELS as u32;
+
+/// Which channel of the recording a block goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecordingSide {
+    /// Our microphone, as sent
+    Local,
+    /// What playback played
+    Remote,
+}
+
+enum RecorderMessage {
+    Samples(RecordingSide, Vec<i16>),
+    Finish,
+}
+
+/// Handle the audio callbacks push recorded blocks through
+#[derive(Debug, Clone)]
+pub(crate) struct RecordingSink {
+    tx: SyncSender<RecorderMessage>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl RecordingSink {
+    fn push(&self, side: RecordingSide, samples: Vec<i16>) {
+        match self.tx.try_send(RecorderMessage::Samples(side, samples)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+/// Where capture or playback sends recorded audio, if anywhere. Checked from
+/// the audio callbacks, which skip the block rather than wait on the lock.
+#[derive(Debug, Default)]
+pub(crate) struct RecordingTap(Mutex<Option<RecordingSink>>);
+
+impl RecordingTap {
+    pub(crate) fn set(&self, sink: Option<RecordingSink>) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = sink;
+        }
+    }
+
+    /// Hand the block from `samples` to the recorder. `samples` only runs
+    /// while recording.
+    pub(crate) fn push_with(&self, side: RecordingSide, samples: impl FnOnce() -> Vec<i16>) {
+        if let Ok(slot) = self.0.try_lock() {
+            if let Some(sink) = slot.as_ref() {
+                sink.push(side, samples());
+            }
+        }
+    }
+}
+
+/// Returned when a recording starts
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecordingInfo {
+    pub path: PathBuf,
+    /// Base64 AES-256 key the file is sealed with. Not stored anywhere else.
+    pub key: String,
+}
+
+/// Returned when a recording stops
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub duration_secs: f64,
+    /// Blocks lost because the writer fell behind
+    pub dropped_blocks: u64,
+}
+
+/// A recording in progress: the writer thread and the sink feeding it
+pub struct CallRecorder {
+    sink: RecordingSink,
+    path: PathBuf,
+    writer: Option<JoinHandle<Result<u64>>>,
+}
+
+impl CallRecorder {
+    /// Create `path` and start the writer thread. Fails if the file exists.
+    pub fn start(path: impl AsRef<Path>) -> Result<(Self, RecordingInfo)> {
+        let path = path.as_ref().to_path_buf();
+        let mut key_bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut key_bytes)
+            .map_err(|_| anyhow::anyhow!("Failed to generate recording key"))?;
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+        let writer = SealedWavWriter::new(file, &key_bytes)?;
+
+        let (tx, rx) = mpsc::sync_channel(RECORDING_QUEUE_BLOCKS);
+        let handle = std::thread::Builder::new()
+            .name("call-recorder".to_string())
+            .spawn(move || run_writer(writer, rx))
+            .context("Failed to start recording writer")?;
+
+        let info = RecordingInfo {
+            path: path.clone(),
+            key: BASE64.encode(key_bytes),
+        };
+        Ok((
+            Self {
+                sink: RecordingSink {
+                    tx,
+                    dropped: Arc::new(AtomicU64::new(0)),
+                },
+                path,
+                writer: Some(handle),
+            },
+            info,
+        ))
+    }
+
+    pub(crate) fn sink(&self) -> RecordingSink {
+        self.sink.clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write out what is queued, seal the final header and close the file
+    pub fn finish(mut self) -> Result<RecordingSummary> {
+        // Blocking is fine here: this is not an audio callback
+        let _ = self.sink.tx.send(RecorderMessage::Finish);
+        let frames = self
+            .writer
+            .take()
+            .context("Recording already finished")?
+            .join()
+            .map_err(|_| anyhow::anyhow!("Recording writer panicked"))??;
+        Ok(RecordingSummary {
+            path: self.path.clone(),
+            duration_secs: frames as f64 / SAMPLE_RATE as f64,
+            dropped_blocks: self.sink.dropped.load(Ordering::Relaxed),
+        })
+    }
+}
+
+impl Drop for CallRecorder {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            let _ = self.sink.tx.send(RecorderMessage::Finish);
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Writer thread: pair up the two sides and write them interleaved. Returns
+/// the frames written.
+fn run_writer(mut writer: SealedWavWriter, rx: Receiver<RecorderMessage>) -> Result<u64> {
+    let mut local = VecDeque::new();
+    let mut remote = VecDeque::new();
+    // A closed channel finishes like an explicit `Finish`
+    while let Ok(RecorderMessage::Samples(side, samples)) = rx.recv() {
+        match side {
+            RecordingSide::Local => local.extend(samples),
+            RecordingSide::Remote => remote.extend(samples),
+        }
+        pad_stalled_side(&mut local, &mut remote);
+        writer.write_frames(&mut local, &mut remote)?;
+    }
+    // Whatever one side got ahead by goes out against silence
+    let tail = local.len().max(remote.len());
+    local.resize(tail, 0);
+    remote.resize(tail, 0);
+    writer.write_frames(&mut local, &mut remote)?;
+    writer.finish()
+}
+
+/// Pad the side that fell `MAX_SIDE_SKEW_SAMPLES` behind with silence
+fn pad_stalled_side(local: &mut VecDeque<i16>, remote: &mut VecDeque<i16>) {
+    if local.len() > remote.len() + MAX_SIDE_SKEW_SAMPLES {
+        remote.resize(local.len(), 0);
+    } else if remote.len() > local.len() + MAX_SIDE_SKEW_SAMPLES {
+        local.resize(remote.len(), 0);
+    }
+}
+
+struct SealedWavWriter {
+    file: BufWriter<File>,
+    key: LessSafeKey,
+    nonce_counter: u64,
+    /// Interleaved PCM not yet sealed
+    pending: Vec<u8>,
+    frames: u64,
+}
+
+impl SealedWavWriter {
+    fn new(file: File, key: &[u8; 32]) -> Result<Self> {
+        let key = UnboundKey::new(&aead::AES_256_GCM, key)
+            .map_err(|_| anyhow::anyhow!("Invalid recording key"))?;
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            key: LessSafeKey::new(key),
+            nonce_counter: 0,
+            pending: Vec::with_capacity(RECORD_CHUNK_BYTES),
+            frames: 0,
+        };
+        writer.file.write_all(RECORDING_MAGIC)?;
+        // Sizes unknown yet; rewritten by `finish`
+        writer.write_record(&wav_header(0))?;
+        Ok(writer)
+    }
+
+    /// Interleave and write the frames both sides have
+    fn write_frames(
+        &mut self,
+        local: &mut VecDeque<i16>,
+        remote: &mut VecDeque<i16>,
+    ) -> Result<()> {
+        let frames = local.len().min(remote.len());
+        for (left, right) in local.drain(..frames).zip(remote.drain(..frames)) {
+            self.pending.extend_from_slice(&left.to_le_bytes());
+            self.pending.extend_from_slice(&right.to_le_bytes());
+            if self.pending.len() >= RECORD_CHUNK_BYTES {
+                self.flush_pending()?;
+            }
+        }
+        self.frames += frames as u64;
+        Ok(())
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::take(&mut self.pending);
+        self.write_record(&chunk)?;
+        self.pending = chunk;
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn write_record(&mut self, plaintext: &[u8]) -> Result<()> {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[4..].copy_from_slice(&self.nonce_counter.to_be_bytes());
+        self.nonce_counter += 1;
+        let mut sealed = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to seal recording chunk"))?;
+        self.file.write_all(&(sealed.len() as u32).to_le_bytes())?;
+        self.file.write_all(&nonce)?;
+        self.file.write_all(&sealed)?;
+        Ok(())
+    }
+
+    /// Seal the rest, reseal the header with the final sizes and sync
+    fn finish(mut self) -> Result<u64> {
+        self.flush_pending()?;
+        self.file.flush()?;
+        self.file
+            .seek(SeekFrom::Start(RECORDING_MAGIC.len() as u64))?;
+        // A fresh nonce: the header's first one sealed different sizes
+        self.write_record(&wav_header(self.frames))?;
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+        Ok(self.frames)
+    }
+}
+
+/// 16-bit stereo PCM WAV header for `frames` frames. Sizes past 4 GiB,
+/// about 6 hours, saturate.
+fn wav_header(frames: u64) -> [u8; WAV_HEADER_LEN] {
+    let data_len = u32::try_from(frames * u64::from(BYTES_PER_FRAME)).unwrap_or(u32::MAX - 36);
+    let mut header = [0u8; WAV_HEADER_LEN];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&(36 + data_len).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&RECORDING_CHANNELS.to_le_bytes());
+    header[24..28].copy_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header[28..32].copy_from_slice(&(SAMPLE_RATE * BYTES_PER_FRAME).to_le_bytes());
+    header[32..34].copy_from_slice(&(BYTES_PER_FRAME as u16).to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// Decrypt a recording into WAV bytes with the base64 key from
+/// [`RecordingInfo`]
+pub fn read_recording(path: impl AsRef<Path>, key: &str) -> Result<Vec<u8>> {
+    let key_bytes = BASE64
+        .decode(key)
+        .map_err(|_| anyhow::anyhow!("Recording key is not valid base64"))?;
+    let key = UnboundKey::new(&aead::AES_256_GCM, &key_bytes)
+        .map_err(|_| anyhow::anyhow!("Recording key must be 32 bytes"))?;
+    let key = LessSafeKey::new(key);
+
+    let mut contents = Vec::new();
+    File::open(path.as_ref())
+        .with_context(|| format!("Failed to open recording {}", path.as_ref().display()))?
+        .read_to_end(&mut contents)?;
+    let mut rest = contents
+        .strip_prefix(RECORDING_MAGIC.as_slice())
+        .context("Not a call recording")?;
+
+    let mut wav = Vec::new();
+    while !rest.is_empty() {
+        if rest.len() < 4 + NONCE_LEN {
+            anyhow::bail!("Recording is truncated");
+        }
+        let len = u32::from_le_bytes(rest[..4].try_into().expect("4 bytes")) as usize;
+        let nonce: [u8; NONCE_LEN] = rest[4..4 + NONCE_LEN].try_into().expect("nonce length");
+        let body = &rest[4 + NONCE_LEN..];
+        if body.len() < len {
+            anyhow::bail!("Recording is truncated");
+        }
+        let mut sealed = body[..len].to_vec();
+        let plaintext = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("Wrong key, or the recording was modified"))?;
+        wav.extend_from_slice(plaintext);
+        rest = &body[len..];
+    }
+    Ok(wav)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("p2p-chat-{}-{}.p2prec", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn pcm(wav: &[u8]) -> Vec<(i16, i16)> {
+        wav[WAV_HEADER_LEN..]
+            .chunks_exact(4)
+            .map(|frame| {
+                (
+                    i16::from_le_bytes([frame[0], frame[1]]),
+                    i16::from_le_bytes([frame[2], frame[3]]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recording_round_trips_with_its_key_only() {
+        let path = temp_path("round-trip");
+        let (recorder, info) = CallRecorder::start(&path).expect("start");
+        let sink = recorder.sink();
+        sink.push(RecordingSide::Local, vec![1, 2, 3]);
+        sink.push(RecordingSide::Remote, vec![-1, -2]);
+        sink.push(RecordingSide::Remote, vec![-3, -4]);
+        let summary = recorder.finish().expect("finish");
+        assert_eq!(summary.dropped_blocks, 0);
+
+        let on_disk = std::fs::read(&path).expect("read");
+        assert!(on_disk.starts_with(RECORDING_MAGIC));
+        assert!(!on_disk.windows(4).any(|w| w == b"RIFF"));
+
+        let wav = read_recording(&path, &info.key).expect("decrypt");
+        assert_eq!(&wav[0..4], b"RIFF");
+        let data_len = u32::from_le_bytes(wav[40..44].try_into().unwrap());
+        assert_eq!(data_len as usize, wav.len() - WAV_HEADER_LEN);
+        // The local side ran short and was padded at the end
+        assert_eq!(pcm(&wav), vec![(1, -1), (2, -2), (3, -3), (0, -4)]);
+        assert!((summary.duration_secs - 4.0 / SAMPLE_RATE as f64).abs() < 1e-9);
+
+        let other_key = BASE64.encode([7u8; 32]);
+        assert!(read_recording(&path, &other_key).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn a_silent_side_is_padded_instead_of_holding_audio_back() {
+        let mut local: VecDeque<i16> = VecDeque::from(vec![5; MAX_SIDE_SKEW_SAMPLES + 10]);
+        let mut remote = VecDeque::from(vec![9; 4]);
+        pad_stalled_side(&mut local, &mut remote);
+        assert_eq!(remote.len(), local.len());
+        assert_eq!(remote.iter().take(4).copied().collect::<Vec<_>>(), [9; 4]);
+        assert!(remote.iter().skip(4).all(|&s| s == 0));
+    }
+
+    #[test]
+    fn start_refuses_to_overwrite() {
+        let path = temp_path("exists");
+        std::fs::write(&path, b"keep me").expect("write");
+        assert!(CallRecorder::start(&path).is_err());
+        assert_eq!(std::fs::read(&path).expect("read"), b"keep me");
+        let _ = std::fs::remove_file(&path);
+    }
+}
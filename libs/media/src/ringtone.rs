@@ -0,0 +1,480 @@
+//! Incoming-call ringtone and outgoing-call ringback playback.
+//!
+//! Both get their own output stream instead of going through
+//! `AudioPlayback`, so the ringtone can ring on a different device than call
+//! audio (speakers vs headset) and neither touches the call's sample queue.
+
+use crate::audio::{
+    choose_supported_format, classify_build_error, classify_play_error, device_candidates,
+    wait_for_stream_start, AudioDeviceError, AudioDirection, CHANNELS, SAMPLE_RATE,
+};
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Ringback cadence the caller hears while the other side rings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RingbackRegion {
+    /// 440+480 Hz, 2 s on, 4 s off
+    #[default]
+    Us,
+    /// 400+450 Hz, 0.4 s on, 0.2 s off, 0.4 s on, 2 s off
+    Uk,
+}
+
+impl FromStr for RingbackRegion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "us" => Ok(Self::Us),
+            "uk" | "gb" => Ok(Self::Uk),
+            other => Err(anyhow::anyhow!("Unknown ringback region: {}", other)),
+        }
+    }
+}
+
+/// A decoded ringtone: mono samples at the file's own sample rate. Cheap to
+/// clone; the samples are shared.
+#[derive(Debug, Clone)]
+pub struct RingtoneClip {
+    samples: Arc<[f32]>,
+    sample_rate: u32,
+}
+
+impl RingtoneClip {
+    /// Decode a WAV file: PCM 8/16/24/32-bit or 32-bit float, any channel
+    /// count (downmixed to mono), any sample rate.
+    pub fn from_wav(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            anyhow::bail!("Not a RIFF/WAVE file");
+        }
+
+        let mut format: Option<(u16, u16, u32, u16)> = None;
+        let mut data: Option<&[u8]> = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+            let body_start = offset + 8;
+            // Some encoders write a bogus size for the last chunk
+            let body_end = body_start.saturating_add(size).min(bytes.len());
+            let body = &bytes[body_start..body_end];
+
+            match id {
+                b"fmt " => {
+                    if body.len() < 16 {
+                        anyhow::bail!("WAV fmt chunk is too short");
+                    }
+                    let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                    if tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+                        // The real format is the first two bytes of the sub-format GUID
+                        tag = u16::from_le_bytes([body[24], body[25]]);
+                    }
+                    let channels = u16::from_le_bytes([body[2], body[3]]);
+                    let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                    let bits = u16::from_le_bytes([body[14], body[15]]);
+                    format = Some((tag, channels, sample_rate, bits));
+                }
+                b"data" => data = Some(body),
+                _ => {}
+            }
+
+            // Chunks are padded to an even length
+            offset = body_start.saturating_add(size).saturating_add(size & 1);
+        }
+
+        let (tag, channels, sample_rate, bits) =
+            format.ok_or_else(|| anyhow::anyhow!("WAV file has no fmt chunk"))?;
+        let data = data.ok_or_else(|| anyhow::anyhow!("WAV file has no data chunk"))?;
+        if channels == 0 || sample_rate == 0 {
+            anyhow::bail!("WAV file has {} channels at {} Hz", channels, sample_rate);
+        }
+
+        let interleaved: Vec<f32> = match (tag, bits) {
+            (WAVE_FORMAT_PCM, 8) => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+            (WAVE_FORMAT_PCM, 16) => data
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect(),
+            (WAVE_FORMAT_PCM, 24) => data
+                .chunks_exact(3)
+                .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0)
+                .collect(),
+            (WAVE_FORMAT_PCM, 32) => data
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0)
+                .collect(),
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => data
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).clamp(-1.0, 1.0))
+                .collect(),
+            _ => anyhow::bail!("Unsupported WAV encoding (format {}, {} bits)", tag, bits),
+        };
+
+        let channels = channels as usize;
+        let samples: Vec<f32> = interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        if samples.is_empty() {
+            anyhow::bail!("WAV file contains no audio");
+        }
+
+        Ok(Self {
+            samples: samples.into(),
+            sample_rate,
+        })
+    }
+
+    pub fn from_wav_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read ringtone {}: {}", path.display(), e))?;
+        Self::from_wav(&bytes)
+    }
+
+    /// Built-in ring used when no ringtone file is configured: two short
+    /// 440+480 Hz bursts, then two seconds of silence before it loops.
+    pub fn default_tone() -> Self {
+        Self::cadence((440.0, 480.0), &[(400, 200), (400, 2000)])
+    }
+
+    /// One loop of the ringback tone for `region`
+    pub fn ringback(region: RingbackRegion) -> Self {
+        match region {
+            RingbackRegion::Us => Self::cadence((440.0, 480.0), &[(2000, 4000)]),
+            RingbackRegion::Uk => Self::cadence((400.0, 450.0), &[(400, 200), (400, 2000)]),
+        }
+    }
+
+    /// Dual-tone bursts, each `(on_ms, off_ms)` in `cadence` being a burst
+    /// followed by silence.
+    fn cadence(freqs: (f32, f32), cadence: &[(usize, usize)]) -> Self {
+        const FADE_MS: usize = 10;
+
+        let per_ms = SAMPLE_RATE as usize / 1000;
+        let fade = FADE_MS * per_ms;
+        let total_ms: usize = cadence.iter().map(|(on, off)| on + off).sum();
+        let mut samples = Vec::with_capacity(total_ms * per_ms);
+
+        for &(on_ms, off_ms) in cadence {
+            let burst = on_ms * per_ms;
+            for n in 0..burst {
+                let t = n as f32 / SAMPLE_RATE as f32;
+                let tone = (std::f32::consts::TAU * freqs.0 * t).sin()
+                    + (std::f32::consts::TAU * freqs.1 * t).sin();
+                // Short ramps so the bursts don't click
+                let envelope = (n.min(burst - 1 - n) as f32 / fade as f32).min(1.0);
+                samples.push(tone * 0.2 * envelope);
+            }
+            samples.resize(samples.len() + off_ms * per_ms, 0.0);
+        }
+
+        Self {
+            samples: samples.into(),
+            sample_rate: SAMPLE_RATE,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Reads a clip in a loop at the output device's rate, interpolating
+/// between source samples.
+struct RingtoneCursor {
+    clip: RingtoneClip,
+    position: f64,
+    step: f64,
+    volume: f32,
+}
+
+impl RingtoneCursor {
+    fn new(clip: RingtoneClip, output_rate: u32, volume: f32) -> Self {
+        let step = clip.sample_rate as f64 / output_rate.max(1) as f64;
+        Self {
+            clip,
+            position: 0.0,
+            step,
+            volume,
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let samples = &self.clip.samples;
+        let len = samples.len();
+        let index = self.position as usize;
+        let frac = (self.position - index as f64) as f32;
+        let current = samples[index % len];
+        let next = samples[(index + 1) % len];
+
+        self.position += self.step;
+        if self.position >= len as f64 {
+            self.position -= len as f64;
+        }
+
+        ((current + (next - current) * frac) * self.volume).clamp(-1.0, 1.0)
+    }
+}
+
+/// A looping ringtone on its own output stream. Rings until `stop` is called
+/// or the player is dropped.
+pub struct RingtonePlayer {
+    clip: RingtoneClip,
+    device_name: Option<String>,
+    stop: Arc<AtomicBool>,
+}
+
+impl RingtonePlayer {
+    /// Open `device_name` (the default output if `None`, or if the named
+    /// device is gone) and start ringing. `volume` is clamped to 0.0..=2.0.
+    pub fn start(clip: RingtoneClip, device_name: Option<&str>, volume: f32) -> Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let volume = volume.clamp(0.0, 2.0);
+        let device_name_owned = device_name.map(|s| s.to_string());
+        let (startup_tx, startup_rx) = std::sync::mpsc::sync_channel(1);
+
+        let thread_clip = clip.clone();
+        let thread_stop = stop.clone();
+        let thread_device = device_name_owned.clone();
+        thread::spawn(move || {
+            let host = cpal::default_host();
+            let candidates =
+                device_candidates(&host, AudioDirection::Output, thread_device.as_deref());
+            let mut first_error: Option<AudioDeviceError> = None;
+            let mut opened = None;
+
+            for device in &candidates {
+                let device_label = device.name().unwrap_or_else(|_| "unknown".to_string());
+                let config = match choose_supported_format(device, AudioDirection::Output, CHANNELS)
+                {
+                    Ok(c) => c,
+                    Err(e) => {
+                        first_error.get_or_insert(AudioDeviceError::from_details(
+                            AudioDirection::Output,
+                            &device_label,
+                            e.to_string(),
+                        ));
+                        continue;
+                    }
+                };
+
+                let sample_format = config.sample_format();
+                let stream_config: StreamConfig = config.into();
+                let cursor =
+                    RingtoneCursor::new(thread_clip.clone(), stream_config.sample_rate.0, volume);
+
+                let stream_result = match sample_format {
+                    SampleFormat::F32 => build_stream::<f32>(device, &stream_config, cursor),
+                    SampleFormat::F64 => build_stream::<f64>(device, &stream_config, cursor),
+                    SampleFormat::I16 => build_stream::<i16>(device, &stream_config, cursor),
+                    SampleFormat::I32 => build_stream::<i32>(device, &stream_config, cursor),
+                    SampleFormat::U16 => build_stream::<u16>(device, &stream_config, cursor),
+                    SampleFormat::U32 => build_stream::<u32>(device, &stream_config, cursor),
+                    _ => Err(cpal::BuildStreamError::StreamConfigNotSupported),
+                };
+
+                let stream = match stream_result {
+                    Ok(s) => s,
+                    Err(e) => {
+                        first_error.get_or_insert(classify_build_error(
+                            AudioDirection::Output,
+                            &device_label,
+                            &e,
+                        ));
+                        continue;
+                    }
+                };
+
+                if let Err(e) = stream.play() {
+                    first_error.get_or_insert(classify_play_error(
+                        AudioDirection::Output,
+                        &device_label,
+                        &e,
+                    ));
+                    continue;
+                }
+
+                tracing::info!("Ringing on output device '{}'", device_label);
+                opened = Some(stream);
+                break;
+            }
+
+            let _stream = match opened {
+                Some(stream) => {
+                    let _ = startup_tx.send(Ok(()));
+                    stream
+                }
+                None => {
+                    let error = first_error.unwrap_or_else(|| AudioDeviceError::NotFound {
+                        direction: AudioDirection::Output,
+                        device: thread_device.unwrap_or_else(|| "default".to_string()),
+                    });
+                    tracing::error!("Ringtone: {}", error);
+                    let _ = startup_tx.send(Err(error));
+                    return;
+                }
+            };
+
+            while !thread_stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        wait_for_stream_start(&startup_rx, AudioDirection::Output)?;
+
+        Ok(Self {
+            clip,
+            device_name: device_name_owned,
+            stop,
+        })
+    }
+
+    pub fn clip(&self) -> &RingtoneClip {
+        &self.clip
+    }
+
+    pub fn device_name(&self) -> Option<&str> {
+        self.device_name.as_deref()
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for RingtonePlayer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut cursor: RingtoneCursor,
+) -> std::result::Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = (config.channels as usize).max(1);
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _info| {
+            for frame in data.chunks_mut(channels) {
+                frame.fill(T::from_sample(cursor.next_sample()));
+            }
+        },
+        |err| tracing::error!("Ringtone stream error: {}", err),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(format: u16, channels: u16, sample_rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut out = Vec::new();
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&format.to_le_bytes());
+        out.extend_from_slice(&channels.to_le_bytes());
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&bits.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    #[test]
+    fn decodes_and_downmixes_pcm16_stereo() {
+        let data: Vec<u8> = [16384i16, 0, -16384, -16384]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let clip = RingtoneClip::from_wav(&wav(WAVE_FORMAT_PCM, 2, 22050, 16, &data)).unwrap();
+
+        assert_eq!(clip.sample_rate(), 22050);
+        assert_eq!(&clip.samples[..], &[0.25, -0.5]);
+    }
+
+    #[test]
+    fn rejects_unsupported_or_broken_files() {
+        assert!(RingtoneClip::from_wav(b"not a wav file").is_err());
+        assert!(RingtoneClip::from_wav(&wav(WAVE_FORMAT_PCM, 1, 48000, 12, &[0, 0])).is_err());
+        assert!(RingtoneClip::from_wav(&wav(WAVE_FORMAT_PCM, 1, 48000, 16, &[])).is_err());
+    }
+
+    #[test]
+    fn cursor_loops_and_resamples_to_the_device_rate() {
+        let clip = RingtoneClip {
+            samples: vec![0.0, 1.0].into(),
+            sample_rate: 24000,
+        };
+        let mut cursor = RingtoneCursor::new(clip, 48000, 1.0);
+        let out: Vec<f32> = (0..6).map(|_| cursor.next_sample()).collect();
+
+        assert_eq!(out, vec![0.0, 0.5, 1.0, 0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn default_tone_has_a_silent_tail() {
+        let clip = RingtoneClip::default_tone();
+
+        assert_eq!(clip.duration(), Duration::from_millis(3000));
+        assert!(clip.samples.iter().any(|s| s.abs() > 0.1));
+        assert!(clip.samples[clip.samples.len() - 1000..]
+            .iter()
+            .all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn ringback_cadence_follows_the_region() {
+        let active = |clip: &RingtoneClip, from_ms: usize, to_ms: usize| {
+            let per_ms = clip.sample_rate as usize / 1000;
+            clip.samples[from_ms * per_ms..to_ms * per_ms]
+                .iter()
+                .any(|s| s.abs() > 0.1)
+        };
+
+        let us = RingtoneClip::ringback(RingbackRegion::Us);
+        assert_eq!(us.duration(), Duration::from_secs(6));
+        assert!(active(&us, 0, 2000));
+        assert!(!active(&us, 2000, 6000));
+
+        let uk = RingtoneClip::ringback("UK".parse().expect("region"));
+        assert_eq!(uk.duration(), Duration::from_millis(3000));
+        assert!(active(&uk, 0, 400));
+        assert!(!active(&uk, 400, 600));
+        assert!(active(&uk, 600, 1000));
+        assert!(!active(&uk, 1000, 3000));
+
+        assert!("mars".parse::<RingbackRegion>().is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! SDP munging helpers applied to local descriptions before they are set.
+//!
+//! webrtc-rs doesn't expose bandwidth or codec parameter knobs directly, so
+//! callers can pass an [`SdpTransform`] to `create_offer`/`accept_offer` to
+//! rewrite the generated SDP text.
+
+/// Callback that rewrites a local SDP before `set_local_description`.
+pub type SdpTransform = dyn Fn(String) -> String + Send + Sync;
+
+/// Set `maxaveragebitrate` (bits/s) on every Opus payload's fmtp line,
+/// adding the fmtp line when the payload has none.
+pub fn cap_opus_bitrate(sdp: &str, max_bitrate: u32) -> String {
+    let line_ending = line_ending(sdp);
+    let lines: Vec<&str> = sdp.lines().collect();
+    let opus_payloads: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("a=rtpmap:"))
+        .filter_map(|rest| {
+            let (pt, codec) = rest.split_once(' ')?;
+            codec
+                .to_ascii_lowercase()
+                .starts_with("opus/")
+                .then_some(pt)
+        })
+        .collect();
+
+    let mut out: Vec<String> = Vec::with_capacity(lines.len() + opus_payloads.len());
+    for line in &lines {
+        if let Some(rest) = line.strip_prefix("a=fmtp:") {
+            if let Some((pt, params)) = rest.split_once(' ') {
+                if opus_payloads.contains(&pt) {
+                    out.push(format!(
+                        "a=fmtp:{} {}",
+                        pt,
+                        set_fmtp_param(params, "maxaveragebitrate", &max_bitrate.to_string())
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        out.push(line.to_string());
+
+        if let Some(rest) = line.strip_prefix("a=rtpmap:") {
+            if let Some((pt, _)) = rest.split_once(' ') {
+                let has_fmtp = lines
+                    .iter()
+                    .any(|l| l.starts_with(&format!("a=fmtp:{} ", pt)));
+                if opus_payloads.contains(&pt) && !has_fmtp {
+                    out.push(format!("a=fmtp:{} maxaveragebitrate={}", pt, max_bitrate));
+                }
+            }
+        }
+    }
+
+    join_lines(&out, line_ending)
+}
+
+/// Add (or replace) a `b=AS:` bandwidth line in every media section.
+pub fn set_bandwidth_limit(sdp: &str, kbps: u32) -> String {
+    let line_ending = line_ending(sdp);
+    let mut out: Vec<String> = Vec::new();
+    let mut in_media = false;
+    let mut pending_insert = false;
+
+    for line in sdp.lines() {
+        if line.starts_with("m=") {
+            in_media = true;
+            pending_insert = true;
+            out.push(line.to_string());
+            continue;
+        }
+        if in_media && line.starts_with("b=AS:") {
+            continue;
+        }
+        // RFC 4566 orders b= after i= and c= inside a media section.
+        if pending_insert && !line.starts_with("i=") && !line.starts_with("c=") {
+            out.push(format!("b=AS:{}", kbps));
+            pending_insert = false;
+        }
+        out.push(line.to_string());
+    }
+    if pending_insert {
+        out.push(format!("b=AS:{}", kbps));
+    }
+
+    join_lines(&out, line_ending)
+}
+
+fn set_fmtp_param(params: &str, key: &str, value: &str) -> String {
+    let mut replaced = false;
+    let mut parts: Vec<String> = params
+        .split(';')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.split_once('=') {
+            Some((k, _)) if k.eq_ignore_ascii_case(key) => {
+                replaced = true;
+                format!("{}={}", key, value)
+            }
+            _ => p.to_string(),
+        })
+        .collect();
+    if !replaced {
+        parts.push(format!("{}={}", key, value));
+    }
+    parts.join(";")
+}
+
+fn line_ending(sdp: &str) -> &'static str {
+    if sdp.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+fn join_lines(lines: &[String], line_ending: &str) -> String {
+    let mut joined = lines.join(line_ending);
+    joined.push_str(line_ending);
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+    const OFFER: &str = "v=0\r\n\
+o=- 123456 2 IN IP4 127.0.0.1\r\n\
+s=-\r\n\
+t=0 0\r\n\
+a=group:BUNDLE 0\r\n\
+m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n\
+c=IN IP4 0.0.0.0\r\n\
+a=mid:0\r\n\
+a=rtpmap:111 opus/48000/2\r\n\
+a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+a=rtpmap:0 PCMU/8000\r\n\
+a=sendrecv\r\n";
+
+    #[test]
+    fn opus_cap_rewrites_fmtp_and_sdp_still_parses() {
+        let munged = cap_opus_bitrate(OFFER, 24_000);
+
+        assert!(
+            munged.contains("a=fmtp:111 minptime=10;useinbandfec=1;maxaveragebitrate=24000\r\n")
+        );
+        assert!(!munged.contains("a=fmtp:0 "));
+        assert!(RTCSessionDescription::offer(munged).is_ok());
+    }
+
+    #[test]
+    fn opus_cap_adds_missing_fmtp_line() {
+        let without_fmtp = OFFER.replace("a=fmtp:111 minptime=10;useinbandfec=1\r\n", "");
+        let munged = cap_opus_bitrate(&without_fmtp, 32_000);
+        assert!(
+            munged.contains("a=rtpmap:111 opus/48000/2\r\na=fmtp:111 maxaveragebitrate=32000\r\n")
+        );
+    }
+
+    #[test]
+    fn bandwidth_line_follows_connection_line() {
+        let munged = set_bandwidth_limit(&set_bandwidth_limit(OFFER, 64), 48);
+
+        assert!(munged.contains("c=IN IP4 0.0.0.0\r\nb=AS:48\r\na=mid:0"));
+        assert_eq!(munged.matches("b=AS:").count(), 1);
+        assert!(RTCSessionDescription::offer(munged).is_ok());
+    }
+}
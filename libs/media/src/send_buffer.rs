@@ -0,0 +1,96 @@
+//! Backpressure for the audio DataChannel.
+//!
+//! webrtc-rs queues whatever is sent on a channel until SCTP gets it out, so
+//! on a congested link the queue, and the latency behind it, grows without
+//! bound. Audio that arrives a second late is useless: the send loops check
+//! how much is already queued and drop the frame in hand instead of adding
+//! to it.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Default for `AudioSettings::send_buffer_limit_bytes`: a few hundred ms
+/// of audio at the higher bitrates
+pub const DEFAULT_SEND_BUFFER_LIMIT_BYTES: u32 = 4096;
+
+/// Settings and counters shared by every audio send loop. The settings are
+/// live copies of `AudioSettings`, so a change applies mid-call.
+#[derive(Debug)]
+pub(crate) struct SendControls {
+    /// Seconds of silence before a keepalive goes out; 0 disables them
+    keepalive_interval: AtomicU32,
+    /// Queued bytes above which frames are dropped; 0 disables the check
+    buffer_limit_bytes: AtomicU32,
+    /// Frames dropped because the channel was backed up
+    frames_dropped: AtomicU64,
+}
+
+impl SendControls {
+    pub(crate) fn new(keepalive_interval: u32, buffer_limit_bytes: u32) -> Self {
+        Self {
+            keepalive_interval: AtomicU32::new(keepalive_interval),
+            buffer_limit_bytes: AtomicU32::new(buffer_limit_bytes),
+            frames_dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn keepalive_interval(&self) -> u32 {
+        self.keepalive_interval.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_keepalive_interval(&self, seconds: u32) {
+        self.keepalive_interval.store(seconds, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_buffer_limit(&self, bytes: u32) {
+        self.buffer_limit_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Whether a frame may be sent with `buffered` bytes still queued on
+    /// the channel. Frames turned away are counted.
+    pub(crate) fn admit(&self, buffered: usize) -> bool {
+        let limit = self.buffer_limit_bytes.load(Ordering::Relaxed);
+        if limit == 0 || buffered <= limit as usize {
+            return true;
+        }
+        self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+        false
+    }
+
+    pub(crate) fn frames_dropped(&self) -> u64 {
+        self.frames_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Start counting again for the next call
+    pub(crate) fn reset_counters(&self) {
+        self.frames_dropped.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_dropped_only_over_the_limit() {
+        let controls = SendControls::new(15, 1000);
+        assert!(controls.admit(0));
+        assert!(controls.admit(1000));
+        assert!(!controls.admit(1001));
+        assert!(!controls.admit(50_000));
+        assert_eq!(controls.frames_dropped(), 2);
+
+        controls.reset_counters();
+        assert_eq!(controls.frames_dropped(), 0);
+    }
+
+    #[test]
+    fn zero_limit_disables_the_check() {
+        let controls = SendControls::new(15, 0);
+        assert!(controls.admit(usize::MAX));
+        assert_eq!(controls.frames_dropped(), 0);
+
+        controls.set_buffer_limit(10);
+        assert!(!controls.admit(11));
+        assert_eq!(controls.keepalive_interval(), 15);
+    }
+}
@@ -0,0 +1,293 @@
+//! Sound clips played into a call.
+//!
+//! A clip is decoded from a WAV file once, downmixed to mono and resampled
+//! to 48 kHz, then fed to the capture pipeline block by block. It goes out
+//! through the same encode, encrypt and send path as the microphone, so
+//! sequence numbers, mute and recording all apply to it as to speech.
+//!
+//! PCM (8, 16, 24 and 32-bit) and 32-bit float WAV files are supported.
+
+use crate::audio::{resample_to_48k, SAMPLE_RATE};
+use anyhow::{Context as _, Result};
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest clip accepted, to keep a stray long file out of memory
+pub const MAX_CLIP_DURATION: Duration = Duration::from_secs(60);
+
+/// Ramp at both ends of a clip so it starts and stops without a click
+const CLIP_FADE_SAMPLES: usize = SAMPLE_RATE as usize / 200; // 5 ms
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// A decoded clip: mono, 48 kHz, samples in [-1, 1]
+#[derive(Debug, Clone)]
+pub struct SoundClip {
+    samples: Vec<f32>,
+}
+
+impl SoundClip {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read sound clip {}", path.display()))?;
+        Self::from_wav(&bytes)
+    }
+
+    /// Decode a WAV file held in memory
+    pub fn from_wav(bytes: &[u8]) -> Result<Self> {
+        let wav = WavData::parse(bytes)?;
+        let frames = wav.data.len() / wav.block_align();
+        let max_frames = MAX_CLIP_DURATION.as_secs() as usize * wav.sample_rate as usize;
+        if frames > max_frames {
+            anyhow::bail!(
+                "Sound clip is longer than {} s",
+                MAX_CLIP_DURATION.as_secs()
+            );
+        }
+
+        let mono: Vec<f32> = wav
+            .data
+            .chunks_exact(wav.block_align())
+            .map(|frame| {
+                let sum: f32 = frame
+                    .chunks_exact(wav.bytes_per_sample())
+                    .map(|sample| wav.decode_sample(sample))
+                    .sum();
+                sum / wav.channels as f32
+            })
+            .collect();
+        let mut phase = 0;
+        let mut samples = resample_to_48k(&mono, 1, wav.sample_rate, &mut phase);
+
+        let fade = CLIP_FADE_SAMPLES.min(samples.len() / 2);
+        let len = samples.len();
+        for i in 0..fade {
+            let gain = i as f32 / fade as f32;
+            samples[i] *= gain;
+            samples[len - 1 - i] *= gain;
+        }
+        Ok(Self { samples })
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / f64::from(SAMPLE_RATE))
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+/// A clip being fed into capture
+#[derive(Debug)]
+pub(crate) struct ClipInjection {
+    clip: SoundClip,
+    position: usize,
+    /// Send the clip alone instead of mixing it over the microphone
+    replace_mic: bool,
+}
+
+impl ClipInjection {
+    pub(crate) fn new(clip: SoundClip, replace_mic: bool) -> Self {
+        Self {
+            clip,
+            position: 0,
+            replace_mic,
+        }
+    }
+
+    /// Put the next part of the clip into `block`, `layout` interleaved
+    /// channels at 48 kHz, on every channel. Returns whether any of the
+    /// clip was left to play.
+    pub(crate) fn apply(&mut self, block: &mut [f32], layout: usize) -> bool {
+        let remaining = &self.clip.samples[self.position..];
+        if remaining.is_empty() {
+            return false;
+        }
+        for (i, frame) in block.chunks_mut(layout.max(1)).enumerate() {
+            let sample = remaining.get(i).copied().unwrap_or(0.0);
+            for out in frame {
+                *out = if self.replace_mic {
+                    sample
+                } else {
+                    (*out + sample).clamp(-1.0, 1.0)
+                };
+            }
+        }
+        self.position += (block.len() / layout.max(1)).min(remaining.len());
+        true
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.position >= self.clip.samples.len()
+    }
+}
+
+/// The parts of a WAV file needed to decode it
+struct WavData<'a> {
+    format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    data: &'a [u8],
+}
+
+impl<'a> WavData<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            anyhow::bail!("Not a WAV file");
+        }
+
+        let mut fmt = None;
+        let mut data = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+            let start = offset + 8;
+            // Writers that never patched the size leave it at 0 or past the
+            // end; take the rest of the file
+            let end = start.saturating_add(size).min(bytes.len());
+            match id {
+                b"fmt " => fmt = Some(&bytes[start..end]),
+                b"data" => data = Some(&bytes[start..end]),
+                _ => {}
+            }
+            // Chunks are padded to an even length
+            offset = end + (size & 1);
+        }
+
+        let fmt = fmt.context("WAV file has no fmt chunk")?;
+        let data = data.context("WAV file has no data chunk")?;
+        if fmt.len() < 16 {
+            anyhow::bail!("WAV fmt chunk is too short");
+        }
+        let read_u16 = |at: usize| u16::from_le_bytes([fmt[at], fmt[at + 1]]);
+        let mut format = read_u16(0);
+        if format == WAVE_FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+            // The real format leads the sub-format GUID
+            format = read_u16(24);
+        }
+        let wav = Self {
+            format,
+            channels: read_u16(2),
+            sample_rate: u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]),
+            bits_per_sample: read_u16(14),
+            data,
+        };
+
+        let supported = match wav.format {
+            WAVE_FORMAT_PCM => matches!(wav.bits_per_sample, 8 | 16 | 24 | 32),
+            WAVE_FORMAT_IEEE_FLOAT => wav.bits_per_sample == 32,
+            _ => false,
+        };
+        if !supported {
+            anyhow::bail!(
+                "Unsupported WAV encoding (format {}, {} bits)",
+                wav.format,
+                wav.bits_per_sample
+            );
+        }
+        if wav.channels == 0 || wav.sample_rate == 0 {
+            anyhow::bail!("WAV file has no channels or sample rate");
+        }
+        Ok(wav)
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        usize::from(self.bits_per_sample / 8)
+    }
+
+    fn block_align(&self) -> usize {
+        self.bytes_per_sample() * usize::from(self.channels)
+    }
+
+    fn decode_sample(&self, bytes: &[u8]) -> f32 {
+        match (self.format, bytes) {
+            (WAVE_FORMAT_IEEE_FLOAT, &[a, b, c, d]) => f32::from_le_bytes([a, b, c, d]),
+            (_, &[a]) => (f32::from(a) - 128.0) / 128.0,
+            (_, &[a, b]) => f32::from(i16::from_le_bytes([a, b])) / 32768.0,
+            (_, &[a, b, c]) => (i32::from_le_bytes([0, a, b, c]) >> 8) as f32 / 8_388_608.0,
+            (_, &[a, b, c, d]) => i32::from_le_bytes([a, b, c, d]) as f32 / 2_147_483_648.0,
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_i16(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * u32::from(channels) * 2).to_le_bytes());
+        wav.extend_from_slice(&(channels * 2).to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn stereo_44k_clip_is_downmixed_and_resampled() {
+        // One second of left at half scale, right silent
+        let samples: Vec<i16> = (0..44_100).flat_map(|_| [16_384, 0]).collect();
+        let clip = SoundClip::from_wav(&wav_i16(2, 44_100, &samples)).expect("clip");
+        assert_eq!(clip.samples().len(), SAMPLE_RATE as usize);
+        assert!((clip.samples()[SAMPLE_RATE as usize / 2] - 0.25).abs() < 1e-3);
+        assert_eq!(clip.duration(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn clip_fades_in_and_out() {
+        let clip = SoundClip::from_wav(&wav_i16(1, SAMPLE_RATE, &[16_384; 4800])).expect("clip");
+        let samples = clip.samples();
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(*samples.last().unwrap(), 0.0);
+        assert!((samples[2400] - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn injection_mixes_or_replaces_until_the_clip_ends() {
+        let clip = SoundClip {
+            samples: vec![0.25; 3],
+        };
+        let mut mixed = ClipInjection::new(clip.clone(), false);
+        let mut block = vec![0.5; 4]; // 2 stereo frames
+        assert!(mixed.apply(&mut block, 2));
+        assert_eq!(block, vec![0.75; 4]);
+        let mut block = vec![0.5; 4];
+        assert!(mixed.apply(&mut block, 2));
+        assert_eq!(block, vec![0.75, 0.75, 0.5, 0.5]);
+        assert!(mixed.is_finished());
+        assert!(!mixed.apply(&mut block, 2));
+
+        let mut replaced = ClipInjection::new(clip, true);
+        let mut block = vec![0.5; 4];
+        assert!(replaced.apply(&mut block, 1));
+        assert_eq!(block, vec![0.25, 0.25, 0.25, 0.0]);
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_decode() {
+        assert!(SoundClip::from_wav(b"not a wav file").is_err());
+        let mut wav = wav_i16(1, SAMPLE_RATE, &[0; 10]);
+        wav[20] = 2; // ADPCM
+        assert!(SoundClip::from_wav(&wav).is_err());
+    }
+}
@@ -8,6 +8,14 @@ pub mod signaling {
         matches!(version, LEGACY_PROTOCOL_VERSION | PROTOCOL_VERSION)
     }
 
+    /// Highest version in `min..=max` this build supports, if any. Used to
+    /// settle the connection's version from a client `Hello`.
+    pub fn negotiate_protocol_version(min: u8, max: u8) -> Option<u8> {
+        (min..=max)
+            .rev()
+            .find(|version| is_supported_protocol_version(*version))
+    }
+
     fn default_message_version() -> u8 {
         LEGACY_PROTOCOL_VERSION
     }
@@ -29,15 +37,48 @@ pub mod signaling {
         pub sdp_m_line_index: Option<u16>,
     }
 
+    /// What a mid-call renegotiation changes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum RenegotiationKind {
+        AudioOnly,
+        ScreenShare,
+    }
+
     #[derive(Debug, Serialize, Deserialize)]
     #[serde(tag = "type", content = "payload")]
     pub enum SignalingMessage {
+        /// First message on a connection (client -> server): the client's
+        /// own version and the range it can speak
+        #[serde(rename = "hello")]
+        Hello {
+            #[serde(default)]
+            trace_id: Option<String>,
+            client_version: u8,
+            min: u8,
+            max: u8,
+        },
+        /// Reply to `Hello` (server -> client). `chosen_version` is the
+        /// highest version both sides support, or `None` when the ranges do
+        /// not overlap; `min`/`max` are the server's own range.
+        #[serde(rename = "server_hello")]
+        ServerHello {
+            #[serde(default)]
+            trace_id: Option<String>,
+            chosen_version: Option<u8>,
+            min: u8,
+            max: u8,
+        },
         #[serde(rename = "offer")]
         Offer {
             #[serde(default = "default_message_version")]
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            /// Minted by the caller when the call starts and carried on every
+            /// message of that call, so client and server logs share one id.
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
             sdp: String,
         },
@@ -47,6 +88,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
             sdp: String,
         },
@@ -56,6 +99,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
             candidate: String,
             sdp_mid: Option<String>,
@@ -79,8 +124,14 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
             public_key: String,
+            /// How long to ring before giving up, in seconds. The server
+            /// clamps it and falls back to its default when absent.
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            ring_timeout_secs: Option<u32>,
         },
         /// Incoming call notification (server -> callee)
         #[serde(rename = "incoming_call")]
@@ -89,9 +140,20 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             caller_id: String,
             caller_name: String,
             public_key: String,
+            /// Caller's free-text status message, shown while ringing
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            caller_status: Option<String>,
+            /// The callee is already in a call; answer with `CallWaiting`
+            #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+            while_busy: bool,
+            /// Seconds left before the server stops the call ringing
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            ring_timeout_secs: Option<u32>,
         },
         /// Accept an incoming call
         #[serde(rename = "call_accept")]
@@ -100,6 +162,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             caller_id: String,
             public_key: String,
         },
@@ -110,6 +174,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
             public_key: String,
         },
@@ -120,6 +186,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             caller_id: String,
         },
         /// Call was declined (server -> caller)
@@ -129,6 +197,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
         },
         /// End an active call
@@ -138,6 +208,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             peer_id: String,
         },
         /// Call ended notification
@@ -147,6 +219,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             peer_id: String,
         },
         /// Target user is busy (in another call)
@@ -156,6 +230,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             caller_id: String,
         },
         /// Cancel outgoing call before answer
@@ -165,6 +241,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
         },
         /// Call was cancelled (server -> callee)
@@ -174,8 +252,122 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             caller_id: String,
         },
+        /// Answer to an `IncomingCall` that rang while busy (callee -> server).
+        /// Accepting ends the callee's current call; `public_key` is only
+        /// needed then.
+        #[serde(rename = "call_waiting")]
+        CallWaiting {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
+            caller_id: String,
+            accept: bool,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            public_key: Option<String>,
+        },
+        /// ICE restart offer for a call whose network path failed. The
+        /// peer answers with a regular `Answer`; keys and the data channel
+        /// stay as they are.
+        #[serde(rename = "call_restart")]
+        CallRestart {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
+            target_id: String,
+            sdp: String,
+        },
+        /// New offer on an established call, e.g. to add a screen share.
+        /// The peer answers with a regular `Answer`.
+        #[serde(rename = "renegotiate")]
+        Renegotiate {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
+            target_id: String,
+            sdp: String,
+            kind: RenegotiationKind,
+        },
+        /// Mid-call proposal to change the audio settings both peers must
+        /// share: packet duration and channel count. The peer replies with
+        /// `AudioParamsAnswer`; neither side switches before that.
+        #[serde(rename = "audio_params_offer")]
+        AudioParamsOffer {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
+            target_id: String,
+            frame_duration_ms: f32,
+            channels: u8,
+        },
+        /// Reply to `AudioParamsOffer`. Accepted carries the offered
+        /// parameters, which the replying peer has switched to; rejected
+        /// carries the ones it keeps using.
+        #[serde(rename = "audio_params_answer")]
+        AudioParamsAnswer {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
+            target_id: String,
+            accepted: bool,
+            frame_duration_ms: f32,
+            channels: u8,
+        },
+        /// Ask which call the server has the sender in, e.g. after the app
+        /// restarted mid-call. Answered with `CallStateSnapshot`.
+        #[serde(rename = "call_state_query")]
+        CallStateQuery {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+        },
+        /// The sender's own call state (server -> client). Both peers are
+        /// `None` when the server has them in no call.
+        #[serde(rename = "call_state_snapshot")]
+        CallStateSnapshot {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
+            /// Peer of the connected call
+            active_peer: Option<String>,
+            /// Peer of the call still ringing, either way
+            pending_peer: Option<String>,
+        },
+        /// The sender's mic and speaking state in the voice channel they
+        /// are in (client -> server). Members of the server get it as a
+        /// `VOICE_STATE_UPDATE` event.
+        #[serde(rename = "voice_state")]
+        VoiceState {
+            #[serde(default = "default_message_version")]
+            version: u8,
+            #[serde(default)]
+            trace_id: Option<String>,
+            channel_id: String,
+            muted: bool,
+            speaking: bool,
+        },
         /// Call cannot proceed (offline peer, expired ringing state, etc.)
         #[serde(rename = "call_unavailable")]
         CallUnavailable {
@@ -183,6 +375,8 @@ pub mod signaling {
             version: u8,
             #[serde(default)]
             trace_id: Option<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            call_id: Option<String>,
             target_id: String,
             reason: String,
         },
@@ -191,6 +385,10 @@ pub mod signaling {
     impl SignalingMessage {
         pub fn version(&self) -> u8 {
             match self {
+                SignalingMessage::Hello { client_version, .. } => *client_version,
+                SignalingMessage::ServerHello { chosen_version, .. } => {
+                    chosen_version.unwrap_or(LEGACY_PROTOCOL_VERSION)
+                }
                 SignalingMessage::Offer { version, .. }
                 | SignalingMessage::Answer { version, .. }
                 | SignalingMessage::Candidate { version, .. }
@@ -206,13 +404,57 @@ pub mod signaling {
                 | SignalingMessage::CallBusy { version, .. }
                 | SignalingMessage::CallCancel { version, .. }
                 | SignalingMessage::CallCancelled { version, .. }
+                | SignalingMessage::CallWaiting { version, .. }
+                | SignalingMessage::CallStateQuery { version, .. }
+                | SignalingMessage::CallStateSnapshot { version, .. }
+                | SignalingMessage::CallRestart { version, .. }
+                | SignalingMessage::Renegotiate { version, .. }
+                | SignalingMessage::AudioParamsOffer { version, .. }
+                | SignalingMessage::AudioParamsAnswer { version, .. }
+                | SignalingMessage::VoiceState { version, .. }
                 | SignalingMessage::CallUnavailable { version, .. } => *version,
             }
         }
 
+        /// The call this message belongs to, if any. `Identify`, the
+        /// version handshake, the call state query and voice channel state
+        /// are not part of a call.
+        pub fn call_id(&self) -> Option<&str> {
+            match self {
+                SignalingMessage::Identify { .. }
+                | SignalingMessage::Hello { .. }
+                | SignalingMessage::ServerHello { .. }
+                | SignalingMessage::CallStateQuery { .. }
+                | SignalingMessage::VoiceState { .. } => None,
+                SignalingMessage::Offer { call_id, .. }
+                | SignalingMessage::Answer { call_id, .. }
+                | SignalingMessage::Candidate { call_id, .. }
+                | SignalingMessage::CallInitiate { call_id, .. }
+                | SignalingMessage::IncomingCall { call_id, .. }
+                | SignalingMessage::CallAccept { call_id, .. }
+                | SignalingMessage::CallAccepted { call_id, .. }
+                | SignalingMessage::CallDecline { call_id, .. }
+                | SignalingMessage::CallDeclined { call_id, .. }
+                | SignalingMessage::CallEnd { call_id, .. }
+                | SignalingMessage::CallEnded { call_id, .. }
+                | SignalingMessage::CallBusy { call_id, .. }
+                | SignalingMessage::CallCancel { call_id, .. }
+                | SignalingMessage::CallCancelled { call_id, .. }
+                | SignalingMessage::CallWaiting { call_id, .. }
+                | SignalingMessage::CallStateSnapshot { call_id, .. }
+                | SignalingMessage::CallRestart { call_id, .. }
+                | SignalingMessage::Renegotiate { call_id, .. }
+                | SignalingMessage::AudioParamsOffer { call_id, .. }
+                | SignalingMessage::AudioParamsAnswer { call_id, .. }
+                | SignalingMessage::CallUnavailable { call_id, .. } => call_id.as_deref(),
+            }
+        }
+
         pub fn trace_id(&self) -> Option<&str> {
             match self {
-                SignalingMessage::Offer { trace_id, .. }
+                SignalingMessage::Hello { trace_id, .. }
+                | SignalingMessage::ServerHello { trace_id, .. }
+                | SignalingMessage::Offer { trace_id, .. }
                 | SignalingMessage::Answer { trace_id, .. }
                 | SignalingMessage::Candidate { trace_id, .. }
                 | SignalingMessage::Identify { trace_id, .. }
@@ -227,6 +469,14 @@ pub mod signaling {
                 | SignalingMessage::CallBusy { trace_id, .. }
                 | SignalingMessage::CallCancel { trace_id, .. }
                 | SignalingMessage::CallCancelled { trace_id, .. }
+                | SignalingMessage::CallWaiting { trace_id, .. }
+                | SignalingMessage::CallStateQuery { trace_id, .. }
+                | SignalingMessage::CallStateSnapshot { trace_id, .. }
+                | SignalingMessage::CallRestart { trace_id, .. }
+                | SignalingMessage::Renegotiate { trace_id, .. }
+                | SignalingMessage::AudioParamsOffer { trace_id, .. }
+                | SignalingMessage::AudioParamsAnswer { trace_id, .. }
+                | SignalingMessage::VoiceState { trace_id, .. }
                 | SignalingMessage::CallUnavailable { trace_id, .. } => trace_id.as_deref(),
             }
         }
@@ -248,6 +498,7 @@ pub mod signaling {
             let message = SignalingMessage::Offer {
                 version: PROTOCOL_VERSION,
                 trace_id: Some("trace-123".to_string()),
+                call_id: None,
                 target_id: "peer-2".to_string(),
                 sdp: "sdp".to_string(),
             };
@@ -256,6 +507,254 @@ pub mod signaling {
             assert!(json.contains("\"version\":1"));
             assert!(json.contains("\"trace_id\":\"trace-123\""));
         }
+
+        #[test]
+        fn negotiation_picks_the_highest_common_version() {
+            assert_eq!(
+                negotiate_protocol_version(LEGACY_PROTOCOL_VERSION, PROTOCOL_VERSION + 3),
+                Some(PROTOCOL_VERSION)
+            );
+            assert_eq!(
+                negotiate_protocol_version(LEGACY_PROTOCOL_VERSION, LEGACY_PROTOCOL_VERSION),
+                Some(LEGACY_PROTOCOL_VERSION)
+            );
+        }
+
+        #[test]
+        fn negotiation_fails_when_ranges_do_not_overlap() {
+            assert_eq!(
+                negotiate_protocol_version(PROTOCOL_VERSION + 1, PROTOCOL_VERSION + 4),
+                None
+            );
+            // An inverted range is empty
+            assert_eq!(
+                negotiate_protocol_version(PROTOCOL_VERSION, LEGACY_PROTOCOL_VERSION),
+                None
+            );
+        }
+
+        #[test]
+        fn hello_round_trips() {
+            let json = r#"{"type":"hello","payload":{"client_version":3,"min":2,"max":3}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(json).expect("parse signaling");
+            assert_eq!(parsed.version(), 3);
+            assert_eq!(parsed.call_id(), None);
+
+            let reply = SignalingMessage::ServerHello {
+                trace_id: None,
+                chosen_version: None,
+                min: LEGACY_PROTOCOL_VERSION,
+                max: PROTOCOL_VERSION,
+            };
+            let json = serde_json::to_string(&reply).expect("serialize signaling");
+            assert!(json.contains("\"type\":\"server_hello\""));
+            assert!(json.contains("\"chosen_version\":null"));
+            let parsed: SignalingMessage = serde_json::from_str(&json).expect("parse signaling");
+            assert!(matches!(
+                parsed,
+                SignalingMessage::ServerHello {
+                    chosen_version: None,
+                    ..
+                }
+            ));
+        }
+
+        #[test]
+        fn incoming_call_status_is_optional() {
+            let json = r#"{"type":"incoming_call","payload":{"version":1,"caller_id":"u1","caller_name":"alice","public_key":"pk"}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(json).expect("parse signaling");
+            assert!(matches!(
+                parsed,
+                SignalingMessage::IncomingCall {
+                    caller_status: None,
+                    ..
+                }
+            ));
+
+            let message = SignalingMessage::IncomingCall {
+                version: PROTOCOL_VERSION,
+                trace_id: None,
+                call_id: None,
+                caller_id: "u1".to_string(),
+                caller_name: "alice".to_string(),
+                public_key: "pk".to_string(),
+                caller_status: Some("commuting".to_string()),
+                while_busy: false,
+                ring_timeout_secs: None,
+            };
+            let json = serde_json::to_string(&message).expect("serialize signaling");
+            assert!(json.contains("\"caller_status\":\"commuting\""));
+            assert!(!json.contains("while_busy"));
+            assert!(!json.contains("ring_timeout_secs"));
+        }
+
+        #[test]
+        fn ring_timeout_is_optional_on_initiate() {
+            let legacy = r#"{"type":"call_initiate","payload":{"version":1,"target_id":"u2","public_key":"pk"}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(legacy).expect("parse signaling");
+            assert!(matches!(
+                parsed,
+                SignalingMessage::CallInitiate {
+                    ring_timeout_secs: None,
+                    ..
+                }
+            ));
+
+            let json = r#"{"type":"call_initiate","payload":{"version":1,"target_id":"u2","public_key":"pk","ring_timeout_secs":12}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(json).expect("parse signaling");
+            assert!(matches!(
+                parsed,
+                SignalingMessage::CallInitiate {
+                    ring_timeout_secs: Some(12),
+                    ..
+                }
+            ));
+        }
+
+        #[test]
+        fn call_waiting_answer_round_trips() {
+            let json = r#"{"type":"incoming_call","payload":{"version":1,"caller_id":"u1","caller_name":"alice","public_key":"pk","while_busy":true}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(json).expect("parse signaling");
+            assert!(matches!(
+                parsed,
+                SignalingMessage::IncomingCall {
+                    while_busy: true,
+                    ..
+                }
+            ));
+
+            let decline = r#"{"type":"call_waiting","payload":{"version":1,"call_id":"call-2","caller_id":"u1","accept":false}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(decline).expect("parse signaling");
+            assert_eq!(parsed.call_id(), Some("call-2"));
+            assert!(matches!(
+                parsed,
+                SignalingMessage::CallWaiting {
+                    accept: false,
+                    public_key: None,
+                    ..
+                }
+            ));
+        }
+
+        #[test]
+        fn call_id_round_trips_and_is_omitted_when_absent() {
+            let legacy = r#"{"type":"call_end","payload":{"version":1,"peer_id":"u2"}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(legacy).expect("parse signaling");
+            assert_eq!(parsed.call_id(), None);
+            let json = serde_json::to_string(&parsed).expect("serialize signaling");
+            assert!(!json.contains("call_id"));
+
+            let message = SignalingMessage::CallEnd {
+                version: PROTOCOL_VERSION,
+                trace_id: None,
+                call_id: Some("call-1".to_string()),
+                peer_id: "u2".to_string(),
+            };
+            let json = serde_json::to_string(&message).expect("serialize signaling");
+            let parsed: SignalingMessage = serde_json::from_str(&json).expect("parse signaling");
+            assert_eq!(parsed.call_id(), Some("call-1"));
+        }
+
+        #[test]
+        fn call_restart_carries_the_offer() {
+            let json = r#"{"type":"call_restart","payload":{"version":1,"call_id":"call-1","target_id":"u2","sdp":"offer"}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(json).expect("parse signaling");
+            assert_eq!(parsed.call_id(), Some("call-1"));
+            assert!(matches!(
+                parsed,
+                SignalingMessage::CallRestart { ref target_id, ref sdp, .. }
+                    if target_id == "u2" && sdp == "offer"
+            ));
+        }
+
+        #[test]
+        fn call_state_snapshot_reports_no_call_as_nulls() {
+            let query = r#"{"type":"call_state_query","payload":{"version":1}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(query).expect("parse signaling");
+            assert_eq!(parsed.call_id(), None);
+
+            let snapshot = SignalingMessage::CallStateSnapshot {
+                version: PROTOCOL_VERSION,
+                trace_id: None,
+                call_id: None,
+                active_peer: None,
+                pending_peer: None,
+            };
+            let json = serde_json::to_string(&snapshot).expect("serialize signaling");
+            assert!(json.contains("\"active_peer\":null"));
+            assert!(json.contains("\"pending_peer\":null"));
+        }
+
+        #[test]
+        fn renegotiate_round_trips_with_its_kind() {
+            let message = SignalingMessage::Renegotiate {
+                version: PROTOCOL_VERSION,
+                trace_id: Some("trace-1".to_string()),
+                call_id: Some("call-1".to_string()),
+                target_id: "u2".to_string(),
+                sdp: "offer".to_string(),
+                kind: RenegotiationKind::ScreenShare,
+            };
+            let json = serde_json::to_string(&message).expect("serialize signaling");
+            assert!(json.contains("\"type\":\"renegotiate\""));
+            assert!(json.contains("\"kind\":\"screen_share\""));
+
+            let parsed: SignalingMessage = serde_json::from_str(&json).expect("parse signaling");
+            assert_eq!(parsed.call_id(), Some("call-1"));
+            assert!(matches!(
+                parsed,
+                SignalingMessage::Renegotiate {
+                    kind: RenegotiationKind::ScreenShare,
+                    ref target_id,
+                    ref sdp,
+                    ..
+                } if target_id == "u2" && sdp == "offer"
+            ));
+        }
+
+        #[test]
+        fn audio_params_answer_round_trips() {
+            let message = SignalingMessage::AudioParamsAnswer {
+                version: PROTOCOL_VERSION,
+                trace_id: None,
+                call_id: Some("call-1".to_string()),
+                target_id: "u2".to_string(),
+                accepted: false,
+                frame_duration_ms: 20.0,
+                channels: 1,
+            };
+            let json = serde_json::to_string(&message).expect("serialize signaling");
+            assert!(json.contains("\"type\":\"audio_params_answer\""));
+
+            let parsed: SignalingMessage = serde_json::from_str(&json).expect("parse signaling");
+            assert_eq!(parsed.call_id(), Some("call-1"));
+            assert!(matches!(
+                parsed,
+                SignalingMessage::AudioParamsAnswer {
+                    accepted: false,
+                    channels: 1,
+                    ref target_id,
+                    ..
+                } if target_id == "u2"
+            ));
+        }
+
+        #[test]
+        fn voice_state_is_not_part_of_a_call() {
+            let json = r#"{"type":"voice_state","payload":{"version":1,"channel_id":"c1","muted":true,"speaking":false}}"#;
+            let parsed: SignalingMessage = serde_json::from_str(json).expect("parse signaling");
+            assert_eq!(parsed.version(), PROTOCOL_VERSION);
+            assert_eq!(parsed.call_id(), None);
+            assert!(matches!(
+                parsed,
+                SignalingMessage::VoiceState {
+                    ref channel_id,
+                    muted: true,
+                    speaking: false,
+                    ..
+                } if channel_id == "c1"
+            ));
+        }
     }
 }
 
@@ -268,3 +767,110 @@ pub mod auth {
         // Add more fields as needed
     }
 }
+
+/// Log-safe stand-ins for secrets, shared by the server and the desktop app
+/// so both print the same fingerprint for the same token or key.
+pub mod redact {
+    use sha2::{Digest, Sha256};
+    use std::fmt;
+    use std::sync::OnceLock;
+
+    /// How much `Redacted` reveals about a secret, from `LOG_REDACTION`:
+    /// `fingerprint` (default) shows length and a short hash so the same value
+    /// can be matched across log lines; `strict` shows nothing.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum RedactionMode {
+        Fingerprint,
+        Strict,
+    }
+
+    impl RedactionMode {
+        fn parse(raw: &str) -> Self {
+            match raw.trim().to_ascii_lowercase().as_str() {
+                "strict" => RedactionMode::Strict,
+                _ => RedactionMode::Fingerprint,
+            }
+        }
+    }
+
+    fn mode() -> RedactionMode {
+        static MODE: OnceLock<RedactionMode> = OnceLock::new();
+        *MODE.get_or_init(|| {
+            std::env::var("LOG_REDACTION")
+                .map(|v| RedactionMode::parse(&v))
+                .unwrap_or(RedactionMode::Fingerprint)
+        })
+    }
+
+    /// First 12 hex characters of the SHA-256 of `secret`. Stable, so it can key
+    /// per-token state (e.g. rate limit buckets) without storing the token.
+    pub fn fingerprint(secret: &str) -> String {
+        let digest = Sha256::digest(secret.as_bytes());
+        digest[..6].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Log-safe stand-in for a token, key or credential. Use it for any
+    /// sensitive value that reaches a log line: `tracing::info!(token = %Redacted(&t))`.
+    pub struct Redacted<'a>(pub &'a str);
+
+    impl fmt::Display for Redacted<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_redacted(f, self.0, mode())
+        }
+    }
+
+    impl fmt::Debug for Redacted<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(self, f)
+        }
+    }
+
+    fn write_redacted(
+        f: &mut fmt::Formatter<'_>,
+        secret: &str,
+        mode: RedactionMode,
+    ) -> fmt::Result {
+        match mode {
+            RedactionMode::Strict => f.write_str("[redacted]"),
+            RedactionMode::Fingerprint if secret.is_empty() => f.write_str("[redacted len=0]"),
+            RedactionMode::Fingerprint => write!(
+                f,
+                "[redacted len={} sha256={}]",
+                secret.len(),
+                fingerprint(secret)
+            ),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        struct WithMode<'a>(&'a str, RedactionMode);
+
+        impl fmt::Display for WithMode<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_redacted(f, self.0, self.1)
+            }
+        }
+
+        #[test]
+        fn redacted_output_never_contains_the_secret() {
+            let token = "Bearer eyJhbGciOiJIUzI1NiJ9.payload.signature";
+
+            let shown = WithMode(token, RedactionMode::Fingerprint).to_string();
+            assert!(!shown.contains("eyJ"));
+            assert!(shown.contains(&format!("len={}", token.len())));
+            assert!(shown.contains(&fingerprint(token)));
+            assert_eq!(fingerprint(token), fingerprint(token));
+            assert_ne!(fingerprint(token), fingerprint("Bearer other"));
+
+            assert_eq!(
+                WithMode(token, RedactionMode::Strict).to_string(),
+                "[redacted]"
+            );
+            assert_eq!(RedactionMode::parse(" STRICT "), RedactionMode::Strict);
+            assert_eq!(RedactionMode::parse("bogus"), RedactionMode::Fingerprint);
+        }
+    }
+}